@@ -1,31 +1,147 @@
 // Android DEX file structures
+use std::collections::HashMap;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use serde::Serialize;
+
 use crate::android_analyzer::apk_analyzer::ApkAnalyzer;
+use crate::android_analyzer::dex_analyzer::parse_dex_version;
+use crate::android_analyzer::dex_index::DexIndex;
+use crate::android_analyzer::dex_verifier::{DexIntegrityReport, DexVerifier};
+use crate::android_analyzer::dex_writer::DexWriter;
+use crate::android_analyzer::smali::SmaliPrinter;
+use crate::android_analyzer::smali_assembler::SmaliAssembler;
+use crate::hierarchy::{descriptor_to_internal_name, ClassHierarchy};
+use crate::java_analyzer::jar::JarReader;
+use crate::model::ClassModel;
+use crate::project::{Project, ProjectData};
+use crate::rename::{project_renamer, Renamer};
+use crate::xref::XrefIndex;
 
 #[derive(Debug, Clone)]
 pub struct AndroidProjectData {
     pub apk_path: String,
-    pub dex_files: Vec<DexFile>,
+    /// Decoded DEX files, filled by `ensure_analyzed`; edits such as assembled smali are made here.
+    /// Shared so commands can take a snapshot and work outside the project lock
+    pub dex_files: Arc<Vec<DexFile>>,
+    /// Index of the raw DEX files, filled by `ensure_indexed`
+    pub dex_index: Option<DexIndex>,
     pub manifest: Option<AndroidManifest>,
     pub resource_table: Option<ResourceTable>,
+    pub hierarchy: Option<Arc<ClassHierarchy>>,
+    pub xrefs: Option<Arc<XrefIndex>>,
+    /// Bumped when classes are edited, see `ProjectData::revision`
+    pub revision: u64,
 }
 
 impl AndroidProjectData {
     pub fn new() -> Self {
         Self {
             apk_path: String::new(),
-            dex_files: Vec::new(),
+            dex_files: Arc::default(),
+            dex_index: None,
             manifest: None,
             resource_table: None,
+            hierarchy: None,
+            xrefs: None,
+            revision: 0,
         }
     }
     
     pub fn with_apk_path(apk_path: String) -> Self {
         Self {
             apk_path,
-            dex_files: Vec::new(),
+            dex_files: Arc::default(),
+            dex_index: None,
             manifest: None,
             resource_table: None,
+            hierarchy: None,
+            xrefs: None,
+            revision: 0,
+        }
+    }
+
+    /// Open the APK and index its DEX files on first use; ids and classes are decoded later, on demand
+    pub fn ensure_indexed(&mut self) -> Result<&mut DexIndex, String> {
+        if self.dex_index.is_none() {
+            let mut analyzer = ApkAnalyzer::new(self.apk_path.clone());
+            let data = analyzer.analyze().map_err(|e| e.to_string())?;
+            self.dex_index = data.dex_index;
+            self.manifest = data.manifest;
+            self.resource_table = data.resource_table;
+        }
+        self.dex_index.as_mut().ok_or_else(|| "No DEX files in project".to_string())
+    }
+
+    /// Decode every DEX file on first use, for edits and analyses that keep whole classes
+    pub fn ensure_analyzed(&mut self) -> Result<(), String> {
+        if !self.dex_files.is_empty() {
+            return Ok(());
+        }
+        let dex_files = self.ensure_indexed()?.load_all().map_err(|e| e.to_string())?;
+        self.dex_files = Arc::new(dex_files);
+        self.hierarchy = None;
+        self.xrefs = None;
+        Ok(())
+    }
+
+    /// Class descriptors of each DEX file
+    pub fn class_names(&mut self) -> Result<Vec<Vec<String>>, String> {
+        // 已解码的 DEX 包含汇编进来的新类，优先使用
+        if !self.dex_files.is_empty() {
+            return Ok(self
+                .dex_files
+                .iter()
+                .map(|dex_file| dex_file.classes.iter().map(|class_def| class_def.class_type.descriptor.clone()).collect())
+                .collect());
+        }
+        Ok(self.ensure_indexed()?.images().iter().map(|image| image.class_names.clone()).collect())
+    }
+
+    /// Superclass and interfaces of every class, as internal names; without decoded DEX files only the class_def headers are read
+    pub fn supertypes(&mut self) -> Result<HashMap<String, Vec<String>>, String> {
+        let names = |super_type: Option<&TypeDescriptor>, interfaces: &[TypeDescriptor]| -> Vec<String> {
+            super_type.into_iter().chain(interfaces).map(|t| descriptor_to_internal_name(&t.descriptor)).collect()
+        };
+        let mut supertypes = HashMap::new();
+        if !self.dex_files.is_empty() {
+            for class_def in self.dex_files.iter().flat_map(|dex_file| &dex_file.classes) {
+                supertypes
+                    .entry(descriptor_to_internal_name(&class_def.class_type.descriptor))
+                    .or_insert_with(|| names(class_def.super_type.as_ref(), &class_def.interfaces));
+            }
+            return Ok(supertypes);
+        }
+        for image in self.ensure_indexed()?.images() {
+            for (index, descriptor) in image.class_names.iter().enumerate() {
+                let name = descriptor_to_internal_name(descriptor);
+                // 同名类以先出现的为准
+                if supertypes.contains_key(&name) {
+                    continue;
+                }
+                let (super_type, interfaces) = image.supertypes(index).map_err(|e| e.to_string())?;
+                supertypes.insert(name, names(super_type.as_ref(), &interfaces));
+            }
         }
+        Ok(supertypes)
+    }
+
+    /// Find a class by descriptor together with its DEX file; without decoded DEX files only that class is decoded
+    pub fn dex_class(&mut self, descriptor: &str) -> Result<Option<(&DexFile, Cow<'_, ClassDef>)>, String> {
+        if self.dex_files.is_empty() {
+            let index = self.ensure_indexed()?;
+            let Some((image_index, class_index)) = index.find_class(descriptor) else {
+                return Ok(None);
+            };
+            let image = &index.images()[image_index];
+            let class_def = image.class(class_index).map_err(|e| e.to_string())?;
+            return Ok(Some((image.ids().map_err(|e| e.to_string())?, Cow::Owned(class_def))));
+        }
+        Ok(self.dex_files.iter().find_map(|dex_file| {
+            let class_def = dex_file.classes.iter().find(|class_def| class_def.class_type.descriptor == descriptor)?;
+            Some((dex_file, Cow::Borrowed(class_def)))
+        }))
     }
 }
 
@@ -54,29 +170,134 @@ pub struct DexFile {
     pub class_defs_offset: u32,
     pub data_size: u32,
     pub data_offset: u32,
+    /// Size of the whole container, 0 before version 041
+    pub container_size: u32,
+    /// Offset of this header in the container, 0 before version 041
+    pub header_offset: u32,
     
     // Parsed data
+    /// Every section listed by the map_list, ordered by offset
+    pub sections: Vec<DexSection>,
     pub strings: Vec<String>,
     pub types: Vec<TypeDescriptor>,
     pub protos: Vec<ProtoDescriptor>,
     pub fields: Vec<FieldDescriptor>,
     pub methods: Vec<MethodDescriptor>,
+    pub method_handles: Vec<MethodHandle>,
+    pub call_sites: Vec<CallSite>,
     pub classes: Vec<ClassDef>,
 }
 
+impl DexFile {
+    /// Format version from the magic, e.g. 35 for `dex\n035\0`
+    pub fn version(&self) -> Option<u32> {
+        parse_dex_version(&self.magic)
+    }
+
+    pub fn has_section(&self, type_code: u16) -> bool {
+        self.sections.iter().any(|section| section.type_code == type_code)
+    }
+
+    /// Copy of the header and id tables without the decoded classes
+    pub fn ids_only(&self) -> DexFile {
+        DexFile {
+            magic: self.magic,
+            checksum: self.checksum,
+            signature: self.signature,
+            file_size: self.file_size,
+            header_size: self.header_size,
+            endian_tag: self.endian_tag,
+            link_size: self.link_size,
+            link_offset: self.link_offset,
+            map_offset: self.map_offset,
+            string_ids_size: self.string_ids_size,
+            string_ids_offset: self.string_ids_offset,
+            type_ids_size: self.type_ids_size,
+            type_ids_offset: self.type_ids_offset,
+            proto_ids_size: self.proto_ids_size,
+            proto_ids_offset: self.proto_ids_offset,
+            field_ids_size: self.field_ids_size,
+            field_ids_offset: self.field_ids_offset,
+            method_ids_size: self.method_ids_size,
+            method_ids_offset: self.method_ids_offset,
+            class_defs_size: self.class_defs_size,
+            class_defs_offset: self.class_defs_offset,
+            data_size: self.data_size,
+            data_offset: self.data_offset,
+            container_size: self.container_size,
+            header_offset: self.header_offset,
+            sections: self.sections.clone(),
+            strings: self.strings.clone(),
+            types: self.types.clone(),
+            protos: self.protos.clone(),
+            fields: self.fields.clone(),
+            methods: self.methods.clone(),
+            method_handles: self.method_handles.clone(),
+            call_sites: self.call_sites.clone(),
+            classes: Vec::new(),
+        }
+    }
+}
+
+/// One map_list entry: where a section starts, how many items it holds and how many bytes it spans.
+#[derive(Debug, Clone, Serialize)]
+pub struct DexSection {
+    pub type_code: u16,
+    /// Item type name as used by the format, e.g. `string_id_item`
+    pub name: String,
+    pub offset: u32,
+    pub count: u32,
+    /// Bytes up to the next section, or to the end of the file for the last one
+    pub size: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct AndroidManifest {
     pub package_name: String,
     pub version_code: u32,
     pub version_name: String,
+    /// `<application android:name>`
+    pub application_name: Option<String>,
+    /// `<application android:backupAgent>`
+    pub backup_agent: Option<String>,
     pub activities: Vec<Activity>,
     pub services: Vec<Service>,
     pub receivers: Vec<Receiver>,
     pub providers: Vec<Provider>,
+    /// `<instrumentation android:name>`
+    pub instrumentations: Vec<String>,
     pub permissions: Vec<Permission>,
     pub uses_permissions: Vec<String>,
 }
 
+impl AndroidManifest {
+    /// 清单里声明、由系统实例化的类的完整类名和种类，`.Foo` 或不带包名的写法相对于应用包名
+    pub fn component_classes(&self) -> Vec<(&'static str, String)> {
+        let components = self
+            .application_name
+            .iter()
+            .map(|name| ("Application", name))
+            .chain(self.backup_agent.iter().map(|name| ("BackupAgent", name)))
+            .chain(self.instrumentations.iter().map(|name| ("Instrumentation", name)))
+            .chain(self.activities.iter().map(|c| ("Activity", &c.name)))
+            .chain(self.services.iter().map(|c| ("Service", &c.name)))
+            .chain(self.receivers.iter().map(|c| ("Receiver", &c.name)))
+            .chain(self.providers.iter().map(|c| ("Provider", &c.name)));
+        components
+            .map(|(kind, name)| {
+                let qualified = if name.starts_with('.') {
+                    format!("{}{}", self.package_name, name)
+                } else if !name.contains('.') {
+                    format!("{}.{}", self.package_name, name)
+                } else {
+                    name.clone()
+                };
+                (kind, qualified)
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ResourceTable {
     pub packages: Vec<ResourcePackage>,
@@ -118,7 +339,7 @@ pub enum ResourceValue {
     ColorStateList(u32),
     Complex {
         parent: u32,
-        values: std::collections::HashMap<u32, ResourceValue>,
+        values: HashMap<u32, ResourceValue>,
     },
     Unknown(u32),
 }
@@ -171,6 +392,58 @@ pub struct MethodDescriptor {
     pub name: String,
 }
 
+/// How a method handle accesses its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodHandleType {
+    StaticPut,
+    StaticGet,
+    InstancePut,
+    InstanceGet,
+    InvokeStatic,
+    InvokeInstance,
+    InvokeConstructor,
+    InvokeDirect,
+    InvokeInterface,
+}
+
+impl MethodHandleType {
+    pub fn from_u16(value: u16) -> Option<Self> {
+        use MethodHandleType::*;
+        [StaticPut, StaticGet, InstancePut, InstanceGet, InvokeStatic, InvokeInstance, InvokeConstructor, InvokeDirect, InvokeInterface]
+            .get(value as usize)
+            .copied()
+    }
+
+    pub fn is_field_access(&self) -> bool {
+        (*self as u16) <= MethodHandleType::InstanceGet as u16
+    }
+}
+
+/// The field or method a method handle refers to.
+#[derive(Debug, Clone)]
+pub enum MethodHandleMember {
+    Field(FieldDescriptor),
+    Method(MethodDescriptor),
+}
+
+/// An entry of the `method_handles` section (version 038 and later).
+#[derive(Debug, Clone)]
+pub struct MethodHandle {
+    pub handle_type: MethodHandleType,
+    pub member: MethodHandleMember,
+}
+
+/// An entry of the `call_site_ids` section, the bootstrap of an `invoke-custom` (version 038 and later).
+#[derive(Debug, Clone)]
+pub struct CallSite {
+    /// Index into `method_handles`
+    pub bootstrap: u32,
+    pub method_name: String,
+    pub method_type: ProtoDescriptor,
+    /// Extra bootstrap arguments
+    pub arguments: Vec<EncodedValue>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClassDef {
     pub class_type: TypeDescriptor,
@@ -191,6 +464,9 @@ pub struct Field {
     pub name: String,
     pub field_type: TypeDescriptor,
     pub value: Option<EncodedValue>,
+    pub annotations: Vec<Annotation>,
+    /// Flags from the `hiddenapi_class_data` section
+    pub hiddenapi_flags: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -199,10 +475,24 @@ pub struct Method {
     pub name: String,
     pub proto: ProtoDescriptor,
     pub code: Option<CodeItem>,
+    pub annotations: Vec<Annotation>,
+    /// One annotation list per declared parameter, empty when the method has no parameter annotations
+    pub parameter_annotations: Vec<Vec<Annotation>>,
+    /// Flags from the `hiddenapi_class_data` section
+    pub hiddenapi_flags: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationVisibility {
+    Build,
+    Runtime,
+    System,
 }
 
 #[derive(Debug, Clone)]
 pub struct Annotation {
+    /// None for annotations nested in encoded values
+    pub visibility: Option<AnnotationVisibility>,
     pub annotation_type: TypeDescriptor,
     pub elements: Vec<AnnotationElement>,
 }
@@ -222,6 +512,9 @@ pub enum EncodedValue {
     Long(i64),
     Float(f32),
     Double(f64),
+    MethodType(ProtoDescriptor),
+    /// Index into the `method_handles` section
+    MethodHandle(u32),
     String(String),
     Type(TypeDescriptor),
     Field(FieldDescriptor),
@@ -268,6 +561,14 @@ pub struct CodeItem {
     pub insns: Vec<u16>,
     pub tries: Vec<TryItem>,
     pub handlers: Vec<EncodedCatchHandler>,
+    pub debug_info: Option<DebugInfo>,
+}
+
+impl CodeItem {
+    /// The handlers a try block jumps to
+    pub fn handler(&self, try_item: &TryItem) -> Option<&EncodedCatchHandler> {
+        self.handlers.iter().find(|handler| handler.offset == try_item.handler_offset)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -279,6 +580,8 @@ pub struct TryItem {
 
 #[derive(Debug, Clone)]
 pub struct EncodedCatchHandler {
+    /// Byte offset inside the handler list, what `TryItem::handler_offset` refers to
+    pub offset: u16,
     pub size: i32,
     pub handlers: Vec<EncodedTypeAddrPair>,
     pub catch_all_addr: Option<u32>,
@@ -290,6 +593,39 @@ pub struct EncodedTypeAddrPair {
     pub addr: u32,
 }
 
+/// Decoded `debug_info_item`: the line table and the live ranges of named locals.
+#[derive(Debug, Clone)]
+pub struct DebugInfo {
+    pub line_start: u32,
+    pub parameter_names: Vec<Option<String>>,
+    pub positions: Vec<DebugPosition>,
+    pub locals: Vec<LocalVariable>,
+    /// Addresses marked by `DBG_SET_PROLOGUE_END`
+    pub prologue_end: Vec<u32>,
+    /// Addresses marked by `DBG_SET_EPILOGUE_BEGIN`
+    pub epilogue_begin: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DebugPosition {
+    /// In 16-bit code units
+    pub address: u32,
+    pub line: u32,
+    /// Set by `DBG_SET_FILE`, None means the class's source file
+    pub source_file: Option<String>,
+}
+
+/// One live range of a local, a restarted local gets a new entry.
+#[derive(Debug, Clone)]
+pub struct LocalVariable {
+    pub register: u32,
+    pub name: Option<String>,
+    pub local_type: Option<TypeDescriptor>,
+    pub signature: Option<String>,
+    pub start_address: u32,
+    pub end_address: u32,
+}
+
 // Android Manifest structures
 #[derive(Debug, Clone)]
 pub struct Activity {
@@ -425,28 +761,173 @@ pub fn android_analyze_apk(apk_path: String) -> Result<String, String> {
     let mut analyzer = ApkAnalyzer::new(apk_path);
     match analyzer.analyze() {
         Ok(data) => Ok(format!("APK analyzed successfully! Found {} DEX files, manifest: {}, resource table: {}", 
-                              data.dex_files.len(),
+                              data.dex_index.as_ref().map_or(0, |index| index.images().len()),
                               data.manifest.is_some(),
                               data.resource_table.is_some())),
         Err(e) => Err(e.to_string())
     }
 }
 
-/// List all files in an Android APK project
+/// List all files in an Android APK project, plus one `smali*/….smali` and one `sources/….java` entry per DEX class
 #[tauri::command]
 pub fn android_project_list_files(project_id: String) -> Result<Vec<String>, String> {
-    use crate::project::PROJECTS;
-    
-    let projects = PROJECTS.lock().unwrap();
-    let project = projects.get(&project_id)
-        .ok_or_else(|| "Project not found".to_string())?;
-        
-    let mut analyzer = ApkAnalyzer::new(project.path.clone());
-    // First analyze to open the APK
-    analyzer.analyze().map_err(|e| e.to_string())?;
-    
-    // Then list files
-    analyzer.list_files().map_err(|e| e.to_string())
+    let renamer = project_renamer(&project_id)?;
+    Project::with_project_mut(&project_id, |project| {
+        let ProjectData::Android(android_data) = &mut project.data else {
+            return Err("Not an Android project".to_string());
+        };
+        let mut files: Vec<String> = JarReader::new(&android_data.apk_path)
+            .list_entries()?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+
+        let class_names = android_data.class_names()?;
+        for (index, names) in class_names.iter().enumerate() {
+            for descriptor in names {
+                let class_name = renamer.class_name(&descriptor_to_internal_name(descriptor));
+                files.push(format!("{}/{}.smali", smali_directory(index), class_name));
+            }
+        }
+        // jadx 的目录布局：所有 DEX 的 Java 源码都在 sources 下
+        for descriptor in class_names.iter().flatten() {
+            let class_name = renamer.class_name(&descriptor_to_internal_name(descriptor));
+            files.push(format!("sources/{}.java", class_name));
+        }
+        Ok(files)
+    })
+}
+
+/// apktool 的目录布局：classes.dex 对应 smali，classesN.dex 对应 smali_classesN
+fn smali_directory(dex_index: usize) -> String {
+    if dex_index == 0 {
+        "smali".to_string()
+    } else {
+        format!("smali_classes{}", dex_index + 1)
+    }
+}
+
+/// 以 smali 形式输出一个类，类名可以是重命名后的名字
+#[tauri::command]
+pub fn android_project_smali_class(project_id: String, class_name: String) -> Result<String, String> {
+    let renamer = project_renamer(&project_id)?;
+    let descriptor = format!("L{};", renamer.map.original_class_name(&class_name));
+    Project::with_project_mut(&project_id, |project| {
+        let ProjectData::Android(android_data) = &mut project.data else {
+            return Err("Not an Android project".to_string());
+        };
+        let (dex_file, class_def) = android_data.dex_class(&descriptor)?.ok_or_else(|| format!("Class not found: {}", class_name))?;
+        let (dex_file, class_def) = renamed_class(dex_file, class_def, &renamer);
+        SmaliPrinter::new(&dex_file).print_class(&class_def).map_err(|e| e.to_string())
+    })
+}
+
+/// 把一个 DEX 类反编译为 Java 源码，类名可以是重命名后的名字
+#[tauri::command]
+pub fn android_project_decompile_class(project_id: String, class_name: String) -> Result<String, String> {
+    let renamer = project_renamer(&project_id)?;
+    let descriptor = format!("L{};", renamer.map.original_class_name(&class_name));
+    Project::with_project_mut(&project_id, |project| {
+        let ProjectData::Android(android_data) = &mut project.data else {
+            return Err("Not an Android project".to_string());
+        };
+        let (dex_file, class_def) = android_data.dex_class(&descriptor)?.ok_or_else(|| format!("Class not found: {}", class_name))?;
+        let (dex_file, class_def) = renamed_class(dex_file, class_def, &renamer);
+        ClassModel::Dex(&dex_file, &class_def).decompile()
+    })
+}
+
+/// 按项目的重命名改写要显示的类；只复制改写 id 表和这一个类，没有重命名时直接借用
+pub(crate) fn renamed_class<'d>(dex_file: &'d DexFile, class_def: Cow<'d, ClassDef>, renamer: &Renamer) -> (Cow<'d, DexFile>, Cow<'d, ClassDef>) {
+    if renamer.is_empty() {
+        return (Cow::Borrowed(dex_file), class_def);
+    }
+    let mut class_def = class_def.into_owned();
+    renamer.dex_class(&mut class_def);
+    (renamer.dex_ids(dex_file), Cow::Owned(class_def))
+}
+
+/// 汇编 smali 并替换项目中同名的类，新类加入第一个 DEX；返回类的内部名
+#[tauri::command]
+pub fn android_project_assemble_smali(project_id: String, smali: String) -> Result<String, String> {
+    // 重命名后的名字只存在于显示层，汇编时无法对应回原名
+    if !project_renamer(&project_id)?.is_empty() {
+        return Err("Smali cannot be assembled while the project has renames".to_string());
+    }
+    Project::with_project_mut(&project_id, |project| {
+        let ProjectData::Android(android_data) = &mut project.data else {
+            return Err("Not an Android project".to_string());
+        };
+        android_data.ensure_analyzed()?;
+        let descriptor = SmaliAssembler::class_descriptor(&smali).ok_or_else(|| "Missing .class directive".to_string())?;
+        let dex_index = android_data
+            .dex_files
+            .iter()
+            .position(|dex_file| dex_file.classes.iter().any(|class_def| class_def.class_type.descriptor == descriptor))
+            .unwrap_or(0);
+        let dex_file = android_data.dex_files.get(dex_index).ok_or_else(|| "No DEX files in project".to_string())?;
+        // 先在副本上汇编，出错时不留下追加的常量
+        let mut patched = dex_file.clone();
+        let class_def = SmaliAssembler::new(&mut patched).assemble(&smali).map_err(|e| e.to_string())?;
+        match patched.classes.iter().position(|existing| existing.class_type.descriptor == class_def.class_type.descriptor) {
+            Some(index) => patched.classes[index] = class_def,
+            None => patched.classes.push(class_def),
+        }
+        Arc::make_mut(&mut android_data.dex_files)[dex_index] = patched;
+        android_data.hierarchy = None;
+        android_data.xrefs = None;
+        android_data.revision += 1;
+        Ok(descriptor_to_internal_name(&descriptor))
+    })
+}
+
+/// 把项目中的第 `dex_index` 个 DEX（含已汇编的修改）写成新的 classes.dex
+#[tauri::command]
+pub fn android_project_write_dex(project_id: String, dex_index: usize, output_path: String) -> Result<(), String> {
+    let bytes = Project::with_project_mut(&project_id, |project| {
+        let ProjectData::Android(android_data) = &mut project.data else {
+            return Err("Not an Android project".to_string());
+        };
+        android_data.ensure_analyzed()?;
+        let dex_file = android_data.dex_files.get(dex_index).ok_or_else(|| format!("DEX index out of range: {}", dex_index))?;
+        DexWriter::new(dex_file).write().map_err(|e| e.to_string())
+    })?;
+    std::fs::write(&output_path, bytes).map_err(|e| format!("Failed to write {}: {}", output_path, e))
+}
+
+/// 第 `dex_index` 个 DEX 的段表（map_list），反映 APK 中的原始文件
+#[tauri::command]
+pub fn android_project_dex_sections(project_id: String, dex_index: usize) -> Result<Vec<DexSection>, String> {
+    Project::with_project_mut(&project_id, |project| {
+        let ProjectData::Android(android_data) = &mut project.data else {
+            return Err("Not an Android project".to_string());
+        };
+        let image = android_data.ensure_indexed()?.images().get(dex_index).ok_or_else(|| format!("DEX index out of range: {}", dex_index))?;
+        Ok(image.sections.clone())
+    })
+}
+
+/// Integrity report of one DEX entry in the APK.
+#[derive(Debug, Clone, Serialize)]
+pub struct DexEntryReport {
+    pub name: String,
+    pub report: DexIntegrityReport,
+}
+
+/// Verify the integrity of every DEX in the APK; works on raw bytes, so files the parser rejects are reported too
+#[tauri::command]
+pub fn android_project_verify_dex(project_id: String) -> Result<Vec<DexEntryReport>, String> {
+    let apk_path = Project::with_project_mut(&project_id, |project| {
+        let ProjectData::Android(android_data) = &project.data else {
+            return Err("Not an Android project".to_string());
+        };
+        Ok(android_data.apk_path.clone())
+    })?;
+    let entries = ApkAnalyzer::new(apk_path).read_dex_entries().map_err(|e| e.to_string())?;
+    Ok(entries
+        .into_iter()
+        .map(|(name, bytes)| DexEntryReport { name, report: DexVerifier::new(&bytes).verify() })
+        .collect())
 }
 
 /// Read file content from an Android APK project
@@ -454,6 +935,15 @@ pub fn android_project_list_files(project_id: String) -> Result<Vec<String>, Str
 pub fn android_project_read_file_content(project_id: String, file_name: String) -> Result<String, String> {
     use crate::project::PROJECTS;
     
+    // 类列表中的 smali 条目不在 APK 里，由 DEX 生成
+    if let Some(class_name) = file_name.strip_suffix(".smali").filter(|_| file_name.starts_with("smali")) {
+        let class_name = class_name.split_once('/').map(|(_, name)| name).unwrap_or(class_name);
+        return android_project_smali_class(project_id, class_name.to_string());
+    }
+    if let Some(class_name) = file_name.strip_prefix("sources/").and_then(|name| name.strip_suffix(".java")) {
+        return android_project_decompile_class(project_id, class_name.to_string());
+    }
+    
     let projects = PROJECTS.lock().unwrap();
     let project = projects.get(&project_id)
         .ok_or_else(|| "Project not found".to_string())?;
@@ -486,4 +976,92 @@ pub fn android_project_read_file_content(project_id: String, file_name: String)
             Ok(format!("Binary file (hex dump):\n{}", hex_lines.join("\n")))
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_bytes;
+    use crate::program::Program;
+    use crate::project::ProjectClasses;
+    use crate::rename::{Rename, SymbolKind};
+
+    const BASE: &str = r#"
+.class public La/Base;
+.super Ljava/lang/Object;
+
+.method public static run()V
+    .registers 0
+    return-void
+.end method
+"#;
+
+    const CHILD: &str = r#"
+.class public La/Child;
+.super La/Base;
+.implements Ljava/lang/Runnable;
+"#;
+
+    fn indexed_project() -> AndroidProjectData {
+        let mut android_data = AndroidProjectData::new();
+        android_data.dex_index = Some(DexIndex::build(vec![("classes.dex".to_string(), dex_bytes(&[BASE, CHILD]))]).unwrap());
+        android_data
+    }
+
+    #[test]
+    fn supertypes_are_read_without_decoding_the_dex() {
+        let mut android_data = indexed_project();
+        let supertypes = android_data.supertypes().unwrap();
+        assert_eq!(supertypes["a/Child"], vec!["a/Base".to_string(), "java/lang/Runnable".to_string()]);
+        assert_eq!(supertypes["a/Base"], vec!["java/lang/Object".to_string()]);
+        assert!(android_data.dex_files.is_empty());
+    }
+
+    #[test]
+    fn whole_program_analyses_read_the_index() {
+        let mut data = ProjectData::Android(indexed_project());
+        let classes = ProjectClasses::of(&mut data).unwrap();
+        assert!(matches!(classes, ProjectClasses::Indexed(_)));
+        let program = Program::from_project(&classes);
+        assert_eq!(program.classes.keys().collect::<Vec<_>>(), ["a/Base", "a/Child"]);
+        let hierarchy = ClassHierarchy::from_project(&classes);
+        assert_eq!(hierarchy.superclass_chain("a/Child"), vec!["a/Base".to_string(), "java/lang/Object".to_string()]);
+        assert!(classes.dex_strings().contains(&"Ljava/lang/Runnable;".to_string()));
+
+        // 解码之后原始字节已释放，同样的分析改读解码结果
+        let ProjectData::Android(android_data) = &mut data else { unreachable!() };
+        android_data.ensure_analyzed().unwrap();
+        let classes = ProjectClasses::of(&mut data).unwrap();
+        assert!(matches!(classes, ProjectClasses::Decoded(_)));
+        assert_eq!(Program::from_project(&classes).classes.len(), 2);
+        assert!(classes.dex_strings().contains(&"Ljava/lang/Runnable;".to_string()));
+    }
+
+    #[test]
+    fn renamed_view_copies_only_the_ids_and_one_class() {
+        let mut android_data = indexed_project();
+        let mut renamer = Renamer::default();
+        for (kind, name, descriptor, new_name) in [
+            (SymbolKind::Class, None, None, "com/example/Base"),
+            (SymbolKind::Method, Some("run"), Some("()V"), "start"),
+        ] {
+            let rename = Rename {
+                kind,
+                class_name: "a/Base".to_string(),
+                name: name.map(str::to_string),
+                descriptor: descriptor.map(str::to_string),
+                new_name: new_name.to_string(),
+            };
+            renamer.map.insert(rename).unwrap();
+        }
+        let (dex_file, class_def) = android_data.dex_class("La/Base;").unwrap().unwrap();
+        let (dex_file, class_def) = renamed_class(dex_file, class_def, &renamer);
+        assert!(dex_file.classes.is_empty());
+        let smali = SmaliPrinter::new(&dex_file).print_class(&class_def).unwrap();
+        assert!(smali.contains(".class public Lcom/example/Base;"), "{}", smali);
+        assert!(smali.contains(".method public static start()V"), "{}", smali);
+
+        // 没有重命名时直接借用
+        let (dex_file, class_def) = android_data.dex_class("La/Base;").unwrap().unwrap();
+        assert!(matches!(renamed_class(dex_file, class_def, &Renamer::default()).0, Cow::Borrowed(_)));
+    }
+}
@@ -1,7 +1,7 @@
 use std::io::{Read, Cursor};
 use zip::ZipArchive;
 use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
-use crate::android_analyzer::dex_analyzer::DexAnalyzer;
+use crate::android_analyzer::dex_index::DexIndex;
 use crate::android_analyzer::axml_analyzer::AXMLAnalyzer;
 use crate::android::AndroidProjectData;
 
@@ -52,34 +52,9 @@ impl ApkAnalyzer {
             }
         }
 
-        // Step 3: Find and analyze DEX files
-        let mut dex_files = Vec::new();
-        let mut dex_index = 0;
-        
-        // Look for classes.dex, classes2.dex, etc.
-        loop {
-            let dex_name = if dex_index == 0 {
-                "classes.dex".to_string()
-            } else {
-                format!("classes{}.dex", dex_index + 1)
-            };
-            
-            match archive.by_name(&dex_name) {
-                Ok(mut dex_file) => {
-                    let mut dex_data = Vec::new();
-                    dex_file.read_to_end(&mut dex_data)?;
-                    
-                    let mut dex_analyzer = DexAnalyzer::new(Cursor::new(dex_data));
-                    let dex_file_struct = dex_analyzer.analyze()?;
-                    dex_files.push(dex_file_struct);
-                    
-                    dex_index += 1;
-                }
-                Err(_) => break,
-            }
-        }
-
-        if dex_files.is_empty() {
+        // Step 3: Index the DEX files; ids and classes are decoded on demand
+        let dex_index = DexIndex::build(Self::dex_entries(&mut archive)?)?;
+        if dex_index.images().is_empty() {
             return Err(AndroidAnalyzeError::InvalidApkFile("No DEX files found in APK".to_string()));
         }
 
@@ -110,11 +85,40 @@ impl ApkAnalyzer {
         
         // Update project data
         project_data.apk_path = self.apk_path.clone();
-        project_data.dex_files = dex_files;
+        project_data.dex_index = Some(dex_index);
 
         Ok(project_data)
     }
 
+    /// Read the raw bytes of classes.dex, classes2.dex, ... without parsing them
+    pub fn read_dex_entries(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let file = std::fs::File::open(&self.apk_path)
+            .map_err(|e| AndroidAnalyzeError::InvalidApkFile(format!("Cannot open APK file: {}", e)))?;
+        let mut archive = ZipArchive::new(file)
+            .map_err(|e| AndroidAnalyzeError::InvalidApkFile(format!("Invalid ZIP archive: {}", e)))?;
+        Self::dex_entries(&mut archive)
+    }
+
+    fn dex_entries(archive: &mut ZipArchive<std::fs::File>) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut entries = Vec::new();
+        // Look for classes.dex, classes2.dex, etc.
+        loop {
+            let dex_name = if entries.is_empty() {
+                "classes.dex".to_string()
+            } else {
+                format!("classes{}.dex", entries.len() + 1)
+            };
+            let Ok(mut dex_file) = archive.by_name(&dex_name) else {
+                break;
+            };
+            let mut dex_data = Vec::new();
+            dex_file.read_to_end(&mut dex_data)?;
+            drop(dex_file);
+            entries.push((dex_name, dex_data));
+        }
+        Ok(entries)
+    }
+
     /// Get the content of a specific file from the APK
     pub fn get_file_content(&mut self, file_path: &str) -> Result<Vec<u8>> {
         if let Some(ref mut archive) = self.zip_archive {
@@ -1,30 +1,58 @@
 use std::io::{Read, Seek};
 use std::collections::HashMap;
 use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
-use crate::android_analyzer::dex_reader::DexReader;
-use crate::android::AndroidManifest;
+use crate::android::{Activity, AndroidManifest, IntentData, IntentFilter, Permission, Provider, Receiver, Service};
+
+const RES_STRING_POOL_TYPE: u16 = 0x0001;
+const RES_XML_TYPE: u16 = 0x0003;
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+const RES_XML_END_ELEMENT_TYPE: u16 = 0x0103;
+const RES_XML_RESOURCE_MAP_TYPE: u16 = 0x0180;
+
+const UTF8_FLAG: u32 = 0x100;
+const NO_INDEX: u32 = 0xFFFFFFFF;
+
+// Res_value 的数据类型
+const TYPE_REFERENCE: u8 = 0x01;
+const TYPE_STRING: u8 = 0x03;
+const TYPE_INT_DEC: u8 = 0x10;
+const TYPE_INT_HEX: u8 = 0x11;
+const TYPE_INT_BOOLEAN: u8 = 0x12;
+
+/// One start tag of a binary XML document, in document order.
+#[derive(Debug, Clone)]
+pub struct XmlElement {
+    pub name: String,
+    /// Attribute names without their namespace, e.g. `name` for `android:name`
+    pub attributes: HashMap<String, String>,
+    /// 0 for the root element
+    pub depth: usize,
+}
+
+impl XmlElement {
+    fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes.get(name).filter(|value| !value.is_empty()).cloned()
+    }
+
+    fn flag(&self, name: &str) -> Option<bool> {
+        self.attributes.get(name).map(|value| value == "true")
+    }
+}
 
 /// AXML (Android XML) analyzer for parsing binary XML files
 pub struct AXMLAnalyzer<R: Read + Seek> {
-    reader: DexReader<R>,
+    reader: R,
 }
 
 impl<R: Read + Seek> AXMLAnalyzer<R> {
     pub fn new(reader: R) -> Self {
-        Self {
-            reader: DexReader::new(reader),
-        }
+        Self { reader }
     }
 
     /// Analyze the binary XML and return the parsed manifest
     pub fn analyze_manifest(&mut self) -> Result<AndroidManifest> {
-        let header = self.read_axml_header()?;
-        let string_pool = self.read_string_pool()?;
-        let _resource_ids = self.read_resource_ids()?;
-        let xml_content = self.read_xml_content(&string_pool)?;
-        
-        // Parse manifest-specific content
-        self.parse_manifest_content(&xml_content, &string_pool)
+        let elements = self.elements()?;
+        parse_manifest(&elements)
     }
 
     /// Analyze the binary XML and return the parsed manifest
@@ -32,297 +60,419 @@ impl<R: Read + Seek> AXMLAnalyzer<R> {
         self.analyze_manifest()
     }
 
-    /// Read AXML header
-    fn read_axml_header(&mut self) -> Result<AXMLHeader> {
-        let magic = self.reader.read_u32()?;
-        if magic != 0x00080003 {
+    /// Every element of the document, e.g. of a compiled layout
+    pub fn elements(&mut self) -> Result<Vec<XmlElement>> {
+        let mut data = Vec::new();
+        self.reader.read_to_end(&mut data)?;
+        let data = &data[..];
+        if read_u16(data, 0)? != RES_XML_TYPE {
             return Err(AndroidAnalyzeError::InvalidManifest("Invalid AXML magic".to_string()));
         }
-
-        let file_size = self.reader.read_u32()?;
-        
-        Ok(AXMLHeader {
-            magic,
-            file_size,
-        })
-    }
-
-    /// Read string pool
-    fn read_string_pool(&mut self) -> Result<Vec<String>> {
-        let chunk_type = self.reader.read_u32()?;
-        if chunk_type != 0x001C0001 {
-            return Err(AndroidAnalyzeError::InvalidManifest("Invalid string pool chunk".to_string()));
-        }
-
-        let chunk_size = self.reader.read_u32()?;
-        let string_count = self.reader.read_u32()?;
-        let style_count = self.reader.read_u32()?;
-        let flags = self.reader.read_u32()?;
-        let strings_start = self.reader.read_u32()?;
-        let styles_start = self.reader.read_u32()?;
-
-        let is_utf8 = (flags & 0x00000100) != 0;
-        
-        // Read string offsets
-        let mut string_offsets = Vec::new();
-        for _ in 0..string_count {
-            string_offsets.push(self.reader.read_u32()?);
-        }
-
-        // Skip style offsets
-        for _ in 0..style_count {
-            self.reader.read_u32()?;
-        }
-
-        // Read strings
+        let end = (read_u32(data, 4)? as usize).min(data.len());
+        let mut position = read_u16(data, 2)? as usize;
         let mut strings = Vec::new();
-        let base_offset = self.reader.stream_position()? as u32;
-        
-        for offset in string_offsets {
-            self.reader.seek((base_offset + offset) as u64)?;
-            
-            let string = if is_utf8 {
-                self.read_utf8_string()?
-            } else {
-                self.read_utf16_string()?
-            };
-            
-            strings.push(string);
+        let mut resource_ids = Vec::new();
+        let mut elements = Vec::new();
+        let mut depth = 0;
+        // 各个块按头部记录的大小依次排列，不认识的块直接跳过
+        while position + 8 <= end {
+            let chunk_type = read_u16(data, position)?;
+            let header_size = read_u16(data, position + 2)? as usize;
+            let chunk_size = read_u32(data, position + 4)? as usize;
+            if chunk_size < 8 || position + chunk_size > end {
+                return Err(AndroidAnalyzeError::ParseError(format!("Truncated XML chunk at 0x{:x}", position)));
+            }
+            let chunk = &data[position..position + chunk_size];
+            match chunk_type {
+                RES_STRING_POOL_TYPE => strings = read_string_pool(chunk)?,
+                RES_XML_RESOURCE_MAP_TYPE => {
+                    resource_ids = (header_size..chunk_size).step_by(4).map(|offset| read_u32(chunk, offset)).collect::<Result<_>>()?;
+                }
+                RES_XML_START_ELEMENT_TYPE => {
+                    let element = read_start_element(chunk, header_size, &strings, &resource_ids, depth)?;
+                    elements.push(element);
+                    depth += 1;
+                }
+                RES_XML_END_ELEMENT_TYPE => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            position += chunk_size;
         }
-
-        Ok(strings)
+        Ok(elements)
     }
+}
 
-    /// Read UTF-8 string
-    fn read_utf8_string(&mut self) -> Result<String> {
-        let _char_count = self.reader.read_u8()?;
-        let byte_count = self.reader.read_u8()?;
-        
-        let mut bytes = vec![0u8; byte_count as usize];
-        self.reader.read_exact(&mut bytes)?;
-        
-        // Skip null terminator
-        self.reader.read_u8()?;
-        
-        String::from_utf8(bytes)
-            .map_err(|e| AndroidAnalyzeError::ParseError(format!("Invalid UTF-8 string: {}", e)))
-    }
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| AndroidAnalyzeError::ParseError(format!("Unexpected end of XML at 0x{:x}", offset)))
+}
 
-    /// Read UTF-16 string
-    fn read_utf16_string(&mut self) -> Result<String> {
-        let char_count = self.reader.read_u16()?;
-        
-        let mut chars = Vec::new();
-        for _ in 0..char_count {
-            chars.push(self.reader.read_u16()?);
-        }
-        
-        // Skip null terminator
-        self.reader.read_u16()?;
-        
-        String::from_utf16(&chars)
-            .map_err(|e| AndroidAnalyzeError::ParseError(format!("Invalid UTF-16 string: {}", e)))
-    }
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| AndroidAnalyzeError::ParseError(format!("Unexpected end of XML at 0x{:x}", offset)))
+}
 
-    /// Read resource IDs
-    fn read_resource_ids(&mut self) -> Result<Vec<u32>> {
-        // Check if there's a resource chunk
-        let chunk_type = self.reader.read_u32()?;
-        if chunk_type != 0x00080180 {
-            // No resource chunk, seek back
-            let current_pos = self.reader.stream_position()?;
-            self.reader.seek(current_pos - 4)?;
-            return Ok(Vec::new());
-        }
+/// 字符串池；偏移都相对于块的起始位置
+fn read_string_pool(chunk: &[u8]) -> Result<Vec<String>> {
+    let header_size = read_u16(chunk, 2)? as usize;
+    let string_count = read_u32(chunk, 8)? as usize;
+    let flags = read_u32(chunk, 16)?;
+    let strings_start = read_u32(chunk, 20)? as usize;
+    let mut strings = Vec::with_capacity(string_count.min(chunk.len() / 4));
+    for index in 0..string_count {
+        let offset = strings_start + read_u32(chunk, header_size + index * 4)? as usize;
+        strings.push(if flags & UTF8_FLAG != 0 { read_utf8_string(chunk, offset)? } else { read_utf16_string(chunk, offset)? });
+    }
+    Ok(strings)
+}
 
-        let chunk_size = self.reader.read_u32()?;
-        let resource_count = (chunk_size - 8) / 4;
-        
-        let mut resource_ids = Vec::new();
-        for _ in 0..resource_count {
-            resource_ids.push(self.reader.read_u32()?);
+/// UTF-8 字符串前有字符数和字节数两个长度，各占一或两个字节
+fn read_utf8_string(chunk: &[u8], offset: usize) -> Result<String> {
+    let length = |offset: usize| -> Result<(usize, usize)> {
+        let first = *chunk.get(offset).ok_or_else(|| AndroidAnalyzeError::ParseError("Truncated string pool".to_string()))? as usize;
+        if first & 0x80 == 0 {
+            return Ok((first, 1));
         }
+        let second = *chunk.get(offset + 1).ok_or_else(|| AndroidAnalyzeError::ParseError("Truncated string pool".to_string()))? as usize;
+        Ok(((first & 0x7F) << 8 | second, 2))
+    };
+    let (_, skip) = length(offset)?;
+    let (byte_count, skip_bytes) = length(offset + skip)?;
+    let start = offset + skip + skip_bytes;
+    let bytes = chunk
+        .get(start..start + byte_count)
+        .ok_or_else(|| AndroidAnalyzeError::ParseError("Truncated string pool".to_string()))?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
 
-        Ok(resource_ids)
+/// UTF-16 字符串前是字符数，最高位置位时占两个 u16
+fn read_utf16_string(chunk: &[u8], offset: usize) -> Result<String> {
+    let mut length = read_u16(chunk, offset)? as usize;
+    let mut start = offset + 2;
+    if length & 0x8000 != 0 {
+        length = (length & 0x7FFF) << 16 | read_u16(chunk, start)? as usize;
+        start += 2;
     }
+    let units = (0..length).map(|i| read_u16(chunk, start + i * 2)).collect::<Result<Vec<u16>>>()?;
+    Ok(String::from_utf16_lossy(&units))
+}
 
-    /// Read XML content
-    fn read_xml_content(&mut self, string_pool: &[String]) -> Result<XmlNode> {
-        let mut nodes = Vec::new();
-        
-        while let Ok(node) = self.read_xml_node(string_pool) {
-            nodes.push(node);
-        }
-        
-        // Find the root node (manifest)
-        for node in nodes {
-            if let XmlNode::StartElement { name, .. } = &node {
-                if name == "manifest" {
-                    return Ok(node);
-                }
+fn read_start_element(chunk: &[u8], header_size: usize, strings: &[String], resource_ids: &[u32], depth: usize) -> Result<XmlElement> {
+    let string = |index: u32| strings.get(index as usize).cloned().unwrap_or_default();
+    let name = string(read_u32(chunk, header_size + 4)?);
+    let attribute_start = read_u16(chunk, header_size + 8)? as usize;
+    let attribute_size = read_u16(chunk, header_size + 10)? as usize;
+    let attribute_count = read_u16(chunk, header_size + 12)? as usize;
+    let mut attributes = HashMap::new();
+    for index in 0..attribute_count {
+        let offset = header_size + attribute_start + index * attribute_size;
+        let name_index = read_u32(chunk, offset + 4)?;
+        let raw_value = read_u32(chunk, offset + 8)?;
+        let data_type = *chunk.get(offset + 15).ok_or_else(|| AndroidAnalyzeError::ParseError("Truncated attribute".to_string()))?;
+        let data = read_u32(chunk, offset + 16)?;
+        // 加固或压缩过的清单可能抹掉属性名，只留下资源 id
+        let mut attribute_name = string(name_index);
+        if attribute_name.is_empty() {
+            match resource_ids.get(name_index as usize).and_then(|id| framework_attribute_name(*id)) {
+                Some(known) => attribute_name = known.to_string(),
+                None => continue,
             }
         }
-        
-        Err(AndroidAnalyzeError::InvalidManifest("No manifest root element found".to_string()))
+        let value = if raw_value != NO_INDEX {
+            string(raw_value)
+        } else {
+            match data_type {
+                TYPE_STRING => string(data),
+                TYPE_INT_DEC => (data as i32).to_string(),
+                TYPE_INT_HEX => format!("0x{:x}", data),
+                TYPE_INT_BOOLEAN => (data != 0).to_string(),
+                TYPE_REFERENCE => format!("@0x{:08x}", data),
+                _ => format!("0x{:08x}", data),
+            }
+        };
+        attributes.insert(attribute_name, value);
+    }
+    Ok(XmlElement { name, attributes, depth })
+}
+
+/// 入口点分析用到的 `android:` 属性的资源 id
+fn framework_attribute_name(id: u32) -> Option<&'static str> {
+    match id {
+        0x01010003 => Some("name"),
+        0x01010010 => Some("exported"),
+        0x01010018 => Some("authorities"),
+        0x0101021b => Some("versionCode"),
+        0x0101021c => Some("versionName"),
+        0x0101027f => Some("backupAgent"),
+        _ => None,
     }
+}
+
+/// 组件内的 intent-filter 挂到最近声明的组件上
+enum Component {
+    Activity,
+    Service,
+    Receiver,
+    Provider,
+}
 
-    /// Read XML node
-    fn read_xml_node(&mut self, string_pool: &[String]) -> Result<XmlNode> {
-        let chunk_type = self.reader.read_u32()?;
-        let chunk_size = self.reader.read_u32()?;
-        
-        match chunk_type {
-            0x00100100 => {
-                // START_NAMESPACE
-                let line_number = self.reader.read_u32()?;
-                let comment = self.reader.read_u32()?;
-                let prefix = self.reader.read_u32()?;
-                let uri = self.reader.read_u32()?;
-                
-                Ok(XmlNode::StartNamespace {
-                    line_number,
-                    prefix: if prefix != 0xFFFFFFFF { Some(string_pool[prefix as usize].clone()) } else { None },
-                    uri: if uri != 0xFFFFFFFF { Some(string_pool[uri as usize].clone()) } else { None },
-                })
+fn parse_manifest(elements: &[XmlElement]) -> Result<AndroidManifest> {
+    let root = elements
+        .first()
+        .filter(|element| element.name == "manifest")
+        .ok_or_else(|| AndroidAnalyzeError::InvalidManifest("No manifest root element found".to_string()))?;
+    let mut manifest = AndroidManifest {
+        package_name: root
+            .attribute("package")
+            .ok_or_else(|| AndroidAnalyzeError::InvalidManifest("Missing package attribute".to_string()))?,
+        version_code: root.attribute("versionCode").and_then(|v| v.parse().ok()).unwrap_or(0),
+        version_name: root.attribute("versionName").unwrap_or_else(|| "1.0".to_string()),
+        application_name: None,
+        backup_agent: None,
+        activities: Vec::new(),
+        services: Vec::new(),
+        receivers: Vec::new(),
+        providers: Vec::new(),
+        instrumentations: Vec::new(),
+        permissions: Vec::new(),
+        uses_permissions: Vec::new(),
+    };
+
+    let mut path: Vec<&str> = Vec::new();
+    let mut component: Option<Component> = None;
+    for element in elements {
+        path.truncate(element.depth);
+        let parent = path.last().copied();
+        path.push(&element.name);
+        let name = element.attribute("name");
+        match (parent, element.name.as_str()) {
+            (Some("manifest"), "application") => {
+                manifest.application_name = name;
+                manifest.backup_agent = element.attribute("backupAgent");
             }
-            0x00100101 => {
-                // END_NAMESPACE
-                Ok(XmlNode::EndNamespace)
+            (Some("manifest"), "instrumentation") => manifest.instrumentations.extend(name),
+            (Some("manifest"), "uses-permission") => manifest.uses_permissions.extend(name),
+            (Some("manifest"), "permission") => {
+                if let Some(name) = name {
+                    manifest.permissions.push(Permission {
+                        name,
+                        protection_level: element.attribute("protectionLevel").unwrap_or_default(),
+                    });
+                }
             }
-            0x00100102 => {
-                // START_TAG
-                let line_number = self.reader.read_u32()?;
-                let comment = self.reader.read_u32()?;
-                let namespace = self.reader.read_u32()?;
-                let name = self.reader.read_u32()?;
-                let attribute_start = self.reader.read_u32()?;
-                let attribute_size = self.reader.read_u32()?;
-                let attribute_count = self.reader.read_u32()?;
-                let id_attribute = self.reader.read_u32()?;
-                let class_attribute = self.reader.read_u32()?;
-                let style_attribute = self.reader.read_u32()?;
-                
-                let element_name = if name != 0xFFFFFFFF {
-                    string_pool[name as usize].clone()
-                } else {
-                    "unknown".to_string()
-                };
-                
-                let mut attributes = HashMap::new();
-                for _ in 0..attribute_count {
-                    let attr_namespace = self.reader.read_u32()?;
-                    let attr_name = self.reader.read_u32()?;
-                    let attr_value = self.reader.read_u32()?;
-                    let attr_type = self.reader.read_u32()?;
-                    let attr_data = self.reader.read_u32()?;
-                    
-                    if attr_name != 0xFFFFFFFF {
-                        let attr_name_str = string_pool[attr_name as usize].clone();
-                        let attr_value_str = if attr_value != 0xFFFFFFFF {
-                            string_pool[attr_value as usize].clone()
-                        } else {
-                            format!("0x{:08x}", attr_data)
-                        };
-                        
-                        attributes.insert(attr_name_str, attr_value_str);
+            (Some("application"), kind) => {
+                component = None;
+                let Some(name) = name else { continue };
+                let exported = element.flag("exported").unwrap_or(false);
+                match kind {
+                    "activity" => {
+                        let label = element.attribute("label");
+                        manifest.activities.push(Activity { name, label, exported, intent_filters: Vec::new() });
+                        component = Some(Component::Activity);
+                    }
+                    "service" => {
+                        manifest.services.push(Service { name, exported, intent_filters: Vec::new() });
+                        component = Some(Component::Service);
+                    }
+                    "receiver" => {
+                        manifest.receivers.push(Receiver { name, exported, intent_filters: Vec::new() });
+                        component = Some(Component::Receiver);
                     }
+                    "provider" => {
+                        manifest.providers.push(Provider {
+                            name,
+                            authorities: element.attribute("authorities").map(|a| a.split(';').map(str::to_string).collect()).unwrap_or_default(),
+                            exported,
+                            grant_uri_permissions: element.flag("grantUriPermissions").unwrap_or(false),
+                        });
+                        component = Some(Component::Provider);
+                    }
+                    _ => {}
                 }
-                
-                Ok(XmlNode::StartElement {
-                    name: element_name,
-                    attributes,
-                })
             }
-            0x00100103 => {
-                // END_TAG
-                Ok(XmlNode::EndElement)
+            (Some("activity" | "service" | "receiver"), "intent-filter") => {
+                let filters = match component {
+                    Some(Component::Activity) => manifest.activities.last_mut().map(|c| &mut c.intent_filters),
+                    Some(Component::Service) => manifest.services.last_mut().map(|c| &mut c.intent_filters),
+                    Some(Component::Receiver) => manifest.receivers.last_mut().map(|c| &mut c.intent_filters),
+                    _ => None,
+                };
+                if let Some(filters) = filters {
+                    filters.push(IntentFilter { actions: Vec::new(), categories: Vec::new(), data: Vec::new() });
+                }
             }
-            0x00100104 => {
-                // TEXT
-                let line_number = self.reader.read_u32()?;
-                let comment = self.reader.read_u32()?;
-                let name = self.reader.read_u32()?;
-                let _unknown1 = self.reader.read_u32()?;
-                let _unknown2 = self.reader.read_u32()?;
-                
-                let text = if name != 0xFFFFFFFF {
-                    string_pool[name as usize].clone()
-                } else {
-                    String::new()
+            (Some("intent-filter"), child) => {
+                let filter = match component {
+                    Some(Component::Activity) => manifest.activities.last_mut().and_then(|c| c.intent_filters.last_mut()),
+                    Some(Component::Service) => manifest.services.last_mut().and_then(|c| c.intent_filters.last_mut()),
+                    Some(Component::Receiver) => manifest.receivers.last_mut().and_then(|c| c.intent_filters.last_mut()),
+                    _ => None,
                 };
-                
-                Ok(XmlNode::Text { content: text })
+                let Some(filter) = filter else { continue };
+                match child {
+                    "action" => filter.actions.extend(name),
+                    "category" => filter.categories.extend(name),
+                    "data" => filter.data.push(IntentData {
+                        scheme: element.attribute("scheme"),
+                        host: element.attribute("host"),
+                        port: element.attribute("port"),
+                        path: element.attribute("path"),
+                        path_pattern: element.attribute("pathPattern"),
+                        path_prefix: element.attribute("pathPrefix"),
+                        mime_type: element.attribute("mimeType"),
+                    }),
+                    _ => {}
+                }
             }
-            _ => Err(AndroidAnalyzeError::InvalidManifest(format!("Unknown chunk type: 0x{:08x}", chunk_type))),
+            _ => {}
         }
     }
+    Ok(manifest)
+}
 
-    /// Parse manifest-specific content
-    fn parse_manifest_content(&mut self, xml_node: &XmlNode, string_pool: &[String]) -> Result<AndroidManifest> {
-        if let XmlNode::StartElement { name, attributes } = xml_node {
-            if name != "manifest" {
-                return Err(AndroidAnalyzeError::InvalidManifest("Expected manifest root element".to_string()));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    type Element<'a> = (usize, &'a str, &'a [(&'a str, &'a str)]);
+
+    /// 按 aapt 的布局编码一个二进制 XML，元素为 (深度, 名称, 属性)
+    fn encode(elements: &[Element]) -> Vec<u8> {
+        let mut strings: Vec<String> = Vec::new();
+        let mut index = |text: &str| -> u32 {
+            match strings.iter().position(|s| s == text) {
+                Some(i) => i as u32,
+                None => {
+                    strings.push(text.to_string());
+                    strings.len() as u32 - 1
+                }
             }
-            
-            let package_name = attributes.get("package")
-                .ok_or_else(|| AndroidAnalyzeError::InvalidManifest("Missing package attribute".to_string()))?
-                .clone();
-            
-            let version_code = attributes.get("versionCode")
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(0);
-            
-            let version_name = attributes.get("versionName")
-                .cloned()
-                .unwrap_or_else(|| "1.0".to_string());
-            
-            // TODO: Parse activities, services, receivers, providers, permissions
-            let activities = Vec::new();
-            let services = Vec::new();
-            let receivers = Vec::new();
-            let providers = Vec::new();
-            let permissions = Vec::new();
-            let uses_permissions = Vec::new();
-            
-            Ok(AndroidManifest {
-                package_name,
-                version_code,
-                version_name,
-                activities,
-                services,
-                receivers,
-                providers,
-                permissions,
-                uses_permissions,
-            })
-        } else {
-            Err(AndroidAnalyzeError::InvalidManifest("Invalid XML structure".to_string()))
+        };
+        let mut body = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+        let end_tags = |body: &mut Vec<u8>, open: &mut Vec<usize>, depth: usize| {
+            while open.len() > depth {
+                open.pop();
+                body.extend(0x0103u16.to_le_bytes());
+                body.extend(0x10u16.to_le_bytes());
+                body.extend(24u32.to_le_bytes());
+                body.extend([0u8; 16]);
+            }
+        };
+        for (depth, name, attributes) in elements {
+            end_tags(&mut body, &mut open, *depth);
+            open.push(*depth);
+            let name = index(name);
+            body.extend(0x0102u16.to_le_bytes());
+            body.extend(0x10u16.to_le_bytes());
+            body.extend((36 + 20 * attributes.len() as u32).to_le_bytes());
+            body.extend(0u32.to_le_bytes());
+            body.extend(NO_INDEX.to_le_bytes());
+            body.extend(NO_INDEX.to_le_bytes());
+            body.extend(name.to_le_bytes());
+            body.extend(20u16.to_le_bytes());
+            body.extend(20u16.to_le_bytes());
+            body.extend((attributes.len() as u16).to_le_bytes());
+            body.extend([0u8; 6]);
+            for (attribute, value) in attributes.iter() {
+                let (attribute, value) = (index(attribute), index(value));
+                body.extend(NO_INDEX.to_le_bytes());
+                body.extend(attribute.to_le_bytes());
+                body.extend(value.to_le_bytes());
+                body.extend(8u16.to_le_bytes());
+                body.extend([0u8, TYPE_STRING]);
+                body.extend(value.to_le_bytes());
+            }
+        }
+        end_tags(&mut body, &mut open, 0);
+
+        let mut pool_data = Vec::new();
+        let mut offsets = Vec::new();
+        for text in &strings {
+            offsets.push(pool_data.len() as u32);
+            let units: Vec<u16> = text.encode_utf16().collect();
+            pool_data.extend((units.len() as u16).to_le_bytes());
+            units.iter().for_each(|unit| pool_data.extend(unit.to_le_bytes()));
+            pool_data.extend(0u16.to_le_bytes());
+        }
+        while pool_data.len() % 4 != 0 {
+            pool_data.push(0);
+        }
+        let strings_start = 28 + 4 * offsets.len() as u32;
+        let mut pool = Vec::new();
+        pool.extend(RES_STRING_POOL_TYPE.to_le_bytes());
+        pool.extend(28u16.to_le_bytes());
+        pool.extend((strings_start + pool_data.len() as u32).to_le_bytes());
+        pool.extend((offsets.len() as u32).to_le_bytes());
+        pool.extend(0u32.to_le_bytes());
+        pool.extend(0u32.to_le_bytes());
+        pool.extend(strings_start.to_le_bytes());
+        pool.extend(0u32.to_le_bytes());
+        offsets.iter().for_each(|offset| pool.extend(offset.to_le_bytes()));
+        pool.extend(pool_data);
+
+        let mut document = Vec::new();
+        document.extend(RES_XML_TYPE.to_le_bytes());
+        document.extend(8u16.to_le_bytes());
+        document.extend((8 + pool.len() as u32 + body.len() as u32).to_le_bytes());
+        document.extend(pool);
+        document.extend(body);
+        document
+    }
+
+    #[test]
+    fn manifest_lists_application_components_and_instrumentation() {
+        let bytes = encode(&[
+            (0, "manifest", &[("package", "com.example"), ("versionName", "2.0")]),
+            (1, "uses-permission", &[("name", "android.permission.INTERNET")]),
+            (1, "instrumentation", &[("name", ".Runner")]),
+            (1, "application", &[("name", ".App"), ("backupAgent", "com.example.Backup")]),
+            (2, "activity", &[("name", ".MainActivity"), ("exported", "true")]),
+            (3, "intent-filter", &[]),
+            (4, "action", &[("name", "android.intent.action.MAIN")]),
+            (2, "service", &[("name", "Sync")]),
+            (2, "provider", &[("name", "com.example.Files"), ("authorities", "a;b")]),
+        ]);
+        let manifest = AXMLAnalyzer::new(Cursor::new(bytes)).analyze_manifest().unwrap();
+        assert_eq!(manifest.package_name, "com.example");
+        assert_eq!(manifest.version_name, "2.0");
+        assert_eq!(manifest.uses_permissions, vec!["android.permission.INTERNET".to_string()]);
+        assert_eq!(manifest.activities[0].intent_filters[0].actions, vec!["android.intent.action.MAIN".to_string()]);
+        assert_eq!(manifest.providers[0].authorities, vec!["a".to_string(), "b".to_string()]);
+
+        let classes: Vec<(&str, String)> = manifest.component_classes();
+        for expected in [
+            ("Application", "com.example.App"),
+            ("BackupAgent", "com.example.Backup"),
+            ("Instrumentation", "com.example.Runner"),
+            ("Activity", "com.example.MainActivity"),
+            ("Service", "com.example.Sync"),
+            ("Provider", "com.example.Files"),
+        ] {
+            assert!(classes.contains(&(expected.0, expected.1.to_string())), "{:?} missing from {:?}", expected, classes);
         }
     }
-}
 
-/// AXML header structure
-#[derive(Debug, Clone)]
-struct AXMLHeader {
-    magic: u32,
-    file_size: u32,
-}
+    #[test]
+    fn layout_elements_keep_their_nesting() {
+        let bytes = encode(&[
+            (0, "LinearLayout", &[]),
+            (1, "com.example.ChartView", &[("id", "@0x7f010001")]),
+            (1, "fragment", &[("name", "com.example.ListFragment")]),
+        ]);
+        let elements = AXMLAnalyzer::new(Cursor::new(bytes)).elements().unwrap();
+        let names: Vec<(usize, &str)> = elements.iter().map(|e| (e.depth, e.name.as_str())).collect();
+        assert_eq!(names, vec![(0, "LinearLayout"), (1, "com.example.ChartView"), (1, "fragment")]);
+        assert_eq!(elements[2].attributes["name"], "com.example.ListFragment");
+    }
 
-/// XML node types
-#[derive(Debug, Clone)]
-enum XmlNode {
-    StartNamespace {
-        line_number: u32,
-        prefix: Option<String>,
-        uri: Option<String>,
-    },
-    EndNamespace,
-    StartElement {
-        name: String,
-        attributes: HashMap<String, String>,
-    },
-    EndElement,
-    Text {
-        content: String,
-    },
+    #[test]
+    fn truncated_documents_are_rejected() {
+        let mut bytes = encode(&[(0, "manifest", &[("package", "com.example")])]);
+        bytes.truncate(bytes.len() - 10);
+        assert!(AXMLAnalyzer::new(Cursor::new(bytes)).elements().is_err());
+    }
 }
@@ -0,0 +1,1777 @@
+// Dalvik 前端：把一个方法的寄存器指令翻译成由 Stmt 组成的控制流图，结构化和输出与 JVM 前端共用
+//
+// 寄存器先按到达定值划分成变量网：能到达同一个读取点的定值属于同一个网，每个网对应一个 Java 局部变量。
+// 这样同一个寄存器先后保存的不相关的值会成为不同的变量，而不是被合并成一个 Object 变量。
+// 只有一个定值、并且在同一个块内只被读取一次的网不生成变量，直接内联到读取它的表达式中。
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::Range;
+
+use crate::android::{ClassDef, CodeItem, DexFile, LocalVariable, Method};
+use crate::android_analyzer::dalvik_opcode::{
+    payload_at, DalvikInstruction, DalvikOpcode, DalvikOpcodeAnalyzer, DalvikReference,
+};
+use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
+use crate::hierarchy::{descriptor_to_internal_name, proto_to_descriptor};
+use crate::java_analyzer::ast::{
+    class_descriptor, expr_to_string, is_wide, parse_method_descriptor, BinaryOp, Expr, InvokeKind, Literal, Stmt,
+    UnaryOp,
+};
+use crate::java_analyzer::controlflow::{BasicBlock, ControlFlowGraph, ExceptionHandler, Terminator};
+use crate::java_analyzer::controlflowbuilder::{coerce, LiftedMethod};
+
+const ACC_STATIC: u32 = 0x0008;
+const OBJECT: &str = "Ljava/lang/Object;";
+const THROWABLE: &str = "Ljava/lang/Throwable;";
+
+/// 寄存器的一次定值
+struct DefSite {
+    /// 定值指令的下标，None 表示方法入口处的参数（或从未赋值就被读取的寄存器）
+    index: Option<usize>,
+    register: u32,
+    wide: bool,
+}
+
+/// 一组互相关联的定值和读取，对应一个 Java 变量
+struct Web {
+    register: u32,
+    defs: Vec<usize>,
+    /// 读取该网的指令下标，同一条指令读两次就出现两次
+    uses: Vec<usize>,
+    descriptor: String,
+    /// 调试信息或参数表中的名字
+    name: Option<String>,
+    parameter: bool,
+}
+
+/// 常量定值的类型要由读取处决定
+enum DefType {
+    Known(String),
+    Const,
+    Unknown,
+}
+
+/// 一条指令读取和写入的寄存器，读取附带该位置期望的类型
+struct Operands {
+    uses: Vec<(u32, Option<String>)>,
+    def: Option<(u32, bool)>,
+}
+
+type RegisterState = HashMap<u32, BTreeSet<usize>>;
+
+pub(crate) struct DalvikLifter<'a> {
+    dex_file: &'a DexFile,
+    class_name: String,
+    method: &'a Method,
+    descriptor: String,
+    code: &'a CodeItem,
+    /// 包含 switch / 数组数据负载在内的全部指令，负载按地址查找
+    instructions: Vec<DalvikInstruction>,
+    /// 去掉负载后的指令，下面所有的指令下标都指这里
+    insns: Vec<DalvikInstruction>,
+    debug_locals: Vec<LocalVariable>,
+    defs: Vec<DefSite>,
+    def_at: Vec<Option<usize>>,
+    web_of_def: Vec<usize>,
+    use_webs: HashMap<(usize, u32), usize>,
+    webs: Vec<Web>,
+    this_web: Option<usize>,
+    inline: HashSet<usize>,
+    block_of_insn: Vec<usize>,
+    names: HashMap<usize, String>,
+    used_names: HashSet<String>,
+    var_types: HashMap<String, String>,
+    var_order: Vec<String>,
+    stored: HashSet<String>,
+    temporaries: HashSet<String>,
+    /// 已经求值、等待内联到读取处的网
+    pending: Vec<(usize, Expr)>,
+    /// new-instance 之后、构造器调用之前的对象
+    uninitialized: HashMap<usize, String>,
+    /// 等待 move-result 取走的调用结果
+    result: Option<Expr>,
+    /// 指令地址 -> 还原出的字符串，替换解密调用和字符串表读取
+    strings: HashMap<u32, String>,
+}
+
+impl<'a> DalvikLifter<'a> {
+    pub fn new(dex_file: &'a DexFile, class_def: &'a ClassDef, method: &'a Method) -> Result<Self> {
+        let code = method
+            .code
+            .as_ref()
+            .ok_or_else(|| AndroidAnalyzeError::ParseError(format!("Method {} has no code", method.name)))?;
+        let instructions = DalvikOpcodeAnalyzer::new().analyze_method(code, dex_file)?;
+        let insns: Vec<DalvikInstruction> = instructions.iter().filter(|i| i.payload.is_none()).cloned().collect();
+        let debug_locals = code
+            .debug_info
+            .iter()
+            .flat_map(|info| info.locals.iter())
+            .filter(|local| local.name.is_some() && local.local_type.is_some())
+            .cloned()
+            .collect();
+        Ok(DalvikLifter {
+            dex_file,
+            class_name: descriptor_to_internal_name(&class_def.class_type.descriptor),
+            method,
+            descriptor: proto_to_descriptor(&method.proto),
+            code,
+            instructions,
+            def_at: vec![None; insns.len()],
+            insns,
+            debug_locals,
+            defs: Vec::new(),
+            web_of_def: Vec::new(),
+            use_webs: HashMap::new(),
+            webs: Vec::new(),
+            this_web: None,
+            inline: HashSet::new(),
+            block_of_insn: Vec::new(),
+            names: HashMap::new(),
+            used_names: ["this", "super"].iter().map(|s| s.to_string()).collect(),
+            var_types: HashMap::new(),
+            var_order: Vec::new(),
+            stored: HashSet::new(),
+            temporaries: HashSet::new(),
+            pending: Vec::new(),
+            uninitialized: HashMap::new(),
+            result: None,
+            strings: HashMap::new(),
+        })
+    }
+
+    pub fn with_strings(mut self, strings: HashMap<u32, String>) -> Self {
+        self.strings = strings;
+        self
+    }
+
+    pub fn build(mut self) -> Result<LiftedMethod> {
+        if self.insns.is_empty() {
+            return Err(AndroidAnalyzeError::ParseError(format!("Method {} has no instructions", self.method.name)));
+        }
+        let code_end = self.code.insns.len() as u32;
+        self.remove_unreachable();
+
+        // 1. 找出所有基本块的起点
+        let mut leaders = BTreeSet::new();
+        leaders.insert(0u32);
+        for insn in &self.insns {
+            let next = insn.address + insn.size as u32;
+            if is_branch(insn.opcode) {
+                leaders.extend(insn.target);
+                leaders.insert(next);
+            } else if matches!(insn.opcode, DalvikOpcode::PackedSwitch | DalvikOpcode::SparseSwitch) {
+                leaders.extend(insn.switch_cases(&self.instructions).into_iter().map(|(_, target)| target));
+                leaders.insert(next);
+            } else if insn.opcode.is_terminator() {
+                leaders.insert(next);
+            }
+        }
+        for try_item in &self.code.tries {
+            leaders.extend(self.try_start(try_item.start_addr));
+            leaders.extend(self.try_start(self.try_end(try_item.start_addr + try_item.insn_count as u32)));
+            if let Some(handler) = self.code.handler(try_item) {
+                leaders.extend(handler.handlers.iter().map(|pair| pair.addr));
+                leaders.extend(handler.catch_all_addr);
+            }
+        }
+        let addresses: HashSet<u32> = self.insns.iter().map(|i| i.address).collect();
+        let leaders: Vec<u32> = leaders.into_iter().filter(|address| addresses.contains(address)).collect();
+        let block_of: HashMap<u32, usize> = leaders.iter().enumerate().map(|(i, address)| (*address, i)).collect();
+        let block_index = |address: u32| -> Result<usize> {
+            block_of
+                .get(&address)
+                .copied()
+                .ok_or_else(|| AndroidAnalyzeError::ParseError(format!("Branch to invalid address 0x{:04x}", address)))
+        };
+
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for (index, insn) in self.insns.iter().enumerate().skip(1) {
+            if block_of.contains_key(&insn.address) {
+                ranges.push(start..index);
+                start = index;
+            }
+        }
+        ranges.push(start..self.insns.len());
+        self.block_of_insn = vec![0; self.insns.len()];
+        for (block, range) in ranges.iter().enumerate() {
+            for index in range.clone() {
+                self.block_of_insn[index] = block;
+            }
+        }
+
+        // 2. 异常表
+        let mut handlers = Vec::new();
+        let mut handler_blocks: HashMap<usize, Option<String>> = HashMap::new();
+        for try_item in &self.code.tries {
+            let end_address = self.try_end(try_item.start_addr + try_item.insn_count as u32);
+            // 整个 try 块都不可达
+            let Some(start_address) = self.try_start(try_item.start_addr).filter(|start| *start < end_address) else {
+                continue;
+            };
+            let start = block_index(start_address)?;
+            let end = match self.try_start(end_address) {
+                Some(address) if address < code_end => block_index(address)?,
+                _ => ranges.len(),
+            };
+            let Some(handler) = self.code.handler(try_item) else {
+                continue;
+            };
+            let catches = handler
+                .handlers
+                .iter()
+                .map(|pair| {
+                    let class_type = self.catch_type(pair.type_idx)?;
+                    Ok((pair.addr, Some(descriptor_to_internal_name(&class_type))))
+                })
+                .chain(handler.catch_all_addr.map(|address| Ok((address, None))))
+                .collect::<Result<Vec<_>>>()?;
+            for (address, class_name) in catches {
+                let handler = block_index(address)?;
+                // multi-catch 时取公共父类 Throwable
+                let catch_type = match (&class_name, handler_blocks.get(&handler)) {
+                    (Some(name), None) => Some(name.clone()),
+                    (Some(name), Some(Some(existing))) if existing == name => Some(name.clone()),
+                    _ => None,
+                };
+                handler_blocks.insert(handler, catch_type);
+                handlers.push(ExceptionHandler {
+                    start,
+                    end,
+                    handler,
+                    class_name,
+                    variable: String::new(),
+                });
+            }
+        }
+
+        // 3. 到达定值分析，划分变量网并推断类型
+        let successors = ranges
+            .iter()
+            .enumerate()
+            .map(|(block, range)| self.block_successors(block, range, &block_index))
+            .collect::<Result<Vec<_>>>()?;
+        self.build_webs(&ranges, &successors, &handlers, &handler_blocks)?;
+
+        let parameter_webs: Vec<usize> = (0..self.webs.len())
+            .filter(|web| self.webs[*web].parameter && Some(*web) != self.this_web)
+            .collect();
+        let parameters: Vec<(String, String)> = parameter_webs
+            .into_iter()
+            .map(|web| (self.web_name(web), self.webs[web].descriptor.clone()))
+            .collect();
+        let parameter_names: HashSet<String> = parameters.iter().map(|(name, _)| name.clone()).collect();
+
+        // 4. 逐块翻译
+        let mut blocks = Vec::new();
+        let mut catch_variables: HashMap<usize, String> = HashMap::new();
+        for (block, range) in ranges.iter().enumerate() {
+            let mut statements = Vec::new();
+            let mut range = range.clone();
+            if let Some(catch_type) = handler_blocks.get(&block) {
+                let first = &self.insns[range.start];
+                let variable = if first.opcode == DalvikOpcode::MoveException {
+                    let web = self.web_of_def[self.def_at[range.start].expect("move-exception defines a register")];
+                    let name = self.web_name(web);
+                    range.start += 1;
+                    if self.webs[web].defs.len() > 1 {
+                        // catch 变量在别处也被赋值：改用新名字，再赋给原来的变量
+                        let renamed = self.unique_name(&format!("{}Ex", name));
+                        statements.push(Stmt::Assign {
+                            target: Expr::Local(name.clone()),
+                            value: Expr::Local(renamed.clone()),
+                        });
+                        self.stored.insert(name);
+                        renamed
+                    } else {
+                        name
+                    }
+                } else {
+                    self.unique_name(&format!("ex{}", first.address))
+                };
+                let descriptor = class_descriptor(catch_type.as_deref().unwrap_or("java/lang/Throwable"));
+                self.var_types.entry(variable.clone()).or_insert(descriptor);
+                catch_variables.insert(block, variable);
+            }
+
+            let terminator = match self.lift_block(range, &mut statements, &block_index) {
+                Ok(terminator) => terminator,
+                Err(error) => {
+                    statements.push(Stmt::Comment(format!("decompilation error: {}", error)));
+                    Terminator::Exit
+                }
+            };
+            self.pending.clear();
+            self.uninitialized.clear();
+            self.result = None;
+            blocks.push(BasicBlock {
+                offset: self.insns[ranges[block].start].address,
+                statements,
+                terminator,
+            });
+        }
+        for handler in handlers.iter_mut() {
+            handler.variable = catch_variables.get(&handler.handler).cloned().unwrap_or_else(|| "ex".to_string());
+        }
+
+        let catch_names: HashSet<&String> = handlers.iter().map(|h| &h.variable).collect();
+        let locals = self
+            .var_order
+            .iter()
+            .filter(|name| !parameter_names.contains(*name) && !catch_names.contains(name))
+            .filter(|name| self.stored.contains(*name) || self.temporaries.contains(*name))
+            .map(|name| {
+                let descriptor = self.var_types.get(name).cloned().unwrap_or_else(|| OBJECT.to_string());
+                (name.clone(), descriptor)
+            })
+            .collect();
+
+        let mut cfg = ControlFlowGraph::new();
+        for block in blocks {
+            cfg.add_block(block);
+        }
+        cfg.handlers = handlers;
+        cfg.temporaries = self.temporaries.clone();
+        Ok(LiftedMethod {
+            cfg,
+            parameters,
+            locals,
+        })
+    }
+
+    /// 异常表中的类型索引直接指向 type_ids
+    fn catch_type(&self, type_idx: u32) -> Result<String> {
+        self.dex_file
+            .types
+            .get(type_idx as usize)
+            .map(|t| t.descriptor.clone())
+            .ok_or_else(|| AndroidAnalyzeError::InvalidDexFile(format!("Invalid type index {}", type_idx)))
+    }
+
+    /// 去掉从入口和异常处理器都到达不了的指令，它们读取的寄存器没有定值
+    fn remove_unreachable(&mut self) {
+        let index_of: HashMap<u32, usize> = self.insns.iter().enumerate().map(|(i, insn)| (insn.address, i)).collect();
+        let mut handlers: Vec<(u32, u32, Vec<u32>)> = Vec::new();
+        for try_item in &self.code.tries {
+            if let Some(handler) = self.code.handler(try_item) {
+                let mut targets: Vec<u32> = handler.handlers.iter().map(|pair| pair.addr).collect();
+                targets.extend(handler.catch_all_addr);
+                handlers.push((try_item.start_addr, try_item.start_addr + try_item.insn_count as u32, targets));
+            }
+        }
+
+        let mut reachable = vec![false; self.insns.len()];
+        let mut worklist = vec![0usize];
+        while let Some(index) = worklist.pop() {
+            if reachable[index] {
+                continue;
+            }
+            reachable[index] = true;
+            let insn = &self.insns[index];
+            let mut targets = Vec::new();
+            if !insn.opcode.is_terminator() {
+                targets.push(insn.address + insn.size as u32);
+            }
+            if is_branch(insn.opcode) {
+                targets.extend(insn.target);
+            } else if matches!(insn.opcode, DalvikOpcode::PackedSwitch | DalvikOpcode::SparseSwitch) {
+                targets.extend(insn.switch_cases(&self.instructions).into_iter().map(|(_, target)| target));
+            }
+            for (start, end, handler_targets) in &handlers {
+                if (*start..*end).contains(&insn.address) {
+                    targets.extend(handler_targets);
+                }
+            }
+            worklist.extend(targets.into_iter().filter_map(|address| index_of.get(&address).copied()));
+        }
+
+        let mut index = 0;
+        self.insns.retain(|_| {
+            index += 1;
+            reachable[index - 1]
+        });
+        self.def_at.truncate(self.insns.len());
+    }
+
+    /// 区间起点之后的第一条指令
+    fn try_start(&self, address: u32) -> Option<u32> {
+        self.insns.iter().map(|insn| insn.address).find(|a| *a >= address)
+    }
+
+    /// try 块结束在 move-result 上时把它也包含进来，保证调用和取结果在同一个块中
+    fn try_end(&self, address: u32) -> u32 {
+        match self.insns.iter().find(|insn| insn.address == address) {
+            Some(insn) if is_move_result(insn.opcode) => address + insn.size as u32,
+            _ => address,
+        }
+    }
+
+    /// 块的正常后继（不含异常边）
+    fn block_successors(&self, block: usize, range: &Range<usize>, block_index: &dyn Fn(u32) -> Result<usize>) -> Result<Vec<usize>> {
+        use DalvikOpcode::*;
+        let last = &self.insns[range.end - 1];
+        let next = block + 1;
+        Ok(match last.opcode {
+            Goto | Goto16 | Goto32 => vec![block_index(last.target.unwrap_or_default())?],
+            _ if is_branch(last.opcode) => vec![next, block_index(last.target.unwrap_or_default())?],
+            PackedSwitch | SparseSwitch => {
+                let mut targets = vec![next];
+                for (_, target) in last.switch_cases(&self.instructions) {
+                    targets.push(block_index(target)?);
+                }
+                targets
+            }
+            opcode if opcode.is_terminator() => vec![],
+            _ => vec![next],
+        })
+    }
+
+    /// 到达定值分析：把能到达同一读取点的定值合并成网，再推断每个网的类型
+    fn build_webs(
+        &mut self,
+        ranges: &[Range<usize>],
+        successors: &[Vec<usize>],
+        handlers: &[ExceptionHandler],
+        handler_blocks: &HashMap<usize, Option<String>>,
+    ) -> Result<()> {
+        let (params, return_type) = parse_method_descriptor(&self.descriptor);
+        let is_static = self.method.access_flags & ACC_STATIC != 0;
+        let first_parameter = self.code.registers_size.saturating_sub(self.code.ins_size) as u32;
+
+        // 参数是入口处的定值
+        let mut entry = RegisterState::new();
+        let mut parameter_defs = Vec::new();
+        let mut register = first_parameter;
+        if !is_static {
+            parameter_defs.push((self.defs.len(), class_descriptor(&self.class_name), None));
+            entry.entry(register).or_default().insert(self.defs.len());
+            self.defs.push(DefSite { index: None, register, wide: false });
+            register += 1;
+        }
+        for (position, param) in params.iter().enumerate() {
+            parameter_defs.push((self.defs.len(), param.clone(), Some(position)));
+            entry.entry(register).or_default().insert(self.defs.len());
+            self.defs.push(DefSite { index: None, register, wide: is_wide(param) });
+            register += if is_wide(param) { 2 } else { 1 };
+        }
+        let operands: Vec<Operands> = self.insns.iter().map(|insn| operands(insn, &return_type)).collect();
+        for (index, operand) in operands.iter().enumerate() {
+            if let Some((register, wide)) = operand.def {
+                self.def_at[index] = Some(self.defs.len());
+                self.defs.push(DefSite { index: Some(index), register, wide });
+            }
+        }
+
+        // 每个块的异常后继
+        let mut exceptional: Vec<Vec<usize>> = vec![Vec::new(); ranges.len()];
+        for handler in handlers {
+            let end = handler.end.min(ranges.len());
+            for targets in exceptional.iter_mut().take(end).skip(handler.start) {
+                if !targets.contains(&handler.handler) {
+                    targets.push(handler.handler);
+                }
+            }
+        }
+
+        // 迭代求每个块入口的到达定值
+        let mut block_in: Vec<RegisterState> = vec![RegisterState::new(); ranges.len()];
+        block_in[0] = entry;
+        let mut worklist: VecDeque<usize> = (0..ranges.len()).collect();
+        let mut queued = vec![true; ranges.len()];
+        while let Some(block) = worklist.pop_front() {
+            queued[block] = false;
+            let mut state = block_in[block].clone();
+            let mut thrown = state.clone();
+            for index in ranges[block].clone() {
+                if let Some(def) = self.def_at[index] {
+                    self.define_register(&mut state, def);
+                    let site = &self.defs[def];
+                    thrown.entry(site.register).or_default().insert(def);
+                }
+            }
+            let targets = successors[block].iter().map(|s| (*s, &state)).chain(exceptional[block].iter().map(|s| (*s, &thrown)));
+            for (successor, out) in targets {
+                if successor >= ranges.len() {
+                    continue;
+                }
+                let mut changed = false;
+                for (register, defs) in out {
+                    let target = block_in[successor].entry(*register).or_default();
+                    for def in defs {
+                        changed |= target.insert(*def);
+                    }
+                }
+                if changed && !queued[successor] {
+                    queued[successor] = true;
+                    worklist.push_back(successor);
+                }
+            }
+        }
+
+        // 读取点把所有到达的定值并入同一个网
+        let mut parent: Vec<usize> = (0..self.defs.len()).collect();
+        let mut use_defs: Vec<((usize, u32), usize)> = Vec::new();
+        for (block, range) in ranges.iter().enumerate() {
+            let mut state = block_in[block].clone();
+            for index in range.clone() {
+                for (register, _) in &operands[index].uses {
+                    let reaching: Vec<usize> = state.get(register).map(|defs| defs.iter().copied().collect()).unwrap_or_default();
+                    let first = match reaching.first() {
+                        Some(first) => *first,
+                        None => {
+                            // 从未赋值就读取的寄存器
+                            parent.push(self.defs.len());
+                            self.defs.push(DefSite { index: None, register: *register, wide: false });
+                            self.defs.len() - 1
+                        }
+                    };
+                    for def in reaching.iter().skip(1) {
+                        union(&mut parent, first, *def);
+                    }
+                    use_defs.push(((index, *register), first));
+                }
+                if let Some(def) = self.def_at[index] {
+                    self.define_register(&mut state, def);
+                }
+            }
+        }
+
+        // 落在同一条调试信息局部变量范围内的定值是同一个变量
+        let mut local_defs: HashMap<usize, usize> = HashMap::new();
+        for def in 0..self.defs.len() {
+            let Some(index) = self.defs[def].index else {
+                continue;
+            };
+            // new-instance 的变量从构造器调用之后才开始
+            let register = self.defs[def].register;
+            let defined_at = match self.insns[index].opcode {
+                DalvikOpcode::NewInstance => (index..self.insns.len())
+                    .find(|i| self.is_constructor_call(*i, register))
+                    .unwrap_or(index),
+                _ => index,
+            };
+            let address = self.insns[defined_at].address + self.insns[defined_at].size as u32;
+            if let Some(local) = self.lookup_local(self.defs[def].register, address) {
+                match local_defs.get(&local) {
+                    Some(first) => union(&mut parent, *first, def),
+                    None => {
+                        local_defs.insert(local, def);
+                    }
+                }
+            }
+        }
+
+        // 给网编号，参数在前
+        let mut web_of_root: HashMap<usize, usize> = HashMap::new();
+        self.web_of_def = vec![0; self.defs.len()];
+        for def in 0..self.defs.len() {
+            let root = find(&mut parent, def);
+            let web = *web_of_root.entry(root).or_insert_with(|| {
+                self.webs.push(Web {
+                    register: self.defs[def].register,
+                    defs: Vec::new(),
+                    uses: Vec::new(),
+                    descriptor: String::new(),
+                    name: None,
+                    parameter: false,
+                });
+                self.webs.len() - 1
+            });
+            self.web_of_def[def] = web;
+            self.webs[web].defs.push(def);
+        }
+        for ((index, register), def) in use_defs {
+            let web = self.web_of_def[def];
+            self.use_webs.insert((index, register), web);
+            self.webs[web].uses.push(index);
+        }
+
+        // 参数名来自调试信息，没有时用 argN
+        let parameter_names = self.code.debug_info.as_ref().map(|info| info.parameter_names.clone()).unwrap_or_default();
+        for (def, descriptor, position) in &parameter_defs {
+            let web = self.web_of_def[*def];
+            self.webs[web].parameter = true;
+            self.webs[web].descriptor = descriptor.clone();
+            match position {
+                None => {
+                    if self.webs[web].defs.len() == 1 {
+                        self.this_web = Some(web);
+                    }
+                }
+                Some(position) => {
+                    let name = parameter_names
+                        .get(*position)
+                        .cloned()
+                        .flatten()
+                        .or_else(|| self.lookup_local(self.defs[*def].register, 0).and_then(|l| self.debug_locals[l].name.clone()))
+                        .unwrap_or_else(|| format!("arg{}", position));
+                    self.webs[web].name = Some(name);
+                }
+            }
+        }
+        for (local, def) in &local_defs {
+            let web = self.web_of_def[*def];
+            if self.webs[web].name.is_none() {
+                self.webs[web].name = self.debug_locals[*local].name.clone();
+                self.webs[web].descriptor = self.debug_locals[*local].local_type.as_ref().map(|t| t.descriptor.clone()).unwrap_or_default();
+            }
+        }
+
+        self.infer_web_types(&operands, handler_blocks);
+        self.find_inline_webs();
+        Ok(())
+    }
+
+    /// 写寄存器：覆盖该寄存器，宽值还覆盖下一个寄存器，前一个寄存器中的宽值同样失效
+    fn define_register(&self, state: &mut RegisterState, def: usize) {
+        let site = &self.defs[def];
+        if site.wide {
+            state.remove(&(site.register + 1));
+        }
+        if site.register > 0 {
+            let previous_wide = state
+                .get(&(site.register - 1))
+                .is_some_and(|defs| defs.iter().any(|d| self.defs[*d].wide));
+            if previous_wide {
+                state.remove(&(site.register - 1));
+            }
+        }
+        state.insert(site.register, BTreeSet::from([def]));
+    }
+
+    fn lookup_local(&self, register: u32, address: u32) -> Option<usize> {
+        self.debug_locals.iter().position(|local| {
+            local.register == register && local.start_address <= address && address < local.end_address.max(local.start_address + 1)
+        })
+    }
+
+    /// 迭代推断每个网的类型：非常量定值的类型合并；只有常量定值时按读取处期望的类型
+    fn infer_web_types(&mut self, operands: &[Operands], handler_blocks: &HashMap<usize, Option<String>>) {
+        let mut types: Vec<Option<String>> = self
+            .webs
+            .iter()
+            .map(|web| (!web.descriptor.is_empty()).then(|| web.descriptor.clone()))
+            .collect();
+        let fixed: Vec<bool> = types.iter().map(Option::is_some).collect();
+        let catch_types: HashMap<u32, String> = handler_blocks
+            .iter()
+            .filter_map(|(block, catch_type)| {
+                let index = self.block_of_insn.iter().position(|b| b == block)?;
+                let name = catch_type.as_deref().unwrap_or("java/lang/Throwable");
+                Some((self.insns[index].address, class_descriptor(name)))
+            })
+            .collect();
+
+        for _ in 0..16 {
+            let mut changed = false;
+            for web in 0..self.webs.len() {
+                if fixed[web] {
+                    continue;
+                }
+                let mut descriptor: Option<String> = None;
+                let mut only_constants = true;
+                for def in &self.webs[web].defs {
+                    match self.def_type(*def, &types, &catch_types) {
+                        DefType::Known(known) => {
+                            only_constants = false;
+                            descriptor = Some(match descriptor {
+                                Some(existing) => merge_types(&existing, &known),
+                                None => known,
+                            });
+                        }
+                        DefType::Const => {}
+                        DefType::Unknown => only_constants = false,
+                    }
+                }
+                if descriptor.is_none() && only_constants {
+                    descriptor = self.webs[web].uses.iter().find_map(|index| self.use_hint(*index, web, operands, &types));
+                }
+                if descriptor.is_some() && descriptor != types[web] {
+                    types[web] = descriptor;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        for (web, descriptor) in types.into_iter().enumerate() {
+            let wide = self.webs[web].defs.iter().any(|def| self.defs[*def].wide);
+            self.webs[web].descriptor = descriptor.unwrap_or_else(|| {
+                let constant = self.webs[web].defs.iter().all(|def| matches!(self.def_type(*def, &[], &HashMap::new()), DefType::Const));
+                match (constant, wide) {
+                    (_, true) => "J".to_string(),
+                    (true, false) => "I".to_string(),
+                    (false, false) => OBJECT.to_string(),
+                }
+            });
+        }
+    }
+
+    fn web_type(&self, index: usize, register: u32, types: &[Option<String>]) -> Option<String> {
+        let web = *self.use_webs.get(&(index, register))?;
+        types.get(web).cloned().flatten()
+    }
+
+    /// 定值指令产生的值的类型
+    fn def_type(&self, def: usize, types: &[Option<String>], catch_types: &HashMap<u32, String>) -> DefType {
+        use DalvikOpcode::*;
+        let Some(index) = self.defs[def].index else {
+            return DefType::Unknown;
+        };
+        let insn = &self.insns[index];
+        let known = |descriptor: &str| DefType::Known(descriptor.to_string());
+        let from = |descriptor: Option<String>| descriptor.map(DefType::Known).unwrap_or(DefType::Unknown);
+        let register = |i: usize| insn.registers.get(i).copied().unwrap_or(0);
+        let element = |types: &[Option<String>]| {
+            self.web_type(index, register(1), types).and_then(|t| t.strip_prefix('[').map(str::to_string))
+        };
+        match insn.opcode {
+            Move | MoveFrom16 | Move16 | MoveWide | MoveWideFrom16 | MoveWide16 | MoveObject | MoveObjectFrom16 | MoveObject16 => {
+                from(self.web_type(index, register(1), types))
+            }
+            MoveResult | MoveResultWide | MoveResultObject => {
+                let Some(previous) = index.checked_sub(1).map(|i| &self.insns[i]) else {
+                    return DefType::Unknown;
+                };
+                match (&previous.reference, previous.opcode) {
+                    (Some(DalvikReference::Type(t)), FilledNewArray | FilledNewArrayRange) => known(&t.descriptor),
+                    (Some(DalvikReference::Method(method)), InvokePolymorphic | InvokePolymorphicRange) => {
+                        from(previous.proto.as_ref().map(|p| p.return_type.descriptor.clone()).or(Some(method.proto.return_type.descriptor.clone())))
+                    }
+                    (Some(DalvikReference::Method(method)), _) => known(&method.proto.return_type.descriptor),
+                    _ => DefType::Unknown,
+                }
+            }
+            MoveException => from(catch_types.get(&insn.address).cloned().or(Some(THROWABLE.to_string()))),
+            Const4 | Const16 | Const | ConstHigh16 | ConstWide16 | ConstWide32 | ConstWide | ConstWideHigh16 => DefType::Const,
+            ConstString | ConstStringJumbo => known("Ljava/lang/String;"),
+            ConstClass => known("Ljava/lang/Class;"),
+            ConstMethodHandle => known("Ljava/lang/invoke/MethodHandle;"),
+            ConstMethodType => known("Ljava/lang/invoke/MethodType;"),
+            CheckCast | NewInstance | NewArray => match &insn.reference {
+                Some(DalvikReference::Type(t)) => known(&t.descriptor),
+                _ => DefType::Unknown,
+            },
+            InstanceOf => known("Z"),
+            ArrayLength | CmplFloat | CmpgFloat | CmplDouble | CmpgDouble | CmpLong => known("I"),
+            Aget => from(element(types).or(Some("I".to_string()))),
+            AgetWide => from(element(types).or(Some("J".to_string()))),
+            AgetObject => from(element(types).or(Some(OBJECT.to_string()))),
+            AgetBoolean => known("Z"),
+            AgetByte => known("B"),
+            AgetChar => known("C"),
+            AgetShort => known("S"),
+            _ if insn.opcode.is_field_access() => match &insn.reference {
+                Some(DalvikReference::Field(field)) => known(&field.field_type.descriptor),
+                _ => DefType::Unknown,
+            },
+            _ => match unary(insn.opcode) {
+                Some((_, result)) => known(result),
+                None => match arithmetic(insn.opcode) {
+                    Some((_, result, _)) => known(result),
+                    None => DefType::Unknown,
+                },
+            },
+        }
+    }
+
+    /// 常量网的某次读取期望的类型
+    fn use_hint(&self, index: usize, web: usize, operands: &[Operands], types: &[Option<String>]) -> Option<String> {
+        use DalvikOpcode::*;
+        let insn = &self.insns[index];
+        let register = |i: usize| insn.registers.get(i).copied().unwrap_or(0);
+        let other_web = |i: usize| self.web_type(index, register(i), types);
+        let position = insn.registers.iter().position(|r| self.use_webs.get(&(index, *r)) == Some(&web))?;
+        match insn.opcode {
+            IfEq | IfNe | IfLt | IfGe | IfGt | IfLe => other_web(1 - position.min(1)),
+            Aput | AputWide | AputObject if position == 0 => {
+                other_web(1).and_then(|t| t.strip_prefix('[').map(str::to_string))
+            }
+            Move | MoveFrom16 | Move16 | MoveWide | MoveWideFrom16 | MoveWide16 | MoveObject | MoveObjectFrom16 | MoveObject16 => {
+                let def = self.def_at[index]?;
+                types[self.web_of_def[def]].clone()
+            }
+            _ => operands[index]
+                .uses
+                .iter()
+                .find(|(r, _)| *r == insn.registers[position])
+                .and_then(|(_, hint)| hint.clone()),
+        }
+    }
+
+    /// 只有一个定值、定值后在同一个块内只读取一次的网可以内联
+    fn find_inline_webs(&mut self) {
+        for web in 0..self.webs.len() {
+            let data = &self.webs[web];
+            if data.parameter || data.name.is_some() || data.defs.len() != 1 {
+                continue;
+            }
+            let Some(def_index) = self.defs[data.defs[0]].index else {
+                continue;
+            };
+            let mut uses = data.uses.clone();
+            let mut defined_at = def_index;
+            match self.insns[def_index].opcode {
+                DalvikOpcode::MoveException => continue,
+                DalvikOpcode::NewInstance => {
+                    // 值在构造器调用处才真正产生
+                    let register = data.register;
+                    let Some(position) = uses.iter().position(|index| self.is_constructor_call(*index, register)) else {
+                        continue;
+                    };
+                    defined_at = uses.remove(position);
+                }
+                DalvikOpcode::NewArray => {
+                    // 紧跟的 fill-array-data 会合并进数组初始化
+                    let next = def_index + 1;
+                    if self.insns.get(next).is_some_and(|insn| insn.opcode == DalvikOpcode::FillArrayData) {
+                        if let Some(position) = uses.iter().position(|index| *index == next) {
+                            defined_at = uses.remove(position);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if let [use_index] = uses.as_slice() {
+                if *use_index > defined_at && self.block_of_insn[*use_index] == self.block_of_insn[defined_at] {
+                    self.inline.insert(web);
+                }
+            }
+        }
+    }
+
+    fn is_constructor_call(&self, index: usize, register: u32) -> bool {
+        let insn = &self.insns[index];
+        matches!(insn.opcode, DalvikOpcode::InvokeDirect | DalvikOpcode::InvokeDirectRange)
+            && insn.registers.first() == Some(&register)
+            && matches!(&insn.reference, Some(DalvikReference::Method(method)) if method.name == "<init>")
+    }
+
+    fn unique_name(&mut self, base: &str) -> String {
+        let base = if is_java_keyword(base) { format!("{}_", base) } else { base.to_string() };
+        let mut name = base.clone();
+        let mut counter = 2;
+        while self.used_names.contains(&name) {
+            name = format!("{}_{}", base, counter);
+            counter += 1;
+        }
+        self.used_names.insert(name.clone());
+        name
+    }
+
+    /// 网对应的变量名，第一次用到时分配
+    fn web_name(&mut self, web: usize) -> String {
+        if let Some(name) = self.names.get(&web) {
+            return name.clone();
+        }
+        let base = self.webs[web].name.clone().unwrap_or_else(|| format!("v{}", self.webs[web].register));
+        let name = self.unique_name(&base);
+        self.var_types.insert(name.clone(), self.webs[web].descriptor.clone());
+        self.var_order.push(name.clone());
+        self.names.insert(web, name.clone());
+        name
+    }
+
+    /// 读取指令的一个寄存器操作数
+    fn read(&mut self, index: usize, register: u32) -> Expr {
+        let Some(web) = self.use_webs.get(&(index, register)).copied() else {
+            return Expr::Local(format!("v{}", register));
+        };
+        if let Some(position) = self.pending.iter().position(|(w, _)| *w == web) {
+            return self.pending.remove(position).1;
+        }
+        if Some(web) == self.this_web {
+            return Expr::This;
+        }
+        if let Some(class_name) = self.uninitialized.get(&web) {
+            return Expr::Uninitialized(class_name.clone());
+        }
+        Expr::Local(self.web_name(web))
+    }
+
+    fn def_descriptor(&self, index: usize) -> String {
+        self.def_at[index]
+            .map(|def| self.webs[self.web_of_def[def]].descriptor.clone())
+            .unwrap_or_default()
+    }
+
+    /// 指令写寄存器：可内联的值暂存，其余赋给变量
+    fn define(&mut self, index: usize, value: Expr, statements: &mut Vec<Stmt>) {
+        match self.def_at[index] {
+            Some(def) => self.define_web(self.web_of_def[def], value, statements),
+            None => self.emit(Stmt::Expr(value), statements),
+        }
+    }
+
+    fn define_web(&mut self, web: usize, value: Expr, statements: &mut Vec<Stmt>) {
+        let value = coerce(value, &self.webs[web].descriptor.clone());
+        if self.inline.contains(&web) {
+            if is_impure(&value) {
+                // 同一时刻最多暂存一个有副作用或读取字段的值，保证求值顺序不变
+                self.flush(statements, is_impure);
+            }
+            self.pending.push((web, value));
+            return;
+        }
+        if self.webs[web].uses.is_empty() && self.webs[web].name.is_none() {
+            if value.has_side_effects() {
+                self.emit(Stmt::Expr(value), statements);
+            }
+            return;
+        }
+        let name = self.web_name(web);
+        self.stored.insert(name.clone());
+        let data = &self.webs[web];
+        if data.uses.len() == 1 && data.name.is_none() && !data.parameter {
+            // 跨块只用一次的值，结构化之后可能与读取处相邻，或者合并成条件表达式
+            self.temporaries.insert(name.clone());
+        }
+        self.emit(
+            Stmt::Assign {
+                target: Expr::Local(name),
+                value,
+            },
+            statements,
+        );
+    }
+
+    /// 输出语句之前，先把暂存的有副作用的值、以及读取了被赋值变量的值写入变量
+    fn emit(&mut self, statement: Stmt, statements: &mut Vec<Stmt>) {
+        let assigned = match &statement {
+            Stmt::Assign { target: Expr::Local(name), .. } => Some(name.clone()),
+            _ => None,
+        };
+        self.flush(statements, |value| is_impure(value) || assigned.as_ref().is_some_and(|name| value.reads_local(name)));
+        statements.push(statement);
+    }
+
+    fn flush(&mut self, statements: &mut Vec<Stmt>, predicate: impl Fn(&Expr) -> bool) {
+        let mut position = 0;
+        while position < self.pending.len() {
+            if !predicate(&self.pending[position].1) {
+                position += 1;
+                continue;
+            }
+            let (web, value) = self.pending.remove(position);
+            let name = self.web_name(web);
+            self.stored.insert(name.clone());
+            self.temporaries.insert(name.clone());
+            statements.push(Stmt::Assign {
+                target: Expr::Local(name),
+                value,
+            });
+        }
+    }
+
+    /// 推断表达式的类型（字段描述符）
+    fn type_of(&self, expr: &Expr) -> Option<String> {
+        Some(match expr {
+            Expr::Literal(literal) => match literal {
+                Literal::Null => return None,
+                Literal::Bool(_) => "Z".to_string(),
+                Literal::Int(_) => "I".to_string(),
+                Literal::Long(_) => "J".to_string(),
+                Literal::Float(_) => "F".to_string(),
+                Literal::Double(_) => "D".to_string(),
+                Literal::Char(_) => "C".to_string(),
+                Literal::String(_) => "Ljava/lang/String;".to_string(),
+                Literal::Class(_) => "Ljava/lang/Class;".to_string(),
+            },
+            Expr::Local(name) => return self.var_types.get(name).cloned(),
+            Expr::This => class_descriptor(&self.class_name),
+            Expr::Super => return None,
+            Expr::Field { descriptor, .. } => descriptor.clone(),
+            Expr::ArrayElement { array, .. } => self.type_of(array)?.strip_prefix('[')?.to_string(),
+            Expr::ArrayLength(_) | Expr::Binary { op: BinaryOp::CompareLong | BinaryOp::CompareFloat, .. } => "I".to_string(),
+            Expr::Binary {
+                op: BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Gt | BinaryOp::Le | BinaryOp::LogicalAnd | BinaryOp::LogicalOr,
+                ..
+            } => "Z".to_string(),
+            Expr::Binary { left, .. } => self.type_of(left)?,
+            Expr::Unary { op: UnaryOp::Not, .. } | Expr::InstanceOf { .. } => "Z".to_string(),
+            Expr::Unary { operand, .. } => self.type_of(operand)?,
+            Expr::Cast { descriptor, .. } => descriptor.clone(),
+            Expr::Invoke { descriptor, .. } => parse_method_descriptor(descriptor).1,
+            Expr::New { class_name, .. } | Expr::Uninitialized(class_name) => class_descriptor(class_name),
+            Expr::NewArray {
+                descriptor,
+                dimensions,
+                extra_dimensions,
+            } => format!("{}{}", "[".repeat(dimensions.len() + extra_dimensions), descriptor),
+            Expr::ArrayInit { descriptor, .. } => format!("[{}", descriptor),
+            Expr::Ternary { then, otherwise, .. } => return self.type_of(then).or_else(|| self.type_of(otherwise)),
+            Expr::MethodRef { .. } => return None,
+        })
+    }
+
+    /// 翻译一个块内的指令，返回块的结束方式
+    fn lift_block(
+        &mut self,
+        range: Range<usize>,
+        statements: &mut Vec<Stmt>,
+        block_index: &dyn Fn(u32) -> Result<usize>,
+    ) -> Result<Terminator> {
+        use DalvikOpcode::*;
+        let (_, return_type) = parse_method_descriptor(&self.descriptor);
+        for index in range {
+            let insn = self.insns[index].clone();
+            let register = |i: usize| insn.registers.get(i).copied().unwrap_or(0);
+            let next_address = insn.address + insn.size as u32;
+            let unsupported = || AndroidAnalyzeError::ParseError(format!("Unsupported instruction {} at 0x{:04x}", insn.opcode.name(), insn.address));
+            match insn.opcode {
+                Nop => {}
+                Move | MoveFrom16 | Move16 | MoveWide | MoveWideFrom16 | MoveWide16 | MoveObject | MoveObjectFrom16 | MoveObject16 => {
+                    let value = self.read(index, register(1));
+                    self.define(index, value, statements);
+                }
+                MoveResult | MoveResultWide | MoveResultObject => {
+                    let value = self.result.take().ok_or_else(|| {
+                        AndroidAnalyzeError::ParseError(format!("move-result without a call at 0x{:04x}", insn.address))
+                    })?;
+                    self.define(index, value, statements);
+                }
+                MoveException => return Err(unsupported()),
+                ReturnVoid => {
+                    self.emit(Stmt::Return(None), statements);
+                    return Ok(Terminator::Exit);
+                }
+                Return | ReturnWide | ReturnObject => {
+                    let value = coerce(self.read(index, register(0)), &return_type);
+                    self.emit(Stmt::Return(Some(value)), statements);
+                    return Ok(Terminator::Exit);
+                }
+                Const4 | Const16 | Const | ConstHigh16 | ConstWide16 | ConstWide32 | ConstWide | ConstWideHigh16 => {
+                    let value = literal(insn.literal.unwrap_or_default(), &self.def_descriptor(index));
+                    self.define(index, value, statements);
+                }
+                ConstString | ConstStringJumbo | ConstClass => {
+                    let value = match &insn.reference {
+                        Some(DalvikReference::String(value)) => Literal::String(value.clone()),
+                        Some(DalvikReference::Type(t)) => Literal::Class(t.descriptor.clone()),
+                        _ => return Err(unsupported()),
+                    };
+                    self.define(index, Expr::Literal(value), statements);
+                }
+                MonitorEnter | MonitorExit => {
+                    let lock = self.read(index, register(0));
+                    let keyword = if insn.opcode == MonitorEnter { "monitorenter" } else { "monitorexit" };
+                    self.emit(Stmt::Comment(format!("{}({})", keyword, expr_to_string(&lock))), statements);
+                }
+                CheckCast => {
+                    let operand = self.read(index, register(0));
+                    let descriptor = type_reference(&insn)?;
+                    let value = if self.type_of(&operand).as_deref() == Some(descriptor.as_str()) {
+                        operand
+                    } else {
+                        Expr::Cast {
+                            descriptor,
+                            operand: Box::new(operand),
+                        }
+                    };
+                    self.define(index, value, statements);
+                }
+                InstanceOf => {
+                    let operand = self.read(index, register(1));
+                    let value = Expr::InstanceOf {
+                        operand: Box::new(operand),
+                        descriptor: type_reference(&insn)?,
+                    };
+                    self.define(index, value, statements);
+                }
+                ArrayLength => {
+                    let array = self.read(index, register(1));
+                    self.define(index, Expr::ArrayLength(Box::new(array)), statements);
+                }
+                NewInstance => {
+                    let class_name = descriptor_to_internal_name(&type_reference(&insn)?);
+                    if let Some(def) = self.def_at[index] {
+                        self.uninitialized.insert(self.web_of_def[def], class_name);
+                    }
+                }
+                NewArray => {
+                    let count = self.read(index, register(1));
+                    let array_type = type_reference(&insn)?;
+                    let total = array_type.chars().take_while(|c| *c == '[').count();
+                    let value = Expr::NewArray {
+                        descriptor: array_type[total..].to_string(),
+                        dimensions: vec![count],
+                        extra_dimensions: total.saturating_sub(1),
+                    };
+                    self.define(index, value, statements);
+                }
+                FilledNewArray | FilledNewArrayRange => {
+                    let array_type = type_reference(&insn)?;
+                    let element = array_type.strip_prefix('[').unwrap_or(&array_type).to_string();
+                    let values = insn
+                        .registers
+                        .iter()
+                        .map(|r| {
+                            let value = self.read(index, *r);
+                            coerce(value, &element)
+                        })
+                        .collect();
+                    self.take_result(index, Expr::ArrayInit { descriptor: element, values }, statements);
+                }
+                FillArrayData => self.fill_array_data(index, &insn, statements)?,
+                Throw => {
+                    let value = self.read(index, register(0));
+                    self.emit(Stmt::Throw(value), statements);
+                    return Ok(Terminator::Exit);
+                }
+                Goto | Goto16 | Goto32 => {
+                    self.flush(statements, |_| true);
+                    return Ok(Terminator::Goto(block_index(insn.target.unwrap_or_default())?));
+                }
+                PackedSwitch | SparseSwitch => {
+                    let selector = self.read(index, register(0));
+                    let mut cases = Vec::new();
+                    for (key, target) in insn.switch_cases(&self.instructions) {
+                        cases.push((key, block_index(target)?));
+                    }
+                    self.flush(statements, |_| true);
+                    return Ok(Terminator::Switch {
+                        selector,
+                        cases,
+                        default: block_index(next_address)?,
+                    });
+                }
+                CmplFloat | CmpgFloat | CmplDouble | CmpgDouble | CmpLong => {
+                    let left = self.read(index, register(1));
+                    let right = self.read(index, register(2));
+                    let op = if insn.opcode == CmpLong { BinaryOp::CompareLong } else { BinaryOp::CompareFloat };
+                    self.define(index, Expr::binary(op, left, right), statements);
+                }
+                IfEq | IfNe | IfLt | IfGe | IfGt | IfLe => {
+                    let left = self.read(index, register(0));
+                    let right = self.read(index, register(1));
+                    let left_type = self.type_of(&left).unwrap_or_default();
+                    let right_type = self.type_of(&right).unwrap_or_default();
+                    let right = coerce(right, &left_type);
+                    let left = coerce(left, &right_type);
+                    let op = relational_op(insn.opcode as u8 - IfEq as u8);
+                    self.flush(statements, |_| true);
+                    return Ok(Terminator::If {
+                        cond: Expr::binary(op, left, right),
+                        target: block_index(insn.target.unwrap_or_default())?,
+                    });
+                }
+                IfEqz | IfNez | IfLtz | IfGez | IfGtz | IfLez => {
+                    let value = self.read(index, register(0));
+                    let op = relational_op(insn.opcode as u8 - IfEqz as u8);
+                    let value_type = self.type_of(&value);
+                    let is_reference = value_type.as_deref().is_some_and(|t| t.starts_with('L') || t.starts_with('['))
+                        || value == Expr::Literal(Literal::Null);
+                    let cond = match value {
+                        Expr::Binary {
+                            op: BinaryOp::CompareLong | BinaryOp::CompareFloat,
+                            left,
+                            right,
+                        } => Expr::Binary { op, left, right },
+                        value if is_reference => Expr::binary(op, value, Expr::Literal(Literal::Null)),
+                        value if value_type.as_deref() == Some("Z") && matches!(op, BinaryOp::Eq | BinaryOp::Ne) => {
+                            if op == BinaryOp::Ne { value } else { value.negate() }
+                        }
+                        value => Expr::binary(op, value, Expr::int(0)),
+                    };
+                    self.flush(statements, |_| true);
+                    return Ok(Terminator::If {
+                        cond,
+                        target: block_index(insn.target.unwrap_or_default())?,
+                    });
+                }
+                Aget | AgetWide | AgetObject | AgetBoolean | AgetByte | AgetChar | AgetShort => {
+                    let array = self.read(index, register(1));
+                    let element = self.read(index, register(2));
+                    let value = match self.strings.get(&insn.address) {
+                        Some(value) => Expr::Literal(Literal::String(value.clone())),
+                        None => Expr::ArrayElement {
+                            array: Box::new(array),
+                            index: Box::new(element),
+                        },
+                    };
+                    self.define(index, value, statements);
+                }
+                Aput | AputWide | AputObject | AputBoolean | AputByte | AputChar | AputShort => {
+                    let value = self.read(index, register(0));
+                    let array = self.read(index, register(1));
+                    let element = self.read(index, register(2));
+                    let element_type = self.type_of(&array).and_then(|t| t.strip_prefix('[').map(str::to_string)).unwrap_or_default();
+                    let target = Expr::ArrayElement {
+                        array: Box::new(array),
+                        index: Box::new(element),
+                    };
+                    self.emit(
+                        Stmt::Assign {
+                            target,
+                            value: coerce(value, &element_type),
+                        },
+                        statements,
+                    );
+                }
+                _ if insn.opcode.is_field_access() => {
+                    let Some(DalvikReference::Field(field)) = &insn.reference else {
+                        return Err(unsupported());
+                    };
+                    let owner = descriptor_to_internal_name(&field.class_type.descriptor);
+                    let descriptor = field.field_type.descriptor.clone();
+                    let op = insn.opcode as u8;
+                    let is_static = op >= Sget as u8;
+                    let is_put = (Iput as u8..Sget as u8).contains(&op) || op >= Sput as u8;
+                    let value = is_put.then(|| self.read(index, register(0)));
+                    let target = (!is_static).then(|| Box::new(self.read(index, register(1))));
+                    let field = Expr::Field {
+                        target,
+                        owner,
+                        name: field.name.clone(),
+                        descriptor: descriptor.clone(),
+                    };
+                    match value {
+                        Some(value) => self.emit(
+                            Stmt::Assign {
+                                target: field,
+                                value: coerce(value, &descriptor),
+                            },
+                            statements,
+                        ),
+                        None => match self.strings.get(&insn.address) {
+                            Some(value) => self.define(index, Expr::Literal(Literal::String(value.clone())), statements),
+                            None => self.define(index, field, statements),
+                        },
+                    }
+                }
+                _ if insn.opcode.is_invoke() => {
+                    if matches!(insn.opcode, InvokeCustom | InvokeCustomRange) {
+                        return Err(unsupported());
+                    }
+                    self.invoke(index, &insn, statements)?;
+                }
+                ConstMethodHandle | ConstMethodType => return Err(unsupported()),
+                opcode => {
+                    if let Some((op, result)) = unary(opcode) {
+                        let operand = self.read(index, register(1));
+                        let value = match op {
+                            Some(BinaryOp::Sub) => Expr::Unary {
+                                op: UnaryOp::Neg,
+                                operand: Box::new(operand),
+                            },
+                            Some(op) => {
+                                let mask = if result == "J" { Expr::Literal(Literal::Long(-1)) } else { Expr::int(-1) };
+                                Expr::binary(op, operand, mask)
+                            }
+                            None => Expr::Cast {
+                                descriptor: result.to_string(),
+                                operand: Box::new(operand),
+                            },
+                        };
+                        self.define(index, value, statements);
+                    } else if let Some((op, _, _)) = arithmetic(opcode) {
+                        let value = if (AddInt2Addr as u8..=RemDouble2Addr as u8).contains(&(opcode as u8)) {
+                            let left = self.read(index, register(0));
+                            let right = self.read(index, register(1));
+                            Expr::binary(op, left, right)
+                        } else if let Some(literal) = insn.literal {
+                            let operand = self.read(index, register(1));
+                            // rsub-int：常量减寄存器
+                            if matches!(opcode, RsubInt | RsubIntLit8) {
+                                Expr::binary(op, Expr::int(literal as i32), operand)
+                            } else {
+                                Expr::binary(op, operand, Expr::int(literal as i32))
+                            }
+                        } else {
+                            let left = self.read(index, register(1));
+                            let right = self.read(index, register(2));
+                            Expr::binary(op, left, right)
+                        };
+                        self.define(index, value, statements);
+                    } else {
+                        return Err(unsupported());
+                    }
+                }
+            }
+        }
+        self.flush(statements, |_| true);
+        Ok(Terminator::FallThrough)
+    }
+
+    fn invoke(&mut self, index: usize, insn: &DalvikInstruction, statements: &mut Vec<Stmt>) -> Result<()> {
+        use DalvikOpcode::*;
+        let Some(DalvikReference::Method(method)) = &insn.reference else {
+            return Err(AndroidAnalyzeError::ParseError(format!("Invalid call at 0x{:04x}", insn.address)));
+        };
+        let owner = descriptor_to_internal_name(&method.class_type.descriptor);
+        // invoke-polymorphic 按调用点的原型传参
+        let descriptor = match (&insn.proto, insn.opcode) {
+            (Some(proto), InvokePolymorphic | InvokePolymorphicRange) => proto_to_descriptor(proto),
+            _ => proto_to_descriptor(&method.proto),
+        };
+        let (params, _) = parse_method_descriptor(&descriptor);
+        let kind = match insn.opcode {
+            InvokeStatic | InvokeStaticRange => InvokeKind::Static,
+            InvokeInterface | InvokeInterfaceRange => InvokeKind::Interface,
+            InvokeDirect | InvokeDirectRange | InvokeSuper | InvokeSuperRange => InvokeKind::Special,
+            _ => InvokeKind::Virtual,
+        };
+        let missing = || AndroidAnalyzeError::ParseError(format!("Missing call argument at 0x{:04x}", insn.address));
+        let mut registers = insn.registers.iter().copied();
+        let receiver = if kind == InvokeKind::Static { None } else { Some(registers.next().ok_or_else(missing)?) };
+        let target = receiver.map(|register| self.read(index, register));
+        let mut args = Vec::new();
+        for param in &params {
+            let register = registers.next().ok_or_else(missing)?;
+            if is_wide(param) {
+                registers.next();
+            }
+            let value = self.read(index, register);
+            args.push(coerce(value, param));
+        }
+
+        if method.name == "<init>" {
+            match target {
+                Some(Expr::Uninitialized(class_name)) => {
+                    let web = receiver.and_then(|register| self.use_webs.get(&(index, register)).copied());
+                    let created = Expr::New {
+                        class_name,
+                        descriptor,
+                        args,
+                    };
+                    match web {
+                        Some(web) => {
+                            self.uninitialized.remove(&web);
+                            self.define_web(web, created, statements);
+                        }
+                        None => self.emit(Stmt::Expr(created), statements),
+                    }
+                }
+                target => {
+                    // 构造器中调用 super(...) / this(...)
+                    let target = match target {
+                        Some(Expr::This) if owner != self.class_name => Expr::Super,
+                        target => target.unwrap_or(Expr::This),
+                    };
+                    self.emit(
+                        Stmt::Expr(Expr::Invoke {
+                            kind,
+                            target: Some(Box::new(target)),
+                            owner,
+                            name: method.name.clone(),
+                            descriptor,
+                            args,
+                        }),
+                        statements,
+                    );
+                }
+            }
+            return Ok(());
+        }
+
+        // 常量参数的解密调用，参数没有副作用，可以整体替换
+        if kind == InvokeKind::Static {
+            if let Some(value) = self.strings.get(&insn.address) {
+                self.take_result(index, Expr::Literal(Literal::String(value.clone())), statements);
+                return Ok(());
+            }
+        }
+        let target = match target {
+            Some(Expr::This) if matches!(insn.opcode, InvokeSuper | InvokeSuperRange) => Some(Expr::Super),
+            target => target,
+        };
+        let invoke = Expr::Invoke {
+            kind,
+            target: target.map(Box::new),
+            owner,
+            name: method.name.clone(),
+            descriptor,
+            args,
+        };
+        self.take_result(index, invoke, statements);
+        Ok(())
+    }
+
+    /// 下一条指令是 move-result 时把值交给它，否则调用本身成为语句
+    fn take_result(&mut self, index: usize, value: Expr, statements: &mut Vec<Stmt>) {
+        let followed = self.insns.get(index + 1).is_some_and(|next| is_move_result(next.opcode))
+            && self.block_of_insn.get(index + 1) == Some(&self.block_of_insn[index]);
+        if followed {
+            self.result = Some(value);
+        } else if value.has_side_effects() {
+            self.emit(Stmt::Expr(value), statements);
+        }
+    }
+
+    /// `fill-array-data`：紧跟在 `new T[n]` 之后时合并成数组初始化，否则逐个元素赋值
+    fn fill_array_data(&mut self, index: usize, insn: &DalvikInstruction, statements: &mut Vec<Stmt>) -> Result<()> {
+        let register = insn.registers.first().copied().unwrap_or(0);
+        let elements = insn
+            .target
+            .and_then(|target| payload_at(&self.instructions, target))
+            .map(|payload| payload.elements())
+            .ok_or_else(|| AndroidAnalyzeError::ParseError(format!("Missing array data at 0x{:04x}", insn.address)))?;
+        let length = elements.len() as i32;
+        let is_new_array = |value: &Expr| {
+            matches!(value, Expr::NewArray { dimensions, extra_dimensions: 0, .. }
+                if dimensions.as_slice() == [Expr::int(length)])
+        };
+        let element_of = |lifter: &Self, array: &Expr| {
+            lifter.type_of(array).and_then(|t| t.strip_prefix('[').map(str::to_string)).unwrap_or_else(|| "I".to_string())
+        };
+
+        // 暂存的 new T[n]：直接换成数组初始化
+        let web = self.use_webs.get(&(index, register)).copied();
+        if let Some(position) = self.pending.iter().position(|(w, value)| Some(*w) == web && is_new_array(value)) {
+            let element = element_of(self, &self.pending[position].1);
+            let values = elements.into_iter().map(|value| literal(value, &element)).collect();
+            self.pending[position].1 = Expr::ArrayInit {
+                descriptor: element,
+                values,
+            };
+            return Ok(());
+        }
+
+        let array = self.read(index, register);
+        let element = element_of(self, &array);
+        let values: Vec<Expr> = elements.into_iter().map(|value| literal(value, &element)).collect();
+        if let Expr::Local(name) = &array {
+            if let Some(Stmt::Assign { target: Expr::Local(target), value }) = statements.last_mut() {
+                if target == name && is_new_array(value) {
+                    *value = Expr::ArrayInit {
+                        descriptor: element,
+                        values,
+                    };
+                    return Ok(());
+                }
+            }
+        }
+        if is_new_array(&array) {
+            // 内联的新数组只在这里用到，初始化后的值没有被使用
+            return Ok(());
+        }
+        let array = match array {
+            array @ (Expr::Local(_) | Expr::This) => array,
+            array => {
+                // 没有变量可以引用的数组，先放进临时变量
+                let name = self.unique_name("array");
+                self.var_types.insert(name.clone(), format!("[{}", element));
+                self.var_order.push(name.clone());
+                self.stored.insert(name.clone());
+                self.emit(
+                    Stmt::Assign {
+                        target: Expr::Local(name.clone()),
+                        value: array,
+                    },
+                    statements,
+                );
+                Expr::Local(name)
+            }
+        };
+        for (position, value) in values.into_iter().enumerate() {
+            self.emit(
+                Stmt::Assign {
+                    target: Expr::ArrayElement {
+                        array: Box::new(array.clone()),
+                        index: Box::new(Expr::int(position as i32)),
+                    },
+                    value,
+                },
+                statements,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 一条指令读写的寄存器
+fn operands(insn: &DalvikInstruction, return_type: &str) -> Operands {
+    use DalvikOpcode::*;
+    let register = |i: usize| insn.registers.get(i).copied().unwrap_or(0);
+    let hint = |descriptor: &str| Some(descriptor.to_string());
+    let reference_type = match &insn.reference {
+        Some(DalvikReference::Type(t)) => Some(t.descriptor.clone()),
+        Some(DalvikReference::Field(field)) => Some(field.field_type.descriptor.clone()),
+        _ => None,
+    };
+    let mut uses = Vec::new();
+    let mut def = None;
+    match insn.opcode {
+        Move | MoveFrom16 | Move16 | MoveObject | MoveObjectFrom16 | MoveObject16 => {
+            uses.push((register(1), None));
+            def = Some((register(0), false));
+        }
+        MoveWide | MoveWideFrom16 | MoveWide16 => {
+            uses.push((register(1), None));
+            def = Some((register(0), true));
+        }
+        MoveResult | MoveResultObject | MoveException => def = Some((register(0), false)),
+        MoveResultWide => def = Some((register(0), true)),
+        Return | ReturnWide | ReturnObject => uses.push((register(0), hint(return_type))),
+        Const4 | Const16 | Const | ConstHigh16 | ConstString | ConstStringJumbo | ConstClass | NewInstance
+        | ConstMethodHandle | ConstMethodType => def = Some((register(0), false)),
+        ConstWide16 | ConstWide32 | ConstWide | ConstWideHigh16 => def = Some((register(0), true)),
+        MonitorEnter | MonitorExit | FillArrayData => uses.push((register(0), hint(OBJECT))),
+        Throw => uses.push((register(0), hint(THROWABLE))),
+        CheckCast => {
+            uses.push((register(0), hint(OBJECT)));
+            def = Some((register(0), false));
+        }
+        InstanceOf | ArrayLength => {
+            uses.push((register(1), hint(OBJECT)));
+            def = Some((register(0), false));
+        }
+        NewArray => {
+            uses.push((register(1), hint("I")));
+            def = Some((register(0), false));
+        }
+        FilledNewArray | FilledNewArrayRange => {
+            let element = reference_type.as_deref().and_then(|t| t.strip_prefix('[')).map(str::to_string);
+            uses.extend(insn.registers.iter().map(|r| (*r, element.clone())));
+        }
+        PackedSwitch | SparseSwitch => uses.push((register(0), hint("I"))),
+        CmplFloat | CmpgFloat | CmplDouble | CmpgDouble | CmpLong => {
+            let operand = match insn.opcode {
+                CmplFloat | CmpgFloat => "F",
+                CmpLong => "J",
+                _ => "D",
+            };
+            uses.push((register(1), hint(operand)));
+            uses.push((register(2), hint(operand)));
+            def = Some((register(0), false));
+        }
+        IfEq | IfNe | IfLt | IfGe | IfGt | IfLe => {
+            uses.push((register(0), None));
+            uses.push((register(1), None));
+        }
+        IfEqz | IfNez => uses.push((register(0), None)),
+        IfLtz | IfGez | IfGtz | IfLez => uses.push((register(0), hint("I"))),
+        Aget | AgetWide | AgetObject | AgetBoolean | AgetByte | AgetChar | AgetShort => {
+            uses.push((register(1), None));
+            uses.push((register(2), hint("I")));
+            def = Some((register(0), insn.opcode == AgetWide));
+        }
+        Aput | AputWide | AputObject | AputBoolean | AputByte | AputChar | AputShort => {
+            let element = match insn.opcode {
+                AputBoolean => hint("Z"),
+                AputByte => hint("B"),
+                AputChar => hint("C"),
+                AputShort => hint("S"),
+                _ => None,
+            };
+            uses.push((register(0), element));
+            uses.push((register(1), None));
+            uses.push((register(2), hint("I")));
+        }
+        opcode if opcode.is_field_access() => {
+            let op = opcode as u8;
+            let owner = match &insn.reference {
+                Some(DalvikReference::Field(field)) => Some(field.class_type.descriptor.clone()),
+                _ => None,
+            };
+            let is_static = op >= Sget as u8;
+            let is_put = (Iput as u8..Sget as u8).contains(&op) || op >= Sput as u8;
+            let wide = matches!(opcode, IgetWide | IputWide | SgetWide | SputWide);
+            if is_put {
+                uses.push((register(0), reference_type.clone()));
+            } else {
+                def = Some((register(0), wide));
+            }
+            if !is_static {
+                uses.push((register(1), owner));
+            }
+        }
+        opcode if opcode.is_invoke() => {
+            let Some(DalvikReference::Method(method)) = &insn.reference else {
+                uses.extend(insn.registers.iter().map(|r| (*r, None)));
+                return Operands { uses, def };
+            };
+            let parameters: Vec<String> = match (&insn.proto, opcode) {
+                (Some(proto), InvokePolymorphic | InvokePolymorphicRange) => {
+                    proto.parameters.iter().map(|p| p.descriptor.clone()).collect()
+                }
+                _ => method.proto.parameters.iter().map(|p| p.descriptor.clone()).collect(),
+            };
+            let mut registers = insn.registers.iter().copied();
+            if !matches!(opcode, InvokeStatic | InvokeStaticRange) {
+                uses.extend(registers.next().map(|r| (r, Some(method.class_type.descriptor.clone()))));
+            }
+            for parameter in parameters {
+                let wide = is_wide(&parameter);
+                uses.extend(registers.next().map(|r| (r, Some(parameter))));
+                if wide {
+                    registers.next();
+                }
+            }
+        }
+        opcode => {
+            if let Some((_, result)) = unary(opcode) {
+                let operand = UNARY_OPERANDS[(opcode as u8 - NegInt as u8) as usize];
+                uses.push((register(1), hint(operand)));
+                def = Some((register(0), is_wide(result)));
+            } else if let Some((_, result, right)) = arithmetic(opcode) {
+                let op = opcode as u8;
+                if (AddInt2Addr as u8..=RemDouble2Addr as u8).contains(&op) {
+                    uses.push((register(0), hint(result)));
+                    uses.push((register(1), hint(right)));
+                } else if insn.literal.is_some() {
+                    uses.push((register(1), hint("I")));
+                } else {
+                    uses.push((register(1), hint(result)));
+                    uses.push((register(2), hint(right)));
+                }
+                def = Some((register(0), is_wide(result)));
+            }
+        }
+    }
+    Operands { uses, def }
+}
+
+const ARITHMETIC_OPS: [BinaryOp; 11] = [
+    BinaryOp::Add,
+    BinaryOp::Sub,
+    BinaryOp::Mul,
+    BinaryOp::Div,
+    BinaryOp::Rem,
+    BinaryOp::And,
+    BinaryOp::Or,
+    BinaryOp::Xor,
+    BinaryOp::Shl,
+    BinaryOp::Shr,
+    BinaryOp::Ushr,
+];
+
+/// 算术指令的 (运算, 结果类型, 右操作数类型)；`rsub-int` 记为减法，操作数顺序由调用方处理
+fn arithmetic(opcode: DalvikOpcode) -> Option<(BinaryOp, &'static str, &'static str)> {
+    use DalvikOpcode::*;
+    let op = opcode as u8;
+    let group = |offset: u8| -> (BinaryOp, &'static str, &'static str) {
+        match offset {
+            0..=10 => (ARITHMETIC_OPS[offset as usize], "I", "I"),
+            // long 的移位次数是 int
+            11..=18 => (ARITHMETIC_OPS[offset as usize - 11], "J", "J"),
+            19..=21 => (ARITHMETIC_OPS[offset as usize - 11], "J", "I"),
+            22..=26 => (ARITHMETIC_OPS[offset as usize - 22], "F", "F"),
+            _ => (ARITHMETIC_OPS[offset as usize - 27], "D", "D"),
+        }
+    };
+    // lit16 / lit8 的第二个运算是 rsub
+    let literal_op = |offset: u8| if offset == 1 { BinaryOp::Sub } else { ARITHMETIC_OPS[offset as usize] };
+    Some(match op {
+        _ if (AddInt as u8..=RemDouble as u8).contains(&op) => group(op - AddInt as u8),
+        _ if (AddInt2Addr as u8..=RemDouble2Addr as u8).contains(&op) => group(op - AddInt2Addr as u8),
+        _ if (AddIntLit16 as u8..=XorIntLit16 as u8).contains(&op) => (literal_op(op - AddIntLit16 as u8), "I", "I"),
+        _ if (AddIntLit8 as u8..=UshrIntLit8 as u8).contains(&op) => (literal_op(op - AddIntLit8 as u8), "I", "I"),
+        _ => return None,
+    })
+}
+
+/// neg-int 到 int-to-short 的操作数类型
+const UNARY_OPERANDS: [&str; 21] = [
+    "I", "I", "J", "J", "F", "D", "I", "I", "I", "J", "J", "J", "F", "F", "F", "D", "D", "D", "I", "I", "I",
+];
+
+/// 一元指令：取负记为 Sub，按位取反记为 Xor，类型转换为 None；第二项是结果类型
+fn unary(opcode: DalvikOpcode) -> Option<(Option<BinaryOp>, &'static str)> {
+    use DalvikOpcode::*;
+    Some(match opcode {
+        NegInt => (Some(BinaryOp::Sub), "I"),
+        NotInt => (Some(BinaryOp::Xor), "I"),
+        NegLong => (Some(BinaryOp::Sub), "J"),
+        NotLong => (Some(BinaryOp::Xor), "J"),
+        NegFloat => (Some(BinaryOp::Sub), "F"),
+        NegDouble => (Some(BinaryOp::Sub), "D"),
+        IntToLong | FloatToLong | DoubleToLong => (None, "J"),
+        IntToFloat | LongToFloat | DoubleToFloat => (None, "F"),
+        IntToDouble | LongToDouble | FloatToDouble => (None, "D"),
+        LongToInt | FloatToInt | DoubleToInt => (None, "I"),
+        IntToByte => (None, "B"),
+        IntToChar => (None, "C"),
+        IntToShort => (None, "S"),
+        _ => return None,
+    })
+}
+
+fn relational_op(offset: u8) -> BinaryOp {
+    [BinaryOp::Eq, BinaryOp::Ne, BinaryOp::Lt, BinaryOp::Ge, BinaryOp::Gt, BinaryOp::Le][offset as usize]
+}
+
+fn is_branch(opcode: DalvikOpcode) -> bool {
+    use DalvikOpcode::*;
+    matches!(opcode, Goto | Goto16 | Goto32) || (IfEq as u8..=IfLez as u8).contains(&(opcode as u8))
+}
+
+fn is_move_result(opcode: DalvikOpcode) -> bool {
+    matches!(opcode, DalvikOpcode::MoveResult | DalvikOpcode::MoveResultWide | DalvikOpcode::MoveResultObject)
+}
+
+fn type_reference(insn: &DalvikInstruction) -> Result<String> {
+    match &insn.reference {
+        Some(DalvikReference::Type(t)) => Ok(t.descriptor.clone()),
+        _ => Err(AndroidAnalyzeError::ParseError(format!("Missing type at 0x{:04x}", insn.address))),
+    }
+}
+
+/// 按类型解释常量的位模式
+fn literal(bits: i64, descriptor: &str) -> Expr {
+    Expr::Literal(match descriptor {
+        "J" => Literal::Long(bits),
+        "F" => Literal::Float(f32::from_bits(bits as u32)),
+        "D" => Literal::Double(f64::from_bits(bits as u64)),
+        "Z" if bits == 0 || bits == 1 => Literal::Bool(bits == 1),
+        "C" if (0..=0xFFFF).contains(&bits) => Literal::Char(bits as u16),
+        _ if bits == 0 && (descriptor.starts_with('L') || descriptor.starts_with('[')) => Literal::Null,
+        _ => Literal::Int(bits as i32),
+    })
+}
+
+fn is_impure(expr: &Expr) -> bool {
+    let mut found = false;
+    expr.visit(&mut |e| found |= matches!(e, Expr::Invoke { .. } | Expr::New { .. } | Expr::Field { .. } | Expr::ArrayElement { .. }));
+    found
+}
+
+fn merge_types(a: &str, b: &str) -> String {
+    let is_int = |t: &str| matches!(t, "Z" | "B" | "S" | "C" | "I");
+    let is_reference = |t: &str| t.starts_with('L') || t.starts_with('[');
+    if a == b {
+        a.to_string()
+    } else if is_int(a) && is_int(b) {
+        "I".to_string()
+    } else if is_reference(a) && is_reference(b) {
+        OBJECT.to_string()
+    } else {
+        a.to_string()
+    }
+}
+
+fn find(parent: &mut [usize], node: usize) -> usize {
+    let mut root = node;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut node = node;
+    while parent[node] != root {
+        let next = parent[node];
+        parent[node] = root;
+        node = next;
+    }
+    root
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    if a != b {
+        // 保留编号小的作为根，参数的网排在前面
+        let (root, child) = if a < b { (a, b) } else { (b, a) };
+        parent[child] = root;
+    }
+}
+
+fn is_java_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "abstract" | "assert" | "boolean" | "break" | "byte" | "case" | "catch" | "char" | "class" | "const"
+            | "continue" | "default" | "do" | "double" | "else" | "enum" | "extends" | "final" | "finally"
+            | "float" | "for" | "goto" | "if" | "implements" | "import" | "instanceof" | "int" | "interface"
+            | "long" | "native" | "new" | "package" | "private" | "protected" | "public" | "return" | "short"
+            | "static" | "strictfp" | "super" | "switch" | "synchronized" | "this" | "throw" | "throws"
+            | "transient" | "try" | "void" | "volatile" | "while" | "true" | "false" | "null"
+    )
+}
@@ -1,419 +1,1135 @@
-use std::collections::HashMap;
+use std::fmt;
+use crate::android::{CodeItem, DexFile, FieldDescriptor, MethodDescriptor, ProtoDescriptor, TypeDescriptor};
 use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
+use crate::hierarchy::proto_to_descriptor;
 
-/// Dalvik opcode analyzer for parsing and analyzing bytecode
-pub struct DalvikOpcodeAnalyzer {
-    opcode_map: HashMap<u8, DalvikOpcode>,
-}
+/// Dalvik opcode analyzer, decodes the 16-bit code units of a `CodeItem`
+#[derive(Debug, Default)]
+pub struct DalvikOpcodeAnalyzer;
 
 impl DalvikOpcodeAnalyzer {
     pub fn new() -> Self {
-        let mut opcode_map = HashMap::new();
-        
-        // Initialize opcode map with Dalvik opcodes
-        opcode_map.insert(0x00, DalvikOpcode::Nop);
-        opcode_map.insert(0x01, DalvikOpcode::Move);
-        opcode_map.insert(0x02, DalvikOpcode::MoveFrom16);
-        opcode_map.insert(0x03, DalvikOpcode::Move16);
-        opcode_map.insert(0x04, DalvikOpcode::MoveWide);
-        opcode_map.insert(0x05, DalvikOpcode::MoveWideFrom16);
-        opcode_map.insert(0x06, DalvikOpcode::MoveWide16);
-        opcode_map.insert(0x07, DalvikOpcode::MoveObject);
-        opcode_map.insert(0x08, DalvikOpcode::MoveObjectFrom16);
-        opcode_map.insert(0x09, DalvikOpcode::MoveObject16);
-        opcode_map.insert(0x0a, DalvikOpcode::MoveResult);
-        opcode_map.insert(0x0b, DalvikOpcode::MoveResultWide);
-        opcode_map.insert(0x0c, DalvikOpcode::MoveResultObject);
-        opcode_map.insert(0x0d, DalvikOpcode::MoveException);
-        opcode_map.insert(0x0e, DalvikOpcode::ReturnVoid);
-        opcode_map.insert(0x0f, DalvikOpcode::Return);
-        opcode_map.insert(0x10, DalvikOpcode::ReturnWide);
-        opcode_map.insert(0x11, DalvikOpcode::ReturnObject);
-        opcode_map.insert(0x12, DalvikOpcode::Const4);
-        opcode_map.insert(0x13, DalvikOpcode::Const16);
-        opcode_map.insert(0x14, DalvikOpcode::Const);
-        opcode_map.insert(0x15, DalvikOpcode::ConstHigh16);
-        opcode_map.insert(0x16, DalvikOpcode::ConstWide16);
-        opcode_map.insert(0x17, DalvikOpcode::ConstWide32);
-        opcode_map.insert(0x18, DalvikOpcode::ConstWide);
-        opcode_map.insert(0x19, DalvikOpcode::ConstWideHigh16);
-        opcode_map.insert(0x1a, DalvikOpcode::ConstString);
-        opcode_map.insert(0x1b, DalvikOpcode::ConstStringJumbo);
-        opcode_map.insert(0x1c, DalvikOpcode::ConstClass);
-        opcode_map.insert(0x1d, DalvikOpcode::MonitorEnter);
-        opcode_map.insert(0x1e, DalvikOpcode::MonitorExit);
-        opcode_map.insert(0x1f, DalvikOpcode::CheckCast);
-        opcode_map.insert(0x20, DalvikOpcode::InstanceOf);
-        opcode_map.insert(0x21, DalvikOpcode::ArrayLength);
-        opcode_map.insert(0x22, DalvikOpcode::NewInstance);
-        opcode_map.insert(0x23, DalvikOpcode::NewArray);
-        opcode_map.insert(0x24, DalvikOpcode::FilledNewArray);
-        opcode_map.insert(0x25, DalvikOpcode::FilledNewArrayRange);
-        opcode_map.insert(0x26, DalvikOpcode::FillArrayData);
-        opcode_map.insert(0x27, DalvikOpcode::Throw);
-        opcode_map.insert(0x28, DalvikOpcode::Goto);
-        opcode_map.insert(0x29, DalvikOpcode::Goto16);
-        opcode_map.insert(0x2a, DalvikOpcode::Goto32);
-        opcode_map.insert(0x2b, DalvikOpcode::PackedSwitch);
-        opcode_map.insert(0x2c, DalvikOpcode::SparseSwitch);
-        
-        // Comparison opcodes
-        opcode_map.insert(0x2d, DalvikOpcode::CmplFloat);
-        opcode_map.insert(0x2e, DalvikOpcode::CmpgFloat);
-        opcode_map.insert(0x2f, DalvikOpcode::CmplDouble);
-        opcode_map.insert(0x30, DalvikOpcode::CmpgDouble);
-        opcode_map.insert(0x31, DalvikOpcode::CmpLong);
-        
-        // Conditional branches
-        opcode_map.insert(0x32, DalvikOpcode::IfEq);
-        opcode_map.insert(0x33, DalvikOpcode::IfNe);
-        opcode_map.insert(0x34, DalvikOpcode::IfLt);
-        opcode_map.insert(0x35, DalvikOpcode::IfGe);
-        opcode_map.insert(0x36, DalvikOpcode::IfGt);
-        opcode_map.insert(0x37, DalvikOpcode::IfLe);
-        opcode_map.insert(0x38, DalvikOpcode::IfEqz);
-        opcode_map.insert(0x39, DalvikOpcode::IfNez);
-        opcode_map.insert(0x3a, DalvikOpcode::IfLtz);
-        opcode_map.insert(0x3b, DalvikOpcode::IfGez);
-        opcode_map.insert(0x3c, DalvikOpcode::IfGtz);
-        opcode_map.insert(0x3d, DalvikOpcode::IfLez);
-        
-        // Array operations
-        opcode_map.insert(0x44, DalvikOpcode::Aget);
-        opcode_map.insert(0x45, DalvikOpcode::AgetWide);
-        opcode_map.insert(0x46, DalvikOpcode::AgetObject);
-        opcode_map.insert(0x47, DalvikOpcode::AgetBoolean);
-        opcode_map.insert(0x48, DalvikOpcode::AgetByte);
-        opcode_map.insert(0x49, DalvikOpcode::AgetChar);
-        opcode_map.insert(0x4a, DalvikOpcode::AgetShort);
-        opcode_map.insert(0x4b, DalvikOpcode::Aput);
-        opcode_map.insert(0x4c, DalvikOpcode::AputWide);
-        opcode_map.insert(0x4d, DalvikOpcode::AputObject);
-        opcode_map.insert(0x4e, DalvikOpcode::AputBoolean);
-        opcode_map.insert(0x4f, DalvikOpcode::AputByte);
-        opcode_map.insert(0x50, DalvikOpcode::AputChar);
-        opcode_map.insert(0x51, DalvikOpcode::AputShort);
-        
-        // Instance field operations
-        opcode_map.insert(0x52, DalvikOpcode::Iget);
-        opcode_map.insert(0x53, DalvikOpcode::IgetWide);
-        opcode_map.insert(0x54, DalvikOpcode::IgetObject);
-        opcode_map.insert(0x55, DalvikOpcode::IgetBoolean);
-        opcode_map.insert(0x56, DalvikOpcode::IgetByte);
-        opcode_map.insert(0x57, DalvikOpcode::IgetChar);
-        opcode_map.insert(0x58, DalvikOpcode::IgetShort);
-        opcode_map.insert(0x59, DalvikOpcode::Iput);
-        opcode_map.insert(0x5a, DalvikOpcode::IputWide);
-        opcode_map.insert(0x5b, DalvikOpcode::IputObject);
-        opcode_map.insert(0x5c, DalvikOpcode::IputBoolean);
-        opcode_map.insert(0x5d, DalvikOpcode::IputByte);
-        opcode_map.insert(0x5e, DalvikOpcode::IputChar);
-        opcode_map.insert(0x5f, DalvikOpcode::IputShort);
-        
-        // Static field operations
-        opcode_map.insert(0x60, DalvikOpcode::Sget);
-        opcode_map.insert(0x61, DalvikOpcode::SgetWide);
-        opcode_map.insert(0x62, DalvikOpcode::SgetObject);
-        opcode_map.insert(0x63, DalvikOpcode::SgetBoolean);
-        opcode_map.insert(0x64, DalvikOpcode::SgetByte);
-        opcode_map.insert(0x65, DalvikOpcode::SgetChar);
-        opcode_map.insert(0x66, DalvikOpcode::SgetShort);
-        opcode_map.insert(0x67, DalvikOpcode::Sput);
-        opcode_map.insert(0x68, DalvikOpcode::SputWide);
-        opcode_map.insert(0x69, DalvikOpcode::SputObject);
-        opcode_map.insert(0x6a, DalvikOpcode::SputBoolean);
-        opcode_map.insert(0x6b, DalvikOpcode::SputByte);
-        opcode_map.insert(0x6c, DalvikOpcode::SputChar);
-        opcode_map.insert(0x6d, DalvikOpcode::SputShort);
-        
-        // Method invocation
-        opcode_map.insert(0x6e, DalvikOpcode::InvokeVirtual);
-        opcode_map.insert(0x6f, DalvikOpcode::InvokeSuper);
-        opcode_map.insert(0x70, DalvikOpcode::InvokeDirect);
-        opcode_map.insert(0x71, DalvikOpcode::InvokeStatic);
-        opcode_map.insert(0x72, DalvikOpcode::InvokeInterface);
-        opcode_map.insert(0x74, DalvikOpcode::InvokeVirtualRange);
-        opcode_map.insert(0x75, DalvikOpcode::InvokeSuperRange);
-        opcode_map.insert(0x76, DalvikOpcode::InvokeDirectRange);
-        opcode_map.insert(0x77, DalvikOpcode::InvokeStaticRange);
-        opcode_map.insert(0x78, DalvikOpcode::InvokeInterfaceRange);
-        
-        // Arithmetic operations
-        opcode_map.insert(0x7b, DalvikOpcode::NegInt);
-        opcode_map.insert(0x7c, DalvikOpcode::NotInt);
-        opcode_map.insert(0x7d, DalvikOpcode::NegLong);
-        opcode_map.insert(0x7e, DalvikOpcode::NotLong);
-        opcode_map.insert(0x7f, DalvikOpcode::NegFloat);
-        opcode_map.insert(0x80, DalvikOpcode::NegDouble);
-        opcode_map.insert(0x81, DalvikOpcode::IntToLong);
-        opcode_map.insert(0x82, DalvikOpcode::IntToFloat);
-        opcode_map.insert(0x83, DalvikOpcode::IntToDouble);
-        opcode_map.insert(0x84, DalvikOpcode::LongToInt);
-        opcode_map.insert(0x85, DalvikOpcode::LongToFloat);
-        opcode_map.insert(0x86, DalvikOpcode::LongToDouble);
-        opcode_map.insert(0x87, DalvikOpcode::FloatToInt);
-        opcode_map.insert(0x88, DalvikOpcode::FloatToLong);
-        opcode_map.insert(0x89, DalvikOpcode::FloatToDouble);
-        opcode_map.insert(0x8a, DalvikOpcode::DoubleToInt);
-        opcode_map.insert(0x8b, DalvikOpcode::DoubleToLong);
-        opcode_map.insert(0x8c, DalvikOpcode::DoubleToFloat);
-        opcode_map.insert(0x8d, DalvikOpcode::IntToByte);
-        opcode_map.insert(0x8e, DalvikOpcode::IntToChar);
-        opcode_map.insert(0x8f, DalvikOpcode::IntToShort);
-        
-        // Binary operations
-        opcode_map.insert(0x90, DalvikOpcode::AddInt);
-        opcode_map.insert(0x91, DalvikOpcode::SubInt);
-        opcode_map.insert(0x92, DalvikOpcode::MulInt);
-        opcode_map.insert(0x93, DalvikOpcode::DivInt);
-        opcode_map.insert(0x94, DalvikOpcode::RemInt);
-        opcode_map.insert(0x95, DalvikOpcode::AndInt);
-        opcode_map.insert(0x96, DalvikOpcode::OrInt);
-        opcode_map.insert(0x97, DalvikOpcode::XorInt);
-        opcode_map.insert(0x98, DalvikOpcode::ShlInt);
-        opcode_map.insert(0x99, DalvikOpcode::ShrInt);
-        opcode_map.insert(0x9a, DalvikOpcode::UshrInt);
-        
-        Self { opcode_map }
+        Self
     }
 
-    /// Decode a single instruction
-    pub fn decode_instruction(&self, bytecode: &[u8], offset: usize) -> Result<DalvikInstruction> {
-        if offset >= bytecode.len() {
-            return Err(AndroidAnalyzeError::ParseError("Bytecode offset out of bounds".to_string()));
+    /// Decode the instruction or payload starting at `address` (in code units)
+    pub fn decode_instruction(&self, insns: &[u16], address: usize, dex_file: &DexFile) -> Result<DalvikInstruction> {
+        let unit = |index: usize| -> Result<u16> {
+            insns.get(address + index).copied().ok_or_else(|| {
+                AndroidAnalyzeError::ParseError(format!("Incomplete instruction at 0x{:04x}", address))
+            })
+        };
+        let first = unit(0)?;
+        let opcode = DalvikOpcode::from_u8((first & 0xff) as u8)
+            .ok_or_else(|| AndroidAnalyzeError::ParseError(format!("Unknown opcode: 0x{:02x} at 0x{:04x}", first & 0xff, address)))?;
+
+        if opcode == DalvikOpcode::Nop && first >> 8 != 0 {
+            if let Some(payload) = decode_payload(insns, address)? {
+                return Ok(DalvikInstruction {
+                    address: address as u32,
+                    size: payload.size(),
+                    payload: Some(payload),
+                    ..DalvikInstruction::new(opcode, address)
+                });
+            }
         }
-        
-        let opcode_byte = bytecode[offset];
-        let opcode = self.opcode_map.get(&opcode_byte)
-            .ok_or_else(|| AndroidAnalyzeError::ParseError(format!("Unknown opcode: 0x{:02x}", opcode_byte)))?;
-        
-        let instruction = match opcode {
-            DalvikOpcode::Nop => DalvikInstruction {
-                opcode: opcode.clone(),
-                operands: vec![],
-                size: 1,
-            },
-            DalvikOpcode::Move => {
-                if offset + 1 >= bytecode.len() {
-                    return Err(AndroidAnalyzeError::ParseError("Incomplete instruction".to_string()));
-                }
-                let operand = bytecode[offset + 1];
-                DalvikInstruction {
-                    opcode: opcode.clone(),
-                    operands: vec![operand as u32],
-                    size: 2,
-                }
+
+        let format = opcode.format();
+        let mut instruction = DalvikInstruction::new(opcode, address);
+        instruction.size = format.size();
+        // 先检查长度，后面按格式直接取各个码元
+        unit(format.size() - 1)?;
+        let aa = (first >> 8) as u32;
+        let a = aa & 0xf;
+        let b = aa >> 4;
+        let u1 = insns.get(address + 1).copied().unwrap_or(0) as u32;
+        let u2 = insns.get(address + 2).copied().unwrap_or(0) as u32;
+        let wide32 = u1 | (u2 << 16);
+        let branch = |offset: i64| (address as i64 + offset) as u32;
+        let mut index = None;
+
+        match format {
+            InstructionFormat::F10x => {}
+            InstructionFormat::F12x => instruction.registers = vec![a, b],
+            InstructionFormat::F11n => {
+                instruction.registers = vec![a];
+                instruction.literal = Some(((b as i8) << 4 >> 4) as i64);
             }
-            DalvikOpcode::ReturnVoid => DalvikInstruction {
-                opcode: opcode.clone(),
-                operands: vec![],
-                size: 1,
-            },
-            DalvikOpcode::InvokeVirtual | DalvikOpcode::InvokeSuper | DalvikOpcode::InvokeDirect | 
-            DalvikOpcode::InvokeStatic | DalvikOpcode::InvokeInterface => {
-                if offset + 5 >= bytecode.len() {
-                    return Err(AndroidAnalyzeError::ParseError("Incomplete invoke instruction".to_string()));
-                }
-                let arg_count = (bytecode[offset + 1] >> 4) & 0xf;
-                let method_idx = ((bytecode[offset + 3] as u16) << 8) | (bytecode[offset + 2] as u16);
-                let args = bytecode[offset + 1] & 0xf;
-                
-                DalvikInstruction {
-                    opcode: opcode.clone(),
-                    operands: vec![arg_count as u32, method_idx as u32, args as u32],
-                    size: 6,
-                }
+            InstructionFormat::F11x => instruction.registers = vec![aa],
+            InstructionFormat::F10t => instruction.target = Some(branch(aa as u8 as i8 as i64)),
+            InstructionFormat::F20t => instruction.target = Some(branch(u1 as u16 as i16 as i64)),
+            InstructionFormat::F22x => instruction.registers = vec![aa, u1],
+            InstructionFormat::F21t => {
+                instruction.registers = vec![aa];
+                instruction.target = Some(branch(u1 as u16 as i16 as i64));
+            }
+            InstructionFormat::F21s => {
+                instruction.registers = vec![aa];
+                instruction.literal = Some(u1 as u16 as i16 as i64);
+            }
+            InstructionFormat::F21h => {
+                instruction.registers = vec![aa];
+                // const/high16 填高 16 位，const-wide/high16 填最高 16 位
+                let shift = if opcode == DalvikOpcode::ConstWideHigh16 { 48 } else { 16 };
+                let value = (u1 as i64) << shift;
+                instruction.literal = Some(if shift == 16 { value as i32 as i64 } else { value });
+            }
+            InstructionFormat::F21c => {
+                instruction.registers = vec![aa];
+                index = Some(u1);
+            }
+            InstructionFormat::F23x => instruction.registers = vec![aa, u1 & 0xff, u1 >> 8],
+            InstructionFormat::F22b => {
+                instruction.registers = vec![aa, u1 & 0xff];
+                instruction.literal = Some((u1 >> 8) as u8 as i8 as i64);
+            }
+            InstructionFormat::F22t => {
+                instruction.registers = vec![a, b];
+                instruction.target = Some(branch(u1 as u16 as i16 as i64));
+            }
+            InstructionFormat::F22s => {
+                instruction.registers = vec![a, b];
+                instruction.literal = Some(u1 as u16 as i16 as i64);
             }
-            _ => {
-                // Default case for other opcodes
-                DalvikInstruction {
-                    opcode: opcode.clone(),
-                    operands: vec![],
-                    size: 2,
+            InstructionFormat::F22c => {
+                instruction.registers = vec![a, b];
+                index = Some(u1);
+            }
+            InstructionFormat::F30t => instruction.target = Some(branch(wide32 as i32 as i64)),
+            InstructionFormat::F32x => instruction.registers = vec![u1, u2],
+            InstructionFormat::F31i => {
+                instruction.registers = vec![aa];
+                instruction.literal = Some(wide32 as i32 as i64);
+            }
+            InstructionFormat::F31t => {
+                instruction.registers = vec![aa];
+                instruction.target = Some(branch(wide32 as i32 as i64));
+            }
+            InstructionFormat::F31c => {
+                instruction.registers = vec![aa];
+                index = Some(wide32);
+            }
+            InstructionFormat::F35c | InstructionFormat::F45cc => {
+                // A 为参数个数，G 为第五个寄存器，C..F 在第三个码元中
+                let count = b as usize;
+                if count > 5 {
+                    return Err(AndroidAnalyzeError::ParseError(format!("Invalid argument count {} at 0x{:04x}", count, address)));
                 }
+                let all = [u2 & 0xf, (u2 >> 4) & 0xf, (u2 >> 8) & 0xf, (u2 >> 12) & 0xf, a];
+                instruction.registers = all[..count].to_vec();
+                index = Some(u1);
             }
-        };
-        
+            InstructionFormat::F3rc | InstructionFormat::F4rcc => {
+                instruction.registers = (u2..u2 + aa).collect();
+                index = Some(u1);
+            }
+            InstructionFormat::F51l => {
+                let literal = (0..4).map(|i| insns[address + 1 + i] as u64).fold(0u64, |acc, part| acc >> 16 | part << 48);
+                instruction.registers = vec![aa];
+                instruction.literal = Some(literal as i64);
+            }
+        }
+
+        if let Some(index) = index {
+            instruction.reference = Some(resolve_reference(&opcode, index, dex_file)?);
+        }
+        if matches!(format, InstructionFormat::F45cc | InstructionFormat::F4rcc) {
+            let proto = insns[address + 3] as u32;
+            instruction.proto = Some(lookup(&dex_file.protos, proto, "proto")?.clone());
+        }
         Ok(instruction)
     }
 
-    /// Analyze a method's bytecode
-    pub fn analyze_method(&self, bytecode: &[u8]) -> Result<Vec<DalvikInstruction>> {
+    /// Decode every instruction and payload of a method body
+    pub fn analyze_method(&self, code: &CodeItem, dex_file: &DexFile) -> Result<Vec<DalvikInstruction>> {
         let mut instructions = Vec::new();
-        let mut offset = 0;
-        
-        while offset < bytecode.len() {
-            let instruction = self.decode_instruction(bytecode, offset)?;
-            offset += instruction.size;
+        let mut address = 0;
+
+        while address < code.insns.len() {
+            let instruction = self.decode_instruction(&code.insns, address, dex_file)?;
+            address += instruction.size;
             instructions.push(instruction);
         }
-        
+
         Ok(instructions)
     }
 }
 
-/// Dalvik opcodes
-#[derive(Debug, Clone, PartialEq)]
+fn lookup<'a, T>(items: &'a [T], index: u32, what: &str) -> Result<&'a T> {
+    items
+        .get(index as usize)
+        .ok_or_else(|| AndroidAnalyzeError::InvalidDexFile(format!("Invalid {} index {}", what, index)))
+}
+
+/// 按操作码确定索引指向的表
+fn resolve_reference(opcode: &DalvikOpcode, index: u32, dex_file: &DexFile) -> Result<DalvikReference> {
+    use DalvikOpcode::*;
+    Ok(match opcode {
+        ConstString | ConstStringJumbo => DalvikReference::String(lookup(&dex_file.strings, index, "string")?.clone()),
+        ConstClass | CheckCast | InstanceOf | NewInstance | NewArray | FilledNewArray | FilledNewArrayRange => {
+            DalvikReference::Type(lookup(&dex_file.types, index, "type")?.clone())
+        }
+        InvokeCustom | InvokeCustomRange => DalvikReference::CallSite(index),
+        ConstMethodHandle => DalvikReference::MethodHandle(index),
+        ConstMethodType => DalvikReference::Proto(lookup(&dex_file.protos, index, "proto")?.clone()),
+        _ if opcode.is_field_access() => DalvikReference::Field(lookup(&dex_file.fields, index, "field")?.clone()),
+        _ => DalvikReference::Method(lookup(&dex_file.methods, index, "method")?.clone()),
+    })
+}
+
+/// nop 的高字节为 1/2/3 时是 switch 或数组数据的负载
+fn decode_payload(insns: &[u16], address: usize) -> Result<Option<DalvikPayload>> {
+    let incomplete = || AndroidAnalyzeError::ParseError(format!("Incomplete payload at 0x{:04x}", address));
+    let unit = |index: usize| insns.get(address + index).copied().ok_or_else(incomplete);
+    let int = |index: usize| -> Result<i32> { Ok((unit(index)? as u32 | (unit(index + 1)? as u32) << 16) as i32) };
+
+    let payload = match insns[address] {
+        0x0100 => {
+            let size = unit(1)? as usize;
+            let first_key = int(2)?;
+            let targets = (0..size).map(|i| int(4 + i * 2)).collect::<Result<Vec<_>>>()?;
+            DalvikPayload::PackedSwitch { first_key, targets }
+        }
+        0x0200 => {
+            let size = unit(1)? as usize;
+            let keys = (0..size).map(|i| int(2 + i * 2)).collect::<Result<Vec<_>>>()?;
+            let targets = (0..size).map(|i| int(2 + (size + i) * 2)).collect::<Result<Vec<_>>>()?;
+            DalvikPayload::SparseSwitch { keys, targets }
+        }
+        0x0300 => {
+            let element_width = unit(1)?;
+            let count = (unit(2)? as u32 | (unit(3)? as u32) << 16) as usize;
+            let length = count.checked_mul(element_width as usize).ok_or_else(incomplete)?;
+            // 数据按字节排列，末尾可能有一个填充字节
+            let units = length.div_ceil(2);
+            if insns.len() < address + 4 + units {
+                return Err(incomplete());
+            }
+            let mut data: Vec<u8> = insns[address + 4..address + 4 + units]
+                .iter()
+                .flat_map(|unit| unit.to_le_bytes())
+                .collect();
+            data.truncate(length);
+            DalvikPayload::FillArrayData { element_width, data }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(payload))
+}
+
+/// Dalvik instruction formats, named after the spec (`22c` is two units, two registers, one constant pool index)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionFormat {
+    F10x,
+    F12x,
+    F11n,
+    F11x,
+    F10t,
+    F20t,
+    F22x,
+    F21t,
+    F21s,
+    F21h,
+    F21c,
+    F23x,
+    F22b,
+    F22t,
+    F22s,
+    F22c,
+    F30t,
+    F32x,
+    F31i,
+    F31t,
+    F31c,
+    F35c,
+    F3rc,
+    F45cc,
+    F4rcc,
+    F51l,
+}
+
+impl InstructionFormat {
+    /// Size in 16-bit code units
+    pub fn size(&self) -> usize {
+        use InstructionFormat::*;
+        match self {
+            F10x | F12x | F11n | F11x | F10t => 1,
+            F20t | F22x | F21t | F21s | F21h | F21c | F23x | F22b | F22t | F22s | F22c => 2,
+            F30t | F32x | F31i | F31t | F31c | F35c | F3rc => 3,
+            F45cc | F4rcc => 4,
+            F51l => 5,
+        }
+    }
+}
+
+/// Dalvik opcodes, the discriminant is the opcode byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
 pub enum DalvikOpcode {
-    Nop,
-    Move,
-    MoveFrom16,
-    Move16,
-    MoveWide,
-    MoveWideFrom16,
-    MoveWide16,
-    MoveObject,
-    MoveObjectFrom16,
-    MoveObject16,
-    MoveResult,
-    MoveResultWide,
-    MoveResultObject,
-    MoveException,
-    ReturnVoid,
-    Return,
-    ReturnWide,
-    ReturnObject,
-    Const4,
-    Const16,
-    Const,
-    ConstHigh16,
-    ConstWide16,
-    ConstWide32,
-    ConstWide,
-    ConstWideHigh16,
-    ConstString,
-    ConstStringJumbo,
-    ConstClass,
-    MonitorEnter,
-    MonitorExit,
-    CheckCast,
-    InstanceOf,
-    ArrayLength,
-    NewInstance,
-    NewArray,
-    FilledNewArray,
-    FilledNewArrayRange,
-    FillArrayData,
-    Throw,
-    Goto,
-    Goto16,
-    Goto32,
-    PackedSwitch,
-    SparseSwitch,
-    CmplFloat,
-    CmpgFloat,
-    CmplDouble,
-    CmpgDouble,
-    CmpLong,
-    IfEq,
-    IfNe,
-    IfLt,
-    IfGe,
-    IfGt,
-    IfLe,
-    IfEqz,
-    IfNez,
-    IfLtz,
-    IfGez,
-    IfGtz,
-    IfLez,
-    Aget,
-    AgetWide,
-    AgetObject,
-    AgetBoolean,
-    AgetByte,
-    AgetChar,
-    AgetShort,
-    Aput,
-    AputWide,
-    AputObject,
-    AputBoolean,
-    AputByte,
-    AputChar,
-    AputShort,
-    Iget,
-    IgetWide,
-    IgetObject,
-    IgetBoolean,
-    IgetByte,
-    IgetChar,
-    IgetShort,
-    Iput,
-    IputWide,
-    IputObject,
-    IputBoolean,
-    IputByte,
-    IputChar,
-    IputShort,
-    Sget,
-    SgetWide,
-    SgetObject,
-    SgetBoolean,
-    SgetByte,
-    SgetChar,
-    SgetShort,
-    Sput,
-    SputWide,
-    SputObject,
-    SputBoolean,
-    SputByte,
-    SputChar,
-    SputShort,
-    InvokeVirtual,
-    InvokeSuper,
-    InvokeDirect,
-    InvokeStatic,
-    InvokeInterface,
-    InvokeVirtualRange,
-    InvokeSuperRange,
-    InvokeDirectRange,
-    InvokeStaticRange,
-    InvokeInterfaceRange,
-    NegInt,
-    NotInt,
-    NegLong,
-    NotLong,
-    NegFloat,
-    NegDouble,
-    IntToLong,
-    IntToFloat,
-    IntToDouble,
-    LongToInt,
-    LongToFloat,
-    LongToDouble,
-    FloatToInt,
-    FloatToLong,
-    FloatToDouble,
-    DoubleToInt,
-    DoubleToLong,
-    DoubleToFloat,
-    IntToByte,
-    IntToChar,
-    IntToShort,
-    AddInt,
-    SubInt,
-    MulInt,
-    DivInt,
-    RemInt,
-    AndInt,
-    OrInt,
-    XorInt,
-    ShlInt,
-    ShrInt,
-    UshrInt,
+    Nop = 0x00,
+    Move = 0x01,
+    MoveFrom16 = 0x02,
+    Move16 = 0x03,
+    MoveWide = 0x04,
+    MoveWideFrom16 = 0x05,
+    MoveWide16 = 0x06,
+    MoveObject = 0x07,
+    MoveObjectFrom16 = 0x08,
+    MoveObject16 = 0x09,
+    MoveResult = 0x0a,
+    MoveResultWide = 0x0b,
+    MoveResultObject = 0x0c,
+    MoveException = 0x0d,
+    ReturnVoid = 0x0e,
+    Return = 0x0f,
+    ReturnWide = 0x10,
+    ReturnObject = 0x11,
+    Const4 = 0x12,
+    Const16 = 0x13,
+    Const = 0x14,
+    ConstHigh16 = 0x15,
+    ConstWide16 = 0x16,
+    ConstWide32 = 0x17,
+    ConstWide = 0x18,
+    ConstWideHigh16 = 0x19,
+    ConstString = 0x1a,
+    ConstStringJumbo = 0x1b,
+    ConstClass = 0x1c,
+    MonitorEnter = 0x1d,
+    MonitorExit = 0x1e,
+    CheckCast = 0x1f,
+    InstanceOf = 0x20,
+    ArrayLength = 0x21,
+    NewInstance = 0x22,
+    NewArray = 0x23,
+    FilledNewArray = 0x24,
+    FilledNewArrayRange = 0x25,
+    FillArrayData = 0x26,
+    Throw = 0x27,
+    Goto = 0x28,
+    Goto16 = 0x29,
+    Goto32 = 0x2a,
+    PackedSwitch = 0x2b,
+    SparseSwitch = 0x2c,
+    CmplFloat = 0x2d,
+    CmpgFloat = 0x2e,
+    CmplDouble = 0x2f,
+    CmpgDouble = 0x30,
+    CmpLong = 0x31,
+    IfEq = 0x32,
+    IfNe = 0x33,
+    IfLt = 0x34,
+    IfGe = 0x35,
+    IfGt = 0x36,
+    IfLe = 0x37,
+    IfEqz = 0x38,
+    IfNez = 0x39,
+    IfLtz = 0x3a,
+    IfGez = 0x3b,
+    IfGtz = 0x3c,
+    IfLez = 0x3d,
+    Aget = 0x44,
+    AgetWide = 0x45,
+    AgetObject = 0x46,
+    AgetBoolean = 0x47,
+    AgetByte = 0x48,
+    AgetChar = 0x49,
+    AgetShort = 0x4a,
+    Aput = 0x4b,
+    AputWide = 0x4c,
+    AputObject = 0x4d,
+    AputBoolean = 0x4e,
+    AputByte = 0x4f,
+    AputChar = 0x50,
+    AputShort = 0x51,
+    Iget = 0x52,
+    IgetWide = 0x53,
+    IgetObject = 0x54,
+    IgetBoolean = 0x55,
+    IgetByte = 0x56,
+    IgetChar = 0x57,
+    IgetShort = 0x58,
+    Iput = 0x59,
+    IputWide = 0x5a,
+    IputObject = 0x5b,
+    IputBoolean = 0x5c,
+    IputByte = 0x5d,
+    IputChar = 0x5e,
+    IputShort = 0x5f,
+    Sget = 0x60,
+    SgetWide = 0x61,
+    SgetObject = 0x62,
+    SgetBoolean = 0x63,
+    SgetByte = 0x64,
+    SgetChar = 0x65,
+    SgetShort = 0x66,
+    Sput = 0x67,
+    SputWide = 0x68,
+    SputObject = 0x69,
+    SputBoolean = 0x6a,
+    SputByte = 0x6b,
+    SputChar = 0x6c,
+    SputShort = 0x6d,
+    InvokeVirtual = 0x6e,
+    InvokeSuper = 0x6f,
+    InvokeDirect = 0x70,
+    InvokeStatic = 0x71,
+    InvokeInterface = 0x72,
+    InvokeVirtualRange = 0x74,
+    InvokeSuperRange = 0x75,
+    InvokeDirectRange = 0x76,
+    InvokeStaticRange = 0x77,
+    InvokeInterfaceRange = 0x78,
+    NegInt = 0x7b,
+    NotInt = 0x7c,
+    NegLong = 0x7d,
+    NotLong = 0x7e,
+    NegFloat = 0x7f,
+    NegDouble = 0x80,
+    IntToLong = 0x81,
+    IntToFloat = 0x82,
+    IntToDouble = 0x83,
+    LongToInt = 0x84,
+    LongToFloat = 0x85,
+    LongToDouble = 0x86,
+    FloatToInt = 0x87,
+    FloatToLong = 0x88,
+    FloatToDouble = 0x89,
+    DoubleToInt = 0x8a,
+    DoubleToLong = 0x8b,
+    DoubleToFloat = 0x8c,
+    IntToByte = 0x8d,
+    IntToChar = 0x8e,
+    IntToShort = 0x8f,
+    AddInt = 0x90,
+    SubInt = 0x91,
+    MulInt = 0x92,
+    DivInt = 0x93,
+    RemInt = 0x94,
+    AndInt = 0x95,
+    OrInt = 0x96,
+    XorInt = 0x97,
+    ShlInt = 0x98,
+    ShrInt = 0x99,
+    UshrInt = 0x9a,
+    AddLong = 0x9b,
+    SubLong = 0x9c,
+    MulLong = 0x9d,
+    DivLong = 0x9e,
+    RemLong = 0x9f,
+    AndLong = 0xa0,
+    OrLong = 0xa1,
+    XorLong = 0xa2,
+    ShlLong = 0xa3,
+    ShrLong = 0xa4,
+    UshrLong = 0xa5,
+    AddFloat = 0xa6,
+    SubFloat = 0xa7,
+    MulFloat = 0xa8,
+    DivFloat = 0xa9,
+    RemFloat = 0xaa,
+    AddDouble = 0xab,
+    SubDouble = 0xac,
+    MulDouble = 0xad,
+    DivDouble = 0xae,
+    RemDouble = 0xaf,
+    AddInt2Addr = 0xb0,
+    SubInt2Addr = 0xb1,
+    MulInt2Addr = 0xb2,
+    DivInt2Addr = 0xb3,
+    RemInt2Addr = 0xb4,
+    AndInt2Addr = 0xb5,
+    OrInt2Addr = 0xb6,
+    XorInt2Addr = 0xb7,
+    ShlInt2Addr = 0xb8,
+    ShrInt2Addr = 0xb9,
+    UshrInt2Addr = 0xba,
+    AddLong2Addr = 0xbb,
+    SubLong2Addr = 0xbc,
+    MulLong2Addr = 0xbd,
+    DivLong2Addr = 0xbe,
+    RemLong2Addr = 0xbf,
+    AndLong2Addr = 0xc0,
+    OrLong2Addr = 0xc1,
+    XorLong2Addr = 0xc2,
+    ShlLong2Addr = 0xc3,
+    ShrLong2Addr = 0xc4,
+    UshrLong2Addr = 0xc5,
+    AddFloat2Addr = 0xc6,
+    SubFloat2Addr = 0xc7,
+    MulFloat2Addr = 0xc8,
+    DivFloat2Addr = 0xc9,
+    RemFloat2Addr = 0xca,
+    AddDouble2Addr = 0xcb,
+    SubDouble2Addr = 0xcc,
+    MulDouble2Addr = 0xcd,
+    DivDouble2Addr = 0xce,
+    RemDouble2Addr = 0xcf,
+    AddIntLit16 = 0xd0,
+    RsubInt = 0xd1,
+    MulIntLit16 = 0xd2,
+    DivIntLit16 = 0xd3,
+    RemIntLit16 = 0xd4,
+    AndIntLit16 = 0xd5,
+    OrIntLit16 = 0xd6,
+    XorIntLit16 = 0xd7,
+    AddIntLit8 = 0xd8,
+    RsubIntLit8 = 0xd9,
+    MulIntLit8 = 0xda,
+    DivIntLit8 = 0xdb,
+    RemIntLit8 = 0xdc,
+    AndIntLit8 = 0xdd,
+    OrIntLit8 = 0xde,
+    XorIntLit8 = 0xdf,
+    ShlIntLit8 = 0xe0,
+    ShrIntLit8 = 0xe1,
+    UshrIntLit8 = 0xe2,
+    InvokePolymorphic = 0xfa,
+    InvokePolymorphicRange = 0xfb,
+    InvokeCustom = 0xfc,
+    InvokeCustomRange = 0xfd,
+    ConstMethodHandle = 0xfe,
+    ConstMethodType = 0xff,
+}
+
+/// 按操作码字节排序，供二分查找
+const OPCODES: &[(DalvikOpcode, &str, InstructionFormat)] = &[
+    (DalvikOpcode::Nop, "nop", InstructionFormat::F10x),
+    (DalvikOpcode::Move, "move", InstructionFormat::F12x),
+    (DalvikOpcode::MoveFrom16, "move/from16", InstructionFormat::F22x),
+    (DalvikOpcode::Move16, "move/16", InstructionFormat::F32x),
+    (DalvikOpcode::MoveWide, "move-wide", InstructionFormat::F12x),
+    (DalvikOpcode::MoveWideFrom16, "move-wide/from16", InstructionFormat::F22x),
+    (DalvikOpcode::MoveWide16, "move-wide/16", InstructionFormat::F32x),
+    (DalvikOpcode::MoveObject, "move-object", InstructionFormat::F12x),
+    (DalvikOpcode::MoveObjectFrom16, "move-object/from16", InstructionFormat::F22x),
+    (DalvikOpcode::MoveObject16, "move-object/16", InstructionFormat::F32x),
+    (DalvikOpcode::MoveResult, "move-result", InstructionFormat::F11x),
+    (DalvikOpcode::MoveResultWide, "move-result-wide", InstructionFormat::F11x),
+    (DalvikOpcode::MoveResultObject, "move-result-object", InstructionFormat::F11x),
+    (DalvikOpcode::MoveException, "move-exception", InstructionFormat::F11x),
+    (DalvikOpcode::ReturnVoid, "return-void", InstructionFormat::F10x),
+    (DalvikOpcode::Return, "return", InstructionFormat::F11x),
+    (DalvikOpcode::ReturnWide, "return-wide", InstructionFormat::F11x),
+    (DalvikOpcode::ReturnObject, "return-object", InstructionFormat::F11x),
+    (DalvikOpcode::Const4, "const/4", InstructionFormat::F11n),
+    (DalvikOpcode::Const16, "const/16", InstructionFormat::F21s),
+    (DalvikOpcode::Const, "const", InstructionFormat::F31i),
+    (DalvikOpcode::ConstHigh16, "const/high16", InstructionFormat::F21h),
+    (DalvikOpcode::ConstWide16, "const-wide/16", InstructionFormat::F21s),
+    (DalvikOpcode::ConstWide32, "const-wide/32", InstructionFormat::F31i),
+    (DalvikOpcode::ConstWide, "const-wide", InstructionFormat::F51l),
+    (DalvikOpcode::ConstWideHigh16, "const-wide/high16", InstructionFormat::F21h),
+    (DalvikOpcode::ConstString, "const-string", InstructionFormat::F21c),
+    (DalvikOpcode::ConstStringJumbo, "const-string/jumbo", InstructionFormat::F31c),
+    (DalvikOpcode::ConstClass, "const-class", InstructionFormat::F21c),
+    (DalvikOpcode::MonitorEnter, "monitor-enter", InstructionFormat::F11x),
+    (DalvikOpcode::MonitorExit, "monitor-exit", InstructionFormat::F11x),
+    (DalvikOpcode::CheckCast, "check-cast", InstructionFormat::F21c),
+    (DalvikOpcode::InstanceOf, "instance-of", InstructionFormat::F22c),
+    (DalvikOpcode::ArrayLength, "array-length", InstructionFormat::F12x),
+    (DalvikOpcode::NewInstance, "new-instance", InstructionFormat::F21c),
+    (DalvikOpcode::NewArray, "new-array", InstructionFormat::F22c),
+    (DalvikOpcode::FilledNewArray, "filled-new-array", InstructionFormat::F35c),
+    (DalvikOpcode::FilledNewArrayRange, "filled-new-array/range", InstructionFormat::F3rc),
+    (DalvikOpcode::FillArrayData, "fill-array-data", InstructionFormat::F31t),
+    (DalvikOpcode::Throw, "throw", InstructionFormat::F11x),
+    (DalvikOpcode::Goto, "goto", InstructionFormat::F10t),
+    (DalvikOpcode::Goto16, "goto/16", InstructionFormat::F20t),
+    (DalvikOpcode::Goto32, "goto/32", InstructionFormat::F30t),
+    (DalvikOpcode::PackedSwitch, "packed-switch", InstructionFormat::F31t),
+    (DalvikOpcode::SparseSwitch, "sparse-switch", InstructionFormat::F31t),
+    (DalvikOpcode::CmplFloat, "cmpl-float", InstructionFormat::F23x),
+    (DalvikOpcode::CmpgFloat, "cmpg-float", InstructionFormat::F23x),
+    (DalvikOpcode::CmplDouble, "cmpl-double", InstructionFormat::F23x),
+    (DalvikOpcode::CmpgDouble, "cmpg-double", InstructionFormat::F23x),
+    (DalvikOpcode::CmpLong, "cmp-long", InstructionFormat::F23x),
+    (DalvikOpcode::IfEq, "if-eq", InstructionFormat::F22t),
+    (DalvikOpcode::IfNe, "if-ne", InstructionFormat::F22t),
+    (DalvikOpcode::IfLt, "if-lt", InstructionFormat::F22t),
+    (DalvikOpcode::IfGe, "if-ge", InstructionFormat::F22t),
+    (DalvikOpcode::IfGt, "if-gt", InstructionFormat::F22t),
+    (DalvikOpcode::IfLe, "if-le", InstructionFormat::F22t),
+    (DalvikOpcode::IfEqz, "if-eqz", InstructionFormat::F21t),
+    (DalvikOpcode::IfNez, "if-nez", InstructionFormat::F21t),
+    (DalvikOpcode::IfLtz, "if-ltz", InstructionFormat::F21t),
+    (DalvikOpcode::IfGez, "if-gez", InstructionFormat::F21t),
+    (DalvikOpcode::IfGtz, "if-gtz", InstructionFormat::F21t),
+    (DalvikOpcode::IfLez, "if-lez", InstructionFormat::F21t),
+    (DalvikOpcode::Aget, "aget", InstructionFormat::F23x),
+    (DalvikOpcode::AgetWide, "aget-wide", InstructionFormat::F23x),
+    (DalvikOpcode::AgetObject, "aget-object", InstructionFormat::F23x),
+    (DalvikOpcode::AgetBoolean, "aget-boolean", InstructionFormat::F23x),
+    (DalvikOpcode::AgetByte, "aget-byte", InstructionFormat::F23x),
+    (DalvikOpcode::AgetChar, "aget-char", InstructionFormat::F23x),
+    (DalvikOpcode::AgetShort, "aget-short", InstructionFormat::F23x),
+    (DalvikOpcode::Aput, "aput", InstructionFormat::F23x),
+    (DalvikOpcode::AputWide, "aput-wide", InstructionFormat::F23x),
+    (DalvikOpcode::AputObject, "aput-object", InstructionFormat::F23x),
+    (DalvikOpcode::AputBoolean, "aput-boolean", InstructionFormat::F23x),
+    (DalvikOpcode::AputByte, "aput-byte", InstructionFormat::F23x),
+    (DalvikOpcode::AputChar, "aput-char", InstructionFormat::F23x),
+    (DalvikOpcode::AputShort, "aput-short", InstructionFormat::F23x),
+    (DalvikOpcode::Iget, "iget", InstructionFormat::F22c),
+    (DalvikOpcode::IgetWide, "iget-wide", InstructionFormat::F22c),
+    (DalvikOpcode::IgetObject, "iget-object", InstructionFormat::F22c),
+    (DalvikOpcode::IgetBoolean, "iget-boolean", InstructionFormat::F22c),
+    (DalvikOpcode::IgetByte, "iget-byte", InstructionFormat::F22c),
+    (DalvikOpcode::IgetChar, "iget-char", InstructionFormat::F22c),
+    (DalvikOpcode::IgetShort, "iget-short", InstructionFormat::F22c),
+    (DalvikOpcode::Iput, "iput", InstructionFormat::F22c),
+    (DalvikOpcode::IputWide, "iput-wide", InstructionFormat::F22c),
+    (DalvikOpcode::IputObject, "iput-object", InstructionFormat::F22c),
+    (DalvikOpcode::IputBoolean, "iput-boolean", InstructionFormat::F22c),
+    (DalvikOpcode::IputByte, "iput-byte", InstructionFormat::F22c),
+    (DalvikOpcode::IputChar, "iput-char", InstructionFormat::F22c),
+    (DalvikOpcode::IputShort, "iput-short", InstructionFormat::F22c),
+    (DalvikOpcode::Sget, "sget", InstructionFormat::F21c),
+    (DalvikOpcode::SgetWide, "sget-wide", InstructionFormat::F21c),
+    (DalvikOpcode::SgetObject, "sget-object", InstructionFormat::F21c),
+    (DalvikOpcode::SgetBoolean, "sget-boolean", InstructionFormat::F21c),
+    (DalvikOpcode::SgetByte, "sget-byte", InstructionFormat::F21c),
+    (DalvikOpcode::SgetChar, "sget-char", InstructionFormat::F21c),
+    (DalvikOpcode::SgetShort, "sget-short", InstructionFormat::F21c),
+    (DalvikOpcode::Sput, "sput", InstructionFormat::F21c),
+    (DalvikOpcode::SputWide, "sput-wide", InstructionFormat::F21c),
+    (DalvikOpcode::SputObject, "sput-object", InstructionFormat::F21c),
+    (DalvikOpcode::SputBoolean, "sput-boolean", InstructionFormat::F21c),
+    (DalvikOpcode::SputByte, "sput-byte", InstructionFormat::F21c),
+    (DalvikOpcode::SputChar, "sput-char", InstructionFormat::F21c),
+    (DalvikOpcode::SputShort, "sput-short", InstructionFormat::F21c),
+    (DalvikOpcode::InvokeVirtual, "invoke-virtual", InstructionFormat::F35c),
+    (DalvikOpcode::InvokeSuper, "invoke-super", InstructionFormat::F35c),
+    (DalvikOpcode::InvokeDirect, "invoke-direct", InstructionFormat::F35c),
+    (DalvikOpcode::InvokeStatic, "invoke-static", InstructionFormat::F35c),
+    (DalvikOpcode::InvokeInterface, "invoke-interface", InstructionFormat::F35c),
+    (DalvikOpcode::InvokeVirtualRange, "invoke-virtual/range", InstructionFormat::F3rc),
+    (DalvikOpcode::InvokeSuperRange, "invoke-super/range", InstructionFormat::F3rc),
+    (DalvikOpcode::InvokeDirectRange, "invoke-direct/range", InstructionFormat::F3rc),
+    (DalvikOpcode::InvokeStaticRange, "invoke-static/range", InstructionFormat::F3rc),
+    (DalvikOpcode::InvokeInterfaceRange, "invoke-interface/range", InstructionFormat::F3rc),
+    (DalvikOpcode::NegInt, "neg-int", InstructionFormat::F12x),
+    (DalvikOpcode::NotInt, "not-int", InstructionFormat::F12x),
+    (DalvikOpcode::NegLong, "neg-long", InstructionFormat::F12x),
+    (DalvikOpcode::NotLong, "not-long", InstructionFormat::F12x),
+    (DalvikOpcode::NegFloat, "neg-float", InstructionFormat::F12x),
+    (DalvikOpcode::NegDouble, "neg-double", InstructionFormat::F12x),
+    (DalvikOpcode::IntToLong, "int-to-long", InstructionFormat::F12x),
+    (DalvikOpcode::IntToFloat, "int-to-float", InstructionFormat::F12x),
+    (DalvikOpcode::IntToDouble, "int-to-double", InstructionFormat::F12x),
+    (DalvikOpcode::LongToInt, "long-to-int", InstructionFormat::F12x),
+    (DalvikOpcode::LongToFloat, "long-to-float", InstructionFormat::F12x),
+    (DalvikOpcode::LongToDouble, "long-to-double", InstructionFormat::F12x),
+    (DalvikOpcode::FloatToInt, "float-to-int", InstructionFormat::F12x),
+    (DalvikOpcode::FloatToLong, "float-to-long", InstructionFormat::F12x),
+    (DalvikOpcode::FloatToDouble, "float-to-double", InstructionFormat::F12x),
+    (DalvikOpcode::DoubleToInt, "double-to-int", InstructionFormat::F12x),
+    (DalvikOpcode::DoubleToLong, "double-to-long", InstructionFormat::F12x),
+    (DalvikOpcode::DoubleToFloat, "double-to-float", InstructionFormat::F12x),
+    (DalvikOpcode::IntToByte, "int-to-byte", InstructionFormat::F12x),
+    (DalvikOpcode::IntToChar, "int-to-char", InstructionFormat::F12x),
+    (DalvikOpcode::IntToShort, "int-to-short", InstructionFormat::F12x),
+    (DalvikOpcode::AddInt, "add-int", InstructionFormat::F23x),
+    (DalvikOpcode::SubInt, "sub-int", InstructionFormat::F23x),
+    (DalvikOpcode::MulInt, "mul-int", InstructionFormat::F23x),
+    (DalvikOpcode::DivInt, "div-int", InstructionFormat::F23x),
+    (DalvikOpcode::RemInt, "rem-int", InstructionFormat::F23x),
+    (DalvikOpcode::AndInt, "and-int", InstructionFormat::F23x),
+    (DalvikOpcode::OrInt, "or-int", InstructionFormat::F23x),
+    (DalvikOpcode::XorInt, "xor-int", InstructionFormat::F23x),
+    (DalvikOpcode::ShlInt, "shl-int", InstructionFormat::F23x),
+    (DalvikOpcode::ShrInt, "shr-int", InstructionFormat::F23x),
+    (DalvikOpcode::UshrInt, "ushr-int", InstructionFormat::F23x),
+    (DalvikOpcode::AddLong, "add-long", InstructionFormat::F23x),
+    (DalvikOpcode::SubLong, "sub-long", InstructionFormat::F23x),
+    (DalvikOpcode::MulLong, "mul-long", InstructionFormat::F23x),
+    (DalvikOpcode::DivLong, "div-long", InstructionFormat::F23x),
+    (DalvikOpcode::RemLong, "rem-long", InstructionFormat::F23x),
+    (DalvikOpcode::AndLong, "and-long", InstructionFormat::F23x),
+    (DalvikOpcode::OrLong, "or-long", InstructionFormat::F23x),
+    (DalvikOpcode::XorLong, "xor-long", InstructionFormat::F23x),
+    (DalvikOpcode::ShlLong, "shl-long", InstructionFormat::F23x),
+    (DalvikOpcode::ShrLong, "shr-long", InstructionFormat::F23x),
+    (DalvikOpcode::UshrLong, "ushr-long", InstructionFormat::F23x),
+    (DalvikOpcode::AddFloat, "add-float", InstructionFormat::F23x),
+    (DalvikOpcode::SubFloat, "sub-float", InstructionFormat::F23x),
+    (DalvikOpcode::MulFloat, "mul-float", InstructionFormat::F23x),
+    (DalvikOpcode::DivFloat, "div-float", InstructionFormat::F23x),
+    (DalvikOpcode::RemFloat, "rem-float", InstructionFormat::F23x),
+    (DalvikOpcode::AddDouble, "add-double", InstructionFormat::F23x),
+    (DalvikOpcode::SubDouble, "sub-double", InstructionFormat::F23x),
+    (DalvikOpcode::MulDouble, "mul-double", InstructionFormat::F23x),
+    (DalvikOpcode::DivDouble, "div-double", InstructionFormat::F23x),
+    (DalvikOpcode::RemDouble, "rem-double", InstructionFormat::F23x),
+    (DalvikOpcode::AddInt2Addr, "add-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::SubInt2Addr, "sub-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::MulInt2Addr, "mul-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::DivInt2Addr, "div-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::RemInt2Addr, "rem-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::AndInt2Addr, "and-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::OrInt2Addr, "or-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::XorInt2Addr, "xor-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::ShlInt2Addr, "shl-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::ShrInt2Addr, "shr-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::UshrInt2Addr, "ushr-int/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::AddLong2Addr, "add-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::SubLong2Addr, "sub-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::MulLong2Addr, "mul-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::DivLong2Addr, "div-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::RemLong2Addr, "rem-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::AndLong2Addr, "and-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::OrLong2Addr, "or-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::XorLong2Addr, "xor-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::ShlLong2Addr, "shl-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::ShrLong2Addr, "shr-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::UshrLong2Addr, "ushr-long/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::AddFloat2Addr, "add-float/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::SubFloat2Addr, "sub-float/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::MulFloat2Addr, "mul-float/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::DivFloat2Addr, "div-float/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::RemFloat2Addr, "rem-float/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::AddDouble2Addr, "add-double/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::SubDouble2Addr, "sub-double/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::MulDouble2Addr, "mul-double/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::DivDouble2Addr, "div-double/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::RemDouble2Addr, "rem-double/2addr", InstructionFormat::F12x),
+    (DalvikOpcode::AddIntLit16, "add-int/lit16", InstructionFormat::F22s),
+    (DalvikOpcode::RsubInt, "rsub-int", InstructionFormat::F22s),
+    (DalvikOpcode::MulIntLit16, "mul-int/lit16", InstructionFormat::F22s),
+    (DalvikOpcode::DivIntLit16, "div-int/lit16", InstructionFormat::F22s),
+    (DalvikOpcode::RemIntLit16, "rem-int/lit16", InstructionFormat::F22s),
+    (DalvikOpcode::AndIntLit16, "and-int/lit16", InstructionFormat::F22s),
+    (DalvikOpcode::OrIntLit16, "or-int/lit16", InstructionFormat::F22s),
+    (DalvikOpcode::XorIntLit16, "xor-int/lit16", InstructionFormat::F22s),
+    (DalvikOpcode::AddIntLit8, "add-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::RsubIntLit8, "rsub-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::MulIntLit8, "mul-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::DivIntLit8, "div-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::RemIntLit8, "rem-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::AndIntLit8, "and-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::OrIntLit8, "or-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::XorIntLit8, "xor-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::ShlIntLit8, "shl-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::ShrIntLit8, "shr-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::UshrIntLit8, "ushr-int/lit8", InstructionFormat::F22b),
+    (DalvikOpcode::InvokePolymorphic, "invoke-polymorphic", InstructionFormat::F45cc),
+    (DalvikOpcode::InvokePolymorphicRange, "invoke-polymorphic/range", InstructionFormat::F4rcc),
+    (DalvikOpcode::InvokeCustom, "invoke-custom", InstructionFormat::F35c),
+    (DalvikOpcode::InvokeCustomRange, "invoke-custom/range", InstructionFormat::F3rc),
+    (DalvikOpcode::ConstMethodHandle, "const-method-handle", InstructionFormat::F21c),
+    (DalvikOpcode::ConstMethodType, "const-method-type", InstructionFormat::F21c),
+];
+
+impl DalvikOpcode {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        OPCODES
+            .binary_search_by_key(&value, |(opcode, _, _)| *opcode as u8)
+            .ok()
+            .map(|index| OPCODES[index].0)
+    }
+
+    /// Looks an opcode up by its smali mnemonic
+    pub fn from_name(name: &str) -> Option<Self> {
+        OPCODES.iter().find(|(_, mnemonic, _)| *mnemonic == name).map(|(opcode, _, _)| *opcode)
+    }
+
+    fn entry(&self) -> &'static (DalvikOpcode, &'static str, InstructionFormat) {
+        // 枚举值都来自 OPCODES，查找不会失败
+        let index = OPCODES.binary_search_by_key(&(*self as u8), |(opcode, _, _)| *opcode as u8).unwrap_or(0);
+        &OPCODES[index]
+    }
+
+    /// Smali mnemonic, e.g. `invoke-virtual/range`
+    pub fn name(&self) -> &'static str {
+        self.entry().1
+    }
+
+    pub fn format(&self) -> InstructionFormat {
+        self.entry().2
+    }
+
+    pub fn is_field_access(&self) -> bool {
+        (DalvikOpcode::Iget as u8..=DalvikOpcode::SputShort as u8).contains(&(*self as u8))
+    }
+
+    pub fn is_invoke(&self) -> bool {
+        use DalvikOpcode::*;
+        matches!(self.format(), InstructionFormat::F35c | InstructionFormat::F3rc | InstructionFormat::F45cc | InstructionFormat::F4rcc)
+            && !matches!(self, FilledNewArray | FilledNewArrayRange)
+    }
+
+    /// Ends the basic block without falling through
+    pub fn is_terminator(&self) -> bool {
+        use DalvikOpcode::*;
+        matches!(self, ReturnVoid | Return | ReturnWide | ReturnObject | Throw | Goto | Goto16 | Goto32)
+    }
+}
+
+/// The constant pool entry an instruction refers to
+#[derive(Debug, Clone)]
+pub enum DalvikReference {
+    String(String),
+    Type(TypeDescriptor),
+    Field(FieldDescriptor),
+    Method(MethodDescriptor),
+    Proto(ProtoDescriptor),
+    /// Index into the `call_site_ids` section
+    CallSite(u32),
+    /// Index into the `method_handles` section
+    MethodHandle(u32),
+}
+
+/// Data tables embedded in the instruction stream after the code
+#[derive(Debug, Clone)]
+pub enum DalvikPayload {
+    /// Targets are relative to the `packed-switch` instruction, not the payload
+    PackedSwitch { first_key: i32, targets: Vec<i32> },
+    SparseSwitch { keys: Vec<i32>, targets: Vec<i32> },
+    FillArrayData { element_width: u16, data: Vec<u8> },
+}
+
+impl DalvikPayload {
+    /// Size in 16-bit code units
+    pub fn size(&self) -> usize {
+        match self {
+            DalvikPayload::PackedSwitch { targets, .. } => 4 + targets.len() * 2,
+            DalvikPayload::SparseSwitch { keys, .. } => 2 + keys.len() * 4,
+            DalvikPayload::FillArrayData { data, .. } => 4 + data.len().div_ceil(2),
+        }
+    }
+
+    /// Array elements of a `fill-array-data` payload, sign extended
+    pub fn elements(&self) -> Vec<i64> {
+        match self {
+            DalvikPayload::FillArrayData { element_width, data } if *element_width > 0 => data
+                .chunks(*element_width as usize)
+                .map(|chunk| {
+                    let mut bytes = [0u8; 8];
+                    bytes[..chunk.len()].copy_from_slice(chunk);
+                    let shift = 64 - chunk.len() * 8;
+                    (i64::from_le_bytes(bytes) << shift) >> shift
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 /// Dalvik instruction
 #[derive(Debug, Clone)]
 pub struct DalvikInstruction {
+    /// Address in 16-bit code units
+    pub address: u32,
     pub opcode: DalvikOpcode,
-    pub operands: Vec<u32>,
+    /// Register operands in order, range invokes are expanded
+    pub registers: Vec<u32>,
+    pub literal: Option<i64>,
+    /// Absolute branch target or payload address
+    pub target: Option<u32>,
+    pub reference: Option<DalvikReference>,
+    /// Call site prototype of `invoke-polymorphic`
+    pub proto: Option<ProtoDescriptor>,
+    /// Set on payload pseudo-instructions, whose opcode is `Nop`
+    pub payload: Option<DalvikPayload>,
+    /// Size in 16-bit code units
     pub size: usize,
 }
 
 impl DalvikInstruction {
-    /// Get a human-readable representation of the instruction
-    pub fn to_string(&self) -> String {
-        format!("{:?} {:?}", self.opcode, self.operands)
+    fn new(opcode: DalvikOpcode, address: usize) -> Self {
+        Self {
+            address: address as u32,
+            opcode,
+            registers: Vec::new(),
+            literal: None,
+            target: None,
+            reference: None,
+            proto: None,
+            payload: None,
+            size: 1,
+        }
+    }
+
+    /// Resolve the `(key, absolute target)` pairs of a switch against the decoded method
+    pub fn switch_cases(&self, instructions: &[DalvikInstruction]) -> Vec<(i32, u32)> {
+        let Some(payload) = self.target.and_then(|target| payload_at(instructions, target)) else {
+            return Vec::new();
+        };
+        let absolute = |offset: &i32| (self.address as i64 + *offset as i64) as u32;
+        match (&self.opcode, payload) {
+            (DalvikOpcode::PackedSwitch, DalvikPayload::PackedSwitch { first_key, targets }) => targets
+                .iter()
+                .enumerate()
+                .map(|(i, offset)| (first_key.wrapping_add(i as i32), absolute(offset)))
+                .collect(),
+            (DalvikOpcode::SparseSwitch, DalvikPayload::SparseSwitch { keys, targets }) => {
+                keys.iter().copied().zip(targets.iter().map(absolute)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The payload pseudo-instruction at a code address
+pub fn payload_at(instructions: &[DalvikInstruction], address: u32) -> Option<&DalvikPayload> {
+    instructions
+        .binary_search_by_key(&address, |instruction| instruction.address)
+        .ok()
+        .and_then(|index| instructions[index].payload.as_ref())
+}
+
+impl fmt::Display for DalvikReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DalvikReference::String(value) => write!(f, "{:?}", value),
+            DalvikReference::Type(t) => write!(f, "{}", t.descriptor),
+            DalvikReference::Field(field) => {
+                write!(f, "{}->{}:{}", field.class_type.descriptor, field.name, field.field_type.descriptor)
+            }
+            DalvikReference::Method(method) => {
+                write!(f, "{}->{}{}", method.class_type.descriptor, method.name, proto_to_descriptor(&method.proto))
+            }
+            DalvikReference::Proto(proto) => write!(f, "{}", proto_to_descriptor(proto)),
+            DalvikReference::CallSite(index) => write!(f, "call_site_{}", index),
+            DalvikReference::MethodHandle(index) => write!(f, "method_handle_{}", index),
+        }
+    }
+}
+
+impl fmt::Display for DalvikInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(payload) = &self.payload {
+            return match payload {
+                DalvikPayload::PackedSwitch { first_key, targets } => {
+                    write!(f, "packed-switch-payload {} {:?}", first_key, targets)
+                }
+                DalvikPayload::SparseSwitch { keys, targets } => write!(f, "sparse-switch-payload {:?} {:?}", keys, targets),
+                DalvikPayload::FillArrayData { element_width, data } => {
+                    write!(f, "array-data-payload width={} count={}", element_width, data.len() / (*element_width).max(1) as usize)
+                }
+            };
+        }
+        let mut operands = Vec::new();
+        let range = matches!(self.opcode.format(), InstructionFormat::F3rc | InstructionFormat::F4rcc);
+        if matches!(self.opcode.format(), InstructionFormat::F35c | InstructionFormat::F45cc) || range {
+            let list = match (range, self.registers.first(), self.registers.last()) {
+                (true, Some(first), Some(last)) => format!("v{} .. v{}", first, last),
+                _ => self.registers.iter().map(|r| format!("v{}", r)).collect::<Vec<_>>().join(", "),
+            };
+            operands.push(format!("{{{}}}", list));
+        } else {
+            operands.extend(self.registers.iter().map(|r| format!("v{}", r)));
+        }
+        if let Some(literal) = self.literal {
+            operands.push(if literal < 0 { format!("-{:#x}", literal.unsigned_abs()) } else { format!("{:#x}", literal) });
+        }
+        if let Some(target) = self.target {
+            operands.push(format!(":addr_{:x}", target));
+        }
+        if let Some(reference) = &self.reference {
+            operands.push(reference.to_string());
+        }
+        if let Some(proto) = &self.proto {
+            operands.push(proto_to_descriptor(proto));
+        }
+        if operands.is_empty() {
+            write!(f, "{}", self.opcode.name())
+        } else {
+            write!(f, "{} {}", self.opcode.name(), operands.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+
+    const ALL_FORMATS: &str = r#"
+.class public LAll;
+.super Ljava/lang/Object;
+
+.field public f:I
+
+.method public static five(IIIII)V
+    .registers 5
+    return-void
+.end method
+
+.method public static all()V
+    .registers 20
+    :top
+    nop
+    move v0, v1
+    const/4 v2, -0x3
+    move-result v3
+    goto :a
+    :a
+    goto/16 :b
+    :b
+    move/from16 v4, v17
+    if-eqz v4, :top
+    const/16 v5, -0x100
+    const/high16 v6, 0x7f000000
+    const-wide/high16 v6, 0x4000000000000000L
+    const-string v7, "hi"
+    add-int v8, v9, v10
+    add-int/lit8 v8, v9, -0x2
+    if-eq v0, v1, :a
+    add-int/lit16 v0, v1, 0x1234
+    iget v0, v1, LAll;->f:I
+    goto/32 :top
+    move/16 v16, v17
+    const v0, 0x12345678
+    packed-switch v0, :packed
+    const-string/jumbo v0, "hi"
+    invoke-static {v0, v1, v2, v3, v4}, LAll;->five(IIIII)V
+    invoke-static/range {v10 .. v14}, LAll;->five(IIIII)V
+    filled-new-array {v0, v1}, [I
+    const-wide v0, 0x123456789abcdef0L
+    fill-array-data v0, :array
+    sparse-switch v0, :sparse
+    return-void
+    :packed
+    .packed-switch 0x5
+        :a
+        :b
+    .end packed-switch
+    :sparse
+    .sparse-switch
+        -0x1 -> :a
+        0x64 -> :b
+    .end sparse-switch
+    :array
+    .array-data 2
+        0x1s
+        -0x2s
+        0x3s
+    .end array-data
+.end method
+"#;
+
+    fn decode_all(dex_file: &DexFile) -> Vec<DalvikInstruction> {
+        let method = dex_file.classes[0].direct_methods.iter().find(|m| m.name == "all").expect("all()");
+        DalvikOpcodeAnalyzer::new().analyze_method(method.code.as_ref().expect("code"), dex_file).expect("decode")
+    }
+
+    #[test]
+    fn every_instruction_format_is_decoded() {
+        let dex_file = dex_file(&[ALL_FORMATS]);
+        let decoded: Vec<_> = decode_all(&dex_file)
+            .iter()
+            .filter(|instruction| instruction.payload.is_none())
+            .map(|i| (i.address, i.opcode.name(), i.registers.clone(), i.literal, i.target, i.size))
+            .collect();
+        let expected = vec![
+            (0, "nop", vec![], None, None, 1),
+            (1, "move", vec![0, 1], None, None, 1),
+            (2, "const/4", vec![2], Some(-3), None, 1),
+            (3, "move-result", vec![3], None, None, 1),
+            (4, "goto", vec![], None, Some(5), 1),
+            (5, "goto/16", vec![], None, Some(7), 2),
+            (7, "move/from16", vec![4, 17], None, None, 2),
+            (9, "if-eqz", vec![4], None, Some(0), 2),
+            (11, "const/16", vec![5], Some(-0x100), None, 2),
+            (13, "const/high16", vec![6], Some(0x7f000000), None, 2),
+            (15, "const-wide/high16", vec![6], Some(0x4000000000000000), None, 2),
+            (17, "const-string", vec![7], None, None, 2),
+            (19, "add-int", vec![8, 9, 10], None, None, 2),
+            (21, "add-int/lit8", vec![8, 9], Some(-2), None, 2),
+            (23, "if-eq", vec![0, 1], None, Some(5), 2),
+            (25, "add-int/lit16", vec![0, 1], Some(0x1234), None, 2),
+            (27, "iget", vec![0, 1], None, None, 2),
+            (29, "goto/32", vec![], None, Some(0), 3),
+            (32, "move/16", vec![16, 17], None, None, 3),
+            (35, "const", vec![0], Some(0x12345678), None, 3),
+            (38, "packed-switch", vec![0], None, Some(66), 3),
+            (41, "const-string/jumbo", vec![0], None, None, 3),
+            (44, "invoke-static", vec![0, 1, 2, 3, 4], None, None, 3),
+            (47, "invoke-static/range", vec![10, 11, 12, 13, 14], None, None, 3),
+            (50, "filled-new-array", vec![0, 1], None, None, 3),
+            (53, "const-wide", vec![0], Some(0x123456789abcdef0), None, 5),
+            (58, "fill-array-data", vec![0], None, Some(84), 3),
+            (61, "sparse-switch", vec![0], None, Some(74), 3),
+            (64, "return-void", vec![], None, None, 1),
+            // 负载按 4 字节对齐前的填充
+            (65, "nop", vec![], None, None, 1),
+        ];
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn references_are_resolved_against_the_dex_file() {
+        let dex_file = dex_file(&[ALL_FORMATS]);
+        let references: Vec<(u32, String)> = decode_all(&dex_file)
+            .iter()
+            .filter_map(|i| Some((i.address, i.reference.as_ref()?.to_string())))
+            .collect();
+        assert_eq!(
+            references,
+            [
+                (17, "\"hi\"".to_string()),
+                (27, "LAll;->f:I".to_string()),
+                (41, "\"hi\"".to_string()),
+                (44, "LAll;->five(IIIII)V".to_string()),
+                (47, "LAll;->five(IIIII)V".to_string()),
+                (50, "[I".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn payloads_are_decoded_and_switch_targets_resolved() {
+        let dex_file = dex_file(&[ALL_FORMATS]);
+        let instructions = decode_all(&dex_file);
+        let at = |address: u32| instructions.iter().find(|i| i.address == address).unwrap();
+
+        assert!(matches!(&at(66).payload, Some(DalvikPayload::PackedSwitch { first_key: 5, targets }) if *targets == [-33, -31]));
+        assert_eq!(at(66).size, 8);
+        assert_eq!(at(38).switch_cases(&instructions), [(5, 5), (6, 7)]);
+        assert_eq!(at(74).size, 10);
+        assert_eq!(at(61).switch_cases(&instructions), [(-1, 5), (100, 7)]);
+
+        let array = payload_at(&instructions, 84).expect("fill-array-data payload");
+        assert_eq!(array.size(), 7);
+        assert_eq!(array.elements(), [1, -2, 3]);
+    }
+
+    #[test]
+    fn polymorphic_invokes_carry_the_call_site_proto() {
+        let dex_file = dex_file(&[ALL_FORMATS]);
+        let method = dex_file.methods.iter().position(|m| m.name == "five").unwrap() as u16;
+        let proto = dex_file.protos.iter().position(|p| p.shorty == "V").unwrap() as u16;
+        let analyzer = DalvikOpcodeAnalyzer::new();
+
+        // invoke-polymorphic {v1, v2}, five, ()V
+        let instruction = analyzer.decode_instruction(&[0x20fa, method, 0x0021, proto], 0, &dex_file).unwrap();
+        assert_eq!((instruction.registers.as_slice(), instruction.size), ([1, 2].as_slice(), 4));
+        assert_eq!(instruction.proto.map(|p| p.shorty), Some("V".to_string()));
+        // invoke-polymorphic/range {v3 .. v5}, five, ()V
+        let instruction = analyzer.decode_instruction(&[0x03fb, method, 3, proto], 0, &dex_file).unwrap();
+        assert_eq!(instruction.registers, [3, 4, 5]);
+        assert!(instruction.proto.is_some());
+    }
+
+    #[test]
+    fn truncated_and_invalid_instructions_are_errors() {
+        let dex_file = dex_file(&[ALL_FORMATS]);
+        let analyzer = DalvikOpcodeAnalyzer::new();
+        let invalid: [&[u16]; 5] = [
+            // const-wide 只有四个码元
+            &[0x0018, 1, 2, 3],
+            // invoke-static 参数个数为 6
+            &[0x6071, 0, 0],
+            // packed-switch 负载缺少目标
+            &[0x0100, 2, 0, 0, 1, 0],
+            // fill-array-data 负载缺少数据
+            &[0x0300, 4, 2, 0, 0, 0],
+            // 未使用的操作码
+            &[0x003e],
+        ];
+        for insns in invalid {
+            assert!(analyzer.decode_instruction(insns, 0, &dex_file).is_err(), "{:04x?}", insns);
+        }
     }
 }
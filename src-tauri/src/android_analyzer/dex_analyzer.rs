@@ -1,7 +1,152 @@
+use std::collections::HashMap;
 use std::io::{Read, Seek};
 use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
 use crate::android_analyzer::dex_reader::DexReader;
-use crate::android::{DexFile, TypeDescriptor, ProtoDescriptor, FieldDescriptor, MethodDescriptor, ClassDef};
+use crate::android::{
+    Annotation, AnnotationElement, AnnotationVisibility, CallSite, ClassData, ClassDef, CodeItem, DebugInfo, DebugPosition, DexFile,
+    DexSection, EncodedCatchHandler, EncodedField, EncodedMethod, EncodedTypeAddrPair, EncodedValue, Field, FieldDescriptor, LocalVariable,
+    Method, MethodDescriptor, MethodHandle, MethodHandleMember, MethodHandleType, ProtoDescriptor, TryItem, TypeDescriptor,
+};
+
+const NO_INDEX: u32 = 0xFFFFFFFF;
+const ENDIAN_CONSTANT: u32 = 0x12345678;
+const HEADER_SIZE: u32 = 0x70;
+const CONTAINER_HEADER_SIZE: u32 = 0x78;
+
+const MIN_DEX_VERSION: u32 = 35;
+const MAX_DEX_VERSION: u32 = 41;
+/// First version with `call_site_ids` and `method_handles`
+const METHOD_HANDLE_VERSION: u32 = 38;
+/// First version whose files may be containers of several DEX files
+const CONTAINER_VERSION: u32 = 41;
+const MAX_PREALLOCATION: u32 = 0x10000;
+/// Deepest nesting of encoded arrays and annotations, far above anything d8 emits
+const MAX_ENCODED_DEPTH: u32 = 64;
+
+// map_list 中的段类型
+pub(crate) const TYPE_HEADER_ITEM: u16 = 0x0000;
+pub(crate) const TYPE_STRING_ID_ITEM: u16 = 0x0001;
+pub(crate) const TYPE_TYPE_ID_ITEM: u16 = 0x0002;
+pub(crate) const TYPE_PROTO_ID_ITEM: u16 = 0x0003;
+pub(crate) const TYPE_FIELD_ID_ITEM: u16 = 0x0004;
+pub(crate) const TYPE_METHOD_ID_ITEM: u16 = 0x0005;
+pub(crate) const TYPE_CLASS_DEF_ITEM: u16 = 0x0006;
+pub(crate) const TYPE_CALL_SITE_ID_ITEM: u16 = 0x0007;
+pub(crate) const TYPE_METHOD_HANDLE_ITEM: u16 = 0x0008;
+pub(crate) const TYPE_MAP_LIST: u16 = 0x1000;
+pub(crate) const TYPE_TYPE_LIST: u16 = 0x1001;
+pub(crate) const TYPE_ANNOTATION_SET_REF_LIST: u16 = 0x1002;
+pub(crate) const TYPE_ANNOTATION_SET_ITEM: u16 = 0x1003;
+pub(crate) const TYPE_CLASS_DATA_ITEM: u16 = 0x2000;
+pub(crate) const TYPE_CODE_ITEM: u16 = 0x2001;
+pub(crate) const TYPE_STRING_DATA_ITEM: u16 = 0x2002;
+pub(crate) const TYPE_DEBUG_INFO_ITEM: u16 = 0x2003;
+pub(crate) const TYPE_ANNOTATION_ITEM: u16 = 0x2004;
+pub(crate) const TYPE_ENCODED_ARRAY_ITEM: u16 = 0x2005;
+pub(crate) const TYPE_ANNOTATIONS_DIRECTORY_ITEM: u16 = 0x2006;
+pub(crate) const TYPE_HIDDENAPI_CLASS_DATA_ITEM: u16 = 0xF000;
+
+/// Item type name of a map_list entry, None for unknown types
+pub fn section_name(type_code: u16) -> Option<&'static str> {
+    Some(match type_code {
+        TYPE_HEADER_ITEM => "header_item",
+        TYPE_STRING_ID_ITEM => "string_id_item",
+        TYPE_TYPE_ID_ITEM => "type_id_item",
+        TYPE_PROTO_ID_ITEM => "proto_id_item",
+        TYPE_FIELD_ID_ITEM => "field_id_item",
+        TYPE_METHOD_ID_ITEM => "method_id_item",
+        TYPE_CLASS_DEF_ITEM => "class_def_item",
+        TYPE_CALL_SITE_ID_ITEM => "call_site_id_item",
+        TYPE_METHOD_HANDLE_ITEM => "method_handle_item",
+        TYPE_MAP_LIST => "map_list",
+        TYPE_TYPE_LIST => "type_list",
+        TYPE_ANNOTATION_SET_REF_LIST => "annotation_set_ref_list",
+        TYPE_ANNOTATION_SET_ITEM => "annotation_set_item",
+        TYPE_CLASS_DATA_ITEM => "class_data_item",
+        TYPE_CODE_ITEM => "code_item",
+        TYPE_STRING_DATA_ITEM => "string_data_item",
+        TYPE_DEBUG_INFO_ITEM => "debug_info_item",
+        TYPE_ANNOTATION_ITEM => "annotation_item",
+        TYPE_ENCODED_ARRAY_ITEM => "encoded_array_item",
+        TYPE_ANNOTATIONS_DIRECTORY_ITEM => "annotations_directory_item",
+        TYPE_HIDDENAPI_CLASS_DATA_ITEM => "hiddenapi_class_data_item",
+        _ => return None,
+    })
+}
+
+/// `dex\n039\0` -> 39; None for other magics and unsupported versions
+pub fn parse_dex_version(magic: &[u8; 8]) -> Option<u32> {
+    if &magic[..4] != b"dex\n" || magic[7] != 0 {
+        return None;
+    }
+    let version: u32 = std::str::from_utf8(&magic[4..7]).ok()?.parse().ok()?;
+    (MIN_DEX_VERSION..=MAX_DEX_VERSION).contains(&version).then_some(version)
+}
+
+/// 计数来自文件内容，预分配设上限，被篡改的计数只会在读取时报错而不会耗尽内存
+fn preallocation(count: u32) -> usize {
+    count.min(MAX_PREALLOCATION) as usize
+}
+
+fn find_section(sections: &[DexSection], type_code: u16) -> Option<&DexSection> {
+    sections.iter().find(|section| section.type_code == type_code)
+}
+
+/// The id tables every later section refers to
+struct DexIds<'a> {
+    strings: &'a [String],
+    types: &'a [TypeDescriptor],
+    protos: &'a [ProtoDescriptor],
+    fields: &'a [FieldDescriptor],
+    methods: &'a [MethodDescriptor],
+}
+
+impl<'a> DexIds<'a> {
+    fn of(dex_file: &'a DexFile) -> Self {
+        Self {
+            strings: &dex_file.strings,
+            types: &dex_file.types,
+            protos: &dex_file.protos,
+            fields: &dex_file.fields,
+            methods: &dex_file.methods,
+        }
+    }
+}
+
+/// Header, map_list and class names of one DEX, read without decoding the id tables or class data
+/// A parsed map_list: the sections it lists and the entries skipped while reading it
+pub struct MapList {
+    pub sections: Vec<DexSection>,
+    /// File offset of each skipped entry and why it was skipped
+    pub skipped: Vec<(u32, String)>,
+}
+
+pub(crate) struct DexLayout {
+    pub(crate) version: u32,
+    pub(crate) file_size: u32,
+    pub(crate) container_size: u32,
+    pub(crate) class_defs_offset: u32,
+    pub(crate) sections: Vec<DexSection>,
+    /// Descriptors of the classes in `class_defs` order
+    pub(crate) class_names: Vec<String>,
+}
+
+fn lookup<'a, T>(items: &'a [T], index: u32, what: &str) -> Result<&'a T> {
+    items
+        .get(index as usize)
+        .ok_or_else(|| AndroidAnalyzeError::InvalidDexFile(format!("Invalid {} index {}", what, index)))
+}
+
+/// 静态字段、实例字段、direct 方法、virtual 方法
+type ClassMembers = (Vec<Field>, Vec<Field>, Vec<Method>, Vec<Method>);
+
+/// 字段、方法和参数上的注解，按 field_idx / method_idx 索引
+#[derive(Default)]
+struct MemberAnnotations {
+    fields: HashMap<u32, Vec<Annotation>>,
+    methods: HashMap<u32, Vec<Annotation>>,
+    parameters: HashMap<u32, Vec<Vec<Annotation>>>,
+}
 
 /// DEX file analyzer for parsing Android DEX files
 pub struct DexAnalyzer<R: Read + Seek> {
@@ -17,14 +162,108 @@ impl<R: Read + Seek> DexAnalyzer<R> {
 
     /// Analyze the DEX file and return the parsed structure
     pub fn analyze(&mut self) -> Result<DexFile> {
+        self.analyze_at(0)
+    }
+
+    /// Analyze every DEX file of a version 041 container; older files hold exactly one
+    pub fn analyze_container(&mut self) -> Result<Vec<DexFile>> {
+        let mut dex_files = vec![self.analyze_at(0)?];
+        loop {
+            let last = &dex_files[dex_files.len() - 1];
+            let next = last.header_offset as u64 + last.file_size as u64;
+            if last.container_size == 0 || next >= last.container_size as u64 {
+                break;
+            }
+            dex_files.push(self.analyze_at(next)?);
+        }
+        Ok(dex_files)
+    }
+
+    /// Read only the header and the map_list, for tools that show the file layout
+    pub fn read_sections(&mut self) -> Result<Vec<DexSection>> {
+        Ok(self.read_map(0)?.sections)
+    }
+
+    /// Read the header at `header_offset` and its map_list, keeping the entries that were skipped
+    pub fn read_map(&mut self, header_offset: u64) -> Result<MapList> {
+        let header = self.read_dex_header(header_offset)?;
+        self.read_map_list(&header)
+    }
+
+    /// Read the header, the map_list and the descriptor of every class; the cheap first pass of lazy loading
+    pub(crate) fn read_layout(&mut self, header_offset: u64) -> Result<DexLayout> {
+        let header = self.read_dex_header(header_offset)?;
+        let sections = self.read_map_list(&header)?.sections;
+        self.check_table(&header, header.string_ids_offset, header.string_ids_size, 4, "string_ids")?;
+        self.check_table(&header, header.type_ids_offset, header.type_ids_size, 4, "type_ids")?;
+        self.check_table(&header, header.class_defs_offset, header.class_defs_size, 32, "class_defs")?;
+        let mut class_names = Vec::with_capacity(preallocation(header.class_defs_size));
+        for index in 0..header.class_defs_size {
+            self.reader.seek(header.class_defs_offset as u64 + index as u64 * 32)?;
+            let class_idx = self.reader.read_u32()?;
+            class_names.push(self.read_type_descriptor(&header, class_idx)?);
+        }
+        Ok(DexLayout {
+            version: header.version,
+            file_size: header.file_size,
+            container_size: header.container_size,
+            class_defs_offset: header.class_defs_offset,
+            sections,
+            class_names,
+        })
+    }
+
+    /// 只解码一个类型的描述符，不读取整个字符串表
+    fn read_type_descriptor(&mut self, header: &DexHeader, type_idx: u32) -> Result<String> {
+        if type_idx >= header.type_ids_size {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Invalid class type index {}", type_idx)));
+        }
+        self.reader.seek(header.type_ids_offset as u64 + type_idx as u64 * 4)?;
+        let descriptor_idx = self.reader.read_u32()?;
+        if descriptor_idx >= header.string_ids_size {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Invalid type descriptor string index {}", descriptor_idx)));
+        }
+        self.reader.seek(header.string_ids_offset as u64 + descriptor_idx as u64 * 4)?;
+        let string_data_offset = self.reader.read_u32()?;
+        self.reader.seek(string_data_offset as u64)?;
+        self.read_mutf8_string()
+    }
+
+    /// Parse the DEX file whose header starts at `header_offset`
+    fn analyze_at(&mut self, header_offset: u64) -> Result<DexFile> {
+        let mut dex_file = self.read_ids(header_offset)?;
+        let mut classes = Vec::with_capacity(preallocation(dex_file.class_defs_size));
+        for index in 0..dex_file.class_defs_size {
+            classes.push(self.read_class(&dex_file, index)?);
+        }
+        dex_file.classes = classes;
+        Ok(dex_file)
+    }
+
+    /// Parse the header and the id tables of the DEX at `header_offset`, leaving `classes` empty for `read_class`
+    pub fn read_ids(&mut self, header_offset: u64) -> Result<DexFile> {
+        self.read_ids_with(header_offset, None, None)
+    }
+
+    /// Same as `read_ids`, taking over string and type tables that were already read
+    pub(crate) fn read_ids_with(&mut self, header_offset: u64, strings: Option<Vec<String>>, types: Option<Vec<TypeDescriptor>>) -> Result<DexFile> {
         // Read DEX header
-        let header = self.read_dex_header()?;
+        let header = self.read_dex_header(header_offset)?;
+
+        // Read and validate the map_list
+        let sections = self.read_map_list(&header)?.sections;
         
         // Read string table
-        let strings = self.read_string_table(&header)?;
+        let strings = match strings {
+            Some(strings) => strings,
+            None => self.read_string_table(&header)?,
+        };
         
         // Read type table
-        let types = self.read_type_table(&header, &strings)?;
+        let types = match types {
+            Some(types) => types,
+            None => self.read_type_table(&header, &strings)?,
+        };
         
         // Read proto table
         let protos = self.read_proto_table(&header, &strings, &types)?;
@@ -34,9 +273,18 @@ impl<R: Read + Seek> DexAnalyzer<R> {
         
         // Read method table
         let methods = self.read_method_table(&header, &strings, &types, &protos)?;
-        
-        // Read class definitions
-        let classes = self.read_class_definitions(&header, &strings, &types, &fields, &methods)?;
+
+        // Method handles and call sites (version 038 and later) are only listed in the map_list
+        let method_handles = self.read_method_handles(find_section(&sections, TYPE_METHOD_HANDLE_ITEM), &fields, &methods)?;
+        let ids = DexIds {
+            strings: &strings,
+            types: &types,
+            protos: &protos,
+            fields: &fields,
+            methods: &methods,
+        };
+        let call_sites = self.read_call_sites(find_section(&sections, TYPE_CALL_SITE_ID_ITEM), &ids)?;
+        self.check_table(&header, header.class_defs_offset, header.class_defs_size, 32, "class_defs")?;
 
         Ok(DexFile {
             magic: header.magic,
@@ -62,24 +310,89 @@ impl<R: Read + Seek> DexAnalyzer<R> {
             class_defs_offset: header.class_defs_offset,
             data_size: header.data_size,
             data_offset: header.data_offset,
+            container_size: header.container_size,
+            header_offset: header.header_offset,
+            sections,
             strings,
             types,
             protos,
             fields,
             methods,
-            classes,
+            method_handles,
+            call_sites,
+            classes: Vec::new(),
         })
     }
 
+    /// Decode the `index`-th class definition against the id tables of `dex_file`, as returned by `read_ids`
+    pub fn read_class(&mut self, dex_file: &DexFile, index: u32) -> Result<ClassDef> {
+        if index >= dex_file.class_defs_size {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Invalid class_def index {}", index)));
+        }
+        let mut class_def = self.read_class_definition(dex_file.class_defs_offset, index, &DexIds::of(dex_file))?;
+        if let Some(section) = find_section(&dex_file.sections, TYPE_HIDDENAPI_CLASS_DATA_ITEM) {
+            self.read_hiddenapi_class_data(section, index, &mut class_def)?;
+        }
+        Ok(class_def)
+    }
+
+    /// Read only the string table of the DEX at `header_offset`
+    pub(crate) fn read_strings(&mut self, header_offset: u64) -> Result<Vec<String>> {
+        let header = self.read_dex_header(header_offset)?;
+        self.read_string_table(&header)
+    }
+
+    /// Read only the type table, decoding each descriptor on its own instead of the whole string table
+    pub(crate) fn read_types(&mut self, header_offset: u64) -> Result<Vec<TypeDescriptor>> {
+        let header = self.read_dex_header(header_offset)?;
+        self.check_table(&header, header.string_ids_offset, header.string_ids_size, 4, "string_ids")?;
+        self.check_table(&header, header.type_ids_offset, header.type_ids_size, 4, "type_ids")?;
+        let mut types = Vec::with_capacity(preallocation(header.type_ids_size));
+        for type_idx in 0..header.type_ids_size {
+            let descriptor = self.read_type_descriptor(&header, type_idx)?;
+            types.push(TypeDescriptor::from_descriptor(&descriptor)?);
+        }
+        Ok(types)
+    }
+
+    /// Read only the superclass and interfaces of the `index`-th class, without its members or code
+    pub fn read_supertypes(
+        &mut self,
+        class_defs_offset: u32,
+        class_defs_size: u32,
+        types: &[TypeDescriptor],
+        index: u32,
+    ) -> Result<(Option<TypeDescriptor>, Vec<TypeDescriptor>)> {
+        if index >= class_defs_size {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Invalid class_def index {}", index)));
+        }
+        // class_def_item 中 superclass_idx 和 interfaces_off 位于第 8 字节起
+        self.reader.seek(class_defs_offset as u64 + index as u64 * 32 + 8)?;
+        let superclass_idx = self.reader.read_u32()?;
+        let interfaces_off = self.reader.read_u32()?;
+        let super_type = if superclass_idx != NO_INDEX {
+            Some(lookup(types, superclass_idx, "superclass type")?.clone())
+        } else {
+            None
+        };
+        let interfaces = if interfaces_off != 0 {
+            self.read_type_list(interfaces_off, types)?
+        } else {
+            Vec::new()
+        };
+        Ok((super_type, interfaces))
+    }
+
     /// Read the DEX file header
-    fn read_dex_header(&mut self) -> Result<DexHeader> {
+    fn read_dex_header(&mut self, header_offset: u64) -> Result<DexHeader> {
+        let stream_len = self.reader.stream_len()?;
+        self.reader.seek(header_offset)?;
         let mut magic = [0u8; 8];
         self.reader.read_exact(&mut magic)?;
         
         // Validate DEX magic
-        if &magic != b"dex\n035\0" && &magic != b"dex\n036\0" && &magic != b"dex\n037\0" {
-            return Err(AndroidAnalyzeError::InvalidDexFile("Invalid DEX magic".to_string()));
-        }
+        let version = parse_dex_version(&magic)
+            .ok_or_else(|| AndroidAnalyzeError::InvalidDexFile(format!("Unsupported DEX magic {:?}", String::from_utf8_lossy(&magic))))?;
 
         let checksum = self.reader.read_u32()?;
         
@@ -107,8 +420,32 @@ impl<R: Read + Seek> DexAnalyzer<R> {
         let data_size = self.reader.read_u32()?;
         let data_offset = self.reader.read_u32()?;
 
+        // 041 起文件头增加了容器大小和本文件头在容器中的偏移
+        let (container_size, container_header_offset) = if version >= CONTAINER_VERSION {
+            if header_size < CONTAINER_HEADER_SIZE {
+                return Err(AndroidAnalyzeError::InvalidDexFile(format!("Header size 0x{:x} is too small for version {:03}", header_size, version)));
+            }
+            (self.reader.read_u32()?, self.reader.read_u32()?)
+        } else {
+            (0, 0)
+        };
+
+        if endian_tag != ENDIAN_CONSTANT {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Unsupported endian tag 0x{:08x}", endian_tag)));
+        }
+        if header_size < HEADER_SIZE || file_size < header_size {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Invalid header size 0x{:x} or file size 0x{:x}", header_size, file_size)));
+        }
+        if container_header_offset as u64 != header_offset {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Header at 0x{:x} claims offset 0x{:x}", header_offset, container_header_offset)));
+        }
+        if header_offset + file_size as u64 > stream_len {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("File size 0x{:x} exceeds the 0x{:x} bytes available", file_size, stream_len)));
+        }
+
         Ok(DexHeader {
             magic,
+            version,
             checksum,
             signature,
             file_size,
@@ -131,12 +468,106 @@ impl<R: Read + Seek> DexAnalyzer<R> {
             class_defs_offset,
             data_size,
             data_offset,
+            container_size,
+            header_offset: container_header_offset,
         })
     }
 
+    /// Read the map_list and check it against the header; sizes are derived from the next section's offset
+    fn read_map_list(&mut self, header: &DexHeader) -> Result<MapList> {
+        let invalid = |message: String| AndroidAnalyzeError::InvalidDexFile(message);
+        // 041 容器中各偏移相对于容器起始位置
+        let start = header.header_offset as u64;
+        let end = start + header.file_size as u64;
+        let map_offset = header.map_offset as u64;
+        if !map_offset.is_multiple_of(4) || map_offset < start + header.header_size as u64 || map_offset + 4 > end {
+            return Err(invalid(format!("Invalid map_list offset 0x{:x}", map_offset)));
+        }
+        self.reader.seek(map_offset)?;
+        let size = self.reader.read_u32()?;
+        if map_offset + 4 + size as u64 * 12 > end {
+            return Err(invalid(format!("map_list with {} entries extends past the end of the file", size)));
+        }
+
+        let mut sections: Vec<DexSection> = Vec::with_capacity(size as usize);
+        let mut skipped = Vec::new();
+        for index in 0..size {
+            let type_code = self.reader.read_u16()?;
+            let _unused = self.reader.read_u16()?;
+            let count = self.reader.read_u32()?;
+            let offset = self.reader.read_u32()?;
+            // 运行时只认识已知的类型，未知或重复的条目跳过，交给校验器报告
+            let entry = map_offset as u32 + 4 + index * 12;
+            let Some(name) = section_name(type_code) else {
+                skipped.push((entry, format!("Unknown map_list item type 0x{:04x}", type_code)));
+                continue;
+            };
+            if sections.iter().any(|section| section.type_code == type_code) {
+                skipped.push((entry, format!("Duplicate map_list entry for {}", name)));
+                continue;
+            }
+            if let Some(previous) = sections.last() {
+                if offset <= previous.offset {
+                    return Err(invalid(format!("map_list entry {} at 0x{:x} is not sorted by offset", name, offset)));
+                }
+            }
+            if offset as u64 >= end {
+                return Err(invalid(format!("Section {} at 0x{:x} starts past the end of the file", name, offset)));
+            }
+            sections.push(DexSection {
+                type_code,
+                name: name.to_string(),
+                offset,
+                count,
+                size: 0,
+            });
+        }
+        let ends: Vec<u32> = sections.iter().skip(1).map(|section| section.offset).chain(std::iter::once(end as u32)).collect();
+        for (section, end) in sections.iter_mut().zip(ends) {
+            section.size = end - section.offset;
+        }
+
+        // 文件头中的各个表必须与 map_list 一致
+        for (type_code, count, offset) in [
+            (TYPE_HEADER_ITEM, 1, header.header_offset),
+            (TYPE_STRING_ID_ITEM, header.string_ids_size, header.string_ids_offset),
+            (TYPE_TYPE_ID_ITEM, header.type_ids_size, header.type_ids_offset),
+            (TYPE_PROTO_ID_ITEM, header.proto_ids_size, header.proto_ids_offset),
+            (TYPE_FIELD_ID_ITEM, header.field_ids_size, header.field_ids_offset),
+            (TYPE_METHOD_ID_ITEM, header.method_ids_size, header.method_ids_offset),
+            (TYPE_CLASS_DEF_ITEM, header.class_defs_size, header.class_defs_offset),
+            (TYPE_MAP_LIST, 1, header.map_offset),
+        ] {
+            let consistent = match find_section(&sections, type_code) {
+                Some(section) => section.count == count && section.offset == offset,
+                None => count == 0,
+            };
+            if !consistent {
+                return Err(invalid(format!("map_list disagrees with the header for {}", section_name(type_code).unwrap_or_default())));
+            }
+        }
+        if (header.link_size == 0) != (header.link_offset == 0) || header.link_offset as u64 + header.link_size as u64 > end {
+            return Err(invalid(format!("Invalid link section 0x{:x}+0x{:x}", header.link_offset, header.link_size)));
+        }
+        if header.version < METHOD_HANDLE_VERSION {
+            for type_code in [TYPE_CALL_SITE_ID_ITEM, TYPE_METHOD_HANDLE_ITEM] {
+                if find_section(&sections, type_code).is_some() {
+                    return Err(invalid(format!(
+                        "{} requires DEX version {:03}, file is {:03}",
+                        section_name(type_code).unwrap_or_default(),
+                        METHOD_HANDLE_VERSION,
+                        header.version
+                    )));
+                }
+            }
+        }
+        Ok(MapList { sections, skipped })
+    }
+
     /// Read the string table
     fn read_string_table(&mut self, header: &DexHeader) -> Result<Vec<String>> {
-        let mut strings = Vec::new();
+        self.check_table(header, header.string_ids_offset, header.string_ids_size, 4, "string_ids")?;
+        let mut strings = Vec::with_capacity(preallocation(header.string_ids_size));
         
         // Seek to string IDs
         self.reader.seek(header.string_ids_offset as u64)?;
@@ -159,29 +590,39 @@ impl<R: Read + Seek> DexAnalyzer<R> {
 
     /// Read a Modified UTF-8 string
     fn read_mutf8_string(&mut self) -> Result<String> {
-        let size = self.reader.read_uleb128()?;
-        let mut bytes = vec![0u8; size as usize];
-        self.reader.read_exact(&mut bytes)?;
-        
-        // Convert MUTF-8 to UTF-8
-        String::from_utf8(bytes)
-            .map_err(|e| AndroidAnalyzeError::ParseError(format!("Invalid MUTF-8 string: {}", e)))
+        // 长度是 UTF-16 码元个数而不是字节数，数据以 0 结尾
+        let utf16_size = self.reader.read_uleb128()?;
+        let mut units = Vec::with_capacity(preallocation(utf16_size));
+        loop {
+            let byte = self.reader.read_u8()? as u16;
+            let unit = match byte {
+                0 => break,
+                0x01..=0x7F => byte,
+                0xC0..=0xDF => (byte & 0x1F) << 6 | (self.reader.read_u8()? as u16 & 0x3F),
+                0xE0..=0xEF => {
+                    let second = self.reader.read_u8()? as u16 & 0x3F;
+                    let third = self.reader.read_u8()? as u16 & 0x3F;
+                    (byte & 0x0F) << 12 | second << 6 | third
+                }
+                _ => return Err(AndroidAnalyzeError::ParseError(format!("Invalid MUTF-8 byte 0x{:02x}", byte))),
+            };
+            units.push(unit);
+        }
+        // 补充平面字符以代理对分别编码，单独出现的代理项替换为 U+FFFD
+        Ok(String::from_utf16_lossy(&units))
     }
 
     /// Read the type table
     fn read_type_table(&mut self, header: &DexHeader, strings: &[String]) -> Result<Vec<TypeDescriptor>> {
-        let mut types = Vec::new();
+        self.check_table(header, header.type_ids_offset, header.type_ids_size, 4, "type_ids")?;
+        let mut types = Vec::with_capacity(preallocation(header.type_ids_size));
         
         self.reader.seek(header.type_ids_offset as u64)?;
         
         for _ in 0..header.type_ids_size {
             let descriptor_idx = self.reader.read_u32()?;
-            if descriptor_idx as usize >= strings.len() {
-                return Err(AndroidAnalyzeError::InvalidDexFile("Invalid type descriptor index".to_string()));
-            }
-            
-            let descriptor = strings[descriptor_idx as usize].clone();
-            types.push(TypeDescriptor::from_descriptor(&descriptor)?);
+            let descriptor = lookup(strings, descriptor_idx, "type descriptor string")?;
+            types.push(TypeDescriptor::from_descriptor(descriptor)?);
         }
         
         Ok(types)
@@ -189,7 +630,8 @@ impl<R: Read + Seek> DexAnalyzer<R> {
 
     /// Read the proto table
     fn read_proto_table(&mut self, header: &DexHeader, strings: &[String], types: &[TypeDescriptor]) -> Result<Vec<ProtoDescriptor>> {
-        let mut protos = Vec::new();
+        self.check_table(header, header.proto_ids_offset, header.proto_ids_size, 12, "proto_ids")?;
+        let mut protos = Vec::with_capacity(preallocation(header.proto_ids_size));
         
         self.reader.seek(header.proto_ids_offset as u64)?;
         
@@ -198,12 +640,8 @@ impl<R: Read + Seek> DexAnalyzer<R> {
             let return_type_idx = self.reader.read_u32()?;
             let parameters_off = self.reader.read_u32()?;
             
-            if shorty_idx as usize >= strings.len() || return_type_idx as usize >= types.len() {
-                return Err(AndroidAnalyzeError::InvalidDexFile("Invalid proto descriptor index".to_string()));
-            }
-            
-            let shorty = strings[shorty_idx as usize].clone();
-            let return_type = types[return_type_idx as usize].clone();
+            let shorty = lookup(strings, shorty_idx, "shorty string")?.clone();
+            let return_type = lookup(types, return_type_idx, "return type")?.clone();
             
             let mut parameters = Vec::new();
             if parameters_off > 0 {
@@ -213,10 +651,7 @@ impl<R: Read + Seek> DexAnalyzer<R> {
                 let size = self.reader.read_u32()?;
                 for _ in 0..size {
                     let type_idx = self.reader.read_u16()?;
-                    if type_idx as usize >= types.len() {
-                        return Err(AndroidAnalyzeError::InvalidDexFile("Invalid parameter type index".to_string()));
-                    }
-                    parameters.push(types[type_idx as usize].clone());
+                    parameters.push(lookup(types, type_idx as u32, "parameter type")?.clone());
                 }
                 
                 self.reader.seek(current_pos)?;
@@ -234,7 +669,8 @@ impl<R: Read + Seek> DexAnalyzer<R> {
 
     /// Read the field table
     fn read_field_table(&mut self, header: &DexHeader, strings: &[String], types: &[TypeDescriptor]) -> Result<Vec<FieldDescriptor>> {
-        let mut fields = Vec::new();
+        self.check_table(header, header.field_ids_offset, header.field_ids_size, 8, "field_ids")?;
+        let mut fields = Vec::with_capacity(preallocation(header.field_ids_size));
         
         self.reader.seek(header.field_ids_offset as u64)?;
         
@@ -243,16 +679,10 @@ impl<R: Read + Seek> DexAnalyzer<R> {
             let type_idx = self.reader.read_u16()?;
             let name_idx = self.reader.read_u32()?;
             
-            if class_idx as usize >= types.len() || 
-               type_idx as usize >= types.len() || 
-               name_idx as usize >= strings.len() {
-                return Err(AndroidAnalyzeError::InvalidDexFile("Invalid field descriptor index".to_string()));
-            }
-            
             fields.push(FieldDescriptor {
-                class_type: types[class_idx as usize].clone(),
-                field_type: types[type_idx as usize].clone(),
-                name: strings[name_idx as usize].clone(),
+                class_type: lookup(types, class_idx as u32, "field class type")?.clone(),
+                field_type: lookup(types, type_idx as u32, "field type")?.clone(),
+                name: lookup(strings, name_idx, "field name string")?.clone(),
             });
         }
         
@@ -261,7 +691,8 @@ impl<R: Read + Seek> DexAnalyzer<R> {
 
     /// Read the method table
     fn read_method_table(&mut self, header: &DexHeader, strings: &[String], types: &[TypeDescriptor], protos: &[ProtoDescriptor]) -> Result<Vec<MethodDescriptor>> {
-        let mut methods = Vec::new();
+        self.check_table(header, header.method_ids_offset, header.method_ids_size, 8, "method_ids")?;
+        let mut methods = Vec::with_capacity(preallocation(header.method_ids_size));
         
         self.reader.seek(header.method_ids_offset as u64)?;
         
@@ -270,84 +701,590 @@ impl<R: Read + Seek> DexAnalyzer<R> {
             let proto_idx = self.reader.read_u16()?;
             let name_idx = self.reader.read_u32()?;
             
-            if class_idx as usize >= types.len() || 
-               proto_idx as usize >= protos.len() || 
-               name_idx as usize >= strings.len() {
-                return Err(AndroidAnalyzeError::InvalidDexFile("Invalid method descriptor index".to_string()));
-            }
-            
             methods.push(MethodDescriptor {
-                class_type: types[class_idx as usize].clone(),
-                proto: protos[proto_idx as usize].clone(),
-                name: strings[name_idx as usize].clone(),
+                class_type: lookup(types, class_idx as u32, "method class type")?.clone(),
+                proto: lookup(protos, proto_idx as u32, "proto")?.clone(),
+                name: lookup(strings, name_idx, "method name string")?.clone(),
             });
         }
         
         Ok(methods)
     }
 
-    /// Read class definitions
-    fn read_class_definitions(&mut self, header: &DexHeader, strings: &[String], types: &[TypeDescriptor], _fields: &[FieldDescriptor], _methods: &[MethodDescriptor]) -> Result<Vec<ClassDef>> {
-        let mut classes = Vec::new();
+    /// 表的范围必须落在本 DEX 文件内
+    fn check_table(&self, header: &DexHeader, offset: u32, count: u32, item_size: u64, name: &str) -> Result<()> {
+        let end = header.header_offset as u64 + header.file_size as u64;
+        if count > 0 && offset as u64 + count as u64 * item_size > end {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("{} table at 0x{:x} with {} entries extends past the end of the file", name, offset, count)));
+        }
+        Ok(())
+    }
+
+    /// Read the `method_handles` section
+    fn read_method_handles(&mut self, section: Option<&DexSection>, fields: &[FieldDescriptor], methods: &[MethodDescriptor]) -> Result<Vec<MethodHandle>> {
+        let Some(section) = section else {
+            return Ok(Vec::new());
+        };
+        self.reader.seek(section.offset as u64)?;
+        let mut handles = Vec::with_capacity(preallocation(section.count));
+        for _ in 0..section.count {
+            let handle_type = self.reader.read_u16()?;
+            let _unused = self.reader.read_u16()?;
+            let member_idx = self.reader.read_u16()? as u32;
+            let _unused = self.reader.read_u16()?;
+            let handle_type = MethodHandleType::from_u16(handle_type)
+                .ok_or_else(|| AndroidAnalyzeError::InvalidDexFile(format!("Invalid method handle type {}", handle_type)))?;
+            let member = if handle_type.is_field_access() {
+                MethodHandleMember::Field(lookup(fields, member_idx, "field")?.clone())
+            } else {
+                MethodHandleMember::Method(lookup(methods, member_idx, "method")?.clone())
+            };
+            handles.push(MethodHandle { handle_type, member });
+        }
+        Ok(handles)
+    }
+
+    /// Read the `call_site_ids` section; each call site is an encoded array starting with the bootstrap handle, name and type
+    fn read_call_sites(&mut self, section: Option<&DexSection>, ids: &DexIds) -> Result<Vec<CallSite>> {
+        let Some(section) = section else {
+            return Ok(Vec::new());
+        };
+        let mut call_sites = Vec::with_capacity(preallocation(section.count));
+        for index in 0..section.count {
+            self.reader.seek(section.offset as u64 + index as u64 * 4)?;
+            let call_site_off = self.reader.read_u32()?;
+            self.reader.seek(call_site_off as u64)?;
+            let mut values = self.read_encoded_array(ids, 0)?.into_iter();
+            match (values.next(), values.next(), values.next()) {
+                (Some(EncodedValue::MethodHandle(bootstrap)), Some(EncodedValue::String(method_name)), Some(EncodedValue::MethodType(method_type))) => {
+                    call_sites.push(CallSite {
+                        bootstrap,
+                        method_name,
+                        method_type,
+                        arguments: values.collect(),
+                    });
+                }
+                _ => return Err(AndroidAnalyzeError::InvalidDexFile(format!("Malformed call site {}", index))),
+            }
+        }
+        Ok(call_sites)
+    }
+
+    /// Read one class from the `hiddenapi_class_data` section: one uleb128 of flags for each field and then each method
+    fn read_hiddenapi_class_data(&mut self, section: &DexSection, index: u32, class_def: &mut ClassDef) -> Result<()> {
+        self.reader.seek(section.offset as u64)?;
+        let size = self.reader.read_u32()?;
+        self.reader.seek(section.offset as u64 + 4 + index as u64 * 4)?;
+        let offset = self.reader.read_u32()?;
+        // 偏移为 0 表示该类没有成员需要限制
+        if offset == 0 {
+            return Ok(());
+        }
+        if offset >= size {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Invalid hiddenapi data offset 0x{:x}", offset)));
+        }
+        self.reader.seek(section.offset as u64 + offset as u64)?;
+        for field in class_def.static_fields.iter_mut().chain(class_def.instance_fields.iter_mut()) {
+            field.hiddenapi_flags = Some(self.reader.read_uleb128()?);
+        }
+        for method in class_def.direct_methods.iter_mut().chain(class_def.virtual_methods.iter_mut()) {
+            method.hiddenapi_flags = Some(self.reader.read_uleb128()?);
+        }
+        Ok(())
+    }
+
+    /// Read one entry of the class_defs table
+    fn read_class_definition(&mut self, class_defs_offset: u32, index: u32, ids: &DexIds) -> Result<ClassDef> {
+        self.reader.seek(class_defs_offset as u64 + index as u64 * 32)?;
+        let class_idx = self.reader.read_u32()?;
+        let access_flags = self.reader.read_u32()?;
+        let superclass_idx = self.reader.read_u32()?;
+        let interfaces_off = self.reader.read_u32()?;
+        let source_file_idx = self.reader.read_u32()?;
+        let annotations_off = self.reader.read_u32()?;
+        let class_data_off = self.reader.read_u32()?;
+        let static_values_off = self.reader.read_u32()?;
         
-        self.reader.seek(header.class_defs_offset as u64)?;
+        let class_type = lookup(ids.types, class_idx, "class type")?.clone();
+        let super_type = if superclass_idx != NO_INDEX {
+            Some(lookup(ids.types, superclass_idx, "superclass type")?.clone())
+        } else {
+            None
+        };
         
-        for _ in 0..header.class_defs_size {
-            let class_idx = self.reader.read_u32()?;
-            let access_flags = self.reader.read_u32()?;
-            let superclass_idx = self.reader.read_u32()?;
-            let _interfaces_off = self.reader.read_u32()?;
-            let source_file_idx = self.reader.read_u32()?;
-            let _annotations_off = self.reader.read_u32()?;
-            let _class_data_off = self.reader.read_u32()?;
-            let _static_values_off = self.reader.read_u32()?;
-            
-            if class_idx as usize >= types.len() {
-                return Err(AndroidAnalyzeError::InvalidDexFile("Invalid class type index".to_string()));
+        let source_file = if source_file_idx != NO_INDEX {
+            Some(lookup(ids.strings, source_file_idx, "source file string")?.clone())
+        } else {
+            None
+        };
+        
+        let interfaces = if interfaces_off != 0 {
+            self.read_type_list(interfaces_off, ids.types)?
+        } else {
+            Vec::new()
+        };
+        
+        let (annotations, member_annotations) = if annotations_off != 0 {
+            self.read_annotations_directory(annotations_off, ids)?
+        } else {
+            (Vec::new(), MemberAnnotations::default())
+        };
+        
+        let (mut static_fields, instance_fields, direct_methods, virtual_methods) = if class_data_off != 0 {
+            self.read_class_data(class_data_off, ids, member_annotations)?
+        } else {
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new())
+        };
+        
+        // 静态字段的初始值按 class_data 中的顺序给出，末尾省略的字段取默认值
+        if static_values_off != 0 {
+            self.reader.seek(static_values_off as u64)?;
+            let values = self.read_encoded_array(ids, 0)?;
+            for (field, value) in static_fields.iter_mut().zip(values) {
+                field.value = Some(value);
             }
-            
-            let class_type = types[class_idx as usize].clone();
-            let super_type = if superclass_idx != 0xFFFFFFFF {
-                if superclass_idx as usize >= types.len() {
-                    return Err(AndroidAnalyzeError::InvalidDexFile("Invalid superclass type index".to_string()));
+        }
+        
+        Ok(ClassDef {
+            class_type,
+            access_flags,
+            super_type,
+            interfaces,
+            source_file,
+            annotations,
+            static_fields,
+            instance_fields,
+            direct_methods,
+            virtual_methods,
+        })
+    }
+
+    /// Read a `type_list`
+    fn read_type_list(&mut self, offset: u32, types: &[TypeDescriptor]) -> Result<Vec<TypeDescriptor>> {
+        self.reader.seek(offset as u64)?;
+        let size = self.reader.read_u32()?;
+        let mut list = Vec::with_capacity(preallocation(size));
+        for _ in 0..size {
+            let type_idx = self.reader.read_u16()?;
+            list.push(lookup(types, type_idx as u32, "type")?.clone());
+        }
+        Ok(list)
+    }
+
+    /// Read a `class_data_item`; field and method indices are delta encoded within each list
+    fn read_class_data(
+        &mut self,
+        offset: u32,
+        ids: &DexIds,
+        mut annotations: MemberAnnotations,
+    ) -> Result<ClassMembers> {
+        self.reader.seek(offset as u64)?;
+        let class_data = ClassData {
+            static_fields_size: self.reader.read_uleb128()?,
+            instance_fields_size: self.reader.read_uleb128()?,
+            direct_methods_size: self.reader.read_uleb128()?,
+            virtual_methods_size: self.reader.read_uleb128()?,
+        };
+        
+        let static_fields = self.read_encoded_fields(class_data.static_fields_size)?;
+        let instance_fields = self.read_encoded_fields(class_data.instance_fields_size)?;
+        let mut direct_methods = self.read_encoded_methods(class_data.direct_methods_size)?;
+        let mut virtual_methods = self.read_encoded_methods(class_data.virtual_methods_size)?;
+        // 方法体在数据区的其他位置，读完整个 class_data 之后再跳过去
+        for encoded in direct_methods.iter_mut().chain(virtual_methods.iter_mut()) {
+            if encoded.code_offset != 0 {
+                encoded.code = Some(self.read_code_item(encoded.code_offset, ids)?);
+            }
+        }
+        
+        let mut field = |encoded: EncodedField| -> Result<Field> {
+            let descriptor = lookup(ids.fields, encoded.field_idx, "field")?;
+            Ok(Field {
+                access_flags: encoded.access_flags,
+                name: descriptor.name.clone(),
+                field_type: descriptor.field_type.clone(),
+                value: None,
+                annotations: annotations.fields.remove(&encoded.field_idx).unwrap_or_default(),
+                hiddenapi_flags: None,
+            })
+        };
+        let static_fields = static_fields.into_iter().map(&mut field).collect::<Result<Vec<_>>>()?;
+        let instance_fields = instance_fields.into_iter().map(&mut field).collect::<Result<Vec<_>>>()?;
+        
+        let mut method = |encoded: EncodedMethod| -> Result<Method> {
+            let descriptor = lookup(ids.methods, encoded.method_idx, "method")?;
+            Ok(Method {
+                access_flags: encoded.access_flags,
+                name: descriptor.name.clone(),
+                proto: descriptor.proto.clone(),
+                code: encoded.code,
+                annotations: annotations.methods.remove(&encoded.method_idx).unwrap_or_default(),
+                parameter_annotations: annotations.parameters.remove(&encoded.method_idx).unwrap_or_default(),
+                hiddenapi_flags: None,
+            })
+        };
+        let direct_methods = direct_methods.into_iter().map(&mut method).collect::<Result<Vec<_>>>()?;
+        let virtual_methods = virtual_methods.into_iter().map(&mut method).collect::<Result<Vec<_>>>()?;
+        
+        Ok((static_fields, instance_fields, direct_methods, virtual_methods))
+    }
+
+    fn read_encoded_fields(&mut self, count: u32) -> Result<Vec<EncodedField>> {
+        let mut fields = Vec::with_capacity(preallocation(count));
+        let mut field_idx = 0u32;
+        for _ in 0..count {
+            let field_idx_diff = self.reader.read_uleb128()?;
+            let access_flags = self.reader.read_uleb128()?;
+            field_idx = field_idx.wrapping_add(field_idx_diff);
+            fields.push(EncodedField {
+                field_idx_diff,
+                access_flags,
+                field_idx,
+            });
+        }
+        Ok(fields)
+    }
+
+    fn read_encoded_methods(&mut self, count: u32) -> Result<Vec<EncodedMethod>> {
+        let mut methods = Vec::with_capacity(preallocation(count));
+        let mut method_idx = 0u32;
+        for _ in 0..count {
+            let method_idx_diff = self.reader.read_uleb128()?;
+            let access_flags = self.reader.read_uleb128()?;
+            let code_offset = self.reader.read_uleb128()?;
+            method_idx = method_idx.wrapping_add(method_idx_diff);
+            methods.push(EncodedMethod {
+                method_idx_diff,
+                access_flags,
+                code_offset,
+                method_idx,
+                code: None,
+            });
+        }
+        Ok(methods)
+    }
+
+    /// Read a `code_item` with its try table, handler list and debug info
+    fn read_code_item(&mut self, offset: u32, ids: &DexIds) -> Result<CodeItem> {
+        self.reader.seek(offset as u64)?;
+        let registers_size = self.reader.read_u16()?;
+        let ins_size = self.reader.read_u16()?;
+        let outs_size = self.reader.read_u16()?;
+        let tries_size = self.reader.read_u16()?;
+        let debug_info_offset = self.reader.read_u32()?;
+        let insns_size = self.reader.read_u32()?;
+        let insns = (0..insns_size).map(|_| self.reader.read_u16()).collect::<Result<Vec<_>>>()?;
+        
+        let mut tries = Vec::with_capacity(tries_size as usize);
+        let mut handlers = Vec::new();
+        if tries_size > 0 {
+            // try 表按 4 字节对齐，指令数为奇数时有两字节填充
+            if insns_size % 2 == 1 {
+                self.reader.read_u16()?;
+            }
+            for _ in 0..tries_size {
+                tries.push(TryItem {
+                    start_addr: self.reader.read_u32()?,
+                    insn_count: self.reader.read_u16()?,
+                    handler_offset: self.reader.read_u16()?,
+                });
+            }
+            let list_start = self.reader.stream_position()?;
+            let list_size = self.reader.read_uleb128()?;
+            for _ in 0..list_size {
+                let handler_offset = (self.reader.stream_position()? - list_start) as u16;
+                let size = self.reader.read_sleb128()?;
+                let mut pairs = Vec::with_capacity(preallocation(size.unsigned_abs()));
+                for _ in 0..size.unsigned_abs() {
+                    pairs.push(EncodedTypeAddrPair {
+                        type_idx: self.reader.read_uleb128()?,
+                        addr: self.reader.read_uleb128()?,
+                    });
                 }
-                Some(types[superclass_idx as usize].clone())
-            } else {
-                None
-            };
-            
-            let source_file = if source_file_idx != 0xFFFFFFFF {
-                if source_file_idx as usize >= strings.len() {
-                    return Err(AndroidAnalyzeError::InvalidDexFile("Invalid source file index".to_string()));
+                // size <= 0 时还有一个 catch-all 地址
+                let catch_all_addr = if size <= 0 { Some(self.reader.read_uleb128()?) } else { None };
+                handlers.push(EncodedCatchHandler {
+                    offset: handler_offset,
+                    size,
+                    handlers: pairs,
+                    catch_all_addr,
+                });
+            }
+        }
+        
+        let debug_info = if debug_info_offset != 0 {
+            Some(self.read_debug_info(debug_info_offset, insns_size, ids)?)
+        } else {
+            None
+        };
+        
+        Ok(CodeItem {
+            registers_size,
+            ins_size,
+            outs_size,
+            tries_size,
+            debug_info_offset,
+            insns_size,
+            insns,
+            tries,
+            handlers,
+            debug_info,
+        })
+    }
+
+    /// `uleb128p1` 编码的字符串下标，0 表示没有
+    fn read_optional_string(&mut self, ids: &DexIds) -> Result<Option<String>> {
+        match self.reader.read_uleb128()?.checked_sub(1) {
+            Some(index) => Ok(Some(lookup(ids.strings, index, "string")?.clone())),
+            None => Ok(None),
+        }
+    }
+
+    fn read_optional_type(&mut self, ids: &DexIds) -> Result<Option<TypeDescriptor>> {
+        match self.reader.read_uleb128()?.checked_sub(1) {
+            Some(index) => Ok(Some(lookup(ids.types, index, "type")?.clone())),
+            None => Ok(None),
+        }
+    }
+
+    /// Run the `debug_info_item` state machine
+    fn read_debug_info(&mut self, offset: u32, insns_size: u32, ids: &DexIds) -> Result<DebugInfo> {
+        self.reader.seek(offset as u64)?;
+        let line_start = self.reader.read_uleb128()?;
+        let parameters_size = self.reader.read_uleb128()?;
+        let parameter_names = (0..parameters_size).map(|_| self.read_optional_string(ids)).collect::<Result<Vec<_>>>()?;
+        
+        let mut address = 0u32;
+        let mut line = line_start;
+        let mut source_file = None;
+        let mut positions = Vec::new();
+        let mut locals = Vec::new();
+        let mut prologue_end = Vec::new();
+        let mut epilogue_begin = Vec::new();
+        // 寄存器 -> 正在生效的局部变量；结束的变量留作 RESTART_LOCAL 的模板
+        let mut live: HashMap<u32, LocalVariable> = HashMap::new();
+        let mut ended: HashMap<u32, LocalVariable> = HashMap::new();
+        loop {
+            let opcode = self.reader.read_u8()?;
+            match opcode {
+                0x00 => break,
+                0x01 => address = address.wrapping_add(self.reader.read_uleb128()?),
+                0x02 => line = line.wrapping_add_signed(self.reader.read_sleb128()?),
+                0x03 | 0x04 => {
+                    let register = self.reader.read_uleb128()?;
+                    let name = self.read_optional_string(ids)?;
+                    let local_type = self.read_optional_type(ids)?;
+                    let signature = if opcode == 0x04 { self.read_optional_string(ids)? } else { None };
+                    if let Some(mut previous) = live.remove(&register) {
+                        previous.end_address = address;
+                        locals.push(previous);
+                    }
+                    live.insert(
+                        register,
+                        LocalVariable {
+                            register,
+                            name,
+                            local_type,
+                            signature,
+                            start_address: address,
+                            end_address: insns_size,
+                        },
+                    );
                 }
-                Some(strings[source_file_idx as usize].clone())
-            } else {
-                None
+                0x05 => {
+                    let register = self.reader.read_uleb128()?;
+                    if let Some(mut local) = live.remove(&register) {
+                        local.end_address = address;
+                        ended.insert(register, local.clone());
+                        locals.push(local);
+                    }
+                }
+                0x06 => {
+                    let register = self.reader.read_uleb128()?;
+                    if let Some(local) = ended.get(&register) {
+                        live.insert(
+                            register,
+                            LocalVariable {
+                                start_address: address,
+                                end_address: insns_size,
+                                ..local.clone()
+                            },
+                        );
+                    }
+                }
+                0x07 => prologue_end.push(address),
+                0x08 => epilogue_begin.push(address),
+                0x09 => source_file = self.read_optional_string(ids)?,
+                _ => {
+                    // 特殊操作码同时推进地址和行号，并产生一条位置记录
+                    let adjusted = (opcode - 0x0A) as i32;
+                    line = line.wrapping_add_signed(adjusted % 15 - 4);
+                    address = address.wrapping_add((adjusted / 15) as u32);
+                    positions.push(DebugPosition {
+                        address,
+                        line,
+                        source_file: source_file.clone(),
+                    });
+                }
+            }
+        }
+        locals.extend(live.into_values());
+        locals.sort_by_key(|local| (local.start_address, local.register));
+        
+        Ok(DebugInfo {
+            line_start,
+            parameter_names,
+            positions,
+            locals,
+            prologue_end,
+            epilogue_begin,
+        })
+    }
+
+    /// Read an `annotations_directory_item`: the class annotations plus those of fields, methods and parameters
+    fn read_annotations_directory(&mut self, offset: u32, ids: &DexIds) -> Result<(Vec<Annotation>, MemberAnnotations)> {
+        self.reader.seek(offset as u64)?;
+        let class_annotations_off = self.reader.read_u32()?;
+        let fields_size = self.reader.read_u32()?;
+        let annotated_methods_size = self.reader.read_u32()?;
+        let annotated_parameters_size = self.reader.read_u32()?;
+        let mut read_pairs = |count: u32| -> Result<Vec<(u32, u32)>> {
+            (0..count).map(|_| Ok((self.reader.read_u32()?, self.reader.read_u32()?))).collect()
+        };
+        let field_offsets = read_pairs(fields_size)?;
+        let method_offsets = read_pairs(annotated_methods_size)?;
+        let parameter_offsets = read_pairs(annotated_parameters_size)?;
+        
+        let class_annotations = self.read_annotation_set(class_annotations_off, ids)?;
+        let mut members = MemberAnnotations::default();
+        for (field_idx, set_offset) in field_offsets {
+            members.fields.insert(field_idx, self.read_annotation_set(set_offset, ids)?);
+        }
+        for (method_idx, set_offset) in method_offsets {
+            members.methods.insert(method_idx, self.read_annotation_set(set_offset, ids)?);
+        }
+        for (method_idx, list_offset) in parameter_offsets {
+            self.reader.seek(list_offset as u64)?;
+            let size = self.reader.read_u32()?;
+            let set_offsets = (0..size).map(|_| self.reader.read_u32()).collect::<Result<Vec<_>>>()?;
+            let sets = set_offsets
+                .into_iter()
+                .map(|set_offset| self.read_annotation_set(set_offset, ids))
+                .collect::<Result<Vec<_>>>()?;
+            members.parameters.insert(method_idx, sets);
+        }
+        Ok((class_annotations, members))
+    }
+
+    /// Read an `annotation_set_item`, offset 0 stands for an empty set
+    fn read_annotation_set(&mut self, offset: u32, ids: &DexIds) -> Result<Vec<Annotation>> {
+        if offset == 0 {
+            return Ok(Vec::new());
+        }
+        self.reader.seek(offset as u64)?;
+        let size = self.reader.read_u32()?;
+        let item_offsets = (0..size).map(|_| self.reader.read_u32()).collect::<Result<Vec<_>>>()?;
+        let mut annotations = Vec::with_capacity(item_offsets.len());
+        for item_offset in item_offsets {
+            self.reader.seek(item_offset as u64)?;
+            let visibility = match self.reader.read_u8()? {
+                0x00 => AnnotationVisibility::Build,
+                0x01 => AnnotationVisibility::Runtime,
+                0x02 => AnnotationVisibility::System,
+                other => return Err(AndroidAnalyzeError::InvalidDexFile(format!("Invalid annotation visibility {}", other))),
             };
-            
-            // TODO: Read interfaces, annotations, and class data
-            let interfaces = Vec::new();
-            let annotations = Vec::new();
-            let static_fields = Vec::new();
-            let instance_fields = Vec::new();
-            let direct_methods = Vec::new();
-            let virtual_methods = Vec::new();
-            
-            classes.push(ClassDef {
-                class_type,
-                access_flags,
-                super_type,
-                interfaces,
-                source_file,
-                annotations,
-                static_fields,
-                instance_fields,
-                direct_methods,
-                virtual_methods,
+            let mut annotation = self.read_encoded_annotation(ids, 0)?;
+            annotation.visibility = Some(visibility);
+            annotations.push(annotation);
+        }
+        Ok(annotations)
+    }
+
+    fn read_encoded_annotation(&mut self, ids: &DexIds, depth: u32) -> Result<Annotation> {
+        let type_idx = self.reader.read_uleb128()?;
+        let size = self.reader.read_uleb128()?;
+        let mut elements = Vec::with_capacity(preallocation(size));
+        for _ in 0..size {
+            let name_idx = self.reader.read_uleb128()?;
+            elements.push(AnnotationElement {
+                name: lookup(ids.strings, name_idx, "string")?.clone(),
+                value: self.read_encoded_value(ids, depth)?,
             });
         }
-        
-        Ok(classes)
+        Ok(Annotation {
+            visibility: None,
+            annotation_type: lookup(ids.types, type_idx, "type")?.clone(),
+            elements,
+        })
+    }
+
+    fn read_encoded_array(&mut self, ids: &DexIds, depth: u32) -> Result<Vec<EncodedValue>> {
+        let size = self.reader.read_uleb128()?;
+        (0..size).map(|_| self.read_encoded_value(ids, depth)).collect()
+    }
+
+    /// 小端、按最少字节数存储的无符号值
+    fn read_unsigned(&mut self, size: u8) -> Result<u64> {
+        let mut value = 0u64;
+        for i in 0..size {
+            value |= (self.reader.read_u8()? as u64) << (i * 8);
+        }
+        Ok(value)
+    }
+
+    /// 同上，按最高字节的符号位扩展
+    fn read_signed(&mut self, size: u8) -> Result<i64> {
+        let shift = 64 - size as u32 * 8;
+        Ok(((self.read_unsigned(size)? << shift) as i64) >> shift)
+    }
+
+    /// Read an `encoded_value`: a type byte whose top three bits hold the value size or argument.
+    /// `depth` counts the arrays and annotations around it
+    fn read_encoded_value(&mut self, ids: &DexIds, depth: u32) -> Result<EncodedValue> {
+        let header = self.reader.read_u8()?;
+        let value_type = header & 0x1F;
+        let arg = header >> 5;
+        let size = arg + 1;
+        // 每种类型的值最多占的字节数；byte、array、annotation、null 的参数必须为 0，boolean 的参数只能是 0 或 1
+        let max_arg = match value_type {
+            0x00 | 0x1C | 0x1D | 0x1E => 0,
+            0x02 | 0x03 | 0x1F => 1,
+            0x04 | 0x10 | 0x15..=0x1B => 3,
+            _ => 7,
+        };
+        if arg > max_arg {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!(
+                "Invalid argument {} for encoded value type 0x{:02x}",
+                arg, value_type
+            )));
+        }
+        if matches!(value_type, 0x1C | 0x1D) && depth >= MAX_ENCODED_DEPTH {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!("Encoded values nested deeper than {}", MAX_ENCODED_DEPTH)));
+        }
+        let index = |this: &mut Self| -> Result<u32> { Ok(this.read_unsigned(size)? as u32) };
+        let value = match value_type {
+            0x00 => EncodedValue::Byte(self.read_signed(1)? as i8),
+            0x02 => EncodedValue::Short(self.read_signed(size)? as i16),
+            0x03 => EncodedValue::Char(self.read_unsigned(size)? as u16),
+            0x04 => EncodedValue::Int(self.read_signed(size)? as i32),
+            0x06 => EncodedValue::Long(self.read_signed(size)?),
+            // 浮点数省略的是低位的零字节，读出的字节对齐到高位
+            0x10 => EncodedValue::Float(f32::from_bits((self.read_unsigned(size)? << ((4 - size as u32) * 8)) as u32)),
+            0x11 => EncodedValue::Double(f64::from_bits(self.read_unsigned(size)? << ((8 - size as u32) * 8))),
+            0x15 => EncodedValue::MethodType(lookup(ids.protos, index(self)?, "proto")?.clone()),
+            0x16 => EncodedValue::MethodHandle(index(self)?),
+            0x17 => EncodedValue::String(lookup(ids.strings, index(self)?, "string")?.clone()),
+            0x18 => EncodedValue::Type(lookup(ids.types, index(self)?, "type")?.clone()),
+            0x19 => EncodedValue::Field(lookup(ids.fields, index(self)?, "field")?.clone()),
+            0x1A => EncodedValue::Method(lookup(ids.methods, index(self)?, "method")?.clone()),
+            0x1B => EncodedValue::Enum(lookup(ids.fields, index(self)?, "field")?.clone()),
+            0x1C => EncodedValue::Array(self.read_encoded_array(ids, depth + 1)?),
+            0x1D => EncodedValue::Annotation(self.read_encoded_annotation(ids, depth + 1)?),
+            0x1E => EncodedValue::Null,
+            0x1F => EncodedValue::Boolean(arg != 0),
+            other => return Err(AndroidAnalyzeError::InvalidDexFile(format!("Invalid encoded value type 0x{:02x}", other))),
+        };
+        Ok(value)
     }
 }
 
@@ -355,6 +1292,7 @@ impl<R: Read + Seek> DexAnalyzer<R> {
 #[derive(Debug, Clone)]
 struct DexHeader {
     magic: [u8; 8],
+    version: u32,
     checksum: u32,
     signature: [u8; 20],
     file_size: u32,
@@ -377,4 +1315,155 @@ struct DexHeader {
     class_defs_offset: u32,
     data_size: u32,
     data_offset: u32,
+    container_size: u32,
+    header_offset: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read_value(bytes: &[u8]) -> Result<EncodedValue> {
+        let dex_file = crate::android_analyzer::test_support::empty_dex();
+        DexAnalyzer::new(Cursor::new(bytes.to_vec())).read_encoded_value(&DexIds::of(&dex_file), 0)
+    }
+
+    #[test]
+    fn encoded_values_are_read_by_size() {
+        // 浮点数只存高位字节
+        assert!(matches!(read_value(&[0x30, 0x80, 0x3F]), Ok(EncodedValue::Float(value)) if value == 1.0));
+        assert!(matches!(read_value(&[0x31, 0xF0, 0x3F]), Ok(EncodedValue::Double(value)) if value == 1.0));
+        assert!(matches!(read_value(&[0x24, 0xFF, 0x7F]), Ok(EncodedValue::Int(0x7FFF))));
+        assert!(matches!(read_value(&[0x04, 0xFF]), Ok(EncodedValue::Int(-1))));
+        assert!(matches!(read_value(&[0x3F]), Ok(EncodedValue::Boolean(true))));
+    }
+
+    #[test]
+    fn oversized_encoded_values_are_rejected() {
+        let oversized: [&[u8]; 6] = [
+            // 5 字节的 float 曾在移位时下溢
+            &[0x90, 0, 0, 0, 0, 0x3F],
+            &[0x84, 0, 0, 0, 0, 1],
+            &[0x42, 0, 0, 1],
+            &[0x20, 0, 1],
+            &[0x5E],
+            &[0x9F],
+        ];
+        for bytes in oversized {
+            assert!(matches!(read_value(bytes), Err(AndroidAnalyzeError::InvalidDexFile(_))), "{:02x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn encoded_value_nesting_is_capped() {
+        // 每层是只含一个元素的数组，最内层是 null
+        let nested = |depth: usize| [[0x1C, 0x01].repeat(depth), vec![0x1E]].concat();
+        assert!(read_value(&nested(MAX_ENCODED_DEPTH as usize)).is_ok());
+        assert!(matches!(read_value(&nested(MAX_ENCODED_DEPTH as usize + 1)), Err(AndroidAnalyzeError::InvalidDexFile(_))));
+    }
+
+    #[test]
+    fn unknown_and_duplicate_map_entries_are_skipped() {
+        let mut bytes = crate::android_analyzer::test_support::dex_bytes(&[r#"
+.class public LPlain;
+.super Ljava/lang/Object;
+
+.method public static run()V
+    .registers 0
+    return-void
+.end method
+"#]);
+        let u32_at = |bytes: &[u8], at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let map_offset = u32_at(&bytes, 52) as usize;
+        let entries: Vec<(usize, u16)> = (0..u32_at(&bytes, map_offset) as usize)
+            .map(|index| map_offset + 4 + index * 12)
+            .map(|at| (at, u16::from_le_bytes([bytes[at], bytes[at + 1]])))
+            .collect();
+        let entry = |type_code: u16| entries.iter().find(|(_, code)| *code == type_code).unwrap().0;
+        let (string_data, class_data) = (entry(TYPE_STRING_DATA_ITEM), entry(TYPE_CLASS_DATA_ITEM));
+        bytes[string_data..string_data + 2].copy_from_slice(&0x7777u16.to_le_bytes());
+        bytes[class_data..class_data + 2].copy_from_slice(&TYPE_CODE_ITEM.to_le_bytes());
+
+        let map = DexAnalyzer::new(Cursor::new(bytes.clone())).read_map(0).expect("map_list with skipped entries");
+        assert_eq!(
+            map.skipped,
+            vec![
+                (string_data as u32, "Unknown map_list item type 0x7777".to_string()),
+                (class_data as u32, "Duplicate map_list entry for code_item".to_string()),
+            ]
+        );
+        assert!(find_section(&map.sections, TYPE_STRING_DATA_ITEM).is_none());
+        assert_eq!(map.sections.len(), entries.len() - 2);
+        // 跳过的条目不影响解析
+        let dex_file = DexAnalyzer::new(Cursor::new(bytes)).analyze().expect("parse dex");
+        assert_eq!(dex_file.classes[0].class_type.descriptor, "LPlain;");
+    }
+
+    #[test]
+    fn code_items_keep_tries_handlers_and_debug_info() {
+        let dex_file = crate::android_analyzer::test_support::dex_file(&[r#"
+.class public LGuarded;
+.super Ljava/lang/Object;
+.source "Guarded.java"
+
+.method public static parse(Ljava/lang/String;)I
+    .registers 3
+    .param p0, "text"
+    .line 10
+    .prologue
+    :start
+    invoke-static {p0}, Ljava/lang/Integer;->parseInt(Ljava/lang/String;)I
+    move-result v0
+    .local v0, "value":I
+    .line 11
+    :end
+    return v0
+    :bad
+    .line 13
+    move-exception v1
+    .local v1, "e":Ljava/lang/NumberFormatException;
+    const/4 v0, -0x1
+    .end local v1
+    .line 14
+    .epilogue
+    return v0
+    :any
+    move-exception v1
+    throw v1
+    .catch Ljava/lang/NumberFormatException; {:start .. :end} :bad
+    .catchall {:start .. :end} :any
+.end method
+"#]);
+        let code = dex_file.classes[0].direct_methods[0].code.as_ref().expect("code item");
+        assert_eq!((code.registers_size, code.ins_size, code.outs_size, code.insns_size), (3, 1, 1, 10));
+        assert_eq!(code.insns.len(), 10);
+
+        assert_eq!(code.tries.len(), 1);
+        let try_item = &code.tries[0];
+        assert_eq!((try_item.start_addr, try_item.insn_count), (0, 4));
+        let handler = code.handler(try_item).expect("handler");
+        let catches: Vec<(&str, u32)> = handler
+            .handlers
+            .iter()
+            .map(|pair| (dex_file.types[pair.type_idx as usize].descriptor.as_str(), pair.addr))
+            .collect();
+        assert_eq!(catches, [("Ljava/lang/NumberFormatException;", 5)]);
+        assert_eq!(handler.catch_all_addr, Some(8));
+        assert_eq!(handler.size, -1);
+
+        let debug = code.debug_info.as_ref().expect("debug info");
+        assert_eq!(debug.line_start, 10);
+        assert_eq!(debug.parameter_names, [Some("text".to_string())]);
+        let lines: Vec<(u32, u32)> = debug.positions.iter().map(|p| (p.address, p.line)).collect();
+        assert_eq!(lines, [(0, 10), (4, 11), (5, 13), (7, 14)]);
+        assert_eq!(debug.prologue_end, [0]);
+        assert_eq!(debug.epilogue_begin, [7]);
+        let locals: Vec<_> = debug
+            .locals
+            .iter()
+            .map(|l| (l.register, l.name.as_deref(), l.local_type.as_ref().map(|t| t.descriptor.as_str()), l.start_address, l.end_address))
+            .collect();
+        assert_eq!(locals, [(0, Some("value"), Some("I"), 4, 10), (1, Some("e"), Some("Ljava/lang/NumberFormatException;"), 6, 7)]);
+    }
 }
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::android::{AccessFlags, ClassDef, DexFile, EncodedValue, Method};
+use crate::android_analyzer::dalvik_lifter::DalvikLifter;
+use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
+use crate::hierarchy::{descriptor_to_internal_name, proto_to_descriptor};
+use crate::java_analyzer::ast::{expr_to_string, java_class_name, java_type, parse_method_descriptor, Expr, JavaWriter, Literal, Stmt};
+use crate::java_analyzer::controlflow::simplify;
+use crate::java_analyzer::decompiler::{
+    class_modifiers, coerce_returns, declare_locals, field_modifiers, method_modifiers, remove_implicit_super, simple_class_name,
+};
+
+/// Decompiles DEX classes to Java source
+pub struct DexDecompiler<'a> {
+    dex_file: &'a DexFile,
+    /// (方法名, 描述符, 地址) -> 还原出的字符串
+    strings: HashMap<(String, String, u32), String>,
+}
+
+impl<'a> DexDecompiler<'a> {
+    pub fn new(dex_file: &'a DexFile) -> Self {
+        DexDecompiler { dex_file, strings: HashMap::new() }
+    }
+
+    /// 用还原出的字符串替换对应地址上的解密调用和字符串表读取
+    pub fn with_strings(mut self, strings: HashMap<(String, String, u32), String>) -> Self {
+        self.strings = strings;
+        self
+    }
+
+    /// 把整个类输出为 Java 源码，单个方法失败时在方法体中以注释说明
+    pub fn decompile_class(&self, class_def: &ClassDef) -> Result<String> {
+        let class_name = descriptor_to_internal_name(&class_def.class_type.descriptor);
+        let flags = class_def.access_flags;
+        let is_enum = flags & AccessFlags::ENUM != 0;
+        let is_interface = flags & AccessFlags::INTERFACE != 0;
+
+        let mut writer = JavaWriter::new(0);
+        if let Some(source_file) = &class_def.source_file {
+            writer.line(&format!("// Source file: {}", source_file));
+        }
+        if let Some((package, _)) = class_name.rsplit_once('/') {
+            writer.line(&format!("package {};", package.replace('/', ".")));
+            writer.line("");
+        }
+
+        let mut header = class_modifiers(java_flags(flags));
+        let simple_name = simple_class_name(&class_name);
+        header.push_str(&simple_name);
+        if !is_interface && !is_enum {
+            let super_class = class_def.super_type.as_ref().map(|t| descriptor_to_internal_name(&t.descriptor));
+            if let Some(super_class) = super_class.filter(|s| s != "java/lang/Object") {
+                header.push_str(&format!(" extends {}", java_class_name(&super_class)));
+            }
+        }
+        let interfaces: Vec<String> = class_def
+            .interfaces
+            .iter()
+            .map(|t| descriptor_to_internal_name(&t.descriptor))
+            .filter(|i| !(flags & AccessFlags::ANNOTATION != 0 && i == "java/lang/annotation/Annotation"))
+            .map(|i| java_class_name(&i))
+            .collect();
+        if !interfaces.is_empty() {
+            let keyword = if is_interface { "extends" } else { "implements" };
+            header.push_str(&format!(" {} {}", keyword, interfaces.join(", ")));
+        }
+        writer.line(&format!("{} {{", header));
+        writer.indent();
+
+        let fields: Vec<_> = class_def.static_fields.iter().chain(class_def.instance_fields.iter()).collect();
+        let mut first_member = true;
+        if is_enum {
+            let constants: Vec<&str> = fields
+                .iter()
+                .filter(|f| f.access_flags & AccessFlags::ENUM != 0)
+                .map(|f| f.name.as_str())
+                .collect();
+            writer.line(&format!("{};", constants.join(", ")));
+            first_member = false;
+        }
+
+        // 字段
+        let mut wrote_field = false;
+        for field in &fields {
+            if is_enum && (field.access_flags & AccessFlags::ENUM != 0 || field.name == "$VALUES") {
+                continue;
+            }
+            if first_member {
+                first_member = false;
+            } else if !wrote_field {
+                writer.line("");
+            }
+            wrote_field = true;
+            let mut line = String::new();
+            if field.access_flags & AccessFlags::SYNTHETIC != 0 {
+                line.push_str("/* synthetic */ ");
+            }
+            let mut field_flags = java_flags(field.access_flags);
+            if is_interface {
+                field_flags &= !((AccessFlags::PUBLIC | AccessFlags::STATIC | AccessFlags::FINAL) as u16);
+            }
+            line.push_str(&field_modifiers(field_flags));
+            line.push_str(&format!("{} {}", java_type(&field.field_type.descriptor), field.name));
+            if let Some(constant) = field.value.as_ref().and_then(|value| constant_literal(value, &field.field_type.descriptor)) {
+                line.push_str(&format!(" = {}", expr_to_string(&constant)));
+            }
+            line.push(';');
+            writer.line(&line);
+        }
+
+        // 方法
+        for method in class_def.direct_methods.iter().chain(class_def.virtual_methods.iter()) {
+            let descriptor = proto_to_descriptor(&method.proto);
+            if is_enum && method.access_flags & AccessFlags::STATIC != 0 {
+                if (method.name == "values" || method.name == "$values") && descriptor.starts_with("()") {
+                    continue;
+                }
+                if method.name == "valueOf" && descriptor.starts_with("(Ljava/lang/String;)") {
+                    continue;
+                }
+            }
+            let Some(text) = self.method_source(class_def, method, &simple_name, is_interface, is_enum) else {
+                continue;
+            };
+            if first_member {
+                first_member = false;
+            } else {
+                writer.line("");
+            }
+            for line in text.lines() {
+                writer.line(line);
+            }
+        }
+
+        writer.dedent();
+        writer.line("}");
+        Ok(writer.finish())
+    }
+
+    /// 单个方法的源码，返回 None 表示省略该方法（例如空的静态初始化块）
+    fn method_source(&self, class_def: &ClassDef, method: &Method, simple_name: &str, is_interface: bool, is_enum: bool) -> Option<String> {
+        let flags = method.access_flags;
+        let is_static_init = method.name == "<clinit>";
+        let is_constructor = method.name == "<init>";
+        let (params, return_type) = parse_method_descriptor(&proto_to_descriptor(&method.proto));
+        // 枚举构造器的前两个参数（name、ordinal）由编译器添加
+        let hidden_params = if is_enum && is_constructor && params.len() >= 2 { 2 } else { 0 };
+
+        let lifted = method.code.as_ref().map(|_| {
+            match catch_unwind(AssertUnwindSafe(|| self.method_body(class_def, method, is_enum))) {
+                Ok(result) => result,
+                Err(_) => Err(AndroidAnalyzeError::ParseError("decompiler panicked".to_string())),
+            }
+        });
+
+        // 编译器生成的空构造器和空静态初始化块不输出
+        if let Some(Ok((_, body))) = &lifted {
+            let is_empty = body.iter().all(|s| matches!(s, Stmt::Label(_)));
+            let is_private = flags & AccessFlags::PRIVATE != 0;
+            let is_default_constructor = is_constructor && params.len() == hidden_params && (!is_private || is_enum);
+            if is_empty && (is_static_init || is_default_constructor) {
+                return None;
+            }
+        }
+
+        let mut writer = JavaWriter::new(0);
+        if flags & (AccessFlags::SYNTHETIC | AccessFlags::BRIDGE) != 0 && !is_static_init {
+            writer.line(if flags & AccessFlags::BRIDGE != 0 { "// bridge method" } else { "// synthetic method" });
+        }
+        let mut header = String::new();
+        if is_static_init {
+            header.push_str("static");
+        } else {
+            let mut modifier_flags = java_flags(flags);
+            if is_interface {
+                modifier_flags &= !(AccessFlags::PUBLIC as u16);
+                if flags & AccessFlags::ABSTRACT != 0 {
+                    modifier_flags &= !(AccessFlags::ABSTRACT as u16);
+                } else if flags & (AccessFlags::STATIC | AccessFlags::PRIVATE) == 0 {
+                    header.push_str("default ");
+                }
+            }
+            header.insert_str(0, &method_modifiers(modifier_flags));
+
+            let parameter_names: Vec<String> = match &lifted {
+                Some(Ok((names, _))) => names.clone(),
+                _ => (0..params.len()).map(|i| format!("arg{}", i)).collect(),
+            };
+            let mut parameters = Vec::new();
+            for (index, (descriptor, name)) in params.iter().zip(parameter_names.iter()).enumerate().skip(hidden_params) {
+                let mut ty = java_type(descriptor);
+                if flags & AccessFlags::VARARGS != 0 && index + 1 == params.len() && ty.ends_with("[]") {
+                    ty.truncate(ty.len() - 2);
+                    ty.push_str("...");
+                }
+                parameters.push(format!("{} {}", ty, name));
+            }
+            if is_constructor {
+                header.push_str(simple_name);
+            } else {
+                header.push_str(&format!("{} {}", java_type(&return_type), method.name));
+            }
+            header.push_str(&format!("({})", parameters.join(", ")));
+
+            let exceptions = throws(method);
+            if !exceptions.is_empty() {
+                header.push_str(&format!(" throws {}", exceptions.join(", ")));
+            }
+        }
+
+        match lifted {
+            None => writer.line(&format!("{};", header)),
+            Some(Ok((_, body))) => {
+                writer.line(&format!("{} {{", header));
+                writer.indent();
+                writer.write_body(&body);
+                writer.dedent();
+                writer.line("}");
+            }
+            Some(Err(error)) => {
+                writer.line(&format!("{} {{", header));
+                writer.indent();
+                writer.line(&format!("// Decompilation failed: {}", error));
+                writer.line("throw new UnsupportedOperationException();");
+                writer.dedent();
+                writer.line("}");
+            }
+        }
+        Some(writer.finish())
+    }
+
+    /// 翻译并结构化方法体，返回参数名和语句
+    fn method_body(&self, class_def: &ClassDef, method: &Method, is_enum: bool) -> Result<(Vec<String>, Vec<Stmt>)> {
+        let descriptor = proto_to_descriptor(&method.proto);
+        let strings = self
+            .strings
+            .iter()
+            .filter(|((name, method_descriptor, _), _)| *name == method.name && *method_descriptor == descriptor)
+            .map(|((_, _, address), value)| (*address, value.clone()))
+            .collect();
+        let lifted = DalvikLifter::new(self.dex_file, class_def, method)?.with_strings(strings).build()?;
+        let mut cfg = lifted.cfg;
+        cfg.merge_conditions();
+        let mut body = cfg.structure();
+        simplify(&mut body, &cfg.temporaries);
+
+        if matches!(body.last(), Some(Stmt::Return(None))) {
+            body.pop();
+        }
+        if method.name == "<init>" {
+            remove_implicit_super(&mut body, is_enum);
+        }
+        if method.name == "<clinit>" && is_enum {
+            body.retain(|stmt| !is_enum_constant_init(stmt, class_def));
+            remove_enum_temporaries(&mut body, &descriptor_to_internal_name(&class_def.class_type.descriptor));
+        }
+
+        let (_, return_type) = parse_method_descriptor(&descriptor);
+        coerce_returns(&mut body, &return_type);
+        declare_locals(&mut body, &lifted.locals);
+        let parameters = lifted.parameters.into_iter().map(|(name, _)| name).collect();
+        Ok((parameters, body))
+    }
+}
+
+/// DEX 的访问标志是 32 位，只保留 Java 源码层面的部分；`DECLARED_SYNCHRONIZED` 对应 `synchronized`
+fn java_flags(flags: u32) -> u16 {
+    let mut java = flags as u16;
+    if flags & AccessFlags::DECLARED_SYNCHRONIZED != 0 {
+        java |= AccessFlags::SYNCHRONIZED as u16;
+    }
+    java
+}
+
+/// `dalvik.annotation.Throws` 中列出的异常
+fn throws(method: &Method) -> Vec<String> {
+    method
+        .annotations
+        .iter()
+        .filter(|a| a.annotation_type.descriptor == "Ldalvik/annotation/Throws;")
+        .flat_map(|a| a.elements.iter())
+        .filter_map(|element| match &element.value {
+            EncodedValue::Array(values) => Some(values),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|value| match value {
+            EncodedValue::Type(t) => Some(java_class_name(&descriptor_to_internal_name(&t.descriptor))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 静态字段的初始值，默认值（0、false、null）不输出
+fn constant_literal(value: &EncodedValue, descriptor: &str) -> Option<Expr> {
+    let literal = match value {
+        EncodedValue::Boolean(value) => Literal::Bool(*value),
+        EncodedValue::Byte(value) => Literal::Int(*value as i32),
+        EncodedValue::Short(value) => Literal::Int(*value as i32),
+        EncodedValue::Char(value) => Literal::Char(*value),
+        EncodedValue::Int(value) if descriptor == "Z" => Literal::Bool(*value != 0),
+        EncodedValue::Int(value) if descriptor == "C" => Literal::Char(*value as u16),
+        EncodedValue::Int(value) => Literal::Int(*value),
+        EncodedValue::Long(value) => Literal::Long(*value),
+        EncodedValue::Float(value) => Literal::Float(*value),
+        EncodedValue::Double(value) => Literal::Double(*value),
+        EncodedValue::String(value) => Literal::String(value.clone()),
+        EncodedValue::Type(t) => Literal::Class(t.descriptor.clone()),
+        _ => return None,
+    };
+    let is_default = match &literal {
+        Literal::Bool(value) => !value,
+        Literal::Int(value) => *value == 0,
+        Literal::Char(value) => *value == 0,
+        Literal::Long(value) => *value == 0,
+        Literal::Float(value) => value.to_bits() == 0,
+        Literal::Double(value) => value.to_bits() == 0,
+        _ => false,
+    };
+    (!is_default).then_some(Expr::Literal(literal))
+}
+
+/// 枚举静态初始化块中给常量和 $VALUES 赋值的语句
+fn is_enum_constant_init(stmt: &Stmt, class_def: &ClassDef) -> bool {
+    let class_name = descriptor_to_internal_name(&class_def.class_type.descriptor);
+    match stmt {
+        Stmt::Assign { target: Expr::Field { target: None, owner, name, .. }, .. } if *owner == class_name => {
+            name == "$VALUES"
+                || class_def
+                    .static_fields
+                    .iter()
+                    .any(|f| f.name == *name && f.access_flags & AccessFlags::ENUM != 0)
+        }
+        _ => false,
+    }
+}
+
+/// d8 先把枚举常量和 $VALUES 数组放进寄存器再赋值，去掉赋值语句后剩下的只写不读的局部变量
+fn remove_enum_temporaries(body: &mut Vec<Stmt>, class_name: &str) {
+    // 语句向哪个局部变量写入一个可以丢弃的值
+    let store_target = |stmt: &Stmt| -> Option<String> {
+        let (name, value) = match stmt {
+            Stmt::Assign { target: Expr::Local(name), value } => (name, value),
+            Stmt::Declare { name, value: Some(value), .. } => (name, value),
+            Stmt::Assign { target: Expr::ArrayElement { array, index }, value } => match array.as_ref() {
+                Expr::Local(name) if !index.has_side_effects() => (name, value),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let discardable = match value {
+            Expr::New { class_name: created, .. } => created == class_name,
+            Expr::NewArray { .. } | Expr::ArrayInit { .. } => true,
+            value => !value.has_side_effects(),
+        };
+        discardable.then(|| name.clone())
+    };
+    loop {
+        let candidates: Vec<String> = body.iter().filter_map(store_target).collect();
+        let dead = candidates.into_iter().find(|name| {
+            body.iter().all(|stmt| {
+                if store_target(stmt).as_deref() == Some(name.as_str()) {
+                    return true;
+                }
+                let mut reads = false;
+                stmt.visit(&mut |s| {
+                    let exprs: Vec<&Expr> = match s {
+                        Stmt::Expr(e) | Stmt::Throw(e) | Stmt::Return(Some(e)) => vec![e],
+                        Stmt::Assign { target, value } => vec![target, value],
+                        Stmt::Declare { value: Some(value), .. } => vec![value],
+                        Stmt::If { cond, .. } | Stmt::While { cond, .. } | Stmt::DoWhile { cond, .. } => vec![cond],
+                        Stmt::Switch { selector, .. } => vec![selector],
+                        _ => vec![],
+                    };
+                    reads |= exprs.iter().any(|e| e.reads_local(name));
+                });
+                !reads
+            })
+        });
+        let Some(dead) = dead else {
+            break;
+        };
+        body.retain(|stmt| store_target(stmt).as_deref() != Some(dead.as_str()));
+    }
+}
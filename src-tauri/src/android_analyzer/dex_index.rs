@@ -0,0 +1,276 @@
+// Lazy DEX loading: the index is built once from the raw files, ids and classes are decoded on demand
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+use std::sync::{Arc, OnceLock};
+
+use crate::android::{ClassDef, DexFile, DexSection, TypeDescriptor};
+use crate::android_analyzer::dex_analyzer::DexAnalyzer;
+use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
+
+/// One DEX file of an APK; only the layout and class names are read up front.
+#[derive(Clone)]
+pub struct DexImage {
+    /// Name of the APK entry, e.g. `classes2.dex`
+    pub entry_name: String,
+    pub header_offset: u32,
+    pub version: u32,
+    pub sections: Vec<DexSection>,
+    /// Class descriptors in `class_defs` order
+    pub class_names: Vec<String>,
+    class_defs_offset: u32,
+    // 041 容器中的多个 DEX 共享同一份字节；load_all 之后释放
+    bytes: Option<Arc<[u8]>>,
+    // 克隆索引（例如在项目锁外搜索）时共享已解码的表；每张表单独解码
+    strings: OnceLock<Arc<Vec<String>>>,
+    types: OnceLock<Arc<Vec<TypeDescriptor>>>,
+    ids: OnceLock<Arc<DexFile>>,
+}
+
+impl fmt::Debug for DexImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DexImage")
+            .field("entry_name", &self.entry_name)
+            .field("header_offset", &self.header_offset)
+            .field("version", &self.version)
+            .field("classes", &self.class_names.len())
+            .field("ids_decoded", &self.ids.get().is_some())
+            .field("bytes_released", &self.bytes.is_none())
+            .finish()
+    }
+}
+
+impl DexImage {
+    fn analyzer(&self) -> Result<DexAnalyzer<Cursor<&[u8]>>> {
+        let bytes = self.bytes.as_deref().ok_or_else(|| {
+            AndroidAnalyzeError::InvalidDexFile(format!("{} was already decoded and its bytes released", self.entry_name))
+        })?;
+        Ok(DexAnalyzer::new(Cursor::new(bytes)))
+    }
+
+    /// The string table, decoded on first use
+    pub fn strings(&self) -> Result<&[String]> {
+        if let Some(ids) = self.ids.get() {
+            return Ok(&ids.strings);
+        }
+        if let Some(strings) = self.strings.get() {
+            return Ok(strings);
+        }
+        let strings = self.analyzer()?.read_strings(self.header_offset as u64)?;
+        Ok(self.strings.get_or_init(|| Arc::new(strings)))
+    }
+
+    /// The type table, decoded on first use without reading the whole string table
+    pub fn types(&self) -> Result<&[TypeDescriptor]> {
+        if let Some(ids) = self.ids.get() {
+            return Ok(&ids.types);
+        }
+        if let Some(types) = self.types.get() {
+            return Ok(types);
+        }
+        let types = self.analyzer()?.read_types(self.header_offset as u64)?;
+        Ok(self.types.get_or_init(|| Arc::new(types)))
+    }
+
+    /// The header and all id tables, decoded on first use; `classes` is empty
+    pub fn ids(&self) -> Result<&DexFile> {
+        if let Some(ids) = self.ids.get() {
+            return Ok(ids);
+        }
+        let strings = self.strings.get().map(|strings| strings.to_vec());
+        let types = self.types.get().map(|types| types.to_vec());
+        let ids = self.analyzer()?.read_ids_with(self.header_offset as u64, strings, types)?;
+        Ok(self.ids.get_or_init(|| Arc::new(ids)))
+    }
+
+    /// Decode the `index`-th class; the result is not cached
+    pub fn class(&self, index: usize) -> Result<ClassDef> {
+        self.analyzer()?.read_class(self.ids()?, index as u32)
+    }
+
+    /// Superclass and interfaces of the `index`-th class; only the type table is decoded
+    pub fn supertypes(&self, index: usize) -> Result<(Option<TypeDescriptor>, Vec<TypeDescriptor>)> {
+        let types = self.types()?;
+        self.analyzer()?
+            .read_supertypes(self.class_defs_offset, self.class_names.len() as u32, types, index as u32)
+    }
+
+    /// Decode the whole file, handing over the cached tables instead of keeping a second copy
+    pub fn load(&mut self) -> Result<DexFile> {
+        let strings = self.strings.take().map(unshare);
+        let types = self.types.take().map(unshare);
+        let mut dex_file = match self.ids.take() {
+            Some(ids) => unshare(ids),
+            None => self.analyzer()?.read_ids_with(self.header_offset as u64, strings, types)?,
+        };
+        let mut analyzer = self.analyzer()?;
+        let mut classes = Vec::with_capacity(self.class_names.len());
+        for index in 0..dex_file.class_defs_size {
+            classes.push(analyzer.read_class(&dex_file, index)?);
+        }
+        dex_file.classes = classes;
+        Ok(dex_file)
+    }
+}
+
+// 克隆出去的索引可能仍持有同一张表，此时复制一份
+fn unshare<T: Clone>(shared: Arc<T>) -> T {
+    Arc::try_unwrap(shared).unwrap_or_else(|shared| (*shared).clone())
+}
+
+/// Every DEX file of an APK with a class name lookup, built once when the APK is opened.
+#[derive(Debug, Clone, Default)]
+pub struct DexIndex {
+    images: Vec<DexImage>,
+    /// 类描述符 → (DEX 序号, class_def 序号)；同名类以先出现的为准，与类加载顺序一致
+    classes: HashMap<String, (usize, usize)>,
+}
+
+impl DexIndex {
+    /// Index the raw `classes*.dex` entries; a version 041 entry may yield several images
+    pub fn build(entries: Vec<(String, Vec<u8>)>) -> Result<Self> {
+        let mut index = DexIndex::default();
+        for (entry_name, bytes) in entries {
+            let bytes: Arc<[u8]> = bytes.into();
+            let mut analyzer = DexAnalyzer::new(Cursor::new(&bytes[..]));
+            let mut header_offset = 0u64;
+            loop {
+                let layout = analyzer.read_layout(header_offset)?;
+                let next = header_offset + layout.file_size as u64;
+                let container_size = layout.container_size as u64;
+                let image_index = index.images.len();
+                for (class_index, name) in layout.class_names.iter().enumerate() {
+                    index.classes.entry(name.clone()).or_insert((image_index, class_index));
+                }
+                index.images.push(DexImage {
+                    entry_name: entry_name.clone(),
+                    header_offset: header_offset as u32,
+                    version: layout.version,
+                    sections: layout.sections,
+                    class_names: layout.class_names,
+                    class_defs_offset: layout.class_defs_offset,
+                    bytes: Some(bytes.clone()),
+                    strings: OnceLock::new(),
+                    types: OnceLock::new(),
+                    ids: OnceLock::new(),
+                });
+                if container_size == 0 || next >= container_size {
+                    break;
+                }
+                header_offset = next;
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn images(&self) -> &[DexImage] {
+        &self.images
+    }
+
+    pub fn class_count(&self) -> usize {
+        self.images.iter().map(|image| image.class_names.len()).sum()
+    }
+
+    /// Position of a class given by its descriptor, as (image, class_def) indices
+    pub fn find_class(&self, descriptor: &str) -> Option<(usize, usize)> {
+        self.classes.get(descriptor).copied()
+    }
+
+    /// Decode every image for edits; the raw bytes are released afterwards, the decoded files take their place
+    pub fn load_all(&mut self) -> Result<Vec<DexFile>> {
+        let dex_files = self.images.iter_mut().map(DexImage::load).collect::<Result<Vec<_>>>()?;
+        for image in &mut self.images {
+            image.bytes = None;
+        }
+        Ok(dex_files)
+    }
+
+    /// Decode the classes one at a time and hand each to `visit`, so the whole program is never held in memory.
+    /// Classes that fail to decode are skipped and returned as (descriptor or entry name, reason).
+    pub fn for_each_class(&self, mut visit: impl FnMut(&DexFile, &ClassDef)) -> Vec<(String, String)> {
+        let mut failures = Vec::new();
+        for image in &self.images {
+            let ids = match image.ids() {
+                Ok(ids) => ids,
+                Err(e) => {
+                    failures.push((image.entry_name.clone(), e.to_string()));
+                    continue;
+                }
+            };
+            for (index, name) in image.class_names.iter().enumerate() {
+                match image.class(index) {
+                    Ok(class_def) => visit(ids, &class_def),
+                    Err(e) => failures.push((name.clone(), e.to_string())),
+                }
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_bytes;
+
+    const BASE: &str = r#"
+.class public La/Base;
+.super Ljava/lang/Object;
+
+.method public static greet()Ljava/lang/String;
+    .registers 1
+    const-string v0, "hello"
+    return-object v0
+.end method
+"#;
+
+    const CHILD: &str = r#"
+.class public La/Child;
+.super La/Base;
+.implements Ljava/lang/Runnable;
+"#;
+
+    fn index() -> DexIndex {
+        DexIndex::build(vec![("classes.dex".to_string(), dex_bytes(&[BASE, CHILD]))]).unwrap()
+    }
+
+    #[test]
+    fn supertypes_decode_only_the_type_table() {
+        let index = index();
+        let image = &index.images()[0];
+        let (super_type, interfaces) = image.supertypes(1).unwrap();
+        assert_eq!(super_type.unwrap().descriptor, "La/Base;");
+        assert_eq!(interfaces.iter().map(|t| t.descriptor.as_str()).collect::<Vec<_>>(), ["Ljava/lang/Runnable;"]);
+        assert!(image.types.get().is_some());
+        assert!(image.strings.get().is_none());
+        assert!(image.ids.get().is_none());
+
+        // 完整的 id 表沿用已读的类型表
+        let descriptors = |types: &[TypeDescriptor]| types.iter().map(|t| t.descriptor.clone()).collect::<Vec<_>>();
+        assert_eq!(descriptors(&image.ids().unwrap().types), descriptors(image.types().unwrap()));
+        assert!(image.strings().unwrap().contains(&"hello".to_string()));
+    }
+
+    #[test]
+    fn classes_are_visited_one_at_a_time() {
+        let mut names = Vec::new();
+        let failures = index().for_each_class(|_, class_def| names.push(class_def.class_type.descriptor.clone()));
+        assert!(failures.is_empty());
+        assert_eq!(names, ["La/Base;", "La/Child;"]);
+    }
+
+    #[test]
+    fn load_all_releases_the_bytes() {
+        let mut index = index();
+        let dex_files = index.load_all().unwrap();
+        assert_eq!(dex_files[0].classes.len(), 2);
+        assert_eq!(dex_files[0].classes[1].super_type.as_ref().unwrap().descriptor, "La/Base;");
+
+        let image = &index.images()[0];
+        assert!(image.class(0).is_err());
+        assert!(image.supertypes(1).is_err());
+        // 布局和类名仍可查询
+        assert!(!image.sections.is_empty());
+        assert_eq!(index.find_class("La/Child;"), Some((0, 1)));
+    }
+}
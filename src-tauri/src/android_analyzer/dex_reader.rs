@@ -73,6 +73,14 @@ impl<R: Read + Seek> DexReader<R> {
         Ok(self.reader.stream_position()?)
     }
 
+    /// Total length of the underlying stream; the position is left unchanged
+    pub fn stream_len(&mut self) -> Result<u64> {
+        let position = self.reader.stream_position()?;
+        let len = self.reader.seek(SeekFrom::End(0))?;
+        self.reader.seek(SeekFrom::Start(position))?;
+        Ok(len)
+    }
+
     /// Read a variable-length unsigned integer (LEB128)
     pub fn read_uleb128(&mut self) -> Result<u32> {
         let mut result = 0u32;
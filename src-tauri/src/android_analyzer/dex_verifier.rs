@@ -0,0 +1,836 @@
+// Integrity checks of a raw DEX file: checksum, signature, id and offset ranges, section layout and tamper traces
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+
+use crate::android::DexSection;
+use crate::android_analyzer::dex_analyzer::{
+    parse_dex_version, section_name, DexAnalyzer, TYPE_ANNOTATIONS_DIRECTORY_ITEM, TYPE_ANNOTATION_ITEM, TYPE_ANNOTATION_SET_ITEM,
+    TYPE_ANNOTATION_SET_REF_LIST, TYPE_CALL_SITE_ID_ITEM, TYPE_CLASS_DATA_ITEM, TYPE_CLASS_DEF_ITEM, TYPE_CODE_ITEM, TYPE_DEBUG_INFO_ITEM,
+    TYPE_ENCODED_ARRAY_ITEM, TYPE_FIELD_ID_ITEM, TYPE_HEADER_ITEM, TYPE_HIDDENAPI_CLASS_DATA_ITEM, TYPE_MAP_LIST, TYPE_METHOD_HANDLE_ITEM,
+    TYPE_METHOD_ID_ITEM, TYPE_PROTO_ID_ITEM, TYPE_STRING_DATA_ITEM, TYPE_STRING_ID_ITEM, TYPE_TYPE_ID_ITEM, TYPE_TYPE_LIST,
+};
+use crate::android_analyzer::dex_writer::adler32;
+
+const NO_INDEX: u32 = 0xFFFFFFFF;
+const HEADER_SIZE: usize = 0x70;
+const ENDIAN_CONSTANT: u32 = 0x12345678;
+const ACC_NATIVE: u32 = 0x0100;
+const ACC_ABSTRACT: u32 = 0x0400;
+
+/// 单个文件最多记录的问题条数，其余只计数
+const MAX_ISSUES: usize = 500;
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum IssueSeverity {
+    /// Accepted by the runtime but unusual for compiler output, typical of packers and manual edits
+    Anomaly,
+    /// The runtime verifier rejects the file
+    Error,
+}
+
+/// What a finding is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum IssueKind {
+    Header,
+    Checksum,
+    Signature,
+    /// An id refers past the end of its table
+    IndexOutOfRange,
+    /// An offset points outside the file or outside the section it must point into
+    OffsetOutOfRange,
+    Misaligned,
+    /// A section runs into the next one
+    Overlap,
+    /// An id table is not sorted as the format requires
+    Ordering,
+    /// Traces of post-build modification: appended data, shared items, foreign members
+    Tamper,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DexIssue {
+    pub severity: IssueSeverity,
+    pub kind: IssueKind,
+    /// File offset of the offending structure, if there is one
+    pub offset: Option<u32>,
+    pub message: String,
+}
+
+/// Result of verifying one DEX file.
+#[derive(Debug, Clone, Serialize)]
+pub struct DexIntegrityReport {
+    pub version: Option<u32>,
+    /// `file_size` from the header
+    pub declared_size: u32,
+    pub actual_size: usize,
+    pub checksum: u32,
+    pub computed_checksum: u32,
+    /// Hex encoded SHA-1 signatures
+    pub signature: String,
+    pub computed_signature: String,
+    /// Sections of every DEX in the file, in file order
+    pub sections: Vec<DexSection>,
+    /// false if any issue is an error
+    pub valid: bool,
+    pub issues: Vec<DexIssue>,
+    /// Issues beyond the first few hundred are only counted
+    pub suppressed_issues: usize,
+}
+
+/// Checks a DEX file the way the runtime verifier would, but reports every problem instead of stopping at the first.
+pub struct DexVerifier<'a> {
+    bytes: &'a [u8],
+    /// 正在检查的 DEX 文件头的位置，041 容器中依次指向每个 DEX
+    header: usize,
+    /// 本 DEX 在字节中的结束位置（不超过实际长度）
+    end: usize,
+    sections: Vec<DexSection>,
+    issues: Vec<DexIssue>,
+    suppressed_issues: usize,
+    /// 未记录的问题中错误的条数，决定文件是否有效
+    suppressed_errors: usize,
+}
+
+impl<'a> DexVerifier<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            header: 0,
+            end: bytes.len(),
+            sections: Vec::new(),
+            issues: Vec::new(),
+            suppressed_issues: 0,
+            suppressed_errors: 0,
+        }
+    }
+
+    pub fn verify(mut self) -> DexIntegrityReport {
+        let mut report = DexIntegrityReport {
+            version: None,
+            declared_size: 0,
+            actual_size: self.bytes.len(),
+            checksum: 0,
+            computed_checksum: 0,
+            signature: String::new(),
+            computed_signature: String::new(),
+            sections: Vec::new(),
+            valid: false,
+            issues: Vec::new(),
+            suppressed_issues: 0,
+        };
+        if self.bytes.len() < HEADER_SIZE {
+            self.error(IssueKind::Header, Some(0), format!("File has {} bytes, less than a DEX header", self.bytes.len()));
+            return self.finish(report);
+        }
+
+        let mut magic = [0u8; 8];
+        magic.copy_from_slice(&self.bytes[..8]);
+        report.version = parse_dex_version(&magic);
+        if report.version.is_none() {
+            self.error(IssueKind::Header, Some(0), format!("Unsupported DEX magic {:?}", String::from_utf8_lossy(&magic)));
+        }
+        report.declared_size = self.u32_at(32).unwrap_or(0);
+        self.verify_size(&report);
+
+        // 041 容器中的 DEX 首尾相接，每个都有自己的文件头、校验和与 map_list
+        let version = report.version.unwrap_or(0);
+        let container_size = if version >= 41 { self.u32_at(0x70).unwrap_or(0) as usize } else { 0 };
+        let mut header = 0;
+        while self.verify_dex(header, version, &mut report) {
+            let next = header + self.u32_at(header + 32).unwrap_or(0) as usize;
+            if next < header + HEADER_SIZE || next >= container_size.min(self.bytes.len()) {
+                break;
+            }
+            if next + HEADER_SIZE > self.bytes.len() {
+                self.error(IssueKind::Header, Some(next as u32), format!("DEX header at 0x{:x} is truncated", next));
+                break;
+            }
+            header = next;
+        }
+        self.finish(report)
+    }
+
+    /// 检查 `header` 处的一个 DEX；endian 标记不对时后面的内容无法解读，返回 false
+    fn verify_dex(&mut self, header: usize, version: u32, report: &mut DexIntegrityReport) -> bool {
+        self.header = header;
+        self.sections = Vec::new();
+        let declared = self.u32_at(header + 32).unwrap_or(0) as usize;
+        self.end = (header + declared).clamp(header + HEADER_SIZE, self.bytes.len());
+        if header > 0 && self.bytes[header..header + 8] != self.bytes[..8] {
+            self.error(IssueKind::Header, Some(header as u32), format!("DEX at 0x{:x} has a different magic than the first one", header));
+        }
+        let header_size = self.u32_at(header + 36).unwrap_or(0);
+        let expected_header = if version >= 41 { 0x78 } else { HEADER_SIZE as u32 };
+        if header_size != expected_header {
+            self.error(IssueKind::Header, Some(header as u32 + 36), format!("Header size is 0x{:x}, expected 0x{:x}", header_size, expected_header));
+        }
+
+        let checksum = self.u32_at(header + 8).unwrap_or(0);
+        let computed_checksum = adler32(&self.bytes[header + 12..self.end]);
+        if checksum != computed_checksum {
+            self.error(
+                IssueKind::Checksum,
+                Some(header as u32 + 8),
+                format!("Checksum is 0x{:08x} but the content sums to 0x{:08x}", checksum, computed_checksum),
+            );
+        }
+        let signature = to_hex(&self.bytes[header + 12..header + 32]);
+        let computed_signature = to_hex(&Sha1::digest(&self.bytes[header + 32..self.end]));
+        if signature != computed_signature {
+            self.error(IssueKind::Signature, Some(header as u32 + 12), "SHA-1 signature does not match the content".to_string());
+        }
+        if header == 0 {
+            report.checksum = checksum;
+            report.computed_checksum = computed_checksum;
+            report.signature = signature;
+            report.computed_signature = computed_signature;
+        }
+
+        let endian_tag = self.u32_at(header + 40).unwrap_or(0);
+        if endian_tag != ENDIAN_CONSTANT {
+            self.error(IssueKind::Header, Some(header as u32 + 40), format!("Unsupported endian tag 0x{:08x}", endian_tag));
+            return false;
+        }
+
+        // map_list 无法解析时仍然检查各个 id 表，只是无法核对段的归属
+        match DexAnalyzer::new(Cursor::new(self.bytes)).read_map(header as u64) {
+            Ok(map) => {
+                self.sections = map.sections;
+                for (offset, message) in map.skipped {
+                    self.anomaly(IssueKind::Tamper, Some(offset), message);
+                }
+            }
+            Err(e) => self.error(IssueKind::Header, self.u32_at(header + 52), e.to_string()),
+        }
+        self.verify_layout(version);
+        self.verify_ids();
+        report.sections.append(&mut self.sections);
+        true
+    }
+
+    fn finish(mut self, mut report: DexIntegrityReport) -> DexIntegrityReport {
+        self.issues.sort_by_key(|issue| (std::cmp::Reverse(issue.severity), issue.offset));
+        report.valid = self.issues.iter().all(|issue| issue.severity != IssueSeverity::Error) && self.suppressed_errors == 0;
+        report.issues = self.issues;
+        report.suppressed_issues = self.suppressed_issues;
+        report
+    }
+
+    fn issue(&mut self, severity: IssueSeverity, kind: IssueKind, offset: Option<u32>, message: String) {
+        if self.issues.len() >= MAX_ISSUES {
+            self.suppressed_issues += 1;
+            if severity == IssueSeverity::Error {
+                self.suppressed_errors += 1;
+            }
+            return;
+        }
+        self.issues.push(DexIssue { severity, kind, offset, message });
+    }
+
+    fn error(&mut self, kind: IssueKind, offset: Option<u32>, message: String) {
+        self.issue(IssueSeverity::Error, kind, offset, message);
+    }
+
+    fn anomaly(&mut self, kind: IssueKind, offset: Option<u32>, message: String) {
+        self.issue(IssueSeverity::Anomaly, kind, offset, message);
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = self.bytes.get(offset..offset.checked_add(2)?)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes.get(offset..offset.checked_add(4)?)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn uleb128_at(&self, offset: &mut usize) -> Option<u32> {
+        let mut result = 0u32;
+        for shift in 0..5 {
+            let byte = *self.bytes.get(*offset)?;
+            *offset += 1;
+            result |= ((byte & 0x7F) as u32) << (shift * 7);
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    fn sleb128_at(&self, offset: &mut usize) -> Option<i32> {
+        let mut result = 0i32;
+        for shift in 0..5 {
+            let byte = *self.bytes.get(*offset)?;
+            *offset += 1;
+            result |= ((byte & 0x7F) as i32) << (shift * 7);
+            if byte & 0x80 == 0 {
+                let bits = (shift + 1) * 7;
+                if bits < 32 && byte & 0x40 != 0 {
+                    result |= -1 << bits;
+                }
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    /// 变长段逐个条目走一遍得到的长度；条目格式不定的段（调试信息、注解、encoded_array）返回 None
+    fn data_extent(&self, section: &DexSection) -> Option<u64> {
+        let start = section.offset as usize;
+        let mut cursor = start;
+        for _ in 0..section.count {
+            if requires_alignment(section.type_code) {
+                cursor = cursor.next_multiple_of(4);
+            }
+            let next = match section.type_code {
+                TYPE_TYPE_LIST => cursor + 4 + 2 * self.u32_at(cursor)? as usize,
+                TYPE_ANNOTATION_SET_ITEM | TYPE_ANNOTATION_SET_REF_LIST => cursor + 4 + 4 * self.u32_at(cursor)? as usize,
+                TYPE_ANNOTATIONS_DIRECTORY_ITEM => {
+                    let members = [4, 8, 12].iter().map(|field| self.u32_at(cursor + field).map(|count| count as usize)).sum::<Option<usize>>()?;
+                    cursor + 16 + 8 * members
+                }
+                TYPE_HIDDENAPI_CLASS_DATA_ITEM => cursor + self.u32_at(cursor)? as usize,
+                TYPE_STRING_DATA_ITEM => {
+                    let mut next = cursor;
+                    self.uleb128_at(&mut next)?;
+                    next + self.bytes.get(next..)?.iter().position(|byte| *byte == 0)? + 1
+                }
+                TYPE_CLASS_DATA_ITEM => {
+                    // 字段两个 uleb128，方法三个
+                    let mut next = cursor;
+                    let mut values = 0u64;
+                    for per_member in [2, 2, 3, 3] {
+                        values += per_member * self.uleb128_at(&mut next)? as u64;
+                    }
+                    for _ in 0..values {
+                        self.uleb128_at(&mut next)?;
+                    }
+                    next
+                }
+                TYPE_CODE_ITEM => self.code_item_end(cursor)?,
+                _ => return None,
+            };
+            if next <= cursor || next > self.end {
+                return None;
+            }
+            cursor = next;
+        }
+        Some((cursor - start) as u64)
+    }
+
+    /// code_item 的结束位置：定长部分、指令、try 表和异常处理列表
+    fn code_item_end(&self, offset: usize) -> Option<usize> {
+        let tries = self.u16_at(offset + 6)? as usize;
+        let insns_size = self.u32_at(offset + 12)? as usize;
+        let mut cursor = offset + 16 + insns_size * 2;
+        if tries == 0 {
+            return Some(cursor);
+        }
+        cursor = cursor.next_multiple_of(4) + tries * 8;
+        for _ in 0..self.uleb128_at(&mut cursor)? {
+            let size = self.sleb128_at(&mut cursor)?;
+            // 每个处理器是类型和地址两个 uleb128，size <= 0 时还有 catch_all 地址
+            for _ in 0..size.unsigned_abs() as u64 * 2 {
+                self.uleb128_at(&mut cursor)?;
+            }
+            if size <= 0 {
+                self.uleb128_at(&mut cursor)?;
+            }
+        }
+        Some(cursor)
+    }
+
+    /// 声明的大小与实际长度不一致：截断，或在末尾追加了数据
+    fn verify_size(&mut self, report: &DexIntegrityReport) {
+        let declared = report.declared_size as usize;
+        // 041 容器中 file_size 只是第一个 DEX 的大小
+        let container = if report.version.unwrap_or(0) >= 41 { self.u32_at(0x70).unwrap_or(0) as usize } else { 0 };
+        let expected = if container > 0 { container } else { declared };
+        if expected > self.bytes.len() {
+            self.error(
+                IssueKind::Header,
+                Some(32),
+                format!("Header declares 0x{:x} bytes but the file has only 0x{:x}, it is truncated", expected, self.bytes.len()),
+            );
+        } else if expected < self.bytes.len() {
+            self.anomaly(
+                IssueKind::Tamper,
+                Some(expected as u32),
+                format!("0x{:x} bytes are appended after the declared end of the file", self.bytes.len() - expected),
+            );
+        }
+    }
+
+    /// 段的对齐、长度、数据区归属以及 link 段
+    fn verify_layout(&mut self, version: u32) {
+        let data_size = self.u32_at(self.header + 104).unwrap_or(0) as u64;
+        let data_offset = self.u32_at(self.header + 108).unwrap_or(0) as u64;
+        // 041 起 data_size / data_off 不再使用
+        let check_data = version < 41;
+        if check_data && data_offset + data_size > self.end as u64 {
+            self.error(IssueKind::OffsetOutOfRange, Some(104), format!("Data section 0x{:x}+0x{:x} extends past the end of the file", data_offset, data_size));
+        }
+        let sections = self.sections.clone();
+        for (position, section) in sections.iter().enumerate() {
+            if requires_alignment(section.type_code) && !section.offset.is_multiple_of(4) {
+                self.error(IssueKind::Misaligned, Some(section.offset), format!("Section {} at 0x{:x} is not 4-byte aligned", section.name, section.offset));
+            }
+            let extent = match section.type_code {
+                TYPE_HEADER_ITEM => Some(self.u32_at(self.header + 36).unwrap_or(0) as u64),
+                TYPE_MAP_LIST => self.u32_at(section.offset as usize).map(|size| 4 + size as u64 * 12),
+                _ => item_size(section.type_code).map(|size| size * section.count as u64).or_else(|| self.data_extent(section)),
+            };
+            match extent {
+                Some(extent) if extent > section.size as u64 => self.error(
+                    IssueKind::Overlap,
+                    Some(section.offset),
+                    format!("Section {} needs 0x{:x} bytes but only 0x{:x} are left before the next section", section.name, extent, section.size),
+                ),
+                Some(extent) => {
+                    // 段之间只允许有下一段对齐所需的填充
+                    let mut used = section.offset as u64 + extent;
+                    if sections.get(position + 1).is_some_and(|next| requires_alignment(next.type_code)) {
+                        used = used.next_multiple_of(4);
+                    }
+                    let gap = (section.offset as u64 + section.size as u64).saturating_sub(used);
+                    if gap > 0 {
+                        self.anomaly(IssueKind::Tamper, Some(used as u32), format!("0x{:x} bytes after section {} belong to no section", gap, section.name));
+                    }
+                }
+                None => {}
+            }
+            // 数据段必须位于 data_off .. data_off + data_size 之内
+            let section_end = section.offset as u64 + section.size as u64;
+            if check_data && section.type_code >= TYPE_MAP_LIST && (section.offset as u64) < data_offset
+                || check_data && section.type_code > TYPE_MAP_LIST && section_end > data_offset + data_size
+            {
+                self.error(IssueKind::OffsetOutOfRange, Some(section.offset), format!("Section {} lies outside the data section", section.name));
+            }
+            if section.count == 0 {
+                self.anomaly(IssueKind::Tamper, Some(section.offset), format!("map_list lists an empty {} section", section.name));
+            }
+        }
+
+        let link_size = self.u32_at(self.header + 44).unwrap_or(0);
+        let link_offset = self.u32_at(self.header + 48).unwrap_or(0);
+        if link_size != 0 {
+            self.anomaly(
+                IssueKind::Tamper,
+                Some(link_offset),
+                format!("Link section of 0x{:x} bytes is present; the runtime ignores it, so it can hide data", link_size),
+            );
+        }
+    }
+
+    /// 偏移必须落在指定类型的段内；map_list 损坏时只检查是否在文件内
+    fn check_offset(&mut self, offset: u32, type_code: u16, what: &str, at: usize) -> bool {
+        let inside = if self.sections.is_empty() {
+            (offset as usize) < self.end
+        } else {
+            self.sections
+                .iter()
+                .find(|section| section.type_code == type_code)
+                .is_some_and(|section| offset >= section.offset && (offset as u64) < section.offset as u64 + section.size as u64)
+        };
+        if !inside {
+            self.error(
+                IssueKind::OffsetOutOfRange,
+                Some(at as u32),
+                format!("{} 0x{:x} does not point into the {} section", what, offset, section_name(type_code).unwrap_or_default()),
+            );
+        }
+        inside
+    }
+
+    /// 读取表头中的一个 id 表，表越界时返回 None
+    fn table(&mut self, header_offset: usize, item_size: usize, name: &str) -> Option<(usize, usize)> {
+        let count = self.u32_at(header_offset)? as usize;
+        let offset = self.u32_at(header_offset + 4)? as usize;
+        if count == 0 {
+            return Some((0, 0));
+        }
+        if offset.checked_add(count.checked_mul(item_size)?)? > self.end {
+            self.error(IssueKind::OffsetOutOfRange, Some(header_offset as u32), format!("{} table with {} entries extends past the end of the file", name, count));
+            return None;
+        }
+        Some((offset, count))
+    }
+
+    fn verify_ids(&mut self) {
+        let header = self.header;
+        let strings = self.table(header + 56, 4, "string_ids");
+        let types = self.table(header + 64, 4, "type_ids");
+        let protos = self.table(header + 72, 12, "proto_ids");
+        let fields = self.table(header + 80, 8, "field_ids");
+        let methods = self.table(header + 88, 8, "method_ids");
+        let classes = self.table(header + 96, 32, "class_defs");
+        let string_count = strings.map_or(0, |(_, count)| count);
+        let type_count = types.map_or(0, |(_, count)| count);
+        let proto_count = protos.map_or(0, |(_, count)| count);
+
+        if let Some((offset, count)) = strings {
+            let mut seen = HashSet::new();
+            for index in 0..count {
+                let at = offset + index * 4;
+                let data = self.u32_at(at).unwrap_or(0);
+                if self.check_offset(data, TYPE_STRING_DATA_ITEM, "string_data_off", at) && !seen.insert(data) {
+                    self.anomaly(IssueKind::Tamper, Some(at as u32), format!("string_id {} shares its data with another string", index));
+                }
+            }
+        }
+
+        if let Some((offset, count)) = types {
+            let mut previous = None;
+            for index in 0..count {
+                let at = offset + index * 4;
+                let descriptor = self.u32_at(at).unwrap_or(0);
+                self.check_index(descriptor as usize, string_count, "descriptor string", at);
+                if previous.is_some_and(|previous| descriptor <= previous) {
+                    self.error(IssueKind::Ordering, Some(at as u32), format!("type_id {} is out of order", index));
+                }
+                previous = Some(descriptor);
+            }
+        }
+
+        if let Some((offset, count)) = protos {
+            for index in 0..count {
+                let at = offset + index * 12;
+                self.check_index(self.u32_at(at).unwrap_or(0) as usize, string_count, "shorty string", at);
+                self.check_index(self.u32_at(at + 4).unwrap_or(0) as usize, type_count, "return type", at + 4);
+                let parameters = self.u32_at(at + 8).unwrap_or(0);
+                if parameters != 0 {
+                    self.check_offset(parameters, TYPE_TYPE_LIST, "parameters_off", at + 8);
+                }
+            }
+        }
+
+        let field_classes = fields.map(|(offset, count)| self.verify_members(offset, count, type_count, string_count, |this, at| {
+            this.check_index(this.u16_at(at + 2).unwrap_or(0) as usize, type_count, "field type", at + 2);
+            this.u16_at(at + 2).unwrap_or(0) as u32
+        }, "field_id"));
+        let method_classes = methods.map(|(offset, count)| self.verify_members(offset, count, type_count, string_count, |this, at| {
+            this.check_index(this.u16_at(at + 2).unwrap_or(0) as usize, proto_count, "proto", at + 2);
+            this.u16_at(at + 2).unwrap_or(0) as u32
+        }, "method_id"));
+
+        if let Some((offset, count)) = classes {
+            self.verify_class_defs(offset, count, type_count, string_count, &field_classes.unwrap_or_default(), &method_classes.unwrap_or_default());
+        }
+    }
+
+    fn check_index(&mut self, index: usize, count: usize, what: &str, at: usize) -> bool {
+        if index >= count {
+            self.error(IssueKind::IndexOutOfRange, Some(at as u32), format!("{} index {} is past the end of a table with {} entries", what, index, count));
+            return false;
+        }
+        true
+    }
+
+    /// field_ids 和 method_ids 的布局相同：class_idx (u16)、类型或原型 (u16)、name_idx (u32)，按这三项排序
+    fn verify_members(
+        &mut self,
+        offset: usize,
+        count: usize,
+        type_count: usize,
+        string_count: usize,
+        mut second: impl FnMut(&mut Self, usize) -> u32,
+        name: &str,
+    ) -> Vec<u16> {
+        let mut owners = Vec::with_capacity(count);
+        let mut previous: Option<(u16, u32, u32)> = None;
+        for index in 0..count {
+            let at = offset + index * 8;
+            let class_idx = self.u16_at(at).unwrap_or(0);
+            let name_idx = self.u32_at(at + 4).unwrap_or(0);
+            self.check_index(class_idx as usize, type_count, "class type", at);
+            let second = second(self, at);
+            self.check_index(name_idx as usize, string_count, "name string", at + 4);
+            // 排序键：所属类、名字、类型或原型
+            let key = (class_idx, name_idx, second);
+            if previous.is_some_and(|previous| key <= previous) {
+                self.error(IssueKind::Ordering, Some(at as u32), format!("{} {} is out of order", name, index));
+            }
+            previous = Some(key);
+            owners.push(class_idx);
+        }
+        owners
+    }
+
+    fn verify_class_defs(&mut self, offset: usize, count: usize, type_count: usize, string_count: usize, field_classes: &[u16], method_classes: &[u16]) {
+        let mut defined = HashSet::new();
+        let mut class_data_users: HashMap<u32, usize> = HashMap::new();
+        let mut code_users: HashMap<u32, usize> = HashMap::new();
+        for index in 0..count {
+            let at = offset + index * 32;
+            let class_idx = self.u32_at(at).unwrap_or(0);
+            let superclass_idx = self.u32_at(at + 8).unwrap_or(0);
+            let interfaces_off = self.u32_at(at + 12).unwrap_or(0);
+            let source_file_idx = self.u32_at(at + 16).unwrap_or(0);
+            let annotations_off = self.u32_at(at + 20).unwrap_or(0);
+            let class_data_off = self.u32_at(at + 24).unwrap_or(0);
+            let static_values_off = self.u32_at(at + 28).unwrap_or(0);
+
+            self.check_index(class_idx as usize, type_count, "class type", at);
+            if !defined.insert(class_idx) {
+                self.error(IssueKind::Tamper, Some(at as u32), format!("class_def {} redefines type {}", index, class_idx));
+            }
+            if superclass_idx != NO_INDEX {
+                self.check_index(superclass_idx as usize, type_count, "superclass type", at + 8);
+                if superclass_idx == class_idx {
+                    self.error(IssueKind::Tamper, Some(at as u32 + 8), format!("class_def {} is its own superclass", index));
+                }
+            }
+            if source_file_idx != NO_INDEX {
+                self.check_index(source_file_idx as usize, string_count, "source file string", at + 16);
+            }
+            if interfaces_off != 0 {
+                self.check_offset(interfaces_off, TYPE_TYPE_LIST, "interfaces_off", at + 12);
+            }
+            if annotations_off != 0 {
+                self.check_offset(annotations_off, TYPE_ANNOTATIONS_DIRECTORY_ITEM, "annotations_off", at + 20);
+            }
+            if static_values_off != 0 {
+                self.check_offset(static_values_off, TYPE_ENCODED_ARRAY_ITEM, "static_values_off", at + 28);
+            }
+            if class_data_off != 0 && self.check_offset(class_data_off, TYPE_CLASS_DATA_ITEM, "class_data_off", at + 24) {
+                if let Some(other) = class_data_users.insert(class_data_off, index) {
+                    self.anomaly(IssueKind::Tamper, Some(at as u32 + 24), format!("class_def {} shares its class data with class_def {}", index, other));
+                }
+                self.verify_class_data(class_data_off as usize, class_idx, field_classes, method_classes, &mut code_users);
+            }
+        }
+    }
+
+    /// class_data_item 中的成员必须属于这个类，方法体的有无与 abstract / native 一致
+    fn verify_class_data(&mut self, offset: usize, class_idx: u32, field_classes: &[u16], method_classes: &[u16], code_users: &mut HashMap<u32, usize>) {
+        let mut cursor = offset;
+        let truncated = |this: &mut Self| this.error(IssueKind::OffsetOutOfRange, Some(offset as u32), "class_data_item is truncated".to_string());
+        let mut sizes = [0u32; 4];
+        for size in &mut sizes {
+            match self.uleb128_at(&mut cursor) {
+                Some(value) => *size = value,
+                None => return truncated(self),
+            }
+        }
+        for (list, &size) in sizes.iter().enumerate() {
+            let is_method = list >= 2;
+            let owners = if is_method { method_classes } else { field_classes };
+            let mut member_idx = 0u32;
+            for _ in 0..size {
+                let at = cursor;
+                let (Some(diff), Some(access_flags)) = (self.uleb128_at(&mut cursor), self.uleb128_at(&mut cursor)) else {
+                    return truncated(self);
+                };
+                member_idx = member_idx.wrapping_add(diff);
+                let code_off = if is_method {
+                    match self.uleb128_at(&mut cursor) {
+                        Some(code_off) => code_off,
+                        None => return truncated(self),
+                    }
+                } else {
+                    0
+                };
+                let what = if is_method { "method" } else { "field" };
+                if !self.check_index(member_idx as usize, owners.len(), what, at) {
+                    continue;
+                }
+                if owners[member_idx as usize] as u32 != class_idx {
+                    self.error(IssueKind::Tamper, Some(at as u32), format!("Class type {} declares {} {} of another class", class_idx, what, member_idx));
+                }
+                if is_method {
+                    self.verify_code(code_off, access_flags, member_idx, at, code_users);
+                }
+            }
+        }
+    }
+
+    fn verify_code(&mut self, code_off: u32, access_flags: u32, method_idx: u32, at: usize, code_users: &mut HashMap<u32, usize>) {
+        let bodiless = access_flags & (ACC_ABSTRACT | ACC_NATIVE) != 0;
+        if code_off == 0 {
+            if !bodiless {
+                self.error(IssueKind::Tamper, Some(at as u32), format!("Method {} has no code but is neither abstract nor native", method_idx));
+            }
+            return;
+        }
+        if bodiless {
+            self.error(IssueKind::Tamper, Some(at as u32), format!("Method {} is abstract or native but has code", method_idx));
+        }
+        if !code_off.is_multiple_of(4) {
+            self.error(IssueKind::Misaligned, Some(code_off), format!("code_item of method {} is not 4-byte aligned", method_idx));
+        }
+        if !self.check_offset(code_off, TYPE_CODE_ITEM, "code_off", at) {
+            return;
+        }
+        if let Some(other) = code_users.insert(code_off, method_idx as usize) {
+            self.anomaly(IssueKind::Tamper, Some(code_off), format!("Methods {} and {} share one code_item", other, method_idx));
+        }
+        let offset = code_off as usize;
+        let registers = self.u16_at(offset).unwrap_or(0);
+        let ins = self.u16_at(offset + 2).unwrap_or(0);
+        let insns_size = self.u32_at(offset + 12).unwrap_or(0) as usize;
+        if ins > registers {
+            self.error(IssueKind::Header, Some(code_off), format!("code_item of method {} has {} ins but only {} registers", method_idx, ins, registers));
+        }
+        if offset + 16 + insns_size * 2 > self.end {
+            self.error(IssueKind::OffsetOutOfRange, Some(code_off), format!("Instructions of method {} extend past the end of the file", method_idx));
+        }
+    }
+}
+
+/// 除字符串、class_data、debug_info、注解和 encoded_array 以外的段都要求 4 字节对齐
+fn requires_alignment(type_code: u16) -> bool {
+    !matches!(
+        type_code,
+        TYPE_STRING_DATA_ITEM | TYPE_CLASS_DATA_ITEM | TYPE_DEBUG_INFO_ITEM | TYPE_ANNOTATION_ITEM | TYPE_ENCODED_ARRAY_ITEM
+    )
+}
+
+/// 定长条目的大小
+fn item_size(type_code: u16) -> Option<u64> {
+    Some(match type_code {
+        TYPE_STRING_ID_ITEM | TYPE_TYPE_ID_ITEM | TYPE_CALL_SITE_ID_ITEM => 4,
+        TYPE_FIELD_ID_ITEM | TYPE_METHOD_ID_ITEM | TYPE_METHOD_HANDLE_ITEM => 8,
+        TYPE_PROTO_ID_ITEM => 12,
+        TYPE_CLASS_DEF_ITEM => 32,
+        _ => return None,
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_bytes;
+
+    const SAMPLE: &str = r#"
+.class public LSample;
+.super Ljava/lang/Object;
+.implements Ljava/lang/Runnable;
+.annotation runtime LMarker;
+    value = "class"
+.end annotation
+
+.field public static final NAME:Ljava/lang/String; = "sample"
+
+.method public run()V
+    .registers 2
+    :start
+    const-string v0, "run"
+    invoke-static {v0}, LSample;->check(Ljava/lang/String;)V
+    :end
+    return-void
+    :handler
+    move-exception v1
+    return-void
+    .catch Ljava/lang/Exception; {:start .. :end} :handler
+.end method
+
+.method public static check(Ljava/lang/String;)V
+    .registers 1
+    return-void
+.end method
+"#;
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    /// 修改内容后重新计算签名和校验和，只留下想要检查的问题
+    fn seal(bytes: &mut [u8]) {
+        let signature = Sha1::digest(&bytes[32..]);
+        bytes[12..32].copy_from_slice(&signature);
+        let checksum = adler32(&bytes[12..]);
+        bytes[8..12].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    fn section_offset(bytes: &[u8], type_code: u16) -> usize {
+        let map = u32_at(bytes, 52) as usize;
+        (0..u32_at(bytes, map) as usize)
+            .map(|index| map + 4 + index * 12)
+            .find(|at| u16::from_le_bytes([bytes[*at], bytes[at + 1]]) == type_code)
+            .map(|at| u32_at(bytes, at + 8) as usize)
+            .expect("section in map_list")
+    }
+
+    /// 只有文件头和 map_list 的 041 DEX，位于容器的 `base` 处
+    fn container_dex(base: u32, container_size: u32) -> Vec<u8> {
+        let map = base + 0x78;
+        let mut bytes = vec![0u8; 0x78];
+        bytes[..8].copy_from_slice(b"dex\n041\0");
+        for (at, value) in [(32, 0x94), (36, 0x78), (40, ENDIAN_CONSTANT), (52, map), (104, 28), (108, map), (0x70, container_size), (0x74, base)] {
+            bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        for value in [2, TYPE_HEADER_ITEM as u32, 1, base, TYPE_MAP_LIST as u32, 1, map] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        seal(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn written_files_have_no_issues() {
+        let report = DexVerifier::new(&dex_bytes(&[SAMPLE])).verify();
+        assert!(report.valid);
+        assert!(report.issues.is_empty(), "{:#?}", report.issues);
+    }
+
+    #[test]
+    fn gaps_and_overlaps_between_sections_are_reported() {
+        let bytes = dex_bytes(&[SAMPLE]);
+        // 唯一的注解集合只有一个条目，改动它声明的条目数
+        let set = section_offset(&bytes, TYPE_ANNOTATION_SET_ITEM);
+        let issue = |size: u32| {
+            let mut bytes = bytes.clone();
+            bytes[set..set + 4].copy_from_slice(&size.to_le_bytes());
+            seal(&mut bytes);
+            let report = DexVerifier::new(&bytes).verify();
+            assert_eq!(report.issues.len(), 1, "{:#?}", report.issues);
+            report.issues[0].clone()
+        };
+        let gap = issue(0);
+        assert_eq!((gap.severity, gap.kind), (IssueSeverity::Anomaly, IssueKind::Tamper));
+        assert!(gap.message.contains("belong to no section"));
+        let overlap = issue(9);
+        assert_eq!((overlap.severity, overlap.kind), (IssueSeverity::Error, IssueKind::Overlap));
+    }
+
+    #[test]
+    fn suppressed_anomalies_keep_the_file_valid() {
+        let fields: String = (0..MAX_ISSUES + 10).map(|index| format!(".field public static f{}:I\n", index)).collect();
+        let mut bytes = dex_bytes(&[&format!(".class public LMany;\n.super Ljava/lang/Object;\n{}", fields)]);
+        // 所有 string_id 指向同一份数据
+        let (count, offset) = (u32_at(&bytes, 56) as usize, u32_at(&bytes, 60) as usize);
+        let first = bytes[offset..offset + 4].to_vec();
+        for index in 1..count {
+            bytes[offset + index * 4..offset + index * 4 + 4].copy_from_slice(&first);
+        }
+        seal(&mut bytes);
+        let report = DexVerifier::new(&bytes).verify();
+        assert!(report.suppressed_issues > 0);
+        assert!(report.issues.iter().all(|issue| issue.severity == IssueSeverity::Anomaly));
+        assert!(report.valid);
+    }
+
+    #[test]
+    fn every_dex_in_a_container_is_verified() {
+        let container = [container_dex(0, 0x128), container_dex(0x94, 0x128)].concat();
+        let report = DexVerifier::new(&container).verify();
+        assert!(report.valid, "{:#?}", report.issues);
+        assert_eq!(report.sections.iter().map(|section| section.offset).collect::<Vec<_>>(), vec![0, 0x78, 0x94, 0x10c]);
+
+        let mut tampered = container.clone();
+        tampered[0x94 + 0x60] = 1;
+        let report = DexVerifier::new(&tampered).verify();
+        assert!(!report.valid);
+        let offsets: Vec<Option<u32>> = report.issues.iter().filter(|issue| issue.severity == IssueSeverity::Error).map(|issue| issue.offset).collect();
+        assert!(offsets.contains(&Some(0x94 + 8)) && offsets.contains(&Some(0x94 + 12)), "{:#?}", report.issues);
+    }
+}
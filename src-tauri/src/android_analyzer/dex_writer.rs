@@ -0,0 +1,1130 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use sha1::{Digest, Sha1};
+use crate::android::{Annotation, AnnotationVisibility, ClassDef, CodeItem, DebugInfo, DexFile, EncodedValue, Field, Method, ProtoDescriptor};
+use crate::android_analyzer::dalvik_opcode::{DalvikOpcodeAnalyzer, DalvikReference, InstructionFormat};
+use crate::android_analyzer::dex_analyzer::{
+    TYPE_ANNOTATIONS_DIRECTORY_ITEM, TYPE_ANNOTATION_ITEM, TYPE_ANNOTATION_SET_ITEM, TYPE_ANNOTATION_SET_REF_LIST, TYPE_CLASS_DATA_ITEM,
+    TYPE_CLASS_DEF_ITEM, TYPE_CODE_ITEM, TYPE_DEBUG_INFO_ITEM, TYPE_ENCODED_ARRAY_ITEM, TYPE_FIELD_ID_ITEM, TYPE_HEADER_ITEM, TYPE_MAP_LIST,
+    TYPE_METHOD_ID_ITEM, TYPE_PROTO_ID_ITEM, TYPE_STRING_DATA_ITEM, TYPE_STRING_ID_ITEM, TYPE_TYPE_ID_ITEM, TYPE_TYPE_LIST,
+};
+use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
+
+const NO_INDEX: u32 = 0xFFFFFFFF;
+const HEADER_SIZE: u32 = 0x70;
+const ENDIAN_CONSTANT: u32 = 0x12345678;
+
+/// (返回类型, 参数类型)
+type ProtoKey = (String, Vec<String>);
+/// (所属类, 名字, 类型)
+type FieldKey = (String, String, String);
+/// (所属类, 名字, 原型)
+type MethodKey = (String, String, ProtoKey);
+
+fn proto_key(proto: &ProtoDescriptor) -> ProtoKey {
+    (proto.return_type.descriptor.clone(), proto.parameters.iter().map(|p| p.descriptor.clone()).collect())
+}
+
+fn shorty(proto: &ProtoKey) -> Result<String> {
+    std::iter::once(&proto.0)
+        .chain(proto.1.iter())
+        .map(|descriptor| match descriptor.as_bytes().first() {
+            Some(b'L' | b'[') => Ok('L'),
+            Some(other) => Ok(*other as char),
+            None => Err(AndroidAnalyzeError::InvalidDexFile("Empty type descriptor in prototype".to_string())),
+        })
+        .collect()
+}
+
+/// 类、字段、方法和各参数上的注解集合，按写出的顺序
+fn class_annotation_sets(class_def: &ClassDef) -> impl Iterator<Item = &[Annotation]> {
+    let fields = class_def.static_fields.iter().chain(&class_def.instance_fields).map(|field| field.annotations.as_slice());
+    let methods = class_def.direct_methods.iter().chain(&class_def.virtual_methods);
+    std::iter::once(class_def.annotations.as_slice())
+        .chain(fields)
+        .chain(methods.flat_map(|method| std::iter::once(method.annotations.as_slice()).chain(method.parameter_annotations.iter().map(Vec::as_slice))))
+}
+
+/// Serializes a `DexFile` model into a complete DEX image.
+///
+/// The id tables are rebuilt from everything the classes refer to (plus the model's own tables) and
+/// sorted as the format requires, so instruction and handler indices are remapped on the way out.
+pub struct DexWriter<'a> {
+    dex_file: &'a DexFile,
+}
+
+/// 排好序的各个 id 表和反查索引
+#[derive(Default)]
+struct Pools {
+    strings: Vec<String>,
+    types: Vec<String>,
+    protos: Vec<ProtoKey>,
+    fields: Vec<FieldKey>,
+    methods: Vec<MethodKey>,
+    string_index: HashMap<String, u32>,
+    type_index: HashMap<String, u32>,
+    proto_index: HashMap<ProtoKey, u32>,
+    field_index: HashMap<FieldKey, u32>,
+    method_index: HashMap<MethodKey, u32>,
+}
+
+impl Pools {
+    fn string(&self, value: &str) -> u32 {
+        self.string_index[value]
+    }
+
+    fn type_id(&self, descriptor: &str) -> u32 {
+        self.type_index[descriptor]
+    }
+
+    fn proto(&self, proto: &ProtoKey) -> u32 {
+        self.proto_index[proto]
+    }
+
+    fn field(&self, key: &FieldKey) -> u32 {
+        self.field_index[key]
+    }
+
+    fn method(&self, key: &MethodKey) -> u32 {
+        self.method_index[key]
+    }
+}
+
+/// 收集阶段用的无序集合
+#[derive(Default)]
+struct Collector {
+    strings: HashSet<String>,
+    types: HashSet<String>,
+    protos: HashSet<ProtoKey>,
+    fields: HashSet<FieldKey>,
+    methods: HashSet<MethodKey>,
+}
+
+impl Collector {
+    fn string(&mut self, value: &str) {
+        if !self.strings.contains(value) {
+            self.strings.insert(value.to_string());
+        }
+    }
+
+    fn type_id(&mut self, descriptor: &str) {
+        self.string(descriptor);
+        if !self.types.contains(descriptor) {
+            self.types.insert(descriptor.to_string());
+        }
+    }
+
+    fn proto(&mut self, proto: ProtoKey) {
+        // 无效的原型在写 proto_ids 时报错
+        if let Ok(shorty) = shorty(&proto) {
+            self.string(&shorty);
+        }
+        self.type_id(&proto.0);
+        proto.1.iter().for_each(|parameter| self.type_id(parameter));
+        self.protos.insert(proto);
+    }
+
+    fn field(&mut self, key: FieldKey) {
+        self.type_id(&key.0);
+        self.string(&key.1);
+        self.type_id(&key.2);
+        self.fields.insert(key);
+    }
+
+    fn method(&mut self, key: MethodKey) {
+        self.type_id(&key.0);
+        self.string(&key.1);
+        self.proto(key.2.clone());
+        self.methods.insert(key);
+    }
+
+    fn value(&mut self, value: &EncodedValue) {
+        match value {
+            EncodedValue::String(s) => self.string(s),
+            EncodedValue::Type(t) => self.type_id(&t.descriptor),
+            EncodedValue::Field(f) | EncodedValue::Enum(f) => {
+                self.field((f.class_type.descriptor.clone(), f.name.clone(), f.field_type.descriptor.clone()))
+            }
+            EncodedValue::Method(m) => self.method((m.class_type.descriptor.clone(), m.name.clone(), proto_key(&m.proto))),
+            EncodedValue::MethodType(proto) => self.proto(proto_key(proto)),
+            EncodedValue::Array(values) => values.iter().for_each(|v| self.value(v)),
+            EncodedValue::Annotation(annotation) => self.annotation(annotation),
+            _ => {}
+        }
+    }
+
+    fn annotation(&mut self, annotation: &Annotation) {
+        self.type_id(&annotation.annotation_type.descriptor);
+        for element in &annotation.elements {
+            self.string(&element.name);
+            self.value(&element.value);
+        }
+    }
+
+    fn debug_info(&mut self, debug: &DebugInfo) {
+        debug.parameter_names.iter().flatten().for_each(|name| self.string(name));
+        debug.positions.iter().filter_map(|p| p.source_file.as_ref()).for_each(|file| self.string(file));
+        for local in &debug.locals {
+            local.name.iter().for_each(|name| self.string(name));
+            local.local_type.iter().for_each(|t| self.type_id(&t.descriptor));
+            local.signature.iter().for_each(|signature| self.string(signature));
+        }
+    }
+
+    /// 按 DEX 规定的顺序排序后编号
+    fn into_pools(self) -> Pools {
+        let mut pools = Pools::default();
+        let mut strings: Vec<String> = self.strings.into_iter().collect();
+        // 字符串按 UTF-16 码元排序
+        strings.sort_by_cached_key(|s| s.encode_utf16().collect::<Vec<u16>>());
+        pools.string_index = strings.iter().enumerate().map(|(i, s)| (s.clone(), i as u32)).collect();
+        pools.strings = strings;
+
+        let mut types: Vec<String> = self.types.into_iter().collect();
+        types.sort_by_key(|t| pools.string_index[t]);
+        pools.type_index = types.iter().enumerate().map(|(i, t)| (t.clone(), i as u32)).collect();
+        pools.types = types;
+
+        let mut protos: Vec<ProtoKey> = self.protos.into_iter().collect();
+        protos.sort_by_cached_key(|(ret, params)| (pools.type_index[ret], params.iter().map(|p| pools.type_index[p]).collect::<Vec<_>>()));
+        pools.proto_index = protos.iter().enumerate().map(|(i, p)| (p.clone(), i as u32)).collect();
+        pools.protos = protos;
+
+        let mut fields: Vec<FieldKey> = self.fields.into_iter().collect();
+        fields.sort_by_key(|(class, name, field_type)| (pools.type_index[class], pools.string_index[name], pools.type_index[field_type]));
+        pools.field_index = fields.iter().enumerate().map(|(i, f)| (f.clone(), i as u32)).collect();
+        pools.fields = fields;
+
+        let mut methods: Vec<MethodKey> = self.methods.into_iter().collect();
+        methods.sort_by_key(|(class, name, proto)| (pools.type_index[class], pools.string_index[name], pools.proto_index[proto]));
+        pools.method_index = methods.iter().enumerate().map(|(i, m)| (m.clone(), i as u32)).collect();
+        pools.methods = methods;
+        pools
+    }
+}
+
+/// 小端字节缓冲区
+#[derive(Default)]
+struct ByteWriter {
+    bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+    fn len(&self) -> u32 {
+        self.bytes.len() as u32
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn uleb128(&mut self, mut value: u32) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn sleb128(&mut self, mut value: i32) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+            if done {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    /// 索引加一编码，NO_INDEX 写成 0
+    fn uleb128p1(&mut self, value: Option<u32>) {
+        self.uleb128(value.map(|v| v + 1).unwrap_or(0));
+    }
+
+    fn align(&mut self, alignment: usize) {
+        while !self.bytes.len().is_multiple_of(alignment) {
+            self.bytes.push(0);
+        }
+    }
+
+    fn patch_u32(&mut self, offset: u32, value: u32) {
+        let offset = offset as usize;
+        self.bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// 数据区中某一类条目的起始偏移和个数，用于生成 map_list
+#[derive(Default)]
+struct Sections {
+    entries: BTreeMap<u16, (u32, u32)>,
+}
+
+impl Sections {
+    fn add(&mut self, item_type: u16, offset: u32) {
+        let entry = self.entries.entry(item_type).or_insert((offset, 0));
+        entry.1 += 1;
+    }
+}
+
+impl<'a> DexWriter<'a> {
+    pub fn new(dex_file: &'a DexFile) -> Self {
+        Self { dex_file }
+    }
+
+    pub fn write(&self) -> Result<Vec<u8>> {
+        let dex_file = self.dex_file;
+        let analyzer = DalvikOpcodeAnalyzer::new();
+
+        // 1. 收集所有被引用的字符串、类型、原型、字段和方法
+        let mut collector = Collector::default();
+        dex_file.strings.iter().for_each(|s| collector.string(s));
+        dex_file.types.iter().for_each(|t| collector.type_id(&t.descriptor));
+        dex_file.protos.iter().for_each(|p| collector.proto(proto_key(p)));
+        for field in &dex_file.fields {
+            collector.field((field.class_type.descriptor.clone(), field.name.clone(), field.field_type.descriptor.clone()));
+        }
+        for method in &dex_file.methods {
+            collector.method((method.class_type.descriptor.clone(), method.name.clone(), proto_key(&method.proto)));
+        }
+        for class_def in &dex_file.classes {
+            let class = &class_def.class_type.descriptor;
+            collector.type_id(class);
+            class_def.super_type.iter().for_each(|t| collector.type_id(&t.descriptor));
+            class_def.interfaces.iter().for_each(|t| collector.type_id(&t.descriptor));
+            class_def.source_file.iter().for_each(|s| collector.string(s));
+            class_def.annotations.iter().for_each(|a| collector.annotation(a));
+            for field in class_def.static_fields.iter().chain(&class_def.instance_fields) {
+                collector.field(field_key(class, field));
+                field.value.iter().for_each(|v| collector.value(v));
+                field.annotations.iter().for_each(|a| collector.annotation(a));
+            }
+            for method in class_def.direct_methods.iter().chain(&class_def.virtual_methods) {
+                collector.method(method_key(class, method));
+                method.annotations.iter().for_each(|a| collector.annotation(a));
+                method.parameter_annotations.iter().flatten().for_each(|a| collector.annotation(a));
+                if let Some(debug) = method.code.as_ref().and_then(|code| code.debug_info.as_ref()) {
+                    collector.debug_info(debug);
+                }
+            }
+        }
+        let pools = collector.into_pools();
+        let classes = ordered_classes(&dex_file.classes);
+
+        // 2. 各 id 表大小固定，数据区紧随其后
+        let string_ids_offset = HEADER_SIZE;
+        let type_ids_offset = string_ids_offset + 4 * pools.strings.len() as u32;
+        let proto_ids_offset = type_ids_offset + 4 * pools.types.len() as u32;
+        let field_ids_offset = proto_ids_offset + 12 * pools.protos.len() as u32;
+        let method_ids_offset = field_ids_offset + 8 * pools.fields.len() as u32;
+        let class_defs_offset = method_ids_offset + 8 * pools.methods.len() as u32;
+        let data_offset = class_defs_offset + 32 * classes.len() as u32;
+
+        let mut out = ByteWriter { bytes: vec![0; data_offset as usize] };
+        let mut sections = Sections::default();
+
+        // 3. 数据区：被引用的条目先写，引用者写入时偏移已知
+        let mut type_lists: HashMap<Vec<u32>, u32> = HashMap::new();
+        let mut type_list = |out: &mut ByteWriter, sections: &mut Sections, types: &[u32]| -> u32 {
+            if types.is_empty() {
+                return 0;
+            }
+            if let Some(offset) = type_lists.get(types) {
+                return *offset;
+            }
+            out.align(4);
+            let offset = out.len();
+            sections.add(TYPE_TYPE_LIST, offset);
+            out.u32(types.len() as u32);
+            types.iter().for_each(|t| out.u16(*t as u16));
+            type_lists.insert(types.to_vec(), offset);
+            offset
+        };
+        let proto_parameters: Vec<u32> = pools
+            .protos
+            .iter()
+            .map(|(_, params)| {
+                let types: Vec<u32> = params.iter().map(|p| pools.type_id(p)).collect();
+                type_list(&mut out, &mut sections, &types)
+            })
+            .collect();
+        let interfaces: Vec<u32> = classes
+            .iter()
+            .map(|class_def| {
+                let types: Vec<u32> = class_def.interfaces.iter().map(|t| pools.type_id(&t.descriptor)).collect();
+                type_list(&mut out, &mut sections, &types)
+            })
+            .collect();
+
+        let mut string_data = Vec::with_capacity(pools.strings.len());
+        for value in &pools.strings {
+            let offset = out.len();
+            sections.add(TYPE_STRING_DATA_ITEM, offset);
+            string_data.push(offset);
+            out.uleb128(value.encode_utf16().count() as u32);
+            out.bytes.extend(mutf8(value));
+            out.u8(0);
+        }
+
+        // 方法体：调试信息、代码
+        let mut code_offsets: HashMap<(usize, MethodKey), u32> = HashMap::new();
+        let mut debug_offsets: HashMap<(usize, MethodKey), u32> = HashMap::new();
+        for (class_index, class_def) in classes.iter().enumerate() {
+            for method in class_def.direct_methods.iter().chain(&class_def.virtual_methods) {
+                if let Some(debug) = method.code.as_ref().and_then(|code| code.debug_info.as_ref()) {
+                    let offset = out.len();
+                    sections.add(TYPE_DEBUG_INFO_ITEM, offset);
+                    write_debug_info(&mut out, debug, method.code.as_ref().map(|c| c.insns.len() as u32).unwrap_or(0), &pools);
+                    debug_offsets.insert((class_index, method_key(&class_def.class_type.descriptor, method)), offset);
+                }
+            }
+        }
+        for (class_index, class_def) in classes.iter().enumerate() {
+            for method in class_def.direct_methods.iter().chain(&class_def.virtual_methods) {
+                let Some(code) = &method.code else { continue };
+                let key = (class_index, method_key(&class_def.class_type.descriptor, method));
+                let insns = remap_code(&analyzer, dex_file, code, &pools).map_err(|e| match e {
+                    AndroidAnalyzeError::InvalidDexFile(message) => {
+                        AndroidAnalyzeError::InvalidDexFile(format!("{}->{}: {}", class_def.class_type.descriptor, method.name, message))
+                    }
+                    other => other,
+                })?;
+                out.align(4);
+                let offset = out.len();
+                sections.add(TYPE_CODE_ITEM, offset);
+                write_code_item(&mut out, code, &insns, debug_offsets.get(&key).copied().unwrap_or(0), dex_file, &pools)?;
+                code_offsets.insert(key, offset);
+            }
+        }
+
+        // 注解：条目、集合、参数集合列表、目录，每种各写成一段
+        let annotation_item = |annotation: &Annotation| -> Result<Vec<u8>> {
+            let mut item = ByteWriter::default();
+            item.u8(match annotation.visibility {
+                Some(AnnotationVisibility::Build) => 0x00,
+                Some(AnnotationVisibility::Runtime) | None => 0x01,
+                Some(AnnotationVisibility::System) => 0x02,
+            });
+            write_encoded_annotation(&mut item, annotation, &pools)?;
+            Ok(item.bytes)
+        };
+        // 注解条目不要求对齐，全部写在集合之前
+        let mut annotation_items: HashMap<Vec<u8>, u32> = HashMap::new();
+        for annotation in classes.iter().flat_map(|class_def| class_annotation_sets(class_def)).flatten() {
+            if let Entry::Vacant(entry) = annotation_items.entry(annotation_item(annotation)?) {
+                let offset = out.len();
+                sections.add(TYPE_ANNOTATION_ITEM, offset);
+                out.bytes.extend_from_slice(entry.key());
+                entry.insert(offset);
+            }
+        }
+        let mut annotation_sets: HashMap<Vec<u32>, u32> = HashMap::new();
+        let mut annotation_set = |out: &mut ByteWriter, sections: &mut Sections, annotations: &[Annotation]| -> Result<u32> {
+            if annotations.is_empty() {
+                return Ok(0);
+            }
+            let mut sorted: Vec<&Annotation> = annotations.iter().collect();
+            sorted.sort_by_key(|a| pools.type_id(&a.annotation_type.descriptor));
+            let items = sorted
+                .into_iter()
+                .map(|annotation| Ok(annotation_items[&annotation_item(annotation)?]))
+                .collect::<Result<Vec<u32>>>()?;
+            if let Some(offset) = annotation_sets.get(&items) {
+                return Ok(*offset);
+            }
+            out.align(4);
+            let offset = out.len();
+            sections.add(TYPE_ANNOTATION_SET_ITEM, offset);
+            out.u32(items.len() as u32);
+            items.iter().for_each(|item| out.u32(*item));
+            annotation_sets.insert(items, offset);
+            Ok(offset)
+        };
+
+        let mut directories = Vec::with_capacity(classes.len());
+        for class_def in &classes {
+            let class = &class_def.class_type.descriptor;
+            let class_annotations = annotation_set(&mut out, &mut sections, &class_def.annotations)?;
+            let mut field_annotations = Vec::new();
+            for field in class_def.static_fields.iter().chain(&class_def.instance_fields) {
+                if !field.annotations.is_empty() {
+                    field_annotations.push((pools.field(&field_key(class, field)), annotation_set(&mut out, &mut sections, &field.annotations)?));
+                }
+            }
+            let mut method_annotations = Vec::new();
+            let mut parameter_sets = Vec::new();
+            for method in class_def.direct_methods.iter().chain(&class_def.virtual_methods) {
+                let method_idx = pools.method(&method_key(class, method));
+                if !method.annotations.is_empty() {
+                    method_annotations.push((method_idx, annotation_set(&mut out, &mut sections, &method.annotations)?));
+                }
+                if method.parameter_annotations.iter().any(|set| !set.is_empty()) {
+                    let sets = method
+                        .parameter_annotations
+                        .iter()
+                        .map(|set| annotation_set(&mut out, &mut sections, set))
+                        .collect::<Result<Vec<_>>>()?;
+                    parameter_sets.push((method_idx, sets));
+                }
+            }
+            directories.push((class_annotations, field_annotations, method_annotations, parameter_sets));
+        }
+        let directories: Vec<_> = directories
+            .into_iter()
+            .map(|(class_annotations, fields, methods, parameter_sets)| {
+                let parameters: Vec<(u32, u32)> = parameter_sets
+                    .into_iter()
+                    .map(|(method_idx, sets)| {
+                        out.align(4);
+                        let offset = out.len();
+                        sections.add(TYPE_ANNOTATION_SET_REF_LIST, offset);
+                        out.u32(sets.len() as u32);
+                        sets.iter().for_each(|set| out.u32(*set));
+                        (method_idx, offset)
+                    })
+                    .collect();
+                (class_annotations, fields, methods, parameters)
+            })
+            .collect();
+        let mut directory_offsets = Vec::with_capacity(classes.len());
+        for (class_annotations, mut fields, mut methods, mut parameters) in directories {
+            if class_annotations == 0 && fields.is_empty() && methods.is_empty() && parameters.is_empty() {
+                directory_offsets.push(0);
+                continue;
+            }
+            fields.sort_unstable();
+            methods.sort_unstable();
+            parameters.sort_unstable();
+            out.align(4);
+            let offset = out.len();
+            sections.add(TYPE_ANNOTATIONS_DIRECTORY_ITEM, offset);
+            out.u32(class_annotations);
+            out.u32(fields.len() as u32);
+            out.u32(methods.len() as u32);
+            out.u32(parameters.len() as u32);
+            for (index, set) in fields.iter().chain(&methods).chain(&parameters) {
+                out.u32(*index);
+                out.u32(*set);
+            }
+            directory_offsets.push(offset);
+        }
+
+        // 静态字段初始值，按排序后的字段顺序，省略末尾没有初值的字段
+        let mut static_values = Vec::with_capacity(classes.len());
+        for class_def in &classes {
+            let mut fields: Vec<&Field> = class_def.static_fields.iter().collect();
+            fields.sort_by_key(|field| pools.field(&field_key(&class_def.class_type.descriptor, field)));
+            let Some(last) = fields.iter().rposition(|field| field.value.is_some()) else {
+                static_values.push(0);
+                continue;
+            };
+            let offset = out.len();
+            sections.add(TYPE_ENCODED_ARRAY_ITEM, offset);
+            out.uleb128(last as u32 + 1);
+            for field in &fields[..=last] {
+                let value = field.value.clone().unwrap_or_else(|| default_value(&field.field_type.descriptor));
+                write_encoded_value(&mut out, &value, &pools)?;
+            }
+            static_values.push(offset);
+        }
+
+        let mut class_data_offsets = Vec::with_capacity(classes.len());
+        for (class_index, class_def) in classes.iter().enumerate() {
+            let class = &class_def.class_type.descriptor;
+            let members = class_def.static_fields.len() + class_def.instance_fields.len() + class_def.direct_methods.len() + class_def.virtual_methods.len();
+            if members == 0 {
+                class_data_offsets.push(0);
+                continue;
+            }
+            let offset = out.len();
+            sections.add(TYPE_CLASS_DATA_ITEM, offset);
+            out.uleb128(class_def.static_fields.len() as u32);
+            out.uleb128(class_def.instance_fields.len() as u32);
+            out.uleb128(class_def.direct_methods.len() as u32);
+            out.uleb128(class_def.virtual_methods.len() as u32);
+            for fields in [&class_def.static_fields, &class_def.instance_fields] {
+                let mut encoded: Vec<(u32, u32)> = fields.iter().map(|f| (pools.field(&field_key(class, f)), f.access_flags)).collect();
+                encoded.sort_unstable();
+                let mut previous = 0;
+                for (index, access_flags) in encoded {
+                    out.uleb128(index - previous);
+                    out.uleb128(access_flags);
+                    previous = index;
+                }
+            }
+            for methods in [&class_def.direct_methods, &class_def.virtual_methods] {
+                let mut encoded: Vec<(u32, u32, u32)> = methods
+                    .iter()
+                    .map(|m| {
+                        let key = method_key(class, m);
+                        let code = code_offsets.get(&(class_index, key.clone())).copied().unwrap_or(0);
+                        (pools.method(&key), m.access_flags, code)
+                    })
+                    .collect();
+                encoded.sort_unstable();
+                let mut previous = 0;
+                for (index, access_flags, code) in encoded {
+                    out.uleb128(index - previous);
+                    out.uleb128(access_flags);
+                    out.uleb128(code);
+                    previous = index;
+                }
+            }
+            class_data_offsets.push(offset);
+        }
+
+        // 4. map_list 放在文件末尾
+        out.align(4);
+        let map_offset = out.len();
+        let mut map: Vec<(u16, u32, u32)> = vec![(TYPE_HEADER_ITEM, 1, 0)];
+        let tables = [
+            (TYPE_STRING_ID_ITEM, pools.strings.len(), string_ids_offset),
+            (TYPE_TYPE_ID_ITEM, pools.types.len(), type_ids_offset),
+            (TYPE_PROTO_ID_ITEM, pools.protos.len(), proto_ids_offset),
+            (TYPE_FIELD_ID_ITEM, pools.fields.len(), field_ids_offset),
+            (TYPE_METHOD_ID_ITEM, pools.methods.len(), method_ids_offset),
+            (TYPE_CLASS_DEF_ITEM, classes.len(), class_defs_offset),
+        ];
+        map.extend(tables.iter().filter(|(_, size, _)| *size > 0).map(|(item_type, size, offset)| (*item_type, *size as u32, *offset)));
+        map.extend(sections.entries.iter().map(|(item_type, (offset, size))| (*item_type, *size, *offset)));
+        map.push((TYPE_MAP_LIST, 1, map_offset));
+        map.sort_by_key(|(_, _, offset)| *offset);
+        out.u32(map.len() as u32);
+        for (item_type, size, offset) in &map {
+            out.u16(*item_type);
+            out.u16(0);
+            out.u32(*size);
+            out.u32(*offset);
+        }
+        let file_size = out.len();
+
+        // 5. id 表
+        for (index, offset) in string_data.iter().enumerate() {
+            out.patch_u32(string_ids_offset + 4 * index as u32, *offset);
+        }
+        for (index, descriptor) in pools.types.iter().enumerate() {
+            out.patch_u32(type_ids_offset + 4 * index as u32, pools.string(descriptor));
+        }
+        for (index, proto) in pools.protos.iter().enumerate() {
+            let base = proto_ids_offset + 12 * index as u32;
+            out.patch_u32(base, pools.string(&shorty(proto)?));
+            out.patch_u32(base + 4, pools.type_id(&proto.0));
+            out.patch_u32(base + 8, proto_parameters[index]);
+        }
+        for (index, (class, name, field_type)) in pools.fields.iter().enumerate() {
+            let base = field_ids_offset + 8 * index as u32;
+            let class_idx = pools.type_id(class);
+            let type_idx = pools.type_id(field_type);
+            out.patch_u32(base, class_idx | type_idx << 16);
+            out.patch_u32(base + 4, pools.string(name));
+        }
+        for (index, (class, name, proto)) in pools.methods.iter().enumerate() {
+            let base = method_ids_offset + 8 * index as u32;
+            let class_idx = pools.type_id(class);
+            let proto_idx = pools.proto(proto);
+            out.patch_u32(base, class_idx | proto_idx << 16);
+            out.patch_u32(base + 4, pools.string(name));
+        }
+        if pools.types.len() > 0x10000 || pools.protos.len() > 0x10000 {
+            return Err(AndroidAnalyzeError::InvalidDexFile("Too many types or prototypes for one DEX file".to_string()));
+        }
+        for (index, class_def) in classes.iter().enumerate() {
+            let base = class_defs_offset + 32 * index as u32;
+            out.patch_u32(base, pools.type_id(&class_def.class_type.descriptor));
+            out.patch_u32(base + 4, class_def.access_flags);
+            out.patch_u32(base + 8, class_def.super_type.as_ref().map(|t| pools.type_id(&t.descriptor)).unwrap_or(NO_INDEX));
+            out.patch_u32(base + 12, interfaces[index]);
+            out.patch_u32(base + 16, class_def.source_file.as_ref().map(|s| pools.string(s)).unwrap_or(NO_INDEX));
+            out.patch_u32(base + 20, directory_offsets[index]);
+            out.patch_u32(base + 24, class_data_offsets[index]);
+            out.patch_u32(base + 28, static_values[index]);
+        }
+
+        // 6. 文件头、签名和校验和
+        // 只写出单个 DEX 的布局，041 的容器格式降为 040
+        let magic = match dex_file.version() {
+            Some(version) if version < 41 => dex_file.magic,
+            Some(_) => *b"dex\n040\0",
+            None => *b"dex\n035\0",
+        };
+        let mut header = ByteWriter::default();
+        header.bytes.extend_from_slice(&magic);
+        header.u32(0);
+        header.bytes.extend_from_slice(&[0; 20]);
+        header.u32(file_size);
+        header.u32(HEADER_SIZE);
+        header.u32(ENDIAN_CONSTANT);
+        header.u32(0);
+        header.u32(0);
+        header.u32(map_offset);
+        let table = |size: usize, offset: u32| (size as u32, if size > 0 { offset } else { 0 });
+        for (size, offset) in [
+            table(pools.strings.len(), string_ids_offset),
+            table(pools.types.len(), type_ids_offset),
+            table(pools.protos.len(), proto_ids_offset),
+            table(pools.fields.len(), field_ids_offset),
+            table(pools.methods.len(), method_ids_offset),
+            table(classes.len(), class_defs_offset),
+            (file_size - data_offset, data_offset),
+        ] {
+            header.u32(size);
+            header.u32(offset);
+        }
+        let mut bytes = out.bytes;
+        bytes[..HEADER_SIZE as usize].copy_from_slice(&header.bytes);
+        let signature = Sha1::digest(&bytes[32..]);
+        bytes[12..32].copy_from_slice(&signature);
+        let checksum = adler32(&bytes[12..]);
+        bytes[8..12].copy_from_slice(&checksum.to_le_bytes());
+        Ok(bytes)
+    }
+}
+
+fn field_key(class: &str, field: &Field) -> FieldKey {
+    (class.to_string(), field.name.clone(), field.field_type.descriptor.clone())
+}
+
+fn method_key(class: &str, method: &Method) -> MethodKey {
+    (class.to_string(), method.name.clone(), proto_key(&method.proto))
+}
+
+/// 父类和接口必须排在子类之前
+fn ordered_classes(classes: &[ClassDef]) -> Vec<&ClassDef> {
+    let by_name: HashMap<&str, &ClassDef> = classes.iter().map(|c| (c.class_type.descriptor.as_str(), c)).collect();
+    let mut visited = HashSet::new();
+    let mut ordered = Vec::with_capacity(classes.len());
+    fn visit<'a>(class_def: &'a ClassDef, by_name: &HashMap<&str, &'a ClassDef>, visited: &mut HashSet<&'a str>, ordered: &mut Vec<&'a ClassDef>) {
+        if !visited.insert(class_def.class_type.descriptor.as_str()) {
+            return;
+        }
+        for parent in class_def.super_type.iter().chain(&class_def.interfaces) {
+            if let Some(parent) = by_name.get(parent.descriptor.as_str()) {
+                visit(parent, by_name, visited, ordered);
+            }
+        }
+        ordered.push(class_def);
+    }
+    for class_def in classes {
+        visit(class_def, &by_name, &mut visited, &mut ordered);
+    }
+    ordered
+}
+
+/// 把指令中的索引改写为新表中的位置，指令长度不变
+fn remap_code(analyzer: &DalvikOpcodeAnalyzer, dex_file: &DexFile, code: &CodeItem, pools: &Pools) -> Result<Vec<u16>> {
+    let mut insns = code.insns.clone();
+    let mut address = 0;
+    while address < code.insns.len() {
+        let instruction = analyzer.decode_instruction(&code.insns, address, dex_file)?;
+        address += instruction.size;
+        let Some(reference) = &instruction.reference else { continue };
+        let index = match reference {
+            DalvikReference::String(s) => pools.string(s),
+            DalvikReference::Type(t) => pools.type_id(&t.descriptor),
+            DalvikReference::Field(f) => pools.field(&(f.class_type.descriptor.clone(), f.name.clone(), f.field_type.descriptor.clone())),
+            DalvikReference::Method(m) => pools.method(&(m.class_type.descriptor.clone(), m.name.clone(), proto_key(&m.proto))),
+            DalvikReference::Proto(p) => pools.proto(&proto_key(p)),
+            DalvikReference::CallSite(_) | DalvikReference::MethodHandle(_) => {
+                return Err(AndroidAnalyzeError::InvalidDexFile("call sites and method handles are not supported by the DEX writer".to_string()));
+            }
+        };
+        let at = instruction.address as usize;
+        if instruction.opcode.format() == InstructionFormat::F31c {
+            insns[at + 1] = index as u16;
+            insns[at + 2] = (index >> 16) as u16;
+        } else if index > 0xffff {
+            return Err(AndroidAnalyzeError::InvalidDexFile(format!(
+                "index {} of {} at 0x{:04x} does not fit in 16 bits",
+                index,
+                instruction.opcode.name(),
+                at
+            )));
+        } else {
+            insns[at + 1] = index as u16;
+        }
+        if let Some(proto) = &instruction.proto {
+            insns[at + 3] = pools.proto(&proto_key(proto)) as u16;
+        }
+    }
+    Ok(insns)
+}
+
+fn write_code_item(out: &mut ByteWriter, code: &CodeItem, insns: &[u16], debug_offset: u32, dex_file: &DexFile, pools: &Pools) -> Result<()> {
+    out.u16(code.registers_size);
+    out.u16(code.ins_size);
+    out.u16(code.outs_size);
+    out.u16(code.tries.len() as u16);
+    out.u32(debug_offset);
+    out.u32(insns.len() as u32);
+    insns.iter().for_each(|unit| out.u16(*unit));
+    if code.tries.is_empty() {
+        return Ok(());
+    }
+    if insns.len() % 2 == 1 {
+        out.u16(0);
+    }
+
+    // 先编码处理器列表，得到每个处理器的新偏移
+    let mut handlers = ByteWriter::default();
+    let mut offsets = HashMap::new();
+    handlers.uleb128(code.handlers.len() as u32);
+    for handler in &code.handlers {
+        offsets.insert(handler.offset, handlers.len() as u16);
+        let size = handler.handlers.len() as i32;
+        handlers.sleb128(if handler.catch_all_addr.is_some() { -size } else { size });
+        for pair in &handler.handlers {
+            let catch_type = dex_file
+                .types
+                .get(pair.type_idx as usize)
+                .ok_or_else(|| AndroidAnalyzeError::InvalidDexFile(format!("Invalid catch type index {}", pair.type_idx)))?;
+            handlers.uleb128(pools.type_id(&catch_type.descriptor));
+            handlers.uleb128(pair.addr);
+        }
+        if let Some(address) = handler.catch_all_addr {
+            handlers.uleb128(address);
+        }
+    }
+    for try_item in &code.tries {
+        let handler_offset = offsets
+            .get(&try_item.handler_offset)
+            .ok_or_else(|| AndroidAnalyzeError::InvalidDexFile(format!("Invalid handler offset {}", try_item.handler_offset)))?;
+        out.u32(try_item.start_addr);
+        out.u16(try_item.insn_count);
+        out.u16(*handler_offset);
+    }
+    out.bytes.extend_from_slice(&handlers.bytes);
+    Ok(())
+}
+
+/// 由行号表和局部变量范围重新生成调试字节码
+fn write_debug_info(out: &mut ByteWriter, debug: &DebugInfo, insns_size: u32, pools: &Pools) {
+    const DBG_END_SEQUENCE: u8 = 0x00;
+    const DBG_ADVANCE_PC: u8 = 0x01;
+    const DBG_ADVANCE_LINE: u8 = 0x02;
+    const DBG_START_LOCAL: u8 = 0x03;
+    const DBG_START_LOCAL_EXTENDED: u8 = 0x04;
+    const DBG_END_LOCAL: u8 = 0x05;
+    const DBG_SET_PROLOGUE_END: u8 = 0x07;
+    const DBG_SET_EPILOGUE_BEGIN: u8 = 0x08;
+    const DBG_SET_FILE: u8 = 0x09;
+    const DBG_FIRST_SPECIAL: u8 = 0x0a;
+
+    out.uleb128(debug.line_start);
+    out.uleb128(debug.parameter_names.len() as u32);
+    for name in &debug.parameter_names {
+        out.uleb128p1(name.as_ref().map(|name| pools.string(name)));
+    }
+
+    // 同一地址上的事件顺序：结束局部变量、开始局部变量、序言/尾声标记、行号
+    let mut addresses: Vec<u32> = debug.positions.iter().map(|p| p.address).collect();
+    addresses.extend(debug.locals.iter().flat_map(|l| [l.start_address, l.end_address]));
+    addresses.extend(debug.prologue_end.iter().chain(&debug.epilogue_begin));
+    addresses.retain(|address| *address < insns_size || debug.positions.iter().any(|p| p.address == *address));
+    addresses.sort_unstable();
+    addresses.dedup();
+
+    let mut address = 0;
+    let mut line = debug.line_start as i64;
+    let mut source_file: Option<&String> = None;
+    for at in addresses {
+        if at > address {
+            out.u8(DBG_ADVANCE_PC);
+            out.uleb128(at - address);
+            address = at;
+        }
+        for local in debug.locals.iter().filter(|l| l.end_address == at && at < insns_size) {
+            // 同一寄存器上开始新变量时旧范围隐式结束
+            let replaced = debug.locals.iter().any(|other| other.register == local.register && other.start_address == at);
+            if !replaced {
+                out.u8(DBG_END_LOCAL);
+                out.uleb128(local.register);
+            }
+        }
+        for local in debug.locals.iter().filter(|l| l.start_address == at) {
+            out.u8(if local.signature.is_some() { DBG_START_LOCAL_EXTENDED } else { DBG_START_LOCAL });
+            out.uleb128(local.register);
+            out.uleb128p1(local.name.as_ref().map(|name| pools.string(name)));
+            out.uleb128p1(local.local_type.as_ref().map(|t| pools.type_id(&t.descriptor)));
+            if let Some(signature) = &local.signature {
+                out.uleb128p1(Some(pools.string(signature)));
+            }
+        }
+        if debug.prologue_end.contains(&at) {
+            out.u8(DBG_SET_PROLOGUE_END);
+        }
+        if debug.epilogue_begin.contains(&at) {
+            out.u8(DBG_SET_EPILOGUE_BEGIN);
+        }
+        for position in debug.positions.iter().filter(|p| p.address == at) {
+            if position.source_file.as_ref() != source_file {
+                source_file = position.source_file.as_ref();
+                out.u8(DBG_SET_FILE);
+                out.uleb128p1(source_file.map(|file| pools.string(file)));
+            }
+            let mut diff = position.line as i64 - line;
+            if !(-4..=10).contains(&diff) {
+                out.u8(DBG_ADVANCE_LINE);
+                out.sleb128(diff as i32);
+                diff = 0;
+            }
+            out.u8(DBG_FIRST_SPECIAL + (diff + 4) as u8);
+            line = position.line as i64;
+        }
+    }
+    out.u8(DBG_END_SEQUENCE);
+}
+
+fn write_encoded_annotation(out: &mut ByteWriter, annotation: &Annotation, pools: &Pools) -> Result<()> {
+    out.uleb128(pools.type_id(&annotation.annotation_type.descriptor));
+    out.uleb128(annotation.elements.len() as u32);
+    let mut elements: Vec<_> = annotation.elements.iter().collect();
+    elements.sort_by_key(|element| pools.string(&element.name));
+    for element in elements {
+        out.uleb128(pools.string(&element.name));
+        write_encoded_value(out, &element.value, pools)?;
+    }
+    Ok(())
+}
+
+/// 有符号数去掉多余的符号扩展字节
+fn signed_bytes(value: i64, max: usize) -> Vec<u8> {
+    let bytes = value.to_le_bytes();
+    let mut size = max;
+    while size > 1 {
+        let top = bytes[size - 1];
+        let next_sign = bytes[size - 2] & 0x80;
+        if (top == 0 && next_sign == 0) || (top == 0xff && next_sign != 0) {
+            size -= 1;
+        } else {
+            break;
+        }
+    }
+    bytes[..size].to_vec()
+}
+
+fn unsigned_bytes(value: u64, max: usize) -> Vec<u8> {
+    let bytes = value.to_le_bytes();
+    let size = (1..=max).rev().find(|size| bytes[size - 1] != 0).unwrap_or(1);
+    bytes[..size].to_vec()
+}
+
+/// 浮点数去掉低位的 0 字节，读取时向右补零
+fn float_bytes(bits: u64, max: usize) -> Vec<u8> {
+    let bytes = &bits.to_le_bytes()[..max];
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(max - 1);
+    bytes[start..].to_vec()
+}
+
+fn write_encoded_value(out: &mut ByteWriter, value: &EncodedValue, pools: &Pools) -> Result<()> {
+    let mut tagged = |value_type: u8, bytes: Vec<u8>| {
+        out.u8(value_type | ((bytes.len() as u8 - 1) << 5));
+        out.bytes.extend(bytes);
+    };
+    match value {
+        EncodedValue::Byte(v) => tagged(0x00, vec![*v as u8]),
+        EncodedValue::Short(v) => tagged(0x02, signed_bytes(*v as i64, 2)),
+        EncodedValue::Char(v) => tagged(0x03, unsigned_bytes(*v as u64, 2)),
+        EncodedValue::Int(v) => tagged(0x04, signed_bytes(*v as i64, 4)),
+        EncodedValue::Long(v) => tagged(0x06, signed_bytes(*v, 8)),
+        EncodedValue::Float(v) => tagged(0x10, float_bytes(v.to_bits() as u64, 4)),
+        EncodedValue::Double(v) => tagged(0x11, float_bytes(v.to_bits(), 8)),
+        EncodedValue::MethodType(proto) => tagged(0x15, unsigned_bytes(pools.proto(&proto_key(proto)) as u64, 4)),
+        EncodedValue::MethodHandle(_) => {
+            return Err(AndroidAnalyzeError::InvalidDexFile("method handles are not supported by the DEX writer".to_string()));
+        }
+        EncodedValue::String(s) => tagged(0x17, unsigned_bytes(pools.string(s) as u64, 4)),
+        EncodedValue::Type(t) => tagged(0x18, unsigned_bytes(pools.type_id(&t.descriptor) as u64, 4)),
+        EncodedValue::Field(f) | EncodedValue::Enum(f) => {
+            let index = pools.field(&(f.class_type.descriptor.clone(), f.name.clone(), f.field_type.descriptor.clone()));
+            tagged(if matches!(value, EncodedValue::Enum(_)) { 0x1b } else { 0x19 }, unsigned_bytes(index as u64, 4));
+        }
+        EncodedValue::Method(m) => {
+            let index = pools.method(&(m.class_type.descriptor.clone(), m.name.clone(), proto_key(&m.proto)));
+            tagged(0x1a, unsigned_bytes(index as u64, 4));
+        }
+        EncodedValue::Array(values) => {
+            out.u8(0x1c);
+            out.uleb128(values.len() as u32);
+            for value in values {
+                write_encoded_value(out, value, pools)?;
+            }
+        }
+        EncodedValue::Annotation(annotation) => {
+            out.u8(0x1d);
+            write_encoded_annotation(out, annotation, pools)?;
+        }
+        EncodedValue::Null => out.u8(0x1e),
+        EncodedValue::Boolean(v) => out.u8(0x1f | (*v as u8) << 5),
+    }
+    Ok(())
+}
+
+/// 中间缺少初值的静态字段用类型的零值补齐
+fn default_value(descriptor: &str) -> EncodedValue {
+    match descriptor {
+        "Z" => EncodedValue::Boolean(false),
+        "B" => EncodedValue::Byte(0),
+        "S" => EncodedValue::Short(0),
+        "C" => EncodedValue::Char(0),
+        "I" => EncodedValue::Int(0),
+        "J" => EncodedValue::Long(0),
+        "F" => EncodedValue::Float(0.0),
+        "D" => EncodedValue::Double(0.0),
+        _ => EncodedValue::Null,
+    }
+}
+
+/// MUTF-8：按 UTF-16 码元编码，U+0000 写成两个字节
+fn mutf8(value: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(value.len());
+    for unit in value.encode_utf16() {
+        match unit {
+            0x01..=0x7f => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    bytes
+}
+
+pub fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 每 5552 字节取一次模，不会溢出
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::android::TypeDescriptor;
+    use crate::android_analyzer::dex_analyzer::DexAnalyzer;
+    use crate::android_analyzer::test_support::{dex_bytes, empty_dex};
+
+    const ANNOTATED: &str = r#"
+.class public LAnnotated;
+.super Ljava/lang/Object;
+.annotation runtime LMarker;
+    value = "class"
+.end annotation
+
+.field public static first:I
+    .annotation runtime LMarker;
+        value = "field"
+    .end annotation
+.end field
+
+.field public second:I
+    .annotation build LOther;
+    .end annotation
+    .annotation runtime LMarker;
+        value = "field"
+    .end annotation
+.end field
+
+.method public static run(II)V
+    .registers 2
+    .annotation runtime LMarker;
+        value = "method"
+    .end annotation
+    .param p0
+        .annotation runtime LMarker;
+            value = "parameter"
+        .end annotation
+    .end param
+    .param p1
+        .annotation build LOther;
+        .end annotation
+    .end param
+    return-void
+.end method
+
+.method public stop()V
+    .registers 1
+    .annotation runtime LMarker;
+        value = "field"
+    .end annotation
+    return-void
+.end method
+"#;
+
+    fn u32_at(bytes: &[u8], offset: u32) -> u32 {
+        u32::from_le_bytes(bytes[offset as usize..offset as usize + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn annotations_round_trip_in_contiguous_sections() {
+        let bytes = dex_bytes(&[ANNOTATED]);
+        let dex_file = DexAnalyzer::new(Cursor::new(bytes.clone())).analyze().expect("parse dex");
+        let class_def = &dex_file.classes[0];
+        let values = |annotations: &[Annotation]| -> Vec<String> {
+            annotations.iter().map(|a| format!("{} {:?}", a.annotation_type.descriptor, a.elements.first().map(|e| &e.value))).collect()
+        };
+        assert_eq!(values(&class_def.annotations), vec![r#"LMarker; Some(String("class"))"#]);
+        assert_eq!(values(&class_def.static_fields[0].annotations), vec![r#"LMarker; Some(String("field"))"#]);
+        assert_eq!(values(&class_def.instance_fields[0].annotations).len(), 2);
+        assert_eq!(values(&class_def.virtual_methods[0].annotations), vec![r#"LMarker; Some(String("field"))"#]);
+        let run = &class_def.direct_methods[0];
+        assert_eq!(values(&run.annotations), vec![r#"LMarker; Some(String("method"))"#]);
+        assert_eq!(values(&run.parameter_annotations[0]), vec![r#"LMarker; Some(String("parameter"))"#]);
+        assert_eq!(values(&run.parameter_annotations[1]), vec!["LOther; None"]);
+
+        // 条目、集合、参数集合列表各自连成一段：所有集合都在条目之后，且不越过下一段
+        let section = |type_code: u16| dex_file.sections.iter().find(|s| s.type_code == type_code).expect("section");
+        let (items, sets, ref_lists) = (section(TYPE_ANNOTATION_ITEM), section(TYPE_ANNOTATION_SET_ITEM), section(TYPE_ANNOTATION_SET_REF_LIST));
+        assert_eq!(items.count, 5);
+        assert!(items.offset < sets.offset && sets.offset < ref_lists.offset);
+        let mut offset = sets.offset;
+        for _ in 0..sets.count {
+            offset = offset.next_multiple_of(4);
+            let size = u32_at(&bytes, offset);
+            for entry in 0..size {
+                let item = u32_at(&bytes, offset + 4 + 4 * entry);
+                assert!(items.offset <= item && item < sets.offset);
+            }
+            offset += 4 + 4 * size;
+        }
+        assert!(offset <= ref_lists.offset);
+    }
+
+    #[test]
+    fn empty_descriptors_are_rejected() {
+        let mut dex_file = empty_dex();
+        dex_file.protos.push(ProtoDescriptor {
+            shorty: String::new(),
+            return_type: TypeDescriptor { descriptor: String::new() },
+            parameters: Vec::new(),
+        });
+        assert!(matches!(DexWriter::new(&dex_file).write(), Err(AndroidAnalyzeError::InvalidDexFile(_))));
+    }
+}
@@ -4,11 +4,26 @@ pub mod axml_analyzer;
 pub mod arsc_analyzer;
 pub mod dalvik_opcode;
 pub mod dex_reader;
+pub mod smali;
+pub mod smali_assembler;
+pub mod dex_writer;
+pub mod dalvik_lifter;
+pub mod dex_decompiler;
+pub mod dex_verifier;
+pub mod dex_index;
 pub mod error;
+#[cfg(test)]
+pub mod test_support;
 
 pub use apk_analyzer::ApkAnalyzer;
 pub use dex_analyzer::DexAnalyzer;
 pub use axml_analyzer::AXMLAnalyzer;
 pub use arsc_analyzer::ARSCAnalyzer;
 pub use dalvik_opcode::DalvikOpcodeAnalyzer;
+pub use smali::SmaliPrinter;
+pub use smali_assembler::SmaliAssembler;
+pub use dex_writer::DexWriter;
+pub use dex_decompiler::DexDecompiler;
+pub use dex_verifier::DexVerifier;
+pub use dex_index::DexIndex;
 pub use error::{AndroidAnalyzeError, Result};
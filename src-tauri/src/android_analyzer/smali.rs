@@ -0,0 +1,757 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::android::{AccessFlags, Annotation, AnnotationVisibility, ClassDef, CodeItem, DexFile, EncodedValue, Field, Method};
+use crate::android_analyzer::dalvik_opcode::{DalvikInstruction, DalvikOpcode, DalvikOpcodeAnalyzer, DalvikPayload, DalvikReference, InstructionFormat};
+use crate::android_analyzer::error::Result;
+use crate::hierarchy::proto_to_descriptor;
+
+/// Prints DEX classes in baksmali syntax
+pub struct SmaliPrinter<'a> {
+    dex_file: &'a DexFile,
+    /// (方法名, 描述符, 地址) -> 附在指令后的注释
+    comments: HashMap<(String, String, u32), String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MemberKind {
+    Class,
+    Field,
+    Method,
+}
+
+/// 标签种类，同一种类按地址顺序编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum LabelKind {
+    Goto,
+    Cond,
+    PackedSwitch,
+    SparseSwitch,
+    PackedSwitchData,
+    SparseSwitchData,
+    Array,
+    TryStart,
+    TryEnd,
+    Catch,
+    CatchAll,
+}
+
+impl LabelKind {
+    fn prefix(&self) -> &'static str {
+        match self {
+            LabelKind::Goto => "goto",
+            LabelKind::Cond => "cond",
+            LabelKind::PackedSwitch => "pswitch",
+            LabelKind::SparseSwitch => "sswitch",
+            LabelKind::PackedSwitchData => "pswitch_data",
+            LabelKind::SparseSwitchData => "sswitch_data",
+            LabelKind::Array => "array",
+            LabelKind::TryStart => "try_start",
+            LabelKind::TryEnd => "try_end",
+            LabelKind::Catch => "catch",
+            LabelKind::CatchAll => "catchall",
+        }
+    }
+}
+
+/// 一个方法体内的所有标签：(种类, 地址) -> 名字
+struct Labels {
+    names: BTreeMap<(LabelKind, u32), String>,
+}
+
+impl Labels {
+    fn new(targets: impl IntoIterator<Item = (LabelKind, u32)>) -> Self {
+        let mut addresses: BTreeMap<LabelKind, Vec<u32>> = BTreeMap::new();
+        for (kind, address) in targets {
+            addresses.entry(kind).or_default().push(address);
+        }
+        let mut names = BTreeMap::new();
+        for (kind, mut list) in addresses {
+            list.sort_unstable();
+            list.dedup();
+            for (index, address) in list.into_iter().enumerate() {
+                names.insert((kind, address), format!(":{}_{}", kind.prefix(), index));
+            }
+        }
+        Labels { names }
+    }
+
+    fn get(&self, kind: LabelKind, address: u32) -> String {
+        self.names.get(&(kind, address)).cloned().unwrap_or_else(|| format!(":addr_{:x}", address))
+    }
+
+    /// 某地址上除 try_end 以外的标签
+    fn at(&self, address: u32) -> Vec<&str> {
+        self.names
+            .iter()
+            .filter(|((kind, label_address), _)| *label_address == address && *kind != LabelKind::TryEnd)
+            .map(|(_, name)| name.as_str())
+            .collect()
+    }
+}
+
+impl<'a> SmaliPrinter<'a> {
+    pub fn new(dex_file: &'a DexFile) -> Self {
+        Self { dex_file, comments: HashMap::new() }
+    }
+
+    pub fn with_comments(mut self, comments: HashMap<(String, String, u32), String>) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    pub fn print_class(&self, class_def: &ClassDef) -> Result<String> {
+        let mut out = String::new();
+        out.push_str(&format!(".class {}{}\n", flags_prefix(class_def.access_flags, MemberKind::Class), class_def.class_type.descriptor));
+        if let Some(super_type) = &class_def.super_type {
+            out.push_str(&format!(".super {}\n", super_type.descriptor));
+        }
+        if let Some(source_file) = &class_def.source_file {
+            out.push_str(&format!(".source \"{}\"\n", escape(source_file)));
+        }
+
+        if !class_def.interfaces.is_empty() {
+            out.push_str("\n\n# interfaces\n");
+            for interface in &class_def.interfaces {
+                out.push_str(&format!(".implements {}\n", interface.descriptor));
+            }
+        }
+
+        if !class_def.annotations.is_empty() {
+            out.push_str("\n\n# annotations\n");
+            print_annotations(&mut out, &class_def.annotations, 0);
+        }
+
+        let sections: [(&str, &[Field]); 2] = [("static fields", &class_def.static_fields), ("instance fields", &class_def.instance_fields)];
+        for (title, fields) in sections {
+            if fields.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("\n\n# {}\n", title));
+            for (index, field) in fields.iter().enumerate() {
+                if index > 0 {
+                    out.push('\n');
+                }
+                print_field(&mut out, field);
+            }
+        }
+
+        let sections: [(&str, &[Method]); 2] = [("direct methods", &class_def.direct_methods), ("virtual methods", &class_def.virtual_methods)];
+        for (title, methods) in sections {
+            if methods.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("\n\n# {}\n", title));
+            for (index, method) in methods.iter().enumerate() {
+                if index > 0 {
+                    out.push('\n');
+                }
+                self.print_method(&mut out, method)?;
+            }
+        }
+        Ok(out)
+    }
+
+    fn print_method(&self, out: &mut String, method: &Method) -> Result<()> {
+        let descriptor = proto_to_descriptor(&method.proto);
+        out.push_str(&format!(
+            ".method {}{}{}\n",
+            flags_prefix(method.access_flags, MemberKind::Method),
+            method.name,
+            descriptor
+        ));
+        let registers = Registers::new(method);
+        let comments: HashMap<u32, &str> = self
+            .comments
+            .iter()
+            .filter(|((name, method_descriptor, _), _)| *name == method.name && *method_descriptor == descriptor)
+            .map(|((_, _, address), comment)| (*address, comment.as_str()))
+            .collect();
+        if let Some(code) = &method.code {
+            out.push_str(&format!("    .registers {}\n", code.registers_size));
+        }
+
+        // 参数名来自调试信息，参数注解单独列出
+        let names = method.code.as_ref().and_then(|code| code.debug_info.as_ref()).map(|debug| debug.parameter_names.as_slice()).unwrap_or(&[]);
+        let mut register = if method.access_flags & AccessFlags::STATIC != 0 { 0 } else { 1 };
+        for (index, parameter) in method.proto.parameters.iter().enumerate() {
+            let name = names.get(index).cloned().flatten();
+            let annotations = method.parameter_annotations.get(index).map(Vec::as_slice).unwrap_or(&[]);
+            if name.is_some() || !annotations.is_empty() {
+                let name = name.map(|name| format!(", \"{}\"", escape(&name))).unwrap_or_default();
+                out.push_str(&format!("    .param p{}{}    # {}\n", register, name, parameter.descriptor));
+                if !annotations.is_empty() {
+                    print_annotations(out, annotations, 2);
+                    out.push_str("    .end param\n");
+                }
+            }
+            register += if matches!(parameter.descriptor.as_str(), "J" | "D") { 2 } else { 1 };
+        }
+
+        if !method.annotations.is_empty() {
+            print_annotations(out, &method.annotations, 1);
+        }
+        if let Some(code) = &method.code {
+            self.print_code(out, code, &registers, &comments)?;
+        }
+        while out.ends_with("\n\n") {
+            out.pop();
+        }
+        out.push_str(".end method\n");
+        Ok(())
+    }
+
+    fn print_code(&self, out: &mut String, code: &CodeItem, registers: &Registers, comments: &HashMap<u32, &str>) -> Result<()> {
+        let instructions = DalvikOpcodeAnalyzer::new().analyze_method(code, self.dex_file)?;
+        let labels = Labels::new(code_labels(code, &instructions));
+        let end = code.insns.len() as u32;
+
+        // 按地址收集调试指令和 try 结束处的 .catch
+        let mut directives: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        let mut try_ends: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for try_item in &code.tries {
+            let start = labels.get(LabelKind::TryStart, try_item.start_addr);
+            let end_address = try_item.start_addr + try_item.insn_count as u32;
+            let end_label = labels.get(LabelKind::TryEnd, end_address);
+            let lines = try_ends.entry(end_address).or_default();
+            lines.push(end_label.clone());
+            if let Some(handler) = code.handler(try_item) {
+                for pair in &handler.handlers {
+                    let catch_type = self.dex_file.types.get(pair.type_idx as usize).map(|t| t.descriptor.as_str()).unwrap_or("?");
+                    lines.push(format!(".catch {} {{{} .. {}}} {}", catch_type, start, end_label, labels.get(LabelKind::Catch, pair.addr)));
+                }
+                if let Some(address) = handler.catch_all_addr {
+                    lines.push(format!(".catchall {{{} .. {}}} {}", start, end_label, labels.get(LabelKind::CatchAll, address)));
+                }
+            }
+        }
+        if let Some(debug) = &code.debug_info {
+            for address in &debug.prologue_end {
+                directives.entry(*address).or_default().push(".prologue".to_string());
+            }
+            let mut source_file = None;
+            for position in &debug.positions {
+                let lines = directives.entry(position.address).or_default();
+                if position.source_file != source_file {
+                    source_file = position.source_file.clone();
+                    if let Some(file) = &source_file {
+                        lines.push(format!(".source \"{}\"", escape(file)));
+                    }
+                }
+                lines.push(format!(".line {}", position.line));
+            }
+            for (index, local) in debug.locals.iter().enumerate() {
+                let description = local_description(local);
+                let restarted = debug.locals[..index]
+                    .iter()
+                    .any(|earlier| earlier.register == local.register && earlier.end_address <= local.start_address && local_description(earlier) == description);
+                let register = registers.name(local.register);
+                let line = if restarted {
+                    format!(".restart local {}    # {}", register, description)
+                } else {
+                    format!(".local {}, {}", register, description)
+                };
+                directives.entry(local.start_address).or_default().push(line);
+                // 同一寄存器上开始新的局部变量时，旧的范围隐式结束
+                let replaced = debug.locals.iter().any(|other| other.register == local.register && other.start_address == local.end_address);
+                if local.end_address < end && !replaced {
+                    directives.entry(local.end_address).or_default().push(format!(".end local {}    # {}", register, description));
+                }
+            }
+            for address in &debug.epilogue_begin {
+                directives.entry(*address).or_default().push(".epilogue".to_string());
+            }
+        }
+
+        for (index, instruction) in instructions.iter().enumerate() {
+            // 负载前的对齐 nop 不输出
+            let next_is_payload = instructions.get(index + 1).is_some_and(|next| next.payload.is_some());
+            let address = instruction.address;
+            if instruction.opcode == DalvikOpcode::Nop && instruction.payload.is_none() && next_is_payload && labels.at(address).is_empty() {
+                continue;
+            }
+            if let Some(lines) = try_ends.get(&address) {
+                for line in lines {
+                    out.push_str(&format!("    {}\n", line));
+                }
+            }
+            let lines = directives.get(&address).map(Vec::as_slice).unwrap_or(&[]);
+            let names = labels.at(address);
+            if !lines.is_empty() || !names.is_empty() || instruction.payload.is_some() {
+                blank_line(out);
+            }
+            for line in lines {
+                out.push_str(&format!("    {}\n", line));
+            }
+            for name in names {
+                out.push_str(&format!("    {}\n", name));
+            }
+            self.print_instruction(out, instruction, &instructions, &labels, registers, comments.get(&address).copied());
+        }
+        if let Some(lines) = try_ends.get(&end) {
+            for line in lines {
+                out.push_str(&format!("    {}\n", line));
+            }
+        }
+        Ok(())
+    }
+
+    fn print_instruction(
+        &self,
+        out: &mut String,
+        instruction: &DalvikInstruction,
+        instructions: &[DalvikInstruction],
+        labels: &Labels,
+        registers: &Registers,
+        comment: Option<&str>,
+    ) {
+        if let Some(payload) = &instruction.payload {
+            print_payload(out, instruction, payload, instructions, labels);
+            blank_line(out);
+            return;
+        }
+        let opcode = instruction.opcode;
+        let format = opcode.format();
+        let mut operands = Vec::new();
+        match format {
+            InstructionFormat::F35c | InstructionFormat::F45cc => {
+                let list: Vec<String> = instruction.registers.iter().map(|r| registers.name(*r)).collect();
+                operands.push(format!("{{{}}}", list.join(", ")));
+            }
+            InstructionFormat::F3rc | InstructionFormat::F4rcc => {
+                operands.push(match (instruction.registers.first(), instruction.registers.last()) {
+                    (Some(first), Some(last)) => format!("{{{} .. {}}}", registers.name(*first), registers.name(*last)),
+                    _ => "{}".to_string(),
+                });
+            }
+            _ => operands.extend(instruction.registers.iter().map(|r| registers.name(*r))),
+        }
+        if let Some(literal) = instruction.literal {
+            let wide = matches!(opcode, DalvikOpcode::ConstWide16 | DalvikOpcode::ConstWide32 | DalvikOpcode::ConstWide | DalvikOpcode::ConstWideHigh16);
+            operands.push(format!("{}{}", hex(literal), if wide { "L" } else { "" }));
+        }
+        if let Some(target) = instruction.target {
+            let kind = match opcode {
+                DalvikOpcode::PackedSwitch => LabelKind::PackedSwitchData,
+                DalvikOpcode::SparseSwitch => LabelKind::SparseSwitchData,
+                DalvikOpcode::FillArrayData => LabelKind::Array,
+                DalvikOpcode::Goto | DalvikOpcode::Goto16 | DalvikOpcode::Goto32 => LabelKind::Goto,
+                _ => LabelKind::Cond,
+            };
+            operands.push(labels.get(kind, target));
+        }
+        if let Some(reference) = &instruction.reference {
+            operands.push(match reference {
+                DalvikReference::String(value) => format!("\"{}\"", escape(value)),
+                other => other.to_string(),
+            });
+        }
+        if let Some(proto) = &instruction.proto {
+            operands.push(proto_to_descriptor(proto));
+        }
+        out.push_str(&format!("    {}", opcode.name()));
+        if !operands.is_empty() {
+            out.push_str(&format!(" {}", operands.join(", ")));
+        }
+        match comment {
+            Some(comment) => out.push_str(&format!("    # {}\n", comment)),
+            None => out.push('\n'),
+        }
+        // 跳转、返回和抛出之后空一行
+        if opcode.is_terminator() {
+            blank_line(out);
+        }
+    }
+}
+
+/// 连续的分隔只保留一个空行
+fn blank_line(out: &mut String) {
+    if !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+}
+
+/// 方法体内需要标签的地址
+fn code_labels(code: &CodeItem, instructions: &[DalvikInstruction]) -> Vec<(LabelKind, u32)> {
+    let mut targets = Vec::new();
+    for instruction in instructions {
+        let Some(target) = instruction.target else { continue };
+        match instruction.opcode {
+            DalvikOpcode::PackedSwitch | DalvikOpcode::SparseSwitch => {
+                let packed = instruction.opcode == DalvikOpcode::PackedSwitch;
+                targets.push((if packed { LabelKind::PackedSwitchData } else { LabelKind::SparseSwitchData }, target));
+                for (_, case) in instruction.switch_cases(instructions) {
+                    targets.push((if packed { LabelKind::PackedSwitch } else { LabelKind::SparseSwitch }, case));
+                }
+            }
+            DalvikOpcode::FillArrayData => targets.push((LabelKind::Array, target)),
+            DalvikOpcode::Goto | DalvikOpcode::Goto16 | DalvikOpcode::Goto32 => targets.push((LabelKind::Goto, target)),
+            _ => targets.push((LabelKind::Cond, target)),
+        }
+    }
+    for try_item in &code.tries {
+        targets.push((LabelKind::TryStart, try_item.start_addr));
+        targets.push((LabelKind::TryEnd, try_item.start_addr + try_item.insn_count as u32));
+        if let Some(handler) = code.handler(try_item) {
+            targets.extend(handler.handlers.iter().map(|pair| (LabelKind::Catch, pair.addr)));
+            targets.extend(handler.catch_all_addr.map(|address| (LabelKind::CatchAll, address)));
+        }
+    }
+    targets
+}
+
+fn print_payload(out: &mut String, instruction: &DalvikInstruction, payload: &DalvikPayload, instructions: &[DalvikInstruction], labels: &Labels) {
+    // switch 负载的目标相对于 switch 指令，先找到引用这个负载的指令
+    let owner = instructions.iter().find(|other| other.target == Some(instruction.address) && other.payload.is_none());
+    let cases = owner.map(|owner| owner.switch_cases(instructions)).unwrap_or_default();
+    match payload {
+        DalvikPayload::PackedSwitch { first_key, .. } => {
+            out.push_str(&format!("    .packed-switch {}\n", hex(*first_key as i64)));
+            for (_, target) in &cases {
+                out.push_str(&format!("        {}\n", labels.get(LabelKind::PackedSwitch, *target)));
+            }
+            out.push_str("    .end packed-switch\n");
+        }
+        DalvikPayload::SparseSwitch { .. } => {
+            out.push_str("    .sparse-switch\n");
+            for (key, target) in &cases {
+                out.push_str(&format!("        {} -> {}\n", hex(*key as i64), labels.get(LabelKind::SparseSwitch, *target)));
+            }
+            out.push_str("    .end sparse-switch\n");
+        }
+        DalvikPayload::FillArrayData { element_width, .. } => {
+            let suffix = match element_width {
+                1 => "t",
+                2 => "s",
+                8 => "L",
+                _ => "",
+            };
+            out.push_str(&format!("    .array-data {}\n", element_width));
+            for element in payload.elements() {
+                out.push_str(&format!("        {}{}\n", hex(element), suffix));
+            }
+            out.push_str("    .end array-data\n");
+        }
+    }
+}
+
+/// 参数占用最后 ins_size 个寄存器，按 baksmali 的习惯写成 p0、p1…
+struct Registers {
+    first_parameter: u32,
+}
+
+impl Registers {
+    fn new(method: &Method) -> Self {
+        let first_parameter = method
+            .code
+            .as_ref()
+            .map(|code| code.registers_size.saturating_sub(code.ins_size) as u32)
+            .unwrap_or(0);
+        Registers { first_parameter }
+    }
+
+    fn name(&self, register: u32) -> String {
+        if register >= self.first_parameter {
+            format!("p{}", register - self.first_parameter)
+        } else {
+            format!("v{}", register)
+        }
+    }
+}
+
+fn local_description(local: &crate::android::LocalVariable) -> String {
+    let name = local.name.as_ref().map(|name| format!("\"{}\"", escape(name))).unwrap_or_else(|| "null".to_string());
+    let local_type = local.local_type.as_ref().map(|t| format!(":{}", t.descriptor)).unwrap_or_default();
+    match &local.signature {
+        Some(signature) => format!("{}{}, \"{}\"", name, local_type, escape(signature)),
+        None => format!("{}{}", name, local_type),
+    }
+}
+
+fn print_field(out: &mut String, field: &Field) {
+    let value = field.value.as_ref().map(|value| format!(" = {}", encoded_value(value, 0))).unwrap_or_default();
+    out.push_str(&format!(".field {}{}:{}{}\n", flags_prefix(field.access_flags, MemberKind::Field), field.name, field.field_type.descriptor, value));
+    if !field.annotations.is_empty() {
+        print_annotations(out, &field.annotations, 1);
+        out.push_str(".end field\n");
+    }
+}
+
+fn print_annotations(out: &mut String, annotations: &[Annotation], depth: usize) {
+    let indent = "    ".repeat(depth);
+    for (index, annotation) in annotations.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let visibility = match annotation.visibility {
+            Some(AnnotationVisibility::Build) => "build",
+            Some(AnnotationVisibility::Runtime) | None => "runtime",
+            Some(AnnotationVisibility::System) => "system",
+        };
+        out.push_str(&format!("{}.annotation {} {}\n", indent, visibility, annotation.annotation_type.descriptor));
+        for element in &annotation.elements {
+            out.push_str(&format!("{}    {} = {}\n", indent, element.name, encoded_value(&element.value, depth + 1)));
+        }
+        out.push_str(&format!("{}.end annotation\n", indent));
+    }
+}
+
+/// `depth` 是值所在行的缩进层级，数组和子注解的内容再缩进一级
+fn encoded_value(value: &EncodedValue, depth: usize) -> String {
+    let indent = "    ".repeat(depth);
+    match value {
+        EncodedValue::Byte(v) => format!("{}t", hex(*v as i64)),
+        EncodedValue::Short(v) => format!("{}s", hex(*v as i64)),
+        EncodedValue::Char(v) => format!("'{}'", escape(&String::from_utf16_lossy(&[*v]))),
+        EncodedValue::Int(v) => hex(*v as i64),
+        EncodedValue::Long(v) => format!("{}L", hex(*v)),
+        EncodedValue::Float(v) => format!("{}f", float(format!("{:?}", v), *v as f64)),
+        EncodedValue::Double(v) => float(format!("{:?}", v), *v),
+        EncodedValue::MethodType(proto) => proto_to_descriptor(proto),
+        EncodedValue::MethodHandle(index) => format!("method_handle_{}", index),
+        EncodedValue::String(s) => format!("\"{}\"", escape(s)),
+        EncodedValue::Type(t) => t.descriptor.clone(),
+        EncodedValue::Field(field) => DalvikReference::Field(field.clone()).to_string(),
+        EncodedValue::Method(method) => DalvikReference::Method(method.clone()).to_string(),
+        EncodedValue::Enum(field) => format!(".enum {}", DalvikReference::Field(field.clone())),
+        EncodedValue::Array(values) if values.is_empty() => "{}".to_string(),
+        EncodedValue::Array(values) => {
+            let items: Vec<String> = values.iter().map(|v| format!("{}    {}", indent, encoded_value(v, depth + 1))).collect();
+            format!("{{\n{}\n{}}}", items.join(",\n"), indent)
+        }
+        EncodedValue::Annotation(annotation) => {
+            let mut text = format!(".subannotation {}\n", annotation.annotation_type.descriptor);
+            for element in &annotation.elements {
+                text.push_str(&format!("{}    {} = {}\n", indent, element.name, encoded_value(&element.value, depth + 1)));
+            }
+            text.push_str(&format!("{}.end subannotation", indent));
+            text
+        }
+        EncodedValue::Null => "null".to_string(),
+        EncodedValue::Boolean(v) => v.to_string(),
+    }
+}
+
+/// smali 的整数字面量：`-0x3`、`0x2a`
+fn hex(value: i64) -> String {
+    if value < 0 {
+        format!("-0x{:x}", value.unsigned_abs())
+    } else {
+        format!("0x{:x}", value)
+    }
+}
+
+/// `debug` 是按原精度格式化的值
+fn float(debug: String, value: f64) -> String {
+    if value.is_finite() {
+        debug
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else if value > 0.0 {
+        "Infinity".to_string()
+    } else {
+        "-Infinity".to_string()
+    }
+}
+
+/// 可打印 ASCII 原样输出，其余字符写成 \uXXXX
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '"' => out.push_str("\\\""),
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            ' '..='~' => out.push(c),
+            _ => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// 访问标志，后面带一个空格；同一个位在字段和方法上含义不同
+fn flags_prefix(flags: u32, kind: MemberKind) -> String {
+    let mut names = Vec::new();
+    let mut flag = |bit: u32, name: &'static str| {
+        if flags & bit != 0 {
+            names.push(name);
+        }
+    };
+    flag(AccessFlags::PUBLIC, "public");
+    flag(AccessFlags::PRIVATE, "private");
+    flag(AccessFlags::PROTECTED, "protected");
+    flag(AccessFlags::STATIC, "static");
+    flag(AccessFlags::FINAL, "final");
+    match kind {
+        MemberKind::Method => {
+            flag(AccessFlags::SYNCHRONIZED, "synchronized");
+            flag(AccessFlags::BRIDGE, "bridge");
+            flag(AccessFlags::VARARGS, "varargs");
+            flag(AccessFlags::NATIVE, "native");
+        }
+        MemberKind::Field => {
+            flag(AccessFlags::VOLATILE, "volatile");
+            flag(AccessFlags::TRANSIENT, "transient");
+        }
+        MemberKind::Class => {}
+    }
+    flag(AccessFlags::INTERFACE, "interface");
+    flag(AccessFlags::ABSTRACT, "abstract");
+    flag(AccessFlags::STRICT, "strictfp");
+    flag(AccessFlags::SYNTHETIC, "synthetic");
+    flag(AccessFlags::ANNOTATION, "annotation");
+    flag(AccessFlags::ENUM, "enum");
+    if kind == MemberKind::Method {
+        flag(AccessFlags::CONSTRUCTOR, "constructor");
+        flag(AccessFlags::DECLARED_SYNCHRONIZED, "declared-synchronized");
+    }
+    names.iter().map(|name| format!("{} ", name)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+
+    const SOURCE: &str = r#"
+.class public final LTable;
+.super Ljava/lang/Object;
+.source "Table.java"
+
+.field private static final SIZE:I = 0x3
+
+.method public static lookup(IJ)J
+    .registers 8
+    .param p0, "key"
+    .param p1, "fallback"
+    .line 5
+    :try_start
+    packed-switch p0, :packed
+    sparse-switch p0, :sparse
+    move-wide v0, p1
+    return-wide v0
+    :one
+    const-wide v0, 0x100000000L
+    return-wide v0
+    :two
+    const/4 v2, 0x3
+    new-array v2, v2, [I
+    fill-array-data v2, :array
+    const/4 v3, 0x1
+    aget v3, v2, v3
+    int-to-long v0, v3
+    :try_end
+    return-wide v0
+    :handler
+    move-exception v2
+    const-wide/16 v0, -0x1
+    return-wide v0
+    .catch Ljava/lang/ArithmeticException; {:try_start .. :try_end} :handler
+
+    :packed
+    .packed-switch 0x1
+        :one
+        :two
+    .end packed-switch
+
+    :sparse
+    .sparse-switch
+        -0x5 -> :one
+        0x3e8 -> :two
+    .end sparse-switch
+
+    :array
+    .array-data 4
+        0x1
+        0x2
+        -0x3
+    .end array-data
+.end method
+"#;
+
+    const PRINTED: &str = r#".class public final LTable;
+.super Ljava/lang/Object;
+.source "Table.java"
+
+
+# static fields
+.field private static final SIZE:I = 0x3
+
+
+# direct methods
+.method public static lookup(IJ)J
+    .registers 8
+    .param p0, "key"    # I
+    .param p1, "fallback"    # J
+
+    .line 5
+    :try_start_0
+    packed-switch p0, :pswitch_data_0
+    sparse-switch p0, :sswitch_data_0
+    move-wide v0, p1
+    return-wide v0
+
+    :pswitch_0
+    :sswitch_0
+    const-wide v0, 0x100000000L
+    return-wide v0
+
+    :pswitch_1
+    :sswitch_1
+    const/4 v2, 0x3
+    new-array v2, v2, [I
+    fill-array-data v2, :array_0
+    const/4 v3, 0x1
+    aget v3, v2, v3
+    int-to-long v0, v3
+    :try_end_0
+    .catch Ljava/lang/ArithmeticException; {:try_start_0 .. :try_end_0} :catch_0
+    return-wide v0
+
+    :catch_0
+    move-exception v2
+    const-wide/16 v0, -0x1L
+    return-wide v0
+
+    :pswitch_data_0
+    .packed-switch 0x1
+        :pswitch_0
+        :pswitch_1
+    .end packed-switch
+
+    :sswitch_data_0
+    .sparse-switch
+        -0x5 -> :sswitch_0
+        0x3e8 -> :sswitch_1
+    .end sparse-switch
+
+    :array_0
+    .array-data 4
+        0x1
+        0x2
+        -0x3
+    .end array-data
+.end method
+"#;
+
+    fn print(source: &str) -> String {
+        let dex_file = dex_file(&[source]);
+        SmaliPrinter::new(&dex_file).print_class(&dex_file.classes[0]).expect("print class")
+    }
+
+    #[test]
+    fn classes_print_with_payloads_handlers_and_wide_registers() {
+        assert_eq!(print(SOURCE), PRINTED);
+    }
+
+    #[test]
+    fn printed_smali_assembles_back_to_the_same_output() {
+        assert_eq!(print(PRINTED), PRINTED);
+    }
+}
@@ -0,0 +1,1396 @@
+use std::collections::HashMap;
+use crate::android::{
+    AccessFlags, Annotation, AnnotationElement, AnnotationVisibility, ClassDef, CodeItem, DebugInfo, DebugPosition, DexFile, EncodedCatchHandler,
+    EncodedTypeAddrPair, EncodedValue, Field, FieldDescriptor, LocalVariable, Method, MethodDescriptor, ProtoDescriptor, TryItem, TypeDescriptor,
+};
+use crate::android_analyzer::dalvik_opcode::{DalvikOpcode, InstructionFormat};
+use crate::android_analyzer::error::{AndroidAnalyzeError, Result};
+use crate::hierarchy::proto_to_descriptor;
+use crate::java_analyzer::ast::parse_method_descriptor;
+
+/// Assembles baksmali-syntax classes back into the DEX model.
+///
+/// Instructions index the target file's tables; strings, types and members the class needs are
+/// appended to them, so the result can be handed to `DexWriter` together with the file.
+pub struct SmaliAssembler<'a> {
+    dex_file: &'a mut DexFile,
+    strings: HashMap<String, u32>,
+    types: HashMap<String, u32>,
+    protos: HashMap<String, u32>,
+    fields: HashMap<String, u32>,
+    methods: HashMap<String, u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Char(u16),
+    LBrace,
+    RBrace,
+    Comma,
+    Newline,
+}
+
+/// 词法分析结果，每个记号带行号
+struct Tokens {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Tokens {
+    fn new(source: &str) -> Result<Self> {
+        let mut tokens = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let chars: Vec<char> = line.chars().collect();
+            let mut i = 0;
+            while i < chars.len() {
+                let c = chars[i];
+                match c {
+                    '#' => break,
+                    c if c.is_whitespace() => i += 1,
+                    '{' => {
+                        tokens.push((Token::LBrace, number));
+                        i += 1;
+                    }
+                    '}' => {
+                        tokens.push((Token::RBrace, number));
+                        i += 1;
+                    }
+                    ',' => {
+                        tokens.push((Token::Comma, number));
+                        i += 1;
+                    }
+                    '"' | '\'' => {
+                        let (units, end) = unescape(&chars, i + 1, c).map_err(|message| parse_error(number, &message))?;
+                        i = end;
+                        if c == '"' {
+                            tokens.push((Token::Str(String::from_utf16_lossy(&units)), number));
+                        } else if units.len() == 1 {
+                            tokens.push((Token::Char(units[0]), number));
+                        } else {
+                            return Err(parse_error(number, "character literal must hold one UTF-16 unit"));
+                        }
+                    }
+                    _ => {
+                        let start = i;
+                        while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '{' | '}' | ',' | '"' | '#') {
+                            i += 1;
+                        }
+                        tokens.push((Token::Word(chars[start..i].iter().collect()), number));
+                    }
+                }
+            }
+            tokens.push((Token::Newline, number));
+        }
+        Ok(Self { tokens, position: 0 })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map(|(_, line)| *line)
+            .unwrap_or(0)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn error(&self, message: &str) -> AndroidAnalyzeError {
+        parse_error(self.line(), message)
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.position += 1;
+        }
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn word(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            other => {
+                self.position -= 1;
+                Err(self.error(&format!("expected a word, found {:?}", other)))
+            }
+        }
+    }
+
+    /// `vN` 或 `pN`，参数寄存器从 `first_parameter` 开始
+    fn register(&mut self, first_parameter: u32) -> Result<u32> {
+        let word = self.word()?;
+        parse_register(&word, first_parameter).ok_or_else(|| self.error(&format!("invalid register {}", word)))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Str(value)) => Ok(value),
+            other => {
+                self.position -= 1;
+                Err(self.error(&format!("expected a string, found {:?}", other)))
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => {
+                self.position -= 1;
+                Err(self.error(&format!("expected {:?}, found {:?}", expected, other)))
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(Token::Newline) => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(other) => Err(self.error(&format!("unexpected {:?}", other))),
+        }
+    }
+
+    /// 当前行剩下的单词
+    fn words_to_end_of_line(&mut self) -> Vec<String> {
+        let mut words = Vec::new();
+        while let Some(Token::Word(word)) = self.peek() {
+            words.push(word.clone());
+            self.position += 1;
+        }
+        words
+    }
+}
+
+fn parse_error(line: usize, message: &str) -> AndroidAnalyzeError {
+    AndroidAnalyzeError::ParseError(format!("line {}: {}", line, message))
+}
+
+/// 解析引号内的转义，返回 UTF-16 码元和结束引号之后的位置
+fn unescape(chars: &[char], mut i: usize, quote: char) -> std::result::Result<(Vec<u16>, usize), String> {
+    let mut units = Vec::new();
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        if c == quote {
+            return Ok((units, i));
+        }
+        if c != '\\' {
+            let mut buffer = [0u16; 2];
+            units.extend_from_slice(c.encode_utf16(&mut buffer));
+            continue;
+        }
+        let escaped = *chars.get(i).ok_or("unterminated escape")?;
+        i += 1;
+        match escaped {
+            'n' => units.push('\n' as u16),
+            'r' => units.push('\r' as u16),
+            't' => units.push('\t' as u16),
+            'b' => units.push(0x08),
+            'f' => units.push(0x0c),
+            '0' => units.push(0),
+            'u' => {
+                let digits: String = chars.get(i..i + 4).ok_or("truncated \\u escape")?.iter().collect();
+                units.push(u16::from_str_radix(&digits, 16).map_err(|_| format!("invalid \\u escape {}", digits))?);
+                i += 4;
+            }
+            other => units.push(other as u16),
+        }
+    }
+    Err("unterminated literal".to_string())
+}
+
+/// 整数字面量：十进制或 0x 十六进制，可带 t/s/L 后缀
+fn parse_integer(word: &str) -> Option<i64> {
+    let (negative, body) = match word.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, word),
+    };
+    let body = body.trim_end_matches(['t', 's', 'L', 'l', 'T', 'S']);
+    let magnitude = if let Some(hex) = body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()?
+    } else {
+        body.parse::<u64>().ok()?
+    };
+    Some(if negative { (magnitude as i64).wrapping_neg() } else { magnitude as i64 })
+}
+
+fn parse_float(word: &str) -> Option<f64> {
+    match word.trim_end_matches(['f', 'F', 'd', 'D']) {
+        "NaN" => Some(f64::NAN),
+        "Infinity" => Some(f64::INFINITY),
+        "-Infinity" => Some(f64::NEG_INFINITY),
+        other => other.parse().ok(),
+    }
+}
+
+fn parse_register(word: &str, first_parameter: u32) -> Option<u32> {
+    match word.split_at(1.min(word.len())) {
+        ("v", number) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => number.parse().ok(),
+        ("p", number) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => number.parse::<u32>().ok().map(|n| n + first_parameter),
+        _ => None,
+    }
+}
+
+fn type_descriptor(descriptor: &str) -> TypeDescriptor {
+    TypeDescriptor { descriptor: descriptor.to_string() }
+}
+
+fn parse_proto(descriptor: &str) -> Result<ProtoDescriptor> {
+    if !descriptor.starts_with('(') || !descriptor.contains(')') {
+        return Err(AndroidAnalyzeError::ParseError(format!("invalid method prototype {}", descriptor)));
+    }
+    let (parameters, return_type) = parse_method_descriptor(descriptor);
+    let shorty = std::iter::once(&return_type)
+        .chain(parameters.iter())
+        .map(|d| match d.chars().next() {
+            Some('L') | Some('[') => 'L',
+            Some(c) => c,
+            None => 'V',
+        })
+        .collect();
+    Ok(ProtoDescriptor {
+        shorty,
+        return_type: type_descriptor(&return_type),
+        parameters: parameters.iter().map(|p| type_descriptor(p)).collect(),
+    })
+}
+
+/// `Lcom/a/B;->name:I`
+fn parse_field_reference(word: &str) -> Result<FieldDescriptor> {
+    let (class, member) = word.split_once("->").ok_or_else(|| AndroidAnalyzeError::ParseError(format!("invalid field reference {}", word)))?;
+    let (name, field_type) = member.split_once(':').ok_or_else(|| AndroidAnalyzeError::ParseError(format!("invalid field reference {}", word)))?;
+    Ok(FieldDescriptor { class_type: type_descriptor(class), field_type: type_descriptor(field_type), name: name.to_string() })
+}
+
+/// `Lcom/a/B;->name(I)V`
+fn parse_method_reference(word: &str) -> Result<MethodDescriptor> {
+    let (class, member) = word.split_once("->").ok_or_else(|| AndroidAnalyzeError::ParseError(format!("invalid method reference {}", word)))?;
+    let paren = member.find('(').ok_or_else(|| AndroidAnalyzeError::ParseError(format!("invalid method reference {}", word)))?;
+    Ok(MethodDescriptor { class_type: type_descriptor(class), proto: parse_proto(&member[paren..])?, name: member[..paren].to_string() })
+}
+
+fn access_flag(name: &str) -> Option<u32> {
+    Some(match name {
+        "public" => AccessFlags::PUBLIC,
+        "private" => AccessFlags::PRIVATE,
+        "protected" => AccessFlags::PROTECTED,
+        "static" => AccessFlags::STATIC,
+        "final" => AccessFlags::FINAL,
+        "synchronized" => AccessFlags::SYNCHRONIZED,
+        "volatile" => AccessFlags::VOLATILE,
+        "bridge" => AccessFlags::BRIDGE,
+        "transient" => AccessFlags::TRANSIENT,
+        "varargs" => AccessFlags::VARARGS,
+        "native" => AccessFlags::NATIVE,
+        "interface" => AccessFlags::INTERFACE,
+        "abstract" => AccessFlags::ABSTRACT,
+        "strictfp" => AccessFlags::STRICT,
+        "synthetic" => AccessFlags::SYNTHETIC,
+        "annotation" => AccessFlags::ANNOTATION,
+        "enum" => AccessFlags::ENUM,
+        "constructor" => AccessFlags::CONSTRUCTOR,
+        "declared-synchronized" => AccessFlags::DECLARED_SYNCHRONIZED,
+        _ => return None,
+    })
+}
+
+/// 访问标志后面跟一个成员描述，返回 (标志, 描述)
+fn flags_and_name(tokens: &Tokens, mut words: Vec<String>) -> Result<(u32, String)> {
+    let name = words.pop().ok_or_else(|| tokens.error("missing name"))?;
+    let mut flags = 0;
+    for word in &words {
+        flags |= access_flag(word).ok_or_else(|| tokens.error(&format!("unknown access flag {}", word)))?;
+    }
+    Ok((flags, name))
+}
+
+/// 指令操作数
+#[derive(Debug)]
+enum Operand {
+    Register(u32),
+    Registers(Vec<u32>),
+    Literal(i64),
+    Label(String),
+    Reference(Reference),
+}
+
+#[derive(Debug)]
+enum Reference {
+    String(String),
+    Type(String),
+    Field(FieldDescriptor),
+    Method(MethodDescriptor),
+    Proto(ProtoDescriptor),
+    Index(u32),
+}
+
+/// 一个 try 范围：(起止地址, 按类型捕获, catch-all 地址)
+type TryGroup = ((u32, u32), Vec<EncodedTypeAddrPair>, Option<u32>);
+
+/// 方法体中的一行
+#[derive(Debug)]
+enum BodyItem {
+    Instruction { opcode: DalvikOpcode, operands: Vec<Operand>, line: usize },
+    Label(String),
+    PackedSwitch { first_key: i32, labels: Vec<String> },
+    SparseSwitch { keys: Vec<i32>, labels: Vec<String> },
+    ArrayData { width: u16, elements: Vec<i64> },
+    Line(u32),
+    Source(String),
+    Prologue,
+    Epilogue,
+    Local { register: u32, name: Option<String>, local_type: Option<String>, signature: Option<String> },
+    EndLocal(u32),
+    RestartLocal(u32),
+    Catch { catch_type: Option<String>, start: String, end: String, handler: String },
+}
+
+impl BodyItem {
+    /// 负载必须从 4 字节边界开始
+    fn is_payload(&self) -> bool {
+        matches!(self, BodyItem::PackedSwitch { .. } | BodyItem::SparseSwitch { .. } | BodyItem::ArrayData { .. })
+    }
+
+    fn size(&self) -> u32 {
+        match self {
+            BodyItem::Instruction { opcode, .. } => opcode.format().size() as u32,
+            BodyItem::PackedSwitch { labels, .. } => 4 + 2 * labels.len() as u32,
+            BodyItem::SparseSwitch { keys, .. } => 2 + 4 * keys.len() as u32,
+            BodyItem::ArrayData { width, elements } => 4 + (*width as u32 * elements.len() as u32).div_ceil(2),
+            _ => 0,
+        }
+    }
+}
+
+impl<'a> SmaliAssembler<'a> {
+    pub fn new(dex_file: &'a mut DexFile) -> Self {
+        let strings = dex_file.strings.iter().enumerate().map(|(i, s)| (s.clone(), i as u32)).collect();
+        let types = dex_file.types.iter().enumerate().map(|(i, t)| (t.descriptor.clone(), i as u32)).collect();
+        let protos = dex_file.protos.iter().enumerate().map(|(i, p)| (proto_to_descriptor(p), i as u32)).collect();
+        let fields = dex_file
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (format!("{}->{}:{}", f.class_type.descriptor, f.name, f.field_type.descriptor), i as u32))
+            .collect();
+        let methods = dex_file
+            .methods
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (format!("{}->{}{}", m.class_type.descriptor, m.name, proto_to_descriptor(&m.proto)), i as u32))
+            .collect();
+        Self { dex_file, strings, types, protos, fields, methods }
+    }
+
+    /// The descriptor named by the `.class` directive, without assembling anything
+    pub fn class_descriptor(source: &str) -> Option<String> {
+        source
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with(".class"))
+            .and_then(|line| line.split('#').next())
+            .and_then(|line| line.split_whitespace().last())
+            .filter(|descriptor| descriptor.starts_with('L'))
+            .map(str::to_string)
+    }
+
+    /// Parses one `.class` file. The class is not added to the DEX file's class list.
+    pub fn assemble(&mut self, source: &str) -> Result<ClassDef> {
+        let mut tokens = Tokens::new(source)?;
+        let mut class_def: Option<ClassDef> = None;
+        loop {
+            tokens.skip_newlines();
+            let Some(directive) = tokens.peek_word().map(str::to_string) else {
+                if tokens.peek().is_none() {
+                    break;
+                }
+                return Err(tokens.error("expected a directive"));
+            };
+            tokens.position += 1;
+            if directive == ".class" {
+                let words = tokens.words_to_end_of_line();
+                let (access_flags, descriptor) = flags_and_name(&tokens, words)?;
+                tokens.end_of_line()?;
+                class_def = Some(ClassDef {
+                    class_type: type_descriptor(&descriptor),
+                    access_flags,
+                    super_type: None,
+                    interfaces: Vec::new(),
+                    source_file: None,
+                    annotations: Vec::new(),
+                    static_fields: Vec::new(),
+                    instance_fields: Vec::new(),
+                    direct_methods: Vec::new(),
+                    virtual_methods: Vec::new(),
+                });
+                continue;
+            }
+            let class = class_def.as_mut().ok_or_else(|| tokens.error(".class must come first"))?;
+            match directive.as_str() {
+                ".super" => class.super_type = Some(type_descriptor(&tokens.word()?)),
+                ".source" => class.source_file = Some(tokens.string()?),
+                ".implements" => class.interfaces.push(type_descriptor(&tokens.word()?)),
+                ".annotation" => class.annotations.push(self.annotation(&mut tokens)?),
+                ".field" => {
+                    let field = self.field(&mut tokens)?;
+                    if field.access_flags & AccessFlags::STATIC != 0 {
+                        class.static_fields.push(field);
+                    } else {
+                        class.instance_fields.push(field);
+                    }
+                    continue;
+                }
+                ".method" => {
+                    let descriptor = class.class_type.descriptor.clone();
+                    let method = self.method(&mut tokens, &descriptor)?;
+                    if method.access_flags & (AccessFlags::STATIC | AccessFlags::PRIVATE | AccessFlags::CONSTRUCTOR) != 0 {
+                        class.direct_methods.push(method);
+                    } else {
+                        class.virtual_methods.push(method);
+                    }
+                    continue;
+                }
+                other => return Err(tokens.error(&format!("unexpected directive {}", other))),
+            }
+            tokens.end_of_line()?;
+        }
+        let class_def = class_def.ok_or_else(|| tokens.error("missing .class directive"))?;
+        self.type_index(&class_def.class_type.descriptor);
+        Ok(class_def)
+    }
+
+    fn string_index(&mut self, value: &str) -> u32 {
+        if let Some(index) = self.strings.get(value) {
+            return *index;
+        }
+        let index = self.dex_file.strings.len() as u32;
+        self.dex_file.strings.push(value.to_string());
+        self.strings.insert(value.to_string(), index);
+        index
+    }
+
+    fn type_index(&mut self, descriptor: &str) -> u32 {
+        if let Some(index) = self.types.get(descriptor) {
+            return *index;
+        }
+        self.string_index(descriptor);
+        let index = self.dex_file.types.len() as u32;
+        self.dex_file.types.push(type_descriptor(descriptor));
+        self.types.insert(descriptor.to_string(), index);
+        index
+    }
+
+    fn proto_index(&mut self, proto: &ProtoDescriptor) -> u32 {
+        let key = proto_to_descriptor(proto);
+        if let Some(index) = self.protos.get(&key) {
+            return *index;
+        }
+        self.string_index(&proto.shorty);
+        self.type_index(&proto.return_type.descriptor);
+        proto.parameters.iter().for_each(|p| {
+            self.type_index(&p.descriptor);
+        });
+        let index = self.dex_file.protos.len() as u32;
+        self.dex_file.protos.push(proto.clone());
+        self.protos.insert(key, index);
+        index
+    }
+
+    fn field_index(&mut self, field: &FieldDescriptor) -> u32 {
+        let key = format!("{}->{}:{}", field.class_type.descriptor, field.name, field.field_type.descriptor);
+        if let Some(index) = self.fields.get(&key) {
+            return *index;
+        }
+        self.type_index(&field.class_type.descriptor);
+        self.type_index(&field.field_type.descriptor);
+        self.string_index(&field.name);
+        let index = self.dex_file.fields.len() as u32;
+        self.dex_file.fields.push(field.clone());
+        self.fields.insert(key, index);
+        index
+    }
+
+    fn method_index(&mut self, method: &MethodDescriptor) -> u32 {
+        let key = format!("{}->{}{}", method.class_type.descriptor, method.name, proto_to_descriptor(&method.proto));
+        if let Some(index) = self.methods.get(&key) {
+            return *index;
+        }
+        self.type_index(&method.class_type.descriptor);
+        self.proto_index(&method.proto);
+        self.string_index(&method.name);
+        let index = self.dex_file.methods.len() as u32;
+        self.dex_file.methods.push(method.clone());
+        self.methods.insert(key, index);
+        index
+    }
+
+    fn reference_index(&mut self, reference: &Reference) -> u32 {
+        match reference {
+            Reference::String(value) => self.string_index(value),
+            Reference::Type(descriptor) => self.type_index(descriptor),
+            Reference::Field(field) => self.field_index(field),
+            Reference::Method(method) => self.method_index(method),
+            Reference::Proto(proto) => self.proto_index(proto),
+            Reference::Index(index) => *index,
+        }
+    }
+
+    /// `.field` 之后：标志、名字和类型、可选初值，以及可选的注解块
+    fn field(&mut self, tokens: &mut Tokens) -> Result<Field> {
+        let mut words = tokens.words_to_end_of_line();
+        let mut value = None;
+        if let Some(position) = words.iter().position(|w| w == "=") {
+            let rest = words.split_off(position);
+            if rest.len() > 1 {
+                // 初值是普通单词，记号已经被吃掉了，退回去重新按值解析
+                tokens.position -= rest.len() - 1;
+            }
+            value = Some(self.encoded_value(tokens)?);
+        }
+        let (access_flags, spec) = flags_and_name(tokens, words)?;
+        let (name, field_type) = spec.split_once(':').ok_or_else(|| tokens.error(&format!("invalid field {}", spec)))?;
+        tokens.end_of_line()?;
+        let mut annotations = Vec::new();
+        let checkpoint = tokens.position;
+        tokens.skip_newlines();
+        if tokens.peek_word() == Some(".annotation") {
+            while tokens.peek_word() == Some(".annotation") {
+                tokens.position += 1;
+                annotations.push(self.annotation(tokens)?);
+                tokens.end_of_line()?;
+                tokens.skip_newlines();
+            }
+            if tokens.word()? != ".end" || tokens.word()? != "field" {
+                return Err(tokens.error("expected .end field"));
+            }
+            tokens.end_of_line()?;
+        } else {
+            tokens.position = checkpoint;
+        }
+        Ok(Field { access_flags, name: name.to_string(), field_type: type_descriptor(field_type), value, annotations, hiddenapi_flags: None })
+    }
+
+    /// `.annotation` 之后：可见性、类型和元素，直到 `.end annotation`
+    fn annotation(&mut self, tokens: &mut Tokens) -> Result<Annotation> {
+        let visibility = match tokens.word()?.as_str() {
+            "build" => AnnotationVisibility::Build,
+            "runtime" => AnnotationVisibility::Runtime,
+            "system" => AnnotationVisibility::System,
+            other => return Err(tokens.error(&format!("unknown annotation visibility {}", other))),
+        };
+        let mut annotation = self.annotation_body(tokens, "annotation")?;
+        annotation.visibility = Some(visibility);
+        Ok(annotation)
+    }
+
+    fn annotation_body(&mut self, tokens: &mut Tokens, end: &str) -> Result<Annotation> {
+        let annotation_type = tokens.word()?;
+        self.type_index(&annotation_type);
+        tokens.end_of_line()?;
+        let mut elements = Vec::new();
+        loop {
+            tokens.skip_newlines();
+            let name = tokens.word()?;
+            if name == ".end" {
+                if tokens.word()? != end {
+                    return Err(tokens.error(&format!("expected .end {}", end)));
+                }
+                break;
+            }
+            if tokens.word()? != "=" {
+                return Err(tokens.error("expected = after annotation element name"));
+            }
+            self.string_index(&name);
+            let value = self.encoded_value(tokens)?;
+            tokens.end_of_line()?;
+            elements.push(AnnotationElement { name, value });
+        }
+        Ok(Annotation { visibility: None, annotation_type: type_descriptor(&annotation_type), elements })
+    }
+
+    fn encoded_value(&mut self, tokens: &mut Tokens) -> Result<EncodedValue> {
+        let value = match tokens.next() {
+            Some(Token::Str(value)) => {
+                self.string_index(&value);
+                EncodedValue::String(value)
+            }
+            Some(Token::Char(value)) => EncodedValue::Char(value),
+            Some(Token::LBrace) => {
+                let mut values = Vec::new();
+                loop {
+                    tokens.skip_newlines();
+                    if tokens.peek() == Some(&Token::RBrace) {
+                        tokens.position += 1;
+                        break;
+                    }
+                    values.push(self.encoded_value(tokens)?);
+                    tokens.skip_newlines();
+                    if tokens.peek() == Some(&Token::Comma) {
+                        tokens.position += 1;
+                    }
+                }
+                EncodedValue::Array(values)
+            }
+            Some(Token::Word(word)) => match word.as_str() {
+                ".subannotation" => EncodedValue::Annotation(self.annotation_body(tokens, "subannotation")?),
+                ".enum" => {
+                    let field = parse_field_reference(&tokens.word()?)?;
+                    self.field_index(&field);
+                    EncodedValue::Enum(field)
+                }
+                "null" => EncodedValue::Null,
+                "true" => EncodedValue::Boolean(true),
+                "false" => EncodedValue::Boolean(false),
+                _ => self.word_value(&word).map_err(|e| tokens.error(&e.to_string()))?,
+            },
+            other => return Err(tokens.error(&format!("expected a value, found {:?}", other))),
+        };
+        Ok(value)
+    }
+
+    fn word_value(&mut self, word: &str) -> Result<EncodedValue> {
+        if let Some(index) = word.strip_prefix("method_handle_") {
+            return index.parse().map(EncodedValue::MethodHandle).map_err(|_| AndroidAnalyzeError::ParseError(format!("invalid method handle {}", word)));
+        }
+        if word.contains("->") {
+            return Ok(if word.contains('(') {
+                let method = parse_method_reference(word)?;
+                self.method_index(&method);
+                EncodedValue::Method(method)
+            } else {
+                let field = parse_field_reference(word)?;
+                self.field_index(&field);
+                EncodedValue::Field(field)
+            });
+        }
+        if word.starts_with('(') {
+            let proto = parse_proto(word)?;
+            self.proto_index(&proto);
+            return Ok(EncodedValue::MethodType(proto));
+        }
+        if word.starts_with('L') || word.starts_with('[') || (word.len() == 1 && "VZBSCIJFD".contains(word)) {
+            self.type_index(word);
+            return Ok(EncodedValue::Type(type_descriptor(word)));
+        }
+        let hex = word.trim_start_matches('-').starts_with("0x");
+        if !hex {
+            // 十进制的浮点数：带 f 后缀为 float，否则带小数点、指数或是特殊值为 double
+            let is_float = word.ends_with(['f', 'F']) && parse_float(word).is_some();
+            if is_float {
+                return Ok(EncodedValue::Float(parse_float(word).unwrap_or_default() as f32));
+            }
+            let is_double = word.ends_with(['d', 'D']) || word.contains(['.', 'e', 'E']) || word.contains("Infinity") || word == "NaN";
+            if is_double {
+                return parse_float(word).map(EncodedValue::Double).ok_or_else(|| AndroidAnalyzeError::ParseError(format!("invalid number {}", word)));
+            }
+        }
+        let value = parse_integer(word).ok_or_else(|| AndroidAnalyzeError::ParseError(format!("invalid value {}", word)))?;
+        Ok(match word.chars().last() {
+            Some('t') | Some('T') => EncodedValue::Byte(value as i8),
+            Some('s') | Some('S') => EncodedValue::Short(value as i16),
+            Some('L') | Some('l') => EncodedValue::Long(value),
+            _ => EncodedValue::Int(value as i32),
+        })
+    }
+
+    /// `.method` 之后直到 `.end method`
+    fn method(&mut self, tokens: &mut Tokens, class: &str) -> Result<Method> {
+        let words = tokens.words_to_end_of_line();
+        let (access_flags, spec) = flags_and_name(tokens, words)?;
+        tokens.end_of_line()?;
+        let paren = spec.find('(').ok_or_else(|| tokens.error(&format!("invalid method {}", spec)))?;
+        let name = spec[..paren].to_string();
+        let proto = parse_proto(&spec[paren..]).map_err(|e| tokens.error(&e.to_string()))?;
+        self.method_index(&MethodDescriptor { class_type: type_descriptor(class), proto: proto.clone(), name: name.clone() });
+
+        // 参数寄存器：非静态方法的 p0 是 this，long/double 占两个
+        let is_static = access_flags & AccessFlags::STATIC != 0;
+        let mut parameter_registers = Vec::new();
+        let mut ins_size = if is_static { 0 } else { 1 };
+        for parameter in &proto.parameters {
+            parameter_registers.push(ins_size);
+            ins_size += if matches!(parameter.descriptor.as_str(), "J" | "D") { 2 } else { 1 };
+        }
+
+        let mut registers_size: Option<u32> = None;
+        let mut annotations = Vec::new();
+        let mut parameter_names: Vec<Option<String>> = vec![None; proto.parameters.len()];
+        let mut parameter_annotations: Vec<Vec<Annotation>> = vec![Vec::new(); proto.parameters.len()];
+        let mut items = Vec::new();
+        loop {
+            tokens.skip_newlines();
+            let line = tokens.line();
+            match tokens.next() {
+                Some(Token::Word(word)) => match word.as_str() {
+                    ".end" if tokens.peek_word() == Some("method") => {
+                        tokens.position += 1;
+                        tokens.end_of_line()?;
+                        break;
+                    }
+                    ".registers" | ".locals" => {
+                        let count = parse_integer(&tokens.word()?).ok_or_else(|| tokens.error("invalid register count"))? as u32;
+                        registers_size = Some(if word == ".locals" { count + ins_size } else { count });
+                    }
+                    ".param" => {
+                        let register = tokens.word()?;
+                        let index = register
+                            .strip_prefix('p')
+                            .and_then(|n| n.parse::<u32>().ok())
+                            .and_then(|n| parameter_registers.iter().position(|r| *r == n))
+                            .ok_or_else(|| tokens.error(&format!("{} is not a parameter register", register)))?;
+                        if tokens.peek() == Some(&Token::Comma) {
+                            tokens.position += 1;
+                            let name = tokens.string()?;
+                            self.string_index(&name);
+                            parameter_names[index] = Some(name);
+                        }
+                        tokens.end_of_line()?;
+                        let checkpoint = tokens.position;
+                        tokens.skip_newlines();
+                        if tokens.peek_word() == Some(".annotation") {
+                            while tokens.peek_word() == Some(".annotation") {
+                                tokens.position += 1;
+                                parameter_annotations[index].push(self.annotation(tokens)?);
+                                tokens.end_of_line()?;
+                                tokens.skip_newlines();
+                            }
+                            if tokens.word()? != ".end" || tokens.word()? != "param" {
+                                return Err(tokens.error("expected .end param"));
+                            }
+                        } else {
+                            tokens.position = checkpoint;
+                            continue;
+                        }
+                    }
+                    ".annotation" => annotations.push(self.annotation(tokens)?),
+                    _ => {
+                        let registers = registers_size.unwrap_or(ins_size);
+                        let first_parameter = registers.checked_sub(ins_size).ok_or_else(|| tokens.error("fewer registers than parameters"))?;
+                        items.push(self.body_item(tokens, &word, line, first_parameter)?);
+                    }
+                },
+                other => return Err(tokens.error(&format!("unexpected {:?}", other))),
+            }
+            tokens.end_of_line()?;
+        }
+
+        let has_code = registers_size.is_some() || items.iter().any(|item| matches!(item, BodyItem::Instruction { .. }));
+        let code = if has_code {
+            let registers = registers_size.unwrap_or(ins_size);
+            Some(self.code(items, registers as u16, ins_size as u16, parameter_names).map_err(|e| match e {
+                AndroidAnalyzeError::ParseError(message) => AndroidAnalyzeError::ParseError(format!("{} in {}", message, spec)),
+                other => other,
+            })?)
+        } else {
+            None
+        };
+        if parameter_annotations.iter().all(Vec::is_empty) {
+            parameter_annotations.clear();
+        }
+        Ok(Method { access_flags, name, proto, code, annotations, parameter_annotations, hiddenapi_flags: None })
+    }
+
+    fn body_item(&mut self, tokens: &mut Tokens, word: &str, line: usize, first_parameter: u32) -> Result<BodyItem> {
+        if let Some(label) = word.strip_prefix(':') {
+            return Ok(BodyItem::Label(label.to_string()));
+        }
+        let label = |tokens: &mut Tokens| -> Result<String> {
+            let word = tokens.word()?;
+            word.strip_prefix(':').map(str::to_string).ok_or_else(|| tokens.error(&format!("expected a label, found {}", word)))
+        };
+        let item = match word {
+            ".line" => BodyItem::Line(parse_integer(&tokens.word()?).ok_or_else(|| tokens.error("invalid line number"))? as u32),
+            ".source" => BodyItem::Source(tokens.string()?),
+            ".prologue" => BodyItem::Prologue,
+            ".epilogue" => BodyItem::Epilogue,
+            ".local" => {
+                let register = tokens.register(first_parameter)?;
+                tokens.expect(Token::Comma)?;
+                // 名字是字符串或 null，类型以 :T 紧跟其后
+                let (name, type_word) = match tokens.next() {
+                    Some(Token::Str(name)) => (Some(name), tokens.peek_word().filter(|w| w.starts_with(':')).map(str::to_string)),
+                    Some(Token::Word(word)) if word.starts_with("null") => (None, Some(word["null".len()..].to_string())),
+                    other => return Err(tokens.error(&format!("invalid local name {:?}", other))),
+                };
+                if name.is_some() && type_word.is_some() {
+                    tokens.position += 1;
+                }
+                let local_type = type_word.and_then(|t| t.strip_prefix(':').map(str::to_string)).filter(|t| !t.is_empty());
+                let signature = if tokens.peek() == Some(&Token::Comma) {
+                    tokens.position += 1;
+                    Some(tokens.string()?)
+                } else {
+                    None
+                };
+                name.iter().chain(&signature).for_each(|s| {
+                    self.string_index(s);
+                });
+                local_type.iter().for_each(|t| {
+                    self.type_index(t);
+                });
+                BodyItem::Local { register, name, local_type, signature }
+            }
+            ".end" => {
+                let what = tokens.word()?;
+                match what.as_str() {
+                    "local" => BodyItem::EndLocal(tokens.register(first_parameter)?),
+                    _ => return Err(tokens.error(&format!("unexpected .end {}", what))),
+                }
+            }
+            ".restart" => {
+                if tokens.word()? != "local" {
+                    return Err(tokens.error("expected .restart local"));
+                }
+                BodyItem::RestartLocal(tokens.register(first_parameter)?)
+            }
+            ".catch" | ".catchall" => {
+                let catch_type = if word == ".catch" {
+                    let catch_type = tokens.word()?;
+                    self.type_index(&catch_type);
+                    Some(catch_type)
+                } else {
+                    None
+                };
+                tokens.expect(Token::LBrace)?;
+                let start = label(tokens)?;
+                if tokens.word()? != ".." {
+                    return Err(tokens.error("expected .. in try range"));
+                }
+                let end = label(tokens)?;
+                tokens.expect(Token::RBrace)?;
+                BodyItem::Catch { catch_type, start, end, handler: label(tokens)? }
+            }
+            ".packed-switch" => {
+                let first_key = parse_integer(&tokens.word()?).ok_or_else(|| tokens.error("invalid first key"))? as i32;
+                let mut labels = Vec::new();
+                loop {
+                    tokens.end_of_line()?;
+                    tokens.skip_newlines();
+                    if tokens.peek_word() == Some(".end") {
+                        tokens.position += 1;
+                        tokens.word()?;
+                        break;
+                    }
+                    labels.push(label(tokens)?);
+                }
+                BodyItem::PackedSwitch { first_key, labels }
+            }
+            ".sparse-switch" => {
+                let (mut keys, mut labels) = (Vec::new(), Vec::new());
+                loop {
+                    tokens.end_of_line()?;
+                    tokens.skip_newlines();
+                    let key = tokens.word()?;
+                    if key == ".end" {
+                        tokens.word()?;
+                        break;
+                    }
+                    keys.push(parse_integer(&key).ok_or_else(|| tokens.error("invalid switch key"))? as i32);
+                    if tokens.word()? != "->" {
+                        return Err(tokens.error("expected -> in sparse switch"));
+                    }
+                    labels.push(label(tokens)?);
+                }
+                BodyItem::SparseSwitch { keys, labels }
+            }
+            ".array-data" => {
+                let width = parse_integer(&tokens.word()?).filter(|w| matches!(w, 1 | 2 | 4 | 8)).ok_or_else(|| tokens.error("invalid element width"))? as u16;
+                let mut elements = Vec::new();
+                loop {
+                    tokens.end_of_line()?;
+                    tokens.skip_newlines();
+                    let element = tokens.word()?;
+                    if element == ".end" {
+                        tokens.word()?;
+                        break;
+                    }
+                    let value = match parse_integer(&element) {
+                        Some(value) => value,
+                        // 浮点数组按位存储
+                        None => match parse_float(&element) {
+                            Some(value) if width == 4 => (value as f32).to_bits() as i64,
+                            Some(value) if width == 8 => value.to_bits() as i64,
+                            _ => return Err(tokens.error(&format!("invalid array element {}", element))),
+                        },
+                    };
+                    elements.push(value);
+                }
+                BodyItem::ArrayData { width, elements }
+            }
+            mnemonic => {
+                let opcode = DalvikOpcode::from_name(mnemonic).ok_or_else(|| tokens.error(&format!("unknown instruction {}", mnemonic)))?;
+                let mut operands = Vec::new();
+                while !matches!(tokens.peek(), None | Some(Token::Newline)) {
+                    if !operands.is_empty() {
+                        tokens.expect(Token::Comma)?;
+                    }
+                    operands.push(self.operand(tokens, first_parameter)?);
+                }
+                BodyItem::Instruction { opcode, operands, line }
+            }
+        };
+        Ok(item)
+    }
+
+    fn operand(&mut self, tokens: &mut Tokens, first_parameter: u32) -> Result<Operand> {
+        match tokens.next() {
+            Some(Token::LBrace) => {
+                let mut registers = Vec::new();
+                while tokens.peek() != Some(&Token::RBrace) {
+                    if !registers.is_empty() {
+                        if tokens.peek_word() == Some("..") {
+                            tokens.position += 1;
+                            let last = tokens.register(first_parameter)?;
+                            let first = registers[0];
+                            if last < first {
+                                return Err(tokens.error("invalid register range"));
+                            }
+                            registers = (first..=last).collect();
+                            continue;
+                        }
+                        tokens.expect(Token::Comma)?;
+                    }
+                    registers.push(tokens.register(first_parameter)?);
+                }
+                tokens.position += 1;
+                Ok(Operand::Registers(registers))
+            }
+            Some(Token::Str(value)) => Ok(Operand::Reference(Reference::String(value))),
+            Some(Token::Word(word)) => {
+                if let Some(label) = word.strip_prefix(':') {
+                    return Ok(Operand::Label(label.to_string()));
+                }
+                if let Some(number) = word.strip_prefix("call_site_").or_else(|| word.strip_prefix("method_handle_")) {
+                    return number.parse().map(|n| Operand::Reference(Reference::Index(n))).map_err(|_| tokens.error(&format!("invalid index {}", word)));
+                }
+                if let Some(register) = parse_register(&word, first_parameter) {
+                    return Ok(Operand::Register(register));
+                }
+                if let Some(value) = parse_integer(&word) {
+                    return Ok(Operand::Literal(value));
+                }
+                let reference = if word.contains("->") {
+                    if word.contains('(') {
+                        Reference::Method(parse_method_reference(&word).map_err(|e| tokens.error(&e.to_string()))?)
+                    } else {
+                        Reference::Field(parse_field_reference(&word).map_err(|e| tokens.error(&e.to_string()))?)
+                    }
+                } else if word.starts_with('(') {
+                    Reference::Proto(parse_proto(&word).map_err(|e| tokens.error(&e.to_string()))?)
+                } else {
+                    Reference::Type(word)
+                };
+                Ok(Operand::Reference(reference))
+            }
+            other => Err(tokens.error(&format!("unexpected operand {:?}", other))),
+        }
+    }
+
+    /// 计算地址、解析标签并编码指令、负载、try 块和调试信息
+    fn code(&mut self, items: Vec<BodyItem>, registers_size: u16, ins_size: u16, parameter_names: Vec<Option<String>>) -> Result<CodeItem> {
+        // 第一遍：地址和标签，负载前按需插入对齐用的 nop
+        let mut addresses = Vec::with_capacity(items.len());
+        let mut labels: HashMap<&str, u32> = HashMap::new();
+        let mut pending_labels: Vec<&str> = Vec::new();
+        let mut padding = Vec::new();
+        let mut address = 0u32;
+        for item in &items {
+            if item.is_payload() && address % 2 == 1 {
+                padding.push(address);
+                address += 1;
+                // 紧挨着负载的标签指向负载本身
+                for label in &pending_labels {
+                    labels.insert(*label, address);
+                }
+            }
+            addresses.push(address);
+            match item {
+                BodyItem::Label(name) => {
+                    if labels.insert(name.as_str(), address).is_some() {
+                        return Err(AndroidAnalyzeError::ParseError(format!("duplicate label :{}", name)));
+                    }
+                    pending_labels.push(name);
+                }
+                BodyItem::Instruction { .. } => pending_labels.clear(),
+                _ if item.is_payload() => pending_labels.clear(),
+                _ => {}
+            }
+            address += item.size();
+        }
+        let insns_size = address;
+        let resolve = |name: &str| -> Result<u32> { labels.get(name).copied().ok_or_else(|| AndroidAnalyzeError::ParseError(format!("undefined label :{}", name))) };
+
+        // switch 负载的目标相对于引用它的 switch 指令
+        let mut switch_addresses: HashMap<u32, u32> = HashMap::new();
+        for (item, address) in items.iter().zip(&addresses) {
+            if let BodyItem::Instruction { opcode: DalvikOpcode::PackedSwitch | DalvikOpcode::SparseSwitch, operands, .. } = item {
+                if let Some(Operand::Label(name)) = operands.iter().find(|o| matches!(o, Operand::Label(_))) {
+                    switch_addresses.insert(resolve(name)?, *address);
+                }
+            }
+        }
+
+        // 第二遍：编码
+        let mut insns: Vec<u16> = Vec::with_capacity(insns_size as usize);
+        let mut outs_size = 0u16;
+        for (item, address) in items.iter().zip(&addresses) {
+            while (insns.len() as u32) < *address && padding.contains(&(insns.len() as u32)) {
+                insns.push(DalvikOpcode::Nop as u16);
+            }
+            match item {
+                BodyItem::Instruction { opcode, operands, line } => {
+                    let units = self
+                        .encode(*opcode, operands, *address, &resolve)
+                        .map_err(|e| match e {
+                            AndroidAnalyzeError::ParseError(message) => parse_error(*line, &message),
+                            other => other,
+                        })?;
+                    if opcode.is_invoke() || matches!(opcode, DalvikOpcode::FilledNewArray | DalvikOpcode::FilledNewArrayRange) {
+                        let count = operands.iter().find_map(|o| match o {
+                            Operand::Registers(registers) => Some(registers.len() as u16),
+                            _ => None,
+                        });
+                        outs_size = outs_size.max(count.unwrap_or(0));
+                    }
+                    insns.extend(units);
+                }
+                BodyItem::PackedSwitch { first_key, labels } => {
+                    let base = switch_addresses.get(address).copied().unwrap_or(*address);
+                    insns.push(0x0100);
+                    insns.push(labels.len() as u16);
+                    insns.push(*first_key as u16);
+                    insns.push((*first_key >> 16) as u16);
+                    for label in labels {
+                        let offset = resolve(label)? as i32 - base as i32;
+                        insns.push(offset as u16);
+                        insns.push((offset >> 16) as u16);
+                    }
+                }
+                BodyItem::SparseSwitch { keys, labels } => {
+                    let base = switch_addresses.get(address).copied().unwrap_or(*address);
+                    insns.push(0x0200);
+                    insns.push(keys.len() as u16);
+                    for key in keys {
+                        insns.push(*key as u16);
+                        insns.push((*key >> 16) as u16);
+                    }
+                    for label in labels {
+                        let offset = resolve(label)? as i32 - base as i32;
+                        insns.push(offset as u16);
+                        insns.push((offset >> 16) as u16);
+                    }
+                }
+                BodyItem::ArrayData { width, elements } => {
+                    insns.push(0x0300);
+                    insns.push(*width);
+                    insns.push(elements.len() as u16);
+                    insns.push((elements.len() >> 16) as u16);
+                    let mut bytes: Vec<u8> = elements.iter().flat_map(|e| e.to_le_bytes()[..*width as usize].to_vec()).collect();
+                    if bytes.len() % 2 == 1 {
+                        bytes.push(0);
+                    }
+                    insns.extend(bytes.chunks(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])));
+                }
+                _ => {}
+            }
+        }
+
+        let (tries, handlers) = self.tries(&items, &resolve)?;
+        let debug_info = debug_info(&items, &addresses, insns_size, parameter_names);
+        Ok(CodeItem {
+            registers_size,
+            ins_size,
+            outs_size,
+            tries_size: tries.len() as u16,
+            debug_info_offset: 0,
+            insns_size,
+            insns,
+            tries,
+            handlers,
+            debug_info,
+        })
+    }
+
+    /// `.catch` 按 try 范围分组，同一范围的处理器合成一个列表
+    fn tries(&mut self, items: &[BodyItem], resolve: &dyn Fn(&str) -> Result<u32>) -> Result<(Vec<TryItem>, Vec<EncodedCatchHandler>)> {
+        let mut groups: Vec<TryGroup> = Vec::new();
+        for item in items {
+            let BodyItem::Catch { catch_type, start, end, handler } = item else { continue };
+            let range = (resolve(start)?, resolve(end)?);
+            if range.1 <= range.0 || range.1 - range.0 > u16::MAX as u32 {
+                return Err(AndroidAnalyzeError::ParseError(format!("invalid try range :{} .. :{}", start, end)));
+            }
+            let index = match groups.iter().position(|(r, _, _)| *r == range) {
+                Some(index) => index,
+                None => {
+                    groups.push((range, Vec::new(), None));
+                    groups.len() - 1
+                }
+            };
+            let addr = resolve(handler)?;
+            match catch_type {
+                Some(catch_type) => {
+                    let type_idx = self.type_index(catch_type);
+                    groups[index].1.push(EncodedTypeAddrPair { type_idx, addr });
+                }
+                None => groups[index].2 = Some(addr),
+            }
+        }
+        groups.sort_by_key(|(range, _, _)| *range);
+        if groups.windows(2).any(|pair| pair[0].0 .1 > pair[1].0 .0) {
+            return Err(AndroidAnalyzeError::ParseError("overlapping try ranges must share the same bounds".to_string()));
+        }
+
+        // 偏移按编码后的字节数计算，与读取时的含义一致
+        let mut offset = uleb128_size(groups.len() as u32);
+        let mut tries = Vec::with_capacity(groups.len());
+        let mut handlers = Vec::with_capacity(groups.len());
+        for ((start, end), pairs, catch_all_addr) in groups {
+            let size = if catch_all_addr.is_some() { -(pairs.len() as i32) } else { pairs.len() as i32 };
+            tries.push(TryItem { start_addr: start, insn_count: (end - start) as u16, handler_offset: offset as u16 });
+            let length = sleb128_size(size)
+                + pairs.iter().map(|p| uleb128_size(p.type_idx) + uleb128_size(p.addr)).sum::<usize>()
+                + catch_all_addr.map(uleb128_size).unwrap_or(0);
+            handlers.push(EncodedCatchHandler { offset: offset as u16, size, handlers: pairs, catch_all_addr });
+            offset += length;
+        }
+        Ok((tries, handlers))
+    }
+
+    fn encode(&mut self, opcode: DalvikOpcode, operands: &[Operand], address: u32, resolve: &dyn Fn(&str) -> Result<u32>) -> Result<Vec<u16>> {
+        let mut registers = Vec::new();
+        let mut literal = None;
+        let mut offset = None;
+        let mut references = Vec::new();
+        for operand in operands {
+            match operand {
+                Operand::Register(register) => registers.push(*register),
+                Operand::Registers(list) => registers.extend(list),
+                Operand::Literal(value) => literal = Some(*value),
+                Operand::Label(name) => offset = Some(resolve(name)? as i64 - address as i64),
+                Operand::Reference(reference) => references.push(self.reference_index(reference)),
+            }
+        }
+        let op = opcode as u16;
+        let name = opcode.name();
+        let register = |index: usize, bits: u32| -> Result<u16> {
+            let value = *registers.get(index).ok_or_else(|| AndroidAnalyzeError::ParseError(format!("{} is missing a register operand", name)))?;
+            if value >= 1 << bits {
+                return Err(AndroidAnalyzeError::ParseError(format!("register v{} does not fit {} in {}", value, bits, name)));
+            }
+            Ok(value as u16)
+        };
+        let signed = |value: Option<i64>, bits: u32, what: &str| -> Result<i64> {
+            let value = value.ok_or_else(|| AndroidAnalyzeError::ParseError(format!("{} is missing its {}", name, what)))?;
+            let limit = 1i64 << (bits - 1);
+            if value < -limit || value >= limit {
+                return Err(AndroidAnalyzeError::ParseError(format!("{} {} out of range for {}", what, value, name)));
+            }
+            Ok(value)
+        };
+        let index = |position: usize, bits: u32| -> Result<u32> {
+            let value = *references.get(position).ok_or_else(|| AndroidAnalyzeError::ParseError(format!("{} is missing a reference", name)))?;
+            if bits < 32 && value >= 1 << bits {
+                return Err(AndroidAnalyzeError::ParseError(format!("index {} does not fit {} bits in {}", value, bits, name)));
+            }
+            Ok(value)
+        };
+        let register_list = || -> Result<(u16, u16)> {
+            // 35c 的 C..F 放在第三个单元，第五个寄存器放在 G
+            if registers.len() > 5 {
+                return Err(AndroidAnalyzeError::ParseError(format!("{} takes at most 5 registers", name)));
+            }
+            let mut packed = 0u16;
+            for (i, register) in registers.iter().take(4).enumerate() {
+                if *register >= 16 {
+                    return Err(AndroidAnalyzeError::ParseError(format!("register v{} does not fit 4 bits in {}", register, name)));
+                }
+                packed |= (*register as u16) << (4 * i);
+            }
+            let g = match registers.get(4) {
+                Some(register) if *register >= 16 => return Err(AndroidAnalyzeError::ParseError(format!("register v{} does not fit 4 bits in {}", register, name))),
+                Some(register) => *register as u16,
+                None => 0,
+            };
+            Ok((op | (registers.len() as u16) << 12 | g << 8, packed))
+        };
+        let register_range = || -> Result<(u16, u16)> {
+            let first = registers.first().copied().unwrap_or(0);
+            if registers.len() > 255 || first > 0xffff {
+                return Err(AndroidAnalyzeError::ParseError(format!("register range out of bounds in {}", name)));
+            }
+            Ok((op | (registers.len() as u16) << 8, first as u16))
+        };
+
+        use InstructionFormat::*;
+        let units = match opcode.format() {
+            F10x => vec![op],
+            F12x => vec![op | register(0, 4)? << 8 | register(1, 4)? << 12],
+            F11n => vec![op | register(0, 4)? << 8 | ((signed(literal, 4, "literal")? as u16) & 0xf) << 12],
+            F11x => vec![op | register(0, 8)? << 8],
+            F10t => vec![op | ((signed(offset, 8, "branch offset")? as i8 as u8) as u16) << 8],
+            F20t => vec![op, signed(offset, 16, "branch offset")? as u16],
+            F22x => vec![op | register(0, 8)? << 8, register(1, 16)?],
+            F21t => vec![op | register(0, 8)? << 8, signed(offset, 16, "branch offset")? as u16],
+            F21s => vec![op | register(0, 8)? << 8, signed(literal, 16, "literal")? as u16],
+            F21h => {
+                let shift = if opcode == DalvikOpcode::ConstWideHigh16 { 48 } else { 16 };
+                let value = literal.ok_or_else(|| AndroidAnalyzeError::ParseError(format!("{} is missing its literal", name)))?;
+                let high = value >> shift;
+                let fits = if shift == 16 { (-0x8000..0x8000).contains(&high) } else { true };
+                if value & ((1i64 << shift) - 1) != 0 || !fits {
+                    return Err(AndroidAnalyzeError::ParseError(format!("literal 0x{:x} cannot be encoded by {}", value, name)));
+                }
+                vec![op | register(0, 8)? << 8, high as u16]
+            }
+            F21c => vec![op | register(0, 8)? << 8, index(0, 16)? as u16],
+            F23x => vec![op | register(0, 8)? << 8, register(1, 8)? | register(2, 8)? << 8],
+            F22b => vec![op | register(0, 8)? << 8, register(1, 8)? | ((signed(literal, 8, "literal")? as i8 as u8) as u16) << 8],
+            F22t => vec![op | register(0, 4)? << 8 | register(1, 4)? << 12, signed(offset, 16, "branch offset")? as u16],
+            F22s => vec![op | register(0, 4)? << 8 | register(1, 4)? << 12, signed(literal, 16, "literal")? as u16],
+            F22c => vec![op | register(0, 4)? << 8 | register(1, 4)? << 12, index(0, 16)? as u16],
+            F30t => {
+                let value = signed(offset, 32, "branch offset")?;
+                vec![op, value as u16, (value >> 16) as u16]
+            }
+            F32x => vec![op, register(0, 16)?, register(1, 16)?],
+            F31i => {
+                let value = literal.ok_or_else(|| AndroidAnalyzeError::ParseError(format!("{} is missing its literal", name)))?;
+                // const 接受按 32 位书写的无符号值
+                if value < i32::MIN as i64 || value > u32::MAX as i64 {
+                    return Err(AndroidAnalyzeError::ParseError(format!("literal {} out of range for {}", value, name)));
+                }
+                vec![op | register(0, 8)? << 8, value as u16, (value >> 16) as u16]
+            }
+            F31t => {
+                let value = signed(offset, 32, "branch offset")?;
+                vec![op | register(0, 8)? << 8, value as u16, (value >> 16) as u16]
+            }
+            F31c => {
+                let value = index(0, 32)?;
+                vec![op | register(0, 8)? << 8, value as u16, (value >> 16) as u16]
+            }
+            F35c => {
+                let (first, packed) = register_list()?;
+                vec![first, index(0, 16)? as u16, packed]
+            }
+            F3rc => {
+                let (first, start) = register_range()?;
+                vec![first, index(0, 16)? as u16, start]
+            }
+            F45cc => {
+                let (first, packed) = register_list()?;
+                vec![first, index(0, 16)? as u16, packed, index(1, 16)? as u16]
+            }
+            F4rcc => {
+                let (first, start) = register_range()?;
+                vec![first, index(0, 16)? as u16, start, index(1, 16)? as u16]
+            }
+            F51l => {
+                let value = literal.ok_or_else(|| AndroidAnalyzeError::ParseError(format!("{} is missing its literal", name)))?;
+                vec![op | register(0, 8)? << 8, value as u16, (value >> 16) as u16, (value >> 32) as u16, (value >> 48) as u16]
+            }
+        };
+        Ok(units)
+    }
+}
+
+/// 由 `.line`、`.local` 等指令重建行号表和局部变量范围，没有调试指令时返回 None
+fn debug_info(items: &[BodyItem], addresses: &[u32], insns_size: u32, parameter_names: Vec<Option<String>>) -> Option<DebugInfo> {
+    let mut debug = DebugInfo {
+        line_start: 0,
+        parameter_names,
+        positions: Vec::new(),
+        locals: Vec::new(),
+        prologue_end: Vec::new(),
+        epilogue_begin: Vec::new(),
+    };
+    let mut has_debug = debug.parameter_names.iter().any(Option::is_some);
+    let mut source_file = None;
+    let mut open: HashMap<u32, usize> = HashMap::new();
+    let mut last: HashMap<u32, usize> = HashMap::new();
+    for (item, address) in items.iter().zip(addresses) {
+        let address = *address;
+        // 结束寄存器上正在生效的变量，记下来供 .restart local 使用
+        let mut close = |locals: &mut Vec<LocalVariable>, open: &mut HashMap<u32, usize>, register: u32| {
+            if let Some(index) = open.remove(&register) {
+                locals[index].end_address = address;
+                last.insert(register, index);
+            }
+        };
+        match item {
+            BodyItem::Line(line) => {
+                if debug.positions.is_empty() {
+                    debug.line_start = *line;
+                }
+                debug.positions.push(DebugPosition { address, line: *line, source_file: source_file.clone() });
+            }
+            BodyItem::Source(file) => source_file = Some(file.clone()),
+            BodyItem::Prologue => debug.prologue_end.push(address),
+            BodyItem::Epilogue => debug.epilogue_begin.push(address),
+            BodyItem::Local { register, name, local_type, signature } => {
+                close(&mut debug.locals, &mut open, *register);
+                open.insert(*register, debug.locals.len());
+                debug.locals.push(LocalVariable {
+                    register: *register,
+                    name: name.clone(),
+                    local_type: local_type.as_deref().map(type_descriptor),
+                    signature: signature.clone(),
+                    start_address: address,
+                    end_address: insns_size,
+                });
+            }
+            BodyItem::EndLocal(register) => close(&mut debug.locals, &mut open, *register),
+            BodyItem::RestartLocal(register) => {
+                close(&mut debug.locals, &mut open, *register);
+                if let Some(previous) = last.get(register).map(|index| debug.locals[*index].clone()) {
+                    open.insert(*register, debug.locals.len());
+                    debug.locals.push(LocalVariable { start_address: address, end_address: insns_size, ..previous });
+                }
+            }
+            _ => continue,
+        }
+        has_debug = true;
+    }
+    has_debug.then_some(debug)
+}
+
+fn uleb128_size(mut value: u32) -> usize {
+    let mut size = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        size += 1;
+    }
+    size
+}
+
+fn sleb128_size(mut value: i32) -> usize {
+    let mut size = 1;
+    loop {
+        let byte = value & 0x7f;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            return size;
+        }
+        size += 1;
+    }
+}
@@ -0,0 +1,62 @@
+// DEX fixtures for tests: classes are assembled from smali and written to real DEX bytes
+use std::io::Cursor;
+
+use crate::android::DexFile;
+use crate::android_analyzer::dex_analyzer::DexAnalyzer;
+use crate::android_analyzer::dex_writer::DexWriter;
+use crate::android_analyzer::smali_assembler::SmaliAssembler;
+
+/// A version 035 file without any ids or classes
+pub fn empty_dex() -> DexFile {
+    DexFile {
+        magic: *b"dex\n035\0",
+        checksum: 0,
+        signature: [0; 20],
+        file_size: 0,
+        header_size: 0x70,
+        endian_tag: 0x12345678,
+        link_size: 0,
+        link_offset: 0,
+        map_offset: 0,
+        string_ids_size: 0,
+        string_ids_offset: 0,
+        type_ids_size: 0,
+        type_ids_offset: 0,
+        proto_ids_size: 0,
+        proto_ids_offset: 0,
+        field_ids_size: 0,
+        field_ids_offset: 0,
+        method_ids_size: 0,
+        method_ids_offset: 0,
+        class_defs_size: 0,
+        class_defs_offset: 0,
+        data_size: 0,
+        data_offset: 0,
+        container_size: 0,
+        header_offset: 0,
+        sections: Vec::new(),
+        strings: Vec::new(),
+        types: Vec::new(),
+        protos: Vec::new(),
+        fields: Vec::new(),
+        methods: Vec::new(),
+        method_handles: Vec::new(),
+        call_sites: Vec::new(),
+        classes: Vec::new(),
+    }
+}
+
+/// Assemble the smali classes and write them as one DEX file
+pub fn dex_bytes(classes: &[&str]) -> Vec<u8> {
+    let mut dex_file = empty_dex();
+    for source in classes {
+        let class_def = SmaliAssembler::new(&mut dex_file).assemble(source).expect("assemble smali");
+        dex_file.classes.push(class_def);
+    }
+    DexWriter::new(&dex_file).write().expect("write dex")
+}
+
+/// Assemble the smali classes and read them back through the parser
+pub fn dex_file(classes: &[&str]) -> DexFile {
+    DexAnalyzer::new(Cursor::new(dex_bytes(classes))).analyze().expect("parse dex")
+}
@@ -0,0 +1,565 @@
+// Interprocedural call graph built with class hierarchy analysis or rapid type analysis
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::hierarchy::normalize_class_name;
+use crate::program::{CodeReference, InvokeKind, Program};
+use crate::project::{Project, ProjectClasses, ProjectData};
+
+const ACC_INTERFACE: u32 = 0x0200;
+const ACC_ABSTRACT: u32 = 0x0400;
+
+/// 路径查询默认返回的最大路径数
+const DEFAULT_MAX_PATHS: usize = 20;
+
+/// How virtual and interface calls are resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CallGraphAlgorithm {
+    /// Every concrete subtype of the receiver's static type may be the receiver
+    Cha,
+    /// Only subtypes instantiated somewhere in the project (`new`, constructor references,
+    /// Android manifest components) may be the receiver
+    Rta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CallGraphFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CallGraphOptions {
+    pub algorithm: CallGraphAlgorithm,
+    /// Keep calls into methods that are not defined in the project (JDK, Android framework, ...)
+    #[serde(default)]
+    pub include_external: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum CallKind {
+    Virtual,
+    Special,
+    Static,
+    Interface,
+    /// Lambda body or method reference, called later through the created object
+    MethodHandle,
+}
+
+impl From<InvokeKind> for CallKind {
+    fn from(kind: InvokeKind) -> Self {
+        match kind {
+            InvokeKind::Virtual => CallKind::Virtual,
+            InvokeKind::Special => CallKind::Special,
+            InvokeKind::Static => CallKind::Static,
+            InvokeKind::Interface => CallKind::Interface,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct CallGraphNode {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+    /// The method is only referenced, not defined in the project
+    pub external: bool,
+}
+
+/// One call site; `caller` and `callee` index into [`CallGraph::nodes`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CallGraphEdge {
+    pub caller: usize,
+    pub callee: usize,
+    /// Bytecode offset of the invoke instruction, absent for method handles
+    pub offset: Option<u32>,
+    pub kind: CallKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CallGraph {
+    pub algorithm: CallGraphAlgorithm,
+    pub nodes: Vec<CallGraphNode>,
+    pub edges: Vec<CallGraphEdge>,
+}
+
+/// Selects the methods a path starts or ends at; without a descriptor every overload matches.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MethodSelector {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: Option<String>,
+}
+
+struct CallGraphBuilder<'a> {
+    program: &'a Program,
+    options: &'a CallGraphOptions,
+    instantiated: Option<HashSet<String>>,
+    nodes: Vec<CallGraphNode>,
+    node_ids: HashMap<(String, String, String), usize>,
+    edges: Vec<CallGraphEdge>,
+    // (owner, name, descriptor) -> 虚调用的分派目标
+    dispatch_cache: HashMap<(String, String, String), Vec<usize>>,
+}
+
+impl<'a> CallGraphBuilder<'a> {
+    fn node(&mut self, class_name: &str, method_name: &str, descriptor: &str, external: bool) -> usize {
+        let key = (class_name.to_string(), method_name.to_string(), descriptor.to_string());
+        if let Some(id) = self.node_ids.get(&key) {
+            return *id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(CallGraphNode {
+            class_name: key.0.clone(),
+            method_name: key.1.clone(),
+            descriptor: key.2.clone(),
+            external,
+        });
+        self.node_ids.insert(key, id);
+        id
+    }
+
+    /// 解析到项目中的声明，找不到时作为外部方法
+    fn resolved(&mut self, owner: &str, name: &str, descriptor: &str) -> Option<usize> {
+        match self.program.resolve_method(owner, name, descriptor) {
+            Some(class) => {
+                let class_name = class.name.clone();
+                Some(self.node(&class_name, name, descriptor, false))
+            }
+            None if self.options.include_external => Some(self.node(owner, name, descriptor, true)),
+            None => None,
+        }
+    }
+
+    fn dispatch(&mut self, owner: &str, name: &str, descriptor: &str) -> Vec<usize> {
+        let key = (owner.to_string(), name.to_string(), descriptor.to_string());
+        if let Some(targets) = self.dispatch_cache.get(&key) {
+            return targets.clone();
+        }
+        let program = self.program;
+        let receivers = std::iter::once(owner.to_string())
+            .chain(program.hierarchy.subtypes(owner, true).into_iter().map(|summary| summary.name));
+        let mut targets = BTreeSet::new();
+        for receiver in receivers {
+            let Some(class) = program.classes.get(&receiver) else {
+                continue;
+            };
+            if class.access_flags & (ACC_INTERFACE | ACC_ABSTRACT) != 0 {
+                continue;
+            }
+            if self.instantiated.as_ref().is_some_and(|types| !types.contains(&receiver)) {
+                continue;
+            }
+            match program.resolve_method(&receiver, name, descriptor) {
+                Some(declaring) => {
+                    let concrete = declaring.method(name, descriptor).is_some_and(|m| m.has_code);
+                    if concrete {
+                        let class_name = declaring.name.clone();
+                        targets.insert(self.node(&class_name, name, descriptor, false));
+                    }
+                }
+                // 接收者从项目外的父类继承了这个方法
+                None if self.options.include_external => {
+                    targets.insert(self.node(owner, name, descriptor, true));
+                }
+                None => {}
+            }
+        }
+        if program.resolve_method(owner, name, descriptor).is_none() && self.options.include_external {
+            targets.insert(self.node(owner, name, descriptor, true));
+        }
+        let targets: Vec<usize> = targets.into_iter().collect();
+        self.dispatch_cache.insert(key, targets.clone());
+        targets
+    }
+
+    fn build(mut self) -> CallGraph {
+        let program = self.program;
+        for class in program.classes.values() {
+            for method in &class.methods {
+                let caller = self.node(&class.name, &method.name, &method.descriptor, false);
+                for reference in &method.references {
+                    let (callees, offset, kind) = match reference {
+                        CodeReference::Invoke { kind, owner, name, descriptor, offset } => {
+                            let callees = match kind {
+                                InvokeKind::Static | InvokeKind::Special => {
+                                    self.resolved(owner, name, descriptor).into_iter().collect()
+                                }
+                                InvokeKind::Virtual | InvokeKind::Interface => self.dispatch(owner, name, descriptor),
+                            };
+                            (callees, Some(*offset), CallKind::from(*kind))
+                        }
+                        CodeReference::MethodHandle { owner, name, descriptor } => {
+                            let callees = self.resolved(owner, name, descriptor).into_iter().collect();
+                            (callees, None, CallKind::MethodHandle)
+                        }
+                        _ => continue,
+                    };
+                    for callee in callees {
+                        self.edges.push(CallGraphEdge { caller, callee, offset, kind });
+                    }
+                }
+            }
+        }
+        CallGraph {
+            algorithm: self.options.algorithm,
+            nodes: self.nodes,
+            edges: self.edges,
+        }
+    }
+}
+
+/// RTA 使用的实例化类型集合：项目中出现过 `new` 或构造器引用的类，以及系统创建的组件
+fn instantiated_types(program: &Program, components: &[String]) -> HashSet<String> {
+    let mut types: HashSet<String> = components.iter().cloned().collect();
+    for class in program.classes.values() {
+        for method in &class.methods {
+            for reference in &method.references {
+                match reference {
+                    CodeReference::Instantiate(class_name) => {
+                        types.insert(class_name.clone());
+                    }
+                    CodeReference::MethodHandle { owner, name, .. } if name == "<init>" => {
+                        types.insert(owner.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    types
+}
+
+impl CallGraph {
+    pub(crate) fn build(program: &Program, options: &CallGraphOptions, components: &[String]) -> Self {
+        let instantiated = match options.algorithm {
+            CallGraphAlgorithm::Cha => None,
+            CallGraphAlgorithm::Rta => Some(instantiated_types(program, components)),
+        };
+        CallGraphBuilder {
+            program,
+            options,
+            instantiated,
+            nodes: Vec::new(),
+            node_ids: HashMap::new(),
+            edges: Vec::new(),
+            dispatch_cache: HashMap::new(),
+        }
+        .build()
+    }
+
+    fn select(&self, selector: &MethodSelector) -> Vec<usize> {
+        let class_name = normalize_class_name(&selector.class_name);
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| {
+                node.class_name == class_name
+                    && node.method_name == selector.method_name
+                    && selector.descriptor.as_ref().is_none_or(|d| d.is_empty() || d == &node.descriptor)
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// 先从终点反向 BFS 求出各节点到终点的距离，再从起点 DFS 枚举不超过 max_depth 条边的简单路径
+    pub(crate) fn find_paths(&self, from: &[usize], to: &[usize], max_paths: usize, max_depth: Option<usize>) -> Vec<Vec<usize>> {
+        let mut successors: Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+        for edge in &self.edges {
+            if !successors[edge.caller].contains(&edge.callee) {
+                successors[edge.caller].push(edge.callee);
+                predecessors[edge.callee].push(edge.caller);
+            }
+        }
+
+        let mut distance = vec![usize::MAX; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        for &target in to {
+            distance[target] = 0;
+            queue.push_back(target);
+        }
+        while let Some(node) = queue.pop_front() {
+            for &predecessor in &predecessors[node] {
+                if distance[predecessor] == usize::MAX {
+                    distance[predecessor] = distance[node] + 1;
+                    queue.push_back(predecessor);
+                }
+            }
+        }
+
+        let Some(shortest) = from.iter().map(|&id| distance[id]).min().filter(|d| *d != usize::MAX) else {
+            return Vec::new();
+        };
+        let max_depth = max_depth.unwrap_or(shortest).max(shortest);
+        let mut paths = Vec::new();
+        for &start in from {
+            let mut path = vec![start];
+            self.extend_paths(&successors, &distance, to, max_depth, max_paths, &mut path, &mut paths);
+        }
+        paths.sort_by_key(|path| path.len());
+        paths
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn extend_paths(
+        &self,
+        successors: &[Vec<usize>],
+        distance: &[usize],
+        to: &[usize],
+        max_depth: usize,
+        max_paths: usize,
+        path: &mut Vec<usize>,
+        paths: &mut Vec<Vec<usize>>,
+    ) {
+        if paths.len() >= max_paths {
+            return;
+        }
+        let node = *path.last().unwrap();
+        if to.contains(&node) {
+            paths.push(path.clone());
+            return;
+        }
+        let depth = path.len() - 1;
+        for &next in &successors[node] {
+            // 剩余步数不足以到达终点的分支直接剪掉
+            if distance[next] == usize::MAX || depth + 1 + distance[next] > max_depth || path.contains(&next) {
+                continue;
+            }
+            path.push(next);
+            self.extend_paths(successors, distance, to, max_depth, max_paths, path, paths);
+            path.pop();
+        }
+    }
+
+    pub(crate) fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph callgraph {\n    rankdir=LR;\n    node [shape=box, fontname=\"monospace\"];\n");
+        for (id, node) in self.nodes.iter().enumerate() {
+            let label = format!("{}.{}{}", node.class_name.replace('/', "."), node.method_name, node.descriptor);
+            let style = if node.external { ", style=dashed, color=gray" } else { "" };
+            dot.push_str(&format!("    n{} [label=\"{}\"{}];\n", id, dot_escape(&label), style));
+        }
+        let mut seen = HashSet::new();
+        for edge in &self.edges {
+            if !seen.insert((edge.caller, edge.callee, edge.kind)) {
+                continue;
+            }
+            let style = match edge.kind {
+                CallKind::Virtual | CallKind::Interface => " [style=dashed]",
+                CallKind::MethodHandle => " [style=dotted]",
+                CallKind::Static | CallKind::Special => "",
+            };
+            dot.push_str(&format!("    n{} -> n{}{};\n", edge.caller, edge.callee, style));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn project_call_graph(project_id: &str, options: &CallGraphOptions) -> Result<CallGraph, String> {
+    let (classes, components) = Project::with_project_mut(project_id, |project| {
+        let classes = ProjectClasses::of(&mut project.data)?;
+        let components = match &project.data {
+            ProjectData::Android(android_data) => android_data
+                .manifest
+                .as_ref()
+                .map(|manifest| {
+                    manifest
+                        .component_classes()
+                        .iter()
+                        .map(|(_, class_name)| normalize_class_name(class_name))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        Ok((classes, components))
+    })?;
+    // 类在锁外读取和解析
+    let program = Program::from_project(&classes);
+    Ok(CallGraph::build(&program, options, &components))
+}
+
+#[tauri::command(async)]
+pub fn callgraph_build(project_id: String, options: CallGraphOptions) -> Result<CallGraph, String> {
+    project_call_graph(&project_id, &options)
+}
+
+/// Renders the call graph as Graphviz DOT or JSON text for saving to a file.
+#[tauri::command(async)]
+pub fn callgraph_export(project_id: String, options: CallGraphOptions, format: CallGraphFormat) -> Result<String, String> {
+    let graph = project_call_graph(&project_id, &options)?;
+    match format {
+        CallGraphFormat::Dot => Ok(graph.to_dot()),
+        CallGraphFormat::Json => serde_json::to_string_pretty(&graph).map_err(|e| e.to_string()),
+    }
+}
+
+/// Call paths from `from` to `to`, shortest first. Without `max_depth` only the shortest paths
+/// are returned; a larger depth also lists longer detours.
+#[tauri::command(async)]
+pub fn callgraph_find_paths(
+    project_id: String,
+    options: CallGraphOptions,
+    from: MethodSelector,
+    to: MethodSelector,
+    max_paths: Option<usize>,
+    max_depth: Option<usize>,
+) -> Result<Vec<Vec<CallGraphNode>>, String> {
+    let graph = project_call_graph(&project_id, &options)?;
+    let sources = graph.select(&from);
+    if sources.is_empty() {
+        return Err(format!("Method not found: {}.{}", from.class_name, from.method_name));
+    }
+    let targets = graph.select(&to);
+    if targets.is_empty() {
+        return Err(format!("Method not found: {}.{}", to.class_name, to.method_name));
+    }
+    let paths = graph.find_paths(&sources, &targets, max_paths.unwrap_or(DEFAULT_MAX_PATHS), max_depth);
+    Ok(paths
+        .into_iter()
+        .map(|path| path.into_iter().map(|id| graph.nodes[id].clone()).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+    use crate::model::ClassModel;
+
+    const MAIN: &str = r#"
+.class public Lapp/Main;
+.super Ljava/lang/Object;
+
+.method public static main([Ljava/lang/String;)V
+    .registers 2
+    new-instance v0, Lapp/Dog;
+    invoke-direct {v0}, Lapp/Dog;-><init>()V
+    invoke-virtual {v0}, Lapp/Animal;->speak()V
+    invoke-interface {v0}, Lapp/Pet;->play()V
+    invoke-static {}, Lapp/Util;->log()V
+    return-void
+.end method
+"#;
+
+    const PET: &str = r#"
+.class public interface abstract Lapp/Pet;
+.super Ljava/lang/Object;
+
+.method public abstract play()V
+.end method
+"#;
+
+    const ANIMAL: &str = r#"
+.class public Lapp/Animal;
+.super Ljava/lang/Object;
+
+.method public constructor <init>()V
+    .registers 1
+    invoke-direct {p0}, Ljava/lang/Object;-><init>()V
+    return-void
+.end method
+
+.method public speak()V
+    .registers 1
+    return-void
+.end method
+"#;
+
+    const UTIL: &str = r#"
+.class public Lapp/Util;
+.super Ljava/lang/Object;
+
+.method public static log()V
+    .registers 0
+    return-void
+.end method
+"#;
+
+    /// Animal 的子类，重写 speak 并实现 Pet
+    fn pet(name: &str) -> String {
+        format!(
+            r#"
+.class public Lapp/{name};
+.super Lapp/Animal;
+.implements Lapp/Pet;
+
+.method public constructor <init>()V
+    .registers 1
+    invoke-direct {{p0}}, Lapp/Animal;-><init>()V
+    return-void
+.end method
+
+.method public speak()V
+    .registers 1
+    return-void
+.end method
+
+.method public play()V
+    .registers 1
+    return-void
+.end method
+"#
+        )
+    }
+
+    /// main 的调用边，按调用类型和被调方法排序
+    fn main_calls(algorithm: CallGraphAlgorithm) -> Vec<(CallKind, String)> {
+        let dog = pet("Dog");
+        let cat = pet("Cat");
+        let dex_file = dex_file(&[MAIN, PET, ANIMAL, UTIL, &dog, &cat]);
+        let program = Program::from_classes(ClassModel::dex_classes(std::slice::from_ref(&dex_file)));
+        let options = CallGraphOptions { algorithm, include_external: false };
+        let graph = CallGraph::build(&program, &options, &[]);
+        let mut calls: Vec<(CallKind, String)> = graph
+            .edges
+            .iter()
+            .filter(|edge| graph.nodes[edge.caller].method_name == "main")
+            .map(|edge| {
+                let callee = &graph.nodes[edge.callee];
+                (edge.kind, format!("{}.{}", callee.class_name, callee.method_name))
+            })
+            .collect();
+        calls.sort_by_key(|(kind, callee)| (*kind as u8, callee.clone()));
+        calls
+    }
+
+    fn calls(expected: &[(CallKind, &str)]) -> Vec<(CallKind, String)> {
+        expected.iter().map(|(kind, callee)| (*kind, callee.to_string())).collect()
+    }
+
+    #[test]
+    fn cha_dispatches_to_every_concrete_subtype() {
+        assert_eq!(
+            main_calls(CallGraphAlgorithm::Cha),
+            calls(&[
+                (CallKind::Virtual, "app/Animal.speak"),
+                (CallKind::Virtual, "app/Cat.speak"),
+                (CallKind::Virtual, "app/Dog.speak"),
+                (CallKind::Special, "app/Dog.<init>"),
+                (CallKind::Static, "app/Util.log"),
+                (CallKind::Interface, "app/Cat.play"),
+                (CallKind::Interface, "app/Dog.play"),
+            ])
+        );
+    }
+
+    #[test]
+    fn rta_dispatches_only_to_instantiated_types() {
+        assert_eq!(
+            main_calls(CallGraphAlgorithm::Rta),
+            calls(&[
+                (CallKind::Virtual, "app/Dog.speak"),
+                (CallKind::Special, "app/Dog.<init>"),
+                (CallKind::Static, "app/Util.log"),
+                (CallKind::Interface, "app/Dog.play"),
+            ])
+        );
+    }
+}
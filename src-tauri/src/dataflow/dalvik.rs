@@ -0,0 +1,437 @@
+// Dalvik bytecode -> data-flow IR: registers become locals, the pending call result becomes a stack slot
+use std::collections::HashMap;
+
+use super::{BinaryOp, Constant, FlowInstruction, FlowMethod, Operand, Operation, UnaryOp, Variable};
+use crate::android::{DexFile, Method};
+use crate::android_analyzer::dalvik_opcode::{DalvikInstruction, DalvikOpcode, DalvikOpcodeAnalyzer, DalvikReference};
+use crate::hierarchy::proto_to_descriptor;
+
+const ACC_STATIC: u32 = 0x0008;
+
+/// invoke 和 filled-new-array 的结果、catch 到的异常都放在这里，由随后的 move-result / move-exception 读取；
+/// 与 JVM 异常处理器入口压栈的异常是同一个位置
+const RESULT: Variable = Variable::Stack(0);
+
+/// 三地址和 2addr 算术指令的运算，按操作码顺序排列
+const INT_OPS: [BinaryOp; 11] = [
+    BinaryOp::Add,
+    BinaryOp::Sub,
+    BinaryOp::Mul,
+    BinaryOp::Div,
+    BinaryOp::Rem,
+    BinaryOp::And,
+    BinaryOp::Or,
+    BinaryOp::Xor,
+    BinaryOp::Shl,
+    BinaryOp::Shr,
+    BinaryOp::Ushr,
+];
+
+fn register(index: u32) -> Variable {
+    Variable::Local(index as u16)
+}
+
+/// 单条指令读写的寄存器；宽值占相邻的两个寄存器，值只记在低位寄存器上
+#[derive(Default)]
+struct RegisterEffect {
+    uses: Vec<Variable>,
+    defs: Vec<(Variable, Operation)>,
+}
+
+impl RegisterEffect {
+    fn read(&mut self, index: u32, wide: bool) -> Variable {
+        self.uses.push(register(index));
+        if wide {
+            self.uses.push(register(index + 1));
+        }
+        register(index)
+    }
+
+    fn write(&mut self, index: u32, wide: bool, operation: Operation) {
+        self.defs.push((register(index), operation));
+        if wide {
+            self.defs.push((register(index + 1), Operation::Unknown));
+        }
+    }
+}
+
+/// add-int 到 rem-double 的运算、操作数是否为宽值、右操作数是否为宽值（long 移位的位数是 int）、是否为浮点运算
+fn arithmetic(code: u8) -> Option<(BinaryOp, bool, bool, bool)> {
+    match code {
+        0x90..=0x9a => Some((INT_OPS[(code - 0x90) as usize], false, false, false)),
+        0x9b..=0xa5 => {
+            let index = (code - 0x9b) as usize;
+            Some((INT_OPS[index], true, index < 8, false))
+        }
+        0xa6..=0xaa => Some((INT_OPS[(code - 0xa6) as usize], false, false, true)),
+        0xab..=0xaf => Some((INT_OPS[(code - 0xab) as usize], true, true, true)),
+        _ => None,
+    }
+}
+
+/// Dalvik 的浮点常量是无类型的位模式，按整数求值会得到错误的结果，浮点运算不参与常量传播
+fn binary(op: BinaryOp, left: Variable, right: Variable, floating: bool) -> Operation {
+    if floating {
+        Operation::Unknown
+    } else {
+        Operation::Binary(op, Operand::Variable(left), Operand::Variable(right))
+    }
+}
+
+/// 整数源的取负和类型转换，以及源和目标是否为宽值
+fn conversion(opcode: DalvikOpcode) -> Option<(UnaryOp, bool, bool)> {
+    use DalvikOpcode::*;
+    Some(match opcode {
+        NegInt => (UnaryOp::Neg, false, false),
+        NegLong => (UnaryOp::Neg, true, true),
+        IntToLong => (UnaryOp::ToLong, false, true),
+        IntToFloat => (UnaryOp::ToFloat, false, false),
+        IntToDouble => (UnaryOp::ToDouble, false, true),
+        LongToInt => (UnaryOp::ToInt, true, false),
+        LongToFloat => (UnaryOp::ToFloat, true, false),
+        LongToDouble => (UnaryOp::ToDouble, true, true),
+        IntToByte => (UnaryOp::ToByte, false, false),
+        IntToChar => (UnaryOp::ToChar, false, false),
+        IntToShort => (UnaryOp::ToShort, false, false),
+        _ => return None,
+    })
+}
+
+fn returns_value(insn: &DalvikInstruction) -> bool {
+    match (&insn.reference, &insn.proto) {
+        (_, Some(proto)) => proto.return_type.descriptor != "V",
+        (Some(DalvikReference::Method(method)), None) => method.proto.return_type.descriptor != "V",
+        _ => true,
+    }
+}
+
+fn translate(insn: &DalvikInstruction) -> RegisterEffect {
+    use DalvikOpcode::*;
+    let mut e = RegisterEffect::default();
+    let r = |index: usize| insn.registers.get(index).copied().unwrap_or(0);
+    let literal = insn.literal.unwrap_or(0);
+    match insn.opcode {
+        Nop | ReturnVoid | Goto | Goto16 | Goto32 => {}
+        Move | MoveFrom16 | Move16 | MoveObject | MoveObjectFrom16 | MoveObject16 => {
+            let source = e.read(r(1), false);
+            e.write(r(0), false, Operation::Copy(source));
+        }
+        MoveWide | MoveWideFrom16 | MoveWide16 => {
+            let source = e.read(r(1), true);
+            e.write(r(0), true, Operation::Copy(source));
+        }
+        MoveResult | MoveResultObject | MoveException => {
+            e.uses.push(RESULT);
+            e.write(r(0), false, Operation::Copy(RESULT));
+        }
+        MoveResultWide => {
+            e.uses.push(RESULT);
+            e.write(r(0), true, Operation::Copy(RESULT));
+        }
+        Return | ReturnObject | MonitorEnter | MonitorExit | Throw | CheckCast | FillArrayData | PackedSwitch
+        | SparseSwitch | IfEqz | IfNez | IfLtz | IfGez | IfGtz | IfLez | Sput | SputObject | SputBoolean | SputByte
+        | SputChar | SputShort => {
+            e.read(r(0), false);
+        }
+        ReturnWide | SputWide => {
+            e.read(r(0), true);
+        }
+        IfEq | IfNe | IfLt | IfGe | IfGt | IfLe | Iput | IputObject | IputBoolean | IputByte | IputChar | IputShort => {
+            e.read(r(0), false);
+            e.read(r(1), false);
+        }
+        IputWide => {
+            e.read(r(0), true);
+            e.read(r(1), false);
+        }
+        Const4 | Const16 | Const | ConstHigh16 => e.write(r(0), false, Operation::Constant(Constant::Int(literal as i32))),
+        ConstWide16 | ConstWide32 | ConstWide | ConstWideHigh16 => e.write(r(0), true, Operation::Constant(Constant::Long(literal))),
+        ConstString | ConstStringJumbo => {
+            let operation = match &insn.reference {
+                Some(DalvikReference::String(value)) => Operation::Constant(Constant::String(value.clone())),
+                _ => Operation::Unknown,
+            };
+            e.write(r(0), false, operation);
+        }
+        ConstClass | ConstMethodHandle | ConstMethodType | NewInstance | Sget | SgetObject | SgetBoolean | SgetByte
+        | SgetChar | SgetShort => e.write(r(0), false, Operation::Unknown),
+        SgetWide => e.write(r(0), true, Operation::Unknown),
+        InstanceOf | ArrayLength | NewArray | Iget | IgetObject | IgetBoolean | IgetByte | IgetChar | IgetShort => {
+            e.read(r(1), false);
+            e.write(r(0), false, Operation::Unknown);
+        }
+        IgetWide => {
+            e.read(r(1), false);
+            e.write(r(0), true, Operation::Unknown);
+        }
+        Aget | AgetWide | AgetObject | AgetBoolean | AgetByte | AgetChar | AgetShort => {
+            e.read(r(1), false);
+            e.read(r(2), false);
+            e.write(r(0), insn.opcode == AgetWide, Operation::Unknown);
+        }
+        Aput | AputWide | AputObject | AputBoolean | AputByte | AputChar | AputShort => {
+            e.read(r(0), insn.opcode == AputWide);
+            e.read(r(1), false);
+            e.read(r(2), false);
+        }
+        CmplFloat | CmpgFloat | CmplDouble | CmpgDouble | CmpLong => {
+            let (op, wide) = match insn.opcode {
+                CmplFloat => (BinaryOp::CmpL, false),
+                CmpgFloat => (BinaryOp::CmpG, false),
+                CmplDouble => (BinaryOp::CmpL, true),
+                CmpgDouble => (BinaryOp::CmpG, true),
+                _ => (BinaryOp::Cmp, true),
+            };
+            let left = e.read(r(1), wide);
+            let right = e.read(r(2), wide);
+            e.write(r(0), false, binary(op, left, right, insn.opcode != CmpLong));
+        }
+        NotInt | NotLong => {
+            let wide = insn.opcode == NotLong;
+            let source = e.read(r(1), wide);
+            let mask = if wide { Constant::Long(-1) } else { Constant::Int(-1) };
+            e.write(r(0), wide, Operation::Binary(BinaryOp::Xor, Operand::Variable(source), Operand::Constant(mask)));
+        }
+        // 浮点源同样不参与常量传播，见 [`binary`]
+        NegFloat | FloatToInt | FloatToLong | FloatToDouble | NegDouble | DoubleToInt | DoubleToLong | DoubleToFloat => {
+            let (source_wide, target_wide) = match insn.opcode {
+                NegFloat | FloatToInt => (false, false),
+                FloatToLong | FloatToDouble => (false, true),
+                DoubleToInt | DoubleToFloat => (true, false),
+                _ => (true, true),
+            };
+            e.read(r(1), source_wide);
+            e.write(r(0), target_wide, Operation::Unknown);
+        }
+        opcode if opcode.is_invoke() || matches!(opcode, FilledNewArray | FilledNewArrayRange) => {
+            // 参数列表里宽值已经展开为两个寄存器
+            for &index in &insn.registers {
+                e.read(index, false);
+            }
+            if returns_value(insn) {
+                e.defs.push((RESULT, Operation::Unknown));
+            }
+        }
+        opcode => {
+            let code = opcode as u8;
+            if let Some((op, source_wide, target_wide)) = conversion(opcode) {
+                let source = e.read(r(1), source_wide);
+                e.write(r(0), target_wide, Operation::Unary(op, source));
+            } else if let Some((op, wide, right_wide, floating)) = arithmetic(code) {
+                let left = e.read(r(1), wide);
+                let right = e.read(r(2), right_wide);
+                e.write(r(0), wide, binary(op, left, right, floating));
+            } else if let Some((op, wide, right_wide, floating)) = (0xb0..=0xcf).contains(&code).then(|| arithmetic(code - 0x20)).flatten() {
+                // 2addr：vA 既是左操作数也是目标
+                let left = e.read(r(0), wide);
+                let right = e.read(r(1), right_wide);
+                e.write(r(0), wide, binary(op, left, right, floating));
+            } else if (0xd0..=0xe2).contains(&code) {
+                let index = if code <= 0xd7 { code - 0xd0 } else { code - 0xd8 } as usize;
+                let source = e.read(r(1), false);
+                let constant = Operand::Constant(Constant::Int(literal as i32));
+                let operation = match index {
+                    // rsub-int：常量减去寄存器
+                    1 => Operation::Binary(BinaryOp::Sub, constant, Operand::Variable(source)),
+                    _ => Operation::Binary(INT_OPS[index], Operand::Variable(source), constant),
+                };
+                e.write(r(0), false, operation);
+            }
+        }
+    }
+    e
+}
+
+/// 不经过跳转时是否落到下一条指令，以及跳转目标地址；switch 的目标来自负载
+fn branch_targets(insn: &DalvikInstruction, instructions: &[DalvikInstruction]) -> (bool, Vec<u32>) {
+    use DalvikOpcode::*;
+    let targets = match insn.opcode {
+        PackedSwitch | SparseSwitch => insn.switch_cases(instructions).into_iter().map(|(_, target)| target).collect(),
+        Goto | Goto16 | Goto32 | IfEq | IfNe | IfLt | IfGe | IfGt | IfLe | IfEqz | IfNez | IfLtz | IfGez | IfGtz | IfLez => {
+            insn.target.into_iter().collect()
+        }
+        _ => Vec::new(),
+    };
+    (!insn.opcode.is_terminator(), targets)
+}
+
+pub(crate) fn flow_method(dex_file: &DexFile, method: &Method) -> Result<FlowMethod, String> {
+    let descriptor = proto_to_descriptor(&method.proto);
+    let code = method
+        .code
+        .as_ref()
+        .ok_or_else(|| format!("Method {}{} has no code", method.name, descriptor))?;
+    let decoded = DalvikOpcodeAnalyzer::new()
+        .analyze_method(code, dex_file)
+        .map_err(|e| format!("Failed to decode {}{}: {}", method.name, descriptor, e))?;
+    // 负载是嵌在指令流中的数据，不进入控制流
+    let instructions: Vec<&DalvikInstruction> = decoded.iter().filter(|insn| insn.payload.is_none()).collect();
+    let count = instructions.len();
+    let index_of: HashMap<u32, usize> = instructions.iter().enumerate().map(|(i, insn)| (insn.address, i)).collect();
+    let target_index = |address: u32| index_of.get(&address).copied().ok_or_else(|| format!("Invalid branch target {}", address));
+
+    let mut successors: Vec<Vec<usize>> = Vec::with_capacity(count);
+    for (index, insn) in instructions.iter().enumerate() {
+        let (falls_through, targets) = branch_targets(insn, &decoded);
+        let mut next = Vec::new();
+        if falls_through && index + 1 < count {
+            next.push(index + 1);
+        }
+        for target in targets {
+            let target = target_index(target)?;
+            if !next.contains(&target) {
+                next.push(target);
+            }
+        }
+        successors.push(next);
+    }
+
+    let mut handlers: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for try_item in &code.tries {
+        let Some(catch_handler) = code.handler(try_item) else {
+            return Err(format!("Missing catch handler for try block at {}", try_item.start_addr));
+        };
+        let end = try_item.start_addr + try_item.insn_count as u32;
+        let addresses = catch_handler.handlers.iter().map(|pair| pair.addr).chain(catch_handler.catch_all_addr);
+        for address in addresses {
+            let handler = target_index(address)?;
+            for (index, insn) in instructions.iter().enumerate() {
+                if insn.address >= try_item.start_addr && insn.address < end && !handlers[index].contains(&handler) {
+                    handlers[index].push(handler);
+                }
+            }
+        }
+    }
+
+    let flow_instructions = instructions
+        .iter()
+        .enumerate()
+        .map(|(index, insn)| {
+            let effect = translate(insn);
+            FlowInstruction {
+                offset: insn.address,
+                text: insn.to_string(),
+                uses: effect.uses,
+                defs: effect.defs,
+                successors: std::mem::take(&mut successors[index]),
+                handlers: std::mem::take(&mut handlers[index]),
+            }
+        })
+        .collect();
+
+    // 参数放在最后 ins_size 个寄存器中，宽参数占两个
+    let mut parameters = Vec::new();
+    let mut next = code.registers_size.saturating_sub(code.ins_size) as u32;
+    if method.access_flags & ACC_STATIC == 0 {
+        parameters.push(register(next));
+        next += 1;
+    }
+    for parameter in &method.proto.parameters {
+        parameters.push(register(next));
+        next += 1;
+        if matches!(parameter.descriptor.as_str(), "J" | "D") {
+            parameters.push(register(next));
+            next += 1;
+        }
+    }
+
+    Ok(FlowMethod {
+        instructions: flow_instructions,
+        parameters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+    use crate::dataflow::solver::ConstantState;
+    use crate::dataflow::MethodAnalysis;
+
+    fn analysis(smali: &str, method_name: &str) -> MethodAnalysis {
+        let dex_file = dex_file(&[smali]);
+        let method = dex_file.classes[0]
+            .direct_methods
+            .iter()
+            .chain(&dex_file.classes[0].virtual_methods)
+            .find(|method| method.name == method_name)
+            .unwrap();
+        MethodAnalysis::new(flow_method(&dex_file, method).unwrap())
+    }
+
+    const CLASS: &str = r#"
+.class public LFlow;
+.super Ljava/lang/Object;
+
+.method public static sum(IJ)J
+    .registers 6
+    const/4 v0, 0x2
+    add-int/lit8 v1, v0, 0x3
+    if-eqz v3, :skip
+    int-to-long v1, v1
+    add-long/2addr v1, v4
+    return-wide v1
+    :skip
+    move-wide v1, v4
+    return-wide v1
+.end method
+
+.method public static call(Ljava/lang/String;)I
+    .registers 3
+    :start
+    invoke-virtual {v2}, Ljava/lang/String;->length()I
+    move-result v0
+    :end
+    .catch Ljava/lang/Exception; {:start .. :end} :handler
+    return v0
+    :handler
+    move-exception v1
+    const/4 v0, -0x1
+    return v0
+.end method
+"#;
+
+    #[test]
+    fn registers_carry_constants_and_parameters() {
+        let analysis = analysis(CLASS, "sum");
+        let method = &analysis.method;
+        // 6 个寄存器，参数 int 在 v3，long 在 v4/v5
+        assert_eq!(method.parameters, vec![Variable::Local(3), Variable::Local(4), Variable::Local(5)]);
+        let add = method.instructions.iter().position(|insn| insn.text.starts_with("add-int/lit8")).unwrap();
+        assert_eq!(analysis.constants.after[add].get(&Variable::Local(1)), Some(&ConstantState::Constant(Constant::Int(5))));
+
+        let add_long = method.instructions.iter().position(|insn| insn.text.starts_with("add-long/2addr")).unwrap();
+        let instruction = &method.instructions[add_long];
+        for variable in [Variable::Local(1), Variable::Local(2), Variable::Local(4), Variable::Local(5)] {
+            assert!(instruction.uses.contains(&variable), "{} not read by {}", variable, instruction.text);
+        }
+        // 宽参数的高位寄存器在入口有定义
+        assert_eq!(analysis.definitions_of(add_long, Variable::Local(5)).len(), 1);
+        // if-eqz 有两个后继
+        let branch = method.instructions.iter().position(|insn| insn.text.starts_with("if-eqz")).unwrap();
+        assert_eq!(method.instructions[branch].successors.len(), 2);
+    }
+
+    #[test]
+    fn call_results_and_exceptions_flow_into_registers() {
+        let analysis = analysis(CLASS, "call");
+        let method = &analysis.method;
+        let invoke = method.instructions.iter().position(|insn| insn.text.starts_with("invoke-virtual")).unwrap();
+        let move_result = invoke + 1;
+        assert_eq!(method.instructions[invoke].uses, vec![Variable::Local(2)]);
+        let result = analysis.definitions_of(move_result, RESULT);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].site, crate::dataflow::solver::DefinitionSite::Instruction(invoke));
+
+        let handler = method.instructions.iter().position(|insn| insn.text.starts_with("move-exception")).unwrap();
+        assert!(method.instructions[invoke].handlers.contains(&handler));
+        let exception = analysis.definitions_of(handler, RESULT);
+        assert_eq!(exception[0].site, crate::dataflow::solver::DefinitionSite::Handler(handler));
+
+        // return v0 读到的只有 move-result 的定义
+        let ret = method.instructions.iter().position(|insn| insn.text == "return v0").unwrap();
+        let reaching = analysis.definitions_of(ret, Variable::Local(0));
+        assert_eq!(reaching.len(), 1);
+        assert_eq!(reaching[0].site, crate::dataflow::solver::DefinitionSite::Instruction(move_result));
+    }
+}
@@ -0,0 +1,461 @@
+// JVM bytecode -> data-flow IR: operand stack slots become variables
+use std::collections::HashMap;
+
+use super::{BinaryOp, Constant, FlowInstruction, FlowMethod, Operand, Operation, UnaryOp, Variable};
+use crate::diff::constant_text;
+use crate::java_analyzer::attributes::Attribute;
+use crate::java_analyzer::classfile::ClassFile;
+use crate::java_analyzer::constantpool::ConstantPoolEntry;
+use crate::java_analyzer::method::JvmMethod;
+use crate::java_analyzer::opcode::*;
+
+const ACC_STATIC: u16 = 0x0008;
+
+/// 单条指令对操作数栈的影响，栈上记录每个值的类别（long/double 为 2）
+struct StackEffect {
+    stack: Vec<u8>,
+    uses: Vec<Variable>,
+    defs: Vec<(Variable, Operation)>,
+    offset: u32,
+}
+
+impl StackEffect {
+    fn top(&self) -> Variable {
+        Variable::Stack(self.stack.len() as u16 - 1)
+    }
+
+    fn pop(&mut self) -> Result<Variable, String> {
+        if self.stack.is_empty() {
+            return Err(format!("Operand stack underflow at offset {}", self.offset));
+        }
+        let variable = self.top();
+        self.stack.pop();
+        self.uses.push(variable);
+        Ok(variable)
+    }
+
+    fn pop_n(&mut self, count: usize) -> Result<(), String> {
+        for _ in 0..count {
+            self.pop()?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, category: u8, operation: Operation) {
+        self.defs.push((Variable::Stack(self.stack.len() as u16), operation));
+        self.stack.push(category);
+    }
+
+    fn load(&mut self, index: i32, category: u8) {
+        let local = Variable::Local(index as u16);
+        self.uses.push(local);
+        self.push(category, Operation::Copy(local));
+    }
+
+    fn store(&mut self, index: i32) -> Result<(), String> {
+        let value = self.pop()?;
+        self.defs.push((Variable::Local(index as u16), Operation::Copy(value)));
+        Ok(())
+    }
+
+    fn unary(&mut self, op: UnaryOp, category: u8) -> Result<(), String> {
+        let value = self.pop()?;
+        self.push(category, Operation::Unary(op, value));
+        Ok(())
+    }
+
+    fn binary(&mut self, op: BinaryOp, category: u8) -> Result<(), String> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        self.push(category, Operation::Binary(op, Operand::Variable(left), Operand::Variable(right)));
+        Ok(())
+    }
+
+    /// dup/swap 系列：取栈顶 taken 个值，按 order 重新排列（0 为取出的最底部的值）
+    fn rearrange(&mut self, taken: usize, order: &[usize]) -> Result<(), String> {
+        if self.stack.len() < taken {
+            return Err(format!("Operand stack underflow at offset {}", self.offset));
+        }
+        let base = self.stack.len() - taken;
+        let categories: Vec<u8> = self.stack[base..].to_vec();
+        for (position, &source) in order.iter().enumerate() {
+            if position != source || position >= taken {
+                let from = Variable::Stack((base + source) as u16);
+                self.uses.push(from);
+                self.defs.push((Variable::Stack((base + position) as u16), Operation::Copy(from)));
+            }
+        }
+        self.stack.truncate(base);
+        self.stack.extend(order.iter().map(|&source| categories[source]));
+        Ok(())
+    }
+
+    fn category_at(&self, depth: usize) -> Result<u8, String> {
+        self.stack
+            .len()
+            .checked_sub(depth + 1)
+            .map(|index| self.stack[index])
+            .ok_or_else(|| format!("Operand stack underflow at offset {}", self.offset))
+    }
+}
+
+fn value_category(descriptor: &str) -> u8 {
+    match descriptor.as_bytes().first() {
+        Some(b'J') | Some(b'D') => 2,
+        Some(b'V') => 0,
+        _ => 1,
+    }
+}
+
+/// 方法描述符中每个参数的类别和返回值的类别（void 为 0）
+fn method_categories(descriptor: &str) -> (Vec<u8>, u8) {
+    let mut parameters = Vec::new();
+    let bytes = descriptor.as_bytes();
+    let mut position = 1;
+    while position < bytes.len() && bytes[position] != b')' {
+        let start = position;
+        while bytes[position] == b'[' {
+            position += 1;
+        }
+        if bytes[position] == b'L' {
+            while position < bytes.len() && bytes[position] != b';' {
+                position += 1;
+            }
+        }
+        parameters.push(if position == start { value_category(&descriptor[start..]) } else { 1 });
+        position += 1;
+    }
+    let return_category = descriptor.get(position + 1..).map(value_category).unwrap_or(0);
+    (parameters, return_category)
+}
+
+fn translate(class_file: &ClassFile, insn: &Instruction, stack: &[u8]) -> Result<StackEffect, String> {
+    let pool = &class_file.constant_pool;
+    let mut effect = StackEffect {
+        stack: stack.to_vec(),
+        uses: Vec::new(),
+        defs: Vec::new(),
+        offset: insn.offset,
+    };
+    let e = &mut effect;
+    let index = insn.value as usize;
+    match insn.opcode {
+        OP_NOP | OP_BREAKPOINT | OP_GOTO | OP_GOTO_W | OP_RETURN => {}
+        OP_ACONST_NULL => e.push(1, Operation::Constant(Constant::Null)),
+        OP_ICONST_M1..=OP_ICONST_5 => e.push(1, Operation::Constant(Constant::Int(insn.opcode as i32 - OP_ICONST_0 as i32))),
+        OP_LCONST_0 | OP_LCONST_1 => e.push(2, Operation::Constant(Constant::Long((insn.opcode - OP_LCONST_0) as i64))),
+        OP_FCONST_0..=OP_FCONST_2 => e.push(1, Operation::Constant(Constant::Float((insn.opcode - OP_FCONST_0) as f32))),
+        OP_DCONST_0 | OP_DCONST_1 => e.push(2, Operation::Constant(Constant::Double((insn.opcode - OP_DCONST_0) as f64))),
+        OP_BIPUSH | OP_SIPUSH => e.push(1, Operation::Constant(Constant::Int(insn.value))),
+        OP_LDC | OP_LDC_W | OP_LDC2_W => {
+            let constant = match pool.get_entry(index) {
+                Some(ConstantPoolEntry::Integer(value)) => Some(Constant::Int(*value)),
+                Some(ConstantPoolEntry::Float(value)) => Some(Constant::Float(*value)),
+                Some(ConstantPoolEntry::Long(value)) => Some(Constant::Long(*value)),
+                Some(ConstantPoolEntry::Double(value)) => Some(Constant::Double(*value)),
+                Some(ConstantPoolEntry::StringRef(_)) => pool.get_string(index).cloned().map(Constant::String),
+                _ => None,
+            };
+            let category = if insn.opcode == OP_LDC2_W { 2 } else { 1 };
+            e.push(category, constant.map_or(Operation::Unknown, Operation::Constant));
+        }
+        OP_ILOAD | OP_FLOAD | OP_ALOAD => e.load(insn.value, 1),
+        OP_LLOAD | OP_DLOAD => e.load(insn.value, 2),
+        OP_ILOAD_0..=OP_ILOAD_3 => e.load((insn.opcode - OP_ILOAD_0) as i32, 1),
+        OP_LLOAD_0..=OP_LLOAD_3 => e.load((insn.opcode - OP_LLOAD_0) as i32, 2),
+        OP_FLOAD_0..=OP_FLOAD_3 => e.load((insn.opcode - OP_FLOAD_0) as i32, 1),
+        OP_DLOAD_0..=OP_DLOAD_3 => e.load((insn.opcode - OP_DLOAD_0) as i32, 2),
+        OP_ALOAD_0..=OP_ALOAD_3 => e.load((insn.opcode - OP_ALOAD_0) as i32, 1),
+        OP_IALOAD | OP_FALOAD | OP_AALOAD | OP_BALOAD | OP_CALOAD | OP_SALOAD => {
+            e.pop_n(2)?;
+            e.push(1, Operation::Unknown);
+        }
+        OP_LALOAD | OP_DALOAD => {
+            e.pop_n(2)?;
+            e.push(2, Operation::Unknown);
+        }
+        OP_ISTORE | OP_LSTORE | OP_FSTORE | OP_DSTORE | OP_ASTORE => e.store(insn.value)?,
+        OP_ISTORE_0..=OP_ISTORE_3 => e.store((insn.opcode - OP_ISTORE_0) as i32)?,
+        OP_LSTORE_0..=OP_LSTORE_3 => e.store((insn.opcode - OP_LSTORE_0) as i32)?,
+        OP_FSTORE_0..=OP_FSTORE_3 => e.store((insn.opcode - OP_FSTORE_0) as i32)?,
+        OP_DSTORE_0..=OP_DSTORE_3 => e.store((insn.opcode - OP_DSTORE_0) as i32)?,
+        OP_ASTORE_0..=OP_ASTORE_3 => e.store((insn.opcode - OP_ASTORE_0) as i32)?,
+        OP_IASTORE..=OP_SASTORE => e.pop_n(3)?,
+        OP_POP => {
+            e.category_at(0)?;
+            e.stack.pop();
+        }
+        OP_POP2 => {
+            let count = if e.category_at(0)? == 2 { 1 } else { 2 };
+            for _ in 0..count {
+                e.category_at(0)?;
+                e.stack.pop();
+            }
+        }
+        OP_DUP => e.rearrange(1, &[0, 0])?,
+        OP_DUP_X1 => e.rearrange(2, &[1, 0, 1])?,
+        OP_DUP_X2 => {
+            if e.category_at(1)? == 2 {
+                e.rearrange(2, &[1, 0, 1])?
+            } else {
+                e.rearrange(3, &[2, 0, 1, 2])?
+            }
+        }
+        OP_DUP2 => {
+            if e.category_at(0)? == 2 {
+                e.rearrange(1, &[0, 0])?
+            } else {
+                e.rearrange(2, &[0, 1, 0, 1])?
+            }
+        }
+        OP_DUP2_X1 => {
+            if e.category_at(0)? == 2 {
+                e.rearrange(2, &[1, 0, 1])?
+            } else {
+                e.rearrange(3, &[1, 2, 0, 1, 2])?
+            }
+        }
+        OP_DUP2_X2 => match (e.category_at(0)?, e.category_at(1)?) {
+            (2, 2) => e.rearrange(2, &[1, 0, 1])?,
+            (2, _) => e.rearrange(3, &[2, 0, 1, 2])?,
+            (_, _) if e.category_at(2)? == 2 => e.rearrange(3, &[1, 2, 0, 1, 2])?,
+            _ => e.rearrange(4, &[2, 3, 0, 1, 2, 3])?,
+        },
+        OP_SWAP => e.rearrange(2, &[1, 0])?,
+        OP_IADD..=OP_DREM => {
+            let op = [BinaryOp::Add, BinaryOp::Sub, BinaryOp::Mul, BinaryOp::Div, BinaryOp::Rem][((insn.opcode - OP_IADD) / 4) as usize];
+            let category = if (insn.opcode - OP_IADD) % 2 == 1 { 2 } else { 1 };
+            e.binary(op, category)?;
+        }
+        OP_INEG..=OP_DNEG => e.unary(UnaryOp::Neg, if (insn.opcode - OP_INEG) % 2 == 1 { 2 } else { 1 })?,
+        OP_ISHL..=OP_LUSHR => {
+            let op = [BinaryOp::Shl, BinaryOp::Shr, BinaryOp::Ushr][((insn.opcode - OP_ISHL) / 2) as usize];
+            e.binary(op, if (insn.opcode - OP_ISHL) % 2 == 1 { 2 } else { 1 })?;
+        }
+        OP_IAND..=OP_LXOR => {
+            let op = [BinaryOp::And, BinaryOp::Or, BinaryOp::Xor][((insn.opcode - OP_IAND) / 2) as usize];
+            e.binary(op, if (insn.opcode - OP_IAND) % 2 == 1 { 2 } else { 1 })?;
+        }
+        OP_IINC => increment(e, insn.value, insn.value2),
+        OP_I2L | OP_F2L | OP_D2L => e.unary(UnaryOp::ToLong, 2)?,
+        OP_I2F | OP_L2F | OP_D2F => e.unary(UnaryOp::ToFloat, 1)?,
+        OP_I2D | OP_L2D | OP_F2D => e.unary(UnaryOp::ToDouble, 2)?,
+        OP_L2I | OP_F2I | OP_D2I => e.unary(UnaryOp::ToInt, 1)?,
+        OP_I2B => e.unary(UnaryOp::ToByte, 1)?,
+        OP_I2C => e.unary(UnaryOp::ToChar, 1)?,
+        OP_I2S => e.unary(UnaryOp::ToShort, 1)?,
+        OP_LCMP => e.binary(BinaryOp::Cmp, 1)?,
+        OP_FCMPL | OP_DCMPL => e.binary(BinaryOp::CmpL, 1)?,
+        OP_FCMPG | OP_DCMPG => e.binary(BinaryOp::CmpG, 1)?,
+        OP_IFEQ..=OP_IFLE | OP_IFNULL | OP_IFNONNULL => e.pop_n(1)?,
+        OP_IF_ICMPEQ..=OP_IF_ACMPNE => e.pop_n(2)?,
+        OP_TABLESWITCH | OP_LOOKUPSWITCH => e.pop_n(1)?,
+        OP_IRETURN..=OP_ARETURN | OP_ATHROW | OP_MONITORENTER | OP_MONITOREXIT | OP_PUTSTATIC => e.pop_n(1)?,
+        OP_PUTFIELD => e.pop_n(2)?,
+        OP_GETSTATIC | OP_GETFIELD => {
+            if insn.opcode == OP_GETFIELD {
+                e.pop_n(1)?;
+            }
+            let descriptor = pool.get_member_ref(index).map(|(_, _, d)| d.clone()).unwrap_or_default();
+            e.push(value_category(&descriptor), Operation::Unknown);
+        }
+        OP_INVOKEVIRTUAL | OP_INVOKESPECIAL | OP_INVOKESTATIC | OP_INVOKEINTERFACE | OP_INVOKEDYNAMIC => {
+            let descriptor = if insn.opcode == OP_INVOKEDYNAMIC {
+                pool.get_invoke_dynamic(index).map(|(_, _, d)| d.clone())
+            } else {
+                pool.get_member_ref(index).map(|(_, _, d)| d.clone())
+            }
+            .ok_or_else(|| format!("Invalid method reference at offset {}", insn.offset))?;
+            let (parameters, return_category) = method_categories(&descriptor);
+            let receiver = !matches!(insn.opcode, OP_INVOKESTATIC | OP_INVOKEDYNAMIC) as usize;
+            e.pop_n(parameters.len() + receiver)?;
+            if return_category > 0 {
+                e.push(return_category, Operation::Unknown);
+            }
+        }
+        OP_NEW => e.push(1, Operation::Unknown),
+        OP_NEWARRAY | OP_ANEWARRAY | OP_ARRAYLENGTH | OP_INSTANCEOF => {
+            e.pop_n(1)?;
+            e.push(1, Operation::Unknown);
+        }
+        OP_MULTIANEWARRAY => {
+            e.pop_n(insn.value2 as usize)?;
+            e.push(1, Operation::Unknown);
+        }
+        // checkcast 读取栈顶的值并原样留在栈上
+        OP_CHECKCAST => {
+            let value = e.top();
+            e.category_at(0)?;
+            e.uses.push(value);
+        }
+        OP_WIDE => match insn.value as u8 {
+            OP_ILOAD | OP_FLOAD | OP_ALOAD => e.load(insn.value2, 1),
+            OP_LLOAD | OP_DLOAD => e.load(insn.value2, 2),
+            OP_ISTORE | OP_LSTORE | OP_FSTORE | OP_DSTORE | OP_ASTORE => e.store(insn.value2)?,
+            OP_IINC => increment(e, insn.value2, insn.pairs.first().map(|(v, _)| *v).unwrap_or(0)),
+            _ => return Err(format!("Subroutines (jsr/ret) are not supported, offset {}", insn.offset)),
+        },
+        OP_JSR | OP_JSR_W | OP_RET => return Err(format!("Subroutines (jsr/ret) are not supported, offset {}", insn.offset)),
+        opcode => return Err(format!("Unsupported opcode 0x{:02x} at offset {}", opcode, insn.offset)),
+    }
+    Ok(effect)
+}
+
+fn increment(effect: &mut StackEffect, index: i32, amount: i32) {
+    let local = Variable::Local(index as u16);
+    effect.uses.push(local);
+    effect.defs.push((
+        local,
+        Operation::Binary(BinaryOp::Add, Operand::Variable(local), Operand::Constant(Constant::Int(amount))),
+    ));
+}
+
+/// 不经过跳转时的后继偏移，以及跳转目标偏移
+fn branch_offsets(insn: &Instruction) -> (bool, Vec<i64>) {
+    let relative = |delta: i32| insn.offset as i64 + delta as i64;
+    match insn.opcode {
+        OP_GOTO | OP_GOTO_W => (false, vec![relative(insn.value)]),
+        OP_IFEQ..=OP_IF_ACMPNE | OP_IFNULL | OP_IFNONNULL => (true, vec![relative(insn.value)]),
+        OP_TABLESWITCH | OP_LOOKUPSWITCH => (
+            false,
+            std::iter::once(insn.value).chain(insn.pairs.iter().map(|(_, target)| *target)).map(relative).collect(),
+        ),
+        OP_IRETURN..=OP_RETURN | OP_ATHROW => (false, Vec::new()),
+        _ => (true, Vec::new()),
+    }
+}
+
+fn instruction_text(class_file: &ClassFile, insn: &Instruction) -> String {
+    let pool = &class_file.constant_pool;
+    let name = opcode_name(insn.opcode);
+    let operand = match insn.opcode {
+        OP_LDC | OP_LDC_W | OP_LDC2_W | OP_GETSTATIC | OP_PUTSTATIC | OP_GETFIELD | OP_PUTFIELD | OP_INVOKEVIRTUAL
+        | OP_INVOKESPECIAL | OP_INVOKESTATIC | OP_INVOKEINTERFACE | OP_NEW | OP_ANEWARRAY | OP_CHECKCAST
+        | OP_INSTANCEOF | OP_MULTIANEWARRAY => constant_text(pool, insn.value as usize),
+        OP_INVOKEDYNAMIC => pool
+            .get_invoke_dynamic(insn.value as usize)
+            .map(|(_, name, descriptor)| format!("{}:{}", name, descriptor))
+            .unwrap_or_default(),
+        OP_IINC => format!("{} {}", insn.value, insn.value2),
+        OP_BIPUSH | OP_SIPUSH | OP_ILOAD | OP_LLOAD | OP_FLOAD | OP_DLOAD | OP_ALOAD | OP_ISTORE | OP_LSTORE
+        | OP_FSTORE | OP_DSTORE | OP_ASTORE => insn.value.to_string(),
+        OP_WIDE => format!("{} {}", opcode_name(insn.value as u8), insn.value2),
+        _ => branch_offsets(insn).1.iter().map(|target| target.to_string()).collect::<Vec<_>>().join(" "),
+    };
+    format!("{} {}", name, operand).trim_end().to_string()
+}
+
+pub(crate) fn flow_method(class_file: &ClassFile, method: &JvmMethod) -> Result<FlowMethod, String> {
+    let code = method
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None,
+        })
+        .ok_or_else(|| format!("Method {}{} has no code", method.name, method.descriptor))?;
+    let instructions = &method.code;
+    let count = instructions.len();
+    let index_of: HashMap<u32, usize> = instructions.iter().enumerate().map(|(i, insn)| (insn.offset, i)).collect();
+    let target_index = |offset: i64| {
+        u32::try_from(offset)
+            .ok()
+            .and_then(|offset| index_of.get(&offset).copied())
+            .ok_or_else(|| format!("Invalid branch target {}", offset))
+    };
+
+    let mut successors: Vec<Vec<usize>> = Vec::with_capacity(count);
+    for (index, insn) in instructions.iter().enumerate() {
+        let (falls_through, targets) = branch_offsets(insn);
+        let mut next = Vec::new();
+        if falls_through && index + 1 < count {
+            next.push(index + 1);
+        }
+        for target in targets {
+            let target = target_index(target)?;
+            if !next.contains(&target) {
+                next.push(target);
+            }
+        }
+        successors.push(next);
+    }
+
+    let mut handlers: Vec<Vec<usize>> = vec![Vec::new(); count];
+    let mut handler_starts = Vec::new();
+    for entry in &code.exception_table {
+        let handler = target_index(entry.handler_pc as i64)?;
+        handler_starts.push(handler);
+        for (index, insn) in instructions.iter().enumerate() {
+            let covered = insn.offset >= entry.start_pc as u32 && insn.offset < entry.end_pc as u32;
+            if covered && !handlers[index].contains(&handler) {
+                handlers[index].push(handler);
+            }
+        }
+    }
+
+    // 模拟操作数栈的深度和类别，每条可达指令只翻译一次
+    let mut stacks: Vec<Option<Vec<u8>>> = vec![None; count];
+    let mut effects: Vec<Option<StackEffect>> = (0..count).map(|_| None).collect();
+    let mut worklist = Vec::new();
+    if count > 0 {
+        stacks[0] = Some(Vec::new());
+        worklist.push(0);
+    }
+    for handler in handler_starts {
+        stacks[handler] = Some(vec![1]);
+        worklist.push(handler);
+    }
+    while let Some(index) = worklist.pop() {
+        if effects[index].is_some() {
+            continue;
+        }
+        let input = stacks[index].clone().unwrap_or_default();
+        let effect = translate(class_file, &instructions[index], &input)?;
+        for &successor in &successors[index] {
+            match &stacks[successor] {
+                None => {
+                    stacks[successor] = Some(effect.stack.clone());
+                    worklist.push(successor);
+                }
+                Some(existing) if existing.len() != effect.stack.len() => {
+                    return Err(format!("Inconsistent operand stack height at offset {}", instructions[successor].offset));
+                }
+                Some(_) => {}
+            }
+        }
+        effects[index] = Some(effect);
+    }
+
+    let flow_instructions = instructions
+        .iter()
+        .zip(effects)
+        .enumerate()
+        .map(|(index, (insn, effect))| {
+            let (uses, defs) = effect.map(|e| (e.uses, e.defs)).unwrap_or_default();
+            FlowInstruction {
+                offset: insn.offset,
+                text: instruction_text(class_file, insn),
+                uses,
+                defs,
+                successors: std::mem::take(&mut successors[index]),
+                handlers: std::mem::take(&mut handlers[index]),
+            }
+        })
+        .collect();
+
+    let mut parameters = Vec::new();
+    let mut slot = 0u16;
+    if method.access_flags & ACC_STATIC == 0 {
+        parameters.push(Variable::Local(0));
+        slot = 1;
+    }
+    for category in method_categories(&method.descriptor).0 {
+        parameters.push(Variable::Local(slot));
+        slot += category as u16;
+    }
+
+    Ok(FlowMethod {
+        instructions: flow_instructions,
+        parameters,
+    })
+}
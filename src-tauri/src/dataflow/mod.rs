@@ -0,0 +1,385 @@
+// Intraprocedural data-flow analysis: reaching definitions, liveness, constant propagation and def-use chains
+pub(crate) mod dalvik;
+pub(crate) mod jvm;
+pub(crate) mod solver;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::hierarchy::{normalize_class_name, proto_to_descriptor};
+use crate::java_analyzer::classfile::ClassFileReader;
+use crate::project::{Project, ProjectData};
+use solver::{solve, ConstantPropagation, ConstantState, Definition, DefinitionSite, Liveness, ReachingDefinitions, Solution};
+
+/// A storage location of the method IR. JVM operand stack slots count values, not words,
+/// so a `long` occupies one slot. Dalvik registers are locals, a wide value takes two of them,
+/// and `stack0` holds the pending call result or caught exception.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum Variable {
+    Local(u16),
+    Stack(u16),
+}
+
+impl Variable {
+    pub fn is_stack(&self) -> bool {
+        matches!(self, Variable::Stack(_))
+    }
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variable::Local(index) => write!(f, "local{}", index),
+            Variable::Stack(index) => write!(f, "stack{}", index),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Constant {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Null,
+}
+
+// 浮点数按位比较，否则 NaN 会让不动点迭代无法收敛
+impl PartialEq for Constant {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Constant::Int(a), Constant::Int(b)) => a == b,
+            (Constant::Long(a), Constant::Long(b)) => a == b,
+            (Constant::Float(a), Constant::Float(b)) => a.to_bits() == b.to_bits(),
+            (Constant::Double(a), Constant::Double(b)) => a.to_bits() == b.to_bits(),
+            (Constant::String(a), Constant::String(b)) => a == b,
+            (Constant::Null, Constant::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(value) => write!(f, "{}", value),
+            Constant::Long(value) => write!(f, "{}L", value),
+            Constant::Float(value) => write!(f, "{:?}f", value),
+            Constant::Double(value) => write!(f, "{:?}d", value),
+            Constant::String(value) => write!(f, "{:?}", value),
+            Constant::Null => write!(f, "null"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+    Neg,
+    ToInt,
+    ToLong,
+    ToFloat,
+    ToDouble,
+    ToByte,
+    ToChar,
+    ToShort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Ushr,
+    /// `lcmp`
+    Cmp,
+    /// `fcmpl` / `dcmpl`: NaN compares as -1
+    CmpL,
+    /// `fcmpg` / `dcmpg`: NaN compares as 1
+    CmpG,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Operand {
+    Variable(Variable),
+    Constant(Constant),
+}
+
+/// How a defined variable gets its value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Operation {
+    Constant(Constant),
+    Copy(Variable),
+    Unary(UnaryOp, Variable),
+    Binary(BinaryOp, Operand, Operand),
+    /// Field and array reads, call results and everything else the analyses do not model
+    Unknown,
+}
+
+/// One instruction of the method IR. Indexes refer to [`FlowMethod::instructions`].
+#[derive(Debug, Clone)]
+pub(crate) struct FlowInstruction {
+    pub offset: u32,
+    pub text: String,
+    pub uses: Vec<Variable>,
+    /// Definitions take effect together, after all uses have been read
+    pub defs: Vec<(Variable, Operation)>,
+    pub successors: Vec<usize>,
+    /// Exception handlers covering the instruction
+    pub handlers: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct FlowMethod {
+    pub instructions: Vec<FlowInstruction>,
+    /// Variables holding `this` and the arguments on entry
+    pub parameters: Vec<Variable>,
+}
+
+impl FlowMethod {
+    pub fn index_of(&self, offset: u32) -> Option<usize> {
+        self.instructions.binary_search_by_key(&offset, |instruction| instruction.offset).ok()
+    }
+}
+
+/// Where a definition comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DefinitionKind {
+    Parameter,
+    Instruction,
+    /// The caught exception pushed on entry to a handler
+    ExceptionHandler,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DefinitionRef {
+    pub variable: String,
+    pub kind: DefinitionKind,
+    /// Offset of the defining instruction or handler, absent for parameters
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConstantBinding {
+    pub variable: String,
+    pub value: String,
+}
+
+/// Data-flow facts holding just before and just after one instruction.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstructionFlow {
+    pub offset: u32,
+    pub text: String,
+    pub uses: Vec<String>,
+    pub defs: Vec<String>,
+    /// Definitions reaching the instruction
+    pub reaching_definitions: Vec<DefinitionRef>,
+    pub live_in: Vec<String>,
+    pub live_out: Vec<String>,
+    /// Variables known to hold a constant before the instruction
+    pub constants_in: Vec<ConstantBinding>,
+    pub constants_out: Vec<ConstantBinding>,
+    /// For each variable the instruction reads, the definitions it may read from
+    pub use_definitions: Vec<UseDefinitions>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UseDefinitions {
+    pub variable: String,
+    pub definitions: Vec<DefinitionRef>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DefUseChain {
+    pub definition: DefinitionRef,
+    /// Offsets of the instructions reading the definition
+    pub uses: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodDataFlow {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+    pub instructions: Vec<InstructionFlow>,
+    pub def_use_chains: Vec<DefUseChain>,
+}
+
+/// 所有分析的结果，def-use 链由到达定义推出
+pub(crate) struct MethodAnalysis {
+    pub method: FlowMethod,
+    pub reaching: Solution<BTreeSet<Definition>>,
+    pub liveness: Solution<BTreeSet<Variable>>,
+    pub constants: Solution<BTreeMap<Variable, ConstantState>>,
+}
+
+impl MethodAnalysis {
+    pub fn new(method: FlowMethod) -> Self {
+        let reaching = solve(&ReachingDefinitions, &method);
+        let liveness = solve(&Liveness, &method);
+        let constants = solve(&ConstantPropagation, &method);
+        MethodAnalysis {
+            method,
+            reaching,
+            liveness,
+            constants,
+        }
+    }
+
+    /// 指令读取某个变量时可能读到的定义
+    pub fn definitions_of(&self, index: usize, variable: Variable) -> Vec<Definition> {
+        self.reaching.before[index].iter().filter(|d| d.variable == variable).copied().collect()
+    }
+
+    pub fn def_use_chains(&self) -> BTreeMap<Definition, BTreeSet<usize>> {
+        let mut chains: BTreeMap<Definition, BTreeSet<usize>> = BTreeMap::new();
+        for (index, instruction) in self.method.instructions.iter().enumerate() {
+            for (variable, _) in &instruction.defs {
+                chains.entry(Definition { site: DefinitionSite::Instruction(index), variable: *variable }).or_default();
+            }
+            for variable in &instruction.uses {
+                for definition in self.definitions_of(index, *variable) {
+                    chains.entry(definition).or_default().insert(index);
+                }
+            }
+        }
+        chains
+    }
+
+    fn definition_ref(&self, definition: &Definition) -> DefinitionRef {
+        let (kind, offset) = match definition.site {
+            DefinitionSite::Parameter => (DefinitionKind::Parameter, None),
+            DefinitionSite::Instruction(index) => (DefinitionKind::Instruction, Some(self.method.instructions[index].offset)),
+            DefinitionSite::Handler(index) => (DefinitionKind::ExceptionHandler, Some(self.method.instructions[index].offset)),
+        };
+        DefinitionRef {
+            variable: definition.variable.to_string(),
+            kind,
+            offset,
+        }
+    }
+
+    fn instruction_flow(&self, index: usize) -> InstructionFlow {
+        let instruction = &self.method.instructions[index];
+        let names = |variables: &BTreeSet<Variable>| variables.iter().map(|v| v.to_string()).collect();
+        // 出栈后的栈槽仍保留旧的事实，只展示仍然活跃的栈变量
+        let live_in = &self.liveness.before[index];
+        let live_out = &self.liveness.after[index];
+        let constants = |facts: &BTreeMap<Variable, ConstantState>, live: &BTreeSet<Variable>| {
+            facts
+                .iter()
+                .filter(|(variable, _)| !variable.is_stack() || live.contains(variable))
+                .filter_map(|(variable, state)| match state {
+                    ConstantState::Constant(value) => Some(ConstantBinding {
+                        variable: variable.to_string(),
+                        value: value.to_string(),
+                    }),
+                    ConstantState::NotConstant => None,
+                })
+                .collect()
+        };
+        let uses: BTreeSet<Variable> = instruction.uses.iter().copied().collect();
+        InstructionFlow {
+            offset: instruction.offset,
+            text: instruction.text.clone(),
+            uses: uses.iter().map(|v| v.to_string()).collect(),
+            defs: instruction.defs.iter().map(|(v, _)| v.to_string()).collect(),
+            reaching_definitions: self.reaching.before[index]
+                .iter()
+                .filter(|d| !d.variable.is_stack() || live_in.contains(&d.variable))
+                .map(|d| self.definition_ref(d))
+                .collect(),
+            live_in: names(live_in),
+            live_out: names(live_out),
+            constants_in: constants(&self.constants.before[index], live_in),
+            constants_out: constants(&self.constants.after[index], live_out),
+            use_definitions: uses
+                .iter()
+                .map(|variable| UseDefinitions {
+                    variable: variable.to_string(),
+                    definitions: self.definitions_of(index, *variable).iter().map(|d| self.definition_ref(d)).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 从项目中取出方法并构建 IR
+pub(crate) fn project_flow_method(project_id: &str, class_name: &str, method_name: &str, descriptor: &str) -> Result<FlowMethod, String> {
+    let class_name = normalize_class_name(class_name);
+    Project::with_project_mut(project_id, |project| match &mut project.data {
+        ProjectData::Java(java_data) => {
+            let bytes = java_data.classpath.read_class(&class_name)?;
+            let class_file = ClassFileReader::new(&bytes)
+                .read()
+                .map_err(|e| format!("Failed to parse class file: {:?}", e))?;
+            let method = class_file
+                .methods
+                .iter()
+                .find(|m| m.name == method_name && m.descriptor == descriptor)
+                .ok_or_else(|| format!("Method not found: {}.{}{}", class_name, method_name, descriptor))?;
+            jvm::flow_method(&class_file, method)
+        }
+        ProjectData::Android(android_data) => {
+            let (dex_file, class_def) = android_data
+                .dex_class(&format!("L{};", class_name))?
+                .ok_or_else(|| format!("Class not found: {}", class_name))?;
+            let method = class_def
+                .direct_methods
+                .iter()
+                .chain(&class_def.virtual_methods)
+                .find(|m| m.name == method_name && proto_to_descriptor(&m.proto) == descriptor)
+                .ok_or_else(|| format!("Method not found: {}.{}{}", class_name, method_name, descriptor))?;
+            dalvik::flow_method(dex_file, method)
+        }
+        _ => Err("Not a Java or Android project".to_string()),
+    })
+}
+
+/// Runs every analysis on a method and returns the facts of each instruction with the def-use chains.
+#[tauri::command(async)]
+pub fn dataflow_analyze_method(project_id: String, class_name: String, method_name: String, descriptor: String) -> Result<MethodDataFlow, String> {
+    let analysis = MethodAnalysis::new(project_flow_method(&project_id, &class_name, &method_name, &descriptor)?);
+    let instructions = (0..analysis.method.instructions.len()).map(|index| analysis.instruction_flow(index)).collect();
+    let def_use_chains = analysis
+        .def_use_chains()
+        .iter()
+        .map(|(definition, uses)| DefUseChain {
+            definition: analysis.definition_ref(definition),
+            uses: uses.iter().map(|index| analysis.method.instructions[*index].offset).collect(),
+        })
+        .collect();
+    Ok(MethodDataFlow {
+        class_name: normalize_class_name(&class_name),
+        method_name,
+        descriptor,
+        instructions,
+        def_use_chains,
+    })
+}
+
+/// Facts of the instruction at `offset`.
+#[tauri::command(async)]
+pub fn dataflow_query_instruction(
+    project_id: String,
+    class_name: String,
+    method_name: String,
+    descriptor: String,
+    offset: u32,
+) -> Result<InstructionFlow, String> {
+    let analysis = MethodAnalysis::new(project_flow_method(&project_id, &class_name, &method_name, &descriptor)?);
+    let index = analysis
+        .method
+        .index_of(offset)
+        .ok_or_else(|| format!("No instruction at offset {}", offset))?;
+    Ok(analysis.instruction_flow(index))
+}
@@ -0,0 +1,382 @@
+// Worklist solver and the standard analyses built on it
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use super::{BinaryOp, Constant, FlowInstruction, FlowMethod, Operand, Operation, UnaryOp, Variable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Forward,
+    Backward,
+}
+
+/// A monotone data-flow problem. `transfer` maps the fact before an instruction to the fact
+/// after it for forward problems, and the fact after to the fact before for backward ones.
+pub(crate) trait Analysis {
+    type Fact: Clone + PartialEq;
+
+    fn direction(&self) -> Direction;
+    /// Fact at method entry (forward) or at the exits (backward)
+    fn boundary(&self, method: &FlowMethod) -> Self::Fact;
+    fn bottom(&self) -> Self::Fact;
+    fn join(&self, into: &mut Self::Fact, other: &Self::Fact);
+    fn transfer(&self, index: usize, instruction: &FlowInstruction, fact: &Self::Fact) -> Self::Fact;
+    /// Crossing an exception edge into `handler`: the operand stack is replaced by the exception.
+    /// Forward problems get the fact before the throwing instruction, backward ones the fact
+    /// before the handler.
+    fn catch(&self, handler: usize, fact: &Self::Fact) -> Self::Fact;
+}
+
+/// Facts in program order: `before[i]` holds just before instruction `i` executes.
+#[derive(Debug, Clone)]
+pub(crate) struct Solution<F> {
+    pub before: Vec<F>,
+    pub after: Vec<F>,
+}
+
+pub(crate) fn solve<A: Analysis>(analysis: &A, method: &FlowMethod) -> Solution<A::Fact> {
+    let instructions = &method.instructions;
+    let count = instructions.len();
+    let mut solution = Solution {
+        before: vec![analysis.bottom(); count],
+        after: vec![analysis.bottom(); count],
+    };
+    if count == 0 {
+        return solution;
+    }
+
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); count];
+    let mut thrown_from: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for (index, instruction) in instructions.iter().enumerate() {
+        for &successor in &instruction.successors {
+            predecessors[successor].push(index);
+        }
+        for &handler in &instruction.handlers {
+            thrown_from[handler].push(index);
+        }
+    }
+
+    let boundary = analysis.boundary(method);
+    let mut queue: VecDeque<usize> = match analysis.direction() {
+        Direction::Forward => (0..count).collect(),
+        Direction::Backward => (0..count).rev().collect(),
+    };
+    let mut queued = vec![true; count];
+
+    while let Some(index) = queue.pop_front() {
+        queued[index] = false;
+        let instruction = &instructions[index];
+        let (changed, dependents): (bool, Vec<usize>) = match analysis.direction() {
+            Direction::Forward => {
+                let mut input = if index == 0 { boundary.clone() } else { analysis.bottom() };
+                for &predecessor in &predecessors[index] {
+                    analysis.join(&mut input, &solution.after[predecessor]);
+                }
+                for &thrower in &thrown_from[index] {
+                    analysis.join(&mut input, &analysis.catch(index, &solution.before[thrower]));
+                }
+                let output = analysis.transfer(index, instruction, &input);
+                let changed = input != solution.before[index] || output != solution.after[index];
+                solution.before[index] = input;
+                solution.after[index] = output;
+                (changed, instruction.successors.iter().chain(instruction.handlers.iter()).copied().collect())
+            }
+            Direction::Backward => {
+                let mut output = if instruction.successors.is_empty() { boundary.clone() } else { analysis.bottom() };
+                for &successor in &instruction.successors {
+                    analysis.join(&mut output, &solution.before[successor]);
+                }
+                for &handler in &instruction.handlers {
+                    analysis.join(&mut output, &analysis.catch(handler, &solution.before[handler]));
+                }
+                let input = analysis.transfer(index, instruction, &output);
+                let changed = input != solution.before[index] || output != solution.after[index];
+                solution.before[index] = input;
+                solution.after[index] = output;
+                (changed, predecessors[index].iter().chain(thrown_from[index].iter()).copied().collect())
+            }
+        };
+        if changed {
+            for dependent in dependents {
+                if !queued[dependent] {
+                    queued[dependent] = true;
+                    queue.push_back(dependent);
+                }
+            }
+        }
+    }
+    solution
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum DefinitionSite {
+    Parameter,
+    Instruction(usize),
+    Handler(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct Definition {
+    pub site: DefinitionSite,
+    pub variable: Variable,
+}
+
+pub(crate) struct ReachingDefinitions;
+
+impl Analysis for ReachingDefinitions {
+    type Fact = BTreeSet<Definition>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self, method: &FlowMethod) -> Self::Fact {
+        method
+            .parameters
+            .iter()
+            .map(|variable| Definition { site: DefinitionSite::Parameter, variable: *variable })
+            .collect()
+    }
+
+    fn bottom(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn join(&self, into: &mut Self::Fact, other: &Self::Fact) {
+        into.extend(other.iter().copied());
+    }
+
+    fn transfer(&self, index: usize, instruction: &FlowInstruction, fact: &Self::Fact) -> Self::Fact {
+        let mut output: Self::Fact = fact
+            .iter()
+            .filter(|d| !instruction.defs.iter().any(|(variable, _)| *variable == d.variable))
+            .copied()
+            .collect();
+        for (variable, _) in &instruction.defs {
+            output.insert(Definition { site: DefinitionSite::Instruction(index), variable: *variable });
+        }
+        output
+    }
+
+    fn catch(&self, handler: usize, fact: &Self::Fact) -> Self::Fact {
+        let mut output: Self::Fact = fact.iter().filter(|d| !d.variable.is_stack()).copied().collect();
+        output.insert(Definition { site: DefinitionSite::Handler(handler), variable: Variable::Stack(0) });
+        output
+    }
+}
+
+pub(crate) struct Liveness;
+
+impl Analysis for Liveness {
+    type Fact = BTreeSet<Variable>;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn boundary(&self, _method: &FlowMethod) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn bottom(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn join(&self, into: &mut Self::Fact, other: &Self::Fact) {
+        into.extend(other.iter().copied());
+    }
+
+    fn transfer(&self, _index: usize, instruction: &FlowInstruction, fact: &Self::Fact) -> Self::Fact {
+        let mut input: Self::Fact = fact
+            .iter()
+            .filter(|variable| !instruction.defs.iter().any(|(defined, _)| defined == *variable))
+            .copied()
+            .collect();
+        input.extend(instruction.uses.iter().copied());
+        input
+    }
+
+    fn catch(&self, _handler: usize, fact: &Self::Fact) -> Self::Fact {
+        fact.iter().filter(|variable| !variable.is_stack()).copied().collect()
+    }
+}
+
+/// 变量不在映射中表示尚无定义到达（格的底）
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ConstantState {
+    Constant(Constant),
+    NotConstant,
+}
+
+pub(crate) struct ConstantPropagation;
+
+impl ConstantPropagation {
+    fn operand(fact: &BTreeMap<Variable, ConstantState>, operand: &Operand) -> Option<ConstantState> {
+        match operand {
+            Operand::Variable(variable) => fact.get(variable).cloned(),
+            Operand::Constant(constant) => Some(ConstantState::Constant(constant.clone())),
+        }
+    }
+
+    /// None 表示操作数尚无定义到达
+    fn evaluate(fact: &BTreeMap<Variable, ConstantState>, operation: &Operation) -> Option<ConstantState> {
+        let known = |state: ConstantState, f: &dyn Fn(Constant) -> Option<Constant>| match state {
+            ConstantState::Constant(value) => f(value).map_or(ConstantState::NotConstant, ConstantState::Constant),
+            ConstantState::NotConstant => ConstantState::NotConstant,
+        };
+        match operation {
+            Operation::Constant(value) => Some(ConstantState::Constant(value.clone())),
+            Operation::Copy(variable) => fact.get(variable).cloned(),
+            Operation::Unary(op, variable) => {
+                let value = fact.get(variable).cloned()?;
+                Some(known(value, &|v| unary(*op, v)))
+            }
+            Operation::Binary(op, left, right) => {
+                let left = Self::operand(fact, left)?;
+                let right = Self::operand(fact, right)?;
+                match (left, right) {
+                    (ConstantState::Constant(a), ConstantState::Constant(b)) => {
+                        Some(binary(*op, a, b).map_or(ConstantState::NotConstant, ConstantState::Constant))
+                    }
+                    _ => Some(ConstantState::NotConstant),
+                }
+            }
+            Operation::Unknown => Some(ConstantState::NotConstant),
+        }
+    }
+}
+
+impl Analysis for ConstantPropagation {
+    type Fact = BTreeMap<Variable, ConstantState>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self, method: &FlowMethod) -> Self::Fact {
+        method.parameters.iter().map(|variable| (*variable, ConstantState::NotConstant)).collect()
+    }
+
+    fn bottom(&self) -> Self::Fact {
+        BTreeMap::new()
+    }
+
+    fn join(&self, into: &mut Self::Fact, other: &Self::Fact) {
+        for (variable, state) in other {
+            match into.get(variable) {
+                None => {
+                    into.insert(*variable, state.clone());
+                }
+                Some(existing) if existing != state => {
+                    into.insert(*variable, ConstantState::NotConstant);
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn transfer(&self, _index: usize, instruction: &FlowInstruction, fact: &Self::Fact) -> Self::Fact {
+        let values: Vec<(Variable, Option<ConstantState>)> = instruction
+            .defs
+            .iter()
+            .map(|(variable, operation)| (*variable, Self::evaluate(fact, operation)))
+            .collect();
+        let mut output = fact.clone();
+        for (variable, value) in values {
+            match value {
+                Some(state) => output.insert(variable, state),
+                None => output.remove(&variable),
+            };
+        }
+        output
+    }
+
+    fn catch(&self, _handler: usize, fact: &Self::Fact) -> Self::Fact {
+        let mut output: Self::Fact = fact
+            .iter()
+            .filter(|(variable, _)| !variable.is_stack())
+            .map(|(variable, state)| (*variable, state.clone()))
+            .collect();
+        output.insert(Variable::Stack(0), ConstantState::NotConstant);
+        output
+    }
+}
+
+/// Java 语义的一元运算，浮点转整数饱和、NaN 转为 0，与 Rust 的 `as` 一致
+pub(crate) fn unary(op: UnaryOp, value: Constant) -> Option<Constant> {
+    use Constant::*;
+    Some(match (op, value) {
+        (UnaryOp::Neg, Int(v)) => Int(v.wrapping_neg()),
+        (UnaryOp::Neg, Long(v)) => Long(v.wrapping_neg()),
+        (UnaryOp::Neg, Float(v)) => Float(-v),
+        (UnaryOp::Neg, Double(v)) => Double(-v),
+        (UnaryOp::ToInt, Long(v)) => Int(v as i32),
+        (UnaryOp::ToInt, Float(v)) => Int(v as i32),
+        (UnaryOp::ToInt, Double(v)) => Int(v as i32),
+        (UnaryOp::ToLong, Int(v)) => Long(v as i64),
+        (UnaryOp::ToLong, Float(v)) => Long(v as i64),
+        (UnaryOp::ToLong, Double(v)) => Long(v as i64),
+        (UnaryOp::ToFloat, Int(v)) => Float(v as f32),
+        (UnaryOp::ToFloat, Long(v)) => Float(v as f32),
+        (UnaryOp::ToFloat, Double(v)) => Float(v as f32),
+        (UnaryOp::ToDouble, Int(v)) => Double(v as f64),
+        (UnaryOp::ToDouble, Long(v)) => Double(v as f64),
+        (UnaryOp::ToDouble, Float(v)) => Double(v as f64),
+        (UnaryOp::ToByte, Int(v)) => Int(v as i8 as i32),
+        (UnaryOp::ToChar, Int(v)) => Int(v as u16 as i32),
+        (UnaryOp::ToShort, Int(v)) => Int(v as i16 as i32),
+        _ => return None,
+    })
+}
+
+/// Java 语义的二元运算；除零等会抛异常的情况返回 None
+pub(crate) fn binary(op: BinaryOp, left: Constant, right: Constant) -> Option<Constant> {
+    use Constant::*;
+    let compare = |ordering: Option<std::cmp::Ordering>, nan: i32| {
+        Int(match ordering {
+            Some(std::cmp::Ordering::Less) => -1,
+            Some(std::cmp::Ordering::Equal) => 0,
+            Some(std::cmp::Ordering::Greater) => 1,
+            None => nan,
+        })
+    };
+    Some(match (op, left, right) {
+        (BinaryOp::Add, Int(a), Int(b)) => Int(a.wrapping_add(b)),
+        (BinaryOp::Sub, Int(a), Int(b)) => Int(a.wrapping_sub(b)),
+        (BinaryOp::Mul, Int(a), Int(b)) => Int(a.wrapping_mul(b)),
+        (BinaryOp::Div, Int(a), Int(b)) if b != 0 => Int(a.wrapping_div(b)),
+        (BinaryOp::Rem, Int(a), Int(b)) if b != 0 => Int(a.wrapping_rem(b)),
+        (BinaryOp::And, Int(a), Int(b)) => Int(a & b),
+        (BinaryOp::Or, Int(a), Int(b)) => Int(a | b),
+        (BinaryOp::Xor, Int(a), Int(b)) => Int(a ^ b),
+        (BinaryOp::Shl, Int(a), Int(b)) => Int(a.wrapping_shl(b as u32 & 31)),
+        (BinaryOp::Shr, Int(a), Int(b)) => Int(a.wrapping_shr(b as u32 & 31)),
+        (BinaryOp::Ushr, Int(a), Int(b)) => Int(((a as u32) >> (b as u32 & 31)) as i32),
+        (BinaryOp::Add, Long(a), Long(b)) => Long(a.wrapping_add(b)),
+        (BinaryOp::Sub, Long(a), Long(b)) => Long(a.wrapping_sub(b)),
+        (BinaryOp::Mul, Long(a), Long(b)) => Long(a.wrapping_mul(b)),
+        (BinaryOp::Div, Long(a), Long(b)) if b != 0 => Long(a.wrapping_div(b)),
+        (BinaryOp::Rem, Long(a), Long(b)) if b != 0 => Long(a.wrapping_rem(b)),
+        (BinaryOp::And, Long(a), Long(b)) => Long(a & b),
+        (BinaryOp::Or, Long(a), Long(b)) => Long(a | b),
+        (BinaryOp::Xor, Long(a), Long(b)) => Long(a ^ b),
+        (BinaryOp::Shl, Long(a), Int(b)) => Long(a.wrapping_shl(b as u32 & 63)),
+        (BinaryOp::Shr, Long(a), Int(b)) => Long(a.wrapping_shr(b as u32 & 63)),
+        (BinaryOp::Ushr, Long(a), Int(b)) => Long(((a as u64) >> (b as u32 & 63)) as i64),
+        (BinaryOp::Cmp, Long(a), Long(b)) => compare(Some(a.cmp(&b)), 0),
+        (BinaryOp::Add, Float(a), Float(b)) => Float(a + b),
+        (BinaryOp::Sub, Float(a), Float(b)) => Float(a - b),
+        (BinaryOp::Mul, Float(a), Float(b)) => Float(a * b),
+        (BinaryOp::Div, Float(a), Float(b)) => Float(a / b),
+        (BinaryOp::Rem, Float(a), Float(b)) => Float(a % b),
+        (BinaryOp::CmpL, Float(a), Float(b)) => compare(a.partial_cmp(&b), -1),
+        (BinaryOp::CmpG, Float(a), Float(b)) => compare(a.partial_cmp(&b), 1),
+        (BinaryOp::Add, Double(a), Double(b)) => Double(a + b),
+        (BinaryOp::Sub, Double(a), Double(b)) => Double(a - b),
+        (BinaryOp::Mul, Double(a), Double(b)) => Double(a * b),
+        (BinaryOp::Div, Double(a), Double(b)) => Double(a / b),
+        (BinaryOp::Rem, Double(a), Double(b)) => Double(a % b),
+        (BinaryOp::CmpL, Double(a), Double(b)) => compare(a.partial_cmp(&b), -1),
+        (BinaryOp::CmpG, Double(a), Double(b)) => compare(a.partial_cmp(&b), 1),
+        _ => return None,
+    })
+}
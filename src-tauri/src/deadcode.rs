@@ -0,0 +1,862 @@
+// Dead code detection: classes, methods and fields not reachable from the entry points of a project
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::android::AndroidManifest;
+use crate::android_analyzer::axml_analyzer::{AXMLAnalyzer, XmlElement};
+use crate::java_analyzer::jar::JarReader;
+use crate::hierarchy::normalize_class_name;
+use crate::java_analyzer::classpath::Classpath;
+use crate::program::{CodeReference, InvokeKind, Program, ProgramClass};
+use crate::project::{Project, ProjectClasses, ProjectData};
+use crate::sbom::parse_manifest;
+
+const ACC_PUBLIC: u32 = 0x0001;
+const ACC_PROTECTED: u32 = 0x0004;
+const ACC_STATIC: u32 = 0x0008;
+const ACC_ENUM: u32 = 0x4000;
+
+/// 由 java.io 序列化机制通过反射访问的成员
+const SERIALIZATION_MEMBERS: &[&str] = &[
+    "serialVersionUID",
+    "serialPersistentFields",
+    "readObject",
+    "writeObject",
+    "readObjectNoData",
+    "readResolve",
+    "writeReplace",
+];
+
+/// java.lang.Object 中可被库代码回调的方法
+const OBJECT_METHODS: &[(&str, &str)] = &[
+    ("toString", "()Ljava/lang/String;"),
+    ("equals", "(Ljava/lang/Object;)Z"),
+    ("hashCode", "()I"),
+    ("finalize", "()V"),
+    ("clone", "()Ljava/lang/Object;"),
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeadCodeOptions {
+    /// Treat public and protected members of public classes as used, for library jars
+    #[serde(default)]
+    pub public_api_is_live: bool,
+    /// Extra roots: `com.foo.Bar` for a whole class or `com.foo.Bar#method` for every overload
+    #[serde(default)]
+    pub extra_entry_points: Vec<String>,
+}
+
+/// A root of the reachability analysis. Without a method name the whole class is a root.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryPoint {
+    pub class_name: String,
+    pub method_name: Option<String>,
+    /// None matches every overload
+    pub descriptor: Option<String>,
+    pub reason: String,
+}
+
+/// Why an item is reported as dead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DeadReason {
+    /// No instruction of the project refers to it
+    NeverReferenced,
+    /// Referenced, but only by code that is itself unreachable
+    OnlyReferencedFromDeadCode,
+    /// An instance method of a class that is used but never instantiated, nor are its subclasses
+    NeverInstantiated,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadItem {
+    pub class_name: String,
+    /// None for classes
+    pub name: Option<String>,
+    pub descriptor: Option<String>,
+    pub reason: DeadReason,
+}
+
+/// Members of dead classes are not listed separately.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadCodeReport {
+    pub entry_points: Vec<EntryPoint>,
+    pub total_classes: usize,
+    pub live_classes: usize,
+    pub total_methods: usize,
+    pub live_methods: usize,
+    pub total_fields: usize,
+    pub live_fields: usize,
+    pub dead_classes: Vec<DeadItem>,
+    pub dead_methods: Vec<DeadItem>,
+    pub dead_fields: Vec<DeadItem>,
+}
+
+type MemberKey = (String, String, String);
+
+/// RTA 风格的可达性分析：虚调用只分派到已实例化的类型
+struct Reachability<'a> {
+    program: &'a Program,
+    entry_points: Vec<EntryPoint>,
+    live_classes: HashSet<String>,
+    instantiated: HashSet<String>,
+    live_methods: HashSet<MemberKey>,
+    live_fields: HashSet<MemberKey>,
+    worklist: Vec<MemberKey>,
+    // (name, descriptor) -> 虚调用的接收者静态类型
+    virtual_calls: HashMap<(String, String), HashSet<String>>,
+    supertypes: HashMap<String, HashSet<String>>,
+}
+
+impl<'a> Reachability<'a> {
+    fn new(program: &'a Program) -> Self {
+        Reachability {
+            program,
+            entry_points: Vec::new(),
+            live_classes: HashSet::new(),
+            instantiated: HashSet::new(),
+            live_methods: HashSet::new(),
+            live_fields: HashSet::new(),
+            worklist: Vec::new(),
+            virtual_calls: HashMap::new(),
+            supertypes: HashMap::new(),
+        }
+    }
+
+    fn add_entry_point(&mut self, entry_point: EntryPoint) {
+        let program = self.program;
+        let Some(class) = program.classes.get(&entry_point.class_name) else {
+            return;
+        };
+        match &entry_point.method_name {
+            None => {
+                self.instantiate(&class.name);
+                for method in &class.methods {
+                    self.mark_method(&class.name, &method.name, &method.descriptor);
+                }
+            }
+            Some(name) => {
+                let mut found = false;
+                for method in class.methods.iter().filter(|m| &m.name == name) {
+                    if entry_point.descriptor.as_ref().is_none_or(|d| d == &method.descriptor) {
+                        self.mark_method(&class.name, &method.name, &method.descriptor);
+                        found = true;
+                    }
+                }
+                if !found {
+                    return;
+                }
+                if name == "<init>" {
+                    self.instantiate(&class.name);
+                }
+            }
+        }
+        self.entry_points.push(entry_point);
+    }
+
+    /// 公开类的 public/protected 成员可能被项目外的代码使用
+    fn add_public_api(&mut self) {
+        let program = self.program;
+        for class in program.classes.values().filter(|c| c.access_flags & ACC_PUBLIC != 0) {
+            self.mark_class(&class.name);
+            for field in class.fields.iter().filter(|f| f.access_flags & (ACC_PUBLIC | ACC_PROTECTED) != 0) {
+                self.live_fields.insert((class.name.clone(), field.name.clone(), field.descriptor.clone()));
+            }
+            for method in class.methods.iter().filter(|m| m.access_flags & (ACC_PUBLIC | ACC_PROTECTED) != 0) {
+                if method.name == "<init>" {
+                    self.instantiate(&class.name);
+                }
+                // 外部代码的调用按虚调用处理，会分派到已实例化的子类型
+                let kind = if method.access_flags & ACC_STATIC != 0 { InvokeKind::Static } else { InvokeKind::Virtual };
+                self.call(kind, &class.name, &method.name, &method.descriptor);
+            }
+        }
+    }
+
+    fn run(&mut self) {
+        let program = self.program;
+        while let Some((owner, name, descriptor)) = self.worklist.pop() {
+            let Some(method) = program.classes.get(&owner).and_then(|c| c.method(&name, &descriptor)) else {
+                continue;
+            };
+            for reference in &method.references {
+                match reference {
+                    CodeReference::Invoke { kind, owner: target, name, descriptor, .. } => {
+                        self.call(*kind, target, name, descriptor)
+                    }
+                    CodeReference::Field { owner: target, name, descriptor, .. } => {
+                        if let Some(class) = program.resolve_field(target, name, descriptor) {
+                            self.mark_class(&class.name);
+                            self.live_fields.insert((class.name.clone(), name.clone(), descriptor.clone()));
+                        }
+                    }
+                    CodeReference::Instantiate(class_name) => self.instantiate(class_name),
+                    CodeReference::Type(class_name) => self.mark_class(class_name),
+                    CodeReference::String(value) => self.string_hint(value, &owner, &name),
+                    CodeReference::MethodHandle { owner: target, name, descriptor } => {
+                        if name == "<init>" {
+                            self.instantiate(target);
+                        }
+                        if let Some(class) = program.resolve_method(target, name, descriptor) {
+                            self.mark_method(&class.name, name, descriptor);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn mark_class(&mut self, class_name: &str) {
+        let program = self.program;
+        let Some(class) = program.classes.get(class_name) else {
+            return;
+        };
+        if !self.live_classes.insert(class.name.clone()) {
+            return;
+        }
+        // 类被使用时会执行静态初始化，并加载其所有父类型
+        self.mark_method(&class.name, "<clinit>", "()V");
+        for supertype in class.super_name.iter().chain(class.interfaces.iter()) {
+            self.mark_class(supertype);
+        }
+        if class.access_flags & ACC_ENUM != 0 {
+            // Enum.valueOf 和 switch 通过反射调用
+            for method in class.methods.iter().filter(|m| m.name == "values" || m.name == "valueOf") {
+                self.mark_method(&class.name, &method.name, &method.descriptor);
+            }
+        }
+        if self.supertypes_of(&class.name).contains("java/io/Serializable") {
+            for field in class.fields.iter().filter(|f| SERIALIZATION_MEMBERS.contains(&f.name.as_str())) {
+                self.live_fields.insert((class.name.clone(), field.name.clone(), field.descriptor.clone()));
+            }
+            for method in class.methods.iter().filter(|m| SERIALIZATION_MEMBERS.contains(&m.name.as_str())) {
+                self.mark_method(&class.name, &method.name, &method.descriptor);
+            }
+        }
+    }
+
+    fn mark_method(&mut self, owner: &str, name: &str, descriptor: &str) {
+        let exists = self.program.classes.get(owner).is_some_and(|c| c.method(name, descriptor).is_some());
+        if !exists {
+            return;
+        }
+        let key = (owner.to_string(), name.to_string(), descriptor.to_string());
+        if self.live_methods.insert(key.clone()) {
+            self.mark_class(owner);
+            self.worklist.push(key);
+        }
+    }
+
+    fn instantiate(&mut self, class_name: &str) {
+        let program = self.program;
+        let Some(class) = program.classes.get(class_name) else {
+            return;
+        };
+        self.mark_class(&class.name);
+        if !self.instantiated.insert(class.name.clone()) {
+            return;
+        }
+        let supertypes = self.supertypes_of(&class.name);
+        let pending: Vec<(String, String)> = self
+            .virtual_calls
+            .iter()
+            .filter(|(_, receivers)| receivers.iter().any(|r| r == &class.name || supertypes.contains(r)))
+            .map(|(signature, _)| signature.clone())
+            .collect();
+        for (name, descriptor) in pending {
+            self.dispatch(&class.name, &name, &descriptor);
+        }
+        for (name, descriptor) in OBJECT_METHODS {
+            self.dispatch(&class.name, name, descriptor);
+        }
+        for (name, descriptor) in self.library_callbacks(class) {
+            self.dispatch(&class.name, &name, &descriptor);
+        }
+    }
+
+    /// 父类型不在项目中时，库代码可能通过这些父类型回调实例的方法
+    fn library_callbacks(&mut self, class: &ProgramClass) -> BTreeSet<(String, String)> {
+        let program = self.program;
+        let supertypes = self.supertypes_of(&class.name);
+        let mut callbacks = BTreeSet::new();
+        let declaring = std::iter::once(class).chain(supertypes.iter().filter_map(|name| program.classes.get(name)));
+        for declaring_class in declaring {
+            // 只有父类型中含有外部类型的类，其方法才可能覆盖库中的方法
+            if !self.extends_library_type(declaring_class) {
+                continue;
+            }
+            for method in &declaring_class.methods {
+                // 包私有方法不能被其他包中的库代码覆盖或调用
+                if method.access_flags & (ACC_PUBLIC | ACC_PROTECTED) == 0
+                    || method.access_flags & ACC_STATIC != 0
+                    || method.name.starts_with('<')
+                {
+                    continue;
+                }
+                callbacks.insert((method.name.clone(), method.descriptor.clone()));
+            }
+        }
+        callbacks
+    }
+
+    fn extends_library_type(&mut self, class: &ProgramClass) -> bool {
+        let program = self.program;
+        self.supertypes_of(&class.name)
+            .iter()
+            .any(|name| name != "java/lang/Object" && !program.classes.contains_key(name))
+    }
+
+    fn call(&mut self, kind: InvokeKind, owner: &str, name: &str, descriptor: &str) {
+        let program = self.program;
+        let Some(declaring) = program.resolve_method(owner, name, descriptor) else {
+            return;
+        };
+        self.mark_method(&declaring.name, name, descriptor);
+        if matches!(kind, InvokeKind::Static | InvokeKind::Special) {
+            return;
+        }
+        let receivers = self.virtual_calls.entry((name.to_string(), descriptor.to_string())).or_default();
+        if !receivers.insert(owner.to_string()) {
+            return;
+        }
+        let mut targets: Vec<String> = program
+            .hierarchy
+            .subtypes(owner, true)
+            .into_iter()
+            .map(|summary| summary.name)
+            .filter(|name| self.instantiated.contains(name))
+            .collect();
+        if self.instantiated.contains(owner) {
+            targets.push(owner.to_string());
+        }
+        for target in targets {
+            self.dispatch(&target, name, descriptor);
+        }
+    }
+
+    fn dispatch(&mut self, receiver: &str, name: &str, descriptor: &str) {
+        if let Some(class) = self.program.resolve_method(receiver, name, descriptor) {
+            let class_name = class.name.clone();
+            self.mark_method(&class_name, name, descriptor);
+        }
+    }
+
+    /// 字符串常量可能是反射的目标：与项目类名相同时视为 Class.forName 加载，
+    /// 与所在类的方法或字段同名时视为按名称查找（MethodHandles、字段更新器等）
+    fn string_hint(&mut self, value: &str, owner: &str, method_name: &str) {
+        if value.is_empty() || value.contains(' ') {
+            return;
+        }
+        let program = self.program;
+        if let Some(class) = program.classes.get(&normalize_class_name(value)) {
+            if !self.instantiated.contains(&class.name) {
+                self.mark_class(&class.name);
+                self.add_entry_point(EntryPoint {
+                    class_name: class.name.clone(),
+                    method_name: Some("<init>".to_string()),
+                    descriptor: None,
+                    reason: format!("Class name string in {}.{}", owner, method_name),
+                });
+            }
+            return;
+        }
+        let Some(class) = program.classes.get(owner) else {
+            return;
+        };
+        for field in class.fields.iter().filter(|f| f.name == value) {
+            self.live_fields.insert((class.name.clone(), field.name.clone(), field.descriptor.clone()));
+        }
+        let pending = class.methods.iter().any(|m| {
+            m.name == value && !self.live_methods.contains(&(class.name.clone(), m.name.clone(), m.descriptor.clone()))
+        });
+        if pending {
+            self.add_entry_point(EntryPoint {
+                class_name: class.name.clone(),
+                method_name: Some(value.to_string()),
+                descriptor: None,
+                reason: format!("Member name string in {}.{}", owner, method_name),
+            });
+        }
+    }
+
+    fn supertypes_of(&mut self, class_name: &str) -> HashSet<String> {
+        if let Some(supertypes) = self.supertypes.get(class_name) {
+            return supertypes.clone();
+        }
+        let supertypes: HashSet<String> = self
+            .program
+            .hierarchy
+            .supertypes(class_name)
+            .into_iter()
+            .map(|summary| summary.name)
+            .collect();
+        self.supertypes.insert(class_name.to_string(), supertypes.clone());
+        supertypes
+    }
+}
+
+/// 整个项目（包括不可达代码）中被引用过的类、方法和字段，用于区分死代码的原因
+#[derive(Default)]
+struct References {
+    classes: HashSet<String>,
+    methods: HashSet<MemberKey>,
+    fields: HashSet<MemberKey>,
+}
+
+impl References {
+    fn collect(program: &Program) -> Self {
+        let mut references = References::default();
+        let mut virtual_targets: HashSet<MemberKey> = HashSet::new();
+        for class in program.classes.values() {
+            for supertype in class.super_name.iter().chain(class.interfaces.iter()) {
+                references.classes.insert(supertype.clone());
+            }
+            for method in &class.methods {
+                for reference in &method.references {
+                    let mut refer_class = |name: &str| {
+                        if name != class.name {
+                            references.classes.insert(name.to_string());
+                        }
+                    };
+                    match reference {
+                        CodeReference::Invoke { kind, owner, name, descriptor, .. } => {
+                            refer_class(owner);
+                            if let Some(declaring) = program.resolve_method(owner, name, descriptor) {
+                                let key = (declaring.name.clone(), name.clone(), descriptor.clone());
+                                if *kind == InvokeKind::Virtual || *kind == InvokeKind::Interface {
+                                    virtual_targets.insert(key.clone());
+                                }
+                                if key != (class.name.clone(), method.name.clone(), method.descriptor.clone()) {
+                                    references.methods.insert(key);
+                                }
+                            }
+                        }
+                        CodeReference::MethodHandle { owner, name, descriptor } => {
+                            refer_class(owner);
+                            if let Some(declaring) = program.resolve_method(owner, name, descriptor) {
+                                references.methods.insert((declaring.name.clone(), name.clone(), descriptor.clone()));
+                            }
+                        }
+                        CodeReference::Field { owner, name, descriptor, .. } => {
+                            refer_class(owner);
+                            if let Some(declaring) = program.resolve_field(owner, name, descriptor) {
+                                references.fields.insert((declaring.name.clone(), name.clone(), descriptor.clone()));
+                            }
+                        }
+                        CodeReference::Instantiate(name) | CodeReference::Type(name) => refer_class(name),
+                        CodeReference::String(value) => refer_class(&normalize_class_name(value)),
+                    }
+                }
+            }
+        }
+        // 被虚调用的方法的重写方法也算被引用
+        for (owner, name, descriptor) in virtual_targets {
+            for overriding in program.hierarchy.overriding_methods(&owner, &name, &descriptor) {
+                references.methods.insert((overriding.class_name, name.clone(), descriptor.clone()));
+            }
+        }
+        references
+    }
+}
+
+/// `Main-Class` 等清单属性和 `META-INF/services` 中注册的实现类
+fn classpath_entry_points(classpath: &Classpath) -> Vec<EntryPoint> {
+    let mut entry_points = Vec::new();
+    for entry in &classpath.entries {
+        let archive = entry.path();
+        if let Ok(bytes) = entry.read_file("META-INF/MANIFEST.MF") {
+            let attributes = parse_manifest(&String::from_utf8_lossy(&bytes));
+            let roots = [
+                ("Main-Class", "main", Some("([Ljava/lang/String;)V")),
+                ("Premain-Class", "premain", None),
+                ("Agent-Class", "agentmain", None),
+                ("Launcher-Agent-Class", "agentmain", None),
+            ];
+            for (attribute, method_name, descriptor) in roots {
+                if let Some(class_name) = attributes.get(attribute) {
+                    entry_points.push(EntryPoint {
+                        class_name: normalize_class_name(class_name),
+                        method_name: Some(method_name.to_string()),
+                        descriptor: descriptor.map(str::to_string),
+                        reason: format!("{} in {}!/META-INF/MANIFEST.MF", attribute, archive),
+                    });
+                }
+            }
+        }
+
+        let Ok(files) = entry.list_entries() else { continue };
+        for file in files.iter().filter(|f| !f.is_directory && f.name.starts_with("META-INF/services/")) {
+            let Ok(bytes) = entry.read_file(&file.name) else { continue };
+            for line in String::from_utf8_lossy(&bytes).lines() {
+                let class_name = line.split('#').next().unwrap_or("").trim();
+                if class_name.is_empty() {
+                    continue;
+                }
+                entry_points.push(EntryPoint {
+                    class_name: normalize_class_name(class_name),
+                    method_name: Some("<init>".to_string()),
+                    descriptor: Some("()V".to_string()),
+                    reason: format!("Service provider in {}!/{}", archive, file.name),
+                });
+            }
+        }
+    }
+    entry_points
+}
+
+/// 四大组件由系统实例化，生命周期方法和布局中声明的回调都可能被调用
+fn manifest_entry_points(manifest: &AndroidManifest) -> Vec<EntryPoint> {
+    manifest
+        .component_classes()
+        .into_iter()
+        .map(|(kind, class_name)| EntryPoint {
+            class_name: normalize_class_name(&class_name),
+            method_name: None,
+            descriptor: None,
+            reason: format!("{} in AndroidManifest.xml", kind),
+        })
+        .collect()
+}
+
+/// 布局 XML 中的自定义 View 和 Fragment 由 LayoutInflater 反射实例化
+fn layout_entry_points(apk_path: &str) -> Result<Vec<EntryPoint>, String> {
+    let reader = JarReader::new(apk_path);
+    let layouts: Vec<String> = reader
+        .list_entries()?
+        .into_iter()
+        .map(|entry| entry.name)
+        .filter(|name| name.starts_with("res/layout") && name.ends_with(".xml"))
+        .collect();
+    let mut entry_points = Vec::new();
+    for (name, data) in reader.read_files(&layouts)? {
+        // 未编译或损坏的布局不影响其它入口点
+        let Ok(elements) = AXMLAnalyzer::new(std::io::Cursor::new(data)).elements() else {
+            continue;
+        };
+        entry_points.extend(layout_classes(&elements).into_iter().map(|(kind, class_name)| EntryPoint {
+            class_name: normalize_class_name(&class_name),
+            method_name: None,
+            descriptor: None,
+            reason: format!("{} in {}", kind, name),
+        }));
+    }
+    Ok(entry_points)
+}
+
+/// 带包名的元素名是自定义 View，`<view class>` 同理；Fragment 写在 `android:name` 或 `class` 上
+fn layout_classes(elements: &[XmlElement]) -> Vec<(&'static str, String)> {
+    let mut classes = Vec::new();
+    for element in elements {
+        let attribute = |name: &str| element.attributes.get(name).filter(|value| value.contains('.')).cloned();
+        if element.name == "fragment" || element.name.ends_with("FragmentContainerView") {
+            classes.extend(attribute("name").or_else(|| attribute("class")).map(|class_name| ("Fragment", class_name)));
+        }
+        if element.name == "view" {
+            classes.extend(attribute("class").map(|class_name| ("View", class_name)));
+        } else if element.name.contains('.') && !element.name.starts_with("android.") {
+            classes.push(("View", element.name.clone()));
+        }
+    }
+    classes
+}
+
+fn extra_entry_point(value: &str) -> EntryPoint {
+    let (class_name, method_name) = match value.split_once('#') {
+        Some((class_name, method_name)) => (class_name, Some(method_name.trim().to_string())),
+        None => (value, None),
+    };
+    EntryPoint {
+        class_name: normalize_class_name(class_name),
+        method_name,
+        descriptor: None,
+        reason: "User supplied".to_string(),
+    }
+}
+
+fn analyze(program: &Program, roots: Vec<EntryPoint>, options: &DeadCodeOptions) -> DeadCodeReport {
+    let mut reachability = Reachability::new(program);
+    for root in roots.into_iter().chain(options.extra_entry_points.iter().map(|v| extra_entry_point(v))) {
+        reachability.add_entry_point(root);
+    }
+    if options.public_api_is_live {
+        reachability.add_public_api();
+    }
+    reachability.run();
+
+    let references = References::collect(program);
+    let reason = |referenced: bool| {
+        if referenced {
+            DeadReason::OnlyReferencedFromDeadCode
+        } else {
+            DeadReason::NeverReferenced
+        }
+    };
+    let instantiable: BTreeSet<&String> = program
+        .classes
+        .keys()
+        .filter(|name| {
+            reachability.instantiated.contains(*name)
+                || program
+                    .hierarchy
+                    .subtypes(name, true)
+                    .iter()
+                    .any(|s| reachability.instantiated.contains(&s.name))
+        })
+        .collect();
+
+    let mut dead_classes = Vec::new();
+    let mut dead_methods = Vec::new();
+    let mut dead_fields = Vec::new();
+    let (mut total_methods, mut total_fields) = (0, 0);
+    for class in program.classes.values() {
+        total_methods += class.methods.len();
+        total_fields += class.fields.len();
+        if !reachability.live_classes.contains(&class.name) {
+            dead_classes.push(DeadItem {
+                class_name: class.name.clone(),
+                name: None,
+                descriptor: None,
+                reason: reason(references.classes.contains(&class.name)),
+            });
+            continue;
+        }
+        for method in &class.methods {
+            let key = (class.name.clone(), method.name.clone(), method.descriptor.clone());
+            if reachability.live_methods.contains(&key) {
+                continue;
+            }
+            let referenced = references.methods.contains(&key);
+            let instance_method = method.access_flags & ACC_STATIC == 0 && !method.name.starts_with('<');
+            dead_methods.push(DeadItem {
+                class_name: class.name.clone(),
+                name: Some(method.name.clone()),
+                descriptor: Some(method.descriptor.clone()),
+                reason: if referenced && instance_method && !instantiable.contains(&class.name) {
+                    DeadReason::NeverInstantiated
+                } else {
+                    reason(referenced)
+                },
+            });
+        }
+        for field in &class.fields {
+            let key = (class.name.clone(), field.name.clone(), field.descriptor.clone());
+            // 常量字段的读取会被编译器内联，无法从指令判断是否使用
+            if field.constant || reachability.live_fields.contains(&key) {
+                continue;
+            }
+            dead_fields.push(DeadItem {
+                class_name: class.name.clone(),
+                name: Some(field.name.clone()),
+                descriptor: Some(field.descriptor.clone()),
+                reason: reason(references.fields.contains(&key)),
+            });
+        }
+    }
+
+    DeadCodeReport {
+        entry_points: reachability.entry_points,
+        total_classes: program.classes.len(),
+        live_classes: reachability.live_classes.len(),
+        total_methods,
+        live_methods: reachability.live_methods.len(),
+        total_fields,
+        live_fields: reachability.live_fields.len(),
+        dead_classes,
+        dead_methods,
+        dead_fields,
+    }
+}
+
+/// Finds classes, methods and fields that cannot be reached from the entry points of the project:
+/// manifest main and agent classes, service providers, Android components, views and fragments
+/// inflated from layouts, class names used as
+/// strings and the roots given in `options`. Virtual calls only reach classes that are instantiated.
+#[tauri::command(async)]
+pub fn deadcode_analyze(project_id: String, options: Option<DeadCodeOptions>) -> Result<DeadCodeReport, String> {
+    let options = options.unwrap_or_default();
+    let (classes, mut roots, apk_path) = Project::with_project_mut(&project_id, |project| {
+        let classes = ProjectClasses::of(&mut project.data)?;
+        match &project.data {
+            ProjectData::Android(android_data) => {
+                let roots = android_data.manifest.as_ref().map(manifest_entry_points).unwrap_or_default();
+                Ok((classes, roots, Some(android_data.apk_path.clone())))
+            }
+            _ => Ok((classes, Vec::new(), None)),
+        }
+    })?;
+    // 类、jar 清单和布局都在锁外读取
+    if let ProjectClasses::Classpath(classpath) = &classes {
+        roots.extend(classpath_entry_points(classpath));
+    }
+    if let Some(apk_path) = apk_path {
+        roots.extend(layout_entry_points(&apk_path)?);
+    }
+    let program = Program::from_project(&classes);
+    if !program.has_code() {
+        return Err("No method bodies were decoded, dead code analysis needs method instructions".to_string());
+    }
+    if roots.is_empty() && options.extra_entry_points.is_empty() && !options.public_api_is_live {
+        return Err("No entry points found, add entry points or treat the public API as used".to_string());
+    }
+    Ok(analyze(&program, roots, &options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+    use crate::model::ClassModel;
+
+    fn element(name: &str, attributes: &[(&str, &str)]) -> XmlElement {
+        XmlElement {
+            name: name.to_string(),
+            attributes: attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            depth: 1,
+        }
+    }
+
+    #[test]
+    fn layouts_root_custom_views_and_fragments() {
+        let elements = [
+            element("LinearLayout", &[]),
+            element("com.example.ChartView", &[]),
+            element("androidx.fragment.app.FragmentContainerView", &[("name", "com.example.ListFragment")]),
+            element("fragment", &[("class", "com.example.DetailFragment")]),
+            element("view", &[("class", "com.example.Badge")]),
+            element("android.widget.TextView", &[]),
+        ];
+        assert_eq!(
+            layout_classes(&elements),
+            vec![
+                ("View", "com.example.ChartView".to_string()),
+                ("Fragment", "com.example.ListFragment".to_string()),
+                ("View", "androidx.fragment.app.FragmentContainerView".to_string()),
+                ("Fragment", "com.example.DetailFragment".to_string()),
+                ("View", "com.example.Badge".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn manifest_roots_include_application_backup_agent_and_instrumentation() {
+        let manifest = AndroidManifest {
+            package_name: "com.example".to_string(),
+            version_code: 1,
+            version_name: "1.0".to_string(),
+            application_name: Some(".App".to_string()),
+            backup_agent: Some("Backup".to_string()),
+            activities: Vec::new(),
+            services: Vec::new(),
+            receivers: Vec::new(),
+            providers: Vec::new(),
+            instrumentations: vec!["com.example.test.Runner".to_string()],
+            permissions: Vec::new(),
+            uses_permissions: Vec::new(),
+        };
+        let roots: Vec<(String, String)> =
+            manifest_entry_points(&manifest).into_iter().map(|entry| (entry.class_name, entry.reason)).collect();
+        assert_eq!(
+            roots,
+            vec![
+                ("com/example/App".to_string(), "Application in AndroidManifest.xml".to_string()),
+                ("com/example/Backup".to_string(), "BackupAgent in AndroidManifest.xml".to_string()),
+                ("com/example/test/Runner".to_string(), "Instrumentation in AndroidManifest.xml".to_string()),
+            ]
+        );
+    }
+
+    const MAIN: &str = r#"
+.class public Lapp/Main;
+.super Ljava/lang/Object;
+
+.method public static main([Ljava/lang/String;)V
+    .registers 2
+    new-instance v0, Lapp/Task;
+    invoke-direct {v0}, Lapp/Task;-><init>()V
+    return-void
+.end method
+"#;
+
+    const TASK: &str = r#"
+.class public Lapp/Task;
+.super Lapp/Base;
+.implements Ljava/lang/Runnable;
+
+.method public constructor <init>()V
+    .registers 1
+    invoke-direct {p0}, Lapp/Base;-><init>()V
+    return-void
+.end method
+
+.method public run()V
+    .registers 1
+    return-void
+.end method
+"#;
+
+    fn dead_methods(base: &str) -> Vec<(String, String)> {
+        let dex_file = dex_file(&[MAIN, TASK, base]);
+        let program = Program::from_classes(ClassModel::dex_classes(std::slice::from_ref(&dex_file)));
+        let roots = vec![EntryPoint {
+            class_name: "app/Main".to_string(),
+            method_name: Some("main".to_string()),
+            descriptor: None,
+            reason: "test".to_string(),
+        }];
+        let report = analyze(&program, roots, &DeadCodeOptions::default());
+        report.dead_methods.into_iter().map(|item| (item.class_name, item.name.unwrap())).collect()
+    }
+
+    #[test]
+    fn in_project_superclass_methods_are_not_library_callbacks() {
+        let base = r#"
+.class public Lapp/Base;
+.super Ljava/lang/Object;
+
+.method public constructor <init>()V
+    .registers 1
+    invoke-direct {p0}, Ljava/lang/Object;-><init>()V
+    return-void
+.end method
+
+.method unused()V
+    .registers 1
+    return-void
+.end method
+
+.method public helper()V
+    .registers 1
+    return-void
+.end method
+"#;
+        assert_eq!(
+            dead_methods(base),
+            vec![("app/Base".to_string(), "helper".to_string()), ("app/Base".to_string(), "unused".to_string())]
+        );
+    }
+
+    #[test]
+    fn package_private_methods_are_not_library_callbacks() {
+        let base = r#"
+.class public Lapp/Base;
+.super Landroid/app/Activity;
+
+.method public constructor <init>()V
+    .registers 1
+    invoke-direct {p0}, Landroid/app/Activity;-><init>()V
+    return-void
+.end method
+
+.method unused()V
+    .registers 1
+    return-void
+.end method
+
+.method protected onCreate(Landroid/os/Bundle;)V
+    .registers 2
+    return-void
+.end method
+"#;
+        assert_eq!(dead_methods(base), vec![("app/Base".to_string(), "unused".to_string())]);
+    }
+}
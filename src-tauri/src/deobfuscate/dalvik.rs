@@ -0,0 +1,644 @@
+// Dalvik 字节码解释器：与 JVM 解释器支持的范围相同，寄存器不带类型，按指令或声明的类型解释
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::android::{ClassDef, DexFile, EncodedValue, Method, TypeDescriptor};
+use crate::android_analyzer::dalvik_opcode::{payload_at, DalvikInstruction, DalvikOpcode, DalvikOpcodeAnalyzer, DalvikReference};
+use crate::hierarchy::{descriptor_to_internal_name, proto_to_descriptor};
+
+use super::machine::{Heap, Intrinsic, Object, Statics, Value, DEPTH_LIMIT, STEP_LIMIT};
+
+const ACC_STATIC: u32 = 0x0008;
+
+/// 按内部类名索引的 DEX 类
+pub(crate) type DexClasses<'a> = HashMap<String, (&'a DexFile, &'a ClassDef)>;
+
+pub(crate) fn index_dex_classes(dex_files: &[DexFile]) -> DexClasses<'_> {
+    let mut classes = HashMap::new();
+    for dex_file in dex_files {
+        for class_def in &dex_file.classes {
+            // 多个 DEX 中重复定义时，与运行时一样以先加载的为准
+            classes
+                .entry(descriptor_to_internal_name(&class_def.class_type.descriptor))
+                .or_insert((dex_file, class_def));
+        }
+    }
+    classes
+}
+
+pub(crate) struct DalvikEmulator<'a> {
+    classes: &'a DexClasses<'a>,
+    pub heap: Heap,
+    statics: Statics,
+    /// 解码过的方法体，解密方法通常被反复调用
+    decoded: HashMap<(String, String, String), Rc<Vec<DalvikInstruction>>>,
+    steps: usize,
+    depth: usize,
+}
+
+impl<'a> DalvikEmulator<'a> {
+    pub fn new(classes: &'a DexClasses<'a>) -> Self {
+        DalvikEmulator {
+            classes,
+            heap: Heap::default(),
+            statics: Statics::default(),
+            decoded: HashMap::new(),
+            steps: 0,
+            depth: 0,
+        }
+    }
+
+    /// 求值一次静态方法调用，每次求值都有独立的指令数上限
+    pub fn call(&mut self, owner: &str, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Value>, String> {
+        self.steps = 0;
+        self.depth = 0;
+        self.invoke(owner, name, descriptor, args, true)
+    }
+
+    /// 类初始化之后静态字段的值
+    pub fn static_value(&mut self, owner: &str, name: &str) -> Result<Value, String> {
+        self.steps = 0;
+        self.depth = 0;
+        self.get_static(owner, name)
+    }
+
+    /// 执行 `<clinit>`。失败时只保留初始化过程中确实写入过的字段
+    fn initialize(&mut self, class_name: &str) -> Result<(), String> {
+        match self.statics.classes.get(class_name) {
+            Some(Some(true)) | Some(None) => return Ok(()),
+            Some(Some(false)) => return Err(format!("Initialization of {} failed", class_name)),
+            None => {}
+        }
+        let classes = self.classes;
+        let Some(&(dex_file, class_def)) = classes.get(class_name) else {
+            return Err(format!("Class {} is not part of the project", class_name));
+        };
+        self.statics.classes.insert(class_name.to_string(), None);
+        if let Some(super_type) = &class_def.super_type {
+            let super_name = descriptor_to_internal_name(&super_type.descriptor);
+            if classes.contains_key(&super_name) {
+                self.initialize(&super_name)?;
+            }
+        }
+
+        let mut defaults = Vec::new();
+        for field in &class_def.static_fields {
+            let key = (class_name.to_string(), field.name.clone());
+            match &field.value {
+                Some(value) => {
+                    // 注解、方法句柄等初始值解释器无法表示，读取时报错
+                    if let Ok(value) = self.encoded_value(value) {
+                        self.statics.values.insert(key, value);
+                    }
+                }
+                None => {
+                    self.statics.values.insert(key.clone(), Value::default_for(&field.field_type.descriptor));
+                    defaults.push(key);
+                }
+            }
+        }
+
+        let initializer = class_def.direct_methods.iter().find(|m| m.name == "<clinit>");
+        let result = match initializer {
+            Some(method) => {
+                let before: Vec<Value> = defaults.iter().map(|key| self.statics.values[key]).collect();
+                let result = self.execute(dex_file, class_name, method, &[]).map(|_| ());
+                if result.is_err() {
+                    // 仍是默认值的字段无法确定是否本应被赋值，全部丢弃
+                    for (key, value) in defaults.iter().zip(before) {
+                        if self.statics.values.get(key) == Some(&value) {
+                            self.statics.values.remove(key);
+                        }
+                    }
+                }
+                result
+            }
+            None => Ok(()),
+        };
+        self.statics.classes.insert(class_name.to_string(), Some(result.is_ok()));
+        result
+    }
+
+    fn encoded_value(&mut self, value: &EncodedValue) -> Result<Value, String> {
+        Ok(match value {
+            EncodedValue::Byte(v) => Value::Int(*v as i32),
+            EncodedValue::Short(v) => Value::Int(*v as i32),
+            EncodedValue::Char(v) => Value::Int(*v as i32),
+            EncodedValue::Int(v) => Value::Int(*v),
+            EncodedValue::Boolean(v) => Value::Int(*v as i32),
+            EncodedValue::Long(v) => Value::Long(*v),
+            EncodedValue::Float(v) => Value::Float(*v),
+            EncodedValue::Double(v) => Value::Double(*v),
+            EncodedValue::String(text) => self.heap.string(text)?,
+            EncodedValue::Null => Value::Null,
+            other => return Err(format!("Unsupported static value {:?}", other)),
+        })
+    }
+
+    fn get_static(&mut self, owner: &str, name: &str) -> Result<Value, String> {
+        if let Some(value) = self.heap.static_field(owner, name)? {
+            return Ok(value);
+        }
+        // 初始化失败时，已经写入的字段仍然可以读取
+        let initialized = self.initialize(owner);
+        match self.statics.values.get(&(owner.to_string(), name.to_string())) {
+            Some(value) => Ok(*value),
+            None => Err(initialized.err().unwrap_or_else(|| format!("Unknown static field {}.{}", owner, name))),
+        }
+    }
+
+    fn put_static(&mut self, owner: &str, name: &str, value: Value) -> Result<(), String> {
+        if !self.classes.contains_key(owner) {
+            return Err(format!("Cannot write static field {}.{}", owner, name));
+        }
+        let _ = self.initialize(owner);
+        self.statics.values.insert((owner.to_string(), name.to_string()), value);
+        Ok(())
+    }
+
+    fn invoke(&mut self, owner: &str, name: &str, descriptor: &str, args: &[Value], is_static: bool) -> Result<Option<Value>, String> {
+        if let Intrinsic::Return(value) = self.heap.invoke(owner, name, descriptor, args)? {
+            return Ok(value);
+        }
+        if !is_static {
+            return Err(format!("Unsupported call {}.{}{}", owner, name, descriptor));
+        }
+        // 静态方法可能声明在父类中
+        let classes = self.classes;
+        let mut current = owner.to_string();
+        loop {
+            let &(dex_file, class_def) = classes
+                .get(&current)
+                .ok_or_else(|| format!("Unsupported call {}.{}{}", owner, name, descriptor))?;
+            if let Some(method) = class_def.direct_methods.iter().find(|m| {
+                m.name == name && m.access_flags & ACC_STATIC != 0 && proto_to_descriptor(&m.proto) == descriptor
+            }) {
+                self.initialize(&current)?;
+                return self.execute(dex_file, &current, method, args);
+            }
+            current = class_def
+                .super_type
+                .as_ref()
+                .map(|super_type| descriptor_to_internal_name(&super_type.descriptor))
+                .ok_or_else(|| format!("Method not found: {}.{}{}", owner, name, descriptor))?;
+        }
+    }
+
+    fn decode(&mut self, dex_file: &DexFile, class_name: &str, method: &Method) -> Result<Rc<Vec<DalvikInstruction>>, String> {
+        let descriptor = proto_to_descriptor(&method.proto);
+        let key = (class_name.to_string(), method.name.clone(), descriptor);
+        if let Some(instructions) = self.decoded.get(&key) {
+            return Ok(instructions.clone());
+        }
+        let code = method
+            .code
+            .as_ref()
+            .ok_or_else(|| format!("Method {}{} has no code", method.name, key.2))?;
+        let instructions = DalvikOpcodeAnalyzer::new()
+            .analyze_method(code, dex_file)
+            .map_err(|e| format!("Failed to decode {}{}: {}", method.name, key.2, e))?;
+        let instructions = Rc::new(instructions);
+        self.decoded.insert(key, instructions.clone());
+        Ok(instructions)
+    }
+
+    fn execute(&mut self, dex_file: &DexFile, class_name: &str, method: &Method, args: &[Value]) -> Result<Option<Value>, String> {
+        let instructions = self.decode(dex_file, class_name, method)?;
+        let code = method.code.as_ref().ok_or("Method has no code")?;
+        if self.depth >= DEPTH_LIMIT {
+            return Err("Call depth limit exceeded".to_string());
+        }
+        self.depth += 1;
+        let mut frame = Frame {
+            registers: vec![Value::Int(0); code.registers_size as usize],
+            result: None,
+        };
+        // 参数放在最后 ins_size 个寄存器中，宽参数占两个
+        let mut register = code.registers_size.saturating_sub(code.ins_size) as u32;
+        for (arg, parameter) in args.iter().zip(&method.proto.parameters) {
+            frame.set(register, *arg)?;
+            register += if matches!(parameter.descriptor.as_str(), "J" | "D") { 2 } else { 1 };
+        }
+        let result = self.run(&instructions, &mut frame);
+        self.depth -= 1;
+        result
+    }
+
+    /// 按被调用方法的声明收集参数：宽参数只取低位寄存器，接收者在最前面
+    fn arguments(frame: &Frame, insn: &DalvikInstruction, parameters: &[TypeDescriptor], is_static: bool) -> Result<Vec<Value>, String> {
+        let mut registers = insn.registers.iter();
+        let mut args = Vec::new();
+        if !is_static {
+            let receiver = registers.next().ok_or("Missing receiver")?;
+            args.push(typed(frame.get(*receiver)?, "Ljava/lang/Object;"));
+        }
+        for parameter in parameters {
+            let register = registers.next().ok_or("Missing argument register")?;
+            args.push(typed(frame.get(*register)?, &parameter.descriptor));
+            if matches!(parameter.descriptor.as_str(), "J" | "D") {
+                registers.next();
+            }
+        }
+        Ok(args)
+    }
+
+    /// 按数组的元素类型解释写入的值
+    fn array_store(&mut self, array: Value, index: i32, value: Value) -> Result<(), String> {
+        let element = match self.heap.get(array)? {
+            Object::Array { element, .. } => element.clone(),
+            other => return Err(format!("Expected array, found {:?}", other)),
+        };
+        self.heap.array_store(array, index, typed(value, &element))
+    }
+
+    fn run(&mut self, instructions: &[DalvikInstruction], frame: &mut Frame) -> Result<Option<Value>, String> {
+        use DalvikOpcode::*;
+        let index_of: HashMap<u32, usize> = instructions.iter().enumerate().map(|(i, insn)| (insn.address, i)).collect();
+        let jump = |insn: &DalvikInstruction, target: Option<u32>| {
+            target
+                .and_then(|address| index_of.get(&address).copied())
+                .ok_or_else(|| format!("Invalid branch target at {}", insn.address))
+        };
+
+        let mut pc = 0;
+        loop {
+            self.steps += 1;
+            if self.steps > STEP_LIMIT {
+                return Err("Step limit exceeded".to_string());
+            }
+            let insn = instructions.get(pc).ok_or("Execution fell off the end of the method")?;
+            if insn.payload.is_some() {
+                return Err(format!("Execution reached the payload at {}", insn.address));
+            }
+            pc += 1;
+            let r = |index: usize| insn.registers.get(index).copied().ok_or_else(|| format!("Missing register operand in {}", insn));
+            let literal = insn.literal.unwrap_or(0);
+            match insn.opcode {
+                Nop | MonitorEnter | MonitorExit | CheckCast => {}
+                Move | MoveFrom16 | Move16 | MoveWide | MoveWideFrom16 | MoveWide16 | MoveObject | MoveObjectFrom16
+                | MoveObject16 => {
+                    let value = frame.get(r(1)?)?;
+                    frame.set(r(0)?, value)?;
+                }
+                MoveResult | MoveResultWide | MoveResultObject => {
+                    let value = frame.result.take().ok_or_else(|| format!("No result to move at {}", insn.address))?;
+                    frame.set(r(0)?, value)?;
+                }
+                ReturnVoid => return Ok(None),
+                Return | ReturnWide | ReturnObject => return Ok(Some(frame.get(r(0)?)?)),
+                Const4 | Const16 | Const | ConstHigh16 => frame.set(r(0)?, Value::Int(literal as i32))?,
+                ConstWide16 | ConstWide32 | ConstWide | ConstWideHigh16 => frame.set(r(0)?, Value::Long(literal))?,
+                ConstString | ConstStringJumbo => {
+                    let Some(DalvikReference::String(text)) = &insn.reference else {
+                        return Err(format!("Invalid string reference at {}", insn.address));
+                    };
+                    let value = self.heap.string(text)?;
+                    frame.set(r(0)?, value)?;
+                }
+
+                ArrayLength => {
+                    let array = frame.get(r(1)?)?;
+                    let length = self.heap.array(array)?.len() as i32;
+                    frame.set(r(0)?, Value::Int(length))?;
+                }
+                NewInstance => {
+                    let object = self.heap.allocate(Object::Uninitialized)?;
+                    frame.set(r(0)?, object)?;
+                }
+                NewArray => {
+                    let element = array_element(insn)?;
+                    let length = int(frame.get(r(1)?)?)?;
+                    let array = self.heap.new_array(&element, length)?;
+                    frame.set(r(0)?, array)?;
+                }
+                FilledNewArray | FilledNewArrayRange => {
+                    let element = array_element(insn)?;
+                    let array = self.heap.new_array(&element, insn.registers.len() as i32)?;
+                    for (index, register) in insn.registers.iter().enumerate() {
+                        let value = frame.get(*register)?;
+                        self.array_store(array, index as i32, value)?;
+                    }
+                    frame.result = Some(array);
+                }
+                FillArrayData => {
+                    let array = frame.get(r(0)?)?;
+                    let payload = insn
+                        .target
+                        .and_then(|target| payload_at(instructions, target))
+                        .ok_or_else(|| format!("Missing array data at {}", insn.address))?;
+                    for (index, element) in payload.elements().into_iter().enumerate() {
+                        let value = if self.heap.array(array)?.first().is_some_and(Value::is_wide) {
+                            Value::Long(element)
+                        } else {
+                            Value::Int(element as i32)
+                        };
+                        self.array_store(array, index as i32, value)?;
+                    }
+                }
+                Throw => return Err(format!("Exception thrown at {}", insn.address)),
+
+                Goto | Goto16 | Goto32 => pc = jump(insn, insn.target)?,
+                PackedSwitch | SparseSwitch => {
+                    let key = int(frame.get(r(0)?)?)?;
+                    if let Some((_, target)) = insn.switch_cases(instructions).into_iter().find(|(k, _)| *k == key) {
+                        pc = jump(insn, Some(target))?;
+                    }
+                }
+                CmplFloat | CmpgFloat | CmplDouble | CmpgDouble | CmpLong => {
+                    let (left, right) = (frame.get(r(1)?)?, frame.get(r(2)?)?);
+                    let ordering = match insn.opcode {
+                        CmpLong => Some(long(left)?.cmp(&long(right)?)),
+                        CmplFloat | CmpgFloat => float(left)?.partial_cmp(&float(right)?),
+                        _ => double(left)?.partial_cmp(&double(right)?),
+                    };
+                    let value = match ordering {
+                        Some(ordering) => ordering as i32,
+                        // NaN：cmpg 得 1，cmpl 得 -1
+                        None if matches!(insn.opcode, CmpgFloat | CmpgDouble) => 1,
+                        None => -1,
+                    };
+                    frame.set(r(0)?, Value::Int(value))?;
+                }
+                IfEq | IfNe => {
+                    let equal = same(frame.get(r(0)?)?, frame.get(r(1)?)?)?;
+                    if equal == (insn.opcode == IfEq) {
+                        pc = jump(insn, insn.target)?;
+                    }
+                }
+                IfLt | IfGe | IfGt | IfLe => {
+                    let (left, right) = (int(frame.get(r(0)?)?)?, int(frame.get(r(1)?)?)?);
+                    if condition(insn.opcode as u8 - IfEq as u8, left, right) {
+                        pc = jump(insn, insn.target)?;
+                    }
+                }
+                IfEqz | IfNez | IfLtz | IfGez | IfGtz | IfLez => {
+                    let value = match frame.get(r(0)?)? {
+                        Value::Null => 0,
+                        Value::Ref(_) => 1,
+                        value => int(value)?,
+                    };
+                    if condition(insn.opcode as u8 - IfEqz as u8, value, 0) {
+                        pc = jump(insn, insn.target)?;
+                    }
+                }
+
+                Aget | AgetWide | AgetObject | AgetBoolean | AgetByte | AgetChar | AgetShort => {
+                    let array = frame.get(r(1)?)?;
+                    let index = int(frame.get(r(2)?)?)?;
+                    let value = self.heap.array_load(array, index)?;
+                    frame.set(r(0)?, value)?;
+                }
+                Aput | AputWide | AputObject | AputBoolean | AputByte | AputChar | AputShort => {
+                    let value = frame.get(r(0)?)?;
+                    let array = frame.get(r(1)?)?;
+                    let index = int(frame.get(r(2)?)?)?;
+                    self.array_store(array, index, value)?;
+                }
+                Sget | SgetWide | SgetObject | SgetBoolean | SgetByte | SgetChar | SgetShort => {
+                    let Some(DalvikReference::Field(field)) = &insn.reference else {
+                        return Err(format!("Invalid field reference at {}", insn.address));
+                    };
+                    let value = self.get_static(&descriptor_to_internal_name(&field.class_type.descriptor), &field.name)?;
+                    frame.set(r(0)?, value)?;
+                }
+                Sput | SputWide | SputObject | SputBoolean | SputByte | SputChar | SputShort => {
+                    let Some(DalvikReference::Field(field)) = &insn.reference else {
+                        return Err(format!("Invalid field reference at {}", insn.address));
+                    };
+                    let value = typed(frame.get(r(0)?)?, &field.field_type.descriptor);
+                    self.put_static(&descriptor_to_internal_name(&field.class_type.descriptor), &field.name, value)?;
+                }
+                InvokeVirtual | InvokeSuper | InvokeDirect | InvokeStatic | InvokeInterface | InvokeVirtualRange
+                | InvokeSuperRange | InvokeDirectRange | InvokeStaticRange | InvokeInterfaceRange => {
+                    let Some(DalvikReference::Method(callee)) = &insn.reference else {
+                        return Err(format!("Invalid method reference at {}", insn.address));
+                    };
+                    let is_static = matches!(insn.opcode, InvokeStatic | InvokeStaticRange);
+                    let descriptor = proto_to_descriptor(&callee.proto);
+                    let args = Self::arguments(frame, insn, &callee.proto.parameters, is_static)?;
+                    // 构造器原地填充 new-instance 分配的对象
+                    let owner = descriptor_to_internal_name(&callee.class_type.descriptor);
+                    frame.result = self.invoke(&owner, &callee.name, &descriptor, &args, is_static)?;
+                }
+
+                NegInt | NotInt | NegLong | NotLong | NegFloat | NegDouble | IntToLong | IntToFloat | IntToDouble
+                | LongToInt | LongToFloat | LongToDouble | FloatToInt | FloatToLong | FloatToDouble | DoubleToInt
+                | DoubleToLong | DoubleToFloat | IntToByte | IntToChar | IntToShort => {
+                    let value = convert(insn.opcode, frame.get(r(1)?)?)?;
+                    frame.set(r(0)?, value)?;
+                }
+                opcode => {
+                    let code = opcode as u8;
+                    if let Some((operation, kind)) = binary_kind(code) {
+                        let value = arithmetic(operation, kind, frame.get(r(1)?)?, frame.get(r(2)?)?)?;
+                        frame.set(r(0)?, value)?;
+                    } else if let Some((operation, kind)) = (0xb0..=0xcf).contains(&code).then(|| binary_kind(code - 0x20)).flatten() {
+                        // 2addr：vA 既是左操作数也是目标
+                        let value = arithmetic(operation, kind, frame.get(r(0)?)?, frame.get(r(1)?)?)?;
+                        frame.set(r(0)?, value)?;
+                    } else if (0xd0..=0xe2).contains(&code) {
+                        let operation = if code <= 0xd7 { code - 0xd0 } else { code - 0xd8 } as usize;
+                        let (source, constant) = (frame.get(r(1)?)?, Value::Int(literal as i32));
+                        // rsub-int：常量减去寄存器
+                        let value = match operation {
+                            1 => arithmetic(1, b'I', constant, source)?,
+                            _ => arithmetic(operation, b'I', source, constant)?,
+                        };
+                        frame.set(r(0)?, value)?;
+                    } else {
+                        return Err(format!("Unsupported instruction {} at {}", opcode.name(), insn.address));
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Frame {
+    registers: Vec<Value>,
+    /// invoke 和 filled-new-array 的结果，由随后的 move-result 读取
+    result: Option<Value>,
+}
+
+impl Frame {
+    fn get(&self, register: u32) -> Result<Value, String> {
+        self.registers.get(register as usize).copied().ok_or_else(|| format!("Invalid register v{}", register))
+    }
+
+    /// 宽值只记在低位寄存器上
+    fn set(&mut self, register: u32, value: Value) -> Result<(), String> {
+        let slot = self.registers.get_mut(register as usize).ok_or_else(|| format!("Invalid register v{}", register))?;
+        *slot = value;
+        Ok(())
+    }
+}
+
+/// 常量指令只给出位模式，按使用处的类型解释；`const/4 vX, 0` 也用作 null
+fn typed(value: Value, descriptor: &str) -> Value {
+    match (descriptor.as_bytes().first(), value) {
+        (Some(b'F'), Value::Int(bits)) => Value::Float(f32::from_bits(bits as u32)),
+        (Some(b'D'), Value::Long(bits)) => Value::Double(f64::from_bits(bits as u64)),
+        (Some(b'J'), Value::Double(v)) => Value::Long(v.to_bits() as i64),
+        (Some(b'L' | b'['), Value::Int(0)) => Value::Null,
+        (Some(b'I' | b'Z' | b'B' | b'S' | b'C'), Value::Float(v)) => Value::Int(v.to_bits() as i32),
+        (_, value) => value,
+    }
+}
+
+fn int(value: Value) -> Result<i32, String> {
+    typed(value, "I").int()
+}
+
+fn long(value: Value) -> Result<i64, String> {
+    typed(value, "J").long()
+}
+
+fn float(value: Value) -> Result<f32, String> {
+    typed(value, "F").float()
+}
+
+fn double(value: Value) -> Result<f64, String> {
+    typed(value, "D").double()
+}
+
+/// if-eq / if-ne 既比较整数也比较引用
+fn same(left: Value, right: Value) -> Result<bool, String> {
+    if matches!(left, Value::Null | Value::Ref(_)) || matches!(right, Value::Null | Value::Ref(_)) {
+        Ok(typed(left, "L") == typed(right, "L"))
+    } else {
+        Ok(int(left)? == int(right)?)
+    }
+}
+
+/// new-array / filled-new-array 的元素类型
+fn array_element(insn: &DalvikInstruction) -> Result<String, String> {
+    match &insn.reference {
+        Some(DalvikReference::Type(array_type)) => array_type
+            .descriptor
+            .strip_prefix('[')
+            .map(str::to_string)
+            .ok_or_else(|| format!("Not an array type: {}", array_type.descriptor)),
+        _ => Err(format!("Invalid type reference at {}", insn.address)),
+    }
+}
+
+fn condition(kind: u8, left: i32, right: i32) -> bool {
+    match kind {
+        0 => left == right,
+        1 => left != right,
+        2 => left < right,
+        3 => left >= right,
+        4 => left > right,
+        _ => left <= right,
+    }
+}
+
+/// add-int 到 rem-double 按操作码顺序排列：运算序号和操作数类型
+fn binary_kind(code: u8) -> Option<(usize, u8)> {
+    match code {
+        0x90..=0x9a => Some(((code - 0x90) as usize, b'I')),
+        0x9b..=0xa5 => Some(((code - 0x9b) as usize, b'J')),
+        0xa6..=0xaa => Some(((code - 0xa6) as usize, b'F')),
+        0xab..=0xaf => Some(((code - 0xab) as usize, b'D')),
+        _ => None,
+    }
+}
+
+/// 运算依次为 add、sub、mul、div、rem、and、or、xor、shl、shr、ushr
+fn arithmetic(operation: usize, kind: u8, left: Value, right: Value) -> Result<Value, String> {
+    Ok(match kind {
+        b'I' => {
+            let (left, right) = (int(left)?, int(right)?);
+            if matches!(operation, 3 | 4) && right == 0 {
+                return Err("Division by zero".to_string());
+            }
+            Value::Int(match operation {
+                0 => left.wrapping_add(right),
+                1 => left.wrapping_sub(right),
+                2 => left.wrapping_mul(right),
+                3 => left.wrapping_div(right),
+                4 => left.wrapping_rem(right),
+                5 => left & right,
+                6 => left | right,
+                7 => left ^ right,
+                8 => left.wrapping_shl(right as u32),
+                9 => left.wrapping_shr(right as u32),
+                _ => (left as u32).wrapping_shr(right as u32) as i32,
+            })
+        }
+        b'J' => {
+            let left = long(left)?;
+            // 移位量是 int，只取低位
+            if operation >= 8 {
+                let shift = int(right)? as u32;
+                return Ok(Value::Long(match operation {
+                    8 => left.wrapping_shl(shift),
+                    9 => left.wrapping_shr(shift),
+                    _ => (left as u64).wrapping_shr(shift) as i64,
+                }));
+            }
+            let right = long(right)?;
+            if matches!(operation, 3 | 4) && right == 0 {
+                return Err("Division by zero".to_string());
+            }
+            Value::Long(match operation {
+                0 => left.wrapping_add(right),
+                1 => left.wrapping_sub(right),
+                2 => left.wrapping_mul(right),
+                3 => left.wrapping_div(right),
+                4 => left.wrapping_rem(right),
+                5 => left & right,
+                6 => left | right,
+                _ => left ^ right,
+            })
+        }
+        b'F' => {
+            let (left, right) = (float(left)?, float(right)?);
+            Value::Float(match operation {
+                0 => left + right,
+                1 => left - right,
+                2 => left * right,
+                3 => left / right,
+                _ => left % right,
+            })
+        }
+        _ => {
+            let (left, right) = (double(left)?, double(right)?);
+            Value::Double(match operation {
+                0 => left + right,
+                1 => left - right,
+                2 => left * right,
+                3 => left / right,
+                _ => left % right,
+            })
+        }
+    })
+}
+
+/// Rust 的 `as` 与 Dalvik 一样饱和转换，NaN 转为 0
+fn convert(opcode: DalvikOpcode, value: Value) -> Result<Value, String> {
+    use DalvikOpcode::*;
+    Ok(match opcode {
+        NegInt => Value::Int(int(value)?.wrapping_neg()),
+        NotInt => Value::Int(!int(value)?),
+        NegLong => Value::Long(long(value)?.wrapping_neg()),
+        NotLong => Value::Long(!long(value)?),
+        NegFloat => Value::Float(-float(value)?),
+        NegDouble => Value::Double(-double(value)?),
+        IntToLong => Value::Long(int(value)? as i64),
+        IntToFloat => Value::Float(int(value)? as f32),
+        IntToDouble => Value::Double(int(value)? as f64),
+        LongToInt => Value::Int(long(value)? as i32),
+        LongToFloat => Value::Float(long(value)? as f32),
+        LongToDouble => Value::Double(long(value)? as f64),
+        FloatToInt => Value::Int(float(value)? as i32),
+        FloatToLong => Value::Long(float(value)? as i64),
+        FloatToDouble => Value::Double(float(value)? as f64),
+        DoubleToInt => Value::Int(double(value)? as i32),
+        DoubleToLong => Value::Long(double(value)? as i64),
+        DoubleToFloat => Value::Float(double(value)? as f32),
+        IntToByte => Value::Int(int(value)? as i8 as i32),
+        IntToChar => Value::Int(int(value)? as u16 as i32),
+        _ => Value::Int(int(value)? as i16 as i32),
+    })
+}
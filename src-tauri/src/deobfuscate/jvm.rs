@@ -0,0 +1,576 @@
+// JVM 字节码解释器：只支持静态方法、基本类型运算、数组和 machine 中的内建库方法
+use std::collections::HashMap;
+
+use crate::java_analyzer::attributes::Attribute;
+use crate::java_analyzer::classfile::ClassFile;
+use crate::java_analyzer::constantpool::ConstantPoolEntry;
+use crate::java_analyzer::method::JvmMethod;
+use crate::java_analyzer::opcode::*;
+
+use super::machine::{Heap, Intrinsic, Object, Statics, Value, DEPTH_LIMIT, STEP_LIMIT};
+
+const ACC_STATIC: u16 = 0x0008;
+
+pub(crate) struct JvmEmulator<'a> {
+    classes: &'a HashMap<String, ClassFile>,
+    pub heap: Heap,
+    statics: Statics,
+    steps: usize,
+    depth: usize,
+}
+
+impl<'a> JvmEmulator<'a> {
+    pub fn new(classes: &'a HashMap<String, ClassFile>) -> Self {
+        JvmEmulator {
+            classes,
+            heap: Heap::default(),
+            statics: Statics::default(),
+            steps: 0,
+            depth: 0,
+        }
+    }
+
+    /// 求值一次静态方法调用，每次求值都有独立的指令数上限
+    pub fn call(&mut self, owner: &str, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Value>, String> {
+        self.steps = 0;
+        self.depth = 0;
+        self.invoke(owner, name, descriptor, args, true)
+    }
+
+    /// 类初始化之后静态字段的值
+    pub fn static_value(&mut self, owner: &str, name: &str) -> Result<Value, String> {
+        self.steps = 0;
+        self.depth = 0;
+        self.get_static(owner, name)
+    }
+
+    /// 执行 `<clinit>`。失败时只保留初始化过程中确实写入过的字段
+    fn initialize(&mut self, class_name: &str) -> Result<(), String> {
+        match self.statics.classes.get(class_name) {
+            Some(Some(true)) | Some(None) => return Ok(()),
+            Some(Some(false)) => return Err(format!("Initialization of {} failed", class_name)),
+            None => {}
+        }
+        let classes = self.classes;
+        let Some(class_file) = classes.get(class_name) else {
+            return Err(format!("Class {} is not part of the project", class_name));
+        };
+        self.statics.classes.insert(class_name.to_string(), None);
+        if let Some(super_name) = class_file.super_class_name() {
+            if classes.contains_key(&super_name) {
+                self.initialize(&super_name)?;
+            }
+        }
+
+        let mut defaults = Vec::new();
+        for field in class_file.fields.iter().filter(|f| f.access_flags & ACC_STATIC != 0) {
+            let key = (class_name.to_string(), field.name.clone());
+            let constant = field.attributes.iter().find_map(|attribute| match attribute {
+                Attribute::ConstantValue(value) => Some(value.constant_value_index as usize),
+                _ => None,
+            });
+            match constant {
+                Some(index) => {
+                    let value = self.constant(class_file, index)?;
+                    self.statics.values.insert(key, value);
+                }
+                None => {
+                    self.statics.values.insert(key.clone(), Value::default_for(&field.descriptor));
+                    defaults.push(key);
+                }
+            }
+        }
+
+        let initializer = class_file.methods.iter().find(|m| m.name == "<clinit>");
+        let result = match initializer {
+            Some(method) => {
+                let before: Vec<Value> = defaults.iter().map(|key| self.statics.values[key]).collect();
+                let result = self.execute(class_file, method, &[]).map(|_| ());
+                if result.is_err() {
+                    // 仍是默认值的字段无法确定是否本应被赋值，全部丢弃
+                    for (key, value) in defaults.iter().zip(before) {
+                        if self.statics.values.get(key) == Some(&value) {
+                            self.statics.values.remove(key);
+                        }
+                    }
+                }
+                result
+            }
+            None => Ok(()),
+        };
+        self.statics.classes.insert(class_name.to_string(), Some(result.is_ok()));
+        result
+    }
+
+    fn get_static(&mut self, owner: &str, name: &str) -> Result<Value, String> {
+        if let Some(value) = self.heap.static_field(owner, name)? {
+            return Ok(value);
+        }
+        // 初始化失败时，已经写入的字段仍然可以读取
+        let initialized = self.initialize(owner);
+        match self.statics.values.get(&(owner.to_string(), name.to_string())) {
+            Some(value) => Ok(*value),
+            None => Err(initialized.err().unwrap_or_else(|| format!("Unknown static field {}.{}", owner, name))),
+        }
+    }
+
+    fn put_static(&mut self, owner: &str, name: &str, value: Value) -> Result<(), String> {
+        if !self.classes.contains_key(owner) {
+            return Err(format!("Cannot write static field {}.{}", owner, name));
+        }
+        let _ = self.initialize(owner);
+        self.statics.values.insert((owner.to_string(), name.to_string()), value);
+        Ok(())
+    }
+
+    fn invoke(&mut self, owner: &str, name: &str, descriptor: &str, args: &[Value], is_static: bool) -> Result<Option<Value>, String> {
+        if let Intrinsic::Return(value) = self.heap.invoke(owner, name, descriptor, args)? {
+            return Ok(value);
+        }
+        if !is_static {
+            return Err(format!("Unsupported call {}.{}{}", owner, name, descriptor));
+        }
+        // 静态方法可能声明在父类中
+        let classes = self.classes;
+        let mut current = owner.to_string();
+        loop {
+            let class_file = classes
+                .get(&current)
+                .ok_or_else(|| format!("Unsupported call {}.{}{}", owner, name, descriptor))?;
+            if let Some(method) = class_file
+                .methods
+                .iter()
+                .find(|m| m.name == name && m.descriptor == descriptor && m.access_flags & ACC_STATIC != 0)
+            {
+                self.initialize(&current)?;
+                return self.execute(class_file, method, args);
+            }
+            current = class_file
+                .super_class_name()
+                .ok_or_else(|| format!("Method not found: {}.{}{}", owner, name, descriptor))?;
+        }
+    }
+
+    fn constant(&mut self, class_file: &ClassFile, index: usize) -> Result<Value, String> {
+        let pool = &class_file.constant_pool;
+        match pool.get_entry(index) {
+            Some(ConstantPoolEntry::Integer(value)) => Ok(Value::Int(*value)),
+            Some(ConstantPoolEntry::Long(value)) => Ok(Value::Long(*value)),
+            Some(ConstantPoolEntry::Float(value)) => Ok(Value::Float(*value)),
+            Some(ConstantPoolEntry::Double(value)) => Ok(Value::Double(*value)),
+            Some(ConstantPoolEntry::StringRef(_)) => {
+                let text = pool.get_string(index).ok_or_else(|| format!("Invalid string constant #{}", index))?;
+                self.heap.string(text)
+            }
+            _ => Err(format!("Unsupported constant #{}", index)),
+        }
+    }
+
+    fn execute(&mut self, class_file: &ClassFile, method: &JvmMethod, args: &[Value]) -> Result<Option<Value>, String> {
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            })
+            .ok_or_else(|| format!("Method {}{} has no code", method.name, method.descriptor))?;
+        if self.depth >= DEPTH_LIMIT {
+            return Err("Call depth limit exceeded".to_string());
+        }
+        self.depth += 1;
+        let mut frame = Frame {
+            locals: vec![Value::Int(0); code.max_locals as usize],
+            stack: Vec::with_capacity(code.max_stack as usize),
+        };
+        let mut slot = 0;
+        for arg in args {
+            frame.store(slot, *arg)?;
+            slot += if arg.is_wide() { 2 } else { 1 };
+        }
+        let result = self.run(class_file, method, &mut frame);
+        self.depth -= 1;
+        result
+    }
+
+    fn run(&mut self, class_file: &ClassFile, method: &JvmMethod, frame: &mut Frame) -> Result<Option<Value>, String> {
+        let pool = &class_file.constant_pool;
+        let instructions = &method.code;
+        let index_of: HashMap<u32, usize> = instructions.iter().enumerate().map(|(i, insn)| (insn.offset, i)).collect();
+        let jump = |insn: &Instruction, relative: i32| {
+            u32::try_from(insn.offset as i64 + relative as i64)
+                .ok()
+                .and_then(|offset| index_of.get(&offset).copied())
+                .ok_or_else(|| format!("Invalid branch target at offset {}", insn.offset))
+        };
+
+        let mut pc = 0;
+        loop {
+            self.steps += 1;
+            if self.steps > STEP_LIMIT {
+                return Err("Step limit exceeded".to_string());
+            }
+            let insn = instructions.get(pc).ok_or("Execution fell off the end of the method")?;
+            pc += 1;
+            let (opcode, operand) = if insn.opcode == OP_WIDE {
+                (insn.value as u8, insn.value2)
+            } else {
+                (insn.opcode, insn.value)
+            };
+            let stack = &mut frame.stack;
+            match opcode {
+                OP_NOP => {}
+                OP_ACONST_NULL => stack.push(Value::Null),
+                OP_ICONST_M1..=OP_ICONST_5 => stack.push(Value::Int(opcode as i32 - OP_ICONST_0 as i32)),
+                OP_LCONST_0 | OP_LCONST_1 => stack.push(Value::Long((opcode - OP_LCONST_0) as i64)),
+                OP_FCONST_0..=OP_FCONST_2 => stack.push(Value::Float((opcode - OP_FCONST_0) as f32)),
+                OP_DCONST_0 | OP_DCONST_1 => stack.push(Value::Double((opcode - OP_DCONST_0) as f64)),
+                OP_BIPUSH | OP_SIPUSH => stack.push(Value::Int(operand)),
+                OP_LDC | OP_LDC_W | OP_LDC2_W => {
+                    let value = self.constant(class_file, operand as usize)?;
+                    frame.stack.push(value);
+                }
+
+                OP_ILOAD | OP_LLOAD | OP_FLOAD | OP_DLOAD | OP_ALOAD => {
+                    let value = frame.load(operand as usize)?;
+                    frame.stack.push(value);
+                }
+                OP_ILOAD_0..=OP_ALOAD_3 => {
+                    let value = frame.load(((opcode - OP_ILOAD_0) % 4) as usize)?;
+                    frame.stack.push(value);
+                }
+                OP_ISTORE | OP_LSTORE | OP_FSTORE | OP_DSTORE | OP_ASTORE => {
+                    let value = frame.pop()?;
+                    frame.store(operand as usize, value)?;
+                }
+                OP_ISTORE_0..=OP_ASTORE_3 => {
+                    let value = frame.pop()?;
+                    frame.store(((opcode - OP_ISTORE_0) % 4) as usize, value)?;
+                }
+                OP_IINC => {
+                    // wide iinc 的增量保存在 pairs 中
+                    let increment = if insn.opcode == OP_WIDE { insn.pairs.first().map(|p| p.0).unwrap_or(0) } else { insn.value2 };
+                    let value = frame.load(operand as usize)?.int()?;
+                    frame.store(operand as usize, Value::Int(value.wrapping_add(increment)))?;
+                }
+
+                OP_IALOAD..=OP_SALOAD => {
+                    let index = frame.pop()?.int()?;
+                    let array = frame.pop()?;
+                    let value = self.heap.array_load(array, index)?;
+                    frame.stack.push(value);
+                }
+                OP_IASTORE..=OP_SASTORE => {
+                    let value = frame.pop()?;
+                    let index = frame.pop()?.int()?;
+                    let array = frame.pop()?;
+                    self.heap.array_store(array, index, value)?;
+                }
+                OP_NEWARRAY => {
+                    let length = frame.pop()?.int()?;
+                    let element = ["Z", "C", "F", "D", "B", "S", "I", "J"].get(operand as usize).copied().unwrap_or("I");
+                    let array = self.heap.new_array(element, length)?;
+                    frame.stack.push(array);
+                }
+                OP_ANEWARRAY => {
+                    let length = frame.pop()?.int()?;
+                    let element = class_descriptor(pool.get_class_name(operand as usize).ok_or("Invalid class reference")?);
+                    let array = self.heap.new_array(&element, length)?;
+                    frame.stack.push(array);
+                }
+                OP_MULTIANEWARRAY => {
+                    let mut lengths = Vec::new();
+                    for _ in 0..insn.value2 {
+                        lengths.insert(0, frame.pop()?.int()?);
+                    }
+                    let descriptor = pool.get_class_name(operand as usize).ok_or("Invalid class reference")?;
+                    let array = self.heap.new_multi_array(descriptor, &lengths)?;
+                    frame.stack.push(array);
+                }
+                OP_ARRAYLENGTH => {
+                    let array = frame.pop()?;
+                    let length = self.heap.array(array)?.len() as i32;
+                    frame.stack.push(Value::Int(length));
+                }
+
+                OP_POP | OP_POP2 => {
+                    let count = words_to_values(stack, stack.len(), (opcode - OP_POP + 1) as usize)?;
+                    stack.truncate(stack.len() - count);
+                }
+                OP_DUP => duplicate(stack, 1, 0)?,
+                OP_DUP_X1 => duplicate(stack, 1, 1)?,
+                OP_DUP_X2 => duplicate(stack, 1, 2)?,
+                OP_DUP2 => duplicate(stack, 2, 0)?,
+                OP_DUP2_X1 => duplicate(stack, 2, 1)?,
+                OP_DUP2_X2 => duplicate(stack, 2, 2)?,
+                OP_SWAP => {
+                    let length = stack.len();
+                    if length < 2 || stack[length - 1].is_wide() || stack[length - 2].is_wide() {
+                        return Err("Invalid swap".to_string());
+                    }
+                    stack.swap(length - 1, length - 2);
+                }
+
+                OP_IADD..=OP_LXOR => {
+                    let right = frame.pop()?;
+                    let left = if matches!(opcode, OP_INEG..=OP_DNEG) { right } else { frame.pop()? };
+                    let value = arithmetic(opcode, left, right)?;
+                    frame.stack.push(value);
+                }
+                OP_I2L..=OP_I2S => {
+                    let value = convert(opcode, frame.pop()?)?;
+                    frame.stack.push(value);
+                }
+                OP_LCMP..=OP_DCMPG => {
+                    let right = frame.pop()?;
+                    let left = frame.pop()?;
+                    let value = compare(opcode, left, right)?;
+                    frame.stack.push(Value::Int(value));
+                }
+
+                OP_IFEQ..=OP_IFLE => {
+                    let value = frame.pop()?.int()?;
+                    if condition(opcode - OP_IFEQ, value, 0) {
+                        pc = jump(insn, operand)?;
+                    }
+                }
+                OP_IF_ICMPEQ..=OP_IF_ICMPLE => {
+                    let right = frame.pop()?.int()?;
+                    let left = frame.pop()?.int()?;
+                    if condition(opcode - OP_IF_ICMPEQ, left, right) {
+                        pc = jump(insn, operand)?;
+                    }
+                }
+                OP_IF_ACMPEQ | OP_IF_ACMPNE => {
+                    let right = frame.pop()?;
+                    let left = frame.pop()?;
+                    if (left == right) == (opcode == OP_IF_ACMPEQ) {
+                        pc = jump(insn, operand)?;
+                    }
+                }
+                OP_IFNULL | OP_IFNONNULL => {
+                    let value = frame.pop()?;
+                    if (value == Value::Null) == (opcode == OP_IFNULL) {
+                        pc = jump(insn, operand)?;
+                    }
+                }
+                OP_GOTO | OP_GOTO_W => pc = jump(insn, operand)?,
+                OP_TABLESWITCH | OP_LOOKUPSWITCH => {
+                    let key = frame.pop()?.int()?;
+                    let target = insn.pairs.iter().find(|(k, _)| *k == key).map(|(_, target)| *target).unwrap_or(operand);
+                    pc = jump(insn, target)?;
+                }
+                OP_IRETURN..=OP_ARETURN => return Ok(Some(frame.pop()?)),
+                OP_RETURN => return Ok(None),
+
+                OP_GETSTATIC => {
+                    let (owner, name, _) = pool.get_member_ref(operand as usize).ok_or("Invalid field reference")?;
+                    let value = self.get_static(owner, name)?;
+                    frame.stack.push(value);
+                }
+                OP_PUTSTATIC => {
+                    let (owner, name, _) = pool.get_member_ref(operand as usize).ok_or("Invalid field reference")?;
+                    let value = frame.pop()?;
+                    self.put_static(owner, name, value)?;
+                }
+                OP_INVOKEVIRTUAL | OP_INVOKESPECIAL | OP_INVOKESTATIC | OP_INVOKEINTERFACE => {
+                    let (owner, name, descriptor) = pool.get_member_ref(operand as usize).ok_or("Invalid method reference")?;
+                    let count = parameter_count(descriptor) + (opcode != OP_INVOKESTATIC) as usize;
+                    if stack.len() < count {
+                        return Err("Operand stack underflow".to_string());
+                    }
+                    // 构造器原地填充 new 分配的对象，栈上 dup 出来的引用随之生效
+                    let args = stack.split_off(stack.len() - count);
+                    if let Some(value) = self.invoke(owner, name, descriptor, &args, opcode == OP_INVOKESTATIC)? {
+                        frame.stack.push(value);
+                    }
+                }
+                OP_NEW => {
+                    let object = self.heap.allocate(Object::Uninitialized)?;
+                    frame.stack.push(object);
+                }
+                OP_CHECKCAST => {}
+                OP_MONITORENTER | OP_MONITOREXIT => {
+                    frame.pop()?;
+                }
+                _ => return Err(format!("Unsupported instruction {} at offset {}", opcode_name(opcode), insn.offset)),
+            }
+        }
+    }
+}
+
+struct Frame {
+    locals: Vec<Value>,
+    stack: Vec<Value>,
+}
+
+impl Frame {
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack.pop().ok_or_else(|| "Operand stack underflow".to_string())
+    }
+
+    fn load(&self, slot: usize) -> Result<Value, String> {
+        self.locals.get(slot).copied().ok_or_else(|| format!("Invalid local variable {}", slot))
+    }
+
+    fn store(&mut self, slot: usize, value: Value) -> Result<(), String> {
+        let local = self.locals.get_mut(slot).ok_or_else(|| format!("Invalid local variable {}", slot))?;
+        *local = value;
+        Ok(())
+    }
+}
+
+/// 从栈的 `end` 位置往下数，恰好占 `words` 个字的值的个数
+fn words_to_values(stack: &[Value], end: usize, words: usize) -> Result<usize, String> {
+    let mut counted = 0;
+    let mut count = 0;
+    while counted < words {
+        let value = end.checked_sub(count + 1).and_then(|i| stack.get(i)).ok_or("Operand stack underflow")?;
+        counted += if value.is_wide() { 2 } else { 1 };
+        count += 1;
+    }
+    if counted != words {
+        return Err("Instruction splits a long or double value".to_string());
+    }
+    Ok(count)
+}
+
+/// dup 系列指令：复制栈顶 `top` 个字，插入到其下 `below` 个字之下
+fn duplicate(stack: &mut Vec<Value>, top: usize, below: usize) -> Result<(), String> {
+    let top_count = words_to_values(stack, stack.len(), top)?;
+    let start = stack.len() - top_count;
+    let below_count = words_to_values(stack, start, below)?;
+    let copied = stack[start..].to_vec();
+    let position = start - below_count;
+    stack.splice(position..position, copied);
+    Ok(())
+}
+
+fn parameter_count(descriptor: &str) -> usize {
+    let parameters = descriptor.trim_start_matches('(').split(')').next().unwrap_or_default();
+    let mut chars = parameters.chars();
+    let mut count = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => continue,
+            'L' => {
+                for c in chars.by_ref() {
+                    if c == ';' {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+        count += 1;
+    }
+    count
+}
+
+fn class_descriptor(class_name: &str) -> String {
+    if class_name.starts_with('[') {
+        class_name.to_string()
+    } else {
+        format!("L{};", class_name)
+    }
+}
+
+fn condition(kind: u8, left: i32, right: i32) -> bool {
+    match kind {
+        0 => left == right,
+        1 => left != right,
+        2 => left < right,
+        3 => left >= right,
+        4 => left > right,
+        _ => left <= right,
+    }
+}
+
+fn arithmetic(opcode: u8, left: Value, right: Value) -> Result<Value, String> {
+    Ok(match opcode {
+        OP_IADD => Value::Int(left.int()?.wrapping_add(right.int()?)),
+        OP_LADD => Value::Long(left.long()?.wrapping_add(right.long()?)),
+        OP_FADD => Value::Float(left.float()? + right.float()?),
+        OP_DADD => Value::Double(left.double()? + right.double()?),
+        OP_ISUB => Value::Int(left.int()?.wrapping_sub(right.int()?)),
+        OP_LSUB => Value::Long(left.long()?.wrapping_sub(right.long()?)),
+        OP_FSUB => Value::Float(left.float()? - right.float()?),
+        OP_DSUB => Value::Double(left.double()? - right.double()?),
+        OP_IMUL => Value::Int(left.int()?.wrapping_mul(right.int()?)),
+        OP_LMUL => Value::Long(left.long()?.wrapping_mul(right.long()?)),
+        OP_FMUL => Value::Float(left.float()? * right.float()?),
+        OP_DMUL => Value::Double(left.double()? * right.double()?),
+        OP_IDIV | OP_IREM => {
+            let divisor = right.int()?;
+            if divisor == 0 {
+                return Err("Division by zero".to_string());
+            }
+            let dividend = left.int()?;
+            Value::Int(if opcode == OP_IDIV { dividend.wrapping_div(divisor) } else { dividend.wrapping_rem(divisor) })
+        }
+        OP_LDIV | OP_LREM => {
+            let divisor = right.long()?;
+            if divisor == 0 {
+                return Err("Division by zero".to_string());
+            }
+            let dividend = left.long()?;
+            Value::Long(if opcode == OP_LDIV { dividend.wrapping_div(divisor) } else { dividend.wrapping_rem(divisor) })
+        }
+        OP_FDIV => Value::Float(left.float()? / right.float()?),
+        OP_DDIV => Value::Double(left.double()? / right.double()?),
+        OP_FREM => Value::Float(left.float()? % right.float()?),
+        OP_DREM => Value::Double(left.double()? % right.double()?),
+        OP_INEG => Value::Int(right.int()?.wrapping_neg()),
+        OP_LNEG => Value::Long(right.long()?.wrapping_neg()),
+        OP_FNEG => Value::Float(-right.float()?),
+        OP_DNEG => Value::Double(-right.double()?),
+        // 移位量是 int，只取低位
+        OP_ISHL => Value::Int(left.int()?.wrapping_shl(right.int()? as u32)),
+        OP_LSHL => Value::Long(left.long()?.wrapping_shl(right.int()? as u32)),
+        OP_ISHR => Value::Int(left.int()?.wrapping_shr(right.int()? as u32)),
+        OP_LSHR => Value::Long(left.long()?.wrapping_shr(right.int()? as u32)),
+        OP_IUSHR => Value::Int((left.int()? as u32).wrapping_shr(right.int()? as u32) as i32),
+        OP_LUSHR => Value::Long((left.long()? as u64).wrapping_shr(right.int()? as u32) as i64),
+        OP_IAND => Value::Int(left.int()? & right.int()?),
+        OP_LAND => Value::Long(left.long()? & right.long()?),
+        OP_IOR => Value::Int(left.int()? | right.int()?),
+        OP_LOR => Value::Long(left.long()? | right.long()?),
+        OP_IXOR => Value::Int(left.int()? ^ right.int()?),
+        _ => Value::Long(left.long()? ^ right.long()?),
+    })
+}
+
+/// Rust 的 `as` 与 JVM 一样饱和转换，NaN 转为 0
+fn convert(opcode: u8, value: Value) -> Result<Value, String> {
+    Ok(match opcode {
+        OP_I2L => Value::Long(value.int()? as i64),
+        OP_I2F => Value::Float(value.int()? as f32),
+        OP_I2D => Value::Double(value.int()? as f64),
+        OP_L2I => Value::Int(value.long()? as i32),
+        OP_L2F => Value::Float(value.long()? as f32),
+        OP_L2D => Value::Double(value.long()? as f64),
+        OP_F2I => Value::Int(value.float()? as i32),
+        OP_F2L => Value::Long(value.float()? as i64),
+        OP_F2D => Value::Double(value.float()? as f64),
+        OP_D2I => Value::Int(value.double()? as i32),
+        OP_D2L => Value::Long(value.double()? as i64),
+        OP_D2F => Value::Float(value.double()? as f32),
+        OP_I2B => Value::Int(value.int()? as i8 as i32),
+        OP_I2C => Value::Int(value.int()? as u16 as i32),
+        _ => Value::Int(value.int()? as i16 as i32),
+    })
+}
+
+fn compare(opcode: u8, left: Value, right: Value) -> Result<i32, String> {
+    let ordering = match opcode {
+        OP_LCMP => Some(left.long()?.cmp(&right.long()?)),
+        OP_FCMPL | OP_FCMPG => left.float()?.partial_cmp(&right.float()?),
+        _ => left.double()?.partial_cmp(&right.double()?),
+    };
+    Ok(match ordering {
+        Some(ordering) => ordering as i32,
+        // NaN：fcmpg/dcmpg 得 1，fcmpl/dcmpl 得 -1
+        None if matches!(opcode, OP_FCMPG | OP_DCMPG) => 1,
+        None => -1,
+    })
+}
@@ -0,0 +1,579 @@
+// 与指令集无关的部分：值、堆，以及 String / StringBuilder / 数组等库方法的内建实现
+use std::collections::HashMap;
+
+use crate::dataflow::Constant;
+
+/// 单次求值最多执行的指令数
+pub(crate) const STEP_LIMIT: usize = 200_000;
+/// 方法调用的最大嵌套深度
+pub(crate) const DEPTH_LIMIT: usize = 32;
+const HEAP_LIMIT: usize = 1 << 20;
+const ARRAY_LIMIT: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Value {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Null,
+    Ref(usize),
+}
+
+impl Value {
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Value::Long(_) | Value::Double(_))
+    }
+
+    pub fn int(self) -> Result<i32, String> {
+        match self {
+            Value::Int(value) => Ok(value),
+            other => Err(format!("Expected int, found {:?}", other)),
+        }
+    }
+
+    pub fn long(self) -> Result<i64, String> {
+        match self {
+            Value::Long(value) => Ok(value),
+            other => Err(format!("Expected long, found {:?}", other)),
+        }
+    }
+
+    pub fn float(self) -> Result<f32, String> {
+        match self {
+            Value::Float(value) => Ok(value),
+            other => Err(format!("Expected float, found {:?}", other)),
+        }
+    }
+
+    pub fn double(self) -> Result<f64, String> {
+        match self {
+            Value::Double(value) => Ok(value),
+            other => Err(format!("Expected double, found {:?}", other)),
+        }
+    }
+
+    pub fn reference(self) -> Result<usize, String> {
+        match self {
+            Value::Ref(index) => Ok(index),
+            Value::Null => Err("Null pointer dereference".to_string()),
+            other => Err(format!("Expected reference, found {:?}", other)),
+        }
+    }
+
+    /// 字段或数组元素的默认值
+    pub fn default_for(descriptor: &str) -> Value {
+        match descriptor.chars().next() {
+            Some('J') => Value::Long(0),
+            Some('F') => Value::Float(0.0),
+            Some('D') => Value::Double(0.0),
+            Some('L') | Some('[') => Value::Null,
+            _ => Value::Int(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Object {
+    /// UTF-16 代码单元
+    String(Vec<u16>),
+    /// StringBuilder 和 StringBuffer
+    Builder(Vec<u16>),
+    Array { element: String, values: Vec<Value> },
+    Charset(String),
+    Base64Decoder,
+    /// `new` 之后、构造器之前
+    Uninitialized,
+}
+
+/// 库方法调用的结果
+pub(crate) enum Intrinsic {
+    Return(Option<Value>),
+    /// 不是已知的库方法
+    Unknown,
+}
+
+#[derive(Default)]
+pub(crate) struct Heap {
+    objects: Vec<Object>,
+}
+
+impl Heap {
+    pub fn allocate(&mut self, object: Object) -> Result<Value, String> {
+        if self.objects.len() >= HEAP_LIMIT {
+            return Err("Heap limit exceeded".to_string());
+        }
+        self.objects.push(object);
+        Ok(Value::Ref(self.objects.len() - 1))
+    }
+
+    pub fn get(&self, value: Value) -> Result<&Object, String> {
+        let index = value.reference()?;
+        self.objects.get(index).ok_or_else(|| format!("Invalid reference {}", index))
+    }
+
+    pub fn get_mut(&mut self, value: Value) -> Result<&mut Object, String> {
+        let index = value.reference()?;
+        self.objects.get_mut(index).ok_or_else(|| format!("Invalid reference {}", index))
+    }
+
+    pub fn string(&mut self, text: &str) -> Result<Value, String> {
+        self.allocate(Object::String(text.encode_utf16().collect()))
+    }
+
+    pub fn new_array(&mut self, element: &str, length: i32) -> Result<Value, String> {
+        if length < 0 || length as usize > ARRAY_LIMIT {
+            return Err(format!("Unsupported array length {}", length));
+        }
+        let values = vec![Value::default_for(element); length as usize];
+        self.allocate(Object::Array {
+            element: element.to_string(),
+            values,
+        })
+    }
+
+    /// `new int[a][b]`，只为给出的维度分配内层数组
+    pub fn new_multi_array(&mut self, descriptor: &str, lengths: &[i32]) -> Result<Value, String> {
+        let element = descriptor.strip_prefix('[').ok_or_else(|| format!("Not an array type: {}", descriptor))?;
+        match lengths.split_first() {
+            None => Ok(Value::Null),
+            Some((length, [])) => self.new_array(element, *length),
+            Some((length, rest)) => {
+                let array = self.new_array(element, *length)?;
+                for index in 0..*length as usize {
+                    let inner = self.new_multi_array(element, rest)?;
+                    self.array_mut(array)?[index] = inner;
+                }
+                Ok(array)
+            }
+        }
+    }
+
+    pub fn array(&self, value: Value) -> Result<&Vec<Value>, String> {
+        match self.get(value)? {
+            Object::Array { values, .. } => Ok(values),
+            other => Err(format!("Expected array, found {:?}", other)),
+        }
+    }
+
+    pub fn array_mut(&mut self, value: Value) -> Result<&mut Vec<Value>, String> {
+        match self.get_mut(value)? {
+            Object::Array { values, .. } => Ok(values),
+            other => Err(format!("Expected array, found {:?}", other)),
+        }
+    }
+
+    pub fn array_load(&self, array: Value, index: i32) -> Result<Value, String> {
+        let values = self.array(array)?;
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| values.get(index).copied())
+            .ok_or_else(|| format!("Array index {} out of bounds", index))
+    }
+
+    /// 按元素类型截断后写入数组
+    pub fn array_store(&mut self, array: Value, index: i32, value: Value) -> Result<(), String> {
+        let (element, values) = match self.get_mut(array)? {
+            Object::Array { element, values } => (element.clone(), values),
+            other => return Err(format!("Expected array, found {:?}", other)),
+        };
+        let slot = usize::try_from(index)
+            .ok()
+            .and_then(|index| values.get_mut(index))
+            .ok_or_else(|| format!("Array index {} out of bounds", index))?;
+        *slot = match (element.as_str(), value) {
+            ("Z", Value::Int(v)) => Value::Int(v & 1),
+            ("B", Value::Int(v)) => Value::Int(v as i8 as i32),
+            ("C", Value::Int(v)) => Value::Int(v as u16 as i32),
+            ("S", Value::Int(v)) => Value::Int(v as i16 as i32),
+            (_, value) => value,
+        };
+        Ok(())
+    }
+
+    /// 字符串或 StringBuilder 的内容
+    pub fn chars(&self, value: Value) -> Result<&Vec<u16>, String> {
+        match self.get(value)? {
+            Object::String(chars) | Object::Builder(chars) => Ok(chars),
+            other => Err(format!("Expected string, found {:?}", other)),
+        }
+    }
+
+    pub fn text(&self, value: Value) -> Result<String, String> {
+        Ok(String::from_utf16_lossy(self.chars(value)?))
+    }
+
+    fn char_array(&self, value: Value) -> Result<Vec<u16>, String> {
+        self.array(value)?.iter().map(|v| v.int().map(|c| c as u16)).collect()
+    }
+
+    fn byte_array(&self, value: Value) -> Result<Vec<u8>, String> {
+        self.array(value)?.iter().map(|v| v.int().map(|b| b as u8)).collect()
+    }
+
+    fn new_char_array(&mut self, chars: &[u16]) -> Result<Value, String> {
+        self.allocate(Object::Array {
+            element: "C".to_string(),
+            values: chars.iter().map(|c| Value::Int(*c as i32)).collect(),
+        })
+    }
+
+    fn new_byte_array(&mut self, bytes: &[u8]) -> Result<Value, String> {
+        self.allocate(Object::Array {
+            element: "B".to_string(),
+            values: bytes.iter().map(|b| Value::Int(*b as i8 as i32)).collect(),
+        })
+    }
+
+    /// 字符串参数的字符集名称，StandardCharsets 的字段和字符集名称字符串都可以
+    fn charset(&self, value: Value) -> Result<String, String> {
+        match self.get(value)? {
+            Object::Charset(name) => Ok(name.clone()),
+            Object::String(chars) => Ok(String::from_utf16_lossy(chars).to_uppercase()),
+            other => Err(format!("Expected charset, found {:?}", other)),
+        }
+    }
+
+    pub fn constant(&mut self, constant: &Constant) -> Result<Value, String> {
+        Ok(match constant {
+            Constant::Int(value) => Value::Int(*value),
+            Constant::Long(value) => Value::Long(*value),
+            Constant::Float(value) => Value::Float(*value),
+            Constant::Double(value) => Value::Double(*value),
+            Constant::String(value) => self.string(value)?,
+            Constant::Null => Value::Null,
+        })
+    }
+
+    /// 静态字段的内建值，目前只有 StandardCharsets
+    pub fn static_field(&mut self, owner: &str, name: &str) -> Result<Option<Value>, String> {
+        if owner != "java/nio/charset/StandardCharsets" {
+            return Ok(None);
+        }
+        let charset = name.replace('_', "-");
+        Ok(Some(self.allocate(Object::Charset(charset))?))
+    }
+
+    /// 执行已知的库方法。`args` 中实例方法的接收者在最前面
+    pub fn invoke(&mut self, owner: &str, name: &str, descriptor: &str, args: &[Value]) -> Result<Intrinsic, String> {
+        let result = match owner {
+            "java/lang/String" => self.string_method(name, descriptor, args)?,
+            "java/lang/StringBuilder" | "java/lang/StringBuffer" => self.builder_method(name, descriptor, args)?,
+            "java/lang/Object" if name == "<init>" => Some(None),
+            "java/lang/Math" | "java/lang/StrictMath" => math_method(name, descriptor, args)?,
+            "java/lang/Character" => match (name, args) {
+                ("valueOf", [value]) | ("charValue", [value]) => Some(Some(*value)),
+                _ => None,
+            },
+            "java/lang/Integer" => match (name, args) {
+                ("parseInt", [text]) => {
+                    let text = self.text(*text)?;
+                    Some(Some(Value::Int(text.trim().parse().map_err(|_| format!("Invalid integer {:?}", text))?)))
+                }
+                ("valueOf", [value]) if descriptor == "(I)Ljava/lang/Integer;" => Some(Some(*value)),
+                ("intValue", [value]) => Some(Some(*value)),
+                _ => None,
+            },
+            "java/lang/System" if name == "arraycopy" => {
+                let [source, source_position, target, target_position, length] = args else {
+                    return Err("Invalid arraycopy call".to_string());
+                };
+                let (source_position, target_position, length) =
+                    (source_position.int()? as usize, target_position.int()? as usize, length.int()? as usize);
+                let copied = self
+                    .array(*source)?
+                    .get(source_position..source_position + length)
+                    .ok_or("arraycopy out of bounds")?
+                    .to_vec();
+                self.array_mut(*target)?
+                    .get_mut(target_position..target_position + length)
+                    .ok_or("arraycopy out of bounds")?
+                    .copy_from_slice(&copied);
+                Some(None)
+            }
+            "java/util/Arrays" if name == "copyOf" || name == "copyOfRange" => {
+                let (array, start, end) = match args {
+                    [array, length] => (*array, 0, length.int()?),
+                    [array, from, to] => (*array, from.int()?, to.int()?),
+                    _ => return Err("Invalid Arrays call".to_string()),
+                };
+                let element = match self.get(array)? {
+                    Object::Array { element, .. } => element.clone(),
+                    other => return Err(format!("Expected array, found {:?}", other)),
+                };
+                let values = self.array(array)?.clone();
+                let copy = self.new_array(&element, end - start)?;
+                for (offset, slot) in self.array_mut(copy)?.iter_mut().enumerate() {
+                    if let Some(value) = values.get(start as usize + offset) {
+                        *slot = *value;
+                    }
+                }
+                Some(Some(copy))
+            }
+            "java/util/Base64" if name == "getDecoder" || name == "getMimeDecoder" => Some(Some(self.allocate(Object::Base64Decoder)?)),
+            "java/util/Base64$Decoder" if name == "decode" => {
+                let encoded = match args {
+                    [_, input] if descriptor.starts_with("(Ljava/lang/String;") => self.text(*input)?.into_bytes(),
+                    [_, input] => self.byte_array(*input)?,
+                    _ => return Err("Invalid Base64 call".to_string()),
+                };
+                let decoded = base64_decode(&encoded)?;
+                Some(Some(self.new_byte_array(&decoded)?))
+            }
+            _ => None,
+        };
+        Ok(match result {
+            Some(value) => Intrinsic::Return(value),
+            None => Intrinsic::Unknown,
+        })
+    }
+
+    fn string_method(&mut self, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Option<Value>>, String> {
+        let value = match (name, descriptor) {
+            ("<init>", _) => {
+                let chars = match (descriptor, &args[1..]) {
+                    ("()V", []) => Vec::new(),
+                    ("([C)V", [chars]) => self.char_array(*chars)?,
+                    ("([CII)V", [chars, offset, count]) => slice(&self.char_array(*chars)?, offset.int()?, count.int()?)?,
+                    ("([B)V", [bytes]) => decode(&self.byte_array(*bytes)?, "UTF-8")?,
+                    ("([BII)V", [bytes, offset, count]) => decode(&slice(&self.byte_array(*bytes)?, offset.int()?, count.int()?)?, "UTF-8")?,
+                    ("([BLjava/lang/String;)V", [bytes, charset]) | ("([BLjava/nio/charset/Charset;)V", [bytes, charset]) => {
+                        decode(&self.byte_array(*bytes)?, &self.charset(*charset)?)?
+                    }
+                    ("(Ljava/lang/String;)V", [text])
+                    | ("(Ljava/lang/StringBuilder;)V", [text])
+                    | ("(Ljava/lang/StringBuffer;)V", [text]) => self.chars(*text)?.clone(),
+                    _ => return Ok(None),
+                };
+                *self.get_mut(args[0])? = Object::String(chars);
+                return Ok(Some(None));
+            }
+            ("valueOf", "([C)Ljava/lang/String;") | ("copyValueOf", "([C)Ljava/lang/String;") => {
+                let chars = self.char_array(args[0])?;
+                self.allocate(Object::String(chars))?
+            }
+            ("valueOf", "(C)Ljava/lang/String;") => self.allocate(Object::String(vec![args[0].int()? as u16]))?,
+            ("valueOf", "(I)Ljava/lang/String;") => self.string(&args[0].int()?.to_string())?,
+            ("valueOf", "(J)Ljava/lang/String;") => self.string(&args[0].long()?.to_string())?,
+            ("valueOf", "(Ljava/lang/Object;)Ljava/lang/String;") => match args[0] {
+                Value::Null => self.string("null")?,
+                value => {
+                    let chars = self.chars(value)?.clone();
+                    self.allocate(Object::String(chars))?
+                }
+            },
+            ("length", _) => Value::Int(self.chars(args[0])?.len() as i32),
+            ("isEmpty", _) => Value::Int(self.chars(args[0])?.is_empty() as i32),
+            ("charAt", _) => {
+                let index = args[1].int()?;
+                let chars = self.chars(args[0])?;
+                let c = usize::try_from(index).ok().and_then(|i| chars.get(i)).ok_or_else(|| format!("String index {} out of range", index))?;
+                Value::Int(*c as i32)
+            }
+            ("toCharArray", _) => {
+                let chars = self.chars(args[0])?.clone();
+                self.new_char_array(&chars)?
+            }
+            ("getBytes", "()[B") => {
+                let text = self.text(args[0])?;
+                self.new_byte_array(text.as_bytes())?
+            }
+            ("getBytes", "(Ljava/lang/String;)[B") | ("getBytes", "(Ljava/nio/charset/Charset;)[B") => {
+                let charset = self.charset(args[1])?;
+                let bytes = encode(self.chars(args[0])?, &charset)?;
+                self.new_byte_array(&bytes)?
+            }
+            ("intern", _) | ("toString", _) => args[0],
+            ("concat", _) => {
+                let mut chars = self.chars(args[0])?.clone();
+                chars.extend_from_slice(self.chars(args[1])?);
+                self.allocate(Object::String(chars))?
+            }
+            ("substring", _) => {
+                let chars = self.chars(args[0])?;
+                let start = args[1].int()?;
+                let end = match args.get(2) {
+                    Some(end) => end.int()?,
+                    None => chars.len() as i32,
+                };
+                let part = slice(chars, start, end - start)?;
+                self.allocate(Object::String(part))?
+            }
+            ("equals", _) => {
+                let equal = match args[1] {
+                    Value::Null => false,
+                    other => self.chars(args[0])? == self.chars(other)?,
+                };
+                Value::Int(equal as i32)
+            }
+            ("hashCode", _) => Value::Int(self.chars(args[0])?.iter().fold(0i32, |hash, c| hash.wrapping_mul(31).wrapping_add(*c as i32))),
+            ("indexOf", "(I)I") => {
+                let target = args[1].int()?;
+                let position = self.chars(args[0])?.iter().position(|c| *c as i32 == target);
+                Value::Int(position.map(|p| p as i32).unwrap_or(-1))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(Some(value)))
+    }
+
+    fn builder_method(&mut self, name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Option<Value>>, String> {
+        let receiver = args[0];
+        let value = match name {
+            "<init>" => {
+                let chars = match (descriptor, &args[1..]) {
+                    ("()V", []) | ("(I)V", [_]) => Vec::new(),
+                    ("(Ljava/lang/String;)V", [text]) | ("(Ljava/lang/CharSequence;)V", [text]) => self.chars(*text)?.clone(),
+                    _ => return Ok(None),
+                };
+                *self.get_mut(receiver)? = Object::Builder(chars);
+                return Ok(Some(None));
+            }
+            "append" => {
+                let appended: Vec<u16> = match (descriptor.split(')').next().unwrap_or_default(), args[1]) {
+                    ("(C", value) => vec![value.int()? as u16],
+                    ("(I", value) => value.int()?.to_string().encode_utf16().collect(),
+                    ("(J", value) => value.long()?.to_string().encode_utf16().collect(),
+                    ("(Z", value) => (if value.int()? != 0 { "true" } else { "false" }).encode_utf16().collect(),
+                    ("([C", value) => self.char_array(value)?,
+                    (_, Value::Null) => "null".encode_utf16().collect(),
+                    ("(Ljava/lang/String;", value)
+                    | ("(Ljava/lang/CharSequence;", value)
+                    | ("(Ljava/lang/Object;", value)
+                    | ("(Ljava/lang/StringBuilder;", value)
+                    | ("(Ljava/lang/StringBuffer;", value) => self.chars(value)?.clone(),
+                    _ => return Ok(None),
+                };
+                self.builder(receiver)?.extend(appended);
+                receiver
+            }
+            "toString" => {
+                let chars = self.chars(receiver)?.clone();
+                self.allocate(Object::String(chars))?
+            }
+            "length" => Value::Int(self.chars(receiver)?.len() as i32),
+            "charAt" => {
+                let index = args[1].int()?;
+                let chars = self.chars(receiver)?;
+                let c = usize::try_from(index).ok().and_then(|i| chars.get(i)).ok_or_else(|| format!("String index {} out of range", index))?;
+                Value::Int(*c as i32)
+            }
+            "setCharAt" => {
+                let (index, c) = (args[1].int()?, args[2].int()?);
+                let slot = usize::try_from(index)
+                    .ok()
+                    .and_then(|i| self.builder(receiver).ok()?.get_mut(i))
+                    .ok_or_else(|| format!("String index {} out of range", index))?;
+                *slot = c as u16;
+                return Ok(Some(None));
+            }
+            "setLength" => {
+                let length = args[1].int()?;
+                self.builder(receiver)?.resize(length.max(0) as usize, 0);
+                return Ok(Some(None));
+            }
+            "deleteCharAt" => {
+                let index = args[1].int()?;
+                let chars = self.builder(receiver)?;
+                if index < 0 || index as usize >= chars.len() {
+                    return Err(format!("String index {} out of range", index));
+                }
+                chars.remove(index as usize);
+                receiver
+            }
+            "reverse" => {
+                // 按代码点反转，代理对保持原有顺序
+                let text = self.text(receiver)?;
+                let reversed: String = text.chars().rev().collect();
+                *self.builder(receiver)? = reversed.encode_utf16().collect();
+                receiver
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(Some(value)))
+    }
+
+    fn builder(&mut self, value: Value) -> Result<&mut Vec<u16>, String> {
+        match self.get_mut(value)? {
+            Object::Builder(chars) => Ok(chars),
+            other => Err(format!("Expected StringBuilder, found {:?}", other)),
+        }
+    }
+}
+
+fn math_method(name: &str, descriptor: &str, args: &[Value]) -> Result<Option<Option<Value>>, String> {
+    let value = match (name, descriptor, args) {
+        ("abs", "(I)I", [a]) => Value::Int(a.int()?.wrapping_abs()),
+        ("abs", "(J)J", [a]) => Value::Long(a.long()?.wrapping_abs()),
+        ("min", "(II)I", [a, b]) => Value::Int(a.int()?.min(b.int()?)),
+        ("max", "(II)I", [a, b]) => Value::Int(a.int()?.max(b.int()?)),
+        ("min", "(JJ)J", [a, b]) => Value::Long(a.long()?.min(b.long()?)),
+        ("max", "(JJ)J", [a, b]) => Value::Long(a.long()?.max(b.long()?)),
+        _ => return Ok(None),
+    };
+    Ok(Some(Some(value)))
+}
+
+fn slice<T: Clone>(items: &[T], offset: i32, count: i32) -> Result<Vec<T>, String> {
+    usize::try_from(offset)
+        .ok()
+        .zip(usize::try_from(count).ok())
+        .and_then(|(offset, count)| items.get(offset..offset.checked_add(count)?))
+        .map(|part| part.to_vec())
+        .ok_or_else(|| format!("Range {}+{} out of bounds", offset, count))
+}
+
+fn decode(bytes: &[u8], charset: &str) -> Result<Vec<u16>, String> {
+    match charset {
+        "UTF-8" | "UTF8" => Ok(String::from_utf8_lossy(bytes).encode_utf16().collect()),
+        "ISO-8859-1" | "ISO8859-1" | "LATIN1" => Ok(bytes.iter().map(|b| *b as u16).collect()),
+        "US-ASCII" | "ASCII" => Ok(bytes.iter().map(|b| if *b < 0x80 { *b as u16 } else { 0xFFFD }).collect()),
+        "UTF-16BE" => Ok(bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect()),
+        "UTF-16LE" => Ok(bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect()),
+        other => Err(format!("Unsupported charset {}", other)),
+    }
+}
+
+fn encode(chars: &[u16], charset: &str) -> Result<Vec<u8>, String> {
+    match charset {
+        "UTF-8" | "UTF8" => Ok(String::from_utf16_lossy(chars).into_bytes()),
+        "ISO-8859-1" | "ISO8859-1" | "LATIN1" => Ok(chars.iter().map(|c| if *c < 0x100 { *c as u8 } else { b'?' }).collect()),
+        "US-ASCII" | "ASCII" => Ok(chars.iter().map(|c| if *c < 0x80 { *c as u8 } else { b'?' }).collect()),
+        "UTF-16BE" => Ok(chars.iter().flat_map(|c| c.to_be_bytes()).collect()),
+        "UTF-16LE" => Ok(chars.iter().flat_map(|c| c.to_le_bytes()).collect()),
+        other => Err(format!("Unsupported charset {}", other)),
+    }
+}
+
+/// 标准和 URL 安全两种字母表都接受，忽略空白和填充
+fn base64_decode(input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in input {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' | b'\r' | b'\n' | b' ' => continue,
+            other => return Err(format!("Invalid Base64 character {:?}", *other as char)),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(output)
+}
+
+/// 静态字段的取值，以及各个类的初始化状态
+#[derive(Default)]
+pub(crate) struct Statics {
+    pub values: HashMap<(String, String), Value>,
+    /// 类名 -> 初始化是否成功，`None` 表示正在初始化
+    pub classes: HashMap<String, Option<bool>>,
+}
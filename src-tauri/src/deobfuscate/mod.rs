@@ -0,0 +1,760 @@
+// String deobfuscation: emulates decryptor calls with constant arguments and static string tables
+pub(crate) mod dalvik;
+pub(crate) mod jvm;
+pub(crate) mod machine;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::android::{renamed_class as renamed_dex_class, ClassDef, DexFile, EncodedValue, FieldDescriptor, Method, MethodDescriptor};
+use crate::android_analyzer::dalvik_opcode::{DalvikInstruction, DalvikOpcode, DalvikOpcodeAnalyzer, DalvikReference};
+use crate::android_analyzer::dex_decompiler::DexDecompiler;
+use crate::android_analyzer::smali::SmaliPrinter;
+use crate::dataflow::dalvik::flow_method as dalvik_flow_method;
+use crate::dataflow::jvm::flow_method;
+use crate::dataflow::solver::{solve, ConstantPropagation, ConstantState, Definition, DefinitionSite};
+use crate::dataflow::{Constant, MethodAnalysis, Variable};
+use crate::hierarchy::{descriptor_to_internal_name, normalize_class_name, proto_to_descriptor};
+use crate::java_analyzer::attributes::Attribute;
+use crate::java_analyzer::classfile::ClassFile;
+use crate::java_analyzer::decompiler::{format_error, Decompiler};
+use crate::java_analyzer::disassembler::ClassFileDisassembler;
+use crate::java_analyzer::method::JvmMethod;
+use crate::java_analyzer::opcode::*;
+use crate::project::{Project, ProjectClasses, ProjectData};
+use crate::rename::{parse_renamed, project_renamer, Renamer};
+use dalvik::{index_dex_classes, DalvikEmulator, DexClasses};
+use jvm::JvmEmulator;
+use machine::{Heap, Object, Value};
+
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const DEX_STATIC_FINAL: u32 = 0x0018;
+
+/// A string recovered at one instruction.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveredString {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+    pub offset: u32,
+    pub value: String,
+    /// The decryptor method or static field the value was computed by
+    pub source: String,
+}
+
+/// A decryptor call with constant arguments that could not be emulated.
+#[derive(Debug, Clone, Serialize)]
+pub struct EmulationFailure {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+    pub offset: u32,
+    pub source: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StringReport {
+    pub strings: Vec<RecoveredString>,
+    pub failures: Vec<EmulationFailure>,
+}
+
+/// 还原结果所在的指令
+struct Site<'a> {
+    class_name: &'a str,
+    method_name: &'a str,
+    descriptor: &'a str,
+    offset: u32,
+}
+
+impl StringReport {
+    fn record(&mut self, site: &Site, source: String, result: Result<String, String>) {
+        match result {
+            Ok(value) => self.strings.push(RecoveredString {
+                class_name: site.class_name.to_string(),
+                method_name: site.method_name.to_string(),
+                descriptor: site.descriptor.to_string(),
+                offset: site.offset,
+                value,
+                source,
+            }),
+            Err(error) => self.failures.push(EmulationFailure {
+                class_name: site.class_name.to_string(),
+                method_name: site.method_name.to_string(),
+                descriptor: site.descriptor.to_string(),
+                offset: site.offset,
+                source,
+                error,
+            }),
+        }
+    }
+}
+
+/// 被分析的项目代码，在项目锁外使用
+enum ProjectCode {
+    Jvm(HashMap<String, ClassFile>),
+    Dex(Arc<Vec<DexFile>>),
+}
+
+/// 按类名索引的项目类，解释器据此查找被调用的方法
+fn index_classes(class_files: Vec<ClassFile>) -> HashMap<String, ClassFile> {
+    class_files
+        .into_iter()
+        .filter_map(|class_file| Some((class_file.class_name()?, class_file)))
+        .collect()
+}
+
+/// 项目中的全部类，以及需要处理的类名
+fn project_classes(project_id: &str, class_name: Option<&str>) -> Result<(ProjectCode, Vec<String>), String> {
+    let classes = Project::with_project_mut(project_id, |project| {
+        // 解释器需要整个 DEX，先解码并留在项目中
+        if let ProjectData::Android(android_data) = &mut project.data {
+            android_data.ensure_analyzed()?;
+        }
+        ProjectClasses::of(&mut project.data)
+    })?;
+    // class 文件在项目锁外读取和解析
+    let code = match classes {
+        ProjectClasses::Classpath(classpath) => ProjectCode::Jvm(index_classes(classpath.class_files())),
+        ProjectClasses::Decoded(dex_files) => ProjectCode::Dex(dex_files),
+        ProjectClasses::Indexed(_) => return Err("No DEX files in project".to_string()),
+    };
+    let mut names: Vec<String> = match &code {
+        ProjectCode::Jvm(classes) => classes.keys().cloned().collect(),
+        ProjectCode::Dex(dex_files) => index_dex_classes(dex_files).into_keys().collect(),
+    };
+    let targets = match class_name {
+        Some(name) => {
+            let name = normalize_class_name(name);
+            if !names.contains(&name) {
+                return Err(format!("Class not found: {}", name));
+            }
+            vec![name]
+        }
+        None => {
+            names.sort();
+            names
+        }
+    };
+    Ok((code, targets))
+}
+
+fn string_value(heap: &Heap, value: Value) -> Option<String> {
+    match heap.get(value).ok()? {
+        Object::String(chars) => Some(String::from_utf16_lossy(chars)),
+        _ => None,
+    }
+}
+
+/// 解密方法的返回值
+fn decrypted(heap: &Heap, value: Option<Value>) -> Result<String, String> {
+    match value {
+        Some(Value::Null) => Err("Decryptor returned null".to_string()),
+        Some(value) => string_value(heap, value).ok_or_else(|| "Decryptor did not return a string".to_string()),
+        None => Err("Decryptor returned no value".to_string()),
+    }
+}
+
+struct Deobfuscator<'a> {
+    classes: &'a HashMap<String, ClassFile>,
+    emulator: JvmEmulator<'a>,
+    report: StringReport,
+}
+
+impl<'a> Deobfuscator<'a> {
+    fn new(classes: &'a HashMap<String, ClassFile>) -> Self {
+        Deobfuscator {
+            classes,
+            emulator: JvmEmulator::new(classes),
+            report: StringReport::default(),
+        }
+    }
+
+    /// 只有项目中声明为 final 且没有 ConstantValue 的静态字段才由 `<clinit>` 计算
+    fn computed_static(&self, owner: &str, name: &str, descriptor: &str) -> bool {
+        self.classes.get(owner).is_some_and(|class_file| {
+            class_file.fields.iter().any(|field| {
+                field.name == name
+                    && field.descriptor == descriptor
+                    && field.access_flags & (ACC_STATIC | ACC_FINAL) == ACC_STATIC | ACC_FINAL
+                    && !field.attributes.iter().any(|attribute| matches!(attribute, Attribute::ConstantValue(_)))
+            })
+        })
+    }
+
+    fn scan_class(&mut self, class_name: &str) {
+        let classes = self.classes;
+        let Some(class_file) = classes.get(class_name) else {
+            return;
+        };
+        for method in class_file.methods.iter().filter(|m| !m.code.is_empty()) {
+            self.scan_static_tables(class_name, class_file, method);
+            self.scan_decryptor_calls(class_name, class_file, method);
+        }
+    }
+
+    /// `getstatic` 读取 `<clinit>` 计算出的字符串，以及 `getstatic; 常量; aaload` 形式的字符串表
+    fn scan_static_tables(&mut self, class_name: &str, class_file: &ClassFile, method: &JvmMethod) {
+        let pool = &class_file.constant_pool;
+        let code = &method.code;
+        for (index, insn) in code.iter().enumerate() {
+            if insn.opcode != OP_GETSTATIC {
+                continue;
+            }
+            let Some((owner, name, descriptor)) = pool.get_member_ref(insn.value as usize) else {
+                continue;
+            };
+            if !self.computed_static(owner, name, descriptor) {
+                continue;
+            }
+            let (offset, value, source) = match descriptor.as_str() {
+                "Ljava/lang/String;" => {
+                    let Ok(value) = self.emulator.static_value(owner, name) else {
+                        continue;
+                    };
+                    (insn.offset, string_value(&self.emulator.heap, value), format!("{}.{}", owner, name))
+                }
+                "[Ljava/lang/String;" => {
+                    let (Some(constant), Some(load)) = (code.get(index + 1), code.get(index + 2)) else {
+                        continue;
+                    };
+                    let element = match constant.opcode {
+                        OP_ICONST_0..=OP_ICONST_5 => (constant.opcode - OP_ICONST_0) as i32,
+                        OP_BIPUSH | OP_SIPUSH => constant.value,
+                        _ => continue,
+                    };
+                    if load.opcode != OP_AALOAD {
+                        continue;
+                    }
+                    let Ok(array) = self.emulator.static_value(owner, name) else {
+                        continue;
+                    };
+                    let value = self.emulator.heap.array_load(array, element).ok();
+                    (load.offset, value.and_then(|value| string_value(&self.emulator.heap, value)), format!("{}.{}[{}]", owner, name, element))
+                }
+                _ => continue,
+            };
+            if let Some(value) = value {
+                let site = Site {
+                    class_name,
+                    method_name: &method.name,
+                    descriptor: &method.descriptor,
+                    offset,
+                };
+                self.report.record(&site, source, Ok(value));
+            }
+        }
+    }
+
+    /// 参数全为常量、返回 String 的项目静态方法调用
+    fn scan_decryptor_calls(&mut self, class_name: &str, class_file: &ClassFile, method: &JvmMethod) {
+        let pool = &class_file.constant_pool;
+        let candidates: Vec<usize> = method
+            .code
+            .iter()
+            .enumerate()
+            .filter(|(_, insn)| insn.opcode == OP_INVOKESTATIC)
+            .filter(|(_, insn)| {
+                pool.get_member_ref(insn.value as usize).is_some_and(|(owner, _, descriptor)| {
+                    descriptor.ends_with(")Ljava/lang/String;") && self.classes.contains_key(owner)
+                })
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+        let Ok(flow) = flow_method(class_file, method) else {
+            return;
+        };
+        let constants = solve(&ConstantPropagation, &flow);
+
+        for index in candidates {
+            let insn = &method.code[index];
+            let Some((owner, name, descriptor)) = pool.get_member_ref(insn.value as usize) else {
+                continue;
+            };
+            // 调用指令的 uses 按出栈顺序排列，最后一个参数在前
+            let mut arguments = Vec::new();
+            for variable in flow.instructions[index].uses.iter().rev() {
+                match constants.before[index].get(variable) {
+                    Some(ConstantState::Constant(constant)) => arguments.push(constant.clone()),
+                    _ => break,
+                }
+            }
+            if arguments.len() != flow.instructions[index].uses.len() {
+                continue;
+            }
+            let source = format!("{}.{}{}", owner, name, descriptor);
+            let result = arguments
+                .iter()
+                .map(|constant| self.emulator.heap.constant(constant))
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|args| self.emulator.call(owner, name, descriptor, &args))
+                .and_then(|value| decrypted(&self.emulator.heap, value));
+            let site = Site {
+                class_name,
+                method_name: &method.name,
+                descriptor: &method.descriptor,
+                offset: insn.offset,
+            };
+            self.report.record(&site, source, result);
+        }
+    }
+}
+
+/// DEX 中的同一套扫描：寄存器的常量和定义来自 Dalvik 数据流
+struct DexDeobfuscator<'a> {
+    classes: &'a DexClasses<'a>,
+    emulator: DalvikEmulator<'a>,
+    report: StringReport,
+}
+
+impl<'a> DexDeobfuscator<'a> {
+    fn new(classes: &'a DexClasses<'a>) -> Self {
+        DexDeobfuscator {
+            classes,
+            emulator: DalvikEmulator::new(classes),
+            report: StringReport::default(),
+        }
+    }
+
+    /// 没有静态初始值、由 `<clinit>` 计算的 final 静态字段
+    fn computed_static(&self, field: &FieldDescriptor) -> bool {
+        let owner = descriptor_to_internal_name(&field.class_type.descriptor);
+        self.classes.get(&owner).is_some_and(|(_, class_def)| {
+            class_def.static_fields.iter().any(|declared| {
+                declared.name == field.name
+                    && declared.field_type.descriptor == field.field_type.descriptor
+                    && declared.access_flags & DEX_STATIC_FINAL == DEX_STATIC_FINAL
+                    && matches!(declared.value, None | Some(EncodedValue::Null))
+            })
+        })
+    }
+
+    /// `sget-object` 读取的计算出的静态字段
+    fn static_field<'i>(&self, insn: &'i DalvikInstruction) -> Option<&'i FieldDescriptor> {
+        match (&insn.opcode, &insn.reference) {
+            (DalvikOpcode::SgetObject, Some(DalvikReference::Field(field))) if self.computed_static(field) => Some(field),
+            _ => None,
+        }
+    }
+
+    /// 返回 String 的项目静态方法调用
+    fn decryptor<'i>(&self, insn: &'i DalvikInstruction) -> Option<&'i MethodDescriptor> {
+        match (&insn.opcode, &insn.reference) {
+            (DalvikOpcode::InvokeStatic | DalvikOpcode::InvokeStaticRange, Some(DalvikReference::Method(callee)))
+                if callee.proto.return_type.descriptor == "Ljava/lang/String;"
+                    && self.classes.contains_key(&descriptor_to_internal_name(&callee.class_type.descriptor)) =>
+            {
+                Some(callee)
+            }
+            _ => None,
+        }
+    }
+
+    fn scan_class(&mut self, class_name: &str) {
+        let Some(&(dex_file, class_def)) = self.classes.get(class_name) else {
+            return;
+        };
+        for method in class_def.direct_methods.iter().chain(&class_def.virtual_methods) {
+            self.scan_method(class_name, dex_file, method);
+        }
+    }
+
+    fn scan_method(&mut self, class_name: &str, dex_file: &DexFile, method: &Method) {
+        let Some(code) = &method.code else {
+            return;
+        };
+        let Ok(decoded) = DalvikOpcodeAnalyzer::new().analyze_method(code, dex_file) else {
+            return;
+        };
+        // 与数据流 IR 一样跳过负载，下标一一对应
+        let instructions: Vec<&DalvikInstruction> = decoded.iter().filter(|insn| insn.payload.is_none()).collect();
+        if !instructions.iter().any(|insn| self.static_field(insn).is_some() || self.decryptor(insn).is_some()) {
+            return;
+        }
+        let Ok(flow) = dalvik_flow_method(dex_file, method) else {
+            return;
+        };
+        let analysis = MethodAnalysis::new(flow);
+        let descriptor = proto_to_descriptor(&method.proto);
+        let constant = |index: usize, register: u32| match analysis.constants.before[index].get(&Variable::Local(register as u16)) {
+            Some(ConstantState::Constant(constant)) => Some(constant.clone()),
+            _ => None,
+        };
+
+        for (index, insn) in instructions.iter().enumerate() {
+            let site = Site {
+                class_name,
+                method_name: &method.name,
+                descriptor: &descriptor,
+                offset: insn.address,
+            };
+            if let Some(field) = self.static_field(insn).filter(|field| field.field_type.descriptor == "Ljava/lang/String;") {
+                let owner = descriptor_to_internal_name(&field.class_type.descriptor);
+                if let Some(value) = self.emulator.static_value(&owner, &field.name).ok().and_then(|value| string_value(&self.emulator.heap, value)) {
+                    self.report.record(&site, format!("{}.{}", owner, field.name), Ok(value));
+                }
+            } else if insn.opcode == DalvikOpcode::AgetObject {
+                // 数组寄存器唯一的定义是读取字符串表的 sget-object，下标是常量
+                let (Some(&array), Some(&element)) = (insn.registers.get(1), insn.registers.get(2)) else {
+                    continue;
+                };
+                let definitions = analysis.definitions_of(index, Variable::Local(array as u16));
+                let [Definition { site: DefinitionSite::Instruction(definition), .. }] = definitions.as_slice() else {
+                    continue;
+                };
+                let Some(field) = self
+                    .static_field(instructions[*definition])
+                    .filter(|field| field.field_type.descriptor == "[Ljava/lang/String;")
+                else {
+                    continue;
+                };
+                let Some(Constant::Int(element)) = constant(index, element) else {
+                    continue;
+                };
+                let owner = descriptor_to_internal_name(&field.class_type.descriptor);
+                let value = self
+                    .emulator
+                    .static_value(&owner, &field.name)
+                    .and_then(|array| self.emulator.heap.array_load(array, element))
+                    .ok()
+                    .and_then(|value| string_value(&self.emulator.heap, value));
+                if let Some(value) = value {
+                    self.report.record(&site, format!("{}.{}[{}]", owner, field.name, element), Ok(value));
+                }
+            } else if let Some(callee) = self.decryptor(insn) {
+                // 宽参数占两个寄存器，只有低位寄存器带值
+                let mut registers = insn.registers.iter();
+                let mut arguments = Vec::new();
+                for parameter in &callee.proto.parameters {
+                    let Some(value) = registers.next().and_then(|register| constant(index, *register)) else {
+                        break;
+                    };
+                    arguments.push(value);
+                    if matches!(parameter.descriptor.as_str(), "J" | "D") {
+                        registers.next();
+                    }
+                }
+                if arguments.len() != callee.proto.parameters.len() {
+                    continue;
+                }
+                let owner = descriptor_to_internal_name(&callee.class_type.descriptor);
+                let callee_descriptor = proto_to_descriptor(&callee.proto);
+                let source = format!("{}.{}{}", owner, callee.name, callee_descriptor);
+                let result = arguments
+                    .iter()
+                    .map(|constant| self.emulator.heap.constant(constant))
+                    .collect::<Result<Vec<_>, _>>()
+                    .and_then(|args| self.emulator.call(&owner, &callee.name, &callee_descriptor, &args))
+                    .and_then(|value| decrypted(&self.emulator.heap, value));
+                self.report.record(&site, source, result);
+            }
+        }
+    }
+}
+
+fn recover_strings(code: &ProjectCode, targets: &[String]) -> StringReport {
+    match code {
+        ProjectCode::Jvm(classes) => {
+            let mut deobfuscator = Deobfuscator::new(classes);
+            for class_name in targets {
+                deobfuscator.scan_class(class_name);
+            }
+            deobfuscator.report
+        }
+        ProjectCode::Dex(dex_files) => {
+            let classes = index_dex_classes(dex_files);
+            let mut deobfuscator = DexDeobfuscator::new(&classes);
+            for class_name in targets {
+                deobfuscator.scan_class(class_name);
+            }
+            deobfuscator.report
+        }
+    }
+}
+
+/// 按 (方法名, 描述符, 偏移) 索引还原出的字符串，方法名和描述符经过重命名
+fn annotations(report: &StringReport, renamer: &Renamer) -> HashMap<(String, String, u32), String> {
+    report
+        .strings
+        .iter()
+        .map(|string| {
+            let name = renamer.method_name(&string.class_name, &string.method_name, &string.descriptor);
+            ((name, renamer.descriptor(&string.descriptor), string.offset), string.value.clone())
+        })
+        .collect()
+}
+
+/// 重新解析目标类并套用重命名，分析用的类保持二进制中的名字
+fn renamed_class(project_id: &str, class_name: &str, renamer: &Renamer) -> Result<ClassFile, String> {
+    let bytes = Project::with_project(project_id, |project| match &project.data {
+        ProjectData::Java(java_data) => java_data.classpath.read_class(class_name),
+        _ => Err("Not a Java project".to_string()),
+    })?;
+    parse_renamed(&bytes, renamer)
+}
+
+/// 在解码好的 DEX 中找到目标类，套用重命名后交给 `print` 输出
+fn print_dex_class(
+    dex_files: &[DexFile],
+    class_name: &str,
+    renamer: &Renamer,
+    print: impl FnOnce(&DexFile, &ClassDef) -> Result<String, String>,
+) -> Result<String, String> {
+    let classes = index_dex_classes(dex_files);
+    let &(dex_file, class_def) = classes.get(class_name).ok_or_else(|| format!("Class not found: {}", class_name))?;
+    let (dex_file, class_def) = renamed_dex_class(dex_file, Cow::Borrowed(class_def), renamer);
+    print(&dex_file, &class_def)
+}
+
+/// 字符串注释使用 Java 字面量的写法
+fn string_comments(report: &StringReport, renamer: &Renamer) -> HashMap<(String, String, u32), String> {
+    annotations(report, renamer)
+        .into_iter()
+        .map(|(key, value)| (key, format!("\"{}\"", crate::java_analyzer::ast::escape_string(&value))))
+        .collect()
+}
+
+/// Recovers obfuscated strings in one class, or in the whole project when no class is given.
+#[tauri::command(async)]
+pub fn deobfuscate_strings(project_id: String, class_name: Option<String>) -> Result<StringReport, String> {
+    let (code, targets) = project_classes(&project_id, class_name.as_deref())?;
+    Ok(recover_strings(&code, &targets))
+}
+
+/// Disassembles a class with recovered strings as comments on the decrypting instructions.
+/// DEX classes are printed as smali.
+#[tauri::command(async)]
+pub fn deobfuscate_disassemble_class(project_id: String, class_name: String) -> Result<String, String> {
+    let renamer = project_renamer(&project_id)?;
+    let (code, targets) = project_classes(&project_id, Some(&renamer.map.original_class_name(&class_name)))?;
+    let comments = string_comments(&recover_strings(&code, &targets), &renamer);
+    match &code {
+        ProjectCode::Jvm(_) => {
+            let class_file = renamed_class(&project_id, &targets[0], &renamer)?;
+            ClassFileDisassembler::from_class_file(class_file)
+                .with_comments(comments)
+                .disassemble()
+        }
+        ProjectCode::Dex(dex_files) => print_dex_class(dex_files, &targets[0], &renamer, |dex_file, class_def| {
+            SmaliPrinter::new(dex_file)
+                .with_comments(comments)
+                .print_class(class_def)
+                .map_err(|e| e.to_string())
+        }),
+    }
+}
+
+/// Decompiles a class with decryptor calls and string table reads replaced by the recovered literals.
+#[tauri::command(async)]
+pub fn deobfuscate_decompile_class(project_id: String, class_name: String) -> Result<String, String> {
+    let renamer = project_renamer(&project_id)?;
+    let (code, targets) = project_classes(&project_id, Some(&renamer.map.original_class_name(&class_name)))?;
+    let strings = annotations(&recover_strings(&code, &targets), &renamer);
+    match &code {
+        ProjectCode::Jvm(_) => {
+            let class_file = renamed_class(&project_id, &targets[0], &renamer)?;
+            Decompiler::new(&class_file)
+                .with_strings(strings)
+                .decompile_class()
+                .map_err(|e| format!("Failed to decompile class: {}", format_error(&e)))
+        }
+        ProjectCode::Dex(dex_files) => print_dex_class(dex_files, &targets[0], &renamer, |dex_file, class_def| {
+            DexDecompiler::new(dex_file)
+                .with_strings(strings)
+                .decompile_class(class_def)
+                .map_err(|e| format!("Failed to decompile class: {}", e))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+
+    const SECRET: &str = r#"
+.class public LSecret;
+.super Ljava/lang/Object;
+
+.field private static final NAME:Ljava/lang/String;
+.field private static final TABLE:[Ljava/lang/String;
+
+.method static constructor <clinit>()V
+    .registers 3
+    const/4 v0, 0x2
+    new-array v0, v0, [Ljava/lang/String;
+    const/4 v1, 0x1
+    const-string v2, "beta"
+    aput-object v2, v0, v1
+    sput-object v0, LSecret;->TABLE:[Ljava/lang/String;
+    const-string v0, "ab"
+    const-string v1, "cd"
+    invoke-virtual {v0, v1}, Ljava/lang/String;->concat(Ljava/lang/String;)Ljava/lang/String;
+    move-result-object v0
+    sput-object v0, LSecret;->NAME:Ljava/lang/String;
+    return-void
+.end method
+
+.method public static xor(Ljava/lang/String;I)Ljava/lang/String;
+    .registers 6
+    invoke-virtual {p0}, Ljava/lang/String;->toCharArray()[C
+    move-result-object v0
+    array-length v1, v0
+    const/4 v2, 0x0
+    :loop
+    if-ge v2, v1, :done
+    aget-char v3, v0, v2
+    xor-int/2addr v3, p1
+    int-to-char v3, v3
+    aput-char v3, v0, v2
+    add-int/lit8 v2, v2, 0x1
+    goto :loop
+    :done
+    new-instance v3, Ljava/lang/String;
+    invoke-direct {v3, v0}, Ljava/lang/String;-><init>([C)V
+    return-object v3
+.end method
+
+.method public static slice(JI)Ljava/lang/String;
+    .registers 7
+    const/4 v0, 0x4
+    new-array v0, v0, [C
+    fill-array-data v0, :chars
+    long-to-int v1, p0
+    new-instance v2, Ljava/lang/String;
+    invoke-direct {v2, v0, v1, p2}, Ljava/lang/String;-><init>([CII)V
+    return-object v2
+    :chars
+    .array-data 2
+        0x77s
+        0x69s
+        0x64s
+        0x65s
+    .end array-data
+.end method
+
+.method public static broken()Ljava/lang/String;
+    .registers 1
+    const/4 v0, 0x0
+    throw v0
+.end method
+
+.method public static use(I)V
+    .registers 5
+    const-string v0, "idmmn"
+    const/4 v1, 0x1
+    invoke-static {v0, v1}, LSecret;->xor(Ljava/lang/String;I)Ljava/lang/String;
+    move-result-object v0
+    const-wide/16 v1, 0x1
+    const/4 v3, 0x3
+    invoke-static {v1, v2, v3}, LSecret;->slice(JI)Ljava/lang/String;
+    move-result-object v0
+    invoke-static {v0, p0}, LSecret;->xor(Ljava/lang/String;I)Ljava/lang/String;
+    move-result-object v0
+    invoke-static {}, LSecret;->broken()Ljava/lang/String;
+    move-result-object v0
+    sget-object v0, LSecret;->NAME:Ljava/lang/String;
+    sget-object v0, LSecret;->TABLE:[Ljava/lang/String;
+    const/4 v1, 0x1
+    aget-object v2, v0, v1
+    return-void
+.end method
+"#;
+
+    #[test]
+    fn dalvik_decryptors_and_static_tables_are_emulated() {
+        let dex_files = Arc::new(vec![dex_file(&[SECRET])]);
+        let report = recover_strings(&ProjectCode::Dex(dex_files), &["Secret".to_string()]);
+        let recovered: Vec<(&str, &str)> = report
+            .strings
+            .iter()
+            .filter(|string| string.method_name == "use")
+            .map(|string| (string.source.as_str(), string.value.as_str()))
+            .collect();
+        assert_eq!(
+            recovered,
+            vec![
+                ("Secret.xor(Ljava/lang/String;I)Ljava/lang/String;", "hello"),
+                // 宽参数占 v1/v2，int 参数在 v3
+                ("Secret.slice(JI)Ljava/lang/String;", "ide"),
+                ("Secret.NAME", "abcd"),
+                ("Secret.TABLE[1]", "beta"),
+            ]
+        );
+        // 参数不是常量的调用不求值，求值失败的调用单独报告
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].source, "Secret.broken()Ljava/lang/String;");
+    }
+
+    const DECRYPT: &str = r#"
+.class public Lapp/Config;
+.super Ljava/lang/Object;
+
+.method public static dec(Ljava/lang/String;)Ljava/lang/String;
+    .registers 5
+    invoke-virtual {p0}, Ljava/lang/String;->toCharArray()[C
+    move-result-object v0
+    const/4 v1, 0x0
+    :loop
+    array-length v2, v0
+    if-ge v1, v2, :done
+    aget-char v2, v0, v1
+    xor-int/lit8 v2, v2, 0x2a
+    int-to-char v2, v2
+    aput-char v2, v0, v1
+    add-int/lit8 v1, v1, 0x1
+    goto :loop
+    :done
+    new-instance v3, Ljava/lang/String;
+    invoke-direct {v3, v0}, Ljava/lang/String;-><init>([C)V
+    return-object v3
+.end method
+
+.method public static key()Ljava/lang/String;
+    .registers 1
+    const-string v0, "YOIXO^"
+    invoke-static {v0}, Lapp/Config;->dec(Ljava/lang/String;)Ljava/lang/String;
+    move-result-object v0
+    return-object v0
+.end method
+"#;
+
+    #[test]
+    fn dex_output_is_annotated_with_recovered_strings() {
+        let dex_files = Arc::new(vec![dex_file(&[DECRYPT])]);
+        let code = ProjectCode::Dex(dex_files.clone());
+        let targets = vec!["app/Config".to_string()];
+        let report = recover_strings(&code, &targets);
+        let renamer = Renamer::default();
+
+        let smali = print_dex_class(&dex_files, &targets[0], &renamer, |dex_file, class_def| {
+            SmaliPrinter::new(dex_file)
+                .with_comments(string_comments(&report, &renamer))
+                .print_class(class_def)
+                .map_err(|e| e.to_string())
+        })
+        .unwrap();
+        assert!(
+            smali.contains("    invoke-static {v0}, Lapp/Config;->dec(Ljava/lang/String;)Ljava/lang/String;    # \"secret\"\n"),
+            "{}",
+            smali
+        );
+
+        let source = print_dex_class(&dex_files, &targets[0], &renamer, |dex_file, class_def| {
+            DexDecompiler::new(dex_file)
+                .with_strings(annotations(&report, &renamer))
+                .decompile_class(class_def)
+                .map_err(|e| e.to_string())
+        })
+        .unwrap();
+        assert!(source.contains("return \"secret\";"), "{}", source);
+        assert!(!source.contains("dec(\"YOIXO^\")"), "{}", source);
+    }
+}
@@ -0,0 +1,756 @@
+// Compare two Java or Android projects: classes, members, access flags and method bytecode
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::android::{ClassDef, DexFile, EncodedValue, Method, MethodHandleMember};
+use crate::android_analyzer::dalvik_opcode::{payload_at, DalvikOpcode, DalvikOpcodeAnalyzer, DalvikPayload, DalvikReference};
+use crate::hierarchy::{descriptor_to_internal_name, normalize_class_name, proto_to_descriptor};
+use crate::java_analyzer::attributes::{Attribute, Code_attribute};
+use crate::java_analyzer::classfile::{ClassFile, ClassFileReader};
+use crate::java_analyzer::constantpool::{ConstantPool, ConstantPoolEntry};
+use crate::java_analyzer::method::JvmMethod;
+use crate::java_analyzer::opcode::*;
+use crate::model::{ClassModel, MethodModel};
+use crate::program::CodeReference;
+use crate::project::{Project, ProjectClasses, ProjectData};
+
+/// 超过这个规模的方法不做逐行 LCS，只报告首尾相同部分之外的增删
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MemberKind {
+    Field,
+    Method,
+}
+
+/// A field or method present in only one of the two projects.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberSummary {
+    pub kind: MemberKind,
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u32,
+}
+
+/// A field or method present in both projects that differs.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberDiff {
+    pub kind: MemberKind,
+    pub name: String,
+    pub descriptor: String,
+    pub old_access_flags: u32,
+    pub new_access_flags: u32,
+    /// Constant initial value of a field
+    pub value_changed: bool,
+    /// Method bytecode after resolving constant pool operands
+    pub code_changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassDiff {
+    pub class_name: String,
+    pub old_access_flags: u32,
+    pub new_access_flags: u32,
+    pub old_super_name: Option<String>,
+    pub new_super_name: Option<String>,
+    pub added_interfaces: Vec<String>,
+    pub removed_interfaces: Vec<String>,
+    pub added_members: Vec<MemberSummary>,
+    pub removed_members: Vec<MemberSummary>,
+    pub changed_members: Vec<MemberDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectDiff {
+    pub added_classes: Vec<String>,
+    pub removed_classes: Vec<String>,
+    pub changed_classes: Vec<ClassDiff>,
+    pub unchanged_classes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DiffLineKind {
+    Same,
+    Added,
+    Removed,
+}
+
+/// One line of a method bytecode diff.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+/// 与常量池下标无关的类快照，Java 和 DEX 类都转换成这种形式再比较
+struct ClassSnapshot {
+    access_flags: u32,
+    super_name: Option<String>,
+    interfaces: BTreeSet<String>,
+    /// (名称, 描述符) -> (访问标志, 常量初始值)
+    fields: BTreeMap<(String, String), (u32, Option<FieldValue>)>,
+    /// (名称, 描述符) -> (访问标志, 规范化后的指令)
+    methods: BTreeMap<(String, String), (u32, Vec<String>)>,
+}
+
+/// 与常量池和 id 表下标无关的字段初始值
+#[derive(Debug, Clone, PartialEq)]
+enum FieldValue {
+    /// class 文件的 ConstantValue，解析成文本
+    Constant(String),
+    /// DEX 的数值按类型和位模式比较，浮点数的 NaN 也能相等
+    Number(char, u64),
+    String(String),
+    Type(String),
+    /// 字段、方法、枚举常量、方法类型和方法句柄，以 (种类, 描述) 表示
+    Member(&'static str, String),
+    Array(Vec<FieldValue>),
+    Annotation(String, Vec<(String, FieldValue)>),
+    Null,
+}
+
+impl FieldValue {
+    fn from_encoded(dex_file: &DexFile, value: &EncodedValue) -> Self {
+        let member = |kind, reference: DalvikReference| FieldValue::Member(kind, reference.to_string());
+        match value {
+            EncodedValue::Byte(v) => FieldValue::Number('B', *v as u64),
+            EncodedValue::Short(v) => FieldValue::Number('S', *v as u64),
+            EncodedValue::Char(v) => FieldValue::Number('C', *v as u64),
+            EncodedValue::Int(v) => FieldValue::Number('I', *v as u64),
+            EncodedValue::Long(v) => FieldValue::Number('J', *v as u64),
+            EncodedValue::Float(v) => FieldValue::Number('F', v.to_bits() as u64),
+            EncodedValue::Double(v) => FieldValue::Number('D', v.to_bits()),
+            EncodedValue::Boolean(v) => FieldValue::Number('Z', *v as u64),
+            EncodedValue::String(v) => FieldValue::String(v.clone()),
+            EncodedValue::Type(t) => FieldValue::Type(t.descriptor.clone()),
+            EncodedValue::Field(field) => member("field", DalvikReference::Field(field.clone())),
+            EncodedValue::Method(method) => member("method", DalvikReference::Method(method.clone())),
+            EncodedValue::Enum(field) => member("enum", DalvikReference::Field(field.clone())),
+            EncodedValue::MethodType(proto) => member("method type", DalvikReference::Proto(proto.clone())),
+            // 方法句柄在值中只是下标，换成它指向的成员
+            EncodedValue::MethodHandle(index) => match dex_file.method_handles.get(*index as usize) {
+                Some(handle) => {
+                    let target = match &handle.member {
+                        MethodHandleMember::Field(field) => DalvikReference::Field(field.clone()),
+                        MethodHandleMember::Method(method) => DalvikReference::Method(method.clone()),
+                    };
+                    FieldValue::Member("method handle", format!("{:?} {}", handle.handle_type, target))
+                }
+                None => FieldValue::Member("method handle", format!("<invalid {}>", index)),
+            },
+            EncodedValue::Array(values) => FieldValue::Array(values.iter().map(|v| FieldValue::from_encoded(dex_file, v)).collect()),
+            EncodedValue::Annotation(annotation) => FieldValue::Annotation(
+                annotation.annotation_type.descriptor.clone(),
+                annotation
+                    .elements
+                    .iter()
+                    .map(|element| (element.name.clone(), FieldValue::from_encoded(dex_file, &element.value)))
+                    .collect(),
+            ),
+            EncodedValue::Null => FieldValue::Null,
+        }
+    }
+}
+
+impl ClassSnapshot {
+    fn from_model(class: ClassModel) -> Self {
+        match class {
+            ClassModel::Jvm(class_file) => ClassSnapshot::from_class_file(class_file),
+            ClassModel::Dex(dex_file, class_def) => ClassSnapshot::from_class_def(dex_file, class_def),
+        }
+    }
+
+    fn from_class_file(class_file: &ClassFile) -> Self {
+        let pool = &class_file.constant_pool;
+        let fields = class_file
+            .fields
+            .iter()
+            .map(|field| {
+                let value = field.attributes.iter().find_map(|attribute| match attribute {
+                    Attribute::ConstantValue(value) => Some(FieldValue::Constant(constant_text(pool, value.constant_value_index as usize))),
+                    _ => None,
+                });
+                ((field.name.clone(), field.descriptor.clone()), (field.access_flags as u32, value))
+            })
+            .collect();
+        let methods = class_file
+            .methods
+            .iter()
+            .map(|method| {
+                let code = normalize_jvm_code(class_file, method);
+                ((method.name.clone(), method.descriptor.clone()), (method.access_flags as u32, code))
+            })
+            .collect();
+        ClassSnapshot {
+            access_flags: class_file.access_flags as u32,
+            super_name: class_file.super_class_name(),
+            interfaces: class_file.interface_names().into_iter().collect(),
+            fields,
+            methods,
+        }
+    }
+
+    fn from_class_def(dex_file: &DexFile, class_def: &ClassDef) -> Self {
+        let fields = class_def
+            .static_fields
+            .iter()
+            .chain(class_def.instance_fields.iter())
+            .map(|field| {
+                let value = field.value.as_ref().map(|value| FieldValue::from_encoded(dex_file, value));
+                ((field.name.clone(), field.field_type.descriptor.clone()), (field.access_flags, value))
+            })
+            .collect();
+        let methods = class_def
+            .direct_methods
+            .iter()
+            .chain(class_def.virtual_methods.iter())
+            .map(|method| {
+                let code = normalize_dex_code(dex_file, method);
+                ((method.name.clone(), proto_to_descriptor(&method.proto)), (method.access_flags, code))
+            })
+            .collect();
+        ClassSnapshot {
+            access_flags: class_def.access_flags,
+            super_name: class_def.super_type.as_ref().map(|t| descriptor_to_internal_name(&t.descriptor)),
+            interfaces: class_def.interfaces.iter().map(|t| descriptor_to_internal_name(&t.descriptor)).collect(),
+            fields,
+            methods,
+        }
+    }
+}
+
+/// 常量池操作数解析成文本，这样常量池重排不会产生差异
+pub(crate) fn constant_text(pool: &ConstantPool, index: usize) -> String {
+    match pool.get_entry(index) {
+        Some(ConstantPoolEntry::Integer(value)) => value.to_string(),
+        Some(ConstantPoolEntry::Float(value)) => format!("{:?}f", value),
+        Some(ConstantPoolEntry::Long(value)) => format!("{}L", value),
+        Some(ConstantPoolEntry::Double(value)) => format!("{:?}d", value),
+        Some(ConstantPoolEntry::StringRef(_)) => format!("{:?}", pool.get_string(index).cloned().unwrap_or_default()),
+        Some(ConstantPoolEntry::ClassRef(_)) => pool.get_class_name(index).cloned().unwrap_or_default(),
+        Some(ConstantPoolEntry::FieldRef(..) | ConstantPoolEntry::MethodRef(..) | ConstantPoolEntry::InterfaceMethodRef(..)) => {
+            match pool.get_member_ref(index) {
+                Some((owner, name, descriptor)) => format!("{}.{}:{}", owner, name, descriptor),
+                None => format!("#{}", index),
+            }
+        }
+        Some(ConstantPoolEntry::MethodHandleRef(..)) => match pool.get_method_handle(index) {
+            Some((kind, owner, name, descriptor)) => format!("handle {} {}.{}:{}", kind, owner, name, descriptor),
+            None => format!("#{}", index),
+        },
+        Some(ConstantPoolEntry::MethodTypeRef(descriptor_index)) => {
+            format!("methodtype {}", pool.get_utf8(*descriptor_index as usize).cloned().unwrap_or_default())
+        }
+        _ => format!("#{}", index),
+    }
+}
+
+/// 每条指令一行：常量池操作数替换成解析后的值，跳转目标用按出现顺序编号的标签表示，
+/// 异常表附在最后。这样只有真正的代码变化才会体现在差异中。
+fn normalize_jvm_code(class_file: &ClassFile, method: &JvmMethod) -> Vec<String> {
+    let Some(code) = method.attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Code(code) => Some(code),
+        _ => None,
+    }) else {
+        return Vec::new();
+    };
+    let pool = &class_file.constant_pool;
+
+    let mut targets = BTreeSet::new();
+    for insn in &method.code {
+        targets.extend(branch_targets(insn));
+    }
+    for entry in &code.exception_table {
+        targets.extend([entry.start_pc as u32, entry.end_pc as u32, entry.handler_pc as u32]);
+    }
+    let labels: HashMap<u32, String> = targets.iter().enumerate().map(|(i, offset)| (*offset, format!("L{}", i))).collect();
+    let label = |offset: u32| labels.get(&offset).cloned().unwrap_or_else(|| format!("@{}", offset));
+
+    let mut lines = Vec::new();
+    for insn in &method.code {
+        let name = opcode_name(insn.opcode);
+        let operand = match insn.opcode {
+            OP_LDC | OP_LDC_W | OP_LDC2_W | OP_GETSTATIC | OP_PUTSTATIC | OP_GETFIELD | OP_PUTFIELD | OP_INVOKEVIRTUAL
+            | OP_INVOKESPECIAL | OP_INVOKESTATIC | OP_INVOKEINTERFACE | OP_NEW | OP_ANEWARRAY | OP_CHECKCAST
+            | OP_INSTANCEOF => constant_text(pool, insn.value as usize),
+            OP_MULTIANEWARRAY => format!("{} {}", constant_text(pool, insn.value as usize), insn.value2),
+            OP_INVOKEDYNAMIC => invoke_dynamic_text(class_file, insn.value as usize),
+            OP_IINC => format!("{} {}", insn.value, insn.value2),
+            OP_BIPUSH | OP_SIPUSH | OP_NEWARRAY | OP_ILOAD | OP_LLOAD | OP_FLOAD | OP_DLOAD | OP_ALOAD | OP_ISTORE
+            | OP_LSTORE | OP_FSTORE | OP_DSTORE | OP_ASTORE | OP_RET => insn.value.to_string(),
+            OP_WIDE => {
+                let increment = insn.pairs.first().map(|(v, _)| format!(" {}", v)).unwrap_or_default();
+                format!("{} {}{}", opcode_name(insn.value as u8), insn.value2, increment)
+            }
+            OP_TABLESWITCH | OP_LOOKUPSWITCH => {
+                let cases: Vec<String> = insn
+                    .pairs
+                    .iter()
+                    .map(|(key, target)| format!("{}: {}", key, label((insn.offset as i32 + target) as u32)))
+                    .collect();
+                format!("{{{}; default: {}}}", cases.join(", "), label((insn.offset as i32 + insn.value) as u32))
+            }
+            _ => match branch_targets(insn).first() {
+                Some(target) => label(*target),
+                None => String::new(),
+            },
+        };
+        let prefix = labels.get(&insn.offset).map(|l| format!("{}: ", l)).unwrap_or_default();
+        lines.push(format!("{}{} {}", prefix, name, operand).trim_end().to_string());
+    }
+    lines.extend(exception_lines(pool, code, &label));
+    lines
+}
+
+/// Dalvik 版本的规范化：字符串、类型和成员按解析后的值输出，跳转和 switch 目标换成标签，
+/// payload 并入引用它的指令，try 块附在最后
+fn normalize_dex_code(dex_file: &DexFile, method: &Method) -> Vec<String> {
+    let Some(code) = &method.code else {
+        return Vec::new();
+    };
+    let instructions = match DalvikOpcodeAnalyzer::new().analyze_method(code, dex_file) {
+        Ok(instructions) => instructions,
+        Err(e) => return vec![format!("<undecodable: {}>", e)],
+    };
+    // 调用点和方法句柄在指令中只是下标，使用统一模型解析出的目标方法
+    let resolved: HashMap<u32, Vec<CodeReference>> = MethodModel::Dex(dex_file, method)
+        .instructions()
+        .into_iter()
+        .map(|instruction| (instruction.offset, instruction.references))
+        .collect();
+
+    let mut targets = BTreeSet::new();
+    for insn in instructions.iter().filter(|insn| insn.payload.is_none()) {
+        match insn.opcode {
+            DalvikOpcode::PackedSwitch | DalvikOpcode::SparseSwitch => {
+                targets.extend(insn.switch_cases(&instructions).into_iter().map(|(_, target)| target));
+            }
+            DalvikOpcode::FillArrayData => {}
+            _ => targets.extend(insn.target),
+        }
+    }
+    for try_item in &code.tries {
+        targets.extend([try_item.start_addr, try_item.start_addr + try_item.insn_count as u32]);
+        if let Some(handler) = code.handler(try_item) {
+            targets.extend(handler.handlers.iter().map(|pair| pair.addr).chain(handler.catch_all_addr));
+        }
+    }
+    let labels: HashMap<u32, String> = targets.iter().enumerate().map(|(i, address)| (*address, format!("L{}", i))).collect();
+    let label = |address: u32| labels.get(&address).cloned().unwrap_or_else(|| format!("@{}", address));
+
+    let mut lines = Vec::new();
+    for insn in instructions.iter().filter(|insn| insn.payload.is_none()) {
+        let mut operands: Vec<String> = insn.registers.iter().map(|r| format!("v{}", r)).collect();
+        operands.extend(insn.literal.map(|literal| literal.to_string()));
+        match insn.opcode {
+            DalvikOpcode::PackedSwitch | DalvikOpcode::SparseSwitch => {
+                let cases: Vec<String> =
+                    insn.switch_cases(&instructions).into_iter().map(|(key, target)| format!("{}: {}", key, label(target))).collect();
+                operands.push(format!("{{{}}}", cases.join(", ")));
+            }
+            DalvikOpcode::FillArrayData => {
+                let elements = insn.target.and_then(|target| payload_at(&instructions, target)).map(DalvikPayload::elements);
+                operands.push(format!("{:?}", elements.unwrap_or_default()));
+            }
+            _ => operands.extend(insn.target.map(label)),
+        }
+        match &insn.reference {
+            Some(DalvikReference::CallSite(_) | DalvikReference::MethodHandle(_)) => {
+                let handles = resolved.get(&insn.address).into_iter().flatten().filter_map(|reference| match reference {
+                    CodeReference::MethodHandle { owner, name, descriptor } => Some(format!("handle {}.{}:{}", owner, name, descriptor)),
+                    _ => None,
+                });
+                operands.push(format!("[{}]", handles.collect::<Vec<_>>().join(", ")));
+            }
+            Some(reference) => operands.push(reference.to_string()),
+            None => {}
+        }
+        operands.extend(insn.proto.as_ref().map(proto_to_descriptor));
+        let prefix = labels.get(&insn.address).map(|l| format!("{}: ", l)).unwrap_or_default();
+        lines.push(format!("{}{} {}", prefix, insn.opcode.name(), operands.join(", ")).trim_end().to_string());
+    }
+    for try_item in &code.tries {
+        let range = format!("[{}, {})", label(try_item.start_addr), label(try_item.start_addr + try_item.insn_count as u32));
+        let Some(handler) = code.handler(try_item) else { continue };
+        for pair in &handler.handlers {
+            let catch_type = dex_file.types.get(pair.type_idx as usize).map(|t| t.descriptor.as_str()).unwrap_or("?");
+            lines.push(format!("catch {} {} -> {}", catch_type, range, label(pair.addr)));
+        }
+        if let Some(address) = handler.catch_all_addr {
+            lines.push(format!("catch any {} -> {}", range, label(address)));
+        }
+    }
+    lines
+}
+
+fn exception_lines(pool: &ConstantPool, code: &Code_attribute, label: &dyn Fn(u32) -> String) -> Vec<String> {
+    code.exception_table
+        .iter()
+        .map(|entry| {
+            let catch_type = if entry.catch_type == 0 {
+                "any".to_string()
+            } else {
+                pool.get_class_name(entry.catch_type as usize).cloned().unwrap_or_default()
+            };
+            format!(
+                "catch {} [{}, {}) -> {}",
+                catch_type,
+                label(entry.start_pc as u32),
+                label(entry.end_pc as u32),
+                label(entry.handler_pc as u32)
+            )
+        })
+        .collect()
+}
+
+fn invoke_dynamic_text(class_file: &ClassFile, index: usize) -> String {
+    let pool = &class_file.constant_pool;
+    let Some((bootstrap_index, name, descriptor)) = pool.get_invoke_dynamic(index) else {
+        return format!("#{}", index);
+    };
+    let bootstrap = class_file
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::BootstrapMethods(methods) => methods.bootstrap_methods.get(bootstrap_index as usize),
+            _ => None,
+        })
+        .map(|method| {
+            let arguments: Vec<String> = method.bootstrap_arguments.iter().map(|a| constant_text(pool, *a as usize)).collect();
+            format!(" {} [{}]", constant_text(pool, method.bootstrap_method_ref as usize), arguments.join(", "))
+        })
+        .unwrap_or_default();
+    format!("{}:{}{}", name, descriptor, bootstrap)
+}
+
+fn branch_targets(insn: &Instruction) -> Vec<u32> {
+    match insn.opcode {
+        OP_IFEQ..=OP_JSR | OP_IFNULL | OP_IFNONNULL | OP_GOTO_W | OP_JSR_W => vec![(insn.offset as i32 + insn.value) as u32],
+        OP_TABLESWITCH | OP_LOOKUPSWITCH => std::iter::once(insn.value)
+            .chain(insn.pairs.iter().map(|(_, target)| *target))
+            .map(|relative| (insn.offset as i32 + relative) as u32)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn diff_class(class_name: &str, old: &ClassSnapshot, new: &ClassSnapshot) -> Option<ClassDiff> {
+    let mut added_members = Vec::new();
+    let mut removed_members = Vec::new();
+    let mut changed_members = Vec::new();
+
+    for ((name, descriptor), (flags, value)) in &old.fields {
+        match new.fields.get(&(name.clone(), descriptor.clone())) {
+            None => removed_members.push(MemberSummary {
+                kind: MemberKind::Field,
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                access_flags: *flags,
+            }),
+            Some((new_flags, new_value)) if new_flags != flags || new_value != value => changed_members.push(MemberDiff {
+                kind: MemberKind::Field,
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                old_access_flags: *flags,
+                new_access_flags: *new_flags,
+                value_changed: new_value != value,
+                code_changed: false,
+            }),
+            Some(_) => {}
+        }
+    }
+    for ((name, descriptor), (flags, _)) in &new.fields {
+        if !old.fields.contains_key(&(name.clone(), descriptor.clone())) {
+            added_members.push(MemberSummary {
+                kind: MemberKind::Field,
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                access_flags: *flags,
+            });
+        }
+    }
+    for ((name, descriptor), (flags, code)) in &old.methods {
+        match new.methods.get(&(name.clone(), descriptor.clone())) {
+            None => removed_members.push(MemberSummary {
+                kind: MemberKind::Method,
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                access_flags: *flags,
+            }),
+            Some((new_flags, new_code)) if new_flags != flags || new_code != code => changed_members.push(MemberDiff {
+                kind: MemberKind::Method,
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                old_access_flags: *flags,
+                new_access_flags: *new_flags,
+                value_changed: false,
+                code_changed: new_code != code,
+            }),
+            Some(_) => {}
+        }
+    }
+    for ((name, descriptor), (flags, _)) in &new.methods {
+        if !old.methods.contains_key(&(name.clone(), descriptor.clone())) {
+            added_members.push(MemberSummary {
+                kind: MemberKind::Method,
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                access_flags: *flags,
+            });
+        }
+    }
+
+    let added_interfaces: Vec<String> = new.interfaces.difference(&old.interfaces).cloned().collect();
+    let removed_interfaces: Vec<String> = old.interfaces.difference(&new.interfaces).cloned().collect();
+    let unchanged = old.access_flags == new.access_flags
+        && old.super_name == new.super_name
+        && added_interfaces.is_empty()
+        && removed_interfaces.is_empty()
+        && added_members.is_empty()
+        && removed_members.is_empty()
+        && changed_members.is_empty();
+    if unchanged {
+        return None;
+    }
+    Some(ClassDiff {
+        class_name: class_name.to_string(),
+        old_access_flags: old.access_flags,
+        new_access_flags: new.access_flags,
+        old_super_name: old.super_name.clone(),
+        new_super_name: new.super_name.clone(),
+        added_interfaces,
+        removed_interfaces,
+        added_members,
+        removed_members,
+        changed_members,
+    })
+}
+
+/// 按类名取出项目中所有类的快照，类在项目锁外逐个读取和规范化；重复定义的类以第一个为准
+fn project_snapshots(project_id: &str) -> Result<BTreeMap<String, ClassSnapshot>, String> {
+    let classes = ProjectClasses::snapshot(project_id)?;
+    let mut snapshots = BTreeMap::new();
+    classes.for_each_class(|class| {
+        if let Some(class_name) = class.name() {
+            snapshots.entry(class_name).or_insert_with(|| ClassSnapshot::from_model(class));
+        }
+    });
+    Ok(snapshots)
+}
+
+/// 只取出一个类的快照，不读取或解码项目中的其他类
+fn class_snapshot(project_id: &str, class_name: &str) -> Result<Option<ClassSnapshot>, String> {
+    enum Found {
+        Jvm(Vec<u8>),
+        Dex(ClassSnapshot),
+    }
+    let found = Project::with_project_mut(project_id, |project| match &mut project.data {
+        ProjectData::Java(java_data) if java_data.classpath.contains_class(class_name) => {
+            java_data.classpath.read_class(class_name).map(|bytes| Some(Found::Jvm(bytes)))
+        }
+        ProjectData::Java(_) => Ok(None),
+        ProjectData::Android(android_data) => Ok(android_data
+            .dex_class(&format!("L{};", class_name))?
+            .map(|(dex_file, class_def)| Found::Dex(ClassSnapshot::from_class_def(dex_file, &class_def)))),
+        _ => Err("Not a Java or Android project".to_string()),
+    })?;
+    // class 文件在项目锁外解析
+    match found {
+        Some(Found::Jvm(bytes)) => {
+            let class_file = ClassFileReader::new(&bytes).read().map_err(|e| format!("{:?}", e))?;
+            Ok(Some(ClassSnapshot::from_class_file(&class_file)))
+        }
+        Some(Found::Dex(snapshot)) => Ok(Some(snapshot)),
+        None => Ok(None),
+    }
+}
+
+/// Lists the classes and members that differ between two projects, e.g. two releases of an SDK.
+#[tauri::command(async)]
+pub fn diff_projects(old_project_id: String, new_project_id: String) -> Result<ProjectDiff, String> {
+    let old = project_snapshots(&old_project_id)?;
+    let new = project_snapshots(&new_project_id)?;
+
+    let mut diff = ProjectDiff {
+        added_classes: new.keys().filter(|name| !old.contains_key(*name)).cloned().collect(),
+        removed_classes: old.keys().filter(|name| !new.contains_key(*name)).cloned().collect(),
+        changed_classes: Vec::new(),
+        unchanged_classes: 0,
+    };
+    for (class_name, old_class) in &old {
+        if let Some(new_class) = new.get(class_name) {
+            match diff_class(class_name, old_class, new_class) {
+                Some(class_diff) => diff.changed_classes.push(class_diff),
+                None => diff.unchanged_classes += 1,
+            }
+        }
+    }
+    Ok(diff)
+}
+
+/// Line by line diff of the normalized bytecode of one method in both projects.
+#[tauri::command(async)]
+pub fn diff_method(
+    old_project_id: String,
+    new_project_id: String,
+    class_name: String,
+    method_name: String,
+    descriptor: String,
+) -> Result<Vec<DiffLine>, String> {
+    let class_name = normalize_class_name(&class_name);
+    let key = (method_name, descriptor);
+    let method_code = |project_id: &str| -> Result<Option<Vec<String>>, String> {
+        let snapshot = class_snapshot(project_id, &class_name)?;
+        Ok(snapshot.and_then(|mut class| class.methods.remove(&key)).map(|(_, code)| code))
+    };
+    // 只在一侧存在的方法（新增或删除）整体显示为增加或删除的行
+    match (method_code(&old_project_id)?, method_code(&new_project_id)?) {
+        (None, None) => Err(format!("Method {}.{}{} not found in either project", class_name, key.0, key.1)),
+        (old, new) => Ok(diff_lines(&old.unwrap_or_default(), &new.unwrap_or_default())),
+    }
+}
+
+/// 先去掉相同的首尾，再对中间部分做 LCS
+fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    let prefix = old.iter().zip(new.iter()).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let line = |kind: DiffLineKind, text: &String| DiffLine { kind, text: text.clone() };
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(|t| line(DiffLineKind::Same, t)).collect();
+
+    if old_middle.len() * new_middle.len() > MAX_LCS_CELLS {
+        lines.extend(old_middle.iter().map(|t| line(DiffLineKind::Removed, t)));
+        lines.extend(new_middle.iter().map(|t| line(DiffLineKind::Added, t)));
+    } else {
+        // lengths[i][j]：old_middle[i..] 与 new_middle[j..] 的 LCS 长度
+        let (n, m) = (old_middle.len(), new_middle.len());
+        let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i][j] = if old_middle[i] == new_middle[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_middle[i] == new_middle[j] {
+                lines.push(line(DiffLineKind::Same, &old_middle[i]));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || lengths[i + 1][j] >= lengths[i][j + 1]) {
+                lines.push(line(DiffLineKind::Removed, &old_middle[i]));
+                i += 1;
+            } else {
+                lines.push(line(DiffLineKind::Added, &new_middle[j]));
+                j += 1;
+            }
+        }
+    }
+
+    lines.extend(old[old.len() - suffix..].iter().map(|t| line(DiffLineKind::Same, t)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support;
+
+    fn greeter(text: &str) -> String {
+        format!(
+            r#"
+.class public Lcom/example/Greeter;
+.super Ljava/lang/Object;
+
+.method public static greet(I)Ljava/lang/String;
+    .registers 2
+    if-eqz v1, :empty
+    const-string v0, "{}"
+    return-object v0
+    :empty
+    const-string v0, ""
+    return-object v0
+.end method
+"#,
+            text
+        )
+    }
+
+    const OTHER: &str = r#"
+.class public Lcom/example/Aaa;
+.super Ljava/lang/Object;
+
+.method public static name()Ljava/lang/String;
+    .registers 1
+    const-string v0, "aaa"
+    return-object v0
+.end method
+"#;
+
+    fn class_of(dex_file: &DexFile, descriptor: &str) -> ClassSnapshot {
+        let class_def = dex_file.classes.iter().find(|class_def| class_def.class_type.descriptor == descriptor).unwrap();
+        ClassSnapshot::from_class_def(dex_file, class_def)
+    }
+
+    fn greet_code(dex_file: &DexFile) -> Vec<String> {
+        class_of(dex_file, "Lcom/example/Greeter;").methods[&("greet".to_string(), "(I)Ljava/lang/String;".to_string())].1.clone()
+    }
+
+    #[test]
+    fn dex_code_ignores_id_table_reshuffling() {
+        let old = test_support::dex_file(&[&greeter("hello")]);
+        // 新增的类带来排在前面的字符串和类型，原有的下标全部后移
+        let new = test_support::dex_file(&[&greeter("hello"), OTHER]);
+        assert_ne!(old.strings, new.strings);
+        assert_eq!(greet_code(&old), greet_code(&new));
+        assert!(greet_code(&old).iter().any(|line| line.contains("\"hello\"")));
+    }
+
+    #[test]
+    fn dex_code_reports_operand_changes() {
+        let old = test_support::dex_file(&[&greeter("hello")]);
+        let new = test_support::dex_file(&[&greeter("bye")]);
+        let lines = diff_lines(&greet_code(&old), &greet_code(&new));
+        let changed: Vec<&DiffLine> = lines.iter().filter(|line| line.kind != DiffLineKind::Same).collect();
+        assert_eq!(changed.len(), 2);
+        assert!(changed.iter().any(|line| line.kind == DiffLineKind::Added && line.text.contains("\"bye\"")));
+    }
+
+    fn constants(ratio: &str) -> String {
+        format!(
+            r#"
+.class public Lcom/example/Constants;
+.super Ljava/lang/Object;
+
+.field public static final NAME:Ljava/lang/String; = "constants"
+.field public static final RATIO:F = {}
+.field public static final OWNER:Ljava/lang/Class; = Lcom/example/Aaa;
+"#,
+            ratio
+        )
+    }
+
+    #[test]
+    fn dex_field_values_are_compared_structurally() {
+        let old = class_of(&test_support::dex_file(&[&constants("NaNf")]), "Lcom/example/Constants;");
+        // 新增的类改变了字符串和类型的下标，NaN 与自身比较也应当相等
+        let new = class_of(&test_support::dex_file(&[&constants("NaNf"), OTHER]), "Lcom/example/Constants;");
+        assert!(diff_class("com/example/Constants", &old, &new).is_none());
+
+        let changed = class_of(&test_support::dex_file(&[&constants("1.5f")]), "Lcom/example/Constants;");
+        let diff = diff_class("com/example/Constants", &old, &changed).unwrap();
+        let members: Vec<(&str, bool)> = diff.changed_members.iter().map(|member| (member.name.as_str(), member.value_changed)).collect();
+        assert_eq!(members, vec![("RATIO", true)]);
+    }
+}
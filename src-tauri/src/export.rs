@@ -0,0 +1,281 @@
+// Export a project as a directory of decompiled `.java` files plus its resources
+use std::borrow::Cow;
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::android::DexFile;
+use crate::java_analyzer::classpath::Classpath;
+use crate::java_analyzer::decompiler::{format_error, Decompiler};
+use crate::java_analyzer::jar::JarReader;
+use crate::model::ClassModel;
+use crate::project::{Project, ProjectData};
+use crate::rename::{parse_renamed, project_renamer, Renamer};
+
+/// 导出目录中汇总文件的名称
+const SUMMARY_FILE_NAME: &str = "export-summary.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportFailure {
+    /// 类的内部名称，资源文件则为其在归档中的路径
+    pub class_name: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub output_dir: String,
+    pub classes_written: usize,
+    pub resources_written: usize,
+    pub failures: Vec<ExportFailure>,
+}
+
+/// 导出的输入，在项目锁内取出后在锁外读取和写盘
+enum ExportInput {
+    Java(Classpath),
+    Android {
+        apk_path: String,
+        dex_files: Arc<Vec<DexFile>>,
+    },
+}
+
+/// Write every class of the project to `output_dir/sources` and every other file to
+/// `output_dir/resources`. Classes and members carry the project's renames. The summary is also
+/// saved as `export-summary.json`.
+#[tauri::command(async)]
+pub fn export_project_sources(project_id: String, output_dir: String) -> Result<ExportSummary, String> {
+    let renamer = project_renamer(&project_id)?;
+    let input = Project::with_project_mut(&project_id, |project| match &mut project.data {
+        ProjectData::Java(java_data) => Ok(ExportInput::Java(java_data.classpath.clone())),
+        ProjectData::Android(android_data) => {
+            android_data.ensure_analyzed()?;
+            Ok(ExportInput::Android {
+                apk_path: android_data.apk_path.clone(),
+                dex_files: android_data.dex_files.clone(),
+            })
+        }
+        _ => Err("Not a Java or Android project".to_string()),
+    })?;
+
+    let mut exporter = Exporter::new(&output_dir)?;
+    match input {
+        ExportInput::Java(classpath) => {
+            for (file_name, bytes) in classpath.read_all_classes() {
+                let class_name = renamer.class_name(file_name.trim_end_matches(".class"));
+                // 单个类的错误（包括 panic）只记录在汇总中
+                let source = catch_unwind(AssertUnwindSafe(|| {
+                    let class_file = parse_renamed(&bytes, &renamer)?;
+                    Decompiler::new(&class_file)
+                        .decompile_class()
+                        .map_err(|e| format!("Failed to decompile class: {}", format_error(&e)))
+                }))
+                .unwrap_or_else(|_| Err("Decompiler panicked".to_string()));
+                exporter.write_class(&class_name, source);
+            }
+            let resources = classpath.list_entries().into_iter().filter(|entry| !entry.is_class_file && !entry.is_directory);
+            for entry in resources {
+                if let Ok(bytes) = classpath.read_file(&entry.name) {
+                    exporter.write_resource(&entry.name, &bytes);
+                }
+            }
+        }
+        ExportInput::Android { apk_path, dex_files } => {
+            exporter.write_dex_classes(&dex_files, &renamer);
+            let reader = JarReader::new(&apk_path);
+            let names: Vec<String> = reader
+                .list_entries()?
+                .into_iter()
+                .filter(|entry| !entry.is_directory && !is_dex_entry(&entry.name))
+                .map(|entry| entry.name)
+                .collect();
+            for (name, bytes) in reader.read_files(&names)? {
+                exporter.write_resource(&name, &bytes);
+            }
+        }
+    }
+    exporter.finish()
+}
+
+struct Exporter {
+    root: PathBuf,
+    classes_written: usize,
+    resources_written: usize,
+    failures: Vec<ExportFailure>,
+}
+
+impl Exporter {
+    fn new(output_dir: &str) -> Result<Self, String> {
+        let root = PathBuf::from(output_dir);
+        fs::create_dir_all(&root).map_err(|e| format!("Failed to create {}: {}", output_dir, e))?;
+        Ok(Exporter {
+            root,
+            classes_written: 0,
+            resources_written: 0,
+            failures: Vec::new(),
+        })
+    }
+
+    /// 按包结构写出 `sources/com/foo/Bar.java`，反编译失败时不写文件
+    fn write_class(&mut self, class_name: &str, source: Result<String, String>) {
+        let result = source.and_then(|source| {
+            let path = self.target_path("sources", &format!("{}.java", class_name))?;
+            write_file(&path, source.as_bytes())
+        });
+        match result {
+            Ok(()) => self.classes_written += 1,
+            Err(error) => self.failures.push(ExportFailure {
+                class_name: class_name.to_string(),
+                error,
+            }),
+        }
+    }
+
+    /// 与 JVM 类相同，单个类的错误（包括 panic）只记录在汇总中
+    fn write_dex_classes(&mut self, dex_files: &[DexFile], renamer: &Renamer) {
+        for dex_file in dex_files {
+            // 改写过的 id 表每个 DEX 只复制一次
+            let ids = renamer.dex_ids(dex_file);
+            for class_def in &dex_file.classes {
+                let mut class_def = Cow::Borrowed(class_def);
+                if !renamer.is_empty() {
+                    renamer.dex_class(class_def.to_mut());
+                }
+                let class_name = descriptor_to_class_name(&class_def.class_type.descriptor);
+                let source = catch_unwind(AssertUnwindSafe(|| ClassModel::Dex(&ids, &class_def).decompile()))
+                    .unwrap_or_else(|_| Err("Decompiler panicked".to_string()));
+                self.write_class(&class_name, source);
+            }
+        }
+    }
+
+    fn write_resource(&mut self, name: &str, bytes: &[u8]) {
+        let result = self.target_path("resources", name).and_then(|path| write_file(&path, bytes));
+        match result {
+            Ok(()) => self.resources_written += 1,
+            Err(error) => self.failures.push(ExportFailure {
+                class_name: name.to_string(),
+                error,
+            }),
+        }
+    }
+
+    /// 归档中的路径只允许普通组成部分，防止写到导出目录之外
+    fn target_path(&self, section: &str, name: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(name);
+        if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+            return Err(format!("Refusing to write unsafe path {}", name));
+        }
+        Ok(self.root.join(section).join(relative))
+    }
+
+    fn finish(self) -> Result<ExportSummary, String> {
+        let summary = ExportSummary {
+            output_dir: self.root.to_string_lossy().to_string(),
+            classes_written: self.classes_written,
+            resources_written: self.resources_written,
+            failures: self.failures,
+        };
+        let json = serde_json::to_string_pretty(&summary).map_err(|e| e.to_string())?;
+        write_file(&self.root.join(SUMMARY_FILE_NAME), json.as_bytes())?;
+        Ok(summary)
+    }
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(path, bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// `classes.dex`、`classes2.dex` 等已经作为源码导出
+fn is_dex_entry(name: &str) -> bool {
+    !name.contains('/') && name.starts_with("classes") && name.ends_with(".dex")
+}
+
+/// `Lcom/foo/Bar;` -> `com/foo/Bar`
+fn descriptor_to_class_name(descriptor: &str) -> String {
+    descriptor
+        .strip_prefix('L')
+        .and_then(|d| d.strip_suffix(';'))
+        .unwrap_or(descriptor)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+    use crate::rename::{Rename, SymbolKind};
+
+    #[test]
+    fn dex_classes_are_exported_as_decompiled_sources() {
+        let dex_files = vec![dex_file(&[r#"
+.class public Lcom/example/Greeter;
+.super Ljava/lang/Object;
+
+.method public static greet()Ljava/lang/String;
+    .registers 1
+    const-string v0, "hello"
+    return-object v0
+.end method
+"#])];
+        let root = std::env::temp_dir().join(format!("export-dex-{}", std::process::id()));
+        let mut exporter = Exporter::new(&root.to_string_lossy()).unwrap();
+        exporter.write_dex_classes(&dex_files, &Renamer::default());
+        let summary = exporter.finish().unwrap();
+        let source = fs::read_to_string(root.join("sources/com/example/Greeter.java"));
+        fs::remove_dir_all(&root).ok();
+        assert_eq!(summary.classes_written, 1);
+        assert!(summary.failures.is_empty());
+        let source = source.unwrap();
+        assert!(source.contains("return \"hello\";"), "{}", source);
+        assert!(!source.contains("UnsupportedOperationException"));
+    }
+
+    #[test]
+    fn exported_dex_sources_use_the_renamed_names() {
+        let dex_files = vec![dex_file(&[r#"
+.class public La/a;
+.super Ljava/lang/Object;
+
+.method public static b()I
+    .registers 1
+    invoke-static {}, La/a;->b()I
+    move-result v0
+    return v0
+.end method
+"#])];
+        let mut renamer = Renamer::default();
+        for (kind, name, descriptor, new_name) in [
+            (SymbolKind::Class, None, None, "com/example/Counter"),
+            (SymbolKind::Method, Some("b"), Some("()I"), "next"),
+        ] {
+            renamer
+                .map
+                .insert(Rename {
+                    kind,
+                    class_name: "a/a".to_string(),
+                    name: name.map(str::to_string),
+                    descriptor: descriptor.map(str::to_string),
+                    new_name: new_name.to_string(),
+                })
+                .unwrap();
+        }
+        let root = std::env::temp_dir().join(format!("export-renamed-{}", std::process::id()));
+        let mut exporter = Exporter::new(&root.to_string_lossy()).unwrap();
+        exporter.write_dex_classes(&dex_files, &renamer);
+        exporter.finish().unwrap();
+        let source = fs::read_to_string(root.join("sources/com/example/Counter.java"));
+        let original_exists = root.join("sources/a/a.java").exists();
+        fs::remove_dir_all(&root).ok();
+        let source = source.unwrap();
+        assert!(!original_exists);
+        assert!(source.contains("package com.example;"), "{}", source);
+        assert!(source.contains("public static int next()"), "{}", source);
+        assert!(source.contains("return com.example.Counter.next();"), "{}", source);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::android::DexSection;
+use crate::android_analyzer::dex_analyzer::DexAnalyzer;
+use crate::android_analyzer::dex_verifier::{DexIntegrityReport, DexVerifier};
 use crate::project::{PROJECTS, ProjectData};
 use std::{fs::File, io::{Read, Seek, SeekFrom}};
 
@@ -54,4 +57,43 @@ pub fn hex_project_read_page(project_id: &str, page: usize, page_size: usize) ->
         }
         _ => Err("Not a hex project".to_string())
     }
-}
\ No newline at end of file
+}
+
+/// Section table (map_list) of a DEX file opened as a hex project, with offsets and sizes
+#[tauri::command]
+pub fn hex_project_dex_sections(project_id: &str) -> Result<Vec<DexSection>, String> {
+    let mut projects = PROJECTS.lock().unwrap();
+    let project = projects.get_mut(project_id).ok_or("Project not found")?;
+
+    match &mut project.data {
+        ProjectData::Hex(hex_data) => {
+            if let Some(file) = &mut hex_data.file {
+                DexAnalyzer::new(file).read_sections().map_err(|e| e.to_string())
+            } else {
+                Err("File not opened".to_string())
+            }
+        }
+        _ => Err("Not a hex project".to_string())
+    }
+}
+
+/// Verify a DEX file opened as a hex project: checksum, signature, id ranges, section layout and tamper traces
+#[tauri::command]
+pub fn hex_project_verify_dex(project_id: &str) -> Result<DexIntegrityReport, String> {
+    let mut projects = PROJECTS.lock().unwrap();
+    let project = projects.get_mut(project_id).ok_or("Project not found")?;
+
+    match &mut project.data {
+        ProjectData::Hex(hex_data) => {
+            if let Some(file) = &mut hex_data.file {
+                let mut bytes = Vec::new();
+                file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+                file.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+                Ok(DexVerifier::new(&bytes).verify())
+            } else {
+                Err("File not opened".to_string())
+            }
+        }
+        _ => Err("Not a hex project".to_string())
+    }
+}
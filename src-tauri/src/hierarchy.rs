@@ -0,0 +1,500 @@
+// Class hierarchy index shared by Java and Android projects
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::android::{DexFile, ProtoDescriptor};
+use crate::java_analyzer::classpath::Classpath;
+use crate::model::ClassModel;
+use crate::project::{cached_index, ProjectClasses, ProjectData};
+
+const ACC_PUBLIC: u32 = 0x0001;
+const ACC_PRIVATE: u32 = 0x0002;
+const ACC_PROTECTED: u32 = 0x0004;
+const ACC_STATIC: u32 = 0x0008;
+const ACC_INTERFACE: u32 = 0x0200;
+
+/// A method declared by a type, with a JVM style descriptor such as `(ILjava/lang/String;)V`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodInfo {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u32,
+}
+
+impl MethodInfo {
+    /// 构造器、静态方法和私有方法不参与重写；包私有方法只能被同一个包中的子类重写
+    fn is_overridable(&self, declaring_class: &str, subclass: &str) -> bool {
+        self.access_flags & (ACC_PRIVATE | ACC_STATIC) == 0
+            && self.name != "<init>"
+            && self.name != "<clinit>"
+            && (self.access_flags & (ACC_PUBLIC | ACC_PROTECTED) != 0 || package_name(declaring_class) == package_name(subclass))
+    }
+}
+
+/// One class or interface of the project. Names are internal names (`java/lang/String`).
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeNode {
+    pub name: String,
+    pub super_name: Option<String>,
+    pub interfaces: Vec<String>,
+    pub access_flags: u32,
+    pub methods: Vec<MethodInfo>,
+}
+
+impl TypeNode {
+    pub fn is_interface(&self) -> bool {
+        self.access_flags & ACC_INTERFACE != 0
+    }
+}
+
+/// A supertype referenced by project classes that is not defined in the project.
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingSupertype {
+    pub name: String,
+    pub referenced_by: Vec<String>,
+}
+
+/// A method that overrides (or is overridden by) the queried method.
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodOverride {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+}
+
+/// Summary of a type for the type hierarchy panel.
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeSummary {
+    pub name: String,
+    pub is_interface: bool,
+    /// false if the type is only referenced and not defined in the project
+    pub defined: bool,
+}
+
+/// Index of super/sub type relations over every class of a project.
+#[derive(Debug, Clone, Default)]
+pub struct ClassHierarchy {
+    pub types: BTreeMap<String, TypeNode>,
+    // super class -> direct subclasses
+    subclasses: HashMap<String, Vec<String>>,
+    // interface -> classes/interfaces listing it directly
+    implementers: HashMap<String, Vec<String>>,
+}
+
+impl ClassHierarchy {
+    pub fn new(nodes: Vec<TypeNode>) -> Self {
+        let mut hierarchy = ClassHierarchy::default();
+        for node in nodes {
+            // 按优先级先出现的定义生效
+            hierarchy.types.entry(node.name.clone()).or_insert(node);
+        }
+        for node in hierarchy.types.values() {
+            if let Some(super_name) = &node.super_name {
+                hierarchy.subclasses.entry(super_name.clone()).or_default().push(node.name.clone());
+            }
+            for interface in &node.interfaces {
+                hierarchy.implementers.entry(interface.clone()).or_default().push(node.name.clone());
+            }
+        }
+        hierarchy
+    }
+
+    /// 从 classpath 中的所有 class 文件构建索引，无法解析的 class 会被跳过
+    pub fn from_classpath(classpath: &Classpath) -> Self {
+        ClassHierarchy::from_classes(classpath.class_files().iter().map(ClassModel::Jvm))
+    }
+
+    /// 从 DEX 文件中的所有 ClassDef 构建索引
+    pub fn from_dex_files(dex_files: &[DexFile]) -> Self {
+        ClassHierarchy::from_classes(ClassModel::dex_classes(dex_files))
+    }
+
+    /// 逐个类读取项目，只保留类型信息；无法解析的类会被跳过
+    pub(crate) fn from_project(classes: &ProjectClasses) -> Self {
+        let mut nodes = Vec::new();
+        classes.for_each_class(|class| nodes.extend(type_node(&class)));
+        ClassHierarchy::new(nodes)
+    }
+
+    pub(crate) fn from_classes<'a>(classes: impl IntoIterator<Item = ClassModel<'a>>) -> Self {
+        ClassHierarchy::new(classes.into_iter().filter_map(|class| type_node(&class)).collect())
+    }
+
+    pub fn get(&self, class_name: &str) -> Option<&TypeNode> {
+        self.types.get(&normalize_class_name(class_name))
+    }
+
+    fn summary(&self, name: &str) -> TypeSummary {
+        match self.types.get(name) {
+            Some(node) => TypeSummary {
+                name: name.to_string(),
+                is_interface: node.is_interface(),
+                defined: true,
+            },
+            None => TypeSummary {
+                name: name.to_string(),
+                is_interface: false,
+                defined: false,
+            },
+        }
+    }
+
+    /// 所有父类和接口（传递闭包），按广度优先顺序
+    pub fn supertypes(&self, class_name: &str) -> Vec<TypeSummary> {
+        let start = normalize_class_name(class_name);
+        self.walk(&start, |name| {
+            self.types
+                .get(name)
+                .map(|node| node.super_name.iter().chain(node.interfaces.iter()).cloned().collect())
+                .unwrap_or_default()
+        })
+        .iter()
+        .map(|name| self.summary(name))
+        .collect()
+    }
+
+    /// 父类链，从直接父类一直到根类
+    pub fn superclass_chain(&self, class_name: &str) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut current = normalize_class_name(class_name);
+        while let Some(super_name) = self.types.get(&current).and_then(|node| node.super_name.clone()) {
+            if chain.contains(&super_name) {
+                break;
+            }
+            chain.push(super_name.clone());
+            current = super_name;
+        }
+        chain
+    }
+
+    /// 直接或间接的子类型（包括实现类和子接口）
+    pub fn subtypes(&self, class_name: &str, transitive: bool) -> Vec<TypeSummary> {
+        let start = normalize_class_name(class_name);
+        let direct = |name: &str| -> Vec<String> {
+            self.subclasses
+                .get(name)
+                .into_iter()
+                .chain(self.implementers.get(name))
+                .flatten()
+                .cloned()
+                .collect()
+        };
+        let names = if transitive {
+            self.walk(&start, direct)
+        } else {
+            direct(&start)
+        };
+        names.iter().map(|name| self.summary(name)).collect()
+    }
+
+    /// 实现了某个接口的所有非接口类（包括通过父类或子接口间接实现的）
+    pub fn implementers(&self, interface_name: &str) -> Vec<TypeSummary> {
+        self.subtypes(interface_name, true)
+            .into_iter()
+            .filter(|summary| summary.defined && !summary.is_interface)
+            .collect()
+    }
+
+    /// 子类型中重写了指定方法的方法
+    pub fn overriding_methods(&self, class_name: &str, method_name: &str, descriptor: &str) -> Vec<MethodOverride> {
+        let class_name = normalize_class_name(class_name);
+        // 未在项目中定义的方法按可以被任意子类重写处理
+        let base = self.types.get(&class_name).and_then(|node| find_method(node, method_name, descriptor));
+        self.subtypes(&class_name, true)
+            .iter()
+            .filter_map(|summary| self.types.get(&summary.name))
+            .filter(|node| base.is_none_or(|base| base.is_overridable(&class_name, &node.name)))
+            .filter_map(|node| self.find_overridable(node, method_name, descriptor, &node.name))
+            .collect()
+    }
+
+    /// 父类型中被指定方法重写的方法
+    pub fn overridden_methods(&self, class_name: &str, method_name: &str, descriptor: &str) -> Vec<MethodOverride> {
+        let class_name = normalize_class_name(class_name);
+        self.supertypes(&class_name)
+            .iter()
+            .filter_map(|summary| self.types.get(&summary.name))
+            .filter_map(|node| self.find_overridable(node, method_name, descriptor, &class_name))
+            .collect()
+    }
+
+    /// `node` 中声明的、可以被 `subclass` 重写的方法
+    fn find_overridable(&self, node: &TypeNode, method_name: &str, descriptor: &str, subclass: &str) -> Option<MethodOverride> {
+        find_method(node, method_name, descriptor)
+            .filter(|m| m.is_overridable(&node.name, subclass))
+            .map(|m| MethodOverride {
+                class_name: node.name.clone(),
+                method_name: m.name.clone(),
+                descriptor: m.descriptor.clone(),
+            })
+    }
+
+    /// 项目中引用但未定义的父类或接口
+    pub fn missing_supertypes(&self) -> Vec<MissingSupertype> {
+        let mut missing: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for node in self.types.values() {
+            for supertype in node.super_name.iter().chain(node.interfaces.iter()) {
+                if !self.types.contains_key(supertype) {
+                    missing.entry(supertype.clone()).or_default().insert(node.name.clone());
+                }
+            }
+        }
+        missing
+            .into_iter()
+            .map(|(name, referenced_by)| MissingSupertype {
+                name,
+                referenced_by: referenced_by.into_iter().collect(),
+            })
+            .collect()
+    }
+
+    fn walk<F>(&self, start: &str, next: F) -> Vec<String>
+    where
+        F: Fn(&str) -> Vec<String>,
+    {
+        let mut visited = BTreeSet::new();
+        let mut result = Vec::new();
+        let mut queue: VecDeque<String> = next(start).into();
+        while let Some(name) = queue.pop_front() {
+            if name == start || !visited.insert(name.clone()) {
+                continue;
+            }
+            queue.extend(next(&name));
+            result.push(name);
+        }
+        result
+    }
+}
+
+fn find_method<'a>(node: &'a TypeNode, method_name: &str, descriptor: &str) -> Option<&'a MethodInfo> {
+    node.methods.iter().find(|m| m.name == method_name && m.descriptor == descriptor)
+}
+
+/// `com/foo/Bar` -> `com/foo`，默认包为空字符串
+fn package_name(class_name: &str) -> &str {
+    class_name.rsplit_once('/').map_or("", |(package, _)| package)
+}
+
+fn type_node(class: &ClassModel) -> Option<TypeNode> {
+    Some(TypeNode {
+        name: class.name()?,
+        super_name: class.super_name(),
+        interfaces: class.interfaces(),
+        access_flags: class.access_flags(),
+        methods: class
+            .methods()
+            .iter()
+            .map(|method| MethodInfo {
+                name: method.name().to_string(),
+                descriptor: method.descriptor(),
+                access_flags: method.access_flags(),
+            })
+            .collect(),
+    })
+}
+
+/// `Lcom/foo/Bar;` -> `com/foo/Bar`，数组和基本类型保持原样
+pub fn descriptor_to_internal_name(descriptor: &str) -> String {
+    if descriptor.starts_with('L') && descriptor.ends_with(';') {
+        descriptor[1..descriptor.len() - 1].to_string()
+    } else {
+        descriptor.to_string()
+    }
+}
+
+/// 将 DEX proto 转换为 JVM 方法描述符
+pub fn proto_to_descriptor(proto: &ProtoDescriptor) -> String {
+    let parameters: String = proto.parameters.iter().map(|p| p.descriptor.as_str()).collect();
+    format!("({}){}", parameters, proto.return_type.descriptor)
+}
+
+/// 接受 `com.foo.Bar`、`com/foo/Bar` 或 `Lcom/foo/Bar;`
+pub fn normalize_class_name(class_name: &str) -> String {
+    descriptor_to_internal_name(class_name.trim()).replace('.', "/")
+}
+
+/// 取出（必要时在项目锁外构建）项目的类层次索引
+pub(crate) fn project_hierarchy(project_id: &str) -> Result<Arc<ClassHierarchy>, String> {
+    cached_index(
+        project_id,
+        |data| match data {
+            ProjectData::Java(java_data) => Some(&mut java_data.hierarchy),
+            ProjectData::Android(android_data) => Some(&mut android_data.hierarchy),
+            _ => None,
+        },
+        ClassHierarchy::from_project,
+    )
+}
+
+/// 取出项目的类层次索引后执行查询
+pub(crate) fn with_hierarchy<T, F>(project_id: &str, f: F) -> Result<T, String>
+where
+    F: FnOnce(&ClassHierarchy) -> T,
+{
+    let hierarchy = project_hierarchy(project_id)?;
+    Ok(f(&hierarchy))
+}
+
+/// 类型的基本信息（父类、接口、方法）
+#[tauri::command]
+pub fn hierarchy_get_type(project_id: String, class_name: String) -> Result<Option<TypeNode>, String> {
+    with_hierarchy(&project_id, |hierarchy| hierarchy.get(&class_name).cloned())
+}
+
+#[tauri::command]
+pub fn hierarchy_get_supertypes(project_id: String, class_name: String) -> Result<Vec<TypeSummary>, String> {
+    with_hierarchy(&project_id, |hierarchy| hierarchy.supertypes(&class_name))
+}
+
+#[tauri::command]
+pub fn hierarchy_get_subtypes(project_id: String, class_name: String, transitive: bool) -> Result<Vec<TypeSummary>, String> {
+    with_hierarchy(&project_id, |hierarchy| hierarchy.subtypes(&class_name, transitive))
+}
+
+#[tauri::command]
+pub fn hierarchy_get_implementers(project_id: String, interface_name: String) -> Result<Vec<TypeSummary>, String> {
+    with_hierarchy(&project_id, |hierarchy| hierarchy.implementers(&interface_name))
+}
+
+#[tauri::command]
+pub fn hierarchy_get_overriding_methods(project_id: String, class_name: String, method_name: String, descriptor: String) -> Result<Vec<MethodOverride>, String> {
+    with_hierarchy(&project_id, |hierarchy| hierarchy.overriding_methods(&class_name, &method_name, &descriptor))
+}
+
+#[tauri::command]
+pub fn hierarchy_get_overridden_methods(project_id: String, class_name: String, method_name: String, descriptor: String) -> Result<Vec<MethodOverride>, String> {
+    with_hierarchy(&project_id, |hierarchy| hierarchy.overridden_methods(&class_name, &method_name, &descriptor))
+}
+
+#[tauri::command]
+pub fn hierarchy_get_missing_supertypes(project_id: String) -> Result<Vec<MissingSupertype>, String> {
+    with_hierarchy(&project_id, |hierarchy| hierarchy.missing_supertypes())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+
+    /// 方法名指向不存在的常量池项，解析器过去会在这里 panic
+    fn malformed_class() -> Vec<u8> {
+        let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52];
+        // 常量池只有一项 Utf8 "A"
+        bytes.extend([0, 2, 1, 0, 1, b'A']);
+        // access_flags, this_class, super_class, interfaces, fields
+        bytes.extend([0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        // 一个方法，name_index 和 descriptor_index 都是 9
+        bytes.extend([0, 1, 0, 1, 0, 9, 0, 9, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn malformed_classes_do_not_poison_the_project_lock() {
+        let dir = std::env::temp_dir().join(format!("hierarchy-malformed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Bad.class"), malformed_class()).unwrap();
+        let broken = Project::create_project_from_path(dir.to_str().unwrap()).unwrap();
+        let healthy = Project::create_project_from_path(dir.to_str().unwrap()).unwrap();
+
+        assert!(hierarchy_get_subtypes(broken, "java/lang/Object".to_string(), true).unwrap().is_empty());
+        assert!(hierarchy_get_missing_supertypes(healthy).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn node(name: &str, super_name: Option<&str>, interfaces: &[&str], access_flags: u32, methods: &[(&str, u32)]) -> TypeNode {
+        TypeNode {
+            name: name.to_string(),
+            super_name: super_name.map(str::to_string),
+            interfaces: interfaces.iter().map(|i| i.to_string()).collect(),
+            access_flags,
+            methods: methods
+                .iter()
+                .map(|(name, access_flags)| MethodInfo {
+                    name: name.to_string(),
+                    descriptor: "()V".to_string(),
+                    access_flags: *access_flags,
+                })
+                .collect(),
+        }
+    }
+
+    const INTERFACE: u32 = ACC_INTERFACE | 0x0400;
+    const OBJECT: Option<&str> = Some("java/lang/Object");
+
+    /// Task <- Job <- a/Base <- a/Same, b/Other <- b/Deep
+    fn hierarchy() -> ClassHierarchy {
+        ClassHierarchy::new(vec![
+            node("a/Task", None, &[], INTERFACE, &[("run", ACC_PUBLIC)]),
+            node("a/Job", None, &["a/Task"], INTERFACE, &[]),
+            node(
+                "a/Base",
+                OBJECT,
+                &["a/Job"],
+                ACC_PUBLIC,
+                &[("<init>", ACC_PUBLIC), ("run", ACC_PUBLIC), ("hook", ACC_PROTECTED), ("local", 0), ("secret", ACC_PRIVATE), ("create", ACC_STATIC)],
+            ),
+            node("a/Same", Some("a/Base"), &[], ACC_PUBLIC, &[("run", ACC_PUBLIC), ("local", 0), ("secret", ACC_PRIVATE), ("create", ACC_STATIC)]),
+            node("b/Other", Some("a/Base"), &[], ACC_PUBLIC, &[("hook", ACC_PROTECTED), ("local", 0)]),
+            node("b/Deep", Some("b/Other"), &[], ACC_PUBLIC, &[("run", ACC_PUBLIC), ("local", 0)]),
+        ])
+    }
+
+    fn names(summaries: Vec<TypeSummary>) -> Vec<String> {
+        summaries.into_iter().map(|summary| summary.name).collect()
+    }
+
+    fn classes(overrides: Vec<MethodOverride>) -> Vec<String> {
+        let mut classes: Vec<String> = overrides.into_iter().map(|o| o.class_name).collect();
+        classes.sort();
+        classes
+    }
+
+    #[test]
+    fn supertypes_and_subtypes_follow_classes_and_interfaces() {
+        let hierarchy = hierarchy();
+        assert_eq!(names(hierarchy.supertypes("b.Deep")), ["b/Other", "a/Base", "java/lang/Object", "a/Job", "a/Task"]);
+        assert!(!hierarchy.supertypes("b/Deep")[2].defined);
+        assert_eq!(hierarchy.superclass_chain("Lb/Deep;"), ["b/Other", "a/Base", "java/lang/Object"]);
+
+        assert_eq!(names(hierarchy.subtypes("a/Base", false)), ["a/Same", "b/Other"]);
+        assert_eq!(names(hierarchy.subtypes("a/Base", true)), ["a/Same", "b/Other", "b/Deep"]);
+        assert_eq!(names(hierarchy.subtypes("a/Task", false)), ["a/Job"]);
+    }
+
+    #[test]
+    fn implementers_include_indirect_classes_but_not_interfaces() {
+        let hierarchy = hierarchy();
+        assert_eq!(names(hierarchy.implementers("a/Task")), ["a/Base", "a/Same", "b/Other", "b/Deep"]);
+        assert!(hierarchy.implementers("b/Deep").is_empty());
+    }
+
+    #[test]
+    fn overrides_skip_constructors_static_and_private_methods() {
+        let hierarchy = hierarchy();
+        assert_eq!(classes(hierarchy.overriding_methods("a/Task", "run", "()V")), ["a/Base", "a/Same", "b/Deep"]);
+        assert_eq!(classes(hierarchy.overriding_methods("a/Base", "hook", "()V")), ["b/Other"]);
+        assert_eq!(classes(hierarchy.overridden_methods("b/Deep", "run", "()V")), ["a/Base", "a/Task"]);
+        for name in ["<init>", "secret", "create"] {
+            assert!(hierarchy.overridden_methods("a/Same", name, "()V").is_empty(), "{}", name);
+            assert!(hierarchy.overriding_methods("a/Base", name, "()V").is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn package_private_methods_are_only_overridden_within_the_package() {
+        let hierarchy = hierarchy();
+        assert_eq!(classes(hierarchy.overriding_methods("a/Base", "local", "()V")), ["a/Same"]);
+        assert_eq!(classes(hierarchy.overridden_methods("a/Same", "local", "()V")), ["a/Base"]);
+        // b/Other.local 与 b/Deep 在同一个包中
+        assert_eq!(classes(hierarchy.overridden_methods("b/Deep", "local", "()V")), ["b/Other"]);
+
+        let base = &hierarchy.get("a/Base").unwrap().methods[3];
+        assert!(base.is_overridable("a/Base", "a/Same"));
+        assert!(!base.is_overridable("a/Base", "b/Other"));
+        assert!(!base.is_overridable("a/Base", "Default"));
+        assert!(hierarchy.get("a/Base").unwrap().methods[2].is_overridable("a/Base", "b/Other"));
+    }
+}
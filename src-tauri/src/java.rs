@@ -1,50 +1,112 @@
 // Removed unused import of File, since File does not implement Clone, PartialEq, or Eq.
+use std::sync::Arc;
 
-use crate::{java_analyzer::jar::{JarEntry, JarReader}, project::Project}; // Add this import if ZipEntry comes from the 'zip' crate
-use crate::java_analyzer::disassembler::disassemble_classfile;
+use crate::{java_analyzer::jar::JarEntry, project::Project}; // Add this import if ZipEntry comes from the 'zip' crate
+use crate::java_analyzer::classpath::{ClassLocation, Classpath, ClasspathEntryInfo, ClasspathPrecedence, DuplicateClass, SplitPackage};
+use crate::java_analyzer::disassembler::ClassFileDisassembler;
+use crate::model::ClassModel;
+use crate::rename::{parse_renamed, project_renamer, Renamer};
+use crate::hierarchy::ClassHierarchy;
+use crate::xref::XrefIndex;
 
 pub struct JavaProjectData {
-    pub jar_reader:JarReader,
+    pub classpath: Classpath,
     pub class_files: Vec<JarEntry>,
+    pub hierarchy: Option<Arc<ClassHierarchy>>,
+    pub xrefs: Option<Arc<XrefIndex>>,
+    /// Bumped when the classpath changes, see `ProjectData::revision`
+    pub revision: u64,
 }
 
 impl JavaProjectData {
-    pub fn new(jar_path: String) -> Self {
-        // 创建 JAR 读取器并扫描文件
-        let jar_reader = crate::java_analyzer::jar::JarReader::new(&jar_path);
-        let class_files = jar_reader.list_entries().unwrap_or_default();
+    pub fn new(jar_path: String) -> Result<Self, String> {
+        // 创建 classpath，初始只包含打开的 jar / 目录 / class 文件
+        let mut classpath = Classpath::new();
+        classpath.add_path(&jar_path)?;
+        let class_files = classpath.list_entries();
 
-        JavaProjectData {
-            jar_reader,
+        Ok(JavaProjectData {
+            classpath,
             class_files,
-        }
+            hierarchy: None,
+            xrefs: None,
+            revision: 0,
+        })
+    }
+
+    /// classpath 变化后刷新文件列表，并丢弃基于旧 classpath 的索引
+    pub fn refresh(&mut self) {
+        self.class_files = self.classpath.list_entries();
+        self.hierarchy = None;
+        self.xrefs = None;
+        self.revision += 1;
     }
 }
 
+fn with_java_project<T, F>(project_id: &str, f: F) -> Result<T, String>
+where
+    F: FnOnce(&JavaProjectData) -> Result<T, String>,
+{
+    Project::with_project(project_id, |project| {
+        if let crate::project::ProjectData::Java(java_data) = &project.data {
+            f(java_data)
+        } else {
+            Err("Not a Java project".to_string())
+        }
+    })
+}
+
+fn with_java_project_mut<T, F>(project_id: &str, f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut JavaProjectData) -> Result<T, String>,
+{
+    Project::with_project_mut(project_id, |project| {
+        if let crate::project::ProjectData::Java(java_data) = &mut project.data {
+            let result = f(java_data);
+            java_data.refresh();
+            result
+        } else {
+            Err("Not a Java project".to_string())
+        }
+    })
+}
+
 #[tauri::command]
 pub fn java_project_list_files(project_id: String) -> Vec<String> {
+    let Ok(renamer) = project_renamer(&project_id) else {
+        return Vec::new();
+    };
     Project::with_project(&project_id, |project: &Project| {
         if let crate::project::ProjectData::Java(java_data) = &project.data {
-            Ok(java_data.class_files.iter().map(|entry| entry.name.clone()).collect())
+            Ok(java_data.class_files.iter().map(|entry| listed_name(entry, &renamer)).collect())
         } else {
             Err("Not a Java project".to_string())
         }
     })
     .unwrap_or_else(|_| Vec::new())
-    
+
+}
+
+/// class 文件按重命名后的类名列出，读取时再换回原名
+fn listed_name(entry: &JarEntry, renamer: &Renamer) -> String {
+    match entry.name.strip_suffix(".class") {
+        Some(class_name) if entry.is_class_file => Classpath::class_file_name(&renamer.class_name(class_name)),
+        _ => entry.name.clone(),
+    }
 }
 
 #[tauri::command]
 pub fn java_project_read_file_content(project_id: String, file_name: String) -> Result<String, String> {
+    let renamer = project_renamer(&project_id)?;
     Project::with_project(&project_id, |project| {
         if let crate::project::ProjectData::Java(java_data) = &project.data {
-            if file_name.ends_with(".class") {
-                // 如果是 class 文件，进行反汇编
-                let bytes = java_data.jar_reader.read_file(&file_name)?;
-                disassemble_classfile(bytes)
+            if let Some(class_name) = file_name.strip_suffix(".class") {
+                // 如果是 class 文件，按原名读取，套用重命名后进行反汇编
+                let bytes = java_data.classpath.read_class(&renamer.map.original_class_name(class_name))?;
+                ClassFileDisassembler::from_class_file(parse_renamed(&bytes, &renamer)?).disassemble()
             } else {
                 // 其他文件直接读取为字符串
-                java_data.jar_reader.read_file_as_string(&file_name)
+                java_data.classpath.read_file_as_string(&file_name)
             }
         } else {
             Err("Not a Java project".to_string())
@@ -52,3 +114,110 @@ pub fn java_project_read_file_content(project_id: String, file_name: String) ->
     })
 }
 
+/// 把一个类反编译为 Java 源码
+#[tauri::command]
+pub fn java_project_decompile_class(project_id: String, class_name: String) -> Result<String, String> {
+    let renamer = project_renamer(&project_id)?;
+    with_java_project(&project_id, |java_data| {
+        // 界面上可能显示的是重命名后的类名
+        let bytes = java_data.classpath.read_class(&renamer.map.original_class_name(&class_name))?;
+        let class_file = parse_renamed(&bytes, &renamer)?;
+        ClassModel::Jvm(&class_file).decompile()
+    })
+}
+
+/// 列出 classpath 中的所有条目
+#[tauri::command]
+pub fn java_project_list_classpath(project_id: String) -> Result<Vec<ClasspathEntryInfo>, String> {
+    with_java_project(&project_id, |java_data| Ok(java_data.classpath.entry_infos()))
+}
+
+/// 向 classpath 追加 jar、class 目录或单个 class 文件
+#[tauri::command]
+pub fn java_project_add_classpath_entry(project_id: String, path: String) -> Result<Vec<ClasspathEntryInfo>, String> {
+    with_java_project_mut(&project_id, |java_data| {
+        java_data.classpath.add_path(&path)?;
+        Ok(java_data.classpath.entry_infos())
+    })
+}
+
+#[tauri::command]
+pub fn java_project_remove_classpath_entry(project_id: String, index: usize) -> Result<Vec<ClasspathEntryInfo>, String> {
+    with_java_project_mut(&project_id, |java_data| {
+        java_data.classpath.remove_entry(index)?;
+        Ok(java_data.classpath.entry_infos())
+    })
+}
+
+#[tauri::command]
+pub fn java_project_move_classpath_entry(project_id: String, from: usize, to: usize) -> Result<Vec<ClasspathEntryInfo>, String> {
+    with_java_project_mut(&project_id, |java_data| {
+        java_data.classpath.move_entry(from, to)?;
+        Ok(java_data.classpath.entry_infos())
+    })
+}
+
+/// 设置重复类的解析优先级（FirstWins / LastWins）
+#[tauri::command]
+pub fn java_project_set_classpath_precedence(project_id: String, precedence: String) -> Result<(), String> {
+    with_java_project_mut(&project_id, |java_data| {
+        java_data.classpath.precedence = ClasspathPrecedence::parse(&precedence)?;
+        Ok(())
+    })
+}
+
+/// 解析类名，返回提供该类的 classpath 条目
+#[tauri::command]
+pub fn java_project_resolve_class(project_id: String, class_name: String) -> Result<Option<ClassLocation>, String> {
+    with_java_project(&project_id, |java_data| {
+        Ok(java_data.classpath.resolve_class(&class_name).cloned())
+    })
+}
+
+/// 报告重复定义的类（shaded 副本、冲突版本）
+#[tauri::command]
+pub fn java_project_find_duplicate_classes(project_id: String) -> Result<Vec<DuplicateClass>, String> {
+    with_java_project(&project_id, |java_data| Ok(java_data.classpath.find_duplicate_classes()))
+}
+
+/// 报告 split package
+#[tauri::command]
+pub fn java_project_find_split_packages(project_id: String) -> Result<Vec<SplitPackage>, String> {
+    with_java_project(&project_id, |java_data| Ok(java_data.classpath.find_split_packages()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rename::{Rename, SymbolKind};
+
+    fn entry(name: &str) -> JarEntry {
+        JarEntry {
+            name: name.to_string(),
+            size: 0,
+            is_directory: false,
+            is_class_file: name.ends_with(".class"),
+        }
+    }
+
+    #[test]
+    fn listed_class_files_use_renamed_class_names() {
+        let mut renamer = Renamer::default();
+        renamer
+            .map
+            .insert(Rename {
+                kind: SymbolKind::Class,
+                class_name: "a/b".to_string(),
+                name: None,
+                descriptor: None,
+                new_name: "com/example/Client".to_string(),
+            })
+            .unwrap();
+        let listed = listed_name(&entry("a/b.class"), &renamer);
+        assert_eq!(listed, "com/example/Client.class");
+        assert_eq!(listed_name(&entry("a/c.class"), &renamer), "a/c.class");
+        assert_eq!(listed_name(&entry("META-INF/MANIFEST.MF"), &renamer), "META-INF/MANIFEST.MF");
+        // 读取时按原名查找
+        assert_eq!(renamer.map.original_class_name(listed.strip_suffix(".class").unwrap()), "a/b");
+    }
+}
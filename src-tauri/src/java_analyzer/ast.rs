@@ -0,0 +1,810 @@
+// 反编译器的中间表示：Java 表达式 / 语句树，以及把它们打印成 Java 源码的 JavaWriter
+//
+// 类型统一使用字段描述符（`I`、`Ljava/lang/String;`、`[J`），类名使用内部名称（`java/lang/String`），
+// 只在打印时转换成 Java 写法，这样 JVM 和 Dalvik 两个前端可以共用同一套树。
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Bool(bool),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Char(u16),
+    String(String),
+    /// `Foo.class`，保存字段描述符
+    Class(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Shl,
+    Shr,
+    Ushr,
+    And,
+    Or,
+    Xor,
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Gt,
+    Le,
+    LogicalAnd,
+    LogicalOr,
+    /// lcmp
+    CompareLong,
+    /// fcmpl / fcmpg / dcmpl / dcmpg
+    CompareFloat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvokeKind {
+    Static,
+    Virtual,
+    Special,
+    Interface,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Literal),
+    Local(String),
+    This,
+    Super,
+    Field {
+        /// None 表示静态字段
+        target: Option<Box<Expr>>,
+        owner: String,
+        name: String,
+        descriptor: String,
+    },
+    ArrayElement {
+        array: Box<Expr>,
+        index: Box<Expr>,
+    },
+    ArrayLength(Box<Expr>),
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Cast {
+        descriptor: String,
+        operand: Box<Expr>,
+    },
+    InstanceOf {
+        operand: Box<Expr>,
+        descriptor: String,
+    },
+    Invoke {
+        kind: InvokeKind,
+        /// None 表示静态调用
+        target: Option<Box<Expr>>,
+        owner: String,
+        name: String,
+        descriptor: String,
+        args: Vec<Expr>,
+    },
+    /// `new Foo(args)`
+    New {
+        class_name: String,
+        descriptor: String,
+        args: Vec<Expr>,
+    },
+    /// `new` 之后、`<init>` 之前尚未初始化的对象
+    Uninitialized(String),
+    /// `new int[a][b][]`，descriptor 为元素类型
+    NewArray {
+        descriptor: String,
+        dimensions: Vec<Expr>,
+        extra_dimensions: usize,
+    },
+    /// `new int[] {a, b}`
+    ArrayInit {
+        descriptor: String,
+        values: Vec<Expr>,
+    },
+    Ternary {
+        cond: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    /// `Owner::name` 或 `target::name`
+    MethodRef {
+        target: Option<Box<Expr>>,
+        owner: String,
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchCase {
+    /// 为空表示 default
+    pub values: Vec<i32>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatchClause {
+    /// 捕获的类名，多个表示 multi-catch，为空表示 catch-any（finally 的实现方式）
+    pub class_names: Vec<String>,
+    pub variable: String,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Expr(Expr),
+    Assign {
+        target: Expr,
+        value: Expr,
+    },
+    /// 局部变量声明，descriptor 为字段描述符
+    Declare {
+        descriptor: String,
+        name: String,
+        value: Option<Expr>,
+    },
+    Return(Option<Expr>),
+    Throw(Expr),
+    If {
+        cond: Expr,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+    While {
+        label: Option<String>,
+        cond: Expr,
+        body: Vec<Stmt>,
+    },
+    DoWhile {
+        label: Option<String>,
+        body: Vec<Stmt>,
+        cond: Expr,
+    },
+    Switch {
+        label: Option<String>,
+        selector: Expr,
+        cases: Vec<SwitchCase>,
+    },
+    Try {
+        body: Vec<Stmt>,
+        catches: Vec<CatchClause>,
+    },
+    Break(Option<String>),
+    Continue(Option<String>),
+    /// 基本块起点，只有在被 Goto 引用时才会打印
+    Label(u32),
+    /// 无法结构化的跳转，打印为注释
+    Goto(u32),
+    Comment(String),
+}
+
+impl Expr {
+    pub fn int(value: i32) -> Expr {
+        Expr::Literal(Literal::Int(value))
+    }
+
+    pub fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+        Expr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn not(operand: Expr) -> Expr {
+        Expr::Unary {
+            op: UnaryOp::Not,
+            operand: Box::new(operand),
+        }
+    }
+
+    /// 条件取反，比较运算直接翻转运算符
+    pub fn negate(self) -> Expr {
+        match self {
+            Expr::Unary { op: UnaryOp::Not, operand } => *operand,
+            Expr::Literal(Literal::Bool(value)) => Expr::Literal(Literal::Bool(!value)),
+            Expr::Binary { op, left, right } => {
+                let negated = match op {
+                    BinaryOp::Eq => Some(BinaryOp::Ne),
+                    BinaryOp::Ne => Some(BinaryOp::Eq),
+                    BinaryOp::Lt => Some(BinaryOp::Ge),
+                    BinaryOp::Ge => Some(BinaryOp::Lt),
+                    BinaryOp::Gt => Some(BinaryOp::Le),
+                    BinaryOp::Le => Some(BinaryOp::Gt),
+                    BinaryOp::LogicalAnd => {
+                        return Expr::binary(BinaryOp::LogicalOr, left.negate(), right.negate());
+                    }
+                    BinaryOp::LogicalOr => {
+                        return Expr::binary(BinaryOp::LogicalAnd, left.negate(), right.negate());
+                    }
+                    _ => None,
+                };
+                match negated {
+                    Some(op) => Expr::Binary { op, left, right },
+                    None => Expr::not(Expr::Binary { op, left, right }),
+                }
+            }
+            other => Expr::not(other),
+        }
+    }
+
+    /// 表达式是否可能有副作用（调用、对象创建）
+    pub fn has_side_effects(&self) -> bool {
+        let mut found = false;
+        self.visit(&mut |expr| {
+            if matches!(expr, Expr::Invoke { .. } | Expr::New { .. } | Expr::Uninitialized(_)) {
+                found = true;
+            }
+        });
+        found
+    }
+
+    /// 是否可以安全地复制（dup）而不重复求值
+    pub fn is_trivial(&self) -> bool {
+        matches!(self, Expr::Literal(_) | Expr::Local(_) | Expr::This | Expr::Uninitialized(_))
+    }
+
+    pub fn reads_local(&self, name: &str) -> bool {
+        let mut found = false;
+        self.visit(&mut |expr| {
+            if matches!(expr, Expr::Local(local) if local == name) {
+                found = true;
+            }
+        });
+        found
+    }
+
+    /// 先序遍历所有子表达式
+    pub fn visit(&self, f: &mut dyn FnMut(&Expr)) {
+        f(self);
+        match self {
+            Expr::Field { target: Some(target), .. } => target.visit(f),
+            Expr::ArrayElement { array, index } => {
+                array.visit(f);
+                index.visit(f);
+            }
+            Expr::ArrayLength(operand)
+            | Expr::Unary { operand, .. }
+            | Expr::Cast { operand, .. }
+            | Expr::InstanceOf { operand, .. } => operand.visit(f),
+            Expr::Binary { left, right, .. } => {
+                left.visit(f);
+                right.visit(f);
+            }
+            Expr::Invoke { target, args, .. } => {
+                if let Some(target) = target {
+                    target.visit(f);
+                }
+                args.iter().for_each(|arg| arg.visit(f));
+            }
+            Expr::New { args, .. } => args.iter().for_each(|arg| arg.visit(f)),
+            Expr::NewArray { dimensions, .. } => dimensions.iter().for_each(|d| d.visit(f)),
+            Expr::ArrayInit { values, .. } => values.iter().for_each(|v| v.visit(f)),
+            Expr::Ternary { cond, then, otherwise } => {
+                cond.visit(f);
+                then.visit(f);
+                otherwise.visit(f);
+            }
+            Expr::MethodRef { target: Some(target), .. } => target.visit(f),
+            _ => {}
+        }
+    }
+
+    /// 把所有 `Local(name)` 替换为 replacement
+    pub fn replace_local(&mut self, name: &str, replacement: &Expr) {
+        self.visit_mut(&mut |expr| {
+            if matches!(expr, Expr::Local(local) if local == name) {
+                *expr = replacement.clone();
+            }
+        });
+    }
+
+    /// 后序遍历并允许修改所有子表达式
+    pub fn visit_mut(&mut self, f: &mut dyn FnMut(&mut Expr)) {
+        match self {
+            Expr::Field { target: Some(target), .. } => target.visit_mut(f),
+            Expr::ArrayElement { array, index } => {
+                array.visit_mut(f);
+                index.visit_mut(f);
+            }
+            Expr::ArrayLength(operand)
+            | Expr::Unary { operand, .. }
+            | Expr::Cast { operand, .. }
+            | Expr::InstanceOf { operand, .. } => operand.visit_mut(f),
+            Expr::Binary { left, right, .. } => {
+                left.visit_mut(f);
+                right.visit_mut(f);
+            }
+            Expr::Invoke { target, args, .. } => {
+                if let Some(target) = target {
+                    target.visit_mut(f);
+                }
+                args.iter_mut().for_each(|arg| arg.visit_mut(f));
+            }
+            Expr::New { args, .. } => args.iter_mut().for_each(|arg| arg.visit_mut(f)),
+            Expr::NewArray { dimensions, .. } => dimensions.iter_mut().for_each(|d| d.visit_mut(f)),
+            Expr::ArrayInit { values, .. } => values.iter_mut().for_each(|v| v.visit_mut(f)),
+            Expr::Ternary { cond, then, otherwise } => {
+                cond.visit_mut(f);
+                then.visit_mut(f);
+                otherwise.visit_mut(f);
+            }
+            Expr::MethodRef { target: Some(target), .. } => target.visit_mut(f),
+            _ => {}
+        }
+        f(self);
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Ternary { .. } => 2,
+            Expr::Binary { op, .. } => match op {
+                BinaryOp::LogicalOr => 3,
+                BinaryOp::LogicalAnd => 4,
+                BinaryOp::Or => 5,
+                BinaryOp::Xor => 6,
+                BinaryOp::And => 7,
+                BinaryOp::Eq | BinaryOp::Ne => 8,
+                BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Gt | BinaryOp::Le => 9,
+                BinaryOp::Shl | BinaryOp::Shr | BinaryOp::Ushr => 10,
+                BinaryOp::Add | BinaryOp::Sub => 11,
+                BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 12,
+                BinaryOp::CompareLong | BinaryOp::CompareFloat => 15,
+            },
+            Expr::InstanceOf { .. } => 9,
+            Expr::Unary { .. } | Expr::Cast { .. } => 13,
+            Expr::Literal(Literal::Int(v)) if *v < 0 => 13,
+            Expr::Literal(Literal::Long(v)) if *v < 0 => 13,
+            Expr::Literal(Literal::Float(v)) if *v < 0.0 => 13,
+            Expr::Literal(Literal::Double(v)) if *v < 0.0 => 13,
+            _ => 15,
+        }
+    }
+}
+
+impl Stmt {
+    /// 遍历语句树中的所有语句（含嵌套）
+    pub fn visit(&self, f: &mut dyn FnMut(&Stmt)) {
+        f(self);
+        let visit_all = |stmts: &Vec<Stmt>, f: &mut dyn FnMut(&Stmt)| stmts.iter().for_each(|s| s.visit(f));
+        match self {
+            Stmt::If { then, otherwise, .. } => {
+                visit_all(then, f);
+                visit_all(otherwise, f);
+            }
+            Stmt::While { body, .. } | Stmt::DoWhile { body, .. } => visit_all(body, f),
+            Stmt::Switch { cases, .. } => cases.iter().for_each(|case| visit_all(&case.body, f)),
+            Stmt::Try { body, catches } => {
+                visit_all(body, f);
+                catches.iter().for_each(|catch| visit_all(&catch.body, f));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 字段描述符转 Java 类型写法：`[Ljava/lang/String;` -> `String[]`
+pub fn java_type(descriptor: &str) -> String {
+    let dimensions = descriptor.chars().take_while(|c| *c == '[').count();
+    let element = &descriptor[dimensions..];
+    let base = match element {
+        "B" => "byte".to_string(),
+        "C" => "char".to_string(),
+        "D" => "double".to_string(),
+        "F" => "float".to_string(),
+        "I" => "int".to_string(),
+        "J" => "long".to_string(),
+        "S" => "short".to_string(),
+        "Z" => "boolean".to_string(),
+        "V" => "void".to_string(),
+        _ => java_class_name(element.trim_start_matches('L').trim_end_matches(';')),
+    };
+    format!("{}{}", base, "[]".repeat(dimensions))
+}
+
+/// 内部类名转 Java 写法，java.lang 下的类省略包名
+pub fn java_class_name(internal_name: &str) -> String {
+    match internal_name.strip_prefix("java/lang/") {
+        Some(simple) if !simple.contains('/') => simple.to_string(),
+        _ => internal_name.replace('/', "."),
+    }
+}
+
+/// 内部类名或数组描述符转字段描述符
+pub fn class_descriptor(class_name: &str) -> String {
+    if class_name.starts_with('[') {
+        class_name.to_string()
+    } else {
+        format!("L{};", class_name)
+    }
+}
+
+/// 拆分方法描述符，返回 (参数描述符列表, 返回值描述符)
+pub fn parse_method_descriptor(descriptor: &str) -> (Vec<String>, String) {
+    let mut params = Vec::new();
+    let body = descriptor.strip_prefix('(').unwrap_or(descriptor);
+    let (param_part, return_part) = body.split_once(')').unwrap_or((body, "V"));
+    let chars: Vec<char> = param_part.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        while i < chars.len() && chars[i] == '[' {
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == 'L' {
+            while i < chars.len() && chars[i] != ';' {
+                i += 1;
+            }
+        }
+        i += 1;
+        params.push(chars[start..i.min(chars.len())].iter().collect());
+    }
+    (params, return_part.to_string())
+}
+
+/// long / double 占两个局部变量槽和两个操作数栈槽
+pub fn is_wide(descriptor: &str) -> bool {
+    descriptor == "J" || descriptor == "D"
+}
+
+pub fn escape_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn format_literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Null => "null".to_string(),
+        Literal::Bool(value) => value.to_string(),
+        Literal::Int(value) => value.to_string(),
+        Literal::Long(value) => format!("{}L", value),
+        Literal::Float(value) => {
+            if value.is_nan() {
+                "Float.NaN".to_string()
+            } else if value.is_infinite() {
+                if *value > 0.0 { "Float.POSITIVE_INFINITY" } else { "Float.NEGATIVE_INFINITY" }.to_string()
+            } else {
+                format!("{:?}f", value)
+            }
+        }
+        Literal::Double(value) => {
+            if value.is_nan() {
+                "Double.NaN".to_string()
+            } else if value.is_infinite() {
+                if *value > 0.0 { "Double.POSITIVE_INFINITY" } else { "Double.NEGATIVE_INFINITY" }.to_string()
+            } else {
+                format!("{:?}", value)
+            }
+        }
+        Literal::Char(value) => match char::from_u32(*value as u32) {
+            Some('\'') => "'\\''".to_string(),
+            Some('"') => "'\"'".to_string(),
+            Some(c) if !c.is_control() => format!("'{}'", escape_string(&c.to_string())),
+            _ => format!("'\\u{:04x}'", value),
+        },
+        Literal::String(value) => format!("\"{}\"", escape_string(value)),
+        Literal::Class(descriptor) => format!("{}.class", java_type(descriptor)),
+    }
+}
+
+fn binary_operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Rem => "%",
+        BinaryOp::Shl => "<<",
+        BinaryOp::Shr => ">>",
+        BinaryOp::Ushr => ">>>",
+        BinaryOp::And => "&",
+        BinaryOp::Or => "|",
+        BinaryOp::Xor => "^",
+        BinaryOp::Eq => "==",
+        BinaryOp::Ne => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Ge => ">=",
+        BinaryOp::Gt => ">",
+        BinaryOp::Le => "<=",
+        BinaryOp::LogicalAnd => "&&",
+        BinaryOp::LogicalOr => "||",
+        BinaryOp::CompareLong | BinaryOp::CompareFloat => ",",
+    }
+}
+
+/// 把表达式 / 语句树输出为缩进好的 Java 源码
+pub struct JavaWriter {
+    out: String,
+    indent: usize,
+    /// 被 Goto 引用的块，只有这些 Label 会输出
+    goto_targets: HashSet<u32>,
+}
+
+impl JavaWriter {
+    pub fn new(indent: usize) -> Self {
+        JavaWriter {
+            out: String::new(),
+            indent,
+            goto_targets: HashSet::new(),
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    pub fn line(&mut self, text: &str) {
+        if text.is_empty() {
+            self.out.push('\n');
+            return;
+        }
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    pub fn indent(&mut self) {
+        self.indent += 1;
+    }
+
+    pub fn dedent(&mut self) {
+        self.indent = self.indent.saturating_sub(1);
+    }
+
+    /// 输出方法体
+    pub fn write_body(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            stmt.visit(&mut |s| {
+                if let Stmt::Goto(target) = s {
+                    self.goto_targets.insert(*target);
+                }
+            });
+        }
+        self.write_stmts(stmts);
+    }
+
+    fn write_stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.write_stmt(stmt);
+        }
+    }
+
+    fn write_block(&mut self, header: &str, body: &[Stmt]) {
+        self.line(&format!("{} {{", header));
+        self.indent();
+        self.write_stmts(body);
+        self.dedent();
+    }
+
+    fn write_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr) => self.line(&format!("{};", expr_to_string(expr))),
+            Stmt::Assign { target, value } => {
+                let line = assignment(target, value);
+                self.line(&format!("{};", line));
+            }
+            Stmt::Declare { descriptor, name, value } => match value {
+                Some(value) => self.line(&format!("{} {} = {};", java_type(descriptor), name, expr_to_string(value))),
+                None => self.line(&format!("{} {};", java_type(descriptor), name)),
+            },
+            Stmt::Return(Some(expr)) => self.line(&format!("return {};", expr_to_string(expr))),
+            Stmt::Return(None) => self.line("return;"),
+            Stmt::Throw(expr) => self.line(&format!("throw {};", expr_to_string(expr))),
+            Stmt::If { cond, then, otherwise } => {
+                self.write_block(&format!("if ({})", expr_to_string(cond)), then);
+                let mut otherwise = otherwise;
+                loop {
+                    if otherwise.is_empty() {
+                        self.line("}");
+                        break;
+                    }
+                    // else if 链
+                    if let [Stmt::If { cond, then, otherwise: next }] = otherwise.as_slice() {
+                        self.line(&format!("}} else if ({}) {{", expr_to_string(cond)));
+                        self.indent();
+                        self.write_stmts(then);
+                        self.dedent();
+                        otherwise = next;
+                        continue;
+                    }
+                    self.line("} else {");
+                    self.indent();
+                    self.write_stmts(otherwise);
+                    self.dedent();
+                    self.line("}");
+                    break;
+                }
+            }
+            Stmt::While { label, cond, body } => {
+                self.write_label(label);
+                self.write_block(&format!("while ({})", expr_to_string(cond)), body);
+                self.line("}");
+            }
+            Stmt::DoWhile { label, body, cond } => {
+                self.write_label(label);
+                self.write_block("do", body);
+                self.line(&format!("}} while ({});", expr_to_string(cond)));
+            }
+            Stmt::Switch { label, selector, cases } => {
+                self.write_label(label);
+                self.line(&format!("switch ({}) {{", expr_to_string(selector)));
+                self.indent();
+                for case in cases {
+                    if case.values.is_empty() {
+                        self.line("default:");
+                    }
+                    for value in &case.values {
+                        self.line(&format!("case {}:", value));
+                    }
+                    self.indent();
+                    self.write_stmts(&case.body);
+                    self.dedent();
+                }
+                self.dedent();
+                self.line("}");
+            }
+            Stmt::Try { body, catches } => {
+                self.write_block("try", body);
+                for catch in catches {
+                    let class_name = if catch.class_names.is_empty() {
+                        "Throwable".to_string()
+                    } else {
+                        catch.class_names.iter().map(|name| java_class_name(name)).collect::<Vec<_>>().join(" | ")
+                    };
+                    self.line(&format!("}} catch ({} {}) {{", class_name, catch.variable));
+                    self.indent();
+                    self.write_stmts(&catch.body);
+                    self.dedent();
+                }
+                self.line("}");
+            }
+            Stmt::Break(Some(label)) => self.line(&format!("break {};", label)),
+            Stmt::Break(None) => self.line("break;"),
+            Stmt::Continue(Some(label)) => self.line(&format!("continue {};", label)),
+            Stmt::Continue(None) => self.line("continue;"),
+            Stmt::Label(offset) => {
+                if self.goto_targets.contains(offset) {
+                    self.line(&format!("// L{}:", offset));
+                }
+            }
+            Stmt::Goto(offset) => self.line(&format!("// goto L{};", offset)),
+            Stmt::Comment(text) => {
+                for line in text.lines() {
+                    self.line(&format!("// {}", line));
+                }
+            }
+        }
+    }
+
+    fn write_label(&mut self, label: &Option<String>) {
+        if let Some(label) = label {
+            self.line(&format!("{}:", label));
+        }
+    }
+}
+
+fn assignment(target: &Expr, value: &Expr) -> String {
+    let target_text = expr_to_string(target);
+    // x = x + 1 -> x++ / x += n
+    if let Expr::Binary { op, left, right } = value {
+        if **left == *target && !matches!(op, BinaryOp::CompareLong | BinaryOp::CompareFloat) && !is_comparison(*op) {
+            if matches!(**right, Expr::Literal(Literal::Int(1))) && matches!(op, BinaryOp::Add | BinaryOp::Sub) {
+                return format!("{}{}", target_text, if *op == BinaryOp::Add { "++" } else { "--" });
+            }
+            if !matches!(op, BinaryOp::LogicalAnd | BinaryOp::LogicalOr) {
+                return format!("{} {}= {}", target_text, binary_operator(*op), expr_to_string(right));
+            }
+        }
+    }
+    format!("{} = {}", target_text, expr_to_string(value))
+}
+
+fn is_comparison(op: BinaryOp) -> bool {
+    matches!(op, BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Gt | BinaryOp::Le)
+}
+
+fn wrap(expr: &Expr, min_precedence: u8) -> String {
+    let text = expr_to_string(expr);
+    if expr.precedence() < min_precedence {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn args_to_string(args: &[Expr]) -> String {
+    args.iter().map(expr_to_string).collect::<Vec<_>>().join(", ")
+}
+
+pub fn expr_to_string(expr: &Expr) -> String {
+    match expr {
+        Expr::Literal(literal) => format_literal(literal),
+        Expr::Local(name) => name.clone(),
+        Expr::This => "this".to_string(),
+        Expr::Super => "super".to_string(),
+        Expr::Field { target: Some(target), name, .. } => format!("{}.{}", wrap(target, 15), name),
+        Expr::Field { target: None, owner, name, .. } => format!("{}.{}", java_class_name(owner), name),
+        Expr::ArrayElement { array, index } => format!("{}[{}]", wrap(array, 15), expr_to_string(index)),
+        Expr::ArrayLength(array) => format!("{}.length", wrap(array, 15)),
+        Expr::Binary { op: BinaryOp::CompareLong, left, right } => {
+            format!("Long.compare({}, {})", expr_to_string(left), expr_to_string(right))
+        }
+        Expr::Binary { op: BinaryOp::CompareFloat, left, right } => {
+            format!("Double.compare({}, {})", expr_to_string(left), expr_to_string(right))
+        }
+        Expr::Binary { op, left, right } => {
+            let precedence = expr.precedence();
+            format!("{} {} {}", wrap(left, precedence), binary_operator(*op), wrap(right, precedence + 1))
+        }
+        Expr::Unary { op: UnaryOp::Neg, operand } => {
+            let text = wrap(operand, 13);
+            // 避免 - -x 被读成 --x
+            if text.starts_with('-') { format!("-({})", text) } else { format!("-{}", text) }
+        }
+        Expr::Unary { op: UnaryOp::Not, operand } => format!("!{}", wrap(operand, 13)),
+        Expr::Cast { descriptor, operand } => format!("({}) {}", java_type(descriptor), wrap(operand, 13)),
+        Expr::InstanceOf { operand, descriptor } => format!("{} instanceof {}", wrap(operand, 9), java_type(descriptor)),
+        // 构造器中的 super(...) / this(...)
+        Expr::Invoke { target: Some(target), name, args, .. } if name == "<init>" => {
+            format!("{}({})", wrap(target, 15), args_to_string(args))
+        }
+        Expr::Invoke { target: Some(target), name, args, .. } => {
+            format!("{}.{}({})", wrap(target, 15), name, args_to_string(args))
+        }
+        Expr::Invoke { target: None, owner, name, args, .. } => {
+            format!("{}.{}({})", java_class_name(owner), name, args_to_string(args))
+        }
+        Expr::New { class_name, args, .. } => format!("new {}({})", java_class_name(class_name), args_to_string(args)),
+        Expr::Uninitialized(class_name) => format!("new {}", java_class_name(class_name)),
+        Expr::NewArray { descriptor, dimensions, extra_dimensions } => {
+            let sizes: String = dimensions.iter().map(|d| format!("[{}]", expr_to_string(d))).collect();
+            format!("new {}{}{}", java_type(descriptor), sizes, "[]".repeat(*extra_dimensions))
+        }
+        Expr::ArrayInit { descriptor, values } => {
+            format!("new {}[] {{{}}}", java_type(descriptor), args_to_string(values))
+        }
+        Expr::Ternary { cond, then, otherwise } => {
+            format!("{} ? {} : {}", wrap(cond, 3), wrap(then, 3), wrap(otherwise, 2))
+        }
+        Expr::MethodRef { target: Some(target), name, .. } => format!("{}::{}", wrap(target, 15), name),
+        Expr::MethodRef { target: None, owner, name } => {
+            let name = if name == "<init>" { "new" } else { name };
+            format!("{}::{}", java_class_name(owner), name)
+        }
+    }
+}
@@ -61,6 +61,14 @@ pub enum Attribute {
     InnerClasses(InnerClasses_attribute),
     EnclosingMethod(EnclosingMethod_attribute),
     SYNTHETIC(Synthetic_attribute),
+    /// NestHost、NestMembers、Record 等尚未解析的属性，保留原始字节
+    Unknown(Unknown_attribute),
+}
+
+#[derive(Debug)]
+pub struct Unknown_attribute {
+    pub name: String,
+    pub info: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -665,7 +673,10 @@ fn read_verification_type_info(buffer: &mut Buffer) -> Result<VerificationTypeIn
 /// read a raw attribute from the class file
 pub(crate) fn read_raw_attribute(buffer:&mut Buffer, classfile: &ClassFile) -> Result<Attribute> {
     let attribute_name_index = buffer.read_u16()?;
-    let attribute_name = classfile.constant_pool.get_utf8(attribute_name_index as usize).unwrap();
+    let attribute_name = classfile
+        .constant_pool
+        .get_utf8(attribute_name_index as usize)
+        .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid attribute name index {}", attribute_name_index)))?;
     let attribute_length = buffer.read_u32()?;
     match attribute_name.as_str() {
         AttributeNames::CODE => {
@@ -751,8 +762,12 @@ pub(crate) fn read_raw_attribute(buffer:&mut Buffer, classfile: &ClassFile) -> R
             return Ok(Attribute::BootstrapMethods(bootstrap_methods));
         }
         _ => {
-            // Handle other attributes or return an error
-            return Err(JavaAnalyzeError::InvalidClassData("Unknown attribute type".to_owned()));
+            // 未识别的属性按长度跳过，不影响整个类的解析
+            let info = buffer.read_bytes(attribute_length as usize)?;
+            return Ok(Attribute::Unknown(Unknown_attribute {
+                name: attribute_name.clone(),
+                info: info.to_vec(),
+            }));
         }
     }
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
 
 use crate::java_analyzer::attributes::{read_raw_attribute, Attribute};
 use crate::java_analyzer::constantpool::{ConstantPool, ConstantPoolReader};
@@ -34,6 +35,29 @@ pub struct ClassFile {
     pub attributes: Vec<Attribute>,
 }
 
+impl ClassFile {
+    /// this_class 的内部名称，例如 `java/lang/String`
+    pub fn class_name(&self) -> Option<String> {
+        self.constant_pool.get_class_name(self.this_class as usize).cloned()
+    }
+
+    /// super_class 的内部名称，`java/lang/Object` 和 module-info 没有父类
+    pub fn super_class_name(&self) -> Option<String> {
+        if self.super_class == 0 {
+            return None;
+        }
+        self.constant_pool.get_class_name(self.super_class as usize).cloned()
+    }
+
+    /// 所有直接实现的接口的内部名称
+    pub fn interface_names(&self) -> Vec<String> {
+        self.interfaces
+            .iter()
+            .filter_map(|index| self.constant_pool.get_class_name(*index as usize).cloned())
+            .collect()
+    }
+}
+
 // ClassFileReader is responsible for reading the class file
 pub struct ClassFileReader<'a> {
     buffer: Buffer<'a>,
@@ -48,7 +72,13 @@ impl<'a> ClassFileReader<'a> {
         }
     }
 
-    pub fn read(mut self) ->Result<ClassFile> {
+    /// Parse the class file; a panic on malformed input is returned as an error as well
+    pub fn read(self) -> Result<ClassFile> {
+        catch_unwind(AssertUnwindSafe(|| self.read_class_file()))
+            .unwrap_or_else(|_| Err(JavaAnalyzeError::InvalidClassData("Malformed class file".to_string())))
+    }
+
+    fn read_class_file(mut self) -> Result<ClassFile> {
         self.read_magic_number()?;
         self.class_file.minor_version = self.buffer.read_u16()?;
         self.class_file.major_version = self.buffer.read_u16()?;
@@ -0,0 +1,587 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::java_analyzer::classfile::{ClassFile, ClassFileReader};
+use crate::java_analyzer::jar::{JarEntry, JarReader};
+
+/// A single element of a Java classpath.
+#[derive(Clone)]
+pub enum ClasspathEntry {
+    /// A `.jar` (or any zip) archive
+    Jar(JarReader),
+    /// An exploded class directory, e.g. `target/classes`
+    Directory(PathBuf),
+    /// A standalone `.class` file together with its internal class name
+    ClassFile { path: PathBuf, class_name: String },
+}
+
+impl ClasspathEntry {
+    /// 根据路径自动判断条目类型
+    pub fn from_path(path: &str) -> Result<Self, String> {
+        let p = Path::new(path);
+        if p.is_dir() {
+            Ok(ClasspathEntry::Directory(p.to_path_buf()))
+        } else if path.ends_with(".class") {
+            let data = std::fs::read(p).map_err(|e| e.to_string())?;
+            let class_name = read_class_name(&data).unwrap_or_else(|| {
+                p.file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("Unnamed")
+                    .to_string()
+            });
+            Ok(ClasspathEntry::ClassFile {
+                path: p.to_path_buf(),
+                class_name,
+            })
+        } else if p.is_file() {
+            Ok(ClasspathEntry::Jar(JarReader::new(path)))
+        } else {
+            Err(format!("Classpath entry not found: {}", path))
+        }
+    }
+
+    pub fn path(&self) -> String {
+        match self {
+            ClasspathEntry::Jar(reader) => reader.path.clone(),
+            ClasspathEntry::Directory(path) => path.to_string_lossy().to_string(),
+            ClasspathEntry::ClassFile { path, .. } => path.to_string_lossy().to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClasspathEntry::Jar(_) => "jar",
+            ClasspathEntry::Directory(_) => "directory",
+            ClasspathEntry::ClassFile { .. } => "class",
+        }
+    }
+
+    /// 列出条目中的所有文件，名称统一使用 `/` 分隔的相对路径
+    pub fn list_entries(&self) -> Result<Vec<JarEntry>, String> {
+        match self {
+            ClasspathEntry::Jar(reader) => reader.list_entries(),
+            ClasspathEntry::Directory(root) => {
+                let mut entries = Vec::new();
+                walk_directory(root, root, &mut HashSet::new(), &mut entries)?;
+                entries.sort_by(|a, b| a.name.cmp(&b.name));
+                Ok(entries)
+            }
+            ClasspathEntry::ClassFile { path, class_name } => {
+                let size = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+                Ok(vec![JarEntry {
+                    name: format!("{}.class", class_name),
+                    size,
+                    is_directory: false,
+                    is_class_file: true,
+                }])
+            }
+        }
+    }
+
+    /// 读取条目内的文件
+    pub fn read_file(&self, file_name: &str) -> Result<Vec<u8>, String> {
+        match self {
+            ClasspathEntry::Jar(reader) => reader.read_file(file_name),
+            ClasspathEntry::Directory(root) => {
+                // starts_with 只按组成部分逐个比较，不会拦住 `..`，只接受普通的路径组成部分
+                let relative = Path::new(file_name);
+                if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+                    return Err(format!("Invalid file name: {}", file_name));
+                }
+                std::fs::read(root.join(relative)).map_err(|e| e.to_string())
+            }
+            ClasspathEntry::ClassFile { path, class_name } => {
+                if file_name == format!("{}.class", class_name) {
+                    std::fs::read(path).map_err(|e| e.to_string())
+                } else {
+                    Err(format!("File not found: {}", file_name))
+                }
+            }
+        }
+    }
+}
+
+/// 符号链接指向的目录按规范路径只遍历一次，避免链接成环时无限递归
+fn walk_directory(root: &Path, dir: &Path, visited: &mut HashSet<PathBuf>, entries: &mut Vec<JarEntry>) -> Result<(), String> {
+    if !visited.insert(dir.canonicalize().map_err(|e| e.to_string())?) {
+        return Ok(());
+    }
+    for item in std::fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let item = item.map_err(|e| e.to_string())?;
+        let path = item.path();
+        let relative = path
+            .strip_prefix(root)
+            .map_err(|e| e.to_string())?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        if path.is_dir() {
+            walk_directory(root, &path, visited, entries)?;
+        } else {
+            let size = item.metadata().map(|m| m.len()).unwrap_or(0);
+            entries.push(JarEntry {
+                is_class_file: relative.ends_with(".class"),
+                name: relative,
+                size,
+                is_directory: false,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 从 class 文件字节中读取 this_class 的内部名称
+fn read_class_name(data: &[u8]) -> Option<String> {
+    let class_file = ClassFileReader::new(data).read().ok()?;
+    class_file.class_name()
+}
+
+/// Decides which entry provides a class when several entries define it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ClasspathPrecedence {
+    /// The earliest entry on the classpath wins, like the JVM's default class loader
+    FirstWins,
+    /// The latest entry on the classpath wins, useful for patch jars appended at the end
+    LastWins,
+}
+
+impl ClasspathPrecedence {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "FirstWins" | "first" => Ok(ClasspathPrecedence::FirstWins),
+            "LastWins" | "last" => Ok(ClasspathPrecedence::LastWins),
+            _ => Err(format!("Unknown classpath precedence: {}", value)),
+        }
+    }
+}
+
+/// Where a class (or resource) was found on the classpath.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClassLocation {
+    pub entry_index: usize,
+    pub entry_path: String,
+    pub file_name: String,
+    pub size: u64,
+}
+
+/// A class defined by more than one classpath entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateClass {
+    pub class_name: String,
+    pub locations: Vec<ClassLocation>,
+    /// true if the copies differ in content (a real conflict rather than an identical shaded copy)
+    pub conflicting: bool,
+}
+
+/// A package whose classes are spread over several classpath entries.
+#[derive(Debug, Clone, Serialize)]
+pub struct SplitPackage {
+    pub package: String,
+    pub entries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClasspathEntryInfo {
+    pub index: usize,
+    pub path: String,
+    pub kind: String,
+    pub class_count: usize,
+}
+
+/// An ordered list of jars, class directories and class files that resolves
+/// class names across all of them.
+#[derive(Clone)]
+pub struct Classpath {
+    pub entries: Vec<ClasspathEntry>,
+    pub precedence: ClasspathPrecedence,
+    // file name -> every location defining it, in classpath order
+    index: BTreeMap<String, Vec<ClassLocation>>,
+}
+
+impl Classpath {
+    pub fn new() -> Self {
+        Classpath {
+            entries: Vec::new(),
+            precedence: ClasspathPrecedence::FirstWins,
+            index: BTreeMap::new(),
+        }
+    }
+
+    /// 由一组路径构建 classpath，无法打开的条目直接返回错误
+    pub fn from_paths(paths: &[String]) -> Result<Self, String> {
+        let mut classpath = Classpath::new();
+        for path in paths {
+            classpath.add_entry(ClasspathEntry::from_path(path)?)?;
+        }
+        Ok(classpath)
+    }
+
+    pub fn add_path(&mut self, path: &str) -> Result<(), String> {
+        self.add_entry(ClasspathEntry::from_path(path)?)
+    }
+
+    pub fn add_entry(&mut self, entry: ClasspathEntry) -> Result<(), String> {
+        let entry_index = self.entries.len();
+        let entry_path = entry.path();
+        for file in entry.list_entries()? {
+            if file.is_directory {
+                continue;
+            }
+            self.index.entry(file.name.clone()).or_default().push(ClassLocation {
+                entry_index,
+                entry_path: entry_path.clone(),
+                file_name: file.name,
+                size: file.size,
+            });
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn remove_entry(&mut self, entry_index: usize) -> Result<(), String> {
+        if entry_index >= self.entries.len() {
+            return Err(format!("Invalid classpath entry index: {}", entry_index));
+        }
+        self.entries.remove(entry_index);
+        self.rebuild_index()
+    }
+
+    /// 调整条目顺序，顺序决定类的解析优先级
+    pub fn move_entry(&mut self, from: usize, to: usize) -> Result<(), String> {
+        if from >= self.entries.len() || to >= self.entries.len() {
+            return Err(format!("Invalid classpath entry index: {} -> {}", from, to));
+        }
+        let entry = self.entries.remove(from);
+        self.entries.insert(to, entry);
+        self.rebuild_index()
+    }
+
+    fn rebuild_index(&mut self) -> Result<(), String> {
+        let entries = std::mem::take(&mut self.entries);
+        self.index.clear();
+        for entry in entries {
+            self.add_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    pub fn entry_infos(&self) -> Vec<ClasspathEntryInfo> {
+        let mut class_counts = vec![0; self.entries.len()];
+        for locations in self.index.values() {
+            for location in locations.iter().filter(|l| l.file_name.ends_with(".class")) {
+                class_counts[location.entry_index] += 1;
+            }
+        }
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| ClasspathEntryInfo {
+                index,
+                path: entry.path(),
+                kind: entry.kind().to_string(),
+                class_count: class_counts[index],
+            })
+            .collect()
+    }
+
+    /// 将 `com.foo.Bar`、`com/foo/Bar`、`com/foo/Bar.class` 统一为 class 文件名
+    pub fn class_file_name(class_name: &str) -> String {
+        let internal = class_name.trim_end_matches(".class").replace('.', "/");
+        format!("{}.class", internal)
+    }
+
+    fn pick<'a>(&self, locations: &'a [ClassLocation]) -> Option<&'a ClassLocation> {
+        match self.precedence {
+            ClasspathPrecedence::FirstWins => locations.first(),
+            ClasspathPrecedence::LastWins => locations.last(),
+        }
+    }
+
+    /// 按优先级解析文件所在位置
+    pub fn resolve_file(&self, file_name: &str) -> Option<&ClassLocation> {
+        self.index.get(file_name).and_then(|locations| self.pick(locations))
+    }
+
+    /// 按优先级解析类所在位置
+    pub fn resolve_class(&self, class_name: &str) -> Option<&ClassLocation> {
+        self.resolve_file(&Self::class_file_name(class_name))
+    }
+
+    pub fn contains_class(&self, class_name: &str) -> bool {
+        self.resolve_class(class_name).is_some()
+    }
+
+    pub fn read_location(&self, location: &ClassLocation) -> Result<Vec<u8>, String> {
+        self.entries
+            .get(location.entry_index)
+            .ok_or_else(|| format!("Invalid classpath entry index: {}", location.entry_index))?
+            .read_file(&location.file_name)
+    }
+
+    pub fn read_file(&self, file_name: &str) -> Result<Vec<u8>, String> {
+        let location = self
+            .resolve_file(file_name)
+            .ok_or_else(|| format!("File not found on classpath: {}", file_name))?;
+        self.read_location(location)
+    }
+
+    pub fn read_file_as_string(&self, file_name: &str) -> Result<String, String> {
+        let bytes = self.read_file(file_name)?;
+        String::from_utf8(bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn read_class(&self, class_name: &str) -> Result<Vec<u8>, String> {
+        self.read_file(&Self::class_file_name(class_name))
+    }
+
+    /// 列出 classpath 中可见的所有文件，重复的文件只保留优先级最高的一份
+    pub fn list_entries(&self) -> Vec<JarEntry> {
+        self.index
+            .iter()
+            .filter_map(|(name, locations)| {
+                self.pick(locations).map(|location| JarEntry {
+                    name: name.clone(),
+                    size: location.size,
+                    is_directory: false,
+                    is_class_file: name.ends_with(".class"),
+                })
+            })
+            .collect()
+    }
+
+    /// 列出所有可见类的内部名称
+    pub fn class_names(&self) -> Vec<String> {
+        self.index
+            .keys()
+            .filter(|name| name.ends_with(".class"))
+            .map(|name| name.trim_end_matches(".class").to_string())
+            .collect()
+    }
+
+    /// 读取所有可见的 class 文件内容，每个 jar 只打开一次
+    pub fn read_all_classes(&self) -> Vec<(String, Vec<u8>)> {
+        let mut by_entry: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for (name, locations) in &self.index {
+            if !name.ends_with(".class") {
+                continue;
+            }
+            if let Some(location) = self.pick(locations) {
+                by_entry.entry(location.entry_index).or_default().push(location.file_name.clone());
+            }
+        }
+
+        let mut classes = Vec::new();
+        for (entry_index, file_names) in by_entry {
+            match &self.entries[entry_index] {
+                ClasspathEntry::Jar(reader) => {
+                    classes.extend(reader.read_files(&file_names).unwrap_or_default());
+                }
+                entry => {
+                    for file_name in file_names {
+                        if let Ok(bytes) = entry.read_file(&file_name) {
+                            classes.push((file_name, bytes));
+                        }
+                    }
+                }
+            }
+        }
+        classes
+    }
+
+    /// 解析所有可见的 class 文件，无法解析的 class 会被跳过
+    pub fn class_files(&self) -> Vec<ClassFile> {
+        self.parse_classes().0
+    }
+
+    /// 解析所有可见的 class 文件，无法解析的 class 以 (文件名, 原因) 另外返回
+    pub fn parse_classes(&self) -> (Vec<ClassFile>, Vec<(String, String)>) {
+        let mut class_files = Vec::new();
+        let mut failures = Vec::new();
+        for (file_name, bytes) in self.read_all_classes() {
+            match ClassFileReader::new(&bytes).read() {
+                Ok(class_file) => class_files.push(class_file),
+                Err(e) => failures.push((file_name, format!("{:?}", e))),
+            }
+        }
+        (class_files, failures)
+    }
+
+    /// 查找被多个条目定义的类，并比较内容判断是否真正冲突
+    pub fn find_duplicate_classes(&self) -> Vec<DuplicateClass> {
+        self.index
+            .iter()
+            .filter(|(name, locations)| name.ends_with(".class") && locations.len() > 1)
+            .map(|(name, locations)| {
+                let mut hashes = locations.iter().map(|location| {
+                    self.read_location(location).ok().map(|bytes| {
+                        let mut hasher = DefaultHasher::new();
+                        bytes.hash(&mut hasher);
+                        hasher.finish()
+                    })
+                });
+                let first = hashes.next().flatten();
+                let conflicting = first.is_none() || hashes.any(|hash| hash != first);
+                DuplicateClass {
+                    class_name: name.trim_end_matches(".class").to_string(),
+                    locations: locations.clone(),
+                    conflicting,
+                }
+            })
+            .collect()
+    }
+
+    /// 查找分布在多个条目中的包（split package）
+    pub fn find_split_packages(&self) -> Vec<SplitPackage> {
+        let mut packages: HashMap<String, Vec<usize>> = HashMap::new();
+        for (name, locations) in &self.index {
+            if !name.ends_with(".class") {
+                continue;
+            }
+            let package = match name.rfind('/') {
+                Some(pos) => name[..pos].replace('/', "."),
+                None => String::new(),
+            };
+            let entry_indexes = packages.entry(package).or_default();
+            for location in locations {
+                if !entry_indexes.contains(&location.entry_index) {
+                    entry_indexes.push(location.entry_index);
+                }
+            }
+        }
+
+        let mut split_packages: Vec<SplitPackage> = packages
+            .into_iter()
+            .filter(|(_, entry_indexes)| entry_indexes.len() > 1)
+            .map(|(package, mut entry_indexes)| {
+                entry_indexes.sort();
+                SplitPackage {
+                    package,
+                    entries: entry_indexes.iter().map(|i| self.entries[*i].path()).collect(),
+                }
+            })
+            .collect();
+        split_packages.sort_by(|a, b| a.package.cmp(&b.package));
+        split_packages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directory_entry_rejects_paths_leaving_the_root() {
+        let base = std::env::temp_dir().join(format!("classpath-traversal-{}", std::process::id()));
+        let root = base.join("classes");
+        std::fs::create_dir_all(root.join("com/foo")).unwrap();
+        std::fs::write(root.join("com/foo/Bar.class"), b"class").unwrap();
+        std::fs::write(base.join("secret.txt"), b"secret").unwrap();
+
+        let entry = ClasspathEntry::Directory(root.clone());
+        assert_eq!(entry.read_file("com/foo/Bar.class").unwrap(), b"class");
+        for name in ["../secret.txt", "com/../../secret.txt", "/etc/passwd"] {
+            assert!(entry.read_file(name).is_err(), "{} was read", name);
+        }
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    /// 在临时目录下写出文件，返回根目录
+    fn write_tree(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("classpath-{}-{}", name, std::process::id()));
+        for (file, content) in files {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    /// a 和 b 都定义了 com/foo/Bar（内容不同）和 com/foo/Same（内容相同）
+    fn two_entries(name: &str) -> (PathBuf, Classpath) {
+        let base = write_tree(
+            name,
+            &[
+                ("a/com/foo/Bar.class", b"one"),
+                ("a/com/foo/Same.class", b"same"),
+                ("b/com/foo/Bar.class", b"two"),
+                ("b/com/foo/Same.class", b"same"),
+                ("b/org/x/Only.class", b"only"),
+                ("b/Top.class", b"top"),
+            ],
+        );
+        let paths = [base.join("a"), base.join("b")].map(|p| p.to_string_lossy().to_string());
+        (base, Classpath::from_paths(&paths).unwrap())
+    }
+
+    #[test]
+    fn precedence_and_entry_order_decide_which_copy_wins() {
+        let (base, mut classpath) = two_entries("precedence");
+        assert_eq!(classpath.read_class("com.foo.Bar").unwrap(), b"one");
+        assert_eq!(classpath.resolve_class("com/foo/Bar").unwrap().entry_index, 0);
+        assert_eq!(classpath.read_class("org/x/Only.class").unwrap(), b"only");
+
+        classpath.precedence = ClasspathPrecedence::LastWins;
+        assert_eq!(classpath.read_class("com.foo.Bar").unwrap(), b"two");
+        assert_eq!(classpath.list_entries().iter().find(|e| e.name == "com/foo/Bar.class").unwrap().size, 3);
+
+        classpath.precedence = ClasspathPrecedence::FirstWins;
+        classpath.move_entry(1, 0).unwrap();
+        assert_eq!(classpath.read_class("com.foo.Bar").unwrap(), b"two");
+        classpath.remove_entry(0).unwrap();
+        assert_eq!(classpath.read_class("com.foo.Bar").unwrap(), b"one");
+        assert!(!classpath.contains_class("org.x.Only"));
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn duplicate_classes_are_conflicting_only_when_contents_differ() {
+        let (base, classpath) = two_entries("duplicates");
+        let duplicates: Vec<(String, usize, bool)> = classpath
+            .find_duplicate_classes()
+            .into_iter()
+            .map(|duplicate| (duplicate.class_name, duplicate.locations.len(), duplicate.conflicting))
+            .collect();
+        assert_eq!(duplicates, [("com/foo/Bar".to_string(), 2, true), ("com/foo/Same".to_string(), 2, false)]);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn split_packages_list_every_defining_entry() {
+        let (base, classpath) = two_entries("split");
+        let split: Vec<(String, Vec<String>)> = classpath
+            .find_split_packages()
+            .into_iter()
+            .map(|package| (package.package, package.entries))
+            .collect();
+        let entries = vec![base.join("a").to_string_lossy().to_string(), base.join("b").to_string_lossy().to_string()];
+        assert_eq!(split, [("com.foo".to_string(), entries)]);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn missing_entries_are_errors() {
+        let missing = std::env::temp_dir().join(format!("classpath-missing-{}.jar", std::process::id()));
+        let missing = missing.to_string_lossy().to_string();
+        assert!(Classpath::new().add_path(&missing).is_err());
+        assert!(crate::java::JavaProjectData::new(missing).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directory_loops_are_walked_once() {
+        let root = write_tree("symlinks", &[("com/foo/Bar.class", b"class")]);
+        std::os::unix::fs::symlink(&root, root.join("com/foo/loop")).unwrap();
+        let shared = write_tree("symlinks-shared", &[("Shared.class", b"shared")]);
+        std::os::unix::fs::symlink(&shared, root.join("shared")).unwrap();
+
+        let names: Vec<String> = ClasspathEntry::Directory(root.clone()).list_entries().unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["com/foo/Bar.class", "shared/Shared.class"]);
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_dir_all(&shared).unwrap();
+    }
+}
@@ -50,8 +50,8 @@ impl ConstantPool {
         Default::default()
     }
 
-    fn get_entry(&self, index: usize) -> Option<&ConstantPoolEntry> {
-        self.constant_pool.get(index - 1)
+    pub fn get_entry(&self, index: usize) -> Option<&ConstantPoolEntry> {
+        self.constant_pool.get(index.checked_sub(1)?)
     }
 
     pub fn get_utf8(&self, index: usize) -> Option<&String> {
@@ -92,6 +92,59 @@ impl ConstantPool {
             None
         }
     }
+
+    pub fn get_string(&self, index: usize) -> Option<&String> {
+        if let Some(ConstantPoolEntry::StringRef(string_index)) = self.get_entry(index) {
+            self.get_utf8(*string_index as usize)
+        } else {
+            None
+        }
+    }
+
+    /// 解析 Fieldref / Methodref / InterfaceMethodref，返回 (所属类, 名称, 描述符)
+    pub fn get_member_ref(&self, index: usize) -> Option<(&String, &String, &String)> {
+        let (class_index, name_and_type_index) = match self.get_entry(index)? {
+            ConstantPoolEntry::FieldRef(class_index, name_and_type_index)
+            | ConstantPoolEntry::MethodRef(class_index, name_and_type_index)
+            | ConstantPoolEntry::InterfaceMethodRef(class_index, name_and_type_index) => {
+                (*class_index, *name_and_type_index)
+            }
+            _ => return None,
+        };
+        let owner = self.get_class_name(class_index as usize)?;
+        let (name_index, descriptor_index) = self.get_name_and_type(name_and_type_index as usize)?;
+        let name = self.get_utf8(name_index as usize)?;
+        let descriptor = self.get_utf8(descriptor_index as usize)?;
+        Some((owner, name, descriptor))
+    }
+
+    /// 解析 InvokeDynamic，返回 (BootstrapMethods 下标, 名称, 描述符)
+    pub fn get_invoke_dynamic(&self, index: usize) -> Option<(u16, &String, &String)> {
+        if let Some(ConstantPoolEntry::InvokeDynamicRef(bootstrap_index, name_and_type_index)) = self.get_entry(index) {
+            let (name_index, descriptor_index) = self.get_name_and_type(*name_and_type_index as usize)?;
+            Some((*bootstrap_index, self.get_utf8(name_index as usize)?, self.get_utf8(descriptor_index as usize)?))
+        } else {
+            None
+        }
+    }
+
+    /// 解析 MethodHandle，返回 (reference_kind, 所属类, 名称, 描述符)
+    pub fn get_method_handle(&self, index: usize) -> Option<(u8, &String, &String, &String)> {
+        if let Some(ConstantPoolEntry::MethodHandleRef(kind, reference_index)) = self.get_entry(index) {
+            let (owner, name, descriptor) = self.get_member_ref(*reference_index as usize)?;
+            Some((*kind, owner, name, descriptor))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_class_name(&self, index: usize) -> Option<&String> {
+        if let Some(ConstantPoolEntry::ClassRef(name_index)) = self.get_entry(index) {
+            self.get_utf8(*name_index as usize)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Default)]
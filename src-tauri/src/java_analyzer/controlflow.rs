@@ -1,23 +1,61 @@
+/// This module defines the control flow graph (CFG) for a method and turns it back into structured statements.
+///
+/// 图中的块已经由前端（JVM 字节码或 Dalvik 指令）翻译成 `Stmt`，这里只关心块之间的跳转，
+/// 因此两种字节码可以共用同一个结构化算法。
+use std::collections::{HashMap, HashSet};
 
-/// This module defines the control flow graph (CFG) for a Java method.
+use crate::java_analyzer::ast::{CatchClause, Expr, Literal, Stmt, SwitchCase};
+
+/// 块末尾的控制转移
+#[derive(Debug, Clone)]
+pub(crate) enum Terminator {
+    /// 顺序执行到下一个块
+    FallThrough,
+    Goto(usize),
+    /// cond 为真时跳到 target，否则顺序执行
+    If { cond: Expr, target: usize },
+    Switch { selector: Expr, cases: Vec<(i32, usize)>, default: usize },
+    /// return / throw，最后一条语句已经在 statements 中
+    Exit,
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct BasicBlock {
-    offset_start_at: usize,
-    offset_end_at: usize,
+    /// 块起始指令的偏移
+    pub offset: u32,
+    pub statements: Vec<Stmt>,
+    pub terminator: Terminator,
+}
+
+/// 异常表的一项，start / end / handler 都是块下标，end 不包含
+#[derive(Debug, Clone)]
+pub(crate) struct ExceptionHandler {
+    pub start: usize,
+    pub end: usize,
+    pub handler: usize,
+    /// None 表示 catch-any
+    pub class_name: Option<String>,
+    pub variable: String,
 }
 
-/// The control flow graph (CFG) for a Java method.
+/// The control flow graph (CFG) for a method.
+#[derive(Debug, Default)]
 pub(crate) struct ControlFlowGraph {
     pub blocks: Vec<BasicBlock>,
-    pub edges: Vec<(usize, usize)>,
+    pub handlers: Vec<ExceptionHandler>,
+    /// 前端为跨块的操作数栈值引入的临时变量，结构化后会尽量内联
+    pub temporaries: HashSet<String>,
+}
+
+enum Scope {
+    Loop { header: usize, exit: usize, label: String },
+    Switch { exit: usize },
 }
 
 impl ControlFlowGraph {
     /// Creates a new control flow graph.
     pub fn new() -> Self {
-        ControlFlowGraph {
-            blocks: Vec::new(),
-            edges: Vec::new(),
-        }
+        Default::default()
     }
 
     /// Adds a block to the control flow graph.
@@ -25,8 +63,722 @@ impl ControlFlowGraph {
         self.blocks.push(block);
     }
 
-    /// Adds an edge to the control flow graph.
-    pub fn add_edge(&mut self, from: usize, to: usize) {
-        self.edges.push((from, to));
+    /// 块的所有后继（不含异常边）
+    pub fn successors(&self, index: usize) -> Vec<usize> {
+        let next = index + 1;
+        match &self.blocks[index].terminator {
+            Terminator::FallThrough => vec![next],
+            Terminator::Goto(target) => vec![*target],
+            Terminator::If { target, .. } => vec![next, *target],
+            Terminator::Switch { cases, default, .. } => {
+                let mut targets: Vec<usize> = cases.iter().map(|(_, target)| *target).collect();
+                targets.push(*default);
+                targets.sort();
+                targets.dedup();
+                targets
+            }
+            Terminator::Exit => vec![],
+        }
+        .into_iter()
+        .filter(|target| *target < self.blocks.len())
+        .collect()
+    }
+
+    fn predecessor_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.blocks.len()];
+        for index in 0..self.blocks.len() {
+            for successor in self.successors(index) {
+                counts[successor] += 1;
+            }
+        }
+        for handler in &self.handlers {
+            if handler.handler < counts.len() {
+                counts[handler.handler] += 1;
+            }
+        }
+        counts
+    }
+
+    /// 跳过之前合并留下的、只会顺序执行的空块
+    fn skip_empty_blocks(&self, mut index: usize, predecessors: &[usize]) -> usize {
+        while index + 1 < self.blocks.len()
+            && predecessors[index] == 1
+            && self.is_empty_block(index)
+            && matches!(self.blocks[index].terminator, Terminator::FallThrough)
+        {
+            index += 1;
+        }
+        index
+    }
+
+    fn is_empty_block(&self, index: usize) -> bool {
+        self.blocks[index].statements.iter().all(|stmt| matches!(stmt, Stmt::Label(_)))
+    }
+
+    /// 合并短路求值产生的条件链：`if (a) goto T; if (b) goto T;` -> `if (a || b) goto T;`
+    pub fn merge_conditions(&mut self) {
+        loop {
+            let predecessors = self.predecessor_counts();
+            let mut changed = false;
+            for index in (0..self.blocks.len().saturating_sub(1)).rev() {
+                let Terminator::If { cond: first, target } = &self.blocks[index].terminator else {
+                    continue;
+                };
+                let next = self.skip_empty_blocks(index + 1, &predecessors);
+                if predecessors[next] != 1 || !self.is_empty_block(next) || (index + 1..=next).contains(target) {
+                    continue;
+                }
+                let Terminator::If { cond: second, target: second_target } = &self.blocks[next].terminator else {
+                    continue;
+                };
+                let merged = if second_target == target {
+                    Terminator::If {
+                        cond: Expr::binary(crate::java_analyzer::ast::BinaryOp::LogicalOr, first.clone(), second.clone()),
+                        target: *target,
+                    }
+                } else if *target == self.skip_empty_blocks(next + 1, &predecessors) {
+                    Terminator::If {
+                        cond: Expr::binary(crate::java_analyzer::ast::BinaryOp::LogicalAnd, first.clone().negate(), second.clone()),
+                        target: *second_target,
+                    }
+                } else {
+                    continue;
+                };
+                self.blocks[index].terminator = merged;
+                self.blocks[next].terminator = Terminator::FallThrough;
+                changed = true;
+                break;
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// 把整个图结构化为语句序列
+    pub fn structure(&self) -> Vec<Stmt> {
+        let mut structurer = Structurer::new(self);
+        let mut scopes = Vec::new();
+        let count = self.blocks.len();
+        structurer.structure(0, count, count, &mut scopes)
+    }
+}
+
+struct Structurer<'a> {
+    cfg: &'a ControlFlowGraph,
+    /// 每个循环头对应的最后一个回边来源
+    loop_ends: Vec<Option<usize>>,
+    used_labels: HashSet<String>,
+    /// 已经处理过的 try 区域 (start, end)
+    consumed_tries: HashSet<(usize, usize)>,
+    emitted: Vec<bool>,
+}
+
+impl<'a> Structurer<'a> {
+    fn new(cfg: &'a ControlFlowGraph) -> Self {
+        let mut loop_ends = vec![None; cfg.blocks.len()];
+        for index in 0..cfg.blocks.len() {
+            for successor in cfg.successors(index) {
+                if successor <= index {
+                    let end = loop_ends[successor].get_or_insert(index);
+                    *end = (*end).max(index);
+                }
+            }
+        }
+        Structurer {
+            cfg,
+            loop_ends,
+            used_labels: HashSet::new(),
+            consumed_tries: HashSet::new(),
+            emitted: vec![false; cfg.blocks.len()],
+        }
+    }
+
+    /// 结构化 [from, to) 区间的块，exit 是区间执行完毕后控制流到达的块
+    fn structure(&mut self, from: usize, to: usize, exit: usize, scopes: &mut Vec<Scope>) -> Vec<Stmt> {
+        let mut out = Vec::new();
+        let mut index = from;
+        while index < to {
+            if let Some(next) = self.structure_try(index, to, exit, scopes, &mut out) {
+                index = next;
+                continue;
+            }
+            if let Some(next) = self.structure_loop(index, to, scopes, &mut out) {
+                index = next;
+                continue;
+            }
+            index = self.structure_block(index, to, exit, scopes, &mut out);
+        }
+        out
+    }
+
+    fn structure_block(&mut self, index: usize, to: usize, exit: usize, scopes: &mut Vec<Scope>, out: &mut Vec<Stmt>) -> usize {
+        let block = &self.cfg.blocks[index];
+        if self.emitted[index] {
+            // 防御：同一块不会被输出两次
+            out.push(Stmt::Goto(block.offset));
+            return index + 1;
+        }
+        self.emitted[index] = true;
+        out.push(Stmt::Label(block.offset));
+        out.extend(block.statements.iter().cloned());
+
+        match &block.terminator {
+            Terminator::Exit => index + 1,
+            Terminator::FallThrough => {
+                out.extend(self.jump(index + 1, index, to, exit, scopes));
+                index + 1
+            }
+            Terminator::Goto(target) => {
+                out.extend(self.jump(*target, index, to, exit, scopes));
+                index + 1
+            }
+            Terminator::If { cond, target } => {
+                let target = *target;
+                let next = index + 1;
+                if target > next && target <= to && self.jump_target(target, scopes).is_none() {
+                    // then 分支为 [next, target)，若其末尾跳过 else 分支则为 if-else
+                    let last = target - 1;
+                    let else_end = match &self.cfg.blocks[last].terminator {
+                        Terminator::Goto(after) if *after > target && (*after <= to || *after == exit) && self.jump_target(*after, scopes).is_none() => {
+                            Some(*after)
+                        }
+                        _ => None,
+                    };
+                    match else_end {
+                        Some(real_after) => {
+                            // real_after 超出区间时就是区间出口，两个分支结束后自然到达
+                            let after = real_after.min(to);
+                            let then = self.structure(next, target, real_after, scopes);
+                            let otherwise = self.structure(target, after, real_after, scopes);
+                            out.push(Stmt::If {
+                                cond: cond.clone().negate(),
+                                then,
+                                otherwise,
+                            });
+                            if real_after == to && to != exit {
+                                out.extend(self.jump_from_region_end(to, scopes));
+                            }
+                            after
+                        }
+                        None => {
+                            let then = self.structure(next, target, target, scopes);
+                            out.push(Stmt::If {
+                                cond: cond.clone().negate(),
+                                then,
+                                otherwise: vec![],
+                            });
+                            if target == to && to != exit {
+                                out.extend(self.jump_from_region_end(to, scopes));
+                            }
+                            target
+                        }
+                    }
+                } else if target == exit && exit > to && next < to && self.jump_target(target, scopes).is_none() {
+                    // 跳到区间出口：区间剩余部分作为 then 分支
+                    let then = self.structure(next, to, exit, scopes);
+                    out.push(Stmt::If {
+                        cond: cond.clone().negate(),
+                        then,
+                        otherwise: vec![],
+                    });
+                    to
+                } else {
+                    let jump = self
+                        .jump_target(target, scopes)
+                        .unwrap_or(Stmt::Goto(self.block_offset(target)));
+                    out.push(Stmt::If {
+                        cond: cond.clone(),
+                        then: vec![jump],
+                        otherwise: vec![],
+                    });
+                    out.extend(self.jump(next, index, to, exit, scopes));
+                    next
+                }
+            }
+            Terminator::Switch { selector, cases, default } => {
+                self.structure_switch(index, selector, cases, *default, to, exit, scopes, out)
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn structure_switch(
+        &mut self,
+        index: usize,
+        selector: &Expr,
+        cases: &[(i32, usize)],
+        default: usize,
+        to: usize,
+        exit: usize,
+        scopes: &mut Vec<Scope>,
+        out: &mut Vec<Stmt>,
+    ) -> usize {
+        let max_case = cases.iter().map(|(_, target)| *target).max().unwrap_or(default);
+        // 前面的 case 中 break 跳到的位置就是 switch 的出口
+        let breaks: Vec<usize> = (index + 1..max_case.min(to))
+            .filter_map(|b| match &self.cfg.blocks[b].terminator {
+                Terminator::Goto(target) if *target > max_case && (*target <= to || *target == exit) => Some(*target),
+                _ => None,
+            })
+            .collect();
+        // real_exit 超出区间时就是区间出口
+        let real_exit = match breaks.iter().max() {
+            Some(target) => *target,
+            None if default > max_case && (default <= to || default == exit) => default,
+            None => to,
+        };
+        let switch_exit = real_exit.min(to);
+        if switch_exit > to || cases.iter().any(|(_, target)| *target <= index) || default <= index {
+            out.push(Stmt::Comment(format!("switch ({}) could not be structured", crate::java_analyzer::ast::expr_to_string(selector))));
+            for (value, target) in cases {
+                out.push(Stmt::If {
+                    cond: Expr::binary(crate::java_analyzer::ast::BinaryOp::Eq, selector.clone(), Expr::int(*value)),
+                    then: vec![Stmt::Goto(self.block_offset(*target))],
+                    otherwise: vec![],
+                });
+            }
+            out.push(Stmt::Goto(self.block_offset(default)));
+            return index + 1;
+        }
+
+        let mut targets: Vec<usize> = cases.iter().map(|(_, target)| *target).collect();
+        if default < real_exit || (default > switch_exit && default != real_exit) {
+            targets.push(default);
+        }
+        targets.sort();
+        targets.dedup();
+        // 跳出当前区间的 case 只能输出为跳转
+        let is_outside = |target: &usize| *target > switch_exit && *target != real_exit;
+        let outside: Vec<usize> = targets.iter().copied().filter(is_outside).collect();
+        targets.retain(|target| !is_outside(target));
+
+        scopes.push(Scope::Switch { exit: real_exit });
+        let mut switch_cases = Vec::new();
+        for (position, target) in targets.iter().enumerate() {
+            let mut values: Vec<i32> = cases.iter().filter(|(_, t)| t == target).map(|(v, _)| *v).collect();
+            values.sort();
+            let (end, case_exit) = match targets.get(position + 1) {
+                Some(next) => (*next, *next),
+                None => (switch_exit, real_exit),
+            };
+            let body = self.structure(*target, end, case_exit, scopes);
+            if *target == default {
+                // default 和其他 case 共用一段代码时先列出 case 标签
+                if !values.is_empty() {
+                    switch_cases.push(SwitchCase { values, body: vec![] });
+                }
+                switch_cases.push(SwitchCase { values: vec![], body });
+            } else {
+                switch_cases.push(SwitchCase { values, body });
+            }
+        }
+        for target in outside {
+            let mut values: Vec<i32> = cases.iter().filter(|(_, t)| *t == target).map(|(v, _)| *v).collect();
+            values.sort();
+            let body = self.jump_from_region_end(target, scopes).into_iter().collect();
+            if target == default && !values.is_empty() {
+                switch_cases.push(SwitchCase { values, body: vec![] });
+                switch_cases.push(SwitchCase { values: vec![], body });
+            } else if target == default {
+                switch_cases.push(SwitchCase { values: vec![], body });
+            } else {
+                switch_cases.push(SwitchCase { values, body });
+            }
+        }
+        // 直接跳到出口的 case
+        let mut empty: Vec<i32> = cases.iter().filter(|(_, t)| *t == real_exit).map(|(v, _)| *v).collect();
+        if !empty.is_empty() {
+            empty.sort();
+            switch_cases.push(SwitchCase {
+                values: empty,
+                body: vec![Stmt::Break(None)],
+            });
+        }
+        scopes.pop();
+
+        out.push(Stmt::Switch {
+            label: None,
+            selector: selector.clone(),
+            cases: switch_cases,
+        });
+        if real_exit == to && to != exit {
+            out.extend(self.jump_from_region_end(to, scopes));
+        }
+        switch_exit
+    }
+
+    /// 识别以 index 开头的循环，返回循环之后的块
+    fn structure_loop(&mut self, index: usize, to: usize, scopes: &mut Vec<Scope>, out: &mut Vec<Stmt>) -> Option<usize> {
+        let last = self.loop_ends[index]?;
+        if last >= to || self.emitted[index] {
+            return None;
+        }
+        if scopes.iter().any(|scope| matches!(scope, Scope::Loop { header, .. } if *header == index)) {
+            return None;
+        }
+        let exit = last + 1;
+        let label = format!("label{}", self.cfg.blocks[index].offset);
+        scopes.push(Scope::Loop {
+            header: index,
+            exit,
+            label: label.clone(),
+        });
+        let body = self.structure(index, exit, index, scopes);
+        scopes.pop();
+        let label = self.used_labels.contains(&label).then_some(label);
+        out.push(make_loop(label, body));
+        Some(exit)
+    }
+
+    /// 识别以 index 开头的 try 区域，返回 try / catch 之后的块
+    fn structure_try(&mut self, index: usize, to: usize, exit: usize, scopes: &mut Vec<Scope>, out: &mut Vec<Stmt>) -> Option<usize> {
+        let handlers = &self.cfg.handlers;
+        // 同一起点取最外层（end 最大）的区域
+        let end = handlers
+            .iter()
+            .filter(|h| h.start == index && h.end <= to && !self.consumed_tries.contains(&(h.start, h.end)))
+            .map(|h| h.end)
+            .max()?;
+        self.consumed_tries.insert((index, end));
+
+        let group: Vec<&ExceptionHandler> = handlers.iter().filter(|h| h.start == index && h.end == end).collect();
+        let mut handler_starts: Vec<usize> = group.iter().map(|h| h.handler).collect();
+        handler_starts.sort();
+        handler_starts.dedup();
+        let first_handler = handler_starts[0];
+        let last_handler = *handler_starts.last().unwrap();
+        if first_handler < end || last_handler >= to {
+            return None;
+        }
+
+        // try 和前面的 catch 正常结束后跳到的位置
+        let real_after = (index..last_handler)
+            .filter_map(|b| match &self.cfg.blocks[b].terminator {
+                Terminator::Goto(target) if *target > last_handler => Some(*target),
+                _ => None,
+            })
+            .filter(|target| *target <= to || *target == exit)
+            .min()
+            .unwrap_or(to);
+        let after = real_after.min(to);
+
+        // 包含整个 try 的循环优先处理
+        if let Some(last) = self.loop_ends[index] {
+            if last < to && after <= last + 1 && !scopes.iter().any(|s| matches!(s, Scope::Loop { header, .. } if *header == index)) {
+                self.consumed_tries.remove(&(index, end));
+                return None;
+            }
+        }
+
+        let body = self.structure(index, first_handler, real_after, scopes);
+        let mut catches = Vec::new();
+        for (position, handler) in handler_starts.iter().enumerate() {
+            let handler_end = handler_starts.get(position + 1).copied().unwrap_or(after);
+            let entries: Vec<&&ExceptionHandler> = group.iter().filter(|h| h.handler == *handler).collect();
+            let class_names = if entries.iter().any(|h| h.class_name.is_none()) {
+                vec![]
+            } else {
+                entries.iter().filter_map(|h| h.class_name.clone()).collect()
+            };
+            let variable = entries[0].variable.clone();
+            let body = self.structure(*handler, handler_end, real_after, scopes);
+            catches.push(CatchClause {
+                class_names,
+                variable,
+                body,
+            });
+        }
+        out.push(Stmt::Try { body, catches });
+        if real_after == to && to != exit {
+            out.extend(self.jump_from_region_end(to, scopes));
+        }
+        Some(after)
+    }
+
+    /// 从 from 块跳到 target：能自然到达时不需要语句，否则转成 break / continue / goto
+    fn jump(&mut self, target: usize, from: usize, to: usize, exit: usize, scopes: &[Scope]) -> Option<Stmt> {
+        let next = from + 1;
+        if target == next && next < to {
+            return None;
+        }
+        if next >= to && target == exit {
+            return None;
+        }
+        if target >= self.cfg.blocks.len() {
+            return None;
+        }
+        Some(self.jump_target(target, scopes).unwrap_or(Stmt::Goto(self.block_offset(target))))
+    }
+
+    fn jump_from_region_end(&mut self, exit: usize, scopes: &[Scope]) -> Option<Stmt> {
+        if exit >= self.cfg.blocks.len() {
+            return None;
+        }
+        Some(self.jump_target(exit, scopes).unwrap_or(Stmt::Goto(self.block_offset(exit))))
+    }
+
+    /// target 是否是外层循环 / switch 的 continue 或 break 目标
+    fn jump_target(&mut self, target: usize, scopes: &[Scope]) -> Option<Stmt> {
+        let mut innermost_loop = true;
+        let mut innermost_breakable = true;
+        for scope in scopes.iter().rev() {
+            match scope {
+                Scope::Loop { header, exit, label } => {
+                    if *header == target {
+                        return Some(Stmt::Continue(self.label_if(!innermost_loop, label)));
+                    }
+                    if *exit == target {
+                        return Some(Stmt::Break(self.label_if(!innermost_breakable, label)));
+                    }
+                    innermost_loop = false;
+                    innermost_breakable = false;
+                }
+                Scope::Switch { exit } => {
+                    if *exit == target && innermost_breakable {
+                        return Some(Stmt::Break(None));
+                    }
+                    innermost_breakable = false;
+                }
+            }
+        }
+        None
+    }
+
+    fn label_if(&mut self, needed: bool, label: &str) -> Option<String> {
+        if needed {
+            self.used_labels.insert(label.to_string());
+            Some(label.to_string())
+        } else {
+            None
+        }
+    }
+
+    fn block_offset(&self, index: usize) -> u32 {
+        self.cfg.blocks.get(index).map(|block| block.offset).unwrap_or(u32::MAX)
+    }
+}
+
+/// 把 `while (true)` 化简为 while / do-while
+fn make_loop(label: Option<String>, mut body: Vec<Stmt>) -> Stmt {
+    // 去掉循环头的 Label，方便匹配开头的条件
+    let header_label = match body.first() {
+        Some(Stmt::Label(offset)) => {
+            let offset = *offset;
+            body.remove(0);
+            Some(offset)
+        }
+        _ => None,
+    };
+    let restore = |mut body: Vec<Stmt>| {
+        if let Some(offset) = header_label {
+            body.insert(0, Stmt::Label(offset));
+        }
+        body
+    };
+
+    // while (cond) { ... }
+    let leading_break = matches!(body.first(),
+        Some(Stmt::If { then, otherwise, .. }) if otherwise.is_empty() && matches!(then.as_slice(), [Stmt::Break(None)]));
+    if leading_break {
+        if let Stmt::If { cond, .. } = body.remove(0) {
+            return Stmt::While {
+                label,
+                cond: cond.negate(),
+                body: restore(body),
+            };
+        }
+    }
+
+    // do { ... } while (cond);
+    let len = body.len();
+    if len >= 2 && matches!(body[len - 1], Stmt::Break(None)) {
+        if let Stmt::If { then, otherwise, .. } = &body[len - 2] {
+            if otherwise.is_empty() && matches!(then.as_slice(), [Stmt::Continue(None)]) && !continues_loop(&body[..len - 2], label.as_deref()) {
+                body.pop();
+                let Some(Stmt::If { cond, .. }) = body.pop() else { unreachable!() };
+                return Stmt::DoWhile {
+                    label,
+                    body: restore(body),
+                    cond,
+                };
+            }
+        }
+    }
+
+    Stmt::While {
+        label,
+        cond: Expr::Literal(Literal::Bool(true)),
+        body: restore(body),
+    }
+}
+
+/// stmts 中是否有指向当前循环的 continue（不计内层循环自己的 continue）
+fn continues_loop(stmts: &[Stmt], label: Option<&str>) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::Continue(None) => true,
+        Stmt::Continue(Some(l)) => Some(l.as_str()) == label,
+        Stmt::If { then, otherwise, .. } => continues_loop(then, label) || continues_loop(otherwise, label),
+        Stmt::Switch { cases, .. } => cases.iter().any(|case| continues_loop(&case.body, label)),
+        Stmt::Try { body, catches } => {
+            continues_loop(body, label) || catches.iter().any(|catch| continues_loop(&catch.body, label))
+        }
+        Stmt::While { body, .. } | Stmt::DoWhile { body, .. } => {
+            label.is_some() && continues_loop_labeled(body, label)
+        }
+        _ => false,
+    })
+}
+
+fn continues_loop_labeled(stmts: &[Stmt], label: Option<&str>) -> bool {
+    let mut found = false;
+    for stmt in stmts {
+        stmt.visit(&mut |s| {
+            if let Stmt::Continue(Some(l)) = s {
+                if Some(l.as_str()) == label {
+                    found = true;
+                }
+            }
+        });
+    }
+    found
+}
+
+/// 结构化之后的清理：内联只用一次的栈临时变量、合并三目运算
+pub(crate) fn simplify(stmts: &mut Vec<Stmt>, temporaries: &HashSet<String>) {
+    let mut counts = HashMap::new();
+    count_locals(stmts, &mut counts);
+    simplify_block(stmts, temporaries, &counts);
+}
+
+/// 统计每个局部变量被读取的次数
+pub(crate) fn count_locals(stmts: &[Stmt], counts: &mut HashMap<String, usize>) {
+    for stmt in stmts {
+        stmt.visit(&mut |s| {
+            for expr in stmt_exprs(s) {
+                expr.visit(&mut |e| {
+                    if let Expr::Local(name) = e {
+                        *counts.entry(name.clone()).or_default() += 1;
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// 语句自身（不含嵌套语句）直接包含的表达式，赋值目标为局部变量时不计入
+fn stmt_exprs(stmt: &Stmt) -> Vec<&Expr> {
+    match stmt {
+        Stmt::Expr(expr) | Stmt::Throw(expr) | Stmt::Return(Some(expr)) => vec![expr],
+        Stmt::Assign { target: Expr::Local(_), value } => vec![value],
+        Stmt::Assign { target, value } => vec![target, value],
+        Stmt::Declare { value: Some(value), .. } => vec![value],
+        Stmt::If { cond, .. } | Stmt::While { cond, .. } | Stmt::DoWhile { cond, .. } => vec![cond],
+        Stmt::Switch { selector, .. } => vec![selector],
+        _ => vec![],
     }
-}
\ No newline at end of file
+}
+
+fn simplify_block(stmts: &mut Vec<Stmt>, temporaries: &HashSet<String>, counts: &HashMap<String, usize>) {
+    for stmt in stmts.iter_mut() {
+        match stmt {
+            Stmt::If { then, otherwise, .. } => {
+                simplify_block(then, temporaries, counts);
+                simplify_block(otherwise, temporaries, counts);
+            }
+            Stmt::While { body, .. } | Stmt::DoWhile { body, .. } => {
+                simplify_block(body, temporaries, counts)
+            }
+            Stmt::Switch { cases, .. } => cases.iter_mut().for_each(|case| simplify_block(&mut case.body, temporaries, counts)),
+            Stmt::Try { body, catches } => {
+                simplify_block(body, temporaries, counts);
+                catches.iter_mut().for_each(|catch| simplify_block(&mut catch.body, temporaries, counts));
+            }
+            _ => {}
+        }
+    }
+
+    // if (c) {} else { ... } -> if (!c) { ... }
+    for stmt in stmts.iter_mut() {
+        if let Stmt::If { cond, then, otherwise } = stmt {
+            if then.iter().all(|s| matches!(s, Stmt::Label(_))) && !otherwise.is_empty() {
+                *stmt = Stmt::If {
+                    cond: cond.clone().negate(),
+                    then: std::mem::take(otherwise),
+                    otherwise: Vec::new(),
+                };
+            }
+        }
+    }
+
+    // if (c) { t = a; } else { t = b; } -> t = c ? a : b;
+    for stmt in stmts.iter_mut() {
+        let replacement = match stmt {
+            Stmt::If { cond, then, otherwise } => {
+                let then: Vec<&Stmt> = then.iter().filter(|s| !matches!(s, Stmt::Label(_))).collect();
+                let otherwise: Vec<&Stmt> = otherwise.iter().filter(|s| !matches!(s, Stmt::Label(_))).collect();
+                match (then.as_slice(), otherwise.as_slice()) {
+                    (
+                        [Stmt::Assign { target: Expr::Local(a), value: then_value }],
+                        [Stmt::Assign { target: Expr::Local(b), value: else_value }],
+                    ) if a == b && temporaries.contains(a) => Some(Stmt::Assign {
+                        target: Expr::Local(a.clone()),
+                        value: Expr::Ternary {
+                            cond: Box::new(cond.clone()),
+                            then: Box::new(then_value.clone()),
+                            otherwise: Box::new(else_value.clone()),
+                        },
+                    }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some(replacement) = replacement {
+            *stmt = replacement;
+        }
+    }
+
+    // t = expr; use(t); -> use(expr);
+    let mut index = 0;
+    while index + 1 < stmts.len() {
+        let inline = match &stmts[index] {
+            Stmt::Assign { target: Expr::Local(name), value } if temporaries.contains(name) && counts.get(name) == Some(&1) => {
+                Some((name.clone(), value.clone()))
+            }
+            _ => None,
+        };
+        if let Some((name, value)) = inline {
+            let mut next = index + 1;
+            while matches!(stmts.get(next), Some(Stmt::Label(_))) {
+                next += 1;
+            }
+            if next < stmts.len() && replace_in_stmt(&mut stmts[next], &name, &value) {
+                stmts.remove(index);
+                continue;
+            }
+        }
+        index += 1;
+    }
+}
+
+/// 替换语句自身表达式中的局部变量，成功时返回 true
+fn replace_in_stmt(stmt: &mut Stmt, name: &str, value: &Expr) -> bool {
+    let exprs: Vec<&mut Expr> = match stmt {
+        Stmt::Expr(expr) | Stmt::Throw(expr) | Stmt::Return(Some(expr)) => vec![expr],
+        Stmt::Assign { target: Expr::Local(_), value } => vec![value],
+        Stmt::Assign { target, value } => vec![target, value],
+        Stmt::Declare { value: Some(value), .. } => vec![value],
+        Stmt::If { cond, .. } => vec![cond],
+        Stmt::Switch { selector, .. } => vec![selector],
+        _ => vec![],
+    };
+    let mut replaced = false;
+    for expr in exprs {
+        if expr.reads_local(name) {
+            expr.replace_local(name, value);
+            replaced = true;
+        }
+    }
+    replaced
+}
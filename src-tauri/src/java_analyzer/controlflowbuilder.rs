@@ -1,87 +1,1679 @@
-use std::io::Result;
-use crate::java_analyzer::{classfile::ClassFile, method::JvmMethod};
+// JVM 前端：模拟操作数栈，把一个方法的字节码翻译成由 Stmt 组成的控制流图
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::java_analyzer::ast::{
+    class_descriptor, is_wide, parse_method_descriptor, BinaryOp, Expr, InvokeKind, Literal, Stmt, UnaryOp,
+};
+use crate::java_analyzer::attributes::{Attribute, Code_attribute};
+use crate::java_analyzer::classfile::ClassFile;
+use crate::java_analyzer::constantpool::ConstantPoolEntry;
+use crate::java_analyzer::controlflow::{BasicBlock, ControlFlowGraph, ExceptionHandler, Terminator};
+use crate::java_analyzer::error::{JavaAnalyzeError, Result};
+use crate::java_analyzer::method::JvmMethod;
 use crate::java_analyzer::opcode::*;
-use crate::java_analyzer::controlflow::ControlFlowGraph;
-
-#[derive(Clone)]
-enum FlowType {
-    Goto,
-    GotoTenaryOperator,
-    Throw,
-    Return,
-    ReturnValue,
-    Conditional,
-    Switch,
-    Jsr,
-    Ret,
-    Unknown,
-}
-
-pub trait ControlFlowGraphBuilder {
-    fn build(&self, method:&JvmMethod, classfile: &ClassFile) -> Result<ControlFlowGraph>;
-} 
-
-impl ControlFlowGraphBuilder for ControlFlowGraph {
-    fn build(&self, method:&JvmMethod, classfile:&ClassFile) -> Result<ControlFlowGraph> {
-
-        let constantpool = &classfile.constant_pool;
-        let mut blocks = vec![];
-        
-        let instructions = &method.code;
-        let length = instructions.len();
-        let mut flow_types: Vec<FlowType> = vec![FlowType::Unknown; length];
-
-        let mut index = 0;
-        let mut lastStatementOffset = 0;
-
-        while index < length {
-            let opcode = &instructions[index];
-            
-            match opcode.opcode {
-                OP_ISTORE | OP_LSTORE | OP_FSTORE | OP_DSTORE | OP_ASTORE |
-                OP_ISTORE_0 | OP_ISTORE_1 | OP_ISTORE_2 | OP_ISTORE_3 |
-                OP_LSTORE_0 | OP_LSTORE_1 | OP_LSTORE_2 | OP_LSTORE_3 |
-                OP_FSTORE_0 | OP_FSTORE_1 | OP_FSTORE_2 | OP_FSTORE_3 |
-                OP_DSTORE_0 | OP_DSTORE_1 | OP_DSTORE_2 | OP_DSTORE_3 |
-                OP_ASTORE_0 | OP_ASTORE_1 | OP_ASTORE_2 | OP_ASTORE_3 |
-                OP_IASTORE | OP_LASTORE | OP_FASTORE | OP_DASTORE | OP_AASTORE | OP_BASTORE | OP_CASTORE | OP_SASTORE => {
-                    lastStatementOffset = opcode.offset;
+
+const ACC_STATIC: u16 = 0x0008;
+
+/// 翻译结果：控制流图以及需要声明的参数和局部变量
+pub(crate) struct LiftedMethod {
+    pub cfg: ControlFlowGraph,
+    /// (名称, 描述符)
+    pub parameters: Vec<(String, String)>,
+    /// 按首次出现顺序排列的局部变量 (名称, 描述符)，不含参数和 catch 变量
+    pub locals: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum VarKey {
+    /// LocalVariableTable 中的变量
+    Named(u16, String, String),
+    /// 没有调试信息时按 (槽位, 类别) 区分
+    Slot(u16, char),
+}
+
+struct LocalVariableEntry {
+    start: u32,
+    end: u32,
+    slot: u16,
+    name: String,
+    descriptor: String,
+}
+
+pub(crate) struct ControlFlowGraphBuilder<'a> {
+    class_file: &'a ClassFile,
+    class_name: String,
+    method: &'a JvmMethod,
+    code: &'a Code_attribute,
+    local_variable_table: Vec<LocalVariableEntry>,
+    variables: HashMap<VarKey, String>,
+    used_names: HashSet<String>,
+    var_types: HashMap<String, String>,
+    var_order: Vec<String>,
+    parameter_names: HashSet<String>,
+    /// 通过普通 store 赋值过的变量，需要在方法开头声明
+    stored: HashSet<String>,
+    stores_this: bool,
+    temporaries: HashSet<String>,
+    temp_counter: usize,
+    /// 指令偏移 -> 反混淆还原出的字符串，这些指令直接翻译为字面量
+    strings: HashMap<u32, String>,
+}
+
+impl<'a> ControlFlowGraphBuilder<'a> {
+    pub fn new(class_file: &'a ClassFile, method: &'a JvmMethod) -> Result<Self> {
+        let code = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            })
+            .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Method {} has no Code attribute", method.name)))?;
+
+        let mut local_variable_table = Vec::new();
+        for attribute in &code.attributes {
+            if let Attribute::LocalVariableTable(table) = attribute {
+                for entry in &table.local_variable_table {
+                    let pool = &class_file.constant_pool;
+                    if let (Some(name), Some(descriptor)) =
+                        (pool.get_utf8(entry.name_index as usize), pool.get_utf8(entry.descriptor_index as usize))
+                    {
+                        local_variable_table.push(LocalVariableEntry {
+                            start: entry.start_pc as u32,
+                            end: entry.start_pc as u32 + entry.length as u32,
+                            slot: entry.index,
+                            name: name.clone(),
+                            descriptor: descriptor.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let is_static = method.access_flags & ACC_STATIC != 0;
+        let stores_this = !is_static
+            && method.code.iter().any(|insn| match insn.opcode {
+                OP_ASTORE_0 => true,
+                OP_ASTORE => insn.value == 0,
+                OP_WIDE => insn.value as u8 == OP_ASTORE && insn.value2 == 0,
+                _ => false,
+            });
+
+        Ok(ControlFlowGraphBuilder {
+            class_file,
+            class_name: class_file.class_name().unwrap_or_default(),
+            method,
+            code,
+            local_variable_table,
+            variables: HashMap::new(),
+            used_names: ["this", "super"].iter().map(|s| s.to_string()).collect(),
+            var_types: HashMap::new(),
+            var_order: Vec::new(),
+            parameter_names: HashSet::new(),
+            stored: HashSet::new(),
+            stores_this,
+            temporaries: HashSet::new(),
+            temp_counter: 0,
+            strings: HashMap::new(),
+        })
+    }
+
+    pub fn with_strings(mut self, strings: HashMap<u32, String>) -> Self {
+        self.strings = strings;
+        self
+    }
+
+    pub fn build(mut self) -> Result<LiftedMethod> {
+        let parameters = self.declare_parameters();
+        let instructions = &self.method.code;
+        if instructions.is_empty() {
+            return Err(JavaAnalyzeError::InvalidClassData(format!("Method {} has no instructions", self.method.name)));
+        }
+        let code_length = self.code.code.len() as u32;
+
+        // 1. 找出所有基本块的起点
+        let mut leaders = BTreeSet::new();
+        leaders.insert(0u32);
+        for (index, insn) in instructions.iter().enumerate() {
+            let next = instructions.get(index + 1).map(|i| i.offset).unwrap_or(code_length);
+            match insn.opcode {
+                OP_GOTO | OP_GOTO_W | OP_IFEQ | OP_IFNE | OP_IFLT | OP_IFGE | OP_IFGT | OP_IFLE | OP_IF_ICMPEQ
+                | OP_IF_ICMPNE | OP_IF_ICMPLT | OP_IF_ICMPGE | OP_IF_ICMPGT | OP_IF_ICMPLE | OP_IF_ACMPEQ
+                | OP_IF_ACMPNE | OP_IFNULL | OP_IFNONNULL => {
+                    leaders.insert(branch_target(insn));
+                    leaders.insert(next);
+                }
+                OP_TABLESWITCH | OP_LOOKUPSWITCH => {
+                    leaders.insert(branch_target(insn));
+                    for (_, target) in &insn.pairs {
+                        leaders.insert((insn.offset as i32 + target) as u32);
+                    }
+                    leaders.insert(next);
+                }
+                OP_IRETURN | OP_LRETURN | OP_FRETURN | OP_DRETURN | OP_ARETURN | OP_RETURN | OP_ATHROW => {
+                    leaders.insert(next);
+                }
+                OP_JSR | OP_JSR_W | OP_RET => {
+                    return Err(JavaAnalyzeError::InvalidClassData("jsr/ret subroutines are not supported".to_string()));
+                }
+                OP_WIDE if insn.value as u8 == OP_RET => {
+                    return Err(JavaAnalyzeError::InvalidClassData("jsr/ret subroutines are not supported".to_string()));
+                }
+                _ => {}
+            }
+        }
+        for entry in &self.code.exception_table {
+            leaders.insert(entry.start_pc as u32);
+            leaders.insert(entry.end_pc as u32);
+            leaders.insert(entry.handler_pc as u32);
+        }
+        let instruction_offsets: HashSet<u32> = instructions.iter().map(|i| i.offset).collect();
+        let leaders: Vec<u32> = leaders.into_iter().filter(|offset| instruction_offsets.contains(offset)).collect();
+        let block_of: HashMap<u32, usize> = leaders.iter().enumerate().map(|(i, offset)| (*offset, i)).collect();
+        let block_index = |offset: u32| -> Result<usize> {
+            block_of
+                .get(&offset)
+                .copied()
+                .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Branch to invalid offset {}", offset)))
+        };
+
+        // 每个块对应的指令区间
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for (index, insn) in instructions.iter().enumerate().skip(1) {
+            if block_of.contains_key(&insn.offset) {
+                ranges.push(start..index);
+                start = index;
+            }
+        }
+        ranges.push(start..instructions.len());
+
+        // 2. 异常表
+        let mut handlers = Vec::new();
+        let mut handler_blocks: HashMap<usize, Option<String>> = HashMap::new();
+        for entry in &self.code.exception_table {
+            let start = block_index(entry.start_pc as u32)?;
+            let end = if entry.end_pc as u32 >= code_length {
+                leaders.len()
+            } else {
+                block_index(entry.end_pc as u32)?
+            };
+            let handler = block_index(entry.handler_pc as u32)?;
+            let class_name = if entry.catch_type == 0 {
+                None
+            } else {
+                self.class_file.constant_pool.get_class_name(entry.catch_type as usize).cloned()
+            };
+            // multi-catch 时取公共父类 Throwable
+            let catch_descriptor = match (&class_name, handler_blocks.get(&handler)) {
+                (Some(name), None) => Some(name.clone()),
+                (Some(name), Some(Some(existing))) if existing == name => Some(name.clone()),
+                _ => None,
+            };
+            handler_blocks.insert(handler, catch_descriptor);
+            handlers.push(ExceptionHandler {
+                start,
+                end,
+                handler,
+                class_name,
+                variable: String::new(),
+            });
+        }
+
+        // 3. 按控制流顺序模拟每个块
+        let mut blocks: Vec<Option<BasicBlock>> = vec![None; ranges.len()];
+        let mut entry_stacks: Vec<Option<Vec<String>>> = vec![None; ranges.len()];
+        let mut catch_variables: HashMap<usize, String> = HashMap::new();
+        let mut worklist: VecDeque<usize> = VecDeque::new();
+        entry_stacks[0] = Some(vec![]);
+        worklist.push_back(0);
+        let mut handler_order: Vec<usize> = handler_blocks.keys().copied().collect();
+        handler_order.sort();
+        for handler in &handler_order {
+            worklist.push_back(*handler);
+        }
+
+        let mut visited = vec![false; ranges.len()];
+        let mut pending_unreached = (0..ranges.len()).collect::<VecDeque<usize>>();
+        loop {
+            let index = match worklist.pop_front() {
+                Some(index) => index,
+                None => match pending_unreached.pop_front() {
+                    Some(index) => index,
+                    None => break,
+                },
+            };
+            if visited[index] {
+                continue;
+            }
+            visited[index] = true;
+
+            let range = ranges[index].clone();
+            let mut skip_first = false;
+            let mut stack: Vec<Expr> = Vec::new();
+            if let Some(catch_type) = handler_blocks.get(&index) {
+                // 异常处理块入口栈上是异常对象，紧跟的 astore 就是 catch 变量
+                let first = &instructions[range.start];
+                let descriptor = class_descriptor(catch_type.as_deref().unwrap_or("java/lang/Throwable"));
+                let variable = match store_slot(first) {
+                    Some((slot, 'A')) => {
+                        skip_first = true;
+                        let next = instructions.get(range.start + 1).map(|i| i.offset).unwrap_or(code_length);
+                        self.variable(slot, 'A', next, Some(&descriptor))
+                    }
+                    _ => self.unique_name(&format!("ex{}", first.offset)),
+                };
+                self.var_types.entry(variable.clone()).or_insert(descriptor);
+                catch_variables.insert(index, variable.clone());
+                if !skip_first {
+                    stack.push(Expr::Local(variable));
                 }
-                OP_RET => {
-                    panic!("RET instruction not supported");
+            } else if let Some(names) = &entry_stacks[index] {
+                stack.extend(names.iter().map(|name| Expr::Local(name.clone())));
+            }
+
+            let offset = instructions[range.start].offset;
+            let range = if skip_first { range.start + 1..range.end } else { range };
+            let mut statements = Vec::new();
+            let lifted = self.lift_block(&instructions[range.clone()], &mut stack, &mut statements, code_length, &block_index);
+            let terminator = match lifted {
+                Ok(terminator) => terminator,
+                Err(error) => {
+                    statements.push(Stmt::Comment(format!("decompilation error: {:?}", error)));
+                    stack.clear();
+                    Terminator::Exit
                 }
-                OP_PUTSTATIC  | OP_PUTFIELD => {
-                    lastStatementOffset = opcode.offset;
+            };
+
+            let next_block = index + 1;
+            let successors: Vec<usize> = match &terminator {
+                Terminator::FallThrough => vec![next_block],
+                Terminator::Goto(target) => vec![*target],
+                Terminator::If { target, .. } => vec![next_block, *target],
+                Terminator::Switch { cases, default, .. } => {
+                    let mut targets: Vec<usize> = cases.iter().map(|(_, t)| *t).collect();
+                    targets.push(*default);
+                    targets
                 }
-                OP_INVOKEVIRTUAL | OP_INVOKESPECIAL | OP_INVOKESTATIC | OP_INVOKEINTERFACE | OP_INVOKEDYNAMIC => {
-                    let (_, name_and_type_index) = constantpool.get_method_ref(opcode.value as usize).unwrap();
-                    let (_, descriptor_index) = constantpool.get_name_and_type(name_and_type_index as usize).unwrap();
-                    let descriptor = constantpool.get_utf8(descriptor_index as usize).unwrap();
-                    if descriptor.ends_with("V") {
-                        lastStatementOffset  = opcode.offset;
+                Terminator::Exit => vec![],
+            };
+            let successors: Vec<usize> = successors.into_iter().filter(|s| *s < ranges.len()).collect();
+
+            // 块结束时栈上还有值：写入后继块约定的临时变量
+            if !stack.is_empty() {
+                let mut assigned: Vec<Vec<String>> = Vec::new();
+                for successor in &successors {
+                    let names = match &entry_stacks[*successor] {
+                        Some(names) if names.len() == stack.len() => names.clone(),
+                        _ => {
+                            let names: Vec<String> = stack
+                                .iter()
+                                .enumerate()
+                                .map(|(depth, value)| {
+                                    let descriptor = self.type_of(value).unwrap_or_else(|| "Ljava/lang/Object;".to_string());
+                                    self.stack_variable(depth, &descriptor)
+                                })
+                                .collect();
+                            entry_stacks[*successor] = Some(names.clone());
+                            names
+                        }
+                    };
+                    if !assigned.contains(&names) {
+                        for (value, name) in stack.iter().zip(names.iter()) {
+                            if !matches!(value, Expr::Local(local) if local == name) {
+                                statements.push(Stmt::Assign {
+                                    target: Expr::Local(name.clone()),
+                                    value: value.clone(),
+                                });
+                            }
+                        }
+                        assigned.push(names);
                     }
                 }
+            } else {
+                for successor in &successors {
+                    if entry_stacks[*successor].is_none() {
+                        entry_stacks[*successor] = Some(vec![]);
+                    }
+                }
+            }
+            for successor in successors {
+                if !visited[successor] {
+                    worklist.push_back(successor);
+                }
+            }
+
+            blocks[index] = Some(BasicBlock {
+                offset,
+                statements,
+                terminator,
+            });
+        }
+
+        for handler in handlers.iter_mut() {
+            handler.variable = catch_variables.get(&handler.handler).cloned().unwrap_or_else(|| "ex".to_string());
+        }
+
+        // catch 变量如果在别处也被赋值，需要在方法开头声明，catch 参数改用新名字
+        let mut blocks: Vec<BasicBlock> = blocks.into_iter().map(|b| b.expect("every block is lifted")).collect();
+        let mut renamed: HashMap<usize, String> = HashMap::new();
+        for (block, variable) in &catch_variables {
+            if self.stored.contains(variable) {
+                let name = self.unique_name(&format!("{}Ex", variable));
+                blocks[*block].statements.insert(
+                    0,
+                    Stmt::Assign {
+                        target: Expr::Local(variable.clone()),
+                        value: Expr::Local(name.clone()),
+                    },
+                );
+                renamed.insert(*block, name);
+            }
+        }
+        for handler in handlers.iter_mut() {
+            if let Some(name) = renamed.get(&handler.handler) {
+                handler.variable = name.clone();
+            }
+        }
+
+        for block in blocks.iter_mut() {
+            fold_array_initializers(&mut block.statements, &self.temporaries);
+        }
+        self.refine_types(&mut blocks);
+
+        let catch_names: HashSet<&String> = handlers.iter().map(|h| &h.variable).collect();
+        let locals = self
+            .var_order
+            .iter()
+            .filter(|name| !self.parameter_names.contains(*name) && !catch_names.contains(name))
+            .filter(|name| self.stored.contains(*name) || self.temporaries.contains(*name))
+            .map(|name| {
+                let descriptor = self.var_types.get(name).cloned().unwrap_or_else(|| "Ljava/lang/Object;".to_string());
+                (name.clone(), descriptor)
+            })
+            .collect();
+
+        let mut cfg = ControlFlowGraph::new();
+        for block in blocks {
+            cfg.add_block(block);
+        }
+        cfg.handlers = handlers;
+        cfg.temporaries = self.temporaries.clone();
+        Ok(LiftedMethod {
+            cfg,
+            parameters,
+            locals,
+        })
+    }
+
+    /// 没有调试信息的 int 类变量可能实际是 boolean / char / byte / short：
+    /// 把互相赋值的变量归为一组，若组内只出现一种窄类型的用法且没有冲突，就改用该类型
+    fn refine_types(&mut self, blocks: &mut [BasicBlock]) {
+        let named: HashSet<&String> = self
+            .variables
+            .iter()
+            .filter(|(key, _)| matches!(key, VarKey::Named(..)))
+            .map(|(_, name)| name)
+            .collect();
+        let candidates: HashSet<String> = self
+            .var_types
+            .iter()
+            .filter(|(name, descriptor)| {
+                descriptor.as_str() == "I" && !self.parameter_names.contains(*name) && !named.contains(name)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        let mut groups = TypeGroups::default();
+        let (_, return_type) = parse_method_descriptor(&self.method.descriptor);
+        for block in blocks.iter() {
+            for statement in &block.statements {
+                match statement {
+                    Stmt::Assign { target, value } => {
+                        let target_local = match target {
+                            Expr::Local(name) if candidates.contains(name) => Some(name),
+                            _ => None,
+                        };
+                        match (target_local, value) {
+                            (Some(target), Expr::Local(source)) if candidates.contains(source) => groups.union(target, source),
+                            (Some(target), value) => {
+                                if let Some(literals) = int_literals(value) {
+                                    groups.literals(target, literals);
+                                } else {
+                                    groups.require(target, self.type_of(value).as_deref());
+                                }
+                            }
+                            (None, Expr::Local(source)) if candidates.contains(source) => {
+                                groups.require(source, self.type_of(target).as_deref());
+                            }
+                            _ => {}
+                        }
+                    }
+                    Stmt::Return(Some(Expr::Local(name))) if candidates.contains(name) => {
+                        groups.require(name, Some(&return_type));
+                    }
+                    _ => {}
+                }
+                // 调用参数按形参类型约束
+                statement_exprs(statement, &mut |expr| {
+                    let (args, descriptor) = match expr {
+                        Expr::Invoke { args, descriptor, .. } | Expr::New { args, descriptor, .. } => (args, descriptor),
+                        _ => return,
+                    };
+                    let (params, _) = parse_method_descriptor(descriptor);
+                    for (arg, param) in args.iter().zip(params.iter()) {
+                        if let Expr::Local(name) = arg {
+                            if candidates.contains(name) {
+                                groups.require(name, Some(param));
+                            }
+                        }
+                    }
+                });
+            }
+        }
+
+        let mut refined: HashMap<String, String> = HashMap::new();
+        for name in &candidates {
+            if let Some(descriptor) = groups.resolve(name) {
+                refined.insert(name.clone(), descriptor);
+            }
+        }
+        if refined.is_empty() {
+            return;
+        }
+        for (name, descriptor) in &refined {
+            self.var_types.insert(name.clone(), descriptor.clone());
+        }
+
+        // boolean 变量与 0 比较改写为 `x` / `!x`，赋值的常量按新类型书写
+        let simplify = |expr: &mut Expr| {
+            expr.visit_mut(&mut |e| {
+                if let Expr::Binary { op: op @ (BinaryOp::Eq | BinaryOp::Ne), left, right } = e {
+                    if let (Expr::Local(name), Expr::Literal(Literal::Int(0))) = (left.as_ref(), right.as_ref()) {
+                        if refined.get(name).map(String::as_str) == Some("Z") {
+                            let local = Expr::Local(name.clone());
+                            *e = if *op == BinaryOp::Ne { local } else { local.negate() };
+                        }
+                    }
+                }
+            });
+        };
+        for block in blocks.iter_mut() {
+            for statement in block.statements.iter_mut() {
+                if let Stmt::Assign { target: Expr::Local(name), value } = statement {
+                    if let Some(descriptor) = refined.get(name) {
+                        *value = coerce(value.clone(), descriptor);
+                    }
+                }
+                match statement {
+                    Stmt::Expr(expr) | Stmt::Return(Some(expr)) | Stmt::Throw(expr) => simplify(expr),
+                    Stmt::Assign { target, value } => {
+                        simplify(target);
+                        simplify(value);
+                    }
+                    _ => {}
+                }
+            }
+            match &mut block.terminator {
+                Terminator::If { cond, .. } => simplify(cond),
+                Terminator::Switch { selector, .. } => simplify(selector),
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_parameters(&mut self) -> Vec<(String, String)> {
+        let (params, _) = parse_method_descriptor(&self.method.descriptor);
+        let is_static = self.method.access_flags & ACC_STATIC != 0;
+        let mut slot: u16 = if is_static { 0 } else { 1 };
+        let mut parameters = Vec::new();
+        for (index, descriptor) in params.iter().enumerate() {
+            let category = category_of(descriptor);
+            let name = match self.lookup_local(slot, 0) {
+                Some((name, lvt_descriptor)) => {
+                    let name = self.unique_name(&name);
+                    self.variables.insert(VarKey::Named(slot, name.clone(), lvt_descriptor), name.clone());
+                    name
+                }
+                None => self.unique_name(&format!("arg{}", index)),
+            };
+            self.variables.insert(VarKey::Slot(slot, category), name.clone());
+            self.var_types.insert(name.clone(), descriptor.clone());
+            self.parameter_names.insert(name.clone());
+            self.var_order.push(name.clone());
+            parameters.push((name, descriptor.clone()));
+            slot += if is_wide(descriptor) { 2 } else { 1 };
+        }
+        parameters
+    }
+
+    fn lookup_local(&self, slot: u16, pc: u32) -> Option<(String, String)> {
+        self.local_variable_table
+            .iter()
+            .find(|entry| entry.slot == slot && entry.start <= pc && pc < entry.end.max(entry.start + 1))
+            .map(|entry| (entry.name.clone(), entry.descriptor.clone()))
+    }
+
+    fn unique_name(&mut self, base: &str) -> String {
+        let base = if is_java_keyword(base) { format!("{}_", base) } else { base.to_string() };
+        let mut name = base.clone();
+        let mut counter = 2;
+        while self.used_names.contains(&name) {
+            name = format!("{}_{}", base, counter);
+            counter += 1;
+        }
+        self.used_names.insert(name.clone());
+        name
+    }
+
+    /// 局部变量槽位在 pc 处对应的变量名，必要时新建变量
+    fn variable(&mut self, slot: u16, category: char, pc: u32, value_type: Option<&str>) -> String {
+        if let Some((name, descriptor)) = self.lookup_local(slot, pc) {
+            if category_of(&descriptor) == category {
+                let key = VarKey::Named(slot, name.clone(), descriptor.clone());
+                if let Some(existing) = self.variables.get(&key) {
+                    return existing.clone();
+                }
+                let unique = self.unique_name(&name);
+                self.variables.insert(key, unique.clone());
+                self.var_types.insert(unique.clone(), descriptor);
+                self.var_order.push(unique.clone());
+                return unique;
+            }
+        }
+        let key = VarKey::Slot(slot, category);
+        if let Some(existing) = self.variables.get(&key).cloned() {
+            if let Some(value_type) = value_type {
+                self.merge_type(&existing, value_type);
+            }
+            return existing;
+        }
+        let name = self.unique_name(&format!("var{}", slot));
+        self.variables.insert(key, name.clone());
+        let descriptor = value_type.map(|t| t.to_string()).unwrap_or_else(|| match category {
+            'I' => "I".to_string(),
+            'J' => "J".to_string(),
+            'F' => "F".to_string(),
+            'D' => "D".to_string(),
+            _ => "Ljava/lang/Object;".to_string(),
+        });
+        self.var_types.insert(name.clone(), descriptor);
+        self.var_order.push(name.clone());
+        name
+    }
+
+    /// 同一个无名变量被赋予不同引用类型时退化为 Object
+    fn merge_type(&mut self, name: &str, value_type: &str) {
+        if self.parameter_names.contains(name) {
+            return;
+        }
+        if let Some(existing) = self.var_types.get(name) {
+            if existing != value_type && category_of(existing) == 'A' && category_of(value_type) == 'A' {
+                self.var_types.insert(name.to_string(), "Ljava/lang/Object;".to_string());
+            }
+        }
+    }
+
+    fn stack_variable(&mut self, depth: usize, descriptor: &str) -> String {
+        let key = VarKey::Slot(u16::MAX - depth as u16, category_of(descriptor));
+        if let Some(name) = self.variables.get(&key) {
+            let name = name.clone();
+            self.merge_type(&name, descriptor);
+            return name;
+        }
+        let name = self.unique_name(&format!("stack{}", depth));
+        self.variables.insert(key, name.clone());
+        self.var_types.insert(name.clone(), descriptor.to_string());
+        self.var_order.push(name.clone());
+        self.temporaries.insert(name.clone());
+        name
+    }
+
+    fn new_temporary(&mut self, descriptor: &str) -> String {
+        let name = self.unique_name(&format!("tmp{}", self.temp_counter));
+        self.temp_counter += 1;
+        self.var_types.insert(name.clone(), descriptor.to_string());
+        self.var_order.push(name.clone());
+        self.temporaries.insert(name.clone());
+        name
+    }
+
+    /// 把栈上满足条件的值先存入临时变量，保证求值顺序
+    fn spill<F>(&mut self, stack: &mut [Expr], statements: &mut Vec<Stmt>, predicate: F)
+    where
+        F: Fn(&Expr) -> bool,
+    {
+        for value in stack.iter_mut() {
+            if value.is_trivial() || !predicate(value) {
+                continue;
+            }
+            let descriptor = self.type_of(value).unwrap_or_else(|| "Ljava/lang/Object;".to_string());
+            let name = self.new_temporary(&descriptor);
+            statements.push(Stmt::Assign {
+                target: Expr::Local(name.clone()),
+                value: value.clone(),
+            });
+            *value = Expr::Local(name);
+        }
+    }
+
+    /// 需要复制的值如果不是简单表达式，先存入临时变量
+    fn make_trivial(&mut self, value: Expr, statements: &mut Vec<Stmt>) -> Expr {
+        if value.is_trivial() {
+            return value;
+        }
+        let descriptor = self.type_of(&value).unwrap_or_else(|| "Ljava/lang/Object;".to_string());
+        let name = self.new_temporary(&descriptor);
+        statements.push(Stmt::Assign {
+            target: Expr::Local(name.clone()),
+            value,
+        });
+        Expr::Local(name)
+    }
+
+    fn load(&mut self, slot: u16, category: char, pc: u32) -> Expr {
+        if slot == 0 && category == 'A' && self.method.access_flags & ACC_STATIC == 0 && !self.stores_this {
+            return Expr::This;
+        }
+        Expr::Local(self.variable(slot, category, pc, None))
+    }
+
+    fn store(&mut self, slot: u16, category: char, value: Expr, next_pc: u32, stack: &mut [Expr], statements: &mut Vec<Stmt>) {
+        let value_type = self.type_of(&value);
+        let name = self.variable(slot, category, next_pc, value_type.as_deref());
+        self.spill(stack, statements, |expr| expr.reads_local(&name));
+        self.stored.insert(name.clone());
+        let descriptor = self.var_types.get(&name).cloned().unwrap_or_default();
+        statements.push(Stmt::Assign {
+            target: Expr::Local(name),
+            value: coerce(value, &descriptor),
+        });
+    }
+
+    /// 推断表达式的类型（字段描述符）
+    fn type_of(&self, expr: &Expr) -> Option<String> {
+        Some(match expr {
+            Expr::Literal(literal) => match literal {
+                Literal::Null => return None,
+                Literal::Bool(_) => "Z".to_string(),
+                Literal::Int(_) => "I".to_string(),
+                Literal::Long(_) => "J".to_string(),
+                Literal::Float(_) => "F".to_string(),
+                Literal::Double(_) => "D".to_string(),
+                Literal::Char(_) => "C".to_string(),
+                Literal::String(_) => "Ljava/lang/String;".to_string(),
+                Literal::Class(_) => "Ljava/lang/Class;".to_string(),
+            },
+            Expr::Local(name) => return self.var_types.get(name).cloned(),
+            Expr::This => class_descriptor(&self.class_name),
+            Expr::Super => class_descriptor(&self.class_file.super_class_name()?),
+            Expr::Field { descriptor, .. } => descriptor.clone(),
+            Expr::ArrayElement { array, .. } => self.type_of(array)?.strip_prefix('[')?.to_string(),
+            Expr::ArrayLength(_) => "I".to_string(),
+            Expr::Binary { op, left, right } => match op {
+                BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Ge
+                | BinaryOp::Gt
+                | BinaryOp::Le
+                | BinaryOp::LogicalAnd
+                | BinaryOp::LogicalOr => "Z".to_string(),
+                BinaryOp::CompareLong | BinaryOp::CompareFloat => "I".to_string(),
+                BinaryOp::Shl | BinaryOp::Shr | BinaryOp::Ushr => promote(&self.type_of(left)?, "I"),
+                _ => {
+                    let left = self.type_of(left);
+                    let right = self.type_of(right);
+                    match (left, right) {
+                        (Some(l), _) if l == "Ljava/lang/String;" => l,
+                        (_, Some(r)) if r == "Ljava/lang/String;" => r,
+                        (Some(l), Some(r)) if l == "Z" && r == "Z" => l,
+                        (Some(l), Some(r)) => promote(&l, &r),
+                        (Some(t), None) | (None, Some(t)) => promote(&t, "I"),
+                        (None, None) => return None,
+                    }
+                }
+            },
+            Expr::Unary { op: UnaryOp::Not, .. } => "Z".to_string(),
+            Expr::Unary { op: UnaryOp::Neg, operand } => promote(&self.type_of(operand)?, "I"),
+            Expr::Cast { descriptor, .. } => descriptor.clone(),
+            Expr::InstanceOf { .. } => "Z".to_string(),
+            Expr::Invoke { descriptor, .. } => parse_method_descriptor(descriptor).1,
+            Expr::New { class_name, .. } | Expr::Uninitialized(class_name) => class_descriptor(class_name),
+            Expr::NewArray {
+                descriptor,
+                dimensions,
+                extra_dimensions,
+            } => format!("{}{}", "[".repeat(dimensions.len() + extra_dimensions), descriptor),
+            Expr::ArrayInit { descriptor, .. } => format!("[{}", descriptor),
+            Expr::Ternary { then, otherwise, .. } => return self.type_of(then).or_else(|| self.type_of(otherwise)),
+            Expr::MethodRef { .. } => return None,
+        })
+    }
+
+    fn is_category2(&self, expr: &Expr) -> bool {
+        self.type_of(expr).map(|t| is_wide(&t)).unwrap_or(false)
+    }
+
+    /// 弹出 words 个栈槽（long / double 算两个）对应的值，保持原来的顺序
+    fn pop_words(&self, stack: &mut Vec<Expr>, words: usize) -> Result<Vec<Expr>> {
+        let mut values = Vec::new();
+        let mut count = 0;
+        while count < words {
+            let value = pop(stack)?;
+            count += if self.is_category2(&value) { 2 } else { 1 };
+            values.insert(0, value);
+        }
+        if count != words {
+            return Err(JavaAnalyzeError::InvalidClassData("Stack operation splits a long/double value".to_string()));
+        }
+        Ok(values)
+    }
+
+    fn trivial_all(&mut self, values: Vec<Expr>, statements: &mut Vec<Stmt>) -> Vec<Expr> {
+        values.into_iter().map(|v| self.make_trivial(v, statements)).collect()
+    }
+
+    /// 翻译一个块内的指令，返回块的结束方式
+    fn lift_block(
+        &mut self,
+        instructions: &[Instruction],
+        stack: &mut Vec<Expr>,
+        statements: &mut Vec<Stmt>,
+        code_length: u32,
+        block_index: &dyn Fn(u32) -> Result<usize>,
+    ) -> Result<Terminator> {
+        let pool = &self.class_file.constant_pool;
+        for (position, insn) in instructions.iter().enumerate() {
+            let next_pc = instructions.get(position + 1).map(|i| i.offset).unwrap_or_else(|| {
+                // 块的最后一条指令：下一条指令的偏移需要从整个方法中查找
+                self.method
+                    .code
+                    .iter()
+                    .find(|i| i.offset > insn.offset)
+                    .map(|i| i.offset)
+                    .unwrap_or(code_length)
+            });
+            let (opcode, operand) = if insn.opcode == OP_WIDE {
+                (insn.value as u8, insn.value2)
+            } else {
+                (insn.opcode, insn.value)
+            };
+
+            if let Some((slot, category)) = load_slot(opcode, operand) {
+                let value = self.load(slot, category, insn.offset);
+                stack.push(value);
+                continue;
+            }
+            if let Some((slot, category)) = store_slot_of(opcode, operand) {
+                let value = pop(stack)?;
+                self.store(slot, category, value, next_pc, stack, statements);
+                continue;
+            }
+
+            match opcode {
+                OP_NOP | OP_BREAKPOINT => {}
+                OP_ACONST_NULL => stack.push(Expr::Literal(Literal::Null)),
+                OP_ICONST_M1 | OP_ICONST_0 | OP_ICONST_1 | OP_ICONST_2 | OP_ICONST_3 | OP_ICONST_4 | OP_ICONST_5 => {
+                    stack.push(Expr::int(opcode as i32 - OP_ICONST_0 as i32))
+                }
+                OP_LCONST_0 | OP_LCONST_1 => stack.push(Expr::Literal(Literal::Long((opcode - OP_LCONST_0) as i64))),
+                OP_FCONST_0 | OP_FCONST_1 | OP_FCONST_2 => {
+                    stack.push(Expr::Literal(Literal::Float((opcode - OP_FCONST_0) as f32)))
+                }
+                OP_DCONST_0 | OP_DCONST_1 => stack.push(Expr::Literal(Literal::Double((opcode - OP_DCONST_0) as f64))),
+                OP_BIPUSH | OP_SIPUSH => stack.push(Expr::int(operand)),
+                OP_LDC | OP_LDC_W | OP_LDC2_W => {
+                    let literal = match pool.get_entry(operand as usize) {
+                        Some(ConstantPoolEntry::Integer(v)) => Literal::Int(*v),
+                        Some(ConstantPoolEntry::Float(v)) => Literal::Float(*v),
+                        Some(ConstantPoolEntry::Long(v)) => Literal::Long(*v),
+                        Some(ConstantPoolEntry::Double(v)) => Literal::Double(*v),
+                        Some(ConstantPoolEntry::StringRef(_)) => Literal::String(pool.get_string(operand as usize).cloned().unwrap_or_default()),
+                        Some(ConstantPoolEntry::ClassRef(_)) => {
+                            Literal::Class(class_descriptor(pool.get_class_name(operand as usize).map(|s| s.as_str()).unwrap_or("java/lang/Object")))
+                        }
+                        _ => {
+                            return Err(JavaAnalyzeError::InvalidClassData(format!("Unsupported ldc constant #{}", operand)));
+                        }
+                    };
+                    stack.push(Expr::Literal(literal));
+                }
+
+                OP_IALOAD | OP_LALOAD | OP_FALOAD | OP_DALOAD | OP_AALOAD | OP_BALOAD | OP_CALOAD | OP_SALOAD => {
+                    let index = pop(stack)?;
+                    let array = pop(stack)?;
+                    stack.push(match self.strings.get(&insn.offset) {
+                        Some(value) => Expr::Literal(Literal::String(value.clone())),
+                        None => Expr::ArrayElement {
+                            array: Box::new(array),
+                            index: Box::new(index),
+                        },
+                    });
+                }
+                OP_IASTORE | OP_LASTORE | OP_FASTORE | OP_DASTORE | OP_AASTORE | OP_BASTORE | OP_CASTORE | OP_SASTORE => {
+                    let value = pop(stack)?;
+                    let index = pop(stack)?;
+                    let array = pop(stack)?;
+                    let element = self.type_of(&array).and_then(|t| t.strip_prefix('[').map(|s| s.to_string())).unwrap_or_default();
+                    self.spill(stack, statements, |e| e.has_side_effects() || contains_array_access(e));
+                    statements.push(Stmt::Assign {
+                        target: Expr::ArrayElement {
+                            array: Box::new(array),
+                            index: Box::new(index),
+                        },
+                        value: coerce(value, &element),
+                    });
+                }
+
+                OP_POP | OP_POP2 => {
+                    let words = if opcode == OP_POP { 1 } else { 2 };
+                    for value in self.pop_words(stack, words)? {
+                        if value.has_side_effects() && !matches!(value, Expr::Uninitialized(_)) {
+                            statements.push(Stmt::Expr(value));
+                        }
+                    }
+                }
+                OP_DUP | OP_DUP2 => {
+                    let words = if opcode == OP_DUP { 1 } else { 2 };
+                    let values = self.pop_words(stack, words)?;
+                    let values = self.trivial_all(values, statements);
+                    stack.extend(values.iter().cloned());
+                    stack.extend(values);
+                }
+                OP_DUP_X1 | OP_DUP_X2 | OP_DUP2_X1 | OP_DUP2_X2 => {
+                    let (top_words, under_words) = match opcode {
+                        OP_DUP_X1 => (1, 1),
+                        OP_DUP_X2 => (1, 2),
+                        OP_DUP2_X1 => (2, 1),
+                        _ => (2, 2),
+                    };
+                    let top = self.pop_words(stack, top_words)?;
+                    let under = self.pop_words(stack, under_words)?;
+                    let top = self.trivial_all(top, statements);
+                    let under = self.trivial_all(under, statements);
+                    stack.extend(top.iter().cloned());
+                    stack.extend(under);
+                    stack.extend(top);
+                }
+                OP_SWAP => {
+                    let a = pop(stack)?;
+                    let b = pop(stack)?;
+                    stack.push(a);
+                    stack.push(b);
+                }
+
+                OP_IADD | OP_LADD | OP_FADD | OP_DADD => self.binary(stack, BinaryOp::Add)?,
+                OP_ISUB | OP_LSUB | OP_FSUB | OP_DSUB => self.binary(stack, BinaryOp::Sub)?,
+                OP_IMUL | OP_LMUL | OP_FMUL | OP_DMUL => self.binary(stack, BinaryOp::Mul)?,
+                OP_IDIV | OP_LDIV | OP_FDIV | OP_DDIV => self.binary(stack, BinaryOp::Div)?,
+                OP_IREM | OP_LREM | OP_FREM | OP_DREM => self.binary(stack, BinaryOp::Rem)?,
+                OP_ISHL | OP_LSHL => self.binary(stack, BinaryOp::Shl)?,
+                OP_ISHR | OP_LSHR => self.binary(stack, BinaryOp::Shr)?,
+                OP_IUSHR | OP_LUSHR => self.binary(stack, BinaryOp::Ushr)?,
+                OP_IAND | OP_LAND => self.binary(stack, BinaryOp::And)?,
+                OP_IOR | OP_LOR => self.binary(stack, BinaryOp::Or)?,
+                OP_IXOR | OP_LXOR => self.binary(stack, BinaryOp::Xor)?,
+                OP_LCMP => self.binary(stack, BinaryOp::CompareLong)?,
+                OP_FCMPL | OP_FCMPG | OP_DCMPL | OP_DCMPG => self.binary(stack, BinaryOp::CompareFloat)?,
+                OP_INEG | OP_LNEG | OP_FNEG | OP_DNEG => {
+                    let operand = pop(stack)?;
+                    stack.push(Expr::Unary {
+                        op: UnaryOp::Neg,
+                        operand: Box::new(operand),
+                    });
+                }
                 OP_IINC => {
-                    panic!("IINC instruction not supported");
+                    let (slot, increment) = if insn.opcode == OP_WIDE {
+                        (insn.value2 as u16, insn.pairs.first().map(|(v, _)| *v).unwrap_or(0))
+                    } else {
+                        (insn.value as u16, insn.value2)
+                    };
+                    let name = self.variable(slot, 'I', insn.offset, None);
+                    self.spill(stack, statements, |expr| expr.reads_local(&name));
+                    self.stored.insert(name.clone());
+                    let (op, amount) = if increment < 0 { (BinaryOp::Sub, -increment) } else { (BinaryOp::Add, increment) };
+                    statements.push(Stmt::Assign {
+                        target: Expr::Local(name.clone()),
+                        value: Expr::binary(op, Expr::Local(name), Expr::int(amount)),
+                    });
+                }
+
+                OP_I2L | OP_F2L | OP_D2L => self.cast(stack, "J")?,
+                OP_I2F | OP_L2F | OP_D2F => self.cast(stack, "F")?,
+                OP_I2D | OP_L2D | OP_F2D => self.cast(stack, "D")?,
+                OP_L2I | OP_F2I | OP_D2I => self.cast(stack, "I")?,
+                OP_I2B => self.cast(stack, "B")?,
+                OP_I2C => self.cast(stack, "C")?,
+                OP_I2S => self.cast(stack, "S")?,
+
+                OP_IFEQ | OP_IFNE | OP_IFLT | OP_IFGE | OP_IFGT | OP_IFLE => {
+                    let value = pop(stack)?;
+                    let op = relational_op(opcode - OP_IFEQ);
+                    let cond = match value {
+                        Expr::Binary {
+                            op: BinaryOp::CompareLong | BinaryOp::CompareFloat,
+                            left,
+                            right,
+                        } => Expr::Binary { op, left, right },
+                        value if self.type_of(&value).as_deref() == Some("Z") && matches!(op, BinaryOp::Eq | BinaryOp::Ne) => {
+                            if op == BinaryOp::Ne { value } else { value.negate() }
+                        }
+                        value => Expr::binary(op, value, Expr::int(0)),
+                    };
+                    return Ok(Terminator::If {
+                        cond,
+                        target: block_index(branch_target(insn))?,
+                    });
+                }
+                OP_IF_ICMPEQ | OP_IF_ICMPNE | OP_IF_ICMPLT | OP_IF_ICMPGE | OP_IF_ICMPGT | OP_IF_ICMPLE => {
+                    let right = pop(stack)?;
+                    let left = pop(stack)?;
+                    let left_type = self.type_of(&left).unwrap_or_default();
+                    let right_type = self.type_of(&right).unwrap_or_default();
+                    let right = coerce(right, &left_type);
+                    let left = coerce(left, &right_type);
+                    return Ok(Terminator::If {
+                        cond: Expr::binary(relational_op(opcode - OP_IF_ICMPEQ), left, right),
+                        target: block_index(branch_target(insn))?,
+                    });
+                }
+                OP_IF_ACMPEQ | OP_IF_ACMPNE => {
+                    let right = pop(stack)?;
+                    let left = pop(stack)?;
+                    let op = if opcode == OP_IF_ACMPEQ { BinaryOp::Eq } else { BinaryOp::Ne };
+                    return Ok(Terminator::If {
+                        cond: Expr::binary(op, left, right),
+                        target: block_index(branch_target(insn))?,
+                    });
+                }
+                OP_IFNULL | OP_IFNONNULL => {
+                    let value = pop(stack)?;
+                    let op = if opcode == OP_IFNULL { BinaryOp::Eq } else { BinaryOp::Ne };
+                    return Ok(Terminator::If {
+                        cond: Expr::binary(op, value, Expr::Literal(Literal::Null)),
+                        target: block_index(branch_target(insn))?,
+                    });
+                }
+                OP_GOTO | OP_GOTO_W => return Ok(Terminator::Goto(block_index(branch_target(insn))?)),
+                OP_TABLESWITCH | OP_LOOKUPSWITCH => {
+                    let selector = pop(stack)?;
+                    let mut cases = Vec::new();
+                    for (key, target) in &insn.pairs {
+                        cases.push((*key, block_index((insn.offset as i32 + target) as u32)?));
+                    }
+                    return Ok(Terminator::Switch {
+                        selector,
+                        cases,
+                        default: block_index(branch_target(insn))?,
+                    });
+                }
+
+                OP_IRETURN | OP_LRETURN | OP_FRETURN | OP_DRETURN | OP_ARETURN => {
+                    let value = pop(stack)?;
+                    let (_, return_type) = parse_method_descriptor(&self.method.descriptor);
+                    statements.push(Stmt::Return(Some(coerce(value, &return_type))));
+                    return Ok(Terminator::Exit);
+                }
+                OP_RETURN => {
+                    statements.push(Stmt::Return(None));
+                    return Ok(Terminator::Exit);
+                }
+                OP_ATHROW => {
+                    let value = pop(stack)?;
+                    statements.push(Stmt::Throw(value));
+                    return Ok(Terminator::Exit);
+                }
+
+                OP_GETSTATIC | OP_GETFIELD => {
+                    let (owner, name, descriptor) = member_ref(pool, operand)?;
+                    let target = if opcode == OP_GETFIELD { Some(Box::new(pop(stack)?)) } else { None };
+                    if let Some(value) = self.strings.get(&insn.offset) {
+                        stack.push(Expr::Literal(Literal::String(value.clone())));
+                        continue;
+                    }
+                    stack.push(Expr::Field {
+                        target,
+                        owner,
+                        name,
+                        descriptor,
+                    });
+                }
+                OP_PUTSTATIC | OP_PUTFIELD => {
+                    let (owner, name, descriptor) = member_ref(pool, operand)?;
+                    let value = pop(stack)?;
+                    let target = if opcode == OP_PUTFIELD { Some(Box::new(pop(stack)?)) } else { None };
+                    self.spill(stack, statements, |e| e.has_side_effects() || reads_field(e, &name));
+                    statements.push(Stmt::Assign {
+                        target: Expr::Field {
+                            target,
+                            owner,
+                            name,
+                            descriptor: descriptor.clone(),
+                        },
+                        value: coerce(value, &descriptor),
+                    });
+                }
+
+                OP_INVOKEVIRTUAL | OP_INVOKESPECIAL | OP_INVOKESTATIC | OP_INVOKEINTERFACE => {
+                    let (owner, name, descriptor) = member_ref(pool, operand)?;
+                    let (params, return_type) = parse_method_descriptor(&descriptor);
+                    let mut args = Vec::new();
+                    for param in params.iter().rev() {
+                        args.insert(0, coerce(pop(stack)?, param));
+                    }
+                    let kind = match opcode {
+                        OP_INVOKESTATIC => InvokeKind::Static,
+                        OP_INVOKESPECIAL => InvokeKind::Special,
+                        OP_INVOKEINTERFACE => InvokeKind::Interface,
+                        _ => InvokeKind::Virtual,
+                    };
+                    if kind == InvokeKind::Static {
+                        // 常量参数的解密调用，参数没有副作用，可以整体替换
+                        if let Some(value) = self.strings.get(&insn.offset) {
+                            stack.push(Expr::Literal(Literal::String(value.clone())));
+                            continue;
+                        }
+                        self.push_invoke(stack, statements, Expr::Invoke {
+                            kind,
+                            target: None,
+                            owner,
+                            name,
+                            descriptor,
+                            args,
+                        }, &return_type);
+                        continue;
+                    }
+                    let target = pop(stack)?;
+                    if name == "<init>" {
+                        match target {
+                            Expr::Uninitialized(class_name) => {
+                                let created = Expr::New {
+                                    class_name: class_name.clone(),
+                                    descriptor,
+                                    args,
+                                };
+                                // 用构造结果替换栈上 dup 出来的未初始化对象
+                                match stack.iter().rposition(|e| *e == Expr::Uninitialized(class_name.clone())) {
+                                    Some(position) => stack[position] = created,
+                                    None => statements.push(Stmt::Expr(created)),
+                                }
+                            }
+                            target => {
+                                // 构造器中调用 super(...) / this(...)
+                                let target = if matches!(target, Expr::This) && owner != self.class_name { Expr::Super } else { target };
+                                self.spill(stack, statements, |e| e.has_side_effects());
+                                statements.push(Stmt::Expr(Expr::Invoke {
+                                    kind,
+                                    target: Some(Box::new(target)),
+                                    owner,
+                                    name,
+                                    descriptor,
+                                    args,
+                                }));
+                            }
+                        }
+                        continue;
+                    }
+                    let target = if kind == InvokeKind::Special && matches!(target, Expr::This) && owner != self.class_name {
+                        Expr::Super
+                    } else {
+                        target
+                    };
+                    self.push_invoke(stack, statements, Expr::Invoke {
+                        kind,
+                        target: Some(Box::new(target)),
+                        owner,
+                        name,
+                        descriptor,
+                        args,
+                    }, &return_type);
                 }
-                OP_GOTO | OP_GOTO_W => {
-                    panic!("GOTO instruction not supported");
+                OP_INVOKEDYNAMIC => {
+                    let expr = self.invoke_dynamic(operand, stack)?;
+                    let (_, name, descriptor) = pool
+                        .get_invoke_dynamic(operand as usize)
+                        .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid invokedynamic #{}", operand)))?;
+                    let _ = name;
+                    let (_, return_type) = parse_method_descriptor(descriptor);
+                    self.push_invoke(stack, statements, expr, &return_type);
                 }
+
+                OP_NEW => {
+                    let class_name = class_name(pool, operand)?;
+                    stack.push(Expr::Uninitialized(class_name));
+                }
+                OP_NEWARRAY => {
+                    let count = pop(stack)?;
+                    let descriptor = ["Z", "C", "F", "D", "B", "S", "I", "J"].get(operand as usize).copied().unwrap_or("I");
+                    stack.push(Expr::NewArray {
+                        descriptor: descriptor.to_string(),
+                        dimensions: vec![count],
+                        extra_dimensions: 0,
+                    });
+                }
+                OP_ANEWARRAY => {
+                    let count = pop(stack)?;
+                    let element = class_descriptor(&class_name(pool, operand)?);
+                    stack.push(Expr::NewArray {
+                        descriptor: element,
+                        dimensions: vec![count],
+                        extra_dimensions: 0,
+                    });
+                }
+                OP_MULTIANEWARRAY => {
+                    let array_type = class_name(pool, operand)?;
+                    let total = array_type.chars().take_while(|c| *c == '[').count();
+                    let count = insn.value2 as usize;
+                    let mut dimensions = Vec::new();
+                    for _ in 0..count {
+                        dimensions.insert(0, pop(stack)?);
+                    }
+                    stack.push(Expr::NewArray {
+                        descriptor: array_type[total..].to_string(),
+                        dimensions,
+                        extra_dimensions: total.saturating_sub(count),
+                    });
+                }
+                OP_ARRAYLENGTH => {
+                    let array = pop(stack)?;
+                    stack.push(Expr::ArrayLength(Box::new(array)));
+                }
+                OP_CHECKCAST => {
+                    let operand_expr = pop(stack)?;
+                    let descriptor = class_descriptor(&class_name(pool, operand)?);
+                    if self.type_of(&operand_expr).as_deref() == Some(descriptor.as_str()) {
+                        stack.push(operand_expr);
+                    } else {
+                        stack.push(Expr::Cast {
+                            descriptor,
+                            operand: Box::new(operand_expr),
+                        });
+                    }
+                }
+                OP_INSTANCEOF => {
+                    let operand_expr = pop(stack)?;
+                    stack.push(Expr::InstanceOf {
+                        operand: Box::new(operand_expr),
+                        descriptor: class_descriptor(&class_name(pool, operand)?),
+                    });
+                }
+                OP_MONITORENTER | OP_MONITOREXIT => {
+                    let lock = pop(stack)?;
+                    let keyword = if opcode == OP_MONITORENTER { "monitorenter" } else { "monitorexit" };
+                    statements.push(Stmt::Comment(format!("{}({})", keyword, crate::java_analyzer::ast::expr_to_string(&lock))));
+                }
+                _ => {
+                    return Err(JavaAnalyzeError::InvalidClassData(format!(
+                        "Unsupported instruction {} at {}",
+                        opcode_name(opcode),
+                        insn.offset
+                    )));
+                }
+            }
+        }
+        Ok(Terminator::FallThrough)
+    }
+
+    fn binary(&self, stack: &mut Vec<Expr>, op: BinaryOp) -> Result<()> {
+        let right = pop(stack)?;
+        let left = pop(stack)?;
+        stack.push(Expr::binary(op, left, right));
+        Ok(())
+    }
+
+    fn cast(&self, stack: &mut Vec<Expr>, descriptor: &str) -> Result<()> {
+        let operand = pop(stack)?;
+        stack.push(Expr::Cast {
+            descriptor: descriptor.to_string(),
+            operand: Box::new(operand),
+        });
+        Ok(())
+    }
+
+    /// void 调用直接成为语句，其余压栈
+    fn push_invoke(&mut self, stack: &mut Vec<Expr>, statements: &mut Vec<Stmt>, invoke: Expr, return_type: &str) {
+        if return_type == "V" {
+            self.spill(stack, statements, |e| e.has_side_effects() || contains_field_or_array(e));
+            statements.push(Stmt::Expr(invoke));
+        } else {
+            stack.push(invoke);
+        }
+    }
+
+    /// invokedynamic：字符串拼接和 lambda 还原成源码写法，其余保留为调用
+    fn invoke_dynamic(&self, index: i32, stack: &mut Vec<Expr>) -> Result<Expr> {
+        let pool = &self.class_file.constant_pool;
+        let (bootstrap_index, name, descriptor) = pool
+            .get_invoke_dynamic(index as usize)
+            .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid invokedynamic #{}", index)))?;
+        let (params, return_type) = parse_method_descriptor(descriptor);
+        let mut args = Vec::new();
+        for param in params.iter().rev() {
+            args.insert(0, coerce(pop(stack)?, param));
+        }
+
+        let bootstrap = self.class_file.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::BootstrapMethods(methods) => methods.bootstrap_methods.get(bootstrap_index as usize),
+            _ => None,
+        });
+        let handle = bootstrap.and_then(|b| pool.get_method_handle(b.bootstrap_method_ref as usize));
+        let bootstrap_args: Vec<u16> = bootstrap.map(|b| b.bootstrap_arguments.clone()).unwrap_or_default();
+
+        match handle {
+            Some((_, owner, bsm_name, _)) if owner == "java/lang/invoke/StringConcatFactory" => {
+                let mut parts: Vec<Expr> = Vec::new();
+                let mut args = args.into_iter();
+                if bsm_name == "makeConcatWithConstants" {
+                    let recipe = bootstrap_args.first().and_then(|i| pool.get_string(*i as usize)).cloned().unwrap_or_default();
+                    let mut constants = bootstrap_args.iter().skip(1);
+                    let mut text = String::new();
+                    for c in recipe.chars() {
+                        match c {
+                            '\u{1}' | '\u{2}' => {
+                                if !text.is_empty() {
+                                    parts.push(Expr::Literal(Literal::String(std::mem::take(&mut text))));
+                                }
+                                let value = if c == '\u{1}' {
+                                    args.next()
+                                } else {
+                                    constants
+                                        .next()
+                                        .and_then(|i| pool.get_string(*i as usize))
+                                        .map(|s| Expr::Literal(Literal::String(s.clone())))
+                                };
+                                parts.extend(value);
+                            }
+                            c => text.push(c),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Expr::Literal(Literal::String(text)));
+                    }
+                } else {
+                    parts.extend(args);
+                }
+                let is_string = |e: &Expr| self.type_of(e).as_deref() == Some("Ljava/lang/String;");
+                if parts.len() < 2 || !(is_string(&parts[0]) || is_string(&parts[1])) {
+                    parts.insert(0, Expr::Literal(Literal::String(String::new())));
+                }
+                let mut parts = parts.into_iter();
+                let first = parts.next().unwrap_or(Expr::Literal(Literal::String(String::new())));
+                Ok(parts.fold(first, |acc, part| Expr::binary(BinaryOp::Add, acc, part)))
+            }
+            Some((_, owner, _, _)) if owner == "java/lang/invoke/LambdaMetafactory" => {
+                // 第二个参数是实现方法
+                let implementation = bootstrap_args.get(1).and_then(|i| pool.get_method_handle(*i as usize));
+                match implementation {
+                    Some((kind, impl_owner, impl_name, _)) => {
+                        let mut args = args;
+                        // 绑定接收者的实例方法引用：`target::name`
+                        let target = if !args.is_empty() && kind != 6 && kind != 8 {
+                            Some(Box::new(args.remove(0)))
+                        } else {
+                            None
+                        };
+                        if args.is_empty() {
+                            Ok(Expr::MethodRef {
+                                target,
+                                owner: impl_owner.clone(),
+                                name: impl_name.clone(),
+                            })
+                        } else {
+                            // 捕获了额外参数的 lambda，保留为对合成方法的调用
+                            if let Some(target) = target {
+                                args.insert(0, *target);
+                            }
+                            Ok(Expr::Invoke {
+                                kind: InvokeKind::Static,
+                                target: None,
+                                owner: impl_owner.clone(),
+                                name: impl_name.clone(),
+                                descriptor: format!("(){}", return_type),
+                                args,
+                            })
+                        }
+                    }
+                    None => Err(JavaAnalyzeError::InvalidClassData("Invalid lambda bootstrap arguments".to_string())),
+                }
+            }
+            Some((_, owner, _, _)) => Ok(Expr::Invoke {
+                kind: InvokeKind::Static,
+                target: None,
+                owner: owner.clone(),
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                args,
+            }),
+            None => Err(JavaAnalyzeError::InvalidClassData(format!("Missing bootstrap method #{}", bootstrap_index))),
+        }
+    }
+}
+
+fn pop(stack: &mut Vec<Expr>) -> Result<Expr> {
+    stack
+        .pop()
+        .ok_or_else(|| JavaAnalyzeError::InvalidClassData("Operand stack underflow".to_string()))
+}
+
+fn branch_target(insn: &Instruction) -> u32 {
+    (insn.offset as i32 + insn.value) as u32
+}
+
+fn member_ref(pool: &crate::java_analyzer::constantpool::ConstantPool, index: i32) -> Result<(String, String, String)> {
+    pool.get_member_ref(index as usize)
+        .map(|(owner, name, descriptor)| (owner.clone(), name.clone(), descriptor.clone()))
+        .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid member reference #{}", index)))
+}
+
+fn class_name(pool: &crate::java_analyzer::constantpool::ConstantPool, index: i32) -> Result<String> {
+    pool.get_class_name(index as usize)
+        .cloned()
+        .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid class reference #{}", index)))
+}
+
+/// 0..6 依次对应 eq, ne, lt, ge, gt, le
+fn relational_op(index: u8) -> BinaryOp {
+    match index {
+        0 => BinaryOp::Eq,
+        1 => BinaryOp::Ne,
+        2 => BinaryOp::Lt,
+        3 => BinaryOp::Ge,
+        4 => BinaryOp::Gt,
+        _ => BinaryOp::Le,
+    }
+}
+
+/// 二元数值运算的结果类型
+fn promote(left: &str, right: &str) -> String {
+    for wide in ["D", "F", "J"] {
+        if left == wide || right == wide {
+            return wide.to_string();
+        }
+    }
+    if left.starts_with('L') || left.starts_with('[') {
+        return left.to_string();
+    }
+    "I".to_string()
+}
+
+/// 局部变量槽位的类别：I / J / F / D / A
+fn category_of(descriptor: &str) -> char {
+    match descriptor.chars().next() {
+        Some('Z' | 'B' | 'C' | 'S' | 'I') => 'I',
+        Some('J') => 'J',
+        Some('F') => 'F',
+        Some('D') => 'D',
+        _ => 'A',
+    }
+}
+
+fn load_slot(opcode: u8, operand: i32) -> Option<(u16, char)> {
+    Some(match opcode {
+        OP_ILOAD => (operand as u16, 'I'),
+        OP_LLOAD => (operand as u16, 'J'),
+        OP_FLOAD => (operand as u16, 'F'),
+        OP_DLOAD => (operand as u16, 'D'),
+        OP_ALOAD => (operand as u16, 'A'),
+        OP_ILOAD_0..=OP_ILOAD_3 => ((opcode - OP_ILOAD_0) as u16, 'I'),
+        OP_LLOAD_0..=OP_LLOAD_3 => ((opcode - OP_LLOAD_0) as u16, 'J'),
+        OP_FLOAD_0..=OP_FLOAD_3 => ((opcode - OP_FLOAD_0) as u16, 'F'),
+        OP_DLOAD_0..=OP_DLOAD_3 => ((opcode - OP_DLOAD_0) as u16, 'D'),
+        OP_ALOAD_0..=OP_ALOAD_3 => ((opcode - OP_ALOAD_0) as u16, 'A'),
+        _ => return None,
+    })
+}
+
+fn store_slot_of(opcode: u8, operand: i32) -> Option<(u16, char)> {
+    Some(match opcode {
+        OP_ISTORE => (operand as u16, 'I'),
+        OP_LSTORE => (operand as u16, 'J'),
+        OP_FSTORE => (operand as u16, 'F'),
+        OP_DSTORE => (operand as u16, 'D'),
+        OP_ASTORE => (operand as u16, 'A'),
+        OP_ISTORE_0..=OP_ISTORE_3 => ((opcode - OP_ISTORE_0) as u16, 'I'),
+        OP_LSTORE_0..=OP_LSTORE_3 => ((opcode - OP_LSTORE_0) as u16, 'J'),
+        OP_FSTORE_0..=OP_FSTORE_3 => ((opcode - OP_FSTORE_0) as u16, 'F'),
+        OP_DSTORE_0..=OP_DSTORE_3 => ((opcode - OP_DSTORE_0) as u16, 'D'),
+        OP_ASTORE_0..=OP_ASTORE_3 => ((opcode - OP_ASTORE_0) as u16, 'A'),
+        _ => return None,
+    })
+}
+
+fn store_slot(insn: &Instruction) -> Option<(u16, char)> {
+    if insn.opcode == OP_WIDE {
+        store_slot_of(insn.value as u8, insn.value2)
+    } else {
+        store_slot_of(insn.opcode, insn.value)
+    }
+}
+
+/// `t = new T[n]; t[0] = a; ... t[n - 1] = z;` -> `t = new T[] {a, ..., z};`
+///
+/// 中间只允许出现其他临时变量的赋值，此时元素值必须没有副作用，保证求值顺序不变
+fn fold_array_initializers(statements: &mut Vec<Stmt>, temporaries: &HashSet<String>) {
+    let mut index = 0;
+    while index < statements.len() {
+        let (name, descriptor, length) = match &statements[index] {
+            Stmt::Assign {
+                target: Expr::Local(name),
+                value: Expr::NewArray { descriptor, dimensions, extra_dimensions: 0 },
+            } if temporaries.contains(name) => match dimensions.as_slice() {
+                [Expr::Literal(Literal::Int(length))] if *length > 0 => (name.clone(), descriptor.clone(), *length as usize),
                 _ => {
-                   // panic!("Unknown opcode: {}", opcode.opcode);
+                    index += 1;
+                    continue;
                 }
+            },
+            _ => {
+                index += 1;
+                continue;
             }
+        };
+
+        let mut values = Vec::new();
+        let mut interleaved = false;
+        let mut cursor = index + 1;
+        while values.len() < length && cursor < statements.len() {
+            match &statements[cursor] {
+                Stmt::Assign {
+                    target: Expr::ArrayElement { array, index: element },
+                    value,
+                } if matches!(array.as_ref(), Expr::Local(a) if *a == name)
+                    && matches!(element.as_ref(), Expr::Literal(Literal::Int(i)) if *i as usize == values.len())
+                    && !value.reads_local(&name) =>
+                {
+                    values.push(value.clone());
+                }
+                Stmt::Assign { target: Expr::Local(other), value }
+                    if *other != name && temporaries.contains(other) && !value.reads_local(&name) =>
+                {
+                    interleaved = true;
+                }
+                _ => break,
+            }
+            cursor += 1;
+        }
+        let reordered = interleaved && values.iter().any(|v| v.has_side_effects() || contains_field_or_array(v));
+        if values.len() != length || reordered {
             index += 1;
+            continue;
         }
 
-        let mut last_index = 0;
-        
-        
-        Ok(ControlFlowGraph {
-            blocks,
-            edges: vec![],
-        })
+        // 初始化表达式放在最后一个元素赋值的位置，中间的临时变量赋值保持原有顺序
+        let kept: Vec<Stmt> = statements[index + 1..cursor]
+            .iter()
+            .filter(|s| matches!(s, Stmt::Assign { target: Expr::Local(_), .. }))
+            .cloned()
+            .collect();
+        let kept_count = kept.len();
+        let mut replacement = kept;
+        replacement.push(Stmt::Assign {
+            target: Expr::Local(name),
+            value: Expr::ArrayInit { descriptor, values },
+        });
+        statements.splice(index..cursor, replacement);
+        index += kept_count + 1;
     }
+}
+
+/// 无调试信息变量的类型分组（并查集），记录每组需要的窄类型
+#[derive(Default)]
+struct TypeGroups {
+    parent: HashMap<String, String>,
+    required: HashMap<String, HashSet<String>>,
+    literals: HashMap<String, Vec<i32>>,
+    conflicts: HashSet<String>,
+}
+
+impl TypeGroups {
+    fn find(&mut self, name: &str) -> String {
+        let parent = self.parent.get(name).cloned().unwrap_or_else(|| name.to_string());
+        if parent == name {
+            return parent;
+        }
+        let root = self.find(&parent);
+        self.parent.insert(name.to_string(), root.clone());
+        root
+    }
+
+    fn union(&mut self, a: &str, b: &str) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        let required = self.required.remove(&b).unwrap_or_default();
+        self.required.entry(a.clone()).or_default().extend(required);
+        let literals = self.literals.remove(&b).unwrap_or_default();
+        self.literals.entry(a.clone()).or_default().extend(literals);
+        if self.conflicts.remove(&b) {
+            self.conflicts.insert(a.clone());
+        }
+        self.parent.insert(b, a);
+    }
+
+    /// 其他 int 类型的用法（或未知类型）都视为冲突，保持 int
+    fn require(&mut self, name: &str, descriptor: Option<&str>) {
+        let root = self.find(name);
+        match descriptor {
+            Some(descriptor @ ("Z" | "C" | "B" | "S")) => {
+                self.required.entry(root).or_default().insert(descriptor.to_string());
+            }
+            _ => {
+                self.conflicts.insert(root);
+            }
+        }
+    }
+
+    fn literals(&mut self, name: &str, values: Vec<i32>) {
+        let root = self.find(name);
+        self.literals.entry(root).or_default().extend(values);
+    }
+
+    fn resolve(&mut self, name: &str) -> Option<String> {
+        let root = self.find(name);
+        if self.conflicts.contains(&root) {
+            return None;
+        }
+        let required = self.required.get(&root)?;
+        if required.len() != 1 {
+            return None;
+        }
+        let descriptor = required.iter().next()?.clone();
+        let range = match descriptor.as_str() {
+            "Z" => 0..=1,
+            "C" => 0..=0xFFFF,
+            "B" => -128..=127,
+            _ => -32768..=32767,
+        };
+        let literals = self.literals.get(&root).map(Vec::as_slice).unwrap_or_default();
+        literals.iter().all(|value| range.contains(value)).then_some(descriptor)
+    }
+}
+
+/// int 常量或只由 int 常量组成的条件表达式
+fn int_literals(expr: &Expr) -> Option<Vec<i32>> {
+    match expr {
+        Expr::Literal(Literal::Int(value)) => Some(vec![*value]),
+        Expr::Ternary { then, otherwise, .. } => {
+            let mut values = int_literals(then)?;
+            values.extend(int_literals(otherwise)?);
+            Some(values)
+        }
+        _ => None,
+    }
+}
+
+/// 访问平铺语句中的所有表达式
+fn statement_exprs(statement: &Stmt, f: &mut dyn FnMut(&Expr)) {
+    match statement {
+        Stmt::Expr(expr) | Stmt::Return(Some(expr)) | Stmt::Throw(expr) => expr.visit(f),
+        Stmt::Assign { target, value } => {
+            target.visit(f);
+            value.visit(f);
+        }
+        _ => {}
+    }
+}
+
+/// 按目标类型调整常量写法：boolean 的 0/1 写成 false/true，char 写成字符常量
+pub(crate) fn coerce(expr: Expr, descriptor: &str) -> Expr {
+    match descriptor {
+        "Z" => match expr {
+            Expr::Literal(Literal::Int(0)) => Expr::Literal(Literal::Bool(false)),
+            Expr::Literal(Literal::Int(1)) => Expr::Literal(Literal::Bool(true)),
+            Expr::Ternary { cond, then, otherwise } => {
+                let then = coerce(*then, descriptor);
+                let otherwise = coerce(*otherwise, descriptor);
+                match (&then, &otherwise) {
+                    (Expr::Literal(Literal::Bool(true)), Expr::Literal(Literal::Bool(false))) => *cond,
+                    (Expr::Literal(Literal::Bool(false)), Expr::Literal(Literal::Bool(true))) => cond.negate(),
+                    _ => Expr::Ternary {
+                        cond,
+                        then: Box::new(then),
+                        otherwise: Box::new(otherwise),
+                    },
+                }
+            }
+            expr => expr,
+        },
+        "C" => match expr {
+            Expr::Literal(Literal::Int(value)) if (0..=0xFFFF).contains(&value) => Expr::Literal(Literal::Char(value as u16)),
+            expr => expr,
+        },
+        _ => expr,
+    }
+}
+
+fn contains_array_access(expr: &Expr) -> bool {
+    let mut found = false;
+    expr.visit(&mut |e| found |= matches!(e, Expr::ArrayElement { .. }));
+    found
+}
+
+fn contains_field_or_array(expr: &Expr) -> bool {
+    let mut found = false;
+    expr.visit(&mut |e| found |= matches!(e, Expr::ArrayElement { .. } | Expr::Field { .. }));
+    found
+}
+
+fn reads_field(expr: &Expr, field: &str) -> bool {
+    let mut found = false;
+    expr.visit(&mut |e| found |= matches!(e, Expr::Field { name, .. } if name == field));
+    found
+}
 
-    
+fn is_java_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "abstract" | "assert" | "boolean" | "break" | "byte" | "case" | "catch" | "char" | "class" | "const"
+            | "continue" | "default" | "do" | "double" | "else" | "enum" | "extends" | "final" | "finally"
+            | "float" | "for" | "goto" | "if" | "implements" | "import" | "instanceof" | "int" | "interface"
+            | "long" | "native" | "new" | "package" | "private" | "protected" | "public" | "return" | "short"
+            | "static" | "strictfp" | "super" | "switch" | "synchronized" | "this" | "throw" | "throws"
+            | "transient" | "try" | "void" | "volatile" | "while" | "true" | "false" | "null"
+    )
 }
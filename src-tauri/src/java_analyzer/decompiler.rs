@@ -1,12 +1,39 @@
+use std::collections::{HashMap, HashSet};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
 use crate::java_analyzer::{
+    ast::{escape_string, java_class_name, java_type, parse_method_descriptor, Expr, JavaWriter, Stmt},
+    attributes::Attribute,
     classfile::ClassFile,
-    controlflow::ControlFlowGraph,
-    controlflowbuilder::ControlFlowGraphBuilder,
-    error::{Result, JavaAnalyzeError},
+    constantpool::ConstantPoolEntry,
+    controlflow::simplify,
+    controlflowbuilder::{coerce, ControlFlowGraphBuilder},
+    error::{JavaAnalyzeError, Result},
+    method::JvmMethod,
 };
 
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_PROTECTED: u16 = 0x0004;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_SYNCHRONIZED: u16 = 0x0020;
+const ACC_VOLATILE: u16 = 0x0040;
+const ACC_BRIDGE: u16 = 0x0040;
+const ACC_TRANSIENT: u16 = 0x0080;
+const ACC_VARARGS: u16 = 0x0080;
+const ACC_NATIVE: u16 = 0x0100;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_STRICT: u16 = 0x0800;
+const ACC_SYNTHETIC: u16 = 0x1000;
+const ACC_ANNOTATION: u16 = 0x2000;
+const ACC_ENUM: u16 = 0x4000;
+
 pub struct Decompiler<'a> {
-    classfile: &'a ClassFile
+    classfile: &'a ClassFile,
+    /// (方法名, 描述符, 指令偏移) -> 替换该指令结果的字符串字面量
+    strings: HashMap<(String, String, u32), String>,
 }
 
 impl<'a> Decompiler<'a> {
@@ -14,22 +41,660 @@ impl<'a> Decompiler<'a> {
     pub fn new(classfile: &'a ClassFile) -> Self {
         Decompiler {
             classfile,
+            strings: HashMap::new(),
         }
     }
 
-    pub fn decompile_method(&self, method: &str) -> Result<String> {
-        self.classfile.methods.iter()
-            .find(|m| m.name == method)
-            .map(|m| {
+    /// 用反混淆还原出的字符串替换解密调用和字符串表读取
+    pub fn with_strings(mut self, strings: HashMap<(String, String, u32), String>) -> Self {
+        self.strings = strings;
+        self
+    }
+
+    /// 把整个类输出为 Java 源码，单个方法失败时在方法体中以注释说明
+    pub fn decompile_class(&self) -> Result<String> {
+        let class_file = self.classfile;
+        let class_name = class_file
+            .class_name()
+            .ok_or_else(|| JavaAnalyzeError::InvalidClassData("Missing this_class".to_string()))?;
+        let flags = class_file.access_flags;
+        let is_enum = flags & ACC_ENUM != 0;
+        let is_interface = flags & ACC_INTERFACE != 0;
 
-                let controlflowbuilder = ControlFlowGraph::new();
-                controlflowbuilder.build(m, self.classfile).unwrap();
-                let mut result = String::new();
-                result.push_str(&format!("Method: {}\n", m.name));
-                result.push_str(&format!("Descriptor: {}\n", m.descriptor));
-                result.push_str(&format!("Access Flags: {}\n", m.access_flags));
-                result
-            })
-            .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Method {} not found", method)))
+        let mut writer = JavaWriter::new(0);
+        if let Some(source_file) = class_file.attributes.iter().find_map(|a| match a {
+            Attribute::SourceFile(source) => class_file.constant_pool.get_utf8(source.sourcefile_index as usize),
+            _ => None,
+        }) {
+            writer.line(&format!("// Source file: {}", source_file));
+        }
+        if let Some((package, _)) = class_name.rsplit_once('/') {
+            writer.line(&format!("package {};", package.replace('/', ".")));
+            writer.line("");
+        }
+
+        let mut header = class_modifiers(flags);
+        let simple_name = simple_class_name(&class_name);
+        header.push_str(&simple_name);
+        let super_class = class_file.super_class_name();
+        let interfaces = class_file.interface_names();
+        if !is_interface && !is_enum {
+            if let Some(super_class) = super_class.as_deref().filter(|s| *s != "java/lang/Object") {
+                header.push_str(&format!(" extends {}", java_class_name(super_class)));
+            }
+        }
+        let interfaces: Vec<String> = interfaces
+            .iter()
+            .filter(|i| !(flags & ACC_ANNOTATION != 0 && *i == "java/lang/annotation/Annotation"))
+            .map(|i| java_class_name(i))
+            .collect();
+        if !interfaces.is_empty() {
+            let keyword = if is_interface { "extends" } else { "implements" };
+            header.push_str(&format!(" {} {}", keyword, interfaces.join(", ")));
+        }
+        writer.line(&format!("{} {{", header));
+        writer.indent();
+
+        let mut first_member = true;
+        if is_enum {
+            let constants: Vec<&str> = class_file
+                .fields
+                .iter()
+                .filter(|f| f.access_flags & ACC_ENUM != 0)
+                .map(|f| f.name.as_str())
+                .collect();
+            writer.line(&format!("{};", constants.join(", ")));
+            first_member = false;
+        }
+
+        // 字段
+        let mut wrote_field = false;
+        for field in &class_file.fields {
+            if is_enum && (field.access_flags & ACC_ENUM != 0 || field.name == "$VALUES") {
+                continue;
+            }
+            if first_member {
+                first_member = false;
+            } else if !wrote_field {
+                writer.line("");
+            }
+            wrote_field = true;
+            let mut line = String::new();
+            if field.access_flags & ACC_SYNTHETIC != 0 {
+                line.push_str("/* synthetic */ ");
+            }
+            let field_flags = if is_interface {
+                field.access_flags & !(ACC_PUBLIC | ACC_STATIC | ACC_FINAL)
+            } else {
+                field.access_flags
+            };
+            line.push_str(&field_modifiers(field_flags));
+            line.push_str(&format!("{} {}", java_type(&field.descriptor), field.name));
+            let constant = field.attributes.iter().find_map(|a| match a {
+                Attribute::ConstantValue(value) => self.constant_literal(value.constant_value_index, &field.descriptor),
+                _ => None,
+            });
+            if let Some(constant) = constant {
+                line.push_str(&format!(" = {}", constant));
+            }
+            line.push(';');
+            writer.line(&line);
+        }
+
+        // 方法
+        for method in &class_file.methods {
+            if is_enum && method.access_flags & ACC_STATIC != 0 {
+                if (method.name == "values" || method.name == "$values") && method.descriptor.starts_with("()") {
+                    continue;
+                }
+                if method.name == "valueOf" && method.descriptor.starts_with("(Ljava/lang/String;)") {
+                    continue;
+                }
+            }
+            let text = self.method_source(method, &simple_name, is_interface, is_enum);
+            let Some(text) = text else {
+                continue;
+            };
+            if first_member {
+                first_member = false;
+            } else {
+                writer.line("");
+            }
+            for line in text.lines() {
+                writer.line(line);
+            }
+        }
+
+        writer.dedent();
+        writer.line("}");
+        Ok(writer.finish())
+    }
+
+    /// 单个方法的源码，返回 None 表示省略该方法（例如空的静态初始化块）
+    fn method_source(&self, method: &JvmMethod, simple_name: &str, is_interface: bool, is_enum: bool) -> Option<String> {
+        let flags = method.access_flags;
+        let is_static_init = method.name == "<clinit>";
+        let is_constructor = method.name == "<init>";
+        let (params, return_type) = parse_method_descriptor(&method.descriptor);
+        // 枚举构造器的前两个参数（name、ordinal）由编译器添加
+        let hidden_params = if is_enum && is_constructor && params.len() >= 2 { 2 } else { 0 };
+        let has_body = flags & (ACC_ABSTRACT | ACC_NATIVE) == 0;
+
+        let lifted = if has_body {
+            match catch_unwind(AssertUnwindSafe(|| self.method_body(method, is_enum))) {
+                Ok(result) => Some(result),
+                Err(_) => Some(Err(JavaAnalyzeError::InvalidClassData("decompiler panicked".to_string()))),
+            }
+        } else {
+            None
+        };
+
+        // 编译器生成的空构造器和空静态初始化块不输出
+        if let Some(Ok((_, body))) = &lifted {
+            let is_empty = body.iter().all(|s| matches!(s, Stmt::Label(_)));
+            let is_default_constructor = is_constructor && params.len() == hidden_params && (flags & ACC_PRIVATE == 0 || is_enum);
+            if is_empty && (is_static_init || is_default_constructor) {
+                return None;
+            }
+        }
+
+        let mut writer = JavaWriter::new(0);
+        if flags & (ACC_SYNTHETIC | ACC_BRIDGE) != 0 && !is_static_init {
+            writer.line(if flags & ACC_BRIDGE != 0 { "// bridge method" } else { "// synthetic method" });
+        }
+        let mut header = String::new();
+        if is_static_init {
+            header.push_str("static");
+        } else {
+            let mut modifier_flags = flags;
+            if is_interface {
+                modifier_flags &= !ACC_PUBLIC;
+                if flags & ACC_ABSTRACT != 0 {
+                    modifier_flags &= !ACC_ABSTRACT;
+                } else if flags & ACC_STATIC == 0 && flags & ACC_PRIVATE == 0 {
+                    header.push_str("default ");
+                }
+            }
+            header.insert_str(0, &method_modifiers(modifier_flags));
+
+            let parameter_names: Vec<String> = match &lifted {
+                Some(Ok((names, _))) => names.clone(),
+                _ => (0..params.len()).map(|i| format!("arg{}", i)).collect(),
+            };
+            let mut parameters = Vec::new();
+            for (index, (descriptor, name)) in params.iter().zip(parameter_names.iter()).enumerate().skip(hidden_params) {
+                let mut ty = java_type(descriptor);
+                if flags & ACC_VARARGS != 0 && index + 1 == params.len() && ty.ends_with("[]") {
+                    ty.truncate(ty.len() - 2);
+                    ty.push_str("...");
+                }
+                parameters.push(format!("{} {}", ty, name));
+            }
+            if is_constructor {
+                header.push_str(simple_name);
+            } else {
+                header.push_str(&format!("{} {}", java_type(&return_type), method.name));
+            }
+            header.push_str(&format!("({})", parameters.join(", ")));
+
+            let exceptions: Vec<String> = method
+                .attributes
+                .iter()
+                .filter_map(|a| match a {
+                    Attribute::Exceptions(exceptions) => Some(&exceptions.exception_index_table),
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|index| self.classfile.constant_pool.get_class_name(*index as usize))
+                .map(|name| java_class_name(name))
+                .collect();
+            if !exceptions.is_empty() {
+                header.push_str(&format!(" throws {}", exceptions.join(", ")));
+            }
+        }
+
+        match lifted {
+            None => writer.line(&format!("{};", header)),
+            Some(Ok((_, body))) => {
+                writer.line(&format!("{} {{", header));
+                writer.indent();
+                writer.write_body(&body);
+                writer.dedent();
+                writer.line("}");
+            }
+            Some(Err(error)) => {
+                writer.line(&format!("{} {{", header));
+                writer.indent();
+                writer.line(&format!("// Decompilation failed: {}", format_error(&error)));
+                writer.line("throw new UnsupportedOperationException();");
+                writer.dedent();
+                writer.line("}");
+            }
+        }
+        Some(writer.finish())
     }
-}
\ No newline at end of file
+
+    /// 翻译并结构化方法体，返回参数名和语句
+    fn method_body(&self, method: &JvmMethod, is_enum: bool) -> Result<(Vec<String>, Vec<Stmt>)> {
+        let strings = self
+            .strings
+            .iter()
+            .filter(|((name, descriptor, _), _)| *name == method.name && *descriptor == method.descriptor)
+            .map(|((_, _, offset), value)| (*offset, value.clone()))
+            .collect();
+        let lifted = ControlFlowGraphBuilder::new(self.classfile, method)?.with_strings(strings).build()?;
+        let mut cfg = lifted.cfg;
+        cfg.merge_conditions();
+        let mut body = cfg.structure();
+        simplify(&mut body, &cfg.temporaries);
+
+        if matches!(body.last(), Some(Stmt::Return(None))) {
+            body.pop();
+        }
+        if method.name == "<init>" {
+            remove_implicit_super(&mut body, is_enum);
+        }
+        if method.name == "<clinit>" && is_enum {
+            body.retain(|stmt| !is_enum_constant_init(stmt, self.classfile));
+        }
+
+        let (_, return_type) = parse_method_descriptor(&method.descriptor);
+        coerce_returns(&mut body, &return_type);
+        declare_locals(&mut body, &lifted.locals);
+        let parameters = lifted.parameters.into_iter().map(|(name, _)| name).collect();
+        Ok((parameters, body))
+    }
+
+    fn constant_literal(&self, index: u16, descriptor: &str) -> Option<String> {
+        Some(match self.classfile.constant_pool.get_entry(index as usize)? {
+            ConstantPoolEntry::Integer(value) => match descriptor {
+                "Z" => (*value != 0).to_string(),
+                "C" => match char::from_u32(*value as u32) {
+                    Some(c) if !c.is_control() && c != '\'' && c != '\\' => format!("'{}'", c),
+                    _ => format!("(char) {}", value),
+                },
+                _ => value.to_string(),
+            },
+            ConstantPoolEntry::Long(value) => format!("{}L", value),
+            ConstantPoolEntry::Float(value) => format!("{:?}F", value),
+            ConstantPoolEntry::Double(value) => format!("{:?}", value),
+            ConstantPoolEntry::StringRef(_) => {
+                format!("\"{}\"", escape_string(self.classfile.constant_pool.get_string(index as usize)?))
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// 枚举静态初始化块中给常量和 $VALUES 赋值的语句
+fn is_enum_constant_init(stmt: &Stmt, class_file: &ClassFile) -> bool {
+    let class_name = class_file.class_name().unwrap_or_default();
+    match stmt {
+        Stmt::Assign { target: Expr::Field { target: None, owner, name, .. }, .. } if *owner == class_name => {
+            name == "$VALUES"
+                || class_file
+                    .fields
+                    .iter()
+                    .any(|f| f.name == *name && f.access_flags & ACC_ENUM != 0)
+        }
+        _ => false,
+    }
+}
+
+/// 去掉构造器开头隐式的 super()，以及枚举构造器中编译器生成的 super(name, ordinal)
+pub(crate) fn remove_implicit_super(body: &mut Vec<Stmt>, is_enum: bool) {
+    let implicit_super = body.iter().position(|stmt| match stmt {
+        Stmt::Expr(Expr::Invoke { target: Some(target), name, args, .. }) => {
+            **target == Expr::Super && name == "<init>" && (args.is_empty() || is_enum)
+        }
+        _ => false,
+    });
+    if let Some(position) = implicit_super {
+        if body[..position].iter().all(|s| matches!(s, Stmt::Label(_) | Stmt::Comment(_))) {
+            body.remove(position);
+        }
+    }
+}
+
+/// 内联临时变量后，返回值可能变成 `c ? 1 : 0` 这样的形式，按返回类型重新调整
+pub(crate) fn coerce_returns(stmts: &mut [Stmt], return_type: &str) {
+    for stmt in stmts.iter_mut() {
+        match stmt {
+            Stmt::Return(Some(value)) => *value = coerce(value.clone(), return_type),
+            Stmt::If { then, otherwise, .. } => {
+                coerce_returns(then, return_type);
+                coerce_returns(otherwise, return_type);
+            }
+            Stmt::While { body, .. } | Stmt::DoWhile { body, .. } => coerce_returns(body, return_type),
+            Stmt::Switch { cases, .. } => cases.iter_mut().for_each(|case| coerce_returns(&mut case.body, return_type)),
+            Stmt::Try { body, catches } => {
+                coerce_returns(body, return_type);
+                catches.iter_mut().for_each(|catch| coerce_returns(&mut catch.body, return_type));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 把局部变量的第一次顶层赋值改成声明，其余在方法开头声明
+pub(crate) fn declare_locals(body: &mut Vec<Stmt>, locals: &[(String, String)]) {
+    let mut used = HashSet::new();
+    for stmt in body.iter() {
+        collect_names(stmt, &mut used);
+    }
+    let locals: Vec<&(String, String)> = locals.iter().filter(|(name, _)| used.contains(name)).collect();
+    let wanted: HashSet<&str> = locals.iter().map(|(name, _)| name.as_str()).collect();
+
+    let mut seen = HashSet::new();
+    let mut declared = HashSet::new();
+    for stmt in body.iter_mut() {
+        if let Stmt::Assign { target: Expr::Local(name), value } = stmt {
+            if wanted.contains(name.as_str()) && !seen.contains(name) && !value.reads_local(name) {
+                let descriptor = locals.iter().find(|(n, _)| n == name).map(|(_, d)| d.clone()).unwrap_or_default();
+                declared.insert(name.clone());
+                *stmt = Stmt::Declare {
+                    name: name.clone(),
+                    value: Some(coerce(value.clone(), &descriptor)),
+                    descriptor,
+                };
+            }
+        }
+        collect_names(stmt, &mut seen);
+    }
+
+    let declarations: Vec<Stmt> = locals
+        .iter()
+        .filter(|(name, _)| !declared.contains(name))
+        .map(|(name, descriptor)| Stmt::Declare {
+            descriptor: descriptor.clone(),
+            name: name.clone(),
+            value: None,
+        })
+        .collect();
+    body.splice(0..0, declarations);
+}
+
+/// 语句（含嵌套语句）中出现的所有局部变量名
+fn collect_names(stmt: &Stmt, names: &mut HashSet<String>) {
+    let mut add = |expr: &Expr| {
+        expr.visit(&mut |e| {
+            if let Expr::Local(name) = e {
+                names.insert(name.clone());
+            }
+        })
+    };
+    let mut exprs: Vec<Expr> = Vec::new();
+    stmt.visit(&mut |s| match s {
+        Stmt::Expr(e) | Stmt::Throw(e) | Stmt::Return(Some(e)) => exprs.push(e.clone()),
+        Stmt::Assign { target, value } => {
+            exprs.push(target.clone());
+            exprs.push(value.clone());
+        }
+        Stmt::Declare { name, value, .. } => {
+            exprs.push(Expr::Local(name.clone()));
+            if let Some(value) = value {
+                exprs.push(value.clone());
+            }
+        }
+        Stmt::If { cond, .. } | Stmt::While { cond, .. } | Stmt::DoWhile { cond, .. } => exprs.push(cond.clone()),
+        Stmt::Switch { selector, .. } => exprs.push(selector.clone()),
+        _ => {}
+    });
+    for expr in &exprs {
+        add(expr);
+    }
+}
+
+pub(crate) fn format_error(error: &JavaAnalyzeError) -> String {
+    match error {
+        JavaAnalyzeError::InvalidClassData(message) => message.clone(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// `com/foo/Outer$Inner` -> `Outer$Inner`
+pub(crate) fn simple_class_name(class_name: &str) -> String {
+    class_name.rsplit('/').next().unwrap_or(class_name).to_string()
+}
+
+pub(crate) fn class_modifiers(flags: u16) -> String {
+    let mut parts = Vec::new();
+    if flags & ACC_PUBLIC != 0 { parts.push("public"); }
+    if flags & ACC_ANNOTATION != 0 {
+        parts.push("@interface");
+    } else if flags & ACC_INTERFACE != 0 {
+        parts.push("interface");
+    } else if flags & ACC_ENUM != 0 {
+        parts.push("enum");
+    } else {
+        if flags & ACC_ABSTRACT != 0 { parts.push("abstract"); }
+        if flags & ACC_FINAL != 0 { parts.push("final"); }
+        parts.push("class");
+    }
+    parts.push("");
+    parts.join(" ")
+}
+
+pub(crate) fn field_modifiers(flags: u16) -> String {
+    let mut parts = Vec::new();
+    if flags & ACC_PUBLIC != 0 { parts.push("public"); }
+    if flags & ACC_PRIVATE != 0 { parts.push("private"); }
+    if flags & ACC_PROTECTED != 0 { parts.push("protected"); }
+    if flags & ACC_STATIC != 0 { parts.push("static"); }
+    if flags & ACC_FINAL != 0 { parts.push("final"); }
+    if flags & ACC_VOLATILE != 0 { parts.push("volatile"); }
+    if flags & ACC_TRANSIENT != 0 { parts.push("transient"); }
+    parts.push("");
+    parts.join(" ")
+}
+
+pub(crate) fn method_modifiers(flags: u16) -> String {
+    let mut parts = Vec::new();
+    if flags & ACC_PUBLIC != 0 { parts.push("public"); }
+    if flags & ACC_PRIVATE != 0 { parts.push("private"); }
+    if flags & ACC_PROTECTED != 0 { parts.push("protected"); }
+    if flags & ACC_ABSTRACT != 0 { parts.push("abstract"); }
+    if flags & ACC_STATIC != 0 { parts.push("static"); }
+    if flags & ACC_FINAL != 0 { parts.push("final"); }
+    if flags & ACC_SYNCHRONIZED != 0 { parts.push("synchronized"); }
+    if flags & ACC_NATIVE != 0 { parts.push("native"); }
+    if flags & ACC_STRICT != 0 { parts.push("strictfp"); }
+    if parts.is_empty() {
+        return String::new();
+    }
+    parts.push("");
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::java_analyzer::classfile::ClassFileReader;
+
+    /// 一个静态方法：名字、描述符、max_stack、max_locals、字节码和异常表 (start, end, handler)
+    struct TestMethod {
+        name: &'static str,
+        descriptor: &'static str,
+        max_stack: u16,
+        max_locals: u16,
+        code: Vec<u8>,
+        handlers: Vec<(u16, u16, u16)>,
+    }
+
+    fn utf8(pool: &mut Vec<u8>, count: &mut u16, text: &str) -> u16 {
+        pool.push(1);
+        pool.extend((text.len() as u16).to_be_bytes());
+        pool.extend(text.as_bytes());
+        *count += 1;
+        *count
+    }
+
+    /// 拼出只含静态方法的 `public class T`，异常表的 catch_type 都是 0（finally）
+    fn class_file(methods: &[TestMethod]) -> ClassFile {
+        let mut pool = Vec::new();
+        let mut count = 0;
+        let this_name = utf8(&mut pool, &mut count, "T");
+        pool.extend([7, 0, this_name as u8]);
+        count += 1;
+        let this_class = count;
+        let super_name = utf8(&mut pool, &mut count, "java/lang/Object");
+        pool.extend([7, 0, super_name as u8]);
+        count += 1;
+        let super_class = count;
+        let code_name = utf8(&mut pool, &mut count, "Code");
+
+        let mut body = Vec::new();
+        body.extend([0x00, 0x21]);
+        body.extend(this_class.to_be_bytes());
+        body.extend(super_class.to_be_bytes());
+        body.extend([0, 0, 0, 0]);
+        body.extend((methods.len() as u16).to_be_bytes());
+        for method in methods {
+            let name = utf8(&mut pool, &mut count, method.name);
+            let descriptor = utf8(&mut pool, &mut count, method.descriptor);
+            let mut code = Vec::new();
+            code.extend(method.max_stack.to_be_bytes());
+            code.extend(method.max_locals.to_be_bytes());
+            code.extend((method.code.len() as u32).to_be_bytes());
+            code.extend(&method.code);
+            code.extend((method.handlers.len() as u16).to_be_bytes());
+            for (start, end, handler) in &method.handlers {
+                for value in [*start, *end, *handler, 0] {
+                    code.extend(value.to_be_bytes());
+                }
+            }
+            code.extend([0, 0]);
+            body.extend([0x00, 0x09]);
+            body.extend(name.to_be_bytes());
+            body.extend(descriptor.to_be_bytes());
+            body.extend([0, 1]);
+            body.extend(code_name.to_be_bytes());
+            body.extend((code.len() as u32).to_be_bytes());
+            body.extend(code);
+        }
+        body.extend([0, 0]);
+
+        let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52];
+        bytes.extend((count + 1).to_be_bytes());
+        bytes.extend(pool);
+        bytes.extend(body);
+        ClassFileReader::new(&bytes).read().unwrap()
+    }
+
+    fn decompile(method: TestMethod) -> String {
+        Decompiler::new(&class_file(&[method])).decompile_class().unwrap()
+    }
+
+    /// 没有局部变量表，参数和局部变量按槽位命名
+    fn method(name: &'static str, descriptor: &'static str, max_locals: u16, code: Vec<u8>) -> TestMethod {
+        TestMethod {
+            name,
+            descriptor,
+            max_stack: 2,
+            max_locals,
+            code,
+            handlers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn loops_become_while_statements() {
+        // int s = 0; for (int i = 0; i < n; i++) s += i; return s;
+        let code = vec![
+            0x03, 0x3c, 0x03, 0x3d, // iconst_0, istore_1, iconst_0, istore_2
+            0x1c, 0x1a, 0xa2, 0x00, 0x0d, // 4: iload_2, iload_0, if_icmpge 19
+            0x1b, 0x1c, 0x60, 0x3c, // iload_1, iload_2, iadd, istore_1
+            0x84, 0x02, 0x01, 0xa7, 0xff, 0xf4, // iinc 2 1, goto 4
+            0x1b, 0xac, // 19: iload_1, ireturn
+        ];
+        assert_eq!(
+            decompile(method("sum", "(I)I", 3, code)),
+            r#"public class T {
+    public static int sum(int arg0) {
+        int var1 = 0;
+        int var2 = 0;
+        while (var2 < arg0) {
+            var1 += var2;
+            var2++;
+        }
+        return var1;
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn tableswitch_becomes_a_switch() {
+        // iload_0, tableswitch 0..1，跳转偏移相对于 tableswitch
+        let mut code = vec![0x1a, 0xaa, 0, 0];
+        for value in [29i32, 0, 1, 23, 26] {
+            code.extend(value.to_be_bytes());
+        }
+        // 24: bipush 10, ireturn; 27: bipush 20, ireturn; 30: iconst_0, ireturn
+        code.extend([0x10, 10, 0xac, 0x10, 20, 0xac, 0x03, 0xac]);
+        assert_eq!(
+            decompile(method("pick", "(I)I", 1, code)),
+            r#"public class T {
+    public static int pick(int arg0) {
+        switch (arg0) {
+            case 0:
+                return 10;
+            case 1:
+                return 20;
+        }
+        return 0;
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn short_circuit_conditions_are_merged() {
+        // if (a > 0 || b > 0) return 1; return 2;
+        let code = vec![0x1a, 0x9d, 0x00, 0x07, 0x1b, 0x9e, 0x00, 0x05, 0x04, 0xac, 0x05, 0xac];
+        assert_eq!(
+            decompile(method("either", "(II)I", 2, code)),
+            r#"public class T {
+    public static int either(int arg0, int arg1) {
+        if (arg0 > 0 || arg1 > 0) {
+            return 1;
+        }
+        return 2;
+    }
+}
+"#
+        );
+        // return a > 0 && b > 0;
+        let code = vec![0x1a, 0x9e, 0x00, 0x0b, 0x1b, 0x9e, 0x00, 0x07, 0x04, 0xa7, 0x00, 0x04, 0x03, 0xac];
+        assert!(decompile(method("both", "(II)Z", 2, code)).contains("        return arg0 > 0 && arg1 > 0;\n"));
+    }
+
+    #[test]
+    fn conditional_values_become_ternaries() {
+        // return a > b ? a : b;
+        let code = vec![0x1a, 0x1b, 0xa4, 0x00, 0x07, 0x1a, 0xa7, 0x00, 0x04, 0x1b, 0xac];
+        assert!(decompile(method("max", "(II)I", 2, code)).contains("        return arg0 > arg1 ? arg0 : arg1;\n"));
+    }
+
+    #[test]
+    fn finally_handlers_become_catch_any() {
+        // try { a = a / 2; } finally { a++; } return a;
+        let code = vec![
+            0x1a, 0x05, 0x6c, 0x3b, // iload_0, iconst_2, idiv, istore_0
+            0x84, 0x00, 0x01, 0xa7, 0x00, 0x09, // iinc 0 1, goto 16
+            0x4c, 0x84, 0x00, 0x01, 0x2b, 0xbf, // 10: astore_1, iinc 0 1, aload_1, athrow
+            0x1a, 0xac, // 16: iload_0, ireturn
+        ];
+        let source = decompile(TestMethod {
+            handlers: vec![(0, 4, 10)],
+            ..method("guarded", "(I)I", 2, code)
+        });
+        // finally 的副本在正常路径和异常路径上各出现一次，按顺序逐行查找
+        let mut lines = source.lines().map(str::trim);
+        for expected in ["try {", "arg0 /= 2;", "} catch (Throwable var1) {", "arg0++;", "throw var1;", "return arg0;"] {
+            assert!(lines.any(|line| line == expected), "{} missing in\n{}", expected, source);
+        }
+    }
+}
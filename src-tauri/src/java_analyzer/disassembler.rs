@@ -2,21 +2,34 @@ use crate::java_analyzer::classfile::{ClassFile, ClassFileReader};
 use crate::java_analyzer::constantpool::ConstantPoolEntry;
 use crate::java_analyzer::attributes::Attribute;
 use crate::java_analyzer::opcode::*;
+use std::collections::HashMap;
 use std::result::Result;
 
 pub struct ClassFileDisassembler {
     class_file: ClassFile,
+    /// (方法名, 描述符, 指令偏移) -> 附加在指令后面的注释
+    comments: HashMap<(String, String, u32), String>,
 }
 
 impl ClassFileDisassembler {
     pub fn new(data: Vec<u8>) -> Result<Self, String> {
         let reader = ClassFileReader::new(&data);
         match reader.read() {
-            Ok(class_file) => Ok(Self { class_file }),
+            Ok(class_file) => Ok(Self { class_file, comments: HashMap::new() }),
             Err(e) => Err(format!("Failed to parse class file: {:?}", e)),
         }
     }
 
+    /// 反汇编已经解析（可能经过改写）的 class 文件
+    pub fn from_class_file(class_file: ClassFile) -> Self {
+        Self { class_file, comments: HashMap::new() }
+    }
+
+    pub fn with_comments(mut self, comments: HashMap<(String, String, u32), String>) -> Self {
+        self.comments = comments;
+        self
+    }
+
     pub fn disassemble(&self) -> Result<String, String> {
         let mut output = String::new();
         
@@ -190,7 +203,11 @@ impl ClassFileDisassembler {
                     output.push_str("    Bytecode:\n");
                     for instruction in &method.code {
                         let formatted = self.format_instruction(instruction);
-                        output.push_str(&format!("      {}: {}\n", instruction.offset, formatted));
+                        let key = (method.name.clone(), method.descriptor.clone(), instruction.offset);
+                        match self.comments.get(&key) {
+                            Some(comment) => output.push_str(&format!("      {}: {}  // {}\n", instruction.offset, formatted, comment)),
+                            None => output.push_str(&format!("      {}: {}\n", instruction.offset, formatted)),
+                        }
                     }
                 }
             }
@@ -552,8 +569,8 @@ impl ClassFileDisassembler {
                 
                 for (i, (_, target)) in instruction.pairs.iter().enumerate() {
                     let case_value = instruction.value2 + i as i32;
-                    let target_addr = instruction.offset as i32 + (*target as i32);
-                    result.push_str(&format!("         {}: {} // +{}\n", case_value, target_addr, *target as i32));
+                    let target_addr = instruction.offset as i32 + *target;
+                    result.push_str(&format!("         {}: {} // +{}\n", case_value, target_addr, target));
                 }
                 result.push_str("     }");
                 result
@@ -563,8 +580,8 @@ impl ClassFileDisassembler {
                 result.push_str(&format!("         default: {} // +{}\n", instruction.offset as i32 + instruction.value, instruction.value));
                 
                 for (key, target) in &instruction.pairs {
-                    let target_addr = instruction.offset as i32 + (*target as i32);
-                    result.push_str(&format!("         {}: {} // +{}\n", *key as i32, target_addr, *target as i32));
+                    let target_addr = instruction.offset as i32 + *target;
+                    result.push_str(&format!("         {}: {} // +{}\n", key, target_addr, target));
                 }
                 result.push_str("     }");
                 result
@@ -585,7 +602,7 @@ impl ClassFileDisassembler {
                     OP_ASTORE => format!("wide astore {}", instruction.value2),
                     OP_IINC => {
                         // For wide iinc, value2 is the index and pairs[0] contains the increment value
-                        let increment = instruction.pairs.get(0).map(|(_, v)| *v as i16).unwrap_or(0);
+                        let increment = instruction.pairs.get(0).map(|(v, _)| *v).unwrap_or(0);
                         format!("wide iinc {}, {}", instruction.value2, increment)
                     },
                     OP_RET => format!("wide ret {}", instruction.value2),
@@ -713,8 +730,3 @@ impl ClassFileDisassembler {
         }
     }
 }
-
-pub fn disassemble_classfile(class_data: Vec<u8>) -> Result<String, String> {
-    let disassembler = ClassFileDisassembler::new(class_data)?;
-    disassembler.disassemble()
-}
\ No newline at end of file
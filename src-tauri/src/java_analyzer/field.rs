@@ -1,6 +1,6 @@
 use crate::java_analyzer::attributes::{read_raw_attribute, Attribute};
 use crate::java_analyzer::classfile::ClassFile;
-use crate::java_analyzer::error::Result;
+use crate::java_analyzer::error::{JavaAnalyzeError, Result};
 use crate::java_analyzer::io::Buffer;
 
 #[derive(Debug)]
@@ -22,8 +22,14 @@ pub fn read_jvm_field(buffer: &mut Buffer, class_file: &ClassFile) -> Result<Jvm
         attributes.push(raw_atrribute);
     }
 
-    let name =class_file.constant_pool.get_utf8(name_index as usize).unwrap();
-    let descriptor = class_file.constant_pool.get_utf8(descriptor_index as usize).unwrap();
+    let name = class_file
+        .constant_pool
+        .get_utf8(name_index as usize)
+        .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid field name index {}", name_index)))?;
+    let descriptor = class_file
+        .constant_pool
+        .get_utf8(descriptor_index as usize)
+        .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid field descriptor index {}", descriptor_index)))?;
 
     Ok(JvmField {
         access_flags,
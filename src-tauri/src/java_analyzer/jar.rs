@@ -2,6 +2,7 @@ use std::fs::File;
 use std::io::Read;
 use zip::ZipArchive;
 
+#[derive(Clone)]
 pub struct JarReader {
     pub path: String,
 }
@@ -69,6 +70,23 @@ impl JarReader {
         Ok(contents)
     }
 
+    /// 批量读取多个文件，只打开一次压缩包，读取失败的文件会被跳过
+    pub fn read_files(&self, file_names: &[String]) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+
+        let mut result = Vec::with_capacity(file_names.len());
+        for file_name in file_names {
+            if let Ok(mut zip_file) = archive.by_name(file_name) {
+                let mut contents = Vec::new();
+                if zip_file.read_to_end(&mut contents).is_ok() {
+                    result.push((file_name.clone(), contents));
+                }
+            }
+        }
+        Ok(result)
+    }
+
     /// 读取指定文件的内容为字符串（适用于文本文件）
     pub fn read_file_as_string(&self, file_name: &str) -> Result<String, String> {
         let bytes = self.read_file(file_name)?;
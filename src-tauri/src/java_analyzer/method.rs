@@ -17,8 +17,14 @@ pub fn read_jvm_method(buffer:&mut Buffer, classfile:&ClassFile) -> Result<JvmMe
     let descriptor_index = buffer.read_u16()?;
     let attributes_count = buffer.read_u16()?;
     let mut attributes = vec![];
-    let name =classfile.constant_pool.get_utf8(name_index as usize).unwrap();
-    let descriptor = classfile.constant_pool.get_utf8(descriptor_index as usize).unwrap();
+    let name = classfile
+        .constant_pool
+        .get_utf8(name_index as usize)
+        .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid method name index {}", name_index)))?;
+    let descriptor = classfile
+        .constant_pool
+        .get_utf8(descriptor_index as usize)
+        .ok_or_else(|| JavaAnalyzeError::InvalidClassData(format!("Invalid method descriptor index {}", descriptor_index)))?;
 
     let mut code: Vec<Instruction> = vec![];
     for _ in 0..attributes_count {
@@ -323,7 +329,7 @@ impl<'a> JvmCodeReader<'a> {
                         let index = self.read_u16()?;
                         let increment = self.read_i16()?;
                         let mut instruction = Instruction::new3(opcode, offset, wide_opcode as i32, index as i32);
-                        instruction.pairs = vec![(increment as u16, 0)]; // 使用pairs存储increment
+                        instruction.pairs = vec![(increment as i32, 0)]; // 使用pairs存储increment
                         Ok(instruction)
                     }
                     _ => Err(JavaAnalyzeError::InvalidClassData(format!("Invalid wide opcode: {wide_opcode}")))
@@ -377,7 +383,7 @@ impl<'a> JvmCodeReader<'a> {
                 
                 let mut instruction = Instruction::new3(opcode, offset, default_offset, low);
                 instruction.pairs = jump_offsets.into_iter().enumerate()
-                    .map(|(i, offset)| ((low + i as i32) as u16, (offset & 0xFFFF) as u16))
+                    .map(|(i, offset)| (low + i as i32, offset))
                     .collect();
                 Ok(instruction)
             }
@@ -419,7 +425,7 @@ impl<'a> JvmCodeReader<'a> {
                 for _ in 0..npairs {
                     let match_value = self.read_i32()?;
                     let jump_offset = self.read_i32()?;
-                    pairs.push(((match_value & 0xFFFF) as u16, (jump_offset & 0xFFFF) as u16));
+                    pairs.push((match_value, jump_offset));
                 }
                 
                 let mut instruction = Instruction::new3(opcode, offset, default_offset, npairs);
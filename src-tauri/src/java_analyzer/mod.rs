@@ -1,15 +1,17 @@
-mod classfile;
+pub(crate) mod classfile;
 pub(crate) mod disassembler;
-mod decompiler;
+pub(crate) mod decompiler;
 
 mod error;
-mod opcode;
-mod method;
+pub(crate) mod opcode;
+pub(crate) mod method;
 mod io;
-mod constantpool;
-mod attributes;
+pub(crate) mod constantpool;
+pub(crate) mod attributes;
 mod annotions;
-mod field;
-mod controlflow;
-mod controlflowbuilder;
-pub(crate) mod jar;
\ No newline at end of file
+pub(crate) mod field;
+pub(crate) mod ast;
+pub(crate) mod controlflow;
+pub(crate) mod controlflowbuilder;
+pub(crate) mod jar;
+pub(crate) mod classpath;
\ No newline at end of file
@@ -204,6 +204,218 @@ pub const OP_BREAKPOINT: u8 = 0xCA;
 pub const OP_IMPDEP1: u8 = 0xFE;
 pub const OP_IMPDEP2: u8 = 0xFF;
 
+/// 指令助记符，例如 `OP_INVOKEVIRTUAL` -> `invokevirtual`
+pub fn opcode_name(opcode: u8) -> &'static str {
+    match opcode {
+        OP_NOP => "nop",
+        OP_ACONST_NULL => "aconst_null",
+        OP_ICONST_M1 => "iconst_m1",
+        OP_ICONST_0 => "iconst_0",
+        OP_ICONST_1 => "iconst_1",
+        OP_ICONST_2 => "iconst_2",
+        OP_ICONST_3 => "iconst_3",
+        OP_ICONST_4 => "iconst_4",
+        OP_ICONST_5 => "iconst_5",
+        OP_LCONST_0 => "lconst_0",
+        OP_LCONST_1 => "lconst_1",
+        OP_FCONST_0 => "fconst_0",
+        OP_FCONST_1 => "fconst_1",
+        OP_FCONST_2 => "fconst_2",
+        OP_DCONST_0 => "dconst_0",
+        OP_DCONST_1 => "dconst_1",
+        OP_BIPUSH => "bipush",
+        OP_SIPUSH => "sipush",
+        OP_LDC => "ldc",
+        OP_LDC_W => "ldc_w",
+        OP_LDC2_W => "ldc2_w",
+        OP_ILOAD => "iload",
+        OP_LLOAD => "lload",
+        OP_FLOAD => "fload",
+        OP_DLOAD => "dload",
+        OP_ALOAD => "aload",
+        OP_ILOAD_0 => "iload_0",
+        OP_ILOAD_1 => "iload_1",
+        OP_ILOAD_2 => "iload_2",
+        OP_ILOAD_3 => "iload_3",
+        OP_LLOAD_0 => "lload_0",
+        OP_LLOAD_1 => "lload_1",
+        OP_LLOAD_2 => "lload_2",
+        OP_LLOAD_3 => "lload_3",
+        OP_FLOAD_0 => "fload_0",
+        OP_FLOAD_1 => "fload_1",
+        OP_FLOAD_2 => "fload_2",
+        OP_FLOAD_3 => "fload_3",
+        OP_DLOAD_0 => "dload_0",
+        OP_DLOAD_1 => "dload_1",
+        OP_DLOAD_2 => "dload_2",
+        OP_DLOAD_3 => "dload_3",
+        OP_ALOAD_0 => "aload_0",
+        OP_ALOAD_1 => "aload_1",
+        OP_ALOAD_2 => "aload_2",
+        OP_ALOAD_3 => "aload_3",
+        OP_IALOAD => "iaload",
+        OP_LALOAD => "laload",
+        OP_FALOAD => "faload",
+        OP_DALOAD => "daload",
+        OP_AALOAD => "aaload",
+        OP_BALOAD => "baload",
+        OP_CALOAD => "caload",
+        OP_SALOAD => "saload",
+        OP_ISTORE => "istore",
+        OP_LSTORE => "lstore",
+        OP_FSTORE => "fstore",
+        OP_DSTORE => "dstore",
+        OP_ASTORE => "astore",
+        OP_ISTORE_0 => "istore_0",
+        OP_ISTORE_1 => "istore_1",
+        OP_ISTORE_2 => "istore_2",
+        OP_ISTORE_3 => "istore_3",
+        OP_LSTORE_0 => "lstore_0",
+        OP_LSTORE_1 => "lstore_1",
+        OP_LSTORE_2 => "lstore_2",
+        OP_LSTORE_3 => "lstore_3",
+        OP_FSTORE_0 => "fstore_0",
+        OP_FSTORE_1 => "fstore_1",
+        OP_FSTORE_2 => "fstore_2",
+        OP_FSTORE_3 => "fstore_3",
+        OP_DSTORE_0 => "dstore_0",
+        OP_DSTORE_1 => "dstore_1",
+        OP_DSTORE_2 => "dstore_2",
+        OP_DSTORE_3 => "dstore_3",
+        OP_ASTORE_0 => "astore_0",
+        OP_ASTORE_1 => "astore_1",
+        OP_ASTORE_2 => "astore_2",
+        OP_ASTORE_3 => "astore_3",
+        OP_IASTORE => "iastore",
+        OP_LASTORE => "lastore",
+        OP_FASTORE => "fastore",
+        OP_DASTORE => "dastore",
+        OP_AASTORE => "aastore",
+        OP_BASTORE => "bastore",
+        OP_CASTORE => "castore",
+        OP_SASTORE => "sastore",
+        OP_POP => "pop",
+        OP_POP2 => "pop2",
+        OP_DUP => "dup",
+        OP_DUP_X1 => "dup_x1",
+        OP_DUP_X2 => "dup_x2",
+        OP_DUP2 => "dup2",
+        OP_DUP2_X1 => "dup2_x1",
+        OP_DUP2_X2 => "dup2_x2",
+        OP_SWAP => "swap",
+        OP_IADD => "iadd",
+        OP_LADD => "ladd",
+        OP_FADD => "fadd",
+        OP_DADD => "dadd",
+        OP_ISUB => "isub",
+        OP_LSUB => "lsub",
+        OP_FSUB => "fsub",
+        OP_DSUB => "dsub",
+        OP_IMUL => "imul",
+        OP_LMUL => "lmul",
+        OP_FMUL => "fmul",
+        OP_DMUL => "dmul",
+        OP_IDIV => "idiv",
+        OP_LDIV => "ldiv",
+        OP_FDIV => "fdiv",
+        OP_DDIV => "ddiv",
+        OP_IREM => "irem",
+        OP_LREM => "lrem",
+        OP_FREM => "frem",
+        OP_DREM => "drem",
+        OP_INEG => "ineg",
+        OP_LNEG => "lneg",
+        OP_FNEG => "fneg",
+        OP_DNEG => "dneg",
+        OP_ISHL => "ishl",
+        OP_LSHL => "lshl",
+        OP_ISHR => "ishr",
+        OP_LSHR => "lshr",
+        OP_IUSHR => "iushr",
+        OP_LUSHR => "lushr",
+        OP_IAND => "iand",
+        OP_LAND => "land",
+        OP_IOR => "ior",
+        OP_LOR => "lor",
+        OP_IXOR => "ixor",
+        OP_LXOR => "lxor",
+        OP_IINC => "iinc",
+        OP_I2L => "i2l",
+        OP_I2F => "i2f",
+        OP_I2D => "i2d",
+        OP_L2I => "l2i",
+        OP_L2F => "l2f",
+        OP_L2D => "l2d",
+        OP_F2I => "f2i",
+        OP_F2L => "f2l",
+        OP_F2D => "f2d",
+        OP_D2I => "d2i",
+        OP_D2L => "d2l",
+        OP_D2F => "d2f",
+        OP_I2B => "i2b",
+        OP_I2C => "i2c",
+        OP_I2S => "i2s",
+        OP_LCMP => "lcmp",
+        OP_FCMPL => "fcmpl",
+        OP_FCMPG => "fcmpg",
+        OP_DCMPL => "dcmpl",
+        OP_DCMPG => "dcmpg",
+        OP_IFEQ => "ifeq",
+        OP_IFNE => "ifne",
+        OP_IFLT => "iflt",
+        OP_IFGE => "ifge",
+        OP_IFGT => "ifgt",
+        OP_IFLE => "ifle",
+        OP_IF_ICMPEQ => "if_icmpeq",
+        OP_IF_ICMPNE => "if_icmpne",
+        OP_IF_ICMPLT => "if_icmplt",
+        OP_IF_ICMPGE => "if_icmpge",
+        OP_IF_ICMPGT => "if_icmpgt",
+        OP_IF_ICMPLE => "if_icmple",
+        OP_IF_ACMPEQ => "if_acmpeq",
+        OP_IF_ACMPNE => "if_acmpne",
+        OP_GETSTATIC => "getstatic",
+        OP_PUTSTATIC => "putstatic",
+        OP_GETFIELD => "getfield",
+        OP_PUTFIELD => "putfield",
+        OP_INVOKEVIRTUAL => "invokevirtual",
+        OP_INVOKESPECIAL => "invokespecial",
+        OP_INVOKESTATIC => "invokestatic",
+        OP_INVOKEINTERFACE => "invokeinterface",
+        OP_INVOKEDYNAMIC => "invokedynamic",
+        OP_NEW => "new",
+        OP_NEWARRAY => "newarray",
+        OP_ANEWARRAY => "anewarray",
+        OP_ARRAYLENGTH => "arraylength",
+        OP_ATHROW => "athrow",
+        OP_CHECKCAST => "checkcast",
+        OP_INSTANCEOF => "instanceof",
+        OP_MONITORENTER => "monitorenter",
+        OP_MONITOREXIT => "monitorexit",
+        OP_GOTO => "goto",
+        OP_JSR => "jsr",
+        OP_RET => "ret",
+        OP_TABLESWITCH => "tableswitch",
+        OP_LOOKUPSWITCH => "lookupswitch",
+        OP_IRETURN => "ireturn",
+        OP_LRETURN => "lreturn",
+        OP_FRETURN => "freturn",
+        OP_DRETURN => "dreturn",
+        OP_ARETURN => "areturn",
+        OP_RETURN => "return",
+        OP_WIDE => "wide",
+        OP_MULTIANEWARRAY => "multianewarray",
+        OP_IFNULL => "ifnull",
+        OP_IFNONNULL => "ifnonnull",
+        OP_GOTO_W => "goto_w",
+        OP_JSR_W => "jsr_w",
+        OP_BREAKPOINT => "breakpoint",
+        OP_IMPDEP1 => "impdep1",
+        OP_IMPDEP2 => "impdep2",
+        _ => "unknown",
+    }
+}
+
 pub enum ArrayType {
     Boolean,
     Char,
@@ -221,7 +433,7 @@ pub struct Instruction {
     pub offset: u32,
     pub value: i32,
     pub value2: i32,
-    pub pairs: Vec<(u16, u16)>,
+    pub pairs: Vec<(i32, i32)>,
 }
 
 impl Instruction {
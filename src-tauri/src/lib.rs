@@ -3,6 +3,22 @@ pub mod hex;
 pub mod java;
 pub mod android;
 pub mod android_analyzer;
+pub mod hierarchy;
+pub mod xref;
+pub mod search;
+pub mod export;
+pub mod libraries;
+pub mod sbom;
+pub mod diff;
+mod program;
+mod model;
+mod deadcode;
+mod callgraph;
+mod dataflow;
+mod taint;
+mod deobfuscate;
+mod rename;
+mod obfuscation;
 mod java_analyzer;
 
 
@@ -20,11 +36,68 @@ pub fn run() {
             hex::hex_project_get_file_size,
             hex::hex_project_get_total_pages,
             hex::hex_project_read_page,
+            hex::hex_project_dex_sections,
+            hex::hex_project_verify_dex,
             java::java_project_list_files,
             java::java_project_read_file_content,
+            java::java_project_decompile_class,
+            java::java_project_list_classpath,
+            java::java_project_add_classpath_entry,
+            java::java_project_remove_classpath_entry,
+            java::java_project_move_classpath_entry,
+            java::java_project_set_classpath_precedence,
+            java::java_project_resolve_class,
+            java::java_project_find_duplicate_classes,
+            java::java_project_find_split_packages,
             android::android_analyze_apk,
             android::android_project_list_files,
             android::android_project_read_file_content,
+            android::android_project_smali_class,
+            android::android_project_decompile_class,
+            android::android_project_assemble_smali,
+            android::android_project_write_dex,
+            android::android_project_dex_sections,
+            android::android_project_verify_dex,
+            hierarchy::hierarchy_get_type,
+            hierarchy::hierarchy_get_supertypes,
+            hierarchy::hierarchy_get_subtypes,
+            hierarchy::hierarchy_get_implementers,
+            hierarchy::hierarchy_get_overriding_methods,
+            hierarchy::hierarchy_get_overridden_methods,
+            hierarchy::hierarchy_get_missing_supertypes,
+            xref::xref_find_class_usages,
+            xref::xref_find_method_usages,
+            xref::xref_find_field_usages,
+            xref::xref_find_string_usages,
+            search::search_project,
+            export::export_project_sources,
+            libraries::libraries_identify,
+            libraries::libraries_add_fingerprint,
+            sbom::sbom_generate,
+            diff::diff_projects,
+            diff::diff_method,
+            deadcode::deadcode_analyze,
+            callgraph::callgraph_build,
+            callgraph::callgraph_export,
+            callgraph::callgraph_find_paths,
+            dataflow::dataflow_analyze_method,
+            dataflow::dataflow_query_instruction,
+            taint::taint_analyze,
+            taint::taint_default_rules,
+            deobfuscate::deobfuscate_strings,
+            deobfuscate::deobfuscate_disassemble_class,
+            deobfuscate::deobfuscate_decompile_class,
+            rename::rename_list,
+            rename::rename_class,
+            rename::rename_field,
+            rename::rename_method,
+            rename::rename_remove,
+            rename::rename_clear,
+            rename::rename_import_mapping,
+            rename::rename_export_mapping,
+            rename::rename_save,
+            rename::rename_load,
+            obfuscation::obfuscation_report,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
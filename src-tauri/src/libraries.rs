@@ -0,0 +1,481 @@
+// Identify third-party libraries bundled in a project from Maven metadata and class fingerprints
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::hierarchy::{with_hierarchy, ClassHierarchy, TypeNode};
+use crate::java_analyzer::classpath::Classpath;
+use crate::java_analyzer::jar::{JarEntry, JarReader};
+use crate::project::{Project, ProjectData};
+
+const ACC_SYNTHETIC: u32 = 0x1000;
+
+/// 类型名归一化为 `L;` 之后，少量方法的签名在不相关的类之间很容易重复，
+/// 除去默认构造器和 Object 方法后签名少于这个数量的类不参与指纹
+const MIN_FINGERPRINT_SIGNATURES: usize = 4;
+
+/// 几乎每个类都有、对区分类没有帮助的签名
+const COMMON_SIGNATURES: &[&str] = &[
+    "<init>()V",
+    "toString()Ljava/lang/String;",
+    "hashCode()I",
+    "equals(Ljava/lang/Object;)Z",
+    "clone()Ljava/lang/Object;",
+    "finalize()V",
+    "run()V",
+];
+
+/// 库中至少有这个比例的类指纹出现在项目中才算识别成功
+const MIN_MATCH_RATIO: f64 = 0.5;
+
+/// 同时至少要匹配这么多个类，避免只有一两个类的库靠偶然重复被识别
+const MIN_MATCHED_CLASSES: usize = 3;
+
+static COMMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+static PARENT_BLOCK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<parent>(.*?)</parent>").unwrap());
+/// pom.xml 中会重复出现 groupId 等标签、不属于项目自身坐标的段落
+static NESTED_BLOCKS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    ["dependencyManagement", "dependencies", "build", "profiles", "reporting", "pluginRepositories", "plugins"]
+        .iter()
+        .map(|section| Regex::new(&format!(r"(?s)<{0}>.*?</{0}>", section)).unwrap())
+        .collect()
+});
+static GROUP_ID: LazyLock<Regex> = LazyLock::new(|| tag_regex("groupId"));
+static ARTIFACT_ID: LazyLock<Regex> = LazyLock::new(|| tag_regex("artifactId"));
+static VERSION: LazyLock<Regex> = LazyLock::new(|| tag_regex("version"));
+
+fn tag_regex(name: &str) -> Regex {
+    Regex::new(&format!(r"<{0}>\s*([^<]*?)\s*</{0}>", name)).unwrap()
+}
+
+/// How a component was identified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ComponentSource {
+    /// `META-INF/maven/<group>/<artifact>/pom.properties`
+    PomProperties,
+    /// `META-INF/maven/<group>/<artifact>/pom.xml` without a `pom.properties` next to it
+    PomXml,
+    /// Class signatures matched an entry of the fingerprint database
+    Fingerprint,
+}
+
+/// A third-party library found in a project.
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryComponent {
+    pub group_id: Option<String>,
+    pub artifact_id: String,
+    pub version: Option<String>,
+    pub source: ComponentSource,
+    /// Archive and entry the metadata was read from, e.g. `lib/foo.jar!/META-INF/maven/...`
+    pub location: String,
+    /// Packages whose classes matched the fingerprints, shaded packages keep their relocated name
+    pub packages: Vec<String>,
+    /// 1.0 for Maven metadata, the share of fingerprinted classes found otherwise
+    pub confidence: f64,
+}
+
+/// One library version of the user supplied fingerprint database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryFingerprint {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+    /// Hex encoded class fingerprints, see [`class_fingerprint`]
+    pub classes: Vec<String>,
+}
+
+/// The fingerprint database file, a JSON document shared between projects.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FingerprintDatabase {
+    pub libraries: Vec<LibraryFingerprint>,
+}
+
+impl FingerprintDatabase {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid fingerprint database {}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// 同一坐标的旧记录会被替换
+    pub fn insert(&mut self, library: LibraryFingerprint) {
+        self.libraries.retain(|l| {
+            (l.group_id.as_str(), l.artifact_id.as_str(), l.version.as_str())
+                != (library.group_id.as_str(), library.artifact_id.as_str(), library.version.as_str())
+        });
+        self.libraries.push(library);
+    }
+}
+
+/// 类指纹：方法名加上归一化后的描述符，排序后做 FNV-1a。
+///
+/// 非 JDK 的类型在描述符中统一替换为 `L;`，这样重定位（shade）或混淆类名后指纹保持不变。
+pub fn class_fingerprint(node: &TypeNode) -> Option<u64> {
+    let mut signatures: Vec<String> = node
+        .methods
+        .iter()
+        .filter(|method| method.access_flags & ACC_SYNTHETIC == 0 && method.name != "<clinit>")
+        .map(|method| format!("{}{}", method.name, normalize_descriptor(&method.descriptor)))
+        .collect();
+    let distinctive = signatures.iter().filter(|signature| !COMMON_SIGNATURES.contains(&signature.as_str())).count();
+    if distinctive < MIN_FINGERPRINT_SIGNATURES {
+        return None;
+    }
+    signatures.sort();
+    let kind = if node.is_interface() { "interface" } else { "class" };
+    Some(fnv1a(format!("{}\n{}", kind, signatures.join("\n")).as_bytes()))
+}
+
+fn normalize_descriptor(descriptor: &str) -> String {
+    let mut out = String::with_capacity(descriptor.len());
+    let mut rest = descriptor;
+    while let Some(start) = rest.find('L') {
+        out.push_str(&rest[..start]);
+        let end = match rest[start..].find(';') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let class_name = &rest[start + 1..end];
+        if is_platform_class(class_name) {
+            out.push_str(&rest[start..=end]);
+        } else {
+            out.push_str("L;");
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// JDK 和 Android 平台类不会被 shade，保留原名让指纹更有区分度
+fn is_platform_class(class_name: &str) -> bool {
+    ["java/", "javax/", "android/", "kotlin/"].iter().any(|prefix| class_name.starts_with(prefix))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn package_of(class_name: &str) -> String {
+    class_name.rsplit_once('/').map(|(package, _)| package.replace('/', ".")).unwrap_or_default()
+}
+
+/// 按包分组的项目类指纹
+fn project_fingerprints(hierarchy: &ClassHierarchy) -> HashMap<u64, BTreeSet<String>> {
+    let mut fingerprints: HashMap<u64, BTreeSet<String>> = HashMap::new();
+    for node in hierarchy.types.values() {
+        if let Some(fingerprint) = class_fingerprint(node) {
+            fingerprints.entry(fingerprint).or_default().insert(package_of(&node.name));
+        }
+    }
+    fingerprints
+}
+
+/// 对每个 group:artifact 只保留匹配度最高的版本
+fn match_fingerprints(database: &FingerprintDatabase, fingerprints: &HashMap<u64, BTreeSet<String>>) -> Vec<LibraryComponent> {
+    let mut best: BTreeMap<(String, String), LibraryComponent> = BTreeMap::new();
+    for library in &database.libraries {
+        let hashes: BTreeSet<u64> = library.classes.iter().filter_map(|c| u64::from_str_radix(c, 16).ok()).collect();
+        if hashes.is_empty() {
+            continue;
+        }
+        let mut packages = BTreeSet::new();
+        let mut matched = 0;
+        for hash in &hashes {
+            if let Some(found) = fingerprints.get(hash) {
+                matched += 1;
+                packages.extend(found.iter().cloned());
+            }
+        }
+        let confidence = matched as f64 / hashes.len() as f64;
+        if matched < MIN_MATCHED_CLASSES || confidence < MIN_MATCH_RATIO {
+            continue;
+        }
+        let key = (library.group_id.clone(), library.artifact_id.clone());
+        if best.get(&key).is_some_and(|existing| existing.confidence >= confidence) {
+            continue;
+        }
+        best.insert(
+            key,
+            LibraryComponent {
+                group_id: Some(library.group_id.clone()),
+                artifact_id: library.artifact_id.clone(),
+                version: Some(library.version.clone()),
+                source: ComponentSource::Fingerprint,
+                location: String::new(),
+                packages: packages.into_iter().collect(),
+                confidence,
+            },
+        );
+    }
+    best.into_values().collect()
+}
+
+/// `META-INF/maven/<group>/<artifact>/pom.(properties|xml)` 中的 (group, artifact, 文件名)
+fn maven_metadata_path(name: &str) -> Option<(&str, &str, &str)> {
+    let rest = name.strip_prefix("META-INF/maven/")?;
+    let mut parts = rest.split('/');
+    let (group, artifact, file) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || !(file == "pom.properties" || file == "pom.xml") {
+        return None;
+    }
+    Some((group, artifact, file))
+}
+
+/// 从一个归档的所有 Maven 元数据中识别组件，同目录下优先使用 pom.properties
+pub(crate) fn metadata_components<R>(archive: &str, entries: &[JarEntry], mut read: R) -> Vec<LibraryComponent>
+where
+    R: FnMut(&str) -> Result<Vec<u8>, String>,
+{
+    let mut by_artifact: BTreeMap<(&str, &str), Vec<(&str, &str)>> = BTreeMap::new();
+    for entry in entries {
+        if let Some((group, artifact, file)) = maven_metadata_path(&entry.name) {
+            by_artifact.entry((group, artifact)).or_default().push((file, entry.name.as_str()));
+        }
+    }
+
+    let mut components = Vec::new();
+    for ((group, artifact), mut files) in by_artifact {
+        files.sort_by_key(|(file, _)| *file != "pom.properties");
+        for (file, name) in files {
+            let Ok(bytes) = read(name) else { continue };
+            let text = String::from_utf8_lossy(&bytes);
+            let (coordinates, source) = if file == "pom.properties" {
+                (parse_pom_properties(&text), ComponentSource::PomProperties)
+            } else {
+                (parse_pom_xml(&text), ComponentSource::PomXml)
+            };
+            let (group_id, artifact_id, version) = coordinates;
+            components.push(LibraryComponent {
+                group_id: group_id.or_else(|| Some(group.to_string())),
+                artifact_id: artifact_id.unwrap_or_else(|| artifact.to_string()),
+                version,
+                source,
+                location: format!("{}!/{}", archive, name),
+                packages: Vec::new(),
+                confidence: 1.0,
+            });
+            break;
+        }
+    }
+    components
+}
+
+type Coordinates = (Option<String>, Option<String>, Option<String>);
+
+fn parse_pom_properties(text: &str) -> Coordinates {
+    let mut properties = HashMap::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            properties.insert(key.trim(), value.trim().to_string());
+        }
+    }
+    let get = |key: &str| properties.get(key).filter(|v| !v.is_empty()).cloned();
+    (get("groupId"), get("artifactId"), get("version"))
+}
+
+/// 只取项目自身的坐标：先去掉 parent、dependencies 等会重复出现这些标签的段落，
+/// groupId / version 缺失时从 parent 继承，未解析的 `${...}` 属性视为未知
+fn parse_pom_xml(text: &str) -> Coordinates {
+    let text = COMMENT.replace_all(text, "");
+    let parent = PARENT_BLOCK.captures(&text).map(|c| c[1].to_string()).unwrap_or_default();
+    let mut own = PARENT_BLOCK.replace_all(&text, "").to_string();
+    for block in NESTED_BLOCKS.iter() {
+        own = block.replace_all(&own, "").to_string();
+    }
+    let tag = |source: &str, regex: &Regex| {
+        regex
+            .captures(source)
+            .map(|c| c[1].to_string())
+            .filter(|value| !value.is_empty() && !value.contains("${"))
+    };
+    (
+        tag(&own, &GROUP_ID).or_else(|| tag(&parent, &GROUP_ID)),
+        tag(&own, &ARTIFACT_ID),
+        tag(&own, &VERSION).or_else(|| tag(&parent, &VERSION)),
+    )
+}
+
+/// Maven metadata of every archive of the project
+fn project_metadata_components(project_id: &str) -> Result<Vec<LibraryComponent>, String> {
+    // 锁内只取出 classpath 或 APK 路径，归档在锁外读取
+    let source = Project::with_project(project_id, |project| match &project.data {
+        ProjectData::Java(java_data) => Ok(MetadataSource::Classpath(java_data.classpath.clone())),
+        ProjectData::Android(android_data) => Ok(MetadataSource::Apk(android_data.apk_path.clone())),
+        _ => Err("Not a Java or Android project".to_string()),
+    })?;
+    match source {
+        MetadataSource::Classpath(classpath) => Ok(classpath_metadata_components(&classpath)),
+        MetadataSource::Apk(apk_path) => {
+            let reader = JarReader::new(&apk_path);
+            let entries = reader.list_entries()?;
+            Ok(metadata_components(&apk_path, &entries, |name| reader.read_file(name)))
+        }
+    }
+}
+
+enum MetadataSource {
+    Classpath(Classpath),
+    Apk(String),
+}
+
+fn classpath_metadata_components(classpath: &Classpath) -> Vec<LibraryComponent> {
+    let mut components = Vec::new();
+    for entry in &classpath.entries {
+        let Ok(entries) = entry.list_entries() else { continue };
+        components.extend(metadata_components(&entry.path(), &entries, |name| entry.read_file(name)));
+    }
+    components
+}
+
+/// Lists the libraries found in a project. Maven metadata is always used; when `database_path`
+/// is given, classes are also matched against the fingerprint database to find shaded or
+/// stripped copies. A fingerprint match is dropped if metadata already names the same artifact.
+pub(crate) fn identify_libraries(project_id: &str, database_path: Option<&str>) -> Result<Vec<LibraryComponent>, String> {
+    let mut components = project_metadata_components(project_id)?;
+    if let Some(database_path) = database_path {
+        let database = FingerprintDatabase::load(database_path)?;
+        let fingerprints = with_hierarchy(project_id, project_fingerprints)?;
+        for component in match_fingerprints(&database, &fingerprints) {
+            let known = components
+                .iter()
+                .any(|c| c.group_id == component.group_id && c.artifact_id == component.artifact_id);
+            if !known {
+                components.push(component);
+            }
+        }
+    }
+    components.sort_by(|a, b| (&a.group_id, &a.artifact_id, &a.version).cmp(&(&b.group_id, &b.artifact_id, &b.version)));
+    Ok(components)
+}
+
+#[tauri::command(async)]
+pub fn libraries_identify(project_id: String, database_path: Option<String>) -> Result<Vec<LibraryComponent>, String> {
+    identify_libraries(&project_id, database_path.as_deref())
+}
+
+/// Fingerprints the classes of a known library jar and stores them in the database file,
+/// creating the file if needed. Returns the number of class fingerprints recorded.
+#[tauri::command(async)]
+pub fn libraries_add_fingerprint(
+    jar_path: String,
+    group_id: String,
+    artifact_id: String,
+    version: String,
+    database_path: String,
+) -> Result<usize, String> {
+    let classpath = Classpath::from_paths(&[jar_path])?;
+    let hierarchy = ClassHierarchy::from_classpath(&classpath);
+    let classes: BTreeSet<String> = hierarchy
+        .types
+        .values()
+        .filter_map(class_fingerprint)
+        .map(|fingerprint| format!("{:016x}", fingerprint))
+        .collect();
+    if classes.is_empty() {
+        return Err("No classes to fingerprint".to_string());
+    }
+    let count = classes.len();
+
+    let mut database = if std::path::Path::new(&database_path).exists() {
+        FingerprintDatabase::load(&database_path)?
+    } else {
+        FingerprintDatabase::default()
+    };
+    database.insert(LibraryFingerprint {
+        group_id,
+        artifact_id,
+        version,
+        classes: classes.into_iter().collect(),
+    });
+    database.save(&database_path)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hierarchy::MethodInfo;
+
+    fn node(name: &str, methods: &[(&str, &str)]) -> TypeNode {
+        TypeNode {
+            name: name.to_string(),
+            super_name: Some("java/lang/Object".to_string()),
+            interfaces: Vec::new(),
+            access_flags: 0,
+            methods: methods
+                .iter()
+                .map(|(name, descriptor)| MethodInfo { name: name.to_string(), descriptor: descriptor.to_string(), access_flags: 1 })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn classes_with_only_common_signatures_are_not_fingerprinted() {
+        let bean = node("a/Bean", &[("<init>", "()V"), ("toString", "()Ljava/lang/String;"), ("hashCode", "()I"), ("get", "()La/Value;")]);
+        assert_eq!(class_fingerprint(&bean), None);
+    }
+
+    #[test]
+    fn fingerprints_survive_relocation() {
+        let methods = [
+            ("<init>", "(Lcom/lib/Config;)V"),
+            ("parse", "(Ljava/lang/String;)Lcom/lib/Node;"),
+            ("write", "(Lcom/lib/Node;Ljava/io/Writer;)V"),
+            ("setIndent", "(I)V"),
+            ("isLenient", "()Z"),
+        ];
+        let relocated: Vec<(&str, String)> = methods.iter().map(|(name, d)| (*name, d.replace("com/lib/", "shaded/x/"))).collect();
+        let relocated: Vec<(&str, &str)> = relocated.iter().map(|(name, d)| (*name, d.as_str())).collect();
+        let original = class_fingerprint(&node("com/lib/Parser", &methods));
+        assert!(original.is_some());
+        assert_eq!(original, class_fingerprint(&node("shaded/x/Parser", &relocated)));
+    }
+
+    #[test]
+    fn a_single_matching_class_does_not_identify_a_library() {
+        let database = FingerprintDatabase {
+            libraries: vec![LibraryFingerprint {
+                group_id: "com.lib".to_string(),
+                artifact_id: "lib".to_string(),
+                version: "1.0".to_string(),
+                classes: vec!["1".to_string()],
+            }],
+        };
+        let fingerprints = HashMap::from([(1u64, BTreeSet::from(["com.app".to_string()]))]);
+        assert!(match_fingerprints(&database, &fingerprints).is_empty());
+    }
+
+    #[test]
+    fn pom_xml_coordinates_ignore_dependencies_and_inherit_from_parent() {
+        let pom = r#"
+            <project>
+              <parent><groupId>org.parent</groupId><version>2.1</version></parent>
+              <!-- <artifactId>commented</artifactId> -->
+              <artifactId>core</artifactId>
+              <dependencies>
+                <dependency><groupId>junit</groupId><artifactId>junit</artifactId><version>4.13</version></dependency>
+              </dependencies>
+            </project>"#;
+        assert_eq!(
+            parse_pom_xml(pom),
+            (Some("org.parent".to_string()), Some("core".to_string()), Some("2.1".to_string()))
+        );
+    }
+}
@@ -0,0 +1,711 @@
+// Read-only class model shared by JVM class files and DEX classes, so analyses are written once
+use crate::android::{ClassDef, DexFile, EncodedValue, Field, Method, MethodHandleMember};
+use crate::android_analyzer::dalvik_opcode::{DalvikOpcode, DalvikOpcodeAnalyzer, DalvikReference};
+use crate::android_analyzer::dex_decompiler::DexDecompiler;
+use crate::hierarchy::{descriptor_to_internal_name, proto_to_descriptor};
+use crate::java_analyzer::attributes::Attribute;
+use crate::java_analyzer::classfile::ClassFile;
+use crate::java_analyzer::constantpool::ConstantPoolEntry;
+use crate::java_analyzer::decompiler::{format_error, Decompiler};
+use crate::java_analyzer::field::JvmField;
+use crate::java_analyzer::method::JvmMethod;
+use crate::java_analyzer::opcode::*;
+use crate::program::{CodeReference, InvokeKind};
+
+/// A class of a jar or an APK. Names are internal names (`com/foo/Bar`).
+#[derive(Clone, Copy)]
+pub(crate) enum ClassModel<'a> {
+    Jvm(&'a ClassFile),
+    Dex(&'a DexFile, &'a ClassDef),
+}
+
+/// A field of a [`ClassModel`].
+#[derive(Clone, Copy)]
+pub(crate) enum FieldModel<'a> {
+    Jvm(&'a JvmField),
+    Dex(&'a Field),
+}
+
+/// A method of a [`ClassModel`]; the containing file is kept to resolve the body's references.
+#[derive(Clone, Copy)]
+pub(crate) enum MethodModel<'a> {
+    Jvm(&'a ClassFile, &'a JvmMethod),
+    Dex(&'a DexFile, &'a Method),
+}
+
+/// A numeric constant pushed by an instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NumericConstant {
+    Integer(i64),
+    Float(f64),
+}
+
+/// One instruction of a method body with its operands resolved.
+#[derive(Debug, Clone)]
+pub(crate) struct CodeInstruction {
+    /// Byte offset for JVM code, code unit address for Dalvik code
+    pub offset: u32,
+    pub mnemonic: &'static str,
+    pub references: Vec<CodeReference>,
+    /// Dalvik constants are untyped and always reported as integers
+    pub constant: Option<NumericConstant>,
+}
+
+impl<'a> ClassModel<'a> {
+    /// Every class defined by the given DEX files, in file order
+    pub fn dex_classes(dex_files: &'a [DexFile]) -> impl Iterator<Item = ClassModel<'a>> + 'a {
+        dex_files
+            .iter()
+            .flat_map(|dex_file| dex_file.classes.iter().map(move |class_def| ClassModel::Dex(dex_file, class_def)))
+    }
+
+    /// None if the `this_class` entry of a class file is broken
+    pub fn name(&self) -> Option<String> {
+        match self {
+            ClassModel::Jvm(class_file) => class_file.class_name(),
+            ClassModel::Dex(_, class_def) => Some(descriptor_to_internal_name(&class_def.class_type.descriptor)),
+        }
+    }
+
+    pub fn super_name(&self) -> Option<String> {
+        match self {
+            ClassModel::Jvm(class_file) => class_file.super_class_name(),
+            ClassModel::Dex(_, class_def) => class_def.super_type.as_ref().map(|t| descriptor_to_internal_name(&t.descriptor)),
+        }
+    }
+
+    pub fn interfaces(&self) -> Vec<String> {
+        match self {
+            ClassModel::Jvm(class_file) => class_file.interface_names(),
+            ClassModel::Dex(_, class_def) => class_def.interfaces.iter().map(|t| descriptor_to_internal_name(&t.descriptor)).collect(),
+        }
+    }
+
+    pub fn access_flags(&self) -> u32 {
+        match self {
+            ClassModel::Jvm(class_file) => class_file.access_flags as u32,
+            ClassModel::Dex(_, class_def) => class_def.access_flags,
+        }
+    }
+
+    /// Static fields come first for DEX classes
+    pub fn fields(&self) -> Vec<FieldModel<'a>> {
+        match *self {
+            ClassModel::Jvm(class_file) => class_file.fields.iter().map(FieldModel::Jvm).collect(),
+            ClassModel::Dex(_, class_def) => class_def
+                .static_fields
+                .iter()
+                .chain(&class_def.instance_fields)
+                .map(FieldModel::Dex)
+                .collect(),
+        }
+    }
+
+    /// Direct methods come first for DEX classes
+    pub fn methods(&self) -> Vec<MethodModel<'a>> {
+        match *self {
+            ClassModel::Jvm(class_file) => class_file.methods.iter().map(|method| MethodModel::Jvm(class_file, method)).collect(),
+            ClassModel::Dex(dex_file, class_def) => class_def
+                .direct_methods
+                .iter()
+                .chain(&class_def.virtual_methods)
+                .map(|method| MethodModel::Dex(dex_file, method))
+                .collect(),
+        }
+    }
+
+    /// Java source of the whole class, produced by the decompiler of the class's format
+    pub fn decompile(&self) -> Result<String, String> {
+        match self {
+            ClassModel::Jvm(class_file) => Decompiler::new(class_file)
+                .decompile_class()
+                .map_err(|e| format!("Failed to decompile class: {}", format_error(&e))),
+            ClassModel::Dex(dex_file, class_def) => DexDecompiler::new(dex_file)
+                .decompile_class(class_def)
+                .map_err(|e| format!("Failed to decompile class: {}", e)),
+        }
+    }
+}
+
+impl<'a> FieldModel<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            FieldModel::Jvm(field) => &field.name,
+            FieldModel::Dex(field) => &field.name,
+        }
+    }
+
+    pub fn descriptor(&self) -> &'a str {
+        match self {
+            FieldModel::Jvm(field) => &field.descriptor,
+            FieldModel::Dex(field) => &field.field_type.descriptor,
+        }
+    }
+
+    pub fn access_flags(&self) -> u32 {
+        match self {
+            FieldModel::Jvm(field) => field.access_flags as u32,
+            FieldModel::Dex(field) => field.access_flags,
+        }
+    }
+
+    /// Whether the field has a compile time constant value, whose reads may have been inlined
+    pub fn has_constant(&self) -> bool {
+        match self {
+            FieldModel::Jvm(field) => field.attributes.iter().any(|a| matches!(a, Attribute::ConstantValue(_))),
+            FieldModel::Dex(field) => field.value.is_some(),
+        }
+    }
+}
+
+impl<'a> MethodModel<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            MethodModel::Jvm(_, method) => &method.name,
+            MethodModel::Dex(_, method) => &method.name,
+        }
+    }
+
+    /// JVM style descriptor such as `(ILjava/lang/String;)V`
+    pub fn descriptor(&self) -> String {
+        match self {
+            MethodModel::Jvm(_, method) => method.descriptor.clone(),
+            MethodModel::Dex(_, method) => proto_to_descriptor(&method.proto),
+        }
+    }
+
+    pub fn access_flags(&self) -> u32 {
+        match self {
+            MethodModel::Jvm(_, method) => method.access_flags as u32,
+            MethodModel::Dex(_, method) => method.access_flags,
+        }
+    }
+
+    /// false for abstract and native methods
+    pub fn has_code(&self) -> bool {
+        match self {
+            MethodModel::Jvm(_, method) => method.attributes.iter().any(|a| matches!(a, Attribute::Code(_))),
+            MethodModel::Dex(_, method) => method.code.is_some(),
+        }
+    }
+
+    /// The decoded body; a Dalvik body that fails to decode keeps the instructions before the error
+    pub fn instructions(&self) -> Vec<CodeInstruction> {
+        match *self {
+            MethodModel::Jvm(class_file, method) => jvm_instructions(class_file, method),
+            MethodModel::Dex(dex_file, method) => dex_instructions(dex_file, method),
+        }
+    }
+
+    /// Every reference made by the body, followed by the caught exception types
+    pub fn references(&self) -> Vec<CodeReference> {
+        let mut references: Vec<CodeReference> = self.instructions().into_iter().flat_map(|i| i.references).collect();
+        match *self {
+            MethodModel::Jvm(class_file, method) => {
+                for attribute in &method.attributes {
+                    if let Attribute::Code(code) = attribute {
+                        for entry in &code.exception_table {
+                            if let Some(name) = class_file.constant_pool.get_class_name(entry.catch_type as usize) {
+                                references.push(CodeReference::Type(name.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+            MethodModel::Dex(dex_file, method) => {
+                for handler in method.code.iter().flat_map(|code| &code.handlers) {
+                    for pair in &handler.handlers {
+                        if let Some(t) = dex_file.types.get(pair.type_idx as usize) {
+                            references.push(CodeReference::Type(descriptor_to_internal_name(&t.descriptor)));
+                        }
+                    }
+                }
+            }
+        }
+        references
+    }
+}
+
+fn jvm_instructions(class_file: &ClassFile, method: &JvmMethod) -> Vec<CodeInstruction> {
+    let pool = &class_file.constant_pool;
+    method
+        .code
+        .iter()
+        .map(|instruction| {
+            let index = instruction.value as usize;
+            let mut references = Vec::new();
+            let mut constant = None;
+            match instruction.opcode {
+                OP_INVOKEVIRTUAL | OP_INVOKESPECIAL | OP_INVOKESTATIC | OP_INVOKEINTERFACE => {
+                    let kind = match instruction.opcode {
+                        OP_INVOKEVIRTUAL => InvokeKind::Virtual,
+                        OP_INVOKESPECIAL => InvokeKind::Special,
+                        OP_INVOKESTATIC => InvokeKind::Static,
+                        _ => InvokeKind::Interface,
+                    };
+                    if let Some((owner, name, descriptor)) = pool.get_member_ref(index) {
+                        references.push(CodeReference::Invoke {
+                            kind,
+                            owner: element_type(owner),
+                            name: name.clone(),
+                            descriptor: descriptor.clone(),
+                            offset: instruction.offset,
+                        });
+                    }
+                }
+                OP_GETFIELD | OP_GETSTATIC | OP_PUTFIELD | OP_PUTSTATIC => {
+                    if let Some((owner, name, descriptor)) = pool.get_member_ref(index) {
+                        references.push(CodeReference::Field {
+                            owner: owner.clone(),
+                            name: name.clone(),
+                            descriptor: descriptor.clone(),
+                            write: matches!(instruction.opcode, OP_PUTFIELD | OP_PUTSTATIC),
+                        });
+                    }
+                }
+                OP_NEW => {
+                    if let Some(name) = pool.get_class_name(index) {
+                        references.push(CodeReference::Instantiate(name.clone()));
+                    }
+                }
+                OP_CHECKCAST | OP_INSTANCEOF | OP_ANEWARRAY | OP_MULTIANEWARRAY => {
+                    if let Some(name) = pool.get_class_name(index).and_then(|name| referenced_class(name)) {
+                        references.push(CodeReference::Type(name));
+                    }
+                }
+                OP_LDC | OP_LDC_W | OP_LDC2_W => {
+                    if let Some(value) = pool.get_string(index) {
+                        references.push(CodeReference::String(value.clone()));
+                    } else if let Some(name) = pool.get_class_name(index) {
+                        references.extend(referenced_class(name).map(CodeReference::Type));
+                    } else if let Some(handle) = method_handle(class_file, index) {
+                        references.push(handle);
+                    } else {
+                        constant = match index.checked_sub(1).and_then(|i| pool.constant_pool.get(i)) {
+                            Some(ConstantPoolEntry::Integer(value)) => Some(NumericConstant::Integer(*value as i64)),
+                            Some(ConstantPoolEntry::Long(value)) => Some(NumericConstant::Integer(*value)),
+                            Some(ConstantPoolEntry::Float(value)) => Some(NumericConstant::Float(*value as f64)),
+                            Some(ConstantPoolEntry::Double(value)) => Some(NumericConstant::Float(*value)),
+                            _ => None,
+                        };
+                    }
+                }
+                OP_INVOKEDYNAMIC => references.extend(bootstrap_handles(class_file, index)),
+                OP_ICONST_M1..=OP_ICONST_5 => {
+                    constant = Some(NumericConstant::Integer(instruction.opcode as i64 - OP_ICONST_0 as i64));
+                }
+                OP_LCONST_0 | OP_LCONST_1 => constant = Some(NumericConstant::Integer((instruction.opcode - OP_LCONST_0) as i64)),
+                OP_FCONST_0..=OP_FCONST_2 => constant = Some(NumericConstant::Float((instruction.opcode - OP_FCONST_0) as f64)),
+                OP_DCONST_0 | OP_DCONST_1 => constant = Some(NumericConstant::Float((instruction.opcode - OP_DCONST_0) as f64)),
+                OP_BIPUSH | OP_SIPUSH => constant = Some(NumericConstant::Integer(instruction.value as i64)),
+                _ => {}
+            }
+            CodeInstruction {
+                offset: instruction.offset,
+                mnemonic: opcode_name(instruction.opcode),
+                references,
+                constant,
+            }
+        })
+        .collect()
+}
+
+fn method_handle(class_file: &ClassFile, index: usize) -> Option<CodeReference> {
+    class_file
+        .constant_pool
+        .get_method_handle(index)
+        .map(|(_, owner, name, descriptor)| CodeReference::MethodHandle {
+            owner: owner.clone(),
+            name: name.clone(),
+            descriptor: descriptor.clone(),
+        })
+}
+
+/// lambda 和方法引用的目标方法以 MethodHandle 形式出现在引导方法参数中
+pub(crate) fn bootstrap_handles(class_file: &ClassFile, index: usize) -> Vec<CodeReference> {
+    let Some((bootstrap_index, _, _)) = class_file.constant_pool.get_invoke_dynamic(index) else {
+        return Vec::new();
+    };
+    class_file
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::BootstrapMethods(methods) => methods.bootstrap_methods.get(bootstrap_index as usize),
+            _ => None,
+        })
+        .map(|method| {
+            method
+                .bootstrap_arguments
+                .iter()
+                .filter_map(|argument| method_handle(class_file, *argument as usize))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn dex_instructions(dex_file: &DexFile, method: &Method) -> Vec<CodeInstruction> {
+    let Some(code) = &method.code else {
+        return Vec::new();
+    };
+    let analyzer = DalvikOpcodeAnalyzer::new();
+    let mut instructions = Vec::new();
+    let mut address = 0;
+    while address < code.insns.len() {
+        let Ok(instruction) = analyzer.decode_instruction(&code.insns, address, dex_file) else {
+            break;
+        };
+        address += instruction.size;
+        let opcode = instruction.opcode;
+        let mut references = Vec::new();
+        match instruction.reference {
+            Some(DalvikReference::Method(method)) if opcode.is_invoke() => {
+                use DalvikOpcode::*;
+                let kind = match opcode {
+                    InvokeStatic | InvokeStaticRange => InvokeKind::Static,
+                    InvokeDirect | InvokeDirectRange | InvokeSuper | InvokeSuperRange => InvokeKind::Special,
+                    InvokeInterface | InvokeInterfaceRange => InvokeKind::Interface,
+                    _ => InvokeKind::Virtual,
+                };
+                references.push(CodeReference::Invoke {
+                    kind,
+                    owner: element_type(&descriptor_to_internal_name(&method.class_type.descriptor)),
+                    name: method.name,
+                    descriptor: proto_to_descriptor(&method.proto),
+                    offset: instruction.address,
+                });
+            }
+            Some(DalvikReference::Field(field)) => {
+                let name = opcode.name();
+                references.push(CodeReference::Field {
+                    owner: descriptor_to_internal_name(&field.class_type.descriptor),
+                    name: field.name,
+                    descriptor: field.field_type.descriptor,
+                    write: name.starts_with("iput") || name.starts_with("sput"),
+                });
+            }
+            Some(DalvikReference::Type(t)) => {
+                if let Some(name) = referenced_class(&descriptor_to_internal_name(&t.descriptor)) {
+                    references.push(if opcode == DalvikOpcode::NewInstance {
+                        CodeReference::Instantiate(name)
+                    } else {
+                        CodeReference::Type(name)
+                    });
+                }
+            }
+            Some(DalvikReference::String(value)) => references.push(CodeReference::String(value)),
+            Some(DalvikReference::MethodHandle(index)) => references.extend(dex_method_handle(dex_file, index)),
+            Some(DalvikReference::CallSite(index)) => references.extend(call_site_handles(dex_file, index)),
+            _ => {}
+        }
+        let constant = match opcode {
+            DalvikOpcode::Const4
+            | DalvikOpcode::Const16
+            | DalvikOpcode::Const
+            | DalvikOpcode::ConstHigh16
+            | DalvikOpcode::ConstWide16
+            | DalvikOpcode::ConstWide32
+            | DalvikOpcode::ConstWide
+            | DalvikOpcode::ConstWideHigh16 => instruction.literal.map(NumericConstant::Integer),
+            _ => None,
+        };
+        instructions.push(CodeInstruction {
+            offset: instruction.address,
+            mnemonic: opcode.name(),
+            references,
+            constant,
+        });
+    }
+    instructions
+}
+
+fn dex_method_handle(dex_file: &DexFile, index: u32) -> Option<CodeReference> {
+    let (owner, name, descriptor) = match &dex_file.method_handles.get(index as usize)?.member {
+        MethodHandleMember::Field(field) => (&field.class_type, field.name.clone(), field.field_type.descriptor.clone()),
+        MethodHandleMember::Method(method) => (&method.class_type, method.name.clone(), proto_to_descriptor(&method.proto)),
+    };
+    Some(CodeReference::MethodHandle {
+        owner: descriptor_to_internal_name(&owner.descriptor),
+        name,
+        descriptor,
+    })
+}
+
+/// 与 invokedynamic 相同，lambda 的目标方法出现在调用点的引导参数中
+fn call_site_handles(dex_file: &DexFile, index: u32) -> Vec<CodeReference> {
+    dex_file
+        .call_sites
+        .get(index as usize)
+        .map(|call_site| {
+            call_site
+                .arguments
+                .iter()
+                .filter_map(|argument| match argument {
+                    EncodedValue::MethodHandle(handle) => dex_method_handle(dex_file, *handle),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 类型引用指向的类，基本类型数组（`[I`）不引用任何类
+fn referenced_class(name: &str) -> Option<String> {
+    let element = name.trim_start_matches('[');
+    if element.len() != name.len() && !element.starts_with('L') {
+        return None;
+    }
+    Some(element_type(name))
+}
+
+/// `[[Lcom/foo/Bar;` -> `com/foo/Bar`，数组类型引用计入元素类型
+pub(crate) fn element_type(name: &str) -> String {
+    let element = name.trim_start_matches('[');
+    if element.len() != name.len() {
+        descriptor_to_internal_name(element)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::android_analyzer::test_support::dex_file;
+    use crate::java_analyzer::classfile::ClassFileReader;
+
+    /// 按需追加常量池项，相同的项只写一次
+    #[derive(Default)]
+    struct Pool {
+        bytes: Vec<u8>,
+        indices: HashMap<Vec<u8>, u16>,
+    }
+
+    impl Pool {
+        fn entry(&mut self, entry: Vec<u8>) -> [u8; 2] {
+            let next = self.indices.len() as u16 + 1;
+            let index = *self.indices.entry(entry.clone()).or_insert_with(|| {
+                self.bytes.extend(&entry);
+                next
+            });
+            index.to_be_bytes()
+        }
+
+        fn utf8(&mut self, text: &str) -> [u8; 2] {
+            self.entry([&[1], &(text.len() as u16).to_be_bytes()[..], text.as_bytes()].concat())
+        }
+
+        /// 由若干个两字节索引（或整数的两半）组成的项
+        fn indexed(&mut self, tag: u8, parts: &[[u8; 2]]) -> [u8; 2] {
+            self.entry(std::iter::once(tag).chain(parts.concat()).collect())
+        }
+
+        fn class(&mut self, name: &str) -> [u8; 2] {
+            let name = self.utf8(name);
+            self.indexed(7, &[name])
+        }
+
+        fn string(&mut self, value: &str) -> [u8; 2] {
+            let value = self.utf8(value);
+            self.indexed(8, &[value])
+        }
+
+        fn member(&mut self, tag: u8, owner: &str, name: &str, descriptor: &str) -> [u8; 2] {
+            let owner = self.class(owner);
+            let (name, descriptor) = (self.utf8(name), self.utf8(descriptor));
+            let name_and_type = self.indexed(12, &[name, descriptor]);
+            self.indexed(tag, &[owner, name_and_type])
+        }
+
+        fn field(&mut self, owner: &str, name: &str, descriptor: &str) -> [u8; 2] {
+            self.member(9, owner, name, descriptor)
+        }
+
+        fn method(&mut self, owner: &str, name: &str, descriptor: &str) -> [u8; 2] {
+            self.member(10, owner, name, descriptor)
+        }
+    }
+
+    fn attribute(pool: &mut Pool, name: &str, body: &[u8]) -> Vec<u8> {
+        [&pool.utf8(name)[..], &(body.len() as u32).to_be_bytes(), body].concat()
+    }
+
+    fn code(pool: &mut Pool, max_stack: u16, max_locals: u16, code: &[u8], handlers: &[u8]) -> Vec<u8> {
+        let body = [
+            &max_stack.to_be_bytes()[..],
+            &max_locals.to_be_bytes(),
+            &(code.len() as u32).to_be_bytes(),
+            code,
+            &((handlers.len() / 8) as u16).to_be_bytes(),
+            handlers,
+            &[0, 0],
+        ]
+        .concat();
+        attribute(pool, "Code", &body)
+    }
+
+    /// 与 DEX_SAMPLE 对应的 class 文件
+    fn jvm_sample() -> ClassFile {
+        let mut pool = Pool::default();
+        let this_class = pool.class("app/Sample");
+        let super_class = pool.class("java/lang/Thread");
+        let runnable = pool.class("java/lang/Runnable");
+
+        let mut fields = Vec::new();
+        let limit_value = pool.indexed(3, &[[0, 0], [0, 3]]);
+        let constant_value = attribute(&mut pool, "ConstantValue", &limit_value);
+        for (flags, name, descriptor, attributes) in [(0x0018u16, "LIMIT", "I", Some(constant_value)), (0x0002, "name", "Ljava/lang/String;", None)] {
+            fields.extend(flags.to_be_bytes());
+            fields.extend(pool.utf8(name));
+            fields.extend(pool.utf8(descriptor));
+            fields.extend(if attributes.is_some() { [0, 1] } else { [0, 0] });
+            fields.extend(attributes.unwrap_or_default());
+        }
+
+        let thread_init = pool.method("java/lang/Thread", "<init>", "()V");
+        let name_field = pool.field("app/Sample", "name", "Ljava/lang/String;");
+        let init = [&[0x2a, 0xb7][..], &thread_init, &[0x2a, 0x2b, 0xb5], &name_field, &[0xb1]].concat();
+        let init = code(&mut pool, 2, 2, &init, &[]);
+
+        let builder = pool.class("java/lang/StringBuilder");
+        let builder_init = pool.method("java/lang/StringBuilder", "<init>", "()V");
+        let append = pool.method("java/lang/StringBuilder", "append", "(Ljava/lang/String;)Ljava/lang/StringBuilder;");
+        let bang = pool.string("!");
+        let string = pool.class("java/lang/String");
+        let run = pool.member(11, "java/lang/Runnable", "run", "()V");
+        let helper = pool.method("app/Sample", "helper", "(I)V");
+        let caught = pool.class("java/lang/IllegalStateException");
+        let run_code = [
+            &[0xbb][..], &builder, &[0x59, 0xb7], &builder_init, // 0: new, dup, invokespecial
+            &[0x4c, 0x2b, 0xc0], &builder, // 7: astore_1, aload_1, checkcast
+            &[0x2a, 0xb4], &name_field, &[0xb6], &append, // 12: aload_0, getfield, invokevirtual
+            &[0x12, bang[1], 0xb6], &append, &[0x57], // 19: ldc, invokevirtual, pop
+            &[0x08, 0xbc, 10, 0x57], // 25: iconst_5, newarray int, pop
+            &[0x05, 0xbd], &string, &[0x57], // 29: iconst_2, anewarray, pop
+            &[0x2a, 0xb9], &run, &[1, 0], // 34: aload_0, invokeinterface
+            &[0x10, 100, 0xb8], &helper, // 40: bipush, invokestatic
+            &[0xa7, 0, 4, 0x4c, 0xb1], // 45: goto 49, astore_1, return
+        ]
+        .concat();
+        let handlers = [&[0, 0, 0, 45, 0, 48][..], &caught].concat();
+        let run_code = code(&mut pool, 3, 2, &run_code, &handlers);
+        let helper_code = code(&mut pool, 0, 1, &[0xb1], &[]);
+
+        let mut methods = Vec::new();
+        for (flags, name, descriptor, code) in [
+            (0x0001u16, "<init>", "(Ljava/lang/String;)V", init),
+            (0x0001, "run", "()V", run_code),
+            (0x0008, "helper", "(I)V", helper_code),
+        ] {
+            methods.extend(flags.to_be_bytes());
+            methods.extend(pool.utf8(name));
+            methods.extend(pool.utf8(descriptor));
+            methods.extend([0, 1]);
+            methods.extend(code);
+        }
+
+        let bytes = [
+            &[0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52][..],
+            &(pool.indices.len() as u16 + 1).to_be_bytes(),
+            &pool.bytes,
+            &[0x00, 0x21],
+            &this_class,
+            &super_class,
+            &[0, 1],
+            &runnable,
+            &[0, 2],
+            &fields,
+            &[0, 3],
+            &methods,
+            &[0, 0],
+        ]
+        .concat();
+        ClassFileReader::new(&bytes).read().expect("read class file")
+    }
+
+    const DEX_SAMPLE: &str = r#"
+.class public Lapp/Sample;
+.super Ljava/lang/Thread;
+.implements Ljava/lang/Runnable;
+
+.field static final LIMIT:I = 0x3
+
+.field private name:Ljava/lang/String;
+
+.method public constructor <init>(Ljava/lang/String;)V
+    .registers 2
+    invoke-direct {p0}, Ljava/lang/Thread;-><init>()V
+    iput-object p1, p0, Lapp/Sample;->name:Ljava/lang/String;
+    return-void
+.end method
+
+.method static helper(I)V
+    .registers 1
+    return-void
+.end method
+
+.method public run()V
+    .registers 3
+    :start
+    new-instance v0, Ljava/lang/StringBuilder;
+    invoke-direct {v0}, Ljava/lang/StringBuilder;-><init>()V
+    check-cast v0, Ljava/lang/StringBuilder;
+    iget-object v1, p2, Lapp/Sample;->name:Ljava/lang/String;
+    invoke-virtual {v0, v1}, Ljava/lang/StringBuilder;->append(Ljava/lang/String;)Ljava/lang/StringBuilder;
+    const-string v1, "!"
+    invoke-virtual {v0, v1}, Ljava/lang/StringBuilder;->append(Ljava/lang/String;)Ljava/lang/StringBuilder;
+    const/4 v0, 0x5
+    new-array v0, v0, [I
+    const/4 v0, 0x2
+    new-array v0, v0, [Ljava/lang/String;
+    invoke-interface {p2}, Ljava/lang/Runnable;->run()V
+    const/16 v0, 0x64
+    invoke-static {v0}, Lapp/Sample;->helper(I)V
+    :end
+    return-void
+    :handler
+    move-exception v0
+    return-void
+    .catch Ljava/lang/IllegalStateException; {:start .. :end} :handler
+.end method
+"#;
+
+    /// 方法按名字排序，调用的偏移量在两种格式中不同，比较时清零
+    fn summary(class: ClassModel) -> Vec<String> {
+        let mut lines = vec![format!("class {:?} extends {:?} implements {:?}", class.name(), class.super_name(), class.interfaces())];
+        let mut fields: Vec<String> = class
+            .fields()
+            .iter()
+            .map(|f| format!("field {} {} constant={}", f.name(), f.descriptor(), f.has_constant()))
+            .collect();
+        fields.sort();
+        lines.extend(fields);
+        let mut methods = class.methods();
+        methods.sort_by_key(|m| m.name());
+        for method in methods {
+            lines.push(format!("method {}{} code={}", method.name(), method.descriptor(), method.has_code()));
+            for reference in method.references() {
+                let reference = match reference {
+                    CodeReference::Invoke { kind, owner, name, descriptor, .. } => CodeReference::Invoke { kind, owner, name, descriptor, offset: 0 },
+                    other => other,
+                };
+                lines.push(format!("    {:?}", reference));
+            }
+            let constants: Vec<NumericConstant> = method.instructions().iter().filter_map(|i| i.constant).collect();
+            lines.push(format!("    constants {:?}", constants));
+        }
+        lines
+    }
+
+    #[test]
+    fn jvm_and_dex_backends_agree_on_the_same_class() {
+        let class_file = jvm_sample();
+        let dex_file = dex_file(&[DEX_SAMPLE]);
+        let jvm = summary(ClassModel::Jvm(&class_file));
+        let dex = summary(ClassModel::Dex(&dex_file, &dex_file.classes[0]));
+        assert_eq!(jvm, dex);
+        // new int[5] 不引用任何类
+        assert!(!jvm.iter().any(|line| line.contains("Type(\"I\")")));
+    }
+}
@@ -0,0 +1,955 @@
+// Obfuscator and packer detection: scores protection traits of a jar or APK for triage
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use serde::Serialize;
+
+use crate::android::{AndroidManifest, DexFile, Method};
+use crate::android_analyzer::dalvik_opcode::{DalvikInstruction, DalvikOpcode, DalvikOpcodeAnalyzer};
+use crate::hierarchy::normalize_class_name;
+use crate::java_analyzer::jar::JarReader;
+use crate::java_analyzer::method::JvmMethod;
+use crate::java_analyzer::opcode::*;
+use crate::model::{ClassModel, MethodModel};
+use crate::program::{program_class, CodeReference, Program};
+use crate::project::{Project, ProjectClasses, ProjectData};
+
+const ACC_STATIC: u32 = 0x0008;
+const ACC_NATIVE: u32 = 0x0100;
+const ACC_SYNTHETIC: u32 = 0x1000;
+
+/// 每个特征最多列出的证据条数
+const MAX_EVIDENCE: usize = 10;
+
+/// 平台和常见支持库的类不参与标识符统计
+const PLATFORM_PREFIXES: [&str; 9] = ["java/", "javax/", "jdk/", "sun/", "android/", "androidx/", "dalvik/", "kotlin/", "kotlinx/"];
+
+/// 分发块里的 switch 至少要有这么多条回边才视为控制流平坦化
+const MIN_FLATTENING_BACK_EDGES: usize = 3;
+
+/// A protection trait the detector looks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum TraitKind {
+    /// `a`, `b`, `aa` style class, member and package names
+    ShortIdentifiers,
+    /// Non-ASCII, keyword or `lIlI` style names that are hard or illegal to write in Java
+    UnusualIdentifiers,
+    /// Method bodies turned into a state machine dispatched by one switch
+    ControlFlowFlattening,
+    /// String decryptor helpers and string constants that are not readable text
+    StringEncryption,
+    /// Members resolved by name at run time instead of being called directly
+    ReflectionDispatch,
+    /// Code loaded from files or memory through class loaders
+    DynamicCodeLoading,
+    /// JNI libraries and the calls that load them
+    NativeLoader,
+    /// Stubs and libraries of commercial APK packers
+    PackerStub,
+    /// Markers left by a known obfuscator or shrinker
+    KnownObfuscator,
+}
+
+/// One detected trait with a 0..=100 score.
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectedTrait {
+    pub kind: TraitKind,
+    pub score: u32,
+    pub summary: String,
+    /// A few examples, e.g. class names or library entries
+    pub evidence: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ProtectionLevel {
+    None,
+    Light,
+    Moderate,
+    Heavy,
+    /// The real code is most likely encrypted and unpacked at run time
+    Packed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ObfuscationReport {
+    pub class_count: usize,
+    pub method_count: usize,
+    pub string_count: usize,
+    /// false when no method bodies were decoded, so code based checks were skipped
+    pub code_analyzed: bool,
+    /// Combined score of all traits, 0..=100
+    pub score: u32,
+    pub level: ProtectionLevel,
+    /// Names of the packers and obfuscators identified by their markers
+    pub tools: Vec<String>,
+    pub traits: Vec<DetectedTrait>,
+}
+
+/// 已知的 APK 加固：名称、桩代码类名前缀、特征文件名片段
+const PACKERS: [(&str, &[&str], &[&str]); 10] = [
+    ("Qihoo 360 Jiagu", &["com/stub/StubApp", "com/qihoo/util/"], &["libjiagu"]),
+    ("Bangcle", &["com/secneo/apkwrapper/", "com/bangcle/"], &["libsecexe", "libsecmain"]),
+    ("SecNeo DexHelper", &["com/secneo/guard/"], &["libDexHelper"]),
+    ("Tencent Legu", &["com/tencent/StubShell/"], &["libshella", "libshellx", "libtup.so"]),
+    ("Ijiami", &["s/h/e/l/l/", "com/shell/SuperApplication"], &["libexecmain.so", "ijiami.dat", "ijm_lib/"]),
+    ("Baidu Protect", &["com/baidu/protect/"], &["libbaiduprotect"]),
+    ("Alibaba Mobile Security", &["com/ali/mobisecenhance/"], &["libmobisec", "aliprotector"]),
+    ("APKProtect", &["com/apkprotect/"], &["libAPKProtect"]),
+    ("DexProtector", &["com/dexprotector/"], &["libdexprotector", "classes.dex.dat"]),
+    ("Nagapt", &["com/nagapt/"], &["libddog", "libchaosvmp"]),
+];
+
+/// 已知混淆器留下的字符串或类名：名称、标记、是否为类名前缀
+const OBFUSCATOR_MARKERS: [(&str, &str, bool); 3] = [
+    ("Allatori", "ALLATORIxDEMO", false),
+    ("DashO", "com/preemptive/", true),
+    ("R8", "~~R8{", false),
+];
+
+/// 动态加载代码的入口：(所属类, 方法名, 分值)
+const DYNAMIC_LOADERS: [(&str, &str, u32); 8] = [
+    ("dalvik/system/DexClassLoader", "<init>", 60),
+    ("dalvik/system/InMemoryDexClassLoader", "<init>", 70),
+    ("dalvik/system/PathClassLoader", "<init>", 30),
+    ("dalvik/system/DexFile", "loadDex", 70),
+    ("java/net/URLClassLoader", "<init>", 30),
+    ("java/lang/ClassLoader", "defineClass", 50),
+    ("java/security/SecureClassLoader", "defineClass", 50),
+    ("java/lang/invoke/MethodHandles$Lookup", "defineClass", 50),
+];
+
+const NATIVE_LOADERS: [(&str, &str); 4] = [
+    ("java/lang/System", "loadLibrary"),
+    ("java/lang/System", "load"),
+    ("java/lang/Runtime", "loadLibrary"),
+    ("java/lang/Runtime", "load"),
+];
+
+/// 按名字解析成员的反射 API
+const REFLECTION_CALLS: [(&str, &str); 12] = [
+    ("java/lang/Class", "forName"),
+    ("java/lang/Class", "getMethod"),
+    ("java/lang/Class", "getDeclaredMethod"),
+    ("java/lang/Class", "getField"),
+    ("java/lang/Class", "getDeclaredField"),
+    ("java/lang/Class", "getDeclaredConstructor"),
+    ("java/lang/reflect/Method", "invoke"),
+    ("java/lang/reflect/Constructor", "newInstance"),
+    ("java/lang/reflect/Field", "get"),
+    ("java/lang/reflect/Field", "set"),
+    ("java/lang/invoke/MethodHandles$Lookup", "findVirtual"),
+    ("java/lang/invoke/MethodHandles$Lookup", "findStatic"),
+];
+
+const JAVA_KEYWORDS: [&str; 53] = [
+    "abstract", "assert", "boolean", "break", "byte", "case", "catch", "char", "class", "const", "continue", "default", "do",
+    "double", "else", "enum", "extends", "final", "finally", "float", "for", "goto", "if", "implements", "import",
+    "instanceof", "int", "interface", "long", "native", "new", "package", "private", "protected", "public", "return",
+    "short", "static", "strictfp", "super", "switch", "synchronized", "this", "throw", "throws", "transient", "try",
+    "void", "volatile", "while", "true", "false", "null",
+];
+
+/// 被调用方法 (所属类, 方法名) -> 调用者
+type CallerMap = BTreeMap<(String, String), BTreeSet<String>>;
+
+/// 检测需要的项目信息。控制流和字符串解密的指令级检查在读入每个类时完成，之后不保留类本身
+struct Evidence {
+    program: Program,
+    shapes: CodeShapes,
+    strings: Vec<String>,
+    /// 被引用的方法 (所属类, 方法名) -> 引用它的方法
+    references: CallerMap,
+    /// 归档中的文件名
+    entries: Vec<String>,
+    /// 清单中声明但代码中不存在的组件
+    missing_components: Vec<String>,
+}
+
+/// 逐个类检查的方法形状
+#[derive(Default)]
+struct CodeShapes {
+    /// 有方法体的方法数
+    with_code: usize,
+    flattened: Vec<String>,
+    /// 形似解密函数的方法 (所属类, 方法名, 签名)
+    decryptors: Vec<(String, String, String)>,
+}
+
+impl CodeShapes {
+    fn add_class(&mut self, class: &ClassModel) {
+        let class_name = class.name().unwrap_or_default();
+        for method in class.methods() {
+            if method.has_code() {
+                self.with_code += 1;
+                if is_flattened(method) {
+                    self.flattened.push(format!("{}.{}{}", class_name, method.name(), method.descriptor()));
+                }
+            }
+            if is_decryptor(method) {
+                self.decryptors.push((class_name.clone(), method.name().to_string(), method.descriptor()));
+            }
+        }
+    }
+}
+
+impl Evidence {
+    /// 逐个类读取项目；`entries` 是归档中的文件名，`manifest` 只有 Android 项目才有
+    fn from_project(classes: &ProjectClasses, entries: Vec<String>, manifest: Option<&AndroidManifest>) -> Self {
+        let mut shapes = CodeShapes::default();
+        let mut program_classes = Vec::new();
+        classes.for_each_class(|class| {
+            shapes.add_class(&class);
+            program_classes.extend(program_class(class));
+        });
+        let program = Program::new(program_classes);
+        let (code_strings, references) = code_references(&program);
+        // DEX 的字符串池里还有注解、调试信息等处的字符串，全部参与检测
+        let strings = match classes {
+            ProjectClasses::Classpath(_) => code_strings,
+            _ => classes.dex_strings(),
+        };
+        let missing_components = manifest
+            .map(|manifest| {
+                manifest
+                    .component_classes()
+                    .into_iter()
+                    .map(|(_, class_name)| normalize_class_name(&class_name))
+                    .filter(|class_name| !program.classes.contains_key(class_name))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Evidence {
+            program,
+            shapes,
+            strings,
+            references,
+            entries,
+            missing_components,
+        }
+    }
+
+    fn method_count(&self) -> usize {
+        self.program.classes.values().map(|class| class.methods.len()).sum()
+    }
+
+    fn code_analyzed(&self) -> bool {
+        self.program.has_code()
+    }
+
+    /// 被调用的某个方法及调用者
+    fn callers(&self, owner: &str, name: &str) -> Option<&BTreeSet<String>> {
+        self.references.get(&(owner.to_string(), name.to_string()))
+    }
+}
+
+/// 方法体中的字符串常量和调用关系
+fn code_references(program: &Program) -> (Vec<String>, CallerMap) {
+    let mut strings = Vec::new();
+    let mut references: CallerMap = BTreeMap::new();
+    for class in program.classes.values() {
+        for method in &class.methods {
+            for reference in &method.references {
+                match reference {
+                    CodeReference::Invoke { owner, name, .. } => {
+                        references
+                            .entry((owner.clone(), name.clone()))
+                            .or_default()
+                            .insert(format!("{}.{}", class.name, method.name));
+                    }
+                    CodeReference::String(value) => strings.push(value.clone()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    (strings, references)
+}
+
+/// `ratio` 达到 `full` 时记满分
+fn ratio_score(ratio: f64, full: f64) -> u32 {
+    ((ratio / full).min(1.0) * 100.0).round() as u32
+}
+
+fn take_evidence<I: IntoIterator<Item = String>>(items: I) -> Vec<String> {
+    items.into_iter().take(MAX_EVIDENCE).collect()
+}
+
+fn is_platform(class_name: &str) -> bool {
+    PLATFORM_PREFIXES.iter().any(|prefix| class_name.starts_with(prefix))
+}
+
+/// 内部类名取 `$` 之后的部分，匿名内部类的数字名不算
+fn simple_name(class_name: &str) -> &str {
+    let name = class_name.rsplit('/').next().unwrap_or(class_name);
+    name.rsplit('$').next().unwrap_or(name)
+}
+
+fn is_short(name: &str) -> bool {
+    let count = name.chars().count();
+    count > 0 && count <= 2 && !name.chars().all(|c| c.is_ascii_digit())
+}
+
+/// 非 ASCII、Java 关键字、不能作为 Java 标识符，或者只由 I / l / 1 / O / 0 组成的易混淆名字
+fn unusual_reason(name: &str) -> Option<&'static str> {
+    if name.is_empty() || name.starts_with('<') {
+        return None;
+    }
+    if !name.is_ascii() {
+        return Some("non-ASCII");
+    }
+    if JAVA_KEYWORDS.contains(&name) {
+        return Some("keyword");
+    }
+    let mut chars = name.chars();
+    let valid_start = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$');
+    if !valid_start || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$') {
+        return Some("invalid Java identifier");
+    }
+    if name.len() >= 4 && name.chars().all(|c| matches!(c, 'I' | 'l' | '1' | 'O' | '0')) {
+        return Some("confusable");
+    }
+    None
+}
+
+fn detect_identifiers(evidence: &Evidence) -> Vec<DetectedTrait> {
+    let mut total = 0usize;
+    let mut short = Vec::new();
+    let mut unusual: Vec<(String, &'static str)> = Vec::new();
+    let mut short_packages = BTreeSet::new();
+    let mut packages = BTreeSet::new();
+    for class in evidence.program.classes.values().filter(|class| !is_platform(&class.name)) {
+        let mut names = vec![(simple_name(&class.name).to_string(), class.name.clone())];
+        names.extend(class.fields.iter().filter(|f| f.access_flags & ACC_SYNTHETIC == 0).map(|f| (f.name.clone(), format!("{}.{}", class.name, f.name))));
+        names.extend(
+            class
+                .methods
+                .iter()
+                .filter(|m| m.access_flags & ACC_SYNTHETIC == 0 && !m.name.starts_with('<'))
+                .map(|m| (m.name.clone(), format!("{}.{}{}", class.name, m.name, m.descriptor))),
+        );
+        for (name, display) in names {
+            total += 1;
+            if name.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            if let Some(reason) = unusual_reason(&name) {
+                unusual.push((display, reason));
+            } else if is_short(&name) {
+                short.push(display);
+            }
+        }
+        if let Some((package, _)) = class.name.rsplit_once('/') {
+            packages.insert(package.to_string());
+            if package.split('/').all(is_short) {
+                short_packages.insert(package.to_string());
+            }
+        }
+    }
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let mut traits = Vec::new();
+    let short_ratio = short.len() as f64 / total as f64;
+    let package_ratio = if packages.is_empty() { 0.0 } else { short_packages.len() as f64 / packages.len() as f64 };
+    // 只有一两个包时，`a/b` 这样的包名不足以说明问题
+    let package_bonus = if packages.len() >= 3 && package_ratio >= 0.5 { 20 } else { 0 };
+    let score = (ratio_score(short_ratio, 0.5) + package_bonus).min(100);
+    if score >= 10 {
+        traits.push(DetectedTrait {
+            kind: TraitKind::ShortIdentifiers,
+            score,
+            summary: format!(
+                "{} of {} names ({:.0}%) are one or two characters long, {} of {} packages use only short segments",
+                short.len(),
+                total,
+                short_ratio * 100.0,
+                short_packages.len(),
+                packages.len()
+            ),
+            evidence: take_evidence(short),
+        });
+    }
+    if !unusual.is_empty() {
+        let mut reasons: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, reason) in &unusual {
+            *reasons.entry(reason).or_default() += 1;
+        }
+        let ratio = unusual.len() as f64 / total as f64;
+        traits.push(DetectedTrait {
+            kind: TraitKind::UnusualIdentifiers,
+            score: 30 + ratio_score(ratio, 0.2) * 70 / 100,
+            summary: format!(
+                "{} names cannot be written in Java source ({})",
+                unusual.len(),
+                reasons.iter().map(|(reason, count)| format!("{} {}", count, reason)).collect::<Vec<_>>().join(", ")
+            ),
+            evidence: take_evidence(unusual.into_iter().map(|(display, reason)| format!("{} ({})", display.escape_debug(), reason))),
+        });
+    }
+    traits
+}
+
+/// 读写 int 局部变量的指令对应的变量号
+fn int_local(insn: &Instruction, load: bool) -> Option<i32> {
+    let (base, first) = if load { (OP_ILOAD, OP_ILOAD_0) } else { (OP_ISTORE, OP_ISTORE_0) };
+    match insn.opcode {
+        opcode if opcode == base => Some(insn.value),
+        opcode if (first..first + 4).contains(&opcode) => Some((opcode - first) as i32),
+        OP_WIDE if insn.value == base as i32 => Some(insn.value2),
+        _ => None,
+    }
+}
+
+/// 分发块：switch 之前最多三条指令读取状态变量，各分支给状态变量赋值后跳回分发块。
+/// javac 把 `break` 编译成跳到循环末尾的一条 goto，跳向这条 goto 的分支也算回边
+fn is_flattened_jvm(method: &JvmMethod) -> bool {
+    let code = &method.code;
+    let target = |branch: &Instruction| branch.offset as i64 + branch.value as i64;
+    for (index, insn) in code.iter().enumerate() {
+        if !matches!(insn.opcode, OP_TABLESWITCH | OP_LOOKUPSWITCH) || insn.pairs.len() + 1 < MIN_FLATTENING_BACK_EDGES {
+            continue;
+        }
+        let window = &code[index.saturating_sub(3)..index];
+        let Some(state) = window.iter().find_map(|insn| int_local(insn, true)) else {
+            continue;
+        };
+        let head = window[0].offset as i64..=insn.offset as i64;
+        let body = &code[index + 1..];
+        let gotos: Vec<&Instruction> = body.iter().filter(|branch| matches!(branch.opcode, OP_GOTO | OP_GOTO_W)).collect();
+        let trampolines: HashSet<i64> = gotos.iter().filter(|branch| head.contains(&target(branch))).map(|branch| branch.offset as i64).collect();
+        let back_edges = gotos
+            .iter()
+            .filter(|branch| head.contains(&target(branch)) || trampolines.contains(&target(branch)))
+            .count();
+        let state_writes = body.iter().filter(|insn| int_local(insn, false) == Some(state)).count();
+        if back_edges >= MIN_FLATTENING_BACK_EDGES && state_writes >= MIN_FLATTENING_BACK_EDGES {
+            return true;
+        }
+    }
+    false
+}
+
+/// 把结果写入第一个寄存器的指令：move、常量、一元和二元运算
+fn written_register(insn: &DalvikInstruction) -> Option<u32> {
+    match insn.opcode as u8 {
+        0x01..=0x0d | 0x12..=0x19 | 0x7b..=0xe2 => insn.registers.first().copied(),
+        _ => None,
+    }
+}
+
+/// Dalvik 的分发块：switch 直接读取状态寄存器，之前最多三条 move 把状态复制过来；回边的判断与 JVM 相同
+fn is_flattened_dalvik(dex_file: &DexFile, method: &Method) -> bool {
+    use DalvikOpcode::*;
+    let Some(code) = &method.code else {
+        return false;
+    };
+    let Ok(decoded) = DalvikOpcodeAnalyzer::new().analyze_method(code, dex_file) else {
+        return false;
+    };
+    let instructions: Vec<&DalvikInstruction> = decoded.iter().filter(|insn| insn.payload.is_none()).collect();
+    for (index, insn) in instructions.iter().enumerate() {
+        if !matches!(insn.opcode, PackedSwitch | SparseSwitch) || insn.switch_cases(&decoded).len() + 1 < MIN_FLATTENING_BACK_EDGES {
+            continue;
+        }
+        let Some(&switch_register) = insn.registers.first() else {
+            continue;
+        };
+        let window = &instructions[index.saturating_sub(3)..index];
+        let mut state = vec![switch_register];
+        for copy in window.iter().rev() {
+            if let (Move | MoveFrom16 | Move16, [target, source]) = (copy.opcode, copy.registers.as_slice()) {
+                if state.contains(target) {
+                    state.push(*source);
+                }
+            }
+        }
+        let head = window.first().map_or(insn.address, |first| first.address)..=insn.address;
+        let body = &instructions[index + 1..];
+        let gotos: Vec<&DalvikInstruction> = body.iter().copied().filter(|branch| matches!(branch.opcode, Goto | Goto16 | Goto32)).collect();
+        let trampolines: HashSet<u32> = gotos
+            .iter()
+            .filter(|branch| branch.target.is_some_and(|target| head.contains(&target)))
+            .map(|branch| branch.address)
+            .collect();
+        let back_edges = gotos
+            .iter()
+            .filter(|branch| branch.target.is_some_and(|target| head.contains(&target) || trampolines.contains(&target)))
+            .count();
+        let state_writes = body.iter().filter(|insn| written_register(insn).is_some_and(|register| state.contains(&register))).count();
+        if back_edges >= MIN_FLATTENING_BACK_EDGES && state_writes >= MIN_FLATTENING_BACK_EDGES {
+            return true;
+        }
+    }
+    false
+}
+
+fn is_flattened(method: MethodModel) -> bool {
+    match method {
+        MethodModel::Jvm(_, method) => is_flattened_jvm(method),
+        MethodModel::Dex(dex_file, method) => is_flattened_dalvik(dex_file, method),
+    }
+}
+
+fn detect_flattening(evidence: &Evidence) -> Option<DetectedTrait> {
+    let CodeShapes { with_code, flattened, .. } = &evidence.shapes;
+    if flattened.is_empty() {
+        return None;
+    }
+    let ratio = flattened.len() as f64 / *with_code as f64;
+    Some(DetectedTrait {
+        kind: TraitKind::ControlFlowFlattening,
+        score: 20 + ratio_score(ratio, 0.1) * 80 / 100,
+        summary: format!("{} of {} methods dispatch their blocks through a switch inside a loop", flattened.len(), with_code),
+        evidence: take_evidence(flattened.clone()),
+    })
+}
+
+/// 不是可读文本的字符串：大部分字符是控制字符、私有区字符或替换字符
+fn is_garbled(value: &str) -> bool {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 4 {
+        return false;
+    }
+    let odd = chars
+        .iter()
+        .filter(|c| {
+            let code = **c as u32;
+            (code < 0x20 && !matches!(c, '\n' | '\r' | '\t')) || (0x7f..0xa0).contains(&code) || (0xe000..0xf900).contains(&code) || code == 0xfffd
+        })
+        .count();
+    odd * 2 >= chars.len()
+}
+
+/// 解密函数的形状：静态、返回 String、参数只有字符串或基本类型（及其数组），方法体做异或并逐字符处理
+fn is_decryptor(method: MethodModel) -> bool {
+    let descriptor = method.descriptor();
+    if method.access_flags() & ACC_STATIC == 0 || !descriptor.ends_with(")Ljava/lang/String;") || descriptor.starts_with("()") {
+        return false;
+    }
+    let parameters = descriptor[1..descriptor.find(')').unwrap_or(1)].replace("Ljava/lang/String;", "");
+    if parameters.contains('L') {
+        return false;
+    }
+    let instructions = method.instructions();
+    let xor = instructions.iter().any(|insn| matches!(insn.mnemonic, "ixor" | "lxor") || insn.mnemonic.starts_with("xor-"));
+    let per_char = instructions.iter().any(|insn| {
+        matches!(insn.mnemonic, "caload" | "castore" | "baload" | "bastore" | "aget-char" | "aput-char" | "aget-byte" | "aput-byte")
+            || insn.references.iter().any(|reference| {
+                matches!(reference, CodeReference::Invoke { owner, name, .. }
+                    if owner == "java/lang/String" && matches!(name.as_str(), "toCharArray" | "charAt" | "getBytes" | "<init>"))
+            })
+    });
+    xor && per_char
+}
+
+fn detect_string_encryption(evidence: &Evidence) -> Option<DetectedTrait> {
+    let mut decryptors = Vec::new();
+    let mut call_sites = 0usize;
+    for (class_name, name, descriptor) in &evidence.shapes.decryptors {
+        let callers = evidence.callers(class_name, name).map_or(0, BTreeSet::len);
+        if callers > 0 {
+            call_sites += callers;
+            decryptors.push(format!("{}.{}{} (called from {} methods)", class_name, name, descriptor, callers));
+        }
+    }
+    let garbled: Vec<&String> = evidence.strings.iter().filter(|value| is_garbled(value)).collect();
+    if decryptors.is_empty() && garbled.is_empty() {
+        return None;
+    }
+
+    let garbled_score = if evidence.strings.is_empty() { 0 } else { ratio_score(garbled.len() as f64 / evidence.strings.len() as f64, 0.1) };
+    let decryptor_score = if decryptors.is_empty() { 0 } else { 40 + (call_sites.min(60) as u32) };
+    let mut evidence_items = decryptors.clone();
+    evidence_items.extend(garbled.iter().map(|value| format!("\"{}\"", value.escape_debug())));
+    Some(DetectedTrait {
+        kind: TraitKind::StringEncryption,
+        score: decryptor_score.max(garbled_score),
+        summary: format!(
+            "{} decryptor-shaped methods called from {} methods, {} of {} string constants are not readable text",
+            decryptors.len(),
+            call_sites,
+            garbled.len(),
+            evidence.strings.len()
+        ),
+        evidence: take_evidence(evidence_items),
+    })
+}
+
+fn detect_reflection(evidence: &Evidence) -> Option<DetectedTrait> {
+    let mut callers = BTreeSet::new();
+    let mut used = Vec::new();
+    for (owner, name) in REFLECTION_CALLS {
+        if let Some(sites) = evidence.callers(owner, name) {
+            used.push(format!("{}.{}", owner, name));
+            callers.extend(sites.iter().cloned());
+        }
+    }
+    if used.is_empty() {
+        return None;
+    }
+    let invokes = evidence.callers("java/lang/reflect/Method", "invoke").is_some();
+    let (score, summary) = if evidence.code_analyzed() {
+        let ratio = callers.len() as f64 / evidence.method_count().max(1) as f64;
+        (
+            ratio_score(ratio, 0.05),
+            format!("{} methods resolve classes or members by name through {} reflection APIs", callers.len(), used.len()),
+        )
+    } else {
+        // 没有方法体，只知道引用了哪些反射 API
+        (
+            if invokes { 25 } else { 10 },
+            format!("{} reflection APIs are referenced, call sites could not be counted", used.len()),
+        )
+    };
+    if score == 0 {
+        return None;
+    }
+    Some(DetectedTrait {
+        kind: TraitKind::ReflectionDispatch,
+        score,
+        summary,
+        evidence: take_evidence(used.into_iter().chain(callers)),
+    })
+}
+
+/// 引用描述：列出调用者，没有方法体时只知道被引用
+fn reference_evidence(evidence: &Evidence, owner: &str, name: &str) -> Option<String> {
+    let callers = evidence.callers(owner, name)?;
+    Some(match callers.iter().next() {
+        Some(caller) if callers.len() > 1 => format!("{}.{} called from {} and {} more", owner, name, caller, callers.len() - 1),
+        Some(caller) => format!("{}.{} called from {}", owner, name, caller),
+        None => format!("{}.{} referenced", owner, name),
+    })
+}
+
+fn detect_dynamic_loading(evidence: &Evidence) -> Option<DetectedTrait> {
+    let found: Vec<(String, u32)> = DYNAMIC_LOADERS
+        .iter()
+        .filter_map(|(owner, name, score)| Some((reference_evidence(evidence, owner, name)?, *score)))
+        .collect();
+    let hidden_dex: Vec<&String> = evidence
+        .entries
+        .iter()
+        .filter(|name| {
+            let lower = name.to_lowercase();
+            !(name.starts_with("classes") && name.ends_with(".dex")) && (lower.ends_with(".dex") || (lower.ends_with(".jar") && name.starts_with("assets/")))
+        })
+        .collect();
+    if found.is_empty() && hidden_dex.is_empty() {
+        return None;
+    }
+    let score = found.iter().map(|(_, score)| *score).max().unwrap_or(0) + if hidden_dex.is_empty() { 0 } else { 20 };
+    Some(DetectedTrait {
+        kind: TraitKind::DynamicCodeLoading,
+        score: score.min(100),
+        summary: format!("{} class loading APIs are used, {} bundled code files are loaded outside the normal class path", found.len(), hidden_dex.len()),
+        evidence: take_evidence(found.into_iter().map(|(text, _)| text).chain(hidden_dex.into_iter().cloned())),
+    })
+}
+
+fn is_native_library(name: &str) -> bool {
+    [".so", ".dll", ".dylib", ".jnilib"].iter().any(|extension| name.ends_with(extension))
+}
+
+fn detect_native_loaders(evidence: &Evidence) -> Option<DetectedTrait> {
+    let loaders: Vec<String> = NATIVE_LOADERS.iter().filter_map(|(owner, name)| reference_evidence(evidence, owner, name)).collect();
+    let libraries: Vec<&String> = evidence.entries.iter().filter(|name| is_native_library(name)).collect();
+    let native_methods: Vec<String> = evidence
+        .program
+        .classes
+        .values()
+        .filter(|class| !is_platform(&class.name))
+        .flat_map(|class| {
+            class
+                .methods
+                .iter()
+                .filter(|m| m.access_flags & ACC_NATIVE != 0)
+                .map(move |m| format!("{}.{}{}", class.name, m.name, m.descriptor))
+        })
+        .collect();
+    if loaders.is_empty() && libraries.is_empty() && native_methods.is_empty() {
+        return None;
+    }
+    // JNI 很常见，本身只是轻度信号
+    let score = 10 + (libraries.len().min(6) * 5) as u32 + if native_methods.is_empty() { 0 } else { 10 };
+    Some(DetectedTrait {
+        kind: TraitKind::NativeLoader,
+        score: score.min(60),
+        summary: format!(
+            "{} native libraries are bundled, {} library loading calls, {} native methods",
+            libraries.len(),
+            loaders.len(),
+            native_methods.len()
+        ),
+        evidence: take_evidence(loaders.into_iter().chain(libraries.into_iter().cloned()).chain(native_methods)),
+    })
+}
+
+fn detect_packers(evidence: &Evidence, tools: &mut Vec<String>) -> Option<DetectedTrait> {
+    let mut found = Vec::new();
+    for (name, class_prefixes, files) in PACKERS {
+        let classes = evidence
+            .program
+            .classes
+            .keys()
+            .filter(|class_name| class_prefixes.iter().any(|prefix| class_name.starts_with(prefix)));
+        let entries = evidence.entries.iter().filter(|entry| files.iter().any(|file| entry.contains(file)));
+        let matches: Vec<String> = classes.chain(entries).cloned().collect();
+        if !matches.is_empty() {
+            tools.push(name.to_string());
+            found.extend(matches.into_iter().map(|item| format!("{}: {}", name, item)));
+        }
+    }
+    let missing = evidence.missing_components.len();
+    if found.is_empty() && missing == 0 {
+        return None;
+    }
+    let score = if found.is_empty() { 40 + (missing.min(6) * 10) as u32 } else { 90 + (missing.min(2) * 5) as u32 };
+    Some(DetectedTrait {
+        kind: TraitKind::PackerStub,
+        score: score.min(100),
+        summary: format!(
+            "{} packer markers found, {} manifest components are missing from the DEX code",
+            found.len(),
+            missing
+        ),
+        evidence: take_evidence(found.into_iter().chain(evidence.missing_components.iter().map(|c| format!("missing component {}", c)))),
+    })
+}
+
+fn detect_obfuscators(evidence: &Evidence, tools: &mut Vec<String>) -> Option<DetectedTrait> {
+    let mut found = Vec::new();
+    let mut score = 0;
+    for (name, marker, class_prefix) in OBFUSCATOR_MARKERS {
+        let hit = if class_prefix {
+            evidence.program.classes.keys().find(|class_name| class_name.starts_with(marker)).cloned()
+        } else {
+            evidence
+                .strings
+                .iter()
+                .chain(evidence.program.classes.keys())
+                .find(|value| value.contains(marker))
+                .map(|value| value.chars().take(120).collect())
+        };
+        if let Some(hit) = hit {
+            tools.push(name.to_string());
+            found.push(format!("{}: {}", name, hit.escape_debug()));
+            // R8 标记只说明经过了 R8 处理，不一定开启了混淆
+            score = score.max(if name == "R8" { 20 } else { 60 });
+        }
+    }
+    if found.is_empty() {
+        return None;
+    }
+    Some(DetectedTrait {
+        kind: TraitKind::KnownObfuscator,
+        score,
+        summary: format!("Markers of {} found", tools.join(", ")),
+        evidence: take_evidence(found),
+    })
+}
+
+fn detect(evidence: &Evidence) -> ObfuscationReport {
+    let mut tools = Vec::new();
+    let mut traits = detect_identifiers(evidence);
+    traits.extend(detect_flattening(evidence));
+    traits.extend(detect_string_encryption(evidence));
+    traits.extend(detect_reflection(evidence));
+    traits.extend(detect_dynamic_loading(evidence));
+    traits.extend(detect_native_loaders(evidence));
+    traits.extend(detect_packers(evidence, &mut tools));
+    let packer_count = tools.len();
+    let mut obfuscators = Vec::new();
+    traits.extend(detect_obfuscators(evidence, &mut obfuscators));
+    tools.extend(obfuscators);
+    traits.sort_by(|a, b| b.score.cmp(&a.score).then(a.kind.cmp(&b.kind)));
+
+    // 各特征视为独立信号：1 - Π(1 - s)
+    let remaining: f64 = traits.iter().map(|t| 1.0 - t.score.min(100) as f64 / 100.0).product();
+    let score = ((1.0 - remaining) * 100.0).round() as u32;
+    let packed = packer_count > 0 || traits.iter().any(|t| t.kind == TraitKind::PackerStub && t.score >= 80);
+    let level = match score {
+        _ if packed => ProtectionLevel::Packed,
+        0..=14 => ProtectionLevel::None,
+        15..=39 => ProtectionLevel::Light,
+        40..=69 => ProtectionLevel::Moderate,
+        _ => ProtectionLevel::Heavy,
+    };
+
+    let string_count: HashSet<&String> = evidence.strings.iter().collect();
+    ObfuscationReport {
+        class_count: evidence.program.classes.len(),
+        method_count: evidence.method_count(),
+        string_count: string_count.len(),
+        code_analyzed: evidence.code_analyzed(),
+        score,
+        level,
+        tools,
+        traits,
+    }
+}
+
+fn project_evidence(project_id: &str) -> Result<Evidence, String> {
+    let (classes, entries, apk) = Project::with_project_mut(project_id, |project| {
+        let classes = ProjectClasses::of(&mut project.data)?;
+        match &project.data {
+            ProjectData::Java(java_data) => {
+                let entries = java_data.classpath.list_entries().into_iter().filter(|entry| !entry.is_directory).map(|entry| entry.name).collect();
+                Ok((classes, entries, None))
+            }
+            ProjectData::Android(android_data) => Ok((classes, Vec::new(), Some((android_data.apk_path.clone(), android_data.manifest.clone())))),
+            _ => Err("Not a Java or Android project".to_string()),
+        }
+    })?;
+    // APK 的文件列表和所有类都在锁外读取
+    let (entries, manifest) = match apk {
+        Some((apk_path, manifest)) => {
+            let entries = JarReader::new(&apk_path)
+                .list_entries()?
+                .into_iter()
+                .filter(|entry| !entry.is_directory)
+                .map(|entry| entry.name)
+                .collect();
+            (entries, manifest)
+        }
+        None => (entries, None),
+    };
+    Ok(Evidence::from_project(&classes, entries, manifest.as_ref()))
+}
+
+/// Scores the obfuscation and packing traits of a project.
+#[tauri::command(async)]
+pub fn obfuscation_report(project_id: String) -> Result<ObfuscationReport, String> {
+    Ok(detect(&project_evidence(&project_id)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+
+    const MACHINE: &str = r#"
+.class public LMachine;
+.super Ljava/lang/Object;
+
+.method public static run(I)I
+    .registers 3
+    const/4 v0, 0x0
+    :dispatch
+    move v1, v0
+    packed-switch v1, :states
+    return p0
+    :first
+    add-int/lit8 p0, p0, 0x1
+    const/4 v0, 0x1
+    goto :dispatch
+    :second
+    mul-int/lit8 p0, p0, 0x2
+    const/4 v0, 0x2
+    goto :dispatch
+    :third
+    const/4 v0, 0x3
+    goto :dispatch
+    :states
+    .packed-switch 0x0
+        :first
+        :second
+        :third
+    .end packed-switch
+.end method
+
+.method public static pick(I)I
+    .registers 2
+    sparse-switch p0, :cases
+    const/4 v0, 0x0
+    return v0
+    :one
+    const/4 v0, 0x1
+    return v0
+    :two
+    const/4 v0, 0x2
+    return v0
+    :three
+    const/4 v0, 0x3
+    return v0
+    :cases
+    .sparse-switch
+        0x10 -> :one
+        0x20 -> :two
+        0x30 -> :three
+    .end sparse-switch
+.end method
+"#;
+
+    const SECRET: &str = r#"
+.class public LSecret;
+.super Ljava/lang/Object;
+
+.method public static xor(Ljava/lang/String;I)Ljava/lang/String;
+    .registers 6
+    invoke-virtual {p0}, Ljava/lang/String;->toCharArray()[C
+    move-result-object v0
+    array-length v1, v0
+    const/4 v2, 0x0
+    :loop
+    if-ge v2, v1, :done
+    aget-char v3, v0, v2
+    xor-int/2addr v3, p1
+    int-to-char v3, v3
+    aput-char v3, v0, v2
+    add-int/lit8 v2, v2, 0x1
+    goto :loop
+    :done
+    new-instance v3, Ljava/lang/String;
+    invoke-direct {v3, v0}, Ljava/lang/String;-><init>([C)V
+    return-object v3
+.end method
+
+.method public static plain(Ljava/lang/String;)Ljava/lang/String;
+    .registers 1
+    return-object p0
+.end method
+
+.method public static use()V
+    .registers 2
+    const-string v0, "idmmn"
+    const/4 v1, 0x1
+    invoke-static {v0, v1}, LSecret;->xor(Ljava/lang/String;I)Ljava/lang/String;
+    move-result-object v0
+    invoke-static {v0}, LSecret;->plain(Ljava/lang/String;)Ljava/lang/String;
+    return-void
+.end method
+"#;
+
+    fn dex_evidence(classes: &[&str]) -> Evidence {
+        let dex_files = [dex_file(classes)];
+        let mut shapes = CodeShapes::default();
+        for class in ClassModel::dex_classes(&dex_files) {
+            shapes.add_class(&class);
+        }
+        let program = Program::from_classes(ClassModel::dex_classes(&dex_files));
+        let (strings, references) = code_references(&program);
+        Evidence {
+            program,
+            shapes,
+            strings,
+            references,
+            entries: Vec::new(),
+            missing_components: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn dalvik_state_machines_are_flattened() {
+        let detected = detect_flattening(&dex_evidence(&[MACHINE])).expect("flattening detected");
+        // 没有回边的普通 switch 不算
+        assert_eq!(detected.evidence, vec!["Machine.run(I)I".to_string()]);
+        assert!(detected.summary.starts_with("1 of 2 methods"));
+    }
+
+    #[test]
+    fn dalvik_string_decryptors_are_detected() {
+        let detected = detect_string_encryption(&dex_evidence(&[SECRET])).expect("decryptor detected");
+        assert_eq!(detected.evidence, vec!["Secret.xor(Ljava/lang/String;I)Ljava/lang/String; (called from 1 methods)".to_string()]);
+    }
+}
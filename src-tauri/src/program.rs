@@ -0,0 +1,201 @@
+// Whole-program model: every class and member of a project with the references made by each method body
+use std::collections::BTreeMap;
+
+use crate::project::ProjectClasses;
+use crate::hierarchy::{ClassHierarchy, MethodInfo, TypeNode};
+use crate::model::ClassModel;
+
+/// Kind of a call site, mirrors the JVM invoke instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum InvokeKind {
+    Virtual,
+    Special,
+    Static,
+    Interface,
+}
+
+/// One reference made by a method body. Owners are internal names (`com/foo/Bar`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CodeReference {
+    Invoke {
+        kind: InvokeKind,
+        owner: String,
+        name: String,
+        descriptor: String,
+        offset: u32,
+    },
+    Field {
+        owner: String,
+        name: String,
+        descriptor: String,
+        write: bool,
+    },
+    /// `new`
+    Instantiate(String),
+    /// checkcast, instanceof, array creation, class constants and catch types
+    Type(String),
+    String(String),
+    /// Method handle constants and lambda / method reference targets of `invokedynamic`
+    MethodHandle {
+        owner: String,
+        name: String,
+        descriptor: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ProgramField {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u32,
+    /// 有编译期常量值的字段，读取处可能已被编译器内联
+    pub constant: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ProgramMethod {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u32,
+    /// false for abstract and native methods, and for DEX methods whose code is not decoded
+    pub has_code: bool,
+    pub references: Vec<CodeReference>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ProgramClass {
+    pub name: String,
+    pub super_name: Option<String>,
+    pub interfaces: Vec<String>,
+    pub access_flags: u32,
+    pub fields: Vec<ProgramField>,
+    pub methods: Vec<ProgramMethod>,
+}
+
+impl ProgramClass {
+    pub fn method(&self, name: &str, descriptor: &str) -> Option<&ProgramMethod> {
+        self.methods.iter().find(|m| m.name == name && m.descriptor == descriptor)
+    }
+
+    pub fn field(&self, name: &str, descriptor: &str) -> Option<&ProgramField> {
+        self.fields.iter().find(|f| f.name == name && f.descriptor == descriptor)
+    }
+}
+
+/// All classes of a project, the first definition of a class on the classpath wins.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Program {
+    pub classes: BTreeMap<String, ProgramClass>,
+    pub hierarchy: ClassHierarchy,
+}
+
+impl Program {
+    pub(crate) fn new(classes: Vec<ProgramClass>) -> Self {
+        let mut by_name = BTreeMap::new();
+        for class in classes {
+            by_name.entry(class.name.clone()).or_insert(class);
+        }
+        let nodes = by_name.values().map(type_node).collect();
+        Program {
+            classes: by_name,
+            hierarchy: ClassHierarchy::new(nodes),
+        }
+    }
+
+    /// 逐个类读取项目，只保留程序模型需要的部分；无法解析的类会被跳过
+    pub(crate) fn from_project(classes: &ProjectClasses) -> Self {
+        let mut program_classes = Vec::new();
+        classes.for_each_class(|class| program_classes.extend(program_class(class)));
+        Program::new(program_classes)
+    }
+
+    /// 同一个类名只保留第一个定义
+    pub fn from_classes<'a>(classes: impl IntoIterator<Item = ClassModel<'a>>) -> Self {
+        Program::new(classes.into_iter().filter_map(program_class).collect())
+    }
+
+    /// 沿父类链查找方法的声明位置（JVM 方法解析，不含接口默认方法）
+    pub fn resolve_method(&self, owner: &str, name: &str, descriptor: &str) -> Option<&ProgramClass> {
+        std::iter::once(owner.to_string())
+            .chain(self.hierarchy.superclass_chain(owner))
+            .filter_map(|class_name| self.classes.get(&class_name))
+            .find(|class| class.method(name, descriptor).is_some())
+            .or_else(|| {
+                self.hierarchy
+                    .supertypes(owner)
+                    .iter()
+                    .filter_map(|summary| self.classes.get(&summary.name))
+                    .find(|class| class.method(name, descriptor).is_some())
+            })
+    }
+
+    /// 字段可以声明在父类或接口中
+    pub fn resolve_field(&self, owner: &str, name: &str, descriptor: &str) -> Option<&ProgramClass> {
+        std::iter::once(owner.to_string())
+            .chain(self.hierarchy.supertypes(owner).into_iter().map(|summary| summary.name))
+            .filter_map(|class_name| self.classes.get(&class_name))
+            .find(|class| class.field(name, descriptor).is_some())
+    }
+
+    /// Whether any method body was decoded
+    pub fn has_code(&self) -> bool {
+        self.classes.values().any(|class| class.methods.iter().any(|m| m.has_code))
+    }
+}
+
+fn type_node(class: &ProgramClass) -> TypeNode {
+    TypeNode {
+        name: class.name.clone(),
+        super_name: class.super_name.clone(),
+        interfaces: class.interfaces.clone(),
+        access_flags: class.access_flags,
+        methods: class
+            .methods
+            .iter()
+            .map(|method| MethodInfo {
+                name: method.name.clone(),
+                descriptor: method.descriptor.clone(),
+                access_flags: method.access_flags,
+            })
+            .collect(),
+    }
+}
+
+pub(crate) fn program_class(class: ClassModel) -> Option<ProgramClass> {
+    model_class(class, true)
+}
+
+/// 只有类型和成员声明、不解码方法体的类，用于解析成员的声明位置
+pub(crate) fn class_declaration(class: ClassModel) -> Option<ProgramClass> {
+    model_class(class, false)
+}
+
+fn model_class(class: ClassModel, with_references: bool) -> Option<ProgramClass> {
+    Some(ProgramClass {
+        name: class.name()?,
+        super_name: class.super_name(),
+        interfaces: class.interfaces(),
+        access_flags: class.access_flags(),
+        fields: class
+            .fields()
+            .iter()
+            .map(|field| ProgramField {
+                name: field.name().to_string(),
+                descriptor: field.descriptor().to_string(),
+                access_flags: field.access_flags(),
+                constant: field.has_constant(),
+            })
+            .collect(),
+        methods: class
+            .methods()
+            .iter()
+            .map(|method| ProgramMethod {
+                name: method.name().to_string(),
+                descriptor: method.descriptor(),
+                access_flags: method.access_flags(),
+                has_code: method.has_code(),
+                references: if with_references { method.references() } else { Vec::new() },
+            })
+            .collect(),
+    })
+}
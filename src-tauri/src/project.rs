@@ -1,12 +1,17 @@
 use uuid;
 use std::collections::HashMap;
 use std::fs::File;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use once_cell::sync::Lazy;
 
 use crate::hex::HexProjectData;
 use crate::java::JavaProjectData;
-use crate::android::AndroidProjectData;
+use crate::android::{AndroidProjectData, DexFile};
+use crate::android_analyzer::dex_index::DexIndex;
+use crate::java_analyzer::classfile::ClassFileReader;
+use crate::java_analyzer::classpath::Classpath;
+use crate::model::ClassModel;
+use crate::rename::RenameMap;
 
 // 项目文件需要支持类型，比如Hex文件，Java反编译项目，Android反编译项目
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,18 +27,32 @@ pub enum ProjectData {
     Android(AndroidProjectData),
 }
 
+impl ProjectData {
+    /// 每次类发生变化（classpath 调整、汇编修改）时递增，用来判断锁外构建的索引是否过期
+    pub fn revision(&self) -> u64 {
+        match self {
+            ProjectData::Hex(_) => 0,
+            ProjectData::Java(java_data) => java_data.revision,
+            ProjectData::Android(android_data) => android_data.revision,
+        }
+    }
+}
+
 pub struct Project {
     pub project_type: ProjectType,
     pub id: String,
     pub name: String,
     pub path: String,
     pub data: ProjectData,
+    /// 用户重命名和导入的映射，所有视图显示前都经过它
+    pub renames: RenameMap,
 }
 
 pub static PROJECTS: Lazy<Mutex<HashMap<String, Project>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
 impl Project {
-    pub fn new(project_type: ProjectType, name: String, path: String) -> String {
+    /// 打不开的 jar 或目录返回错误，不创建空项目
+    pub fn new(project_type: ProjectType, name: String, path: String) -> Result<String, String> {
         let data = match project_type {
             ProjectType::Hex => {
                 let file = File::open(&path).ok();
@@ -42,36 +61,37 @@ impl Project {
                 })
             },
             ProjectType::Java => {
-                ProjectData::Java(JavaProjectData::new(path.clone()))
+                ProjectData::Java(JavaProjectData::new(path.clone())?)
             },
             ProjectType::Android => {
-                ProjectData::Android(AndroidProjectData {
-                    apk_path: path.clone(),
-                    dex_files: Vec::new(),
-                    manifest: None,
-                    resource_table: None,
-                })
+                ProjectData::Android(AndroidProjectData::with_apk_path(path.clone()))
             }
         };
 
+        let renames = match project_type {
+            ProjectType::Hex => RenameMap::default(),
+            _ => RenameMap::load_default(&path),
+        };
         let project = Project {
             project_type,
             id: uuid::Uuid::new_v4().to_string(),
             name,
             path,
             data,
+            renames,
         };
         let id = project.id.clone();
         let mut projects = PROJECTS.lock().unwrap();
         projects.insert(id.clone(), project);
-        id
+        Ok(id)
     }
 
     /// 给定一个文件或目录路径，自动判断类型并创建 Project
-    pub fn create_project_from_path(path: &str) -> String {
+    pub fn create_project_from_path(path: &str) -> Result<String, String> {
         let project_type = if path.ends_with(".apk") {
             ProjectType::Android
-        } else if path.ends_with(".jar") || path.ends_with(".class") {
+        } else if path.ends_with(".jar") || path.ends_with(".class") || std::path::Path::new(path).is_dir() {
+            // 目录按解压后的 class 目录处理
             ProjectType::Java
         } else {
             ProjectType::Hex // 默认类型为 Hex
@@ -112,10 +132,102 @@ impl Project {
 
 }
 
+/// The classes of a project, taken out under the project lock. Only paths, indexes and shared
+/// decoded files are copied; classes are read and parsed outside the lock.
+#[derive(Clone)]
+pub(crate) enum ProjectClasses {
+    Classpath(Classpath),
+    /// 已解码（含汇编修改）的 DEX
+    Decoded(Arc<Vec<DexFile>>),
+    /// 未解码的 DEX，逐个类解码
+    Indexed(DexIndex),
+}
+
+impl ProjectClasses {
+    /// 在项目锁内调用；Android 项目第一次使用时打开 APK 建立索引
+    pub(crate) fn of(data: &mut ProjectData) -> Result<Self, String> {
+        match data {
+            ProjectData::Java(java_data) => Ok(ProjectClasses::Classpath(java_data.classpath.clone())),
+            ProjectData::Android(android_data) if !android_data.dex_files.is_empty() => {
+                Ok(ProjectClasses::Decoded(android_data.dex_files.clone()))
+            }
+            ProjectData::Android(android_data) => Ok(ProjectClasses::Indexed(android_data.ensure_indexed()?.clone())),
+            ProjectData::Hex(_) => Err("Not a Java or Android project".to_string()),
+        }
+    }
+
+    pub(crate) fn snapshot(project_id: &str) -> Result<Self, String> {
+        Project::with_project_mut(project_id, |project| ProjectClasses::of(&mut project.data))
+    }
+
+    /// Read and parse the classes one at a time and hand each to `visit`. Classes that cannot be
+    /// read or parsed are skipped and returned as (name, reason).
+    pub(crate) fn for_each_class(&self, mut visit: impl FnMut(ClassModel<'_>)) -> Vec<(String, String)> {
+        match self {
+            ProjectClasses::Classpath(classpath) => {
+                let mut failures = Vec::new();
+                for (file_name, bytes) in classpath.read_all_classes() {
+                    match ClassFileReader::new(&bytes).read() {
+                        Ok(class_file) => visit(ClassModel::Jvm(&class_file)),
+                        Err(e) => failures.push((file_name, format!("{:?}", e))),
+                    }
+                }
+                failures
+            }
+            ProjectClasses::Decoded(dex_files) => {
+                ClassModel::dex_classes(dex_files).for_each(visit);
+                Vec::new()
+            }
+            ProjectClasses::Indexed(index) => index.for_each_class(|dex_file, class_def| visit(ClassModel::Dex(dex_file, class_def))),
+        }
+    }
+
+    /// The string pools of every DEX file, including strings only used by annotations and debug info
+    pub(crate) fn dex_strings(&self) -> Vec<String> {
+        match self {
+            ProjectClasses::Classpath(_) => Vec::new(),
+            ProjectClasses::Decoded(dex_files) => dex_files.iter().flat_map(|dex_file| dex_file.strings.iter().cloned()).collect(),
+            ProjectClasses::Indexed(index) => index
+                .images()
+                .iter()
+                .filter_map(|image| image.strings().ok())
+                .flat_map(|strings| strings.iter().cloned())
+                .collect(),
+        }
+    }
+}
+
+/// 取出缓存在项目里的索引（类层次、交叉引用）。缺失时在锁外构建，构建期间项目的类没有变化才写回缓存
+pub(crate) fn cached_index<T>(
+    project_id: &str,
+    slot: fn(&mut ProjectData) -> Option<&mut Option<Arc<T>>>,
+    build: impl FnOnce(&ProjectClasses) -> T,
+) -> Result<Arc<T>, String> {
+    let input = Project::with_project_mut(project_id, |project| {
+        let cached = slot(&mut project.data).ok_or_else(|| "Not a Java or Android project".to_string())?;
+        if let Some(index) = cached {
+            return Ok(Err(index.clone()));
+        }
+        Ok(Ok((ProjectClasses::of(&mut project.data)?, project.data.revision())))
+    })?;
+    let (classes, revision) = match input {
+        Ok(input) => input,
+        Err(index) => return Ok(index),
+    };
+    let index = Arc::new(build(&classes));
+    Project::with_project_mut(project_id, |project| {
+        if project.data.revision() == revision {
+            if let Some(cached) = slot(&mut project.data) {
+                *cached = Some(index.clone());
+            }
+        }
+        Ok(index)
+    })
+}
+
 #[tauri::command]
-pub fn create_project(path: &str) -> String {
-    let project_id = Project::create_project_from_path(path);
-    project_id
+pub fn create_project(path: &str) -> Result<String, String> {
+    Project::create_project_from_path(path)
 }
 
 #[tauri::command]
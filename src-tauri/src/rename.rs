@@ -0,0 +1,893 @@
+// Rename layer over classes, fields and methods, with ProGuard / R8 mapping import and export
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::android::{ClassDef, DexFile, ProtoDescriptor, TypeDescriptor};
+use crate::hierarchy::{normalize_class_name, project_hierarchy, proto_to_descriptor};
+use crate::java_analyzer::attributes::Attribute;
+use crate::java_analyzer::classfile::{ClassFile, ClassFileReader};
+use crate::java_analyzer::constantpool::ConstantPoolEntry;
+use crate::project::{Project, ProjectData};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymbolKind {
+    Class,
+    Field,
+    Method,
+}
+
+/// One rename. Class names and descriptors are always the ones stored in the binary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rename {
+    pub kind: SymbolKind,
+    pub class_name: String,
+    /// Member name, absent for classes
+    pub name: Option<String>,
+    pub descriptor: Option<String>,
+    pub new_name: String,
+}
+
+type MemberKey = (String, String, String);
+
+/// The renames of a project, keyed by the names in the binary.
+#[derive(Debug, Clone, Default)]
+pub struct RenameMap {
+    classes: BTreeMap<String, String>,
+    fields: BTreeMap<MemberKey, String>,
+    methods: BTreeMap<MemberKey, String>,
+}
+
+/// 项目文件旁边默认的重命名存档
+pub fn default_rename_path(project_path: &str) -> String {
+    format!("{}.renames.json", project_path)
+}
+
+impl RenameMap {
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty() && self.fields.is_empty() && self.methods.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len() + self.fields.len() + self.methods.len()
+    }
+
+    pub fn entries(&self) -> Vec<Rename> {
+        let classes = self.classes.iter().map(|(class_name, new_name)| Rename {
+            kind: SymbolKind::Class,
+            class_name: class_name.clone(),
+            name: None,
+            descriptor: None,
+            new_name: new_name.clone(),
+        });
+        let member = |kind: SymbolKind| {
+            move |((class_name, name, descriptor), new_name): (&MemberKey, &String)| Rename {
+                kind,
+                class_name: class_name.clone(),
+                name: Some(name.clone()),
+                descriptor: Some(descriptor.clone()),
+                new_name: new_name.clone(),
+            }
+        };
+        classes
+            .chain(self.fields.iter().map(member(SymbolKind::Field)))
+            .chain(self.methods.iter().map(member(SymbolKind::Method)))
+            .collect()
+    }
+
+    /// 新名称与原名相同时删除该条重命名
+    pub fn insert(&mut self, rename: Rename) -> Result<(), String> {
+        validate_name(&rename)?;
+        let class_name = normalize_class_name(&rename.class_name);
+        match rename.kind {
+            SymbolKind::Class => {
+                let new_name = normalize_class_name(&rename.new_name);
+                if let Some((other, _)) = self.classes.iter().find(|(other, target)| **target == new_name && **other != class_name) {
+                    return Err(format!("{} is already renamed to {}", other, new_name));
+                }
+                if new_name == class_name {
+                    self.classes.remove(&class_name);
+                } else {
+                    self.classes.insert(class_name, new_name);
+                }
+            }
+            SymbolKind::Field | SymbolKind::Method => {
+                let (Some(name), Some(descriptor)) = (rename.name, rename.descriptor) else {
+                    return Err("Member renames need a name and a descriptor".to_string());
+                };
+                let members = if rename.kind == SymbolKind::Field { &mut self.fields } else { &mut self.methods };
+                if rename.new_name == name {
+                    members.remove(&(class_name, name, descriptor));
+                } else {
+                    members.insert((class_name, name, descriptor), rename.new_name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, rename: &Rename) -> bool {
+        let class_name = normalize_class_name(&rename.class_name);
+        match (rename.kind, &rename.name, &rename.descriptor) {
+            (SymbolKind::Class, _, _) => self.classes.remove(&class_name).is_some(),
+            (SymbolKind::Field, Some(name), Some(descriptor)) => self.fields.remove(&(class_name, name.clone(), descriptor.clone())).is_some(),
+            (SymbolKind::Method, Some(name), Some(descriptor)) => self.methods.remove(&(class_name, name.clone(), descriptor.clone())).is_some(),
+            _ => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = RenameMap::default();
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let entries: Vec<Rename> = serde_json::from_str(&content).map_err(|e| format!("Invalid rename file {}: {}", path, e))?;
+        let mut map = RenameMap::default();
+        for rename in entries {
+            map.insert(rename)?;
+        }
+        Ok(map)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.entries()).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// 打开项目时读取默认存档，不存在或无法解析时从空表开始
+    pub fn load_default(project_path: &str) -> Self {
+        let path = default_rename_path(project_path);
+        if Path::new(&path).is_file() {
+            RenameMap::load(&path).unwrap_or_default()
+        } else {
+            RenameMap::default()
+        }
+    }
+
+    /// 界面上显示的（可能已重命名的）类名 -> 二进制中的类名
+    pub fn original_class_name(&self, class_name: &str) -> String {
+        let class_name = normalize_class_name(class_name);
+        self.classes
+            .iter()
+            .find(|(_, new_name)| **new_name == class_name)
+            .map(|(original, _)| original.clone())
+            .unwrap_or(class_name)
+    }
+
+    pub fn original_descriptor(&self, descriptor: &str) -> String {
+        rename_types(descriptor, |name| {
+            self.classes.iter().find(|(_, new_name)| new_name.as_str() == name).map(|(original, _)| original.clone())
+        })
+    }
+
+    /// 把界面上的类名、成员名和描述符换算回二进制中的名字
+    fn original_member(&self, kind: SymbolKind, class_name: &str, name: &str, descriptor: &str) -> MemberKey {
+        let class_name = self.original_class_name(class_name);
+        let descriptor = self.original_descriptor(descriptor);
+        let members = if kind == SymbolKind::Field { &self.fields } else { &self.methods };
+        let name = members
+            .iter()
+            .find(|((owner, _, member_descriptor), new_name)| *owner == class_name && *member_descriptor == descriptor && new_name.as_str() == name)
+            .map(|((_, original, _), _)| original.clone())
+            .unwrap_or_else(|| name.to_string());
+        (class_name, name, descriptor)
+    }
+
+    /// 导入 ProGuard / R8 的 mapping.txt。映射方向是 原名 -> 混淆名，导入后把混淆名重命名回原名
+    pub fn import_mapping(&mut self, content: &str) -> Result<usize, String> {
+        // 第一遍：类映射，成员描述符中的类型需要换算成混淆后的名字
+        let mut obfuscated_classes = HashMap::new();
+        for line in content.lines().filter(|line| !line.starts_with([' ', '\t']) && !line.trim_start().starts_with('#')) {
+            let Some((original, obfuscated)) = line.trim().trim_end_matches(':').split_once(" -> ") else {
+                continue;
+            };
+            obfuscated_classes.insert(original.trim().replace('.', "/"), obfuscated.trim().replace('.', "/"));
+        }
+        let to_obfuscated = |name: &str| obfuscated_classes.get(name).cloned();
+
+        let mut imported = RenameMap::default();
+        let mut current: Option<String> = None;
+        for (number, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let parse_error = || format!("Invalid mapping at line {}: {}", number + 1, trimmed);
+            if !line.starts_with([' ', '\t']) {
+                let (original, obfuscated) = trimmed.trim_end_matches(':').split_once(" -> ").ok_or_else(parse_error)?;
+                let (original, obfuscated) = (original.trim().replace('.', "/"), obfuscated.trim().replace('.', "/"));
+                if original != obfuscated {
+                    imported.classes.insert(obfuscated.clone(), original);
+                }
+                current = Some(obfuscated);
+                continue;
+            }
+            let owner = current.as_ref().ok_or_else(parse_error)?;
+            let (member, obfuscated) = trimmed.split_once(" -> ").ok_or_else(parse_error)?;
+            // 去掉行号前缀 `12:15:`
+            let member = member.trim_start_matches(|c: char| c.is_ascii_digit() || c == ':');
+            let (member_type, rest) = member.split_once(' ').ok_or_else(parse_error)?;
+            let obfuscated = obfuscated.trim().to_string();
+            match rest.split_once('(') {
+                Some((name, rest)) => {
+                    // 带所属类的名字是 R8 记录的内联来源，不是本类的方法
+                    if name.contains('.') {
+                        continue;
+                    }
+                    let arguments = rest.split(')').next().unwrap_or_default();
+                    let parameters: String = arguments
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|argument| !argument.is_empty())
+                        .map(|argument| java_type_to_descriptor(argument, to_obfuscated))
+                        .collect();
+                    let descriptor = format!("({}){}", parameters, java_type_to_descriptor(member_type, to_obfuscated));
+                    if name != obfuscated {
+                        imported.methods.insert((owner.clone(), obfuscated, descriptor), name.to_string());
+                    }
+                }
+                None => {
+                    let name = rest.trim();
+                    let descriptor = java_type_to_descriptor(member_type, to_obfuscated);
+                    if name != obfuscated {
+                        imported.fields.insert((owner.clone(), obfuscated, descriptor), name.to_string());
+                    }
+                }
+            }
+        }
+        let count = imported.len();
+        self.classes.extend(imported.classes);
+        self.fields.extend(imported.fields);
+        self.methods.extend(imported.methods);
+        Ok(count)
+    }
+
+    /// 导出为 mapping.txt：重命名后的名字作为原名，二进制中的名字作为混淆名
+    pub fn export_mapping(&self) -> String {
+        let mut owners: Vec<&String> = self
+            .classes
+            .keys()
+            .chain(self.fields.keys().map(|(owner, _, _)| owner))
+            .chain(self.methods.keys().map(|(owner, _, _)| owner))
+            .collect();
+        owners.sort();
+        owners.dedup();
+
+        let renamed = |name: &str| self.classes.get(name).cloned();
+        let java_type = |descriptor: &str| descriptor_to_java_type(&rename_types(descriptor, renamed));
+        let mut output = String::new();
+        for owner in owners {
+            let new_name = self.classes.get(owner).unwrap_or(owner);
+            output.push_str(&format!("{} -> {}:\n", new_name.replace('/', "."), owner.replace('/', ".")));
+            for ((_, name, descriptor), new_name) in self.fields.range((owner.clone(), String::new(), String::new())..).take_while(|((o, _, _), _)| o == owner) {
+                output.push_str(&format!("    {} {} -> {}\n", java_type(descriptor), new_name, name));
+            }
+            for ((_, name, descriptor), new_name) in self.methods.range((owner.clone(), String::new(), String::new())..).take_while(|((o, _, _), _)| o == owner) {
+                let (parameters, return_type) = split_method_descriptor(descriptor);
+                let parameters: Vec<String> = parameters.iter().map(|p| java_type(p)).collect();
+                output.push_str(&format!("    {} {}({}) -> {}\n", java_type(&return_type), new_name, parameters.join(","), name));
+            }
+        }
+        output
+    }
+}
+
+fn validate_name(rename: &Rename) -> Result<(), String> {
+    let new_name = rename.new_name.trim();
+    let invalid = |c: char| c.is_whitespace() || matches!(c, ';' | '[' | '(' | ')' | '<' | '>' | ':');
+    if new_name.is_empty() || new_name.contains(invalid) {
+        return Err(format!("Invalid name: {:?}", rename.new_name));
+    }
+    if rename.kind != SymbolKind::Class && new_name.contains(['.', '/']) {
+        return Err(format!("Member names cannot contain package separators: {}", new_name));
+    }
+    if rename.kind == SymbolKind::Method && matches!(rename.name.as_deref(), Some("<init>") | Some("<clinit>")) {
+        return Err("Constructors and static initializers cannot be renamed".to_string());
+    }
+    Ok(())
+}
+
+/// 替换描述符或泛型签名中出现的类名，`rename` 返回 None 表示保持原名
+fn rename_types<F>(text: &str, rename: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut previous: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        output.push(c);
+        // `TLFoo;` 这样的类型变量里的 L 不是类名的开始
+        let starts_class = c == 'L' && previous.is_none_or(|p| "(;)<>[:^+-*".contains(p));
+        previous = Some(c);
+        if !starts_class {
+            continue;
+        }
+        let end = rest.find([';', '<', '.']).unwrap_or(rest.len());
+        let name = &rest[..end];
+        match rename(name) {
+            Some(new_name) => output.push_str(&new_name),
+            None => output.push_str(name),
+        }
+        rest = &rest[end..];
+        previous = name.chars().last().or(previous);
+    }
+    output
+}
+
+/// `java.lang.String[]` -> `[Ljava/lang/String;`，类名经 `class_name` 换算
+fn java_type_to_descriptor<F>(java_type: &str, class_name: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let dimensions = java_type.matches("[]").count();
+    let base = java_type.trim_end_matches("[]").trim();
+    let element = match base {
+        "boolean" => "Z".to_string(),
+        "byte" => "B".to_string(),
+        "char" => "C".to_string(),
+        "short" => "S".to_string(),
+        "int" => "I".to_string(),
+        "long" => "J".to_string(),
+        "float" => "F".to_string(),
+        "double" => "D".to_string(),
+        "void" => "V".to_string(),
+        _ => {
+            let name = base.replace('.', "/");
+            format!("L{};", class_name(&name).unwrap_or(name))
+        }
+    };
+    format!("{}{}", "[".repeat(dimensions), element)
+}
+
+fn descriptor_to_java_type(descriptor: &str) -> String {
+    let element = descriptor.trim_start_matches('[');
+    let dimensions = descriptor.len() - element.len();
+    let base = match element {
+        "Z" => "boolean".to_string(),
+        "B" => "byte".to_string(),
+        "C" => "char".to_string(),
+        "S" => "short".to_string(),
+        "I" => "int".to_string(),
+        "J" => "long".to_string(),
+        "F" => "float".to_string(),
+        "D" => "double".to_string(),
+        "V" => "void".to_string(),
+        _ => element.trim_start_matches('L').trim_end_matches(';').replace('/', "."),
+    };
+    format!("{}{}", base, "[]".repeat(dimensions))
+}
+
+/// `(I[Ljava/lang/String;)V` -> (["I", "[Ljava/lang/String;"], "V")
+fn split_method_descriptor(descriptor: &str) -> (Vec<String>, String) {
+    let (parameters, return_type) = descriptor.trim_start_matches('(').split_once(')').unwrap_or(("", descriptor));
+    let mut result = Vec::new();
+    let mut start = 0;
+    let bytes = parameters.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'[' => {
+                index += 1;
+                continue;
+            }
+            b'L' => index += parameters[index..].find(';').map_or(bytes.len() - index, |end| end + 1),
+            _ => index += 1,
+        }
+        result.push(parameters[start..index].to_string());
+        start = index;
+    }
+    (result, return_type.to_string())
+}
+
+/// 视图使用的重命名器：重命名表加上解析继承成员所需的直接父类型
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Renamer {
+    pub map: RenameMap,
+    parents: HashMap<String, Vec<String>>,
+}
+
+/// 取出项目的重命名表；存在成员重命名时还需要类层次，以便通过子类引用的成员也能找到
+pub(crate) fn project_renamer(project_id: &str) -> Result<Renamer, String> {
+    let (map, parents) = Project::with_project_mut(project_id, |project| {
+        let map = project.renames.clone();
+        if map.fields.is_empty() && map.methods.is_empty() {
+            return Ok((map, Some(HashMap::new())));
+        }
+        match &mut project.data {
+            // 只需要父类型，不解码整个 DEX
+            ProjectData::Android(android_data) => Ok((map, Some(android_data.supertypes()?))),
+            ProjectData::Java(_) => Ok((map, None)),
+            _ => Ok((map, Some(HashMap::new()))),
+        }
+    })?;
+    let parents = match parents {
+        Some(parents) => parents,
+        // class 文件在项目锁外解析
+        None => project_hierarchy(project_id)?
+            .types
+            .values()
+            .map(|node| (node.name.clone(), node.super_name.iter().chain(&node.interfaces).cloned().collect()))
+            .collect(),
+    };
+    Ok(Renamer { map, parents })
+}
+
+impl Renamer {
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn class_name(&self, class_name: &str) -> String {
+        self.map.classes.get(class_name).cloned().unwrap_or_else(|| class_name.to_string())
+    }
+
+    pub fn descriptor(&self, descriptor: &str) -> String {
+        if self.map.classes.is_empty() {
+            return descriptor.to_string();
+        }
+        rename_types(descriptor, |name| self.map.classes.get(name).cloned())
+    }
+
+    /// 在所属类及其父类型中查找成员的重命名
+    fn member(&self, members: &BTreeMap<MemberKey, String>, owner: &str, name: &str, descriptor: &str) -> Option<String> {
+        if members.is_empty() {
+            return None;
+        }
+        let mut queue = VecDeque::from([owner.to_string()]);
+        let mut visited = HashSet::new();
+        while let Some(current) = queue.pop_front() {
+            if let Some(new_name) = members.get(&(current.clone(), name.to_string(), descriptor.to_string())) {
+                return Some(new_name.clone());
+            }
+            for parent in self.parents.get(&current).into_iter().flatten() {
+                if visited.insert(parent.clone()) {
+                    queue.push_back(parent.clone());
+                }
+            }
+        }
+        None
+    }
+
+    pub fn field_name(&self, owner: &str, name: &str, descriptor: &str) -> String {
+        self.member(&self.map.fields, owner, name, descriptor).unwrap_or_else(|| name.to_string())
+    }
+
+    pub fn method_name(&self, owner: &str, name: &str, descriptor: &str) -> String {
+        self.member(&self.map.methods, owner, name, descriptor).unwrap_or_else(|| name.to_string())
+    }
+
+    /// 原地改写 class 文件。Utf8 常量可能被多处共用，改名时总是追加新的常量
+    pub fn class_file(&self, class_file: &mut ClassFile) {
+        if self.is_empty() {
+            return;
+        }
+        let Some(owner) = class_file.class_name() else {
+            return;
+        };
+        let original = class_file.constant_pool.constant_pool.clone();
+        let utf8 = |index: u16| match original.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantPoolEntry::Utf8(value)) => Some(value.as_str()),
+            _ => None,
+        };
+        let mut pool = PoolWriter {
+            entries: &mut class_file.constant_pool.constant_pool,
+            added: HashMap::new(),
+        };
+
+        for (position, entry) in original.iter().enumerate() {
+            let renamed = match entry {
+                ConstantPoolEntry::ClassRef(name_index) => {
+                    let Some(name) = utf8(*name_index) else { continue };
+                    let new_name = if name.starts_with('[') { self.descriptor(name) } else { self.class_name(name) };
+                    (new_name != name).then(|| ConstantPoolEntry::ClassRef(pool.utf8(&new_name)))
+                }
+                ConstantPoolEntry::FieldRef(class_index, nat_index)
+                | ConstantPoolEntry::MethodRef(class_index, nat_index)
+                | ConstantPoolEntry::InterfaceMethodRef(class_index, nat_index) => {
+                    let member_owner = match original.get((*class_index as usize).wrapping_sub(1)) {
+                        Some(ConstantPoolEntry::ClassRef(name_index)) => utf8(*name_index),
+                        _ => None,
+                    };
+                    let Some((owner, (name, descriptor))) = member_owner.zip(name_and_type(&original, *nat_index)) else {
+                        continue;
+                    };
+                    let new_name = match entry {
+                        ConstantPoolEntry::FieldRef(..) => self.field_name(owner, name, descriptor),
+                        _ => self.method_name(owner, name, descriptor),
+                    };
+                    let new_descriptor = self.descriptor(descriptor);
+                    if new_name == name && new_descriptor == descriptor {
+                        continue;
+                    }
+                    let nat = pool.name_and_type(&new_name, &new_descriptor);
+                    Some(match entry {
+                        ConstantPoolEntry::FieldRef(..) => ConstantPoolEntry::FieldRef(*class_index, nat),
+                        ConstantPoolEntry::MethodRef(..) => ConstantPoolEntry::MethodRef(*class_index, nat),
+                        _ => ConstantPoolEntry::InterfaceMethodRef(*class_index, nat),
+                    })
+                }
+                ConstantPoolEntry::InvokeDynamicRef(bootstrap_index, nat_index) | ConstantPoolEntry::Dynamic(bootstrap_index, nat_index) => {
+                    let Some((name, descriptor)) = name_and_type(&original, *nat_index) else { continue };
+                    let new_descriptor = self.descriptor(descriptor);
+                    if new_descriptor == descriptor {
+                        continue;
+                    }
+                    let nat = pool.name_and_type(name, &new_descriptor);
+                    Some(match entry {
+                        ConstantPoolEntry::InvokeDynamicRef(..) => ConstantPoolEntry::InvokeDynamicRef(*bootstrap_index, nat),
+                        _ => ConstantPoolEntry::Dynamic(*bootstrap_index, nat),
+                    })
+                }
+                ConstantPoolEntry::MethodTypeRef(descriptor_index) => {
+                    let Some(descriptor) = utf8(*descriptor_index) else { continue };
+                    let new_descriptor = self.descriptor(descriptor);
+                    (new_descriptor != descriptor).then(|| ConstantPoolEntry::MethodTypeRef(pool.utf8(&new_descriptor)))
+                }
+                _ => None,
+            };
+            if let Some(renamed) = renamed {
+                pool.entries[position] = renamed;
+            }
+        }
+
+        for field in &mut class_file.fields {
+            field.name = self.field_name(&owner, &field.name, &field.descriptor);
+            field.descriptor = self.descriptor(&field.descriptor);
+            self.attributes(&mut field.attributes, &utf8, &mut pool);
+        }
+        for method in &mut class_file.methods {
+            method.name = self.method_name(&owner, &method.name, &method.descriptor);
+            method.descriptor = self.descriptor(&method.descriptor);
+            self.attributes(&mut method.attributes, &utf8, &mut pool);
+        }
+        self.attributes(&mut class_file.attributes, &utf8, &mut pool);
+    }
+
+    /// 泛型签名和局部变量表中的类型
+    fn attributes<'p>(&self, attributes: &mut [Attribute], utf8: &dyn Fn(u16) -> Option<&'p str>, pool: &mut PoolWriter) {
+        for attribute in attributes {
+            match attribute {
+                Attribute::Signature(signature) => {
+                    if let Some(text) = utf8(signature.signature_index) {
+                        let renamed = self.descriptor(text);
+                        if renamed != text {
+                            signature.signature_index = pool.utf8(&renamed);
+                        }
+                    }
+                }
+                Attribute::Code(code) => {
+                    for attribute in &mut code.attributes {
+                        if let Attribute::LocalVariableTable(table) = attribute {
+                            for entry in &mut table.local_variable_table {
+                                if let Some(descriptor) = utf8(entry.descriptor_index) {
+                                    let renamed = self.descriptor(descriptor);
+                                    if renamed != descriptor {
+                                        entry.descriptor_index = pool.utf8(&renamed);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn type_descriptor(&self, descriptor: &mut TypeDescriptor) {
+        descriptor.descriptor = self.descriptor(&descriptor.descriptor);
+    }
+
+    fn proto(&self, proto: &mut ProtoDescriptor) {
+        self.type_descriptor(&mut proto.return_type);
+        proto.parameters.iter_mut().for_each(|parameter| self.type_descriptor(parameter));
+    }
+
+    /// 逐个解码类时使用的 id 表：没有重命名时直接借用，否则复制一份不含类的 id 表再改写
+    pub fn dex_ids<'d>(&self, dex_file: &'d DexFile) -> Cow<'d, DexFile> {
+        if self.is_empty() {
+            return Cow::Borrowed(dex_file);
+        }
+        let mut ids = dex_file.ids_only();
+        self.rename_dex_ids(&mut ids);
+        Cow::Owned(ids)
+    }
+
+    fn rename_dex_ids(&self, dex_file: &mut DexFile) {
+        let internal_name = |descriptor: &TypeDescriptor| crate::hierarchy::descriptor_to_internal_name(&descriptor.descriptor);
+        for field in &mut dex_file.fields {
+            field.name = self.field_name(&internal_name(&field.class_type), &field.name, &field.field_type.descriptor);
+            self.type_descriptor(&mut field.class_type);
+            self.type_descriptor(&mut field.field_type);
+        }
+        for method in &mut dex_file.methods {
+            method.name = self.method_name(&internal_name(&method.class_type), &method.name, &proto_to_descriptor(&method.proto));
+            self.type_descriptor(&mut method.class_type);
+            self.proto(&mut method.proto);
+        }
+        dex_file.types.iter_mut().for_each(|descriptor| self.type_descriptor(descriptor));
+        dex_file.protos.iter_mut().for_each(|proto| self.proto(proto));
+    }
+
+    /// 原地改写一个类定义的名字和成员
+    pub fn dex_class(&self, class_def: &mut ClassDef) {
+        if self.is_empty() {
+            return;
+        }
+        let owner = crate::hierarchy::descriptor_to_internal_name(&class_def.class_type.descriptor);
+        for field in class_def.static_fields.iter_mut().chain(class_def.instance_fields.iter_mut()) {
+            field.name = self.field_name(&owner, &field.name, &field.field_type.descriptor);
+            self.type_descriptor(&mut field.field_type);
+        }
+        for method in class_def.direct_methods.iter_mut().chain(class_def.virtual_methods.iter_mut()) {
+            method.name = self.method_name(&owner, &method.name, &proto_to_descriptor(&method.proto));
+            self.proto(&mut method.proto);
+        }
+        self.type_descriptor(&mut class_def.class_type);
+        if let Some(super_type) = &mut class_def.super_type {
+            self.type_descriptor(super_type);
+        }
+        class_def.interfaces.iter_mut().for_each(|interface| self.type_descriptor(interface));
+    }
+}
+
+/// 解析 class 文件并套用重命名
+pub(crate) fn parse_renamed(bytes: &[u8], renamer: &Renamer) -> Result<ClassFile, String> {
+    let mut class_file = ClassFileReader::new(bytes)
+        .read()
+        .map_err(|e| format!("Failed to parse class file: {:?}", e))?;
+    renamer.class_file(&mut class_file);
+    Ok(class_file)
+}
+
+fn name_and_type(entries: &[ConstantPoolEntry], index: u16) -> Option<(&str, &str)> {
+    let utf8 = |index: u16| match entries.get((index as usize).wrapping_sub(1)) {
+        Some(ConstantPoolEntry::Utf8(value)) => Some(value.as_str()),
+        _ => None,
+    };
+    match entries.get((index as usize).wrapping_sub(1))? {
+        ConstantPoolEntry::NameAndTypeRef(name_index, descriptor_index) => Some((utf8(*name_index)?, utf8(*descriptor_index)?)),
+        _ => None,
+    }
+}
+
+/// 向常量池末尾追加条目，相同内容只追加一次
+struct PoolWriter<'a> {
+    entries: &'a mut Vec<ConstantPoolEntry>,
+    added: HashMap<(u8, String), u16>,
+}
+
+impl PoolWriter<'_> {
+    fn push(&mut self, key: (u8, String), entry: ConstantPoolEntry) -> u16 {
+        if let Some(index) = self.added.get(&key) {
+            return *index;
+        }
+        self.entries.push(entry);
+        let index = self.entries.len() as u16;
+        self.added.insert(key, index);
+        index
+    }
+
+    fn utf8(&mut self, value: &str) -> u16 {
+        self.push((1, value.to_string()), ConstantPoolEntry::Utf8(value.to_string()))
+    }
+
+    fn name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let name_index = self.utf8(name);
+        let descriptor_index = self.utf8(descriptor);
+        self.push((12, format!("{}:{}", name, descriptor)), ConstantPoolEntry::NameAndTypeRef(name_index, descriptor_index))
+    }
+}
+
+fn with_renames<T, F>(project_id: &str, f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut RenameMap) -> Result<T, String>,
+{
+    Project::with_project_mut(project_id, |project| match project.data {
+        ProjectData::Java(_) | ProjectData::Android(_) => f(&mut project.renames),
+        _ => Err("Not a Java or Android project".to_string()),
+    })
+}
+
+/// Every rename of the project.
+#[tauri::command]
+pub fn rename_list(project_id: String) -> Result<Vec<Rename>, String> {
+    with_renames(&project_id, |renames| Ok(renames.entries()))
+}
+
+/// Renames a class. `class_name` may be the current display name.
+#[tauri::command]
+pub fn rename_class(project_id: String, class_name: String, new_name: String) -> Result<Rename, String> {
+    with_renames(&project_id, |renames| {
+        let rename = Rename {
+            kind: SymbolKind::Class,
+            class_name: renames.original_class_name(&class_name),
+            name: None,
+            descriptor: None,
+            new_name: normalize_class_name(&new_name),
+        };
+        renames.insert(rename.clone())?;
+        Ok(rename)
+    })
+}
+
+fn rename_member(project_id: &str, kind: SymbolKind, class_name: &str, name: &str, descriptor: &str, new_name: String) -> Result<Rename, String> {
+    with_renames(project_id, |renames| {
+        let (class_name, name, descriptor) = renames.original_member(kind, class_name, name, descriptor);
+        let rename = Rename {
+            kind,
+            class_name,
+            name: Some(name),
+            descriptor: Some(descriptor),
+            new_name: new_name.trim().to_string(),
+        };
+        renames.insert(rename.clone())?;
+        Ok(rename)
+    })
+}
+
+/// Renames a field; names and descriptor may use current display names.
+#[tauri::command]
+pub fn rename_field(project_id: String, class_name: String, field_name: String, descriptor: String, new_name: String) -> Result<Rename, String> {
+    rename_member(&project_id, SymbolKind::Field, &class_name, &field_name, &descriptor, new_name)
+}
+
+/// Renames a method; names and descriptor may use current display names.
+#[tauri::command]
+pub fn rename_method(project_id: String, class_name: String, method_name: String, descriptor: String, new_name: String) -> Result<Rename, String> {
+    rename_member(&project_id, SymbolKind::Method, &class_name, &method_name, &descriptor, new_name)
+}
+
+/// Drops one rename, returning whether it existed.
+#[tauri::command]
+pub fn rename_remove(project_id: String, rename: Rename) -> Result<bool, String> {
+    with_renames(&project_id, |renames| Ok(renames.remove(&rename)))
+}
+
+#[tauri::command]
+pub fn rename_clear(project_id: String) -> Result<(), String> {
+    with_renames(&project_id, |renames| {
+        renames.clear();
+        Ok(())
+    })
+}
+
+/// Imports a ProGuard / R8 `mapping.txt`, restoring the original names. Returns the number of renames read.
+#[tauri::command]
+pub fn rename_import_mapping(project_id: String, path: String) -> Result<usize, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    with_renames(&project_id, |renames| renames.import_mapping(&content))
+}
+
+/// Writes the renames as a ProGuard mapping file, with the new names as the original side.
+#[tauri::command]
+pub fn rename_export_mapping(project_id: String, path: String) -> Result<usize, String> {
+    let (mapping, count) = with_renames(&project_id, |renames| Ok((renames.export_mapping(), renames.len())))?;
+    fs::write(&path, mapping).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(count)
+}
+
+/// Saves the renames as JSON, by default next to the project file where they are picked up on the next open.
+#[tauri::command]
+pub fn rename_save(project_id: String, path: Option<String>) -> Result<String, String> {
+    Project::with_project(&project_id, |project| {
+        let path = path.unwrap_or_else(|| default_rename_path(&project.path));
+        project.renames.save(&path)?;
+        Ok(path)
+    })
+}
+
+/// Replaces the renames with a saved JSON file, by default the one next to the project file.
+#[tauri::command]
+pub fn rename_load(project_id: String, path: Option<String>) -> Result<usize, String> {
+    Project::with_project_mut(&project_id, |project| {
+        let path = path.unwrap_or_else(|| default_rename_path(&project.path));
+        project.renames = RenameMap::load(&path)?;
+        Ok(project.renames.len())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPPING: &str = "\
+# compiler: R8
+com.example.Account -> a.a:
+    java.lang.String owner -> a
+    com.example.Account$Kind kind -> b
+    12:15:void deposit(long,com.example.Account$Kind[]) -> a
+    1:1:int com.example.Util.inlined(int):10:10 -> a
+    int balance() -> c
+com.example.Account$Kind -> a.b:
+com.example.Keep -> com.example.Keep:
+    void run() -> a
+";
+
+    fn member(kind: SymbolKind, class_name: &str, name: &str, descriptor: &str, new_name: &str) -> Rename {
+        Rename {
+            kind,
+            class_name: class_name.to_string(),
+            name: Some(name.to_string()),
+            descriptor: Some(descriptor.to_string()),
+            new_name: new_name.to_string(),
+        }
+    }
+
+    fn class(class_name: &str, new_name: &str) -> Rename {
+        Rename {
+            kind: SymbolKind::Class,
+            class_name: class_name.to_string(),
+            name: None,
+            descriptor: None,
+            new_name: new_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn imported_mappings_rename_obfuscated_names_back() {
+        let mut map = RenameMap::default();
+        assert_eq!(map.import_mapping(MAPPING), Ok(7));
+        assert_eq!(
+            map.entries(),
+            vec![
+                class("a/a", "com/example/Account"),
+                class("a/b", "com/example/Account$Kind"),
+                member(SymbolKind::Field, "a/a", "a", "Ljava/lang/String;", "owner"),
+                member(SymbolKind::Field, "a/a", "b", "La/b;", "kind"),
+                // 描述符中的类型使用混淆后的名字，内联来源被跳过
+                member(SymbolKind::Method, "a/a", "a", "(J[La/b;)V", "deposit"),
+                member(SymbolKind::Method, "a/a", "c", "()I", "balance"),
+                member(SymbolKind::Method, "com/example/Keep", "a", "()V", "run"),
+            ]
+        );
+
+        let renamer = Renamer { map, ..Renamer::default() };
+        assert_eq!(renamer.class_name("a/b"), "com/example/Account$Kind");
+        assert_eq!(renamer.descriptor("(J[La/b;)V"), "(J[Lcom/example/Account$Kind;)V");
+        assert_eq!(renamer.method_name("a/a", "a", "(J[La/b;)V"), "deposit");
+        assert_eq!(renamer.field_name("a/a", "b", "La/b;"), "kind");
+        assert_eq!(renamer.map.original_descriptor("(Lcom/example/Account;)V"), "(La/a;)V");
+    }
+
+    #[test]
+    fn invalid_mapping_lines_are_reported() {
+        let mut map = RenameMap::default();
+        assert!(map.import_mapping("    int a -> b\n").is_err());
+        assert!(map.import_mapping("a.A -> b.B:\n    broken\n").unwrap_err().contains("line 2"));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn exported_mappings_use_renamed_descriptors_and_round_trip() {
+        let mut map = RenameMap::default();
+        map.insert(class("a/a", "com/example/Account")).unwrap();
+        map.insert(class("a/b", "com.example.Kind")).unwrap();
+        map.insert(member(SymbolKind::Field, "a/a", "b", "[La/b;", "kinds")).unwrap();
+        map.insert(member(SymbolKind::Method, "a/a", "a", "(JLa/b;Ljava/lang/String;)La/a;", "with")).unwrap();
+        map.insert(member(SymbolKind::Method, "x/Y", "a", "()V", "run")).unwrap();
+
+        let exported = map.export_mapping();
+        assert_eq!(
+            exported,
+            "\
+com.example.Account -> a.a:
+    com.example.Kind[] kinds -> b
+    com.example.Account with(long,com.example.Kind,java.lang.String) -> a
+com.example.Kind -> a.b:
+x.Y -> x.Y:
+    void run() -> a
+"
+        );
+
+        let mut imported = RenameMap::default();
+        assert_eq!(imported.import_mapping(&exported), Ok(map.len()));
+        assert_eq!(imported.entries(), map.entries());
+    }
+}
@@ -0,0 +1,603 @@
+// Software bill of materials (CycloneDX / SPDX) for Java and Android projects
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+
+use crate::android::AndroidManifest;
+use crate::java_analyzer::classpath::ClasspathEntry;
+use crate::java_analyzer::jar::JarEntry;
+use crate::libraries::{identify_libraries, metadata_components, ComponentSource};
+use crate::project::{Project, ProjectData};
+
+/// 嵌套归档最多展开的层数（例如 Spring Boot jar 中的 `BOOT-INF/lib/*.jar`）
+const MAX_NESTING_DEPTH: usize = 3;
+
+const NESTED_ARCHIVE_EXTENSIONS: [&str; 5] = [".jar", ".war", ".ear", ".aar", ".apk"];
+const NATIVE_LIBRARY_EXTENSIONS: [&str; 4] = [".so", ".dll", ".dylib", ".jnilib"];
+
+/// Output document format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    CycloneDx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComponentKind {
+    Application,
+    Library,
+    /// Native libraries and other files without Maven coordinates
+    File,
+}
+
+#[derive(Debug, Clone)]
+struct SbomComponent {
+    kind: ComponentKind,
+    group: Option<String>,
+    name: String,
+    version: Option<String>,
+    /// (算法, 十六进制摘要)，算法名称使用 CycloneDX 的写法
+    hashes: Vec<(&'static str, String)>,
+    /// 在项目中的位置，嵌套条目用 `!/` 连接
+    location: String,
+    properties: Vec<(String, String)>,
+}
+
+impl SbomComponent {
+    fn purl(&self) -> Option<String> {
+        if self.kind == ComponentKind::File {
+            return None;
+        }
+        let group = self.group.as_ref()?;
+        let mut purl = format!("pkg:maven/{}/{}", group, self.name);
+        if let Some(version) = &self.version {
+            purl.push_str(&format!("@{}", version));
+        }
+        Some(purl)
+    }
+}
+
+/// 在项目锁内取出的输入；读取归档和计算摘要都在锁外进行
+enum SbomInput {
+    Java { name: String, path: String, entries: Vec<EntrySource> },
+    Android { apk_path: String, manifest: Option<Box<AndroidManifest>> },
+}
+
+/// 一个 classpath 条目的位置
+enum EntrySource {
+    Archive(String),
+    Directory(PathBuf),
+    ClassFile(String),
+}
+
+impl EntrySource {
+    fn new(entry: &ClasspathEntry) -> Self {
+        match entry {
+            ClasspathEntry::Jar(_) => EntrySource::Archive(entry.path()),
+            ClasspathEntry::Directory(path) => EntrySource::Directory(path.clone()),
+            ClasspathEntry::ClassFile { .. } => EntrySource::ClassFile(entry.path()),
+        }
+    }
+}
+
+/// Builds the SBOM of a Java or Android project and returns the JSON document. When
+/// `database_path` is given, libraries recognised by class fingerprints are listed as well.
+#[tauri::command(async)]
+pub fn sbom_generate(project_id: String, format: SbomFormat, database_path: Option<String>) -> Result<String, String> {
+    let input = Project::with_project_mut(&project_id, |project| match &mut project.data {
+        ProjectData::Java(java_data) => Ok(SbomInput::Java {
+            name: project.name.clone(),
+            path: project.path.clone(),
+            entries: java_data.classpath.entries.iter().map(EntrySource::new).collect(),
+        }),
+        ProjectData::Android(android_data) => {
+            // 只需要清单，打开 APK 建立索引即可，不解码 DEX
+            android_data.ensure_indexed()?;
+            Ok(SbomInput::Android { apk_path: android_data.apk_path.clone(), manifest: android_data.manifest.clone().map(Box::new) })
+        }
+        _ => Err("Not a Java or Android project".to_string()),
+    })?;
+
+    let (root, mut components) = match input {
+        SbomInput::Java { name, path, entries } => {
+            let mut components = Vec::new();
+            let mut archives = Vec::new();
+            for entry in &entries {
+                archives.push(scan_classpath_entry(entry, &mut components)?);
+            }
+            // 只有一个 jar 时它本身就是被描述的软件
+            let root = if archives.len() == 1 {
+                let mut root = archives.remove(0);
+                root.kind = ComponentKind::Application;
+                root
+            } else {
+                SbomComponent {
+                    kind: ComponentKind::Application,
+                    group: None,
+                    name,
+                    version: None,
+                    hashes: Vec::new(),
+                    location: path,
+                    properties: Vec::new(),
+                }
+            };
+            components.splice(0..0, archives);
+            (root, components)
+        }
+        SbomInput::Android { apk_path, manifest } => {
+            let bytes = fs::read(&apk_path).map_err(|e| format!("Failed to read {}: {}", apk_path, e))?;
+            let mut components = Vec::new();
+            let mut root = scan_archive(ComponentKind::Application, &file_name(&apk_path), &apk_path, &bytes, 0, &mut components)?;
+            if let Some(manifest) = &manifest {
+                root.name = manifest.package_name.clone();
+                root.version = Some(manifest.version_name.clone()).filter(|v| !v.is_empty());
+                root.properties.push(("android:versionCode".to_string(), manifest.version_code.to_string()));
+            }
+            (root, components)
+        }
+    };
+
+    if let Some(database_path) = database_path {
+        let identified = identify_libraries(&project_id, Some(&database_path))?;
+        for library in identified.into_iter().filter(|l| l.source == ComponentSource::Fingerprint) {
+            let known = components
+                .iter()
+                .any(|c| c.group == library.group_id && c.name == library.artifact_id);
+            if known {
+                continue;
+            }
+            components.push(SbomComponent {
+                kind: ComponentKind::Library,
+                group: library.group_id,
+                name: library.artifact_id,
+                version: library.version,
+                hashes: Vec::new(),
+                location: root.location.clone(),
+                properties: vec![
+                    ("analyzer:identifiedBy".to_string(), "fingerprint".to_string()),
+                    ("analyzer:confidence".to_string(), format!("{:.2}", library.confidence)),
+                    ("analyzer:packages".to_string(), library.packages.join(",")),
+                ],
+            });
+        }
+    }
+
+    let document = match format {
+        SbomFormat::CycloneDx => cyclonedx_document(&root, &components),
+        SbomFormat::Spdx => spdx_document(&root, &components),
+    };
+    serde_json::to_string_pretty(&document).map_err(|e| e.to_string())
+}
+
+fn scan_classpath_entry(entry: &EntrySource, components: &mut Vec<SbomComponent>) -> Result<SbomComponent, String> {
+    match entry {
+        EntrySource::Archive(path) => {
+            let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            scan_archive(ComponentKind::Library, &file_name(path), path, &bytes, 0, components)
+        }
+        EntrySource::Directory(path) => {
+            let directory = ClasspathEntry::Directory(path.clone());
+            let path = directory.path();
+            let entries = directory.list_entries()?;
+            let mut component = SbomComponent {
+                kind: ComponentKind::Library,
+                group: None,
+                name: file_name(&path),
+                version: None,
+                hashes: Vec::new(),
+                location: path.clone(),
+                properties: Vec::new(),
+            };
+            scan_contents(&mut component, &entries, |name| directory.read_file(name), 0, components);
+            Ok(component)
+        }
+        EntrySource::ClassFile(path) => {
+            let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            Ok(SbomComponent {
+                kind: ComponentKind::File,
+                group: None,
+                name: file_name(path),
+                version: None,
+                hashes: hashes(&bytes),
+                location: path.clone(),
+                properties: Vec::new(),
+            })
+        }
+    }
+}
+
+/// 描述一个归档本身，嵌入的库、原生库和嵌套归档追加到 components
+fn scan_archive(
+    kind: ComponentKind,
+    name: &str,
+    location: &str,
+    bytes: &[u8],
+    depth: usize,
+    components: &mut Vec<SbomComponent>,
+) -> Result<SbomComponent, String> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Failed to open {}: {}", location, e))?;
+    let entries: Vec<JarEntry> = archive
+        .file_names()
+        .map(|name| JarEntry {
+            name: name.to_string(),
+            size: 0,
+            is_directory: name.ends_with('/'),
+            is_class_file: name.ends_with(".class"),
+        })
+        .collect();
+    let mut component = SbomComponent {
+        kind,
+        group: None,
+        name: name.to_string(),
+        version: None,
+        hashes: hashes(bytes),
+        location: location.to_string(),
+        properties: Vec::new(),
+    };
+    scan_contents(&mut component, &entries, |entry| read_zip_entry(&mut archive, entry), depth, components);
+    Ok(component)
+}
+
+fn scan_contents<R>(
+    component: &mut SbomComponent,
+    entries: &[JarEntry],
+    mut read: R,
+    depth: usize,
+    components: &mut Vec<SbomComponent>,
+) where
+    R: FnMut(&str) -> Result<Vec<u8>, String>,
+{
+    let manifest = read("META-INF/MANIFEST.MF")
+        .map(|bytes| parse_manifest(&String::from_utf8_lossy(&bytes)))
+        .unwrap_or_default();
+    for key in ["Implementation-Title", "Implementation-Version", "Implementation-Vendor", "Bundle-SymbolicName", "Bundle-Version"] {
+        if let Some(value) = manifest.get(key) {
+            component.properties.push((format!("manifest:{}", key), value.clone()));
+        }
+    }
+    component.version = manifest
+        .get("Implementation-Version")
+        .or_else(|| manifest.get("Bundle-Version"))
+        .cloned();
+
+    // 只有一份 Maven 元数据时它描述的就是库归档本身，多份说明其中 shade 了其他库；
+    // 应用（例如 APK）中的元数据总是来自打包进来的库
+    let mut embedded = metadata_components(&component.location, entries, &mut read);
+    if embedded.len() == 1 && component.kind == ComponentKind::Library {
+        let library = embedded.remove(0);
+        component.group = library.group_id;
+        component.name = library.artifact_id;
+        component.version = library.version.or(component.version.take());
+    }
+    for library in embedded {
+        components.push(SbomComponent {
+            kind: ComponentKind::Library,
+            group: library.group_id,
+            name: library.artifact_id,
+            version: library.version,
+            hashes: Vec::new(),
+            location: library.location,
+            properties: vec![("analyzer:identifiedBy".to_string(), "maven-metadata".to_string())],
+        });
+    }
+
+    for entry in entries.iter().filter(|e| !e.is_directory) {
+        let lower = entry.name.to_lowercase();
+        let location = format!("{}!/{}", component.location, entry.name);
+        if NATIVE_LIBRARY_EXTENSIONS.iter().any(|ext| lower.ends_with(ext)) {
+            let Ok(bytes) = read(&entry.name) else { continue };
+            let mut properties = Vec::new();
+            // APK 中的原生库按 `lib/<abi>/libfoo.so` 存放
+            if let ["lib", abi, _] = entry.name.split('/').collect::<Vec<_>>().as_slice() {
+                properties.push(("android:abi".to_string(), abi.to_string()));
+            }
+            components.push(SbomComponent {
+                kind: ComponentKind::File,
+                group: None,
+                name: file_name(&entry.name),
+                version: None,
+                hashes: hashes(&bytes),
+                location,
+                properties,
+            });
+        } else if depth < MAX_NESTING_DEPTH && NESTED_ARCHIVE_EXTENSIONS.iter().any(|ext| lower.ends_with(ext)) {
+            let Ok(bytes) = read(&entry.name) else { continue };
+            // 无法打开的嵌套归档仍然作为文件记录下来
+            let nested = scan_archive(ComponentKind::Library, &file_name(&entry.name), &location, &bytes, depth + 1, components).unwrap_or_else(|_| SbomComponent {
+                kind: ComponentKind::File,
+                group: None,
+                name: file_name(&entry.name),
+                version: None,
+                hashes: hashes(&bytes),
+                location: location.clone(),
+                properties: Vec::new(),
+            });
+            components.push(nested);
+        }
+    }
+}
+
+fn read_zip_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Vec<u8>, String> {
+    let mut file = archive.by_name(name).map_err(|e| e.to_string())?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).map_err(|e| e.to_string())?;
+    Ok(contents)
+}
+
+/// MANIFEST.MF 主段的属性，续行以一个空格开头
+pub(crate) fn parse_manifest(text: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            break;
+        }
+        if let Some(continuation) = line.strip_prefix(' ') {
+            if let Some((_, value)) = current.as_mut() {
+                value.push_str(continuation);
+            }
+            continue;
+        }
+        if let Some((key, value)) = current.take() {
+            attributes.insert(key, value);
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            current = Some((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    if let Some((key, value)) = current {
+        attributes.insert(key, value);
+    }
+    attributes
+}
+
+fn hashes(bytes: &[u8]) -> Vec<(&'static str, String)> {
+    vec![
+        ("SHA-1", to_hex(&Sha1::digest(bytes))),
+        ("SHA-256", to_hex(&Sha256::digest(bytes))),
+    ]
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(path)
+        .to_string()
+}
+
+fn cyclonedx_component(component: &SbomComponent, bom_ref: &str) -> Value {
+    let mut value = json!({
+        "type": match component.kind {
+            ComponentKind::Application => "application",
+            ComponentKind::Library => "library",
+            ComponentKind::File => "file",
+        },
+        "bom-ref": bom_ref,
+        "name": component.name,
+    });
+    if let Some(group) = &component.group {
+        value["group"] = json!(group);
+    }
+    if let Some(version) = &component.version {
+        value["version"] = json!(version);
+    }
+    if let Some(purl) = component.purl() {
+        value["purl"] = json!(purl);
+    }
+    if !component.hashes.is_empty() {
+        value["hashes"] = component
+            .hashes
+            .iter()
+            .map(|(alg, content)| json!({ "alg": alg, "content": content }))
+            .collect();
+    }
+    let mut properties = vec![json!({ "name": "analyzer:location", "value": component.location })];
+    properties.extend(component.properties.iter().map(|(name, value)| json!({ "name": name, "value": value })));
+    value["properties"] = Value::Array(properties);
+    value
+}
+
+fn cyclonedx_document(root: &SbomComponent, components: &[SbomComponent]) -> Value {
+    let refs: Vec<String> = (1..=components.len()).map(|i| format!("component-{}", i)).collect();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": utc_timestamp(),
+            "tools": { "components": [{ "type": "application", "name": "analyzer" }] },
+            "component": cyclonedx_component(root, "root"),
+        },
+        "components": components
+            .iter()
+            .zip(refs.iter())
+            .map(|(component, bom_ref)| cyclonedx_component(component, bom_ref))
+            .collect::<Vec<_>>(),
+        "dependencies": [{ "ref": "root", "dependsOn": refs }],
+    })
+}
+
+fn spdx_package(component: &SbomComponent, spdx_id: &str) -> Value {
+    let mut value = json!({
+        "name": component.name,
+        "SPDXID": spdx_id,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": "NOASSERTION",
+        "copyrightText": "NOASSERTION",
+        "comment": format!("Location: {}", component.location),
+    });
+    if let Some(version) = &component.version {
+        value["versionInfo"] = json!(version);
+    }
+    if let Some(group) = &component.group {
+        value["supplier"] = json!(format!("Organization: {}", group));
+    }
+    if component.kind == ComponentKind::File {
+        value["primaryPackagePurpose"] = json!("FILE");
+    } else if component.kind == ComponentKind::Application {
+        value["primaryPackagePurpose"] = json!("APPLICATION");
+    } else {
+        value["primaryPackagePurpose"] = json!("LIBRARY");
+    }
+    if !component.hashes.is_empty() {
+        value["checksums"] = component
+            .hashes
+            .iter()
+            .map(|(alg, content)| json!({ "algorithm": alg.replace('-', ""), "checksumValue": content }))
+            .collect();
+    }
+    if let Some(purl) = component.purl() {
+        value["externalRefs"] = json!([{
+            "referenceCategory": "PACKAGE-MANAGER",
+            "referenceType": "purl",
+            "referenceLocator": purl,
+        }]);
+    }
+    value
+}
+
+fn spdx_document(root: &SbomComponent, components: &[SbomComponent]) -> Value {
+    let ids: Vec<String> = (1..=components.len()).map(|i| format!("SPDXRef-Package-{}", i)).collect();
+    let mut packages = vec![spdx_package(root, "SPDXRef-Package-root")];
+    packages.extend(components.iter().zip(ids.iter()).map(|(component, id)| spdx_package(component, id)));
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": "SPDXRef-Package-root",
+    })];
+    relationships.extend(ids.iter().map(|id| {
+        json!({
+            "spdxElementId": "SPDXRef-Package-root",
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": id,
+        })
+    }));
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": root.name,
+        "documentNamespace": format!("https://spdx.org/spdxdocs/{}-{}", uri_segment(&root.name), uuid::Uuid::new_v4()),
+        "creationInfo": {
+            "created": utc_timestamp(),
+            "creators": ["Tool: analyzer"],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// 名称中 URI 不允许直接出现的字符按 UTF-8 字节做百分号编码
+fn uri_segment(text: &str) -> String {
+    let mut segment = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            segment.push(byte as char);
+        } else {
+            segment.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    segment
+}
+
+/// 当前 UTC 时间，格式为 `2024-01-31T12:00:00Z`
+fn utc_timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // 公历换算（Howard Hinnant 的 civil_from_days）
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    const POM: (&str, &str) = (
+        "META-INF/maven/com.squareup.okio/okio/pom.properties",
+        "groupId=com.squareup.okio\nartifactId=okio\nversion=3.6.0\n",
+    );
+
+    #[test]
+    fn library_archive_takes_its_single_maven_coordinates() {
+        let bytes = archive(&[POM]);
+        let mut components = Vec::new();
+        let jar = scan_archive(ComponentKind::Library, "okio.jar", "okio.jar", &bytes, 0, &mut components).unwrap();
+        assert_eq!(jar.group.as_deref(), Some("com.squareup.okio"));
+        assert_eq!(jar.name, "okio");
+        assert!(components.is_empty());
+    }
+
+    #[test]
+    fn application_keeps_its_identity_and_lists_embedded_metadata() {
+        let bytes = archive(&[POM, ("classes.dex", "")]);
+        let mut components = Vec::new();
+        let apk = scan_archive(ComponentKind::Application, "app.apk", "app.apk", &bytes, 0, &mut components).unwrap();
+        assert_eq!(apk.group, None);
+        assert_eq!(apk.name, "app.apk");
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].purl().as_deref(), Some("pkg:maven/com.squareup.okio/okio@3.6.0"));
+    }
+
+    #[test]
+    fn document_namespace_escapes_the_name() {
+        let root = SbomComponent {
+            kind: ComponentKind::Application,
+            group: None,
+            name: "my app/1.0?x".to_string(),
+            version: None,
+            hashes: Vec::new(),
+            location: String::new(),
+            properties: Vec::new(),
+        };
+        let namespace = spdx_document(&root, &[])["documentNamespace"].as_str().unwrap().to_string();
+        assert!(namespace.starts_with("https://spdx.org/spdxdocs/my%20app%2F1.0%3Fx-"), "{}", namespace);
+    }
+}
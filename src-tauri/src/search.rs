@@ -0,0 +1,566 @@
+// Full-text and structural search across Java and Android projects
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+
+use crate::java_analyzer::classfile::ClassFileReader;
+use crate::java_analyzer::constantpool::ConstantPoolEntry;
+use crate::model::{ClassModel, CodeInstruction, NumericConstant};
+use crate::program::CodeReference;
+use crate::project::ProjectClasses;
+use crate::rename::project_renamer;
+
+// 每处理多少个类发送一次进度
+const PROGRESS_BATCH: usize = 200;
+
+/// What to search for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchKind {
+    /// String literals loaded by `ldc` (Java) or `const-string` (Dalvik)
+    String,
+    /// Every `Utf8` constant pool entry; for DEX every name, descriptor and string constant a class uses
+    Utf8,
+    ClassName,
+    MethodName,
+    FieldName,
+    /// Whitespace separated mnemonics, each may use `*` and `?`, e.g. `aload_0 getfield invoke*`
+    OpcodeSequence,
+    /// Integer or floating point constants; untyped Dalvik constants are also read as float or double
+    /// when the pattern is a floating point number
+    Number,
+}
+
+/// How `pattern` is matched against names and strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchMode {
+    Exact,
+    Substring,
+    /// `*` matches any run of characters and `?` a single character
+    Wildcard,
+    Regex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub kind: SearchKind,
+    pub pattern: String,
+    pub mode: MatchMode,
+    pub case_sensitive: bool,
+    pub max_results: Option<usize>,
+}
+
+/// A match; member and offset are absent for class level matches.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    pub class_name: String,
+    pub method_name: Option<String>,
+    pub method_descriptor: Option<String>,
+    pub offset: Option<u32>,
+    pub matched: String,
+}
+
+/// Events streamed back to the UI while a search runs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data")]
+pub enum SearchEvent {
+    Progress { processed: usize, total: usize },
+    Results { results: Vec<SearchResult> },
+    Finished { total_results: usize, truncated: bool },
+}
+
+/// Compiled form of the textual part of a query.
+pub enum Matcher {
+    Exact(String, bool),
+    Substring(String, bool),
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn new(pattern: &str, mode: MatchMode, case_sensitive: bool) -> Result<Self, String> {
+        let build_regex = |source: &str| {
+            RegexBuilder::new(source)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|e| e.to_string())
+        };
+        match mode {
+            MatchMode::Exact => Ok(Matcher::Exact(fold_case(pattern, case_sensitive), case_sensitive)),
+            MatchMode::Substring => Ok(Matcher::Substring(fold_case(pattern, case_sensitive), case_sensitive)),
+            MatchMode::Wildcard => Ok(Matcher::Regex(build_regex(&wildcard_to_regex(pattern))?)),
+            MatchMode::Regex => Ok(Matcher::Regex(build_regex(pattern)?)),
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Matcher::Exact(pattern, case_sensitive) => fold_case(text, *case_sensitive) == *pattern,
+            Matcher::Substring(pattern, case_sensitive) => fold_case(text, *case_sensitive).contains(pattern.as_str()),
+            Matcher::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+fn fold_case(text: &str, case_sensitive: bool) -> String {
+    if case_sensitive {
+        text.to_string()
+    } else {
+        text.to_lowercase()
+    }
+}
+
+/// `com.foo.*Impl` -> `^com\.foo\..*Impl$`
+pub fn wildcard_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// A numeric constant to look for; integers also match equal floating point values.
+#[derive(Debug, Clone, Copy)]
+enum NumberPattern {
+    Integer(i64),
+    Float(f64),
+}
+
+impl NumberPattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim();
+        let parsed = if let Some(hex) = pattern.strip_prefix("0x").or_else(|| pattern.strip_prefix("0X")) {
+            i64::from_str_radix(hex, 16).ok().map(NumberPattern::Integer)
+        } else {
+            pattern.parse::<i64>().ok().map(NumberPattern::Integer)
+        };
+        parsed
+            .or_else(|| pattern.parse::<f64>().ok().map(NumberPattern::Float))
+            .ok_or_else(|| format!("Invalid number: {}", pattern))
+    }
+
+    fn matches_integer(&self, value: i64) -> bool {
+        match self {
+            NumberPattern::Integer(n) => *n == value,
+            NumberPattern::Float(f) => *f == value as f64,
+        }
+    }
+
+    fn matches_float(&self, value: f64) -> bool {
+        match self {
+            NumberPattern::Integer(n) => *n as f64 == value,
+            NumberPattern::Float(f) => *f == value,
+        }
+    }
+
+    /// Dalvik 的 `const` 不区分类型：浮点查询时把 32 位或 64 位的位模式解释成 float 或 double
+    fn matches_bits(&self, mnemonic: &str, bits: i64) -> Option<String> {
+        let NumberPattern::Float(f) = self else {
+            return None;
+        };
+        match mnemonic {
+            // float 按单精度比较，否则 0.1 这类值永远对不上
+            "const/4" | "const/16" | "const" | "const/high16" => {
+                let value = f32::from_bits(bits as u32);
+                (*f as f32 == value).then(|| value.to_string())
+            }
+            "const-wide/16" | "const-wide/32" | "const-wide" | "const-wide/high16" => {
+                let value = f64::from_bits(bits as u64);
+                (*f == value).then(|| value.to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The compiled query, applied class by class.
+pub struct Searcher {
+    kind: SearchKind,
+    matcher: Option<Matcher>,
+    opcode_pattern: Vec<Matcher>,
+    number: Option<NumberPattern>,
+}
+
+impl Searcher {
+    pub fn new(query: &SearchQuery) -> Result<Self, String> {
+        let mut searcher = Searcher {
+            kind: query.kind,
+            matcher: None,
+            opcode_pattern: Vec::new(),
+            number: None,
+        };
+        match query.kind {
+            SearchKind::OpcodeSequence => {
+                searcher.opcode_pattern = query
+                    .pattern
+                    .split_whitespace()
+                    .map(|token| Matcher::new(&token.to_lowercase(), MatchMode::Wildcard, false))
+                    .collect::<Result<_, _>>()?;
+                if searcher.opcode_pattern.is_empty() {
+                    return Err("Empty opcode pattern".to_string());
+                }
+            }
+            SearchKind::Number => searcher.number = Some(NumberPattern::parse(&query.pattern)?),
+            _ => searcher.matcher = Some(Matcher::new(&query.pattern, query.mode, query.case_sensitive)?),
+        }
+        Ok(searcher)
+    }
+
+    fn text_matches(&self, text: &str) -> bool {
+        self.matcher.as_ref().is_some_and(|m| m.is_match(text))
+    }
+
+    /// 在一个类中搜索，JVM 与 DEX 类使用同一套规则
+    pub(crate) fn search_class(&self, class: ClassModel) -> Vec<SearchResult> {
+        let Some(class_name) = class.name() else {
+            return Vec::new();
+        };
+        let class_result = |matched: String| SearchResult {
+            class_name: class_name.clone(),
+            method_name: None,
+            method_descriptor: None,
+            offset: None,
+            matched,
+        };
+        let mut results = Vec::new();
+
+        match self.kind {
+            SearchKind::ClassName => {
+                let dotted = class_name.replace('/', ".");
+                if self.text_matches(&dotted) || self.text_matches(&class_name) {
+                    results.push(class_result(dotted));
+                }
+            }
+            SearchKind::FieldName => {
+                for field in class.fields().iter().filter(|f| self.text_matches(f.name())) {
+                    results.push(class_result(format!("{}:{}", field.name(), field.descriptor())));
+                }
+            }
+            SearchKind::MethodName => {
+                for method in class.methods().iter().filter(|m| self.text_matches(m.name())) {
+                    let descriptor = method.descriptor();
+                    results.push(SearchResult {
+                        method_name: Some(method.name().to_string()),
+                        method_descriptor: Some(descriptor.clone()),
+                        ..class_result(format!("{}{}", method.name(), descriptor))
+                    });
+                }
+            }
+            SearchKind::Utf8 => match class {
+                ClassModel::Jvm(class_file) => {
+                    for entry in &class_file.constant_pool.constant_pool {
+                        if let ConstantPoolEntry::Utf8(value) = entry {
+                            if self.text_matches(value) {
+                                results.push(class_result(value.clone()));
+                            }
+                        }
+                    }
+                }
+                ClassModel::Dex(_, class_def) => {
+                    // DEX 的字符串表属于整个文件，按使用它的类、方法和指令报告
+                    let mut texts = vec![class_name.clone()];
+                    texts.extend(class.super_name());
+                    texts.extend(class.interfaces());
+                    texts.extend(class_def.source_file.clone());
+                    for field in class.fields() {
+                        texts.push(field.name().to_string());
+                        texts.push(field.descriptor().to_string());
+                    }
+                    for text in unique(texts.iter().map(String::as_str)).filter(|text| self.text_matches(text)) {
+                        results.push(class_result(text.to_string()));
+                    }
+                    for method in class.methods() {
+                        let descriptor = method.descriptor();
+                        let method_result = |offset: Option<u32>, matched: &str| SearchResult {
+                            method_name: Some(method.name().to_string()),
+                            method_descriptor: Some(descriptor.clone()),
+                            offset,
+                            ..class_result(matched.to_string())
+                        };
+                        for text in unique([method.name(), descriptor.as_str()]).filter(|text| self.text_matches(text)) {
+                            results.push(method_result(None, text));
+                        }
+                        for instruction in method.instructions() {
+                            let texts = unique(instruction.references.iter().flat_map(reference_texts));
+                            for text in texts.filter(|text| self.text_matches(text)) {
+                                results.push(method_result(Some(instruction.offset), text));
+                            }
+                        }
+                    }
+                }
+            },
+            SearchKind::String | SearchKind::Number | SearchKind::OpcodeSequence => {
+                for method in class.methods() {
+                    let method_result = |offset: u32, matched: String| SearchResult {
+                        method_name: Some(method.name().to_string()),
+                        method_descriptor: Some(method.descriptor()),
+                        offset: Some(offset),
+                        ..class_result(matched)
+                    };
+                    let instructions = method.instructions();
+                    if self.kind == SearchKind::OpcodeSequence {
+                        let mnemonics: Vec<&str> = instructions.iter().map(|i| i.mnemonic).collect();
+                        let len = self.opcode_pattern.len();
+                        for start in 0..mnemonics.len().saturating_sub(len - 1) {
+                            let window = &mnemonics[start..start + len];
+                            if window.iter().zip(&self.opcode_pattern).all(|(m, p)| p.is_match(m)) {
+                                results.push(method_result(instructions[start].offset, window.join(" ")));
+                            }
+                        }
+                        continue;
+                    }
+                    for instruction in &instructions {
+                        let matched = if self.kind == SearchKind::String {
+                            instruction.references.iter().find_map(|reference| match reference {
+                                CodeReference::String(value) if self.text_matches(value) => Some(value.clone()),
+                                _ => None,
+                            })
+                        } else {
+                            self.number_in_instruction(instruction)
+                        };
+                        if let Some(matched) = matched {
+                            results.push(method_result(instruction.offset, matched));
+                        }
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    fn number_in_instruction(&self, instruction: &CodeInstruction) -> Option<String> {
+        let number = self.number?;
+        match instruction.constant? {
+            NumericConstant::Integer(value) if number.matches_integer(value) => Some(value.to_string()),
+            NumericConstant::Integer(value) => number.matches_bits(instruction.mnemonic, value),
+            NumericConstant::Float(value) => number.matches_float(value).then(|| value.to_string()),
+        }
+    }
+}
+
+/// Names, descriptors and strings a reference is made of
+fn reference_texts(reference: &CodeReference) -> Vec<&str> {
+    match reference {
+        CodeReference::Invoke { owner, name, descriptor, .. }
+        | CodeReference::Field { owner, name, descriptor, .. }
+        | CodeReference::MethodHandle { owner, name, descriptor } => vec![owner, name, descriptor],
+        CodeReference::Instantiate(name) | CodeReference::Type(name) | CodeReference::String(name) => vec![name],
+    }
+}
+
+/// 去掉重复的文本，保持首次出现的顺序
+fn unique<'t>(texts: impl IntoIterator<Item = &'t str>) -> impl Iterator<Item = &'t str> {
+    let mut seen = Vec::new();
+    texts.into_iter().filter(move |text| {
+        let new = !seen.contains(text);
+        if new {
+            seen.push(*text);
+        }
+        new
+    })
+}
+
+/// Run a search and stream progress and results through `on_event`. Returns the number of results.
+#[tauri::command(async)]
+pub fn search_project(project_id: String, query: SearchQuery, on_event: Channel<SearchEvent>) -> Result<usize, String> {
+    let searcher = Searcher::new(&query)?;
+    let max_results = query.max_results.unwrap_or(usize::MAX);
+    let renamer = project_renamer(&project_id)?;
+
+    // 在项目锁内只取出类的来源，读取、解码和搜索都在锁外进行
+    let input = ProjectClasses::snapshot(&project_id)?;
+
+    let mut total_results = 0;
+    let mut truncated = false;
+    let mut pending = Vec::new();
+    let mut emit = |results: Vec<SearchResult>, processed: usize, total: usize, flush: bool| -> bool {
+        for result in results {
+            if total_results >= max_results {
+                truncated = true;
+                break;
+            }
+            pending.push(result);
+            total_results += 1;
+        }
+        if flush || truncated {
+            if !pending.is_empty() {
+                let _ = on_event.send(SearchEvent::Results { results: std::mem::take(&mut pending) });
+            }
+            let _ = on_event.send(SearchEvent::Progress { processed, total });
+        }
+        !truncated
+    };
+
+    // 出错时也要发送 Finished，界面据此结束搜索状态
+    let search = || -> Result<(), String> {
+        match input {
+            ProjectClasses::Classpath(classpath) => {
+                let classes = classpath.read_all_classes();
+                let total = classes.len();
+                for (i, (_, bytes)) in classes.iter().enumerate() {
+                    let results = match ClassFileReader::new(bytes).read() {
+                        Ok(mut class_file) => {
+                            renamer.class_file(&mut class_file);
+                            searcher.search_class(ClassModel::Jvm(&class_file))
+                        }
+                        Err(_) => Vec::new(),
+                    };
+                    let processed = i + 1;
+                    if !emit(results, processed, total, processed % PROGRESS_BATCH == 0 || processed == total) {
+                        break;
+                    }
+                }
+            }
+            ProjectClasses::Decoded(dex_files) => {
+                let total = dex_files.iter().map(|dex_file| dex_file.classes.len()).sum();
+                let mut processed = 0;
+                'files: for dex_file in dex_files.iter() {
+                    let ids = renamer.dex_ids(dex_file);
+                    for class_def in &dex_file.classes {
+                        let results = if renamer.is_empty() {
+                            searcher.search_class(ClassModel::Dex(&ids, class_def))
+                        } else {
+                            let mut class_def = class_def.clone();
+                            renamer.dex_class(&mut class_def);
+                            searcher.search_class(ClassModel::Dex(&ids, &class_def))
+                        };
+                        processed += 1;
+                        if !emit(results, processed, total, processed % PROGRESS_BATCH == 0 || processed == total) {
+                            break 'files;
+                        }
+                    }
+                }
+            }
+            ProjectClasses::Indexed(index) => {
+                let total = index.class_count();
+                let mut processed = 0;
+                'images: for image in index.images() {
+                    let ids = image.ids().map_err(|e| e.to_string())?;
+                    let ids = renamer.dex_ids(ids);
+                    for class_index in 0..image.class_names.len() {
+                        let results = match image.class(class_index) {
+                            Ok(mut class_def) => {
+                                renamer.dex_class(&mut class_def);
+                                searcher.search_class(ClassModel::Dex(&ids, &class_def))
+                            }
+                            Err(_) => Vec::new(),
+                        };
+                        processed += 1;
+                        if !emit(results, processed, total, processed % PROGRESS_BATCH == 0 || processed == total) {
+                            break 'images;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    };
+    let outcome = search();
+
+    let _ = on_event.send(SearchEvent::Finished { total_results, truncated });
+    outcome.map(|_| total_results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::dex_index::DexIndex;
+    use crate::android_analyzer::test_support;
+
+    const GREETER: &str = r#"
+.class public Lcom/example/Greeter;
+.super Ljava/lang/Object;
+
+.method public static greet()Ljava/lang/String;
+    .registers 1
+    const-string v0, "hello secret"
+    return-object v0
+.end method
+"#;
+
+    fn searcher(kind: SearchKind, pattern: &str) -> Searcher {
+        Searcher::new(&SearchQuery {
+            kind,
+            pattern: pattern.to_string(),
+            mode: MatchMode::Substring,
+            case_sensitive: true,
+            max_results: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn dex_utf8_hits_resolve_to_the_referencing_method() {
+        let dex_file = test_support::dex_file(&[GREETER]);
+        let results: Vec<SearchResult> = ClassModel::dex_classes(std::slice::from_ref(&dex_file))
+            .flat_map(|class| searcher(SearchKind::Utf8, "secret").search_class(class))
+            .collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].class_name, "com/example/Greeter");
+        assert_eq!(results[0].method_name.as_deref(), Some("greet"));
+        assert_eq!(results[0].method_descriptor.as_deref(), Some("()Ljava/lang/String;"));
+        assert_eq!(results[0].offset, Some(0));
+        assert_eq!(results[0].matched, "hello secret");
+    }
+
+    #[test]
+    fn dex_utf8_hits_on_names_are_class_and_method_level() {
+        let dex_file = test_support::dex_file(&[GREETER]);
+        let class = ClassModel::Dex(&dex_file, &dex_file.classes[0]);
+        let results = searcher(SearchKind::Utf8, "Greeter").search_class(class);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].method_name.is_none());
+
+        let results = searcher(SearchKind::Utf8, "greet").search_class(class);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].method_name.as_deref(), Some("greet"));
+        assert_eq!(results[0].offset, None);
+    }
+
+    #[test]
+    fn indexed_classes_give_the_same_results() {
+        let index = DexIndex::build(vec![("classes.dex".to_string(), test_support::dex_bytes(&[GREETER]))]).unwrap();
+        let image = &index.images()[0];
+        let class_def = image.class(0).unwrap();
+        let results = searcher(SearchKind::String, "hello").search_class(ClassModel::Dex(image.ids().unwrap(), &class_def));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].method_name.as_deref(), Some("greet"));
+    }
+
+    const CONSTANTS: &str = r#"
+.class public Lcom/example/Constants;
+.super Ljava/lang/Object;
+
+.method public static values()V
+    .registers 3
+    const/high16 v0, 0x3fc00000
+    const v0, 0x3dcccccd
+    const-wide v1, 0x400921fb54442d18L
+    const/16 v0, 0x2a
+    return-void
+.end method
+"#;
+
+    fn numbers(pattern: &str) -> Vec<(u32, String)> {
+        let dex_file = test_support::dex_file(&[CONSTANTS]);
+        searcher(SearchKind::Number, pattern)
+            .search_class(ClassModel::Dex(&dex_file, &dex_file.classes[0]))
+            .into_iter()
+            .map(|result| (result.offset.unwrap(), result.matched))
+            .collect()
+    }
+
+    #[test]
+    fn dalvik_constants_match_floating_point_patterns() {
+        assert_eq!(numbers("1.5"), vec![(0, "1.5".to_string())]);
+        assert_eq!(numbers("0.1"), vec![(2, "0.1".to_string())]);
+        assert_eq!(numbers("3.141592653589793"), vec![(5, "3.141592653589793".to_string())]);
+        // 整数查询仍按整数匹配，不把位模式当成浮点数
+        assert_eq!(numbers("42"), vec![(10, "42".to_string())]);
+        assert_eq!(numbers("1069547520"), vec![(0, "1069547520".to_string())]);
+        assert!(numbers("2.5").is_empty());
+    }
+}
@@ -0,0 +1,1023 @@
+// Source-to-sink taint tracking over the data-flow IR, with rules loaded from a local JSON file
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::android::DexFile;
+use crate::callgraph::{CallGraph, CallGraphAlgorithm, CallGraphOptions};
+use crate::dataflow::{dalvik, jvm};
+use crate::dataflow::solver::{solve, Analysis, Definition, DefinitionSite, Direction, ReachingDefinitions, Solution};
+use crate::dataflow::{FlowInstruction, FlowMethod, Operand, Operation, Variable};
+use crate::hierarchy::normalize_class_name;
+use crate::java_analyzer::ast::{is_wide, parse_method_descriptor};
+use crate::java_analyzer::classfile::ClassFile;
+use crate::java_analyzer::classpath::Classpath;
+use crate::java_analyzer::opcode::*;
+use crate::model::{bootstrap_handles, ClassModel, CodeInstruction, MethodModel};
+use crate::program::{CodeReference, InvokeKind, Program};
+use crate::project::{Project, ProjectData};
+
+const ACC_STATIC: u32 = 0x0008;
+
+/// (类, 方法, 类别)，方法名支持 `*`
+const BUILTIN_SOURCES: &[(&str, &str, &str)] = &[
+    ("android/telephony/TelephonyManager", "getDeviceId", "device_id"),
+    ("android/telephony/TelephonyManager", "getImei", "device_id"),
+    ("android/telephony/TelephonyManager", "getMeid", "device_id"),
+    ("android/telephony/TelephonyManager", "getSubscriberId", "device_id"),
+    ("android/telephony/TelephonyManager", "getSimSerialNumber", "device_id"),
+    ("android/telephony/TelephonyManager", "getLine1Number", "phone_number"),
+    ("android/provider/Settings$Secure", "getString", "device_id"),
+    ("android/net/wifi/WifiInfo", "getMacAddress", "device_id"),
+    ("android/net/wifi/WifiInfo", "getSSID", "network_info"),
+    ("android/net/wifi/WifiInfo", "getBSSID", "network_info"),
+    ("android/location/Location", "getLatitude", "location"),
+    ("android/location/Location", "getLongitude", "location"),
+    ("android/location/LocationManager", "getLastKnownLocation", "location"),
+    ("android/content/ClipboardManager", "getPrimaryClip", "clipboard"),
+    ("android/content/ClipboardManager", "getText", "clipboard"),
+    ("android/content/Intent", "get*Extra", "intent"),
+    ("android/content/Intent", "getExtras", "intent"),
+    ("android/content/Intent", "getData", "intent"),
+    ("android/content/Intent", "getDataString", "intent"),
+    ("android/accounts/AccountManager", "getAccounts*", "accounts"),
+    ("android/content/ContentResolver", "query", "content_provider"),
+];
+
+/// (类, 方法, 类别)，检查所有参数。JDK 类不在项目的类层次中，常用的具体子类需要单独列出
+const BUILTIN_SINKS: &[(&str, &str, &str)] = &[
+    ("android/util/Log", "*", "logging"),
+    ("java/io/PrintStream", "print*", "logging"),
+    ("java/net/URL", "<init>", "network"),
+    ("java/net/URLConnection", "setRequestProperty", "network"),
+    ("java/net/HttpURLConnection", "setRequestProperty", "network"),
+    ("javax/net/ssl/HttpsURLConnection", "setRequestProperty", "network"),
+    ("okhttp3/Request$Builder", "url", "network"),
+    ("okhttp3/Request$Builder", "*eader", "network"),
+    ("okhttp3/RequestBody", "create", "network"),
+    ("org/apache/http/client/methods/HttpGet", "<init>", "network"),
+    ("org/apache/http/client/methods/HttpPost", "<init>", "network"),
+    ("java/io/OutputStream", "write", "output_stream"),
+    ("java/io/FileOutputStream", "write", "output_stream"),
+    ("java/io/BufferedOutputStream", "write", "output_stream"),
+    ("java/io/DataOutputStream", "write*", "output_stream"),
+    ("java/io/Writer", "write", "output_stream"),
+    ("java/io/Writer", "append", "output_stream"),
+    ("java/io/FileWriter", "write", "output_stream"),
+    ("java/io/BufferedWriter", "write", "output_stream"),
+    ("java/io/OutputStreamWriter", "write", "output_stream"),
+    ("java/io/PrintWriter", "print*", "output_stream"),
+    ("java/io/PrintWriter", "write", "output_stream"),
+    ("android/content/SharedPreferences$Editor", "put*", "storage"),
+    ("android/database/sqlite/SQLiteDatabase", "execSQL", "storage"),
+    ("android/database/sqlite/SQLiteDatabase", "insert*", "storage"),
+    ("android/webkit/WebView", "loadUrl", "webview"),
+    ("android/webkit/WebView", "loadData*", "webview"),
+    ("android/webkit/WebView", "postUrl", "webview"),
+    ("android/webkit/WebView", "evaluateJavascript", "webview"),
+    ("android/telephony/SmsManager", "send*TextMessage", "sms"),
+];
+
+/// A method matched by a rule. Calls on subclasses and implementations of `class_name` match too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodPattern {
+    /// Internal (`android/util/Log`) or dotted class name
+    pub class_name: String,
+    /// `*` matches any run of characters
+    pub method_name: String,
+    #[serde(default)]
+    pub descriptor: Option<String>,
+}
+
+/// The return value of a matching call is tainted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceRule {
+    #[serde(flatten)]
+    pub method: MethodPattern,
+    /// e.g. `device_id`, `location`
+    pub category: String,
+}
+
+/// A tainted argument of a matching call is reported as a leak.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkRule {
+    #[serde(flatten)]
+    pub method: MethodPattern,
+    /// e.g. `network`, `logging`
+    pub category: String,
+    /// Checked argument positions counted from 0 without the receiver, every argument when empty
+    #[serde(default)]
+    pub arguments: Vec<usize>,
+}
+
+/// The rule file, a JSON document.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaintRules {
+    #[serde(default)]
+    pub sources: Vec<SourceRule>,
+    #[serde(default)]
+    pub sinks: Vec<SinkRule>,
+    /// Methods whose return value is clean whatever their arguments
+    #[serde(default)]
+    pub sanitizers: Vec<MethodPattern>,
+}
+
+impl TaintRules {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid taint rules {}: {}", path, e))
+    }
+
+    /// Android privacy sources (device ids, location, clipboard, intent extras, ...) flowing
+    /// to network, logging, file, storage, WebView and SMS sinks.
+    pub fn builtin() -> Self {
+        let pattern = |class_name: &str, method_name: &str| MethodPattern {
+            class_name: class_name.to_string(),
+            method_name: method_name.to_string(),
+            descriptor: None,
+        };
+        TaintRules {
+            sources: BUILTIN_SOURCES
+                .iter()
+                .map(|(class_name, method_name, category)| SourceRule {
+                    method: pattern(class_name, method_name),
+                    category: category.to_string(),
+                })
+                .collect(),
+            sinks: BUILTIN_SINKS
+                .iter()
+                .map(|(class_name, method_name, category)| SinkRule {
+                    method: pattern(class_name, method_name),
+                    category: category.to_string(),
+                    arguments: Vec::new(),
+                })
+                .collect(),
+            sanitizers: Vec::new(),
+        }
+    }
+}
+
+/// An instruction of a project method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaintLocation {
+    pub class_name: String,
+    pub method_name: String,
+    pub descriptor: String,
+    pub offset: u32,
+}
+
+/// A source value reaching a sink argument.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaintFinding {
+    pub source_category: String,
+    pub sink_category: String,
+    /// Call of the source API
+    pub source: TaintLocation,
+    /// e.g. `android/telephony/TelephonyManager.getDeviceId()Ljava/lang/String;`
+    pub source_method: String,
+    /// Call of the sink API
+    pub sink: TaintLocation,
+    pub sink_method: String,
+    /// Project calls passing the tainted value down to the sink's method, outermost first
+    pub call_chain: Vec<TaintLocation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaintReport {
+    pub analyzed_methods: usize,
+    /// Methods whose code could not be translated, with the reason
+    pub skipped_methods: Vec<String>,
+    pub findings: Vec<TaintFinding>,
+}
+
+/// 污点标签：来源调用点，或者（用于方法摘要）某个参数传入的污点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Label {
+    Source(usize),
+    Parameter(usize),
+}
+
+type Labels = BTreeSet<Label>;
+type TaintFact = BTreeMap<Variable, Labels>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Site {
+    method: usize,
+    offset: u32,
+}
+
+/// 指令在污点传播中的语义，由字节码和规则预先算好
+enum Effect {
+    None,
+    Source(usize),
+    Sanitizer,
+    /// `arguments` 以接收者开头；`callees` 为空时按库方法处理：返回值和接收者继承所有参数的污点
+    Invoke {
+        arguments: Vec<Variable>,
+        callees: Vec<usize>,
+        receiver_aliases: Vec<Variable>,
+        sink: Option<usize>,
+    },
+    /// `invokedynamic`：捕获的值流入创建出的对象，也作为前几个参数传给项目中的 lambda 方法体
+    Lambda {
+        captured: Vec<Variable>,
+        bodies: Vec<usize>,
+    },
+    FieldRead {
+        field: (String, String),
+        object: Option<Variable>,
+    },
+    FieldWrite {
+        field: (String, String),
+        value: Variable,
+    },
+    ArrayRead(Variable),
+    ArrayWrite {
+        value: Variable,
+        aliases: Vec<Variable>,
+    },
+    Return(Variable),
+}
+
+struct MethodCode {
+    class_name: String,
+    name: String,
+    descriptor: String,
+    flow: FlowMethod,
+    effects: Vec<Effect>,
+}
+
+/// 参数流向的 sink，键为 (参数, sink 调用点)，值为途经的调用点
+#[derive(Debug, Clone, Default)]
+struct Summary {
+    returns: Labels,
+    parameter_sinks: BTreeMap<(usize, usize), Vec<Site>>,
+}
+
+#[derive(Default)]
+struct MethodResult {
+    summary: Summary,
+    field_sources: Vec<((String, String), usize)>,
+    findings: Vec<((usize, usize), Vec<Site>)>,
+}
+
+impl MethodResult {
+    /// 带有 `label` 的值流向 sink：来源污点构成泄漏，参数污点记入摘要
+    fn reach(&mut self, label: Label, sink: usize, chain: Vec<Site>) {
+        match label {
+            Label::Source(source) => self.findings.push(((source, sink), chain)),
+            Label::Parameter(parameter) => {
+                self.summary.parameter_sinks.entry((parameter, sink)).or_insert(chain);
+            }
+        }
+    }
+}
+
+/// 一条调用指令，`arguments` 以接收者开头
+struct Call<'c> {
+    site: Site,
+    owner: &'c str,
+    name: &'c str,
+    descriptor: &'c str,
+    instance: bool,
+    arguments: Vec<Variable>,
+}
+
+/// Dalvik 的宽值占两个寄存器，污点只记在低位寄存器上；按声明的参数取出每个实参所在的寄存器
+fn declared_registers(registers: &[Variable], descriptor: &str, instance: bool) -> Vec<Variable> {
+    let mut registers = registers.iter().copied();
+    let mut declared: Vec<Variable> = if instance { registers.next().into_iter().collect() } else { Vec::new() };
+    for parameter in parse_method_descriptor(descriptor).0 {
+        declared.extend(registers.next());
+        if is_wide(&parameter) {
+            registers.next();
+        }
+    }
+    declared
+}
+
+struct SourceSite {
+    site: Site,
+    rule: usize,
+    method: String,
+}
+
+struct SinkSite {
+    site: Site,
+    rule: usize,
+    method: String,
+    /// 参数列表以接收者开头
+    instance: bool,
+}
+
+#[derive(Default)]
+struct TaintState {
+    summaries: Vec<Summary>,
+    /// 字段污点与流程无关，只记录真实来源
+    fields: HashMap<(String, String), BTreeSet<usize>>,
+    findings: BTreeMap<(usize, usize), Vec<Site>>,
+}
+
+impl TaintState {
+    fn taint(fact: &TaintFact, variable: &Variable) -> Labels {
+        fact.get(variable).cloned().unwrap_or_default()
+    }
+
+    fn result(&self, effect: &Effect, fact: &TaintFact) -> Labels {
+        match effect {
+            Effect::Source(source) => BTreeSet::from([Label::Source(*source)]),
+            Effect::Invoke { arguments, callees, .. } if callees.is_empty() => {
+                arguments.iter().flat_map(|argument| Self::taint(fact, argument)).collect()
+            }
+            Effect::Lambda { captured, .. } => captured.iter().flat_map(|argument| Self::taint(fact, argument)).collect(),
+            Effect::Invoke { arguments, callees, .. } => {
+                let mut labels = Labels::new();
+                for callee in callees {
+                    for label in &self.summaries[*callee].returns {
+                        match label {
+                            Label::Source(_) => {
+                                labels.insert(*label);
+                            }
+                            Label::Parameter(parameter) => {
+                                if let Some(argument) = arguments.get(*parameter) {
+                                    labels.extend(Self::taint(fact, argument));
+                                }
+                            }
+                        }
+                    }
+                }
+                labels
+            }
+            Effect::FieldRead { field, object } => {
+                let mut labels: Labels =
+                    self.fields.get(field).into_iter().flatten().map(|source| Label::Source(*source)).collect();
+                if let Some(object) = object {
+                    labels.extend(Self::taint(fact, object));
+                }
+                labels
+            }
+            Effect::ArrayRead(array) => Self::taint(fact, array),
+            _ => Labels::new(),
+        }
+    }
+}
+
+struct TaintFlow<'a> {
+    method: &'a MethodCode,
+    state: &'a TaintState,
+}
+
+impl Analysis for TaintFlow<'_> {
+    type Fact = TaintFact;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self, method: &FlowMethod) -> TaintFact {
+        method
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(index, variable)| (*variable, BTreeSet::from([Label::Parameter(index)])))
+            .collect()
+    }
+
+    fn bottom(&self) -> TaintFact {
+        TaintFact::new()
+    }
+
+    fn join(&self, into: &mut TaintFact, other: &TaintFact) {
+        for (variable, labels) in other {
+            into.entry(*variable).or_default().extend(labels.iter().copied());
+        }
+    }
+
+    fn transfer(&self, index: usize, instruction: &FlowInstruction, fact: &TaintFact) -> TaintFact {
+        let effect = &self.method.effects[index];
+        let mut out = fact.clone();
+        for (variable, operation) in &instruction.defs {
+            let labels = match operation {
+                Operation::Constant(_) => Labels::new(),
+                Operation::Copy(source) | Operation::Unary(_, source) => TaintState::taint(fact, source),
+                Operation::Binary(_, left, right) => [left, right]
+                    .into_iter()
+                    .filter_map(|operand| match operand {
+                        Operand::Variable(source) => Some(TaintState::taint(fact, source)),
+                        Operand::Constant(_) => None,
+                    })
+                    .flatten()
+                    .collect(),
+                Operation::Unknown => self.state.result(effect, fact),
+            };
+            if labels.is_empty() {
+                out.remove(variable);
+            } else {
+                out.insert(*variable, labels);
+            }
+        }
+        // 对象被修改（库方法接收到污点参数、数组写入）：对象的所有别名都带上污点
+        let (aliases, labels): (&[Variable], Labels) = match effect {
+            Effect::Invoke { arguments, callees, receiver_aliases, .. } if callees.is_empty() => (
+                receiver_aliases,
+                arguments.iter().skip(1).flat_map(|argument| TaintState::taint(fact, argument)).collect(),
+            ),
+            Effect::ArrayWrite { value, aliases } => (aliases, TaintState::taint(fact, value)),
+            _ => (&[], Labels::new()),
+        };
+        if !labels.is_empty() {
+            for alias in aliases {
+                out.entry(*alias).or_default().extend(labels.iter().copied());
+            }
+        }
+        out
+    }
+
+    fn catch(&self, _handler: usize, fact: &TaintFact) -> TaintFact {
+        fact.iter().filter(|(variable, _)| !variable.is_stack()).map(|(v, l)| (*v, l.clone())).collect()
+    }
+}
+
+/// 通过复制链找到与 `variable` 持有同一个对象的变量，例如 `aload` 之前的局部变量、`dup` 之前的栈槽
+fn aliases(flow: &FlowMethod, reaching: &Solution<BTreeSet<Definition>>, index: usize, variable: Variable) -> Vec<Variable> {
+    let definitions = |at: usize, of: Variable| -> Vec<Definition> {
+        reaching.before[at].iter().filter(|d| d.variable == of).copied().collect()
+    };
+    let mut result = vec![variable];
+    let mut pending = vec![(index, variable)];
+    while let Some((at, current)) = pending.pop() {
+        for definition in definitions(at, current) {
+            let DefinitionSite::Instruction(defining) = definition.site else {
+                continue;
+            };
+            let copied = flow.instructions[defining].defs.iter().find_map(|(defined, operation)| match operation {
+                Operation::Copy(source) if *defined == current => Some(*source),
+                _ => None,
+            });
+            if let Some(source) = copied {
+                // 复制之后源变量没有被重新赋值，两者仍指向同一个对象
+                if !result.contains(&source) && definitions(defining, source) == definitions(index, source) {
+                    result.push(source);
+                    pending.push((defining, source));
+                }
+            }
+        }
+    }
+    result
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+fn pattern_matches(pattern: &MethodPattern, owner_types: &[String], name: &str, descriptor: &str) -> bool {
+    glob_matches(&pattern.method_name, name)
+        && pattern.descriptor.as_ref().is_none_or(|d| d == descriptor)
+        && owner_types.contains(&pattern.class_name)
+}
+
+struct TaintEngine<'a> {
+    program: &'a Program,
+    rules: TaintRules,
+    methods: Vec<MethodCode>,
+    sources: Vec<SourceSite>,
+    sinks: Vec<SinkSite>,
+    supertypes: HashMap<String, Vec<String>>,
+}
+
+impl<'a> TaintEngine<'a> {
+    fn new(program: &'a Program, mut rules: TaintRules) -> Self {
+        for pattern in rules
+            .sources
+            .iter_mut()
+            .map(|rule| &mut rule.method)
+            .chain(rules.sinks.iter_mut().map(|rule| &mut rule.method))
+            .chain(rules.sanitizers.iter_mut())
+        {
+            pattern.class_name = normalize_class_name(&pattern.class_name);
+        }
+        TaintEngine {
+            program,
+            rules,
+            methods: Vec::new(),
+            sources: Vec::new(),
+            sinks: Vec::new(),
+            supertypes: HashMap::new(),
+        }
+    }
+
+    /// 调用的所属类及其全部父类型，规则按其中任何一个类匹配
+    fn owner_types(&mut self, owner: &str) -> Vec<String> {
+        let program = self.program;
+        self.supertypes
+            .entry(owner.to_string())
+            .or_insert_with(|| {
+                std::iter::once(owner.to_string())
+                    .chain(program.hierarchy.supertypes(owner).into_iter().map(|summary| summary.name))
+                    .collect()
+            })
+            .clone()
+    }
+
+    fn field_key(&self, owner: &str, name: &str, descriptor: &str) -> (String, String) {
+        let declaring = self.program.resolve_field(owner, name, descriptor).map(|class| class.name.clone());
+        (declaring.unwrap_or_else(|| owner.to_string()), name.to_string())
+    }
+
+    fn java_effects(
+        &mut self,
+        class_file: &ClassFile,
+        method_index: usize,
+        code: &[Instruction],
+        flow: &FlowMethod,
+        callees: &HashMap<(usize, u32), Vec<usize>>,
+        method_ids: &HashMap<(String, String, String), usize>,
+    ) -> Vec<Effect> {
+        let pool = &class_file.constant_pool;
+        let reaching = solve(&ReachingDefinitions, flow);
+        let mut effects = Vec::with_capacity(code.len());
+        for (index, (insn, instruction)) in code.iter().zip(&flow.instructions).enumerate() {
+            let uses = &instruction.uses;
+            let member = pool.get_member_ref(insn.value as usize).map(|(o, n, d)| (o.clone(), n.clone(), d.clone()));
+            // 不可达的指令没有 uses/defs
+            if uses.is_empty() && instruction.defs.is_empty() {
+                effects.push(Effect::None);
+                continue;
+            }
+            let effect = match (insn.opcode, member) {
+                (OP_INVOKEVIRTUAL | OP_INVOKESPECIAL | OP_INVOKESTATIC | OP_INVOKEINTERFACE, Some((owner, name, descriptor))) => {
+                    let call = Call {
+                        site: Site { method: method_index, offset: insn.offset },
+                        owner: &owner,
+                        name: &name,
+                        descriptor: &descriptor,
+                        instance: insn.opcode != OP_INVOKESTATIC,
+                        arguments: uses.iter().rev().copied().collect(),
+                    };
+                    let callees = callees.get(&(method_index, insn.offset)).cloned().unwrap_or_default();
+                    self.invoke_effect(call, callees, |receiver| aliases(flow, &reaching, index, receiver))
+                }
+                (OP_INVOKEDYNAMIC, _) => Effect::Lambda {
+                    captured: uses.iter().rev().copied().collect(),
+                    bodies: bootstrap_handles(class_file, insn.value as usize)
+                        .into_iter()
+                        .filter_map(|reference| match reference {
+                            CodeReference::MethodHandle { owner, name, descriptor } => method_ids.get(&(owner, name, descriptor)).copied(),
+                            _ => None,
+                        })
+                        .collect(),
+                },
+                (OP_GETFIELD | OP_GETSTATIC, Some((owner, name, descriptor))) => Effect::FieldRead {
+                    field: self.field_key(&owner, &name, &descriptor),
+                    object: uses.first().copied(),
+                },
+                (OP_PUTFIELD | OP_PUTSTATIC, Some((owner, name, descriptor))) => Effect::FieldWrite {
+                    field: self.field_key(&owner, &name, &descriptor),
+                    value: uses[0],
+                },
+                (OP_IALOAD..=OP_SALOAD, _) if uses.len() == 2 => Effect::ArrayRead(uses[1]),
+                (OP_IASTORE..=OP_SASTORE, _) if uses.len() == 3 => Effect::ArrayWrite {
+                    value: uses[0],
+                    aliases: aliases(flow, &reaching, index, uses[2]),
+                },
+                (OP_IRETURN..=OP_ARETURN, _) => Effect::Return(uses[0]),
+                _ => Effect::None,
+            };
+            effects.push(effect);
+        }
+        effects
+    }
+
+    /// Dalvik 指令的语义；寄存器操作数按指令中的顺序排列，数组和字段访问的值在最前面
+    fn dex_effects(
+        &mut self,
+        method_index: usize,
+        code: &[CodeInstruction],
+        flow: &FlowMethod,
+        callees: &HashMap<(usize, u32), Vec<usize>>,
+        method_ids: &HashMap<(String, String, String), usize>,
+    ) -> Vec<Effect> {
+        let reaching = solve(&ReachingDefinitions, flow);
+        let decoded: HashMap<u32, &CodeInstruction> = code.iter().map(|insn| (insn.offset, insn)).collect();
+        let mut effects = Vec::with_capacity(flow.instructions.len());
+        for (index, instruction) in flow.instructions.iter().enumerate() {
+            let uses = &instruction.uses;
+            let Some(insn) = decoded.get(&instruction.offset) else {
+                effects.push(Effect::None);
+                continue;
+            };
+            let mnemonic = insn.mnemonic;
+            let effect = match insn.references.first() {
+                Some(CodeReference::Invoke { kind, owner, name, descriptor, offset }) => {
+                    let instance = *kind != InvokeKind::Static;
+                    let call = Call {
+                        site: Site { method: method_index, offset: *offset },
+                        owner,
+                        name,
+                        descriptor,
+                        instance,
+                        arguments: declared_registers(uses, descriptor, instance),
+                    };
+                    let callees = callees.get(&(method_index, *offset)).cloned().unwrap_or_default();
+                    self.invoke_effect(call, callees, |receiver| aliases(flow, &reaching, index, receiver))
+                }
+                _ if mnemonic.starts_with("invoke-custom") => Effect::Lambda {
+                    captured: uses.clone(),
+                    bodies: insn
+                        .references
+                        .iter()
+                        .filter_map(|reference| match reference {
+                            CodeReference::MethodHandle { owner, name, descriptor } => {
+                                method_ids.get(&(owner.clone(), name.clone(), descriptor.clone())).copied()
+                            }
+                            _ => None,
+                        })
+                        .collect(),
+                },
+                Some(CodeReference::Field { owner, name, descriptor, write: false }) => Effect::FieldRead {
+                    field: self.field_key(owner, name, descriptor),
+                    object: uses.first().copied(),
+                },
+                Some(CodeReference::Field { owner, name, descriptor, write: true }) if !uses.is_empty() => Effect::FieldWrite {
+                    field: self.field_key(owner, name, descriptor),
+                    value: uses[0],
+                },
+                _ if mnemonic.starts_with("aget") && uses.len() == 2 => Effect::ArrayRead(uses[0]),
+                _ if mnemonic.starts_with("aput") && uses.len() >= 3 => Effect::ArrayWrite {
+                    value: uses[0],
+                    aliases: aliases(flow, &reaching, index, uses[uses.len() - 2]),
+                },
+                _ if mnemonic.starts_with("return") && !uses.is_empty() => Effect::Return(uses[0]),
+                _ => Effect::None,
+            };
+            effects.push(effect);
+        }
+        effects
+    }
+
+    /// 调用按规则分为来源、净化和普通调用；`receiver_aliases` 只在库方法可能修改接收者时计算
+    fn invoke_effect(&mut self, call: Call, callees: Vec<usize>, receiver_aliases: impl FnOnce(Variable) -> Vec<Variable>) -> Effect {
+        let Call { site, owner, name, descriptor, instance, arguments } = call;
+        let called = format!("{}.{}{}", owner, name, descriptor);
+        let types = self.owner_types(owner);
+        let matches = |pattern: &MethodPattern| pattern_matches(pattern, &types, name, descriptor);
+        if let Some(rule) = self.rules.sources.iter().position(|rule| matches(&rule.method)) {
+            self.sources.push(SourceSite { site, rule, method: called });
+            return Effect::Source(self.sources.len() - 1);
+        }
+        if self.rules.sanitizers.iter().any(matches) {
+            return Effect::Sanitizer;
+        }
+        let sink = self.rules.sinks.iter().position(|rule| matches(&rule.method)).map(|rule| {
+            self.sinks.push(SinkSite { site, rule, method: called, instance });
+            self.sinks.len() - 1
+        });
+        let receiver_aliases = if instance && callees.is_empty() && arguments.len() > 1 {
+            receiver_aliases(arguments[0])
+        } else {
+            Vec::new()
+        };
+        Effect::Invoke { arguments, callees, receiver_aliases, sink }
+    }
+
+    /// 分析一个方法，返回新的摘要以及写入字段的污点和发现的泄漏
+    fn analyze(&self, method_index: usize, state: &TaintState) -> MethodResult {
+        let method = &self.methods[method_index];
+        let solution = solve(&TaintFlow { method, state }, &method.flow);
+        let mut result = MethodResult::default();
+        for (index, effect) in method.effects.iter().enumerate() {
+            let fact = &solution.before[index];
+            let site = Site { method: method_index, offset: method.flow.instructions[index].offset };
+            match effect {
+                Effect::Return(value) => result.summary.returns.extend(TaintState::taint(fact, value)),
+                Effect::FieldWrite { field, value } => {
+                    for label in TaintState::taint(fact, value) {
+                        if let Label::Source(source) = label {
+                            result.field_sources.push((field.clone(), source));
+                        }
+                    }
+                }
+                Effect::Invoke { arguments, callees, sink, .. } => {
+                    if let Some(sink) = sink {
+                        let rule = &self.rules.sinks[self.sinks[*sink].rule];
+                        let first_argument = self.sinks[*sink].instance as usize;
+                        for (position, argument) in arguments.iter().enumerate().skip(first_argument) {
+                            if !rule.arguments.is_empty() && !rule.arguments.contains(&(position - first_argument)) {
+                                continue;
+                            }
+                            for label in TaintState::taint(fact, argument) {
+                                result.reach(label, *sink, Vec::new());
+                            }
+                        }
+                    }
+                    Self::propagate_parameter_sinks(state, &mut result, site, arguments, callees, fact);
+                }
+                Effect::Lambda { captured, bodies } => {
+                    Self::propagate_parameter_sinks(state, &mut result, site, captured, bodies, fact);
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// 被调方法的参数流向 sink 时，实参的污点也流向那个 sink
+    fn propagate_parameter_sinks(
+        state: &TaintState,
+        result: &mut MethodResult,
+        site: Site,
+        arguments: &[Variable],
+        callees: &[usize],
+        fact: &TaintFact,
+    ) {
+        for callee in callees {
+            for ((parameter, sink), chain) in &state.summaries[*callee].parameter_sinks {
+                let Some(labels) = arguments.get(*parameter).and_then(|argument| fact.get(argument)) else {
+                    continue;
+                };
+                for label in labels {
+                    let extended = std::iter::once(site).chain(chain.iter().copied()).collect();
+                    result.reach(*label, *sink, extended);
+                }
+            }
+        }
+    }
+
+    /// 方法级的工作表：摘要变化时重新分析调用者，字段新增污点时重新分析读取者
+    fn run(&self) -> TaintState {
+        let count = self.methods.len();
+        let mut callers: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); count];
+        let mut readers: HashMap<&(String, String), BTreeSet<usize>> = HashMap::new();
+        for (method_index, method) in self.methods.iter().enumerate() {
+            for effect in &method.effects {
+                match effect {
+                    Effect::Invoke { callees, .. } | Effect::Lambda { bodies: callees, .. } => {
+                        for callee in callees {
+                            callers[*callee].insert(method_index);
+                        }
+                    }
+                    Effect::FieldRead { field, .. } => {
+                        readers.entry(field).or_default().insert(method_index);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut state = TaintState {
+            summaries: vec![Summary::default(); count],
+            ..Default::default()
+        };
+        let mut queue: VecDeque<usize> = (0..count).collect();
+        let mut queued = vec![true; count];
+        while let Some(method_index) = queue.pop_front() {
+            queued[method_index] = false;
+            let MethodResult { summary, field_sources, findings } = self.analyze(method_index, &state);
+            for (key, chain) in findings {
+                state.findings.entry(key).or_insert(chain);
+            }
+            let mut changed_fields = Vec::new();
+            for (field, source) in field_sources {
+                if state.fields.entry(field.clone()).or_default().insert(source) {
+                    changed_fields.push(field);
+                }
+            }
+            // 摘要只增不减，已有条目保留最先找到的调用链，递归调用时才能收敛
+            let current = &mut state.summaries[method_index];
+            let sizes = (current.returns.len(), current.parameter_sinks.len());
+            current.returns.extend(summary.returns);
+            for (key, chain) in summary.parameter_sinks {
+                current.parameter_sinks.entry(key).or_insert(chain);
+            }
+            let mut dependents = Vec::new();
+            if sizes != (current.returns.len(), current.parameter_sinks.len()) {
+                dependents.extend(callers[method_index].iter().copied());
+            }
+            for field in changed_fields {
+                dependents.extend(readers.get(&field).into_iter().flatten().copied());
+            }
+            for dependent in dependents {
+                if !queued[dependent] {
+                    queued[dependent] = true;
+                    queue.push_back(dependent);
+                }
+            }
+        }
+        state
+    }
+
+    fn location(&self, site: &Site) -> TaintLocation {
+        let method = &self.methods[site.method];
+        TaintLocation {
+            class_name: method.class_name.clone(),
+            method_name: method.name.clone(),
+            descriptor: method.descriptor.clone(),
+            offset: site.offset,
+        }
+    }
+
+    fn findings(&self, state: &TaintState) -> Vec<TaintFinding> {
+        state
+            .findings
+            .iter()
+            .map(|((source, sink), chain)| {
+                let (source, sink) = (&self.sources[*source], &self.sinks[*sink]);
+                TaintFinding {
+                    source_category: self.rules.sources[source.rule].category.clone(),
+                    sink_category: self.rules.sinks[sink.rule].category.clone(),
+                    source: self.location(&source.site),
+                    source_method: source.method.clone(),
+                    sink: self.location(&sink.site),
+                    sink_method: sink.method.clone(),
+                    call_chain: chain.iter().map(|site| self.location(site)).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn analyze(classes: &[ClassModel], rules: TaintRules) -> TaintReport {
+    let program = Program::from_classes(classes.iter().copied());
+    let call_graph = CallGraph::build(
+        &program,
+        &CallGraphOptions {
+            algorithm: CallGraphAlgorithm::Cha,
+            include_external: false,
+        },
+        &[],
+    );
+    let mut engine = TaintEngine::new(&program, rules);
+    let mut skipped_methods = Vec::new();
+
+    // 先为所有方法分配编号，调用图的边才能映射到方法
+    let mut method_ids: HashMap<(String, String, String), usize> = HashMap::new();
+    let mut bodies = Vec::new();
+    for class in classes {
+        let Some(class_name) = class.name() else {
+            continue;
+        };
+        for method in class.methods() {
+            let key = (class_name.clone(), method.name().to_string(), method.descriptor());
+            if !method.has_code() || method_ids.contains_key(&key) {
+                continue;
+            }
+            let flow = match method {
+                MethodModel::Jvm(class_file, jvm_method) => jvm::flow_method(class_file, jvm_method),
+                MethodModel::Dex(dex_file, dex_method) => dalvik::flow_method(dex_file, dex_method).map(|mut flow| {
+                    // 参数与实参一样只保留低位寄存器，摘要中的参数序号才能对上
+                    let instance = dex_method.access_flags & ACC_STATIC == 0;
+                    flow.parameters = declared_registers(&flow.parameters, &key.2, instance);
+                    flow
+                }),
+            };
+            match flow {
+                Ok(flow) => {
+                    method_ids.insert(key, bodies.len());
+                    bodies.push((class_name.clone(), method, flow));
+                }
+                Err(e) => skipped_methods.push(format!("{}.{}{}: {}", class_name, key.1, key.2, e)),
+            }
+        }
+    }
+    let node_methods: Vec<Option<usize>> = call_graph
+        .nodes
+        .iter()
+        .map(|node| method_ids.get(&(node.class_name.clone(), node.method_name.clone(), node.descriptor.clone())).copied())
+        .collect();
+    let mut callees: HashMap<(usize, u32), Vec<usize>> = HashMap::new();
+    for edge in &call_graph.edges {
+        if let (Some(caller), Some(callee), Some(offset)) = (node_methods[edge.caller], node_methods[edge.callee], edge.offset) {
+            callees.entry((caller, offset)).or_default().push(callee);
+        }
+    }
+
+    for (method_index, (class_name, method, flow)) in bodies.into_iter().enumerate() {
+        let effects = match method {
+            MethodModel::Jvm(class_file, jvm_method) => {
+                engine.java_effects(class_file, method_index, &jvm_method.code, &flow, &callees, &method_ids)
+            }
+            MethodModel::Dex(..) => engine.dex_effects(method_index, &method.instructions(), &flow, &callees, &method_ids),
+        };
+        engine.methods.push(MethodCode {
+            class_name,
+            name: method.name().to_string(),
+            descriptor: method.descriptor(),
+            flow,
+            effects,
+        });
+    }
+
+    let state = engine.run();
+    TaintReport {
+        analyzed_methods: engine.methods.len(),
+        skipped_methods,
+        findings: engine.findings(&state),
+    }
+}
+
+enum TaintInput {
+    Classpath(Classpath),
+    Dex(Arc<Vec<DexFile>>),
+}
+
+/// Tracks source values to sink arguments across the project's methods. Without a rule file
+/// the built-in Android privacy rules are used.
+#[tauri::command(async)]
+pub fn taint_analyze(project_id: String, rules_path: Option<String>) -> Result<TaintReport, String> {
+    let rules = match rules_path {
+        Some(path) => TaintRules::load(&path)?,
+        None => TaintRules::builtin(),
+    };
+    let input = Project::with_project_mut(&project_id, |project| match &mut project.data {
+        ProjectData::Java(java_data) => Ok(TaintInput::Classpath(java_data.classpath.clone())),
+        ProjectData::Android(android_data) => {
+            android_data.ensure_analyzed()?;
+            Ok(TaintInput::Dex(android_data.dex_files.clone()))
+        }
+        _ => Err("Not a Java or Android project".to_string()),
+    })?;
+    // 解析和分析都在项目锁外进行
+    let class_files = match &input {
+        TaintInput::Classpath(classpath) => classpath.class_files(),
+        TaintInput::Dex(_) => Vec::new(),
+    };
+    let classes: Vec<ClassModel> = match &input {
+        TaintInput::Classpath(_) => class_files.iter().map(ClassModel::Jvm).collect(),
+        TaintInput::Dex(dex_files) => ClassModel::dex_classes(dex_files).collect(),
+    };
+    Ok(analyze(&classes, rules))
+}
+
+/// The built-in rules, a starting point for a custom rule file.
+#[tauri::command]
+pub fn taint_default_rules() -> TaintRules {
+    TaintRules::builtin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+
+    const LEAK: &str = r#"
+.class public LLeak;
+.super Ljava/lang/Object;
+
+.method public static leak(Landroid/telephony/TelephonyManager;)V
+    .registers 5
+    invoke-virtual {v4}, Landroid/telephony/TelephonyManager;->getDeviceId()Ljava/lang/String;
+    move-result-object v2
+    const-wide/16 v0, 0x1
+    invoke-static {v0, v1, v2}, LLeak;->send(JLjava/lang/String;)V
+    return-void
+.end method
+
+.method public static send(JLjava/lang/String;)V
+    .registers 4
+    const-string v0, "tag"
+    invoke-static {v0, v3}, Landroid/util/Log;->d(Ljava/lang/String;Ljava/lang/String;)I
+    return-void
+.end method
+
+.method public static constant()V
+    .registers 2
+    const-string v0, "tag"
+    const-string v1, "value"
+    invoke-static {v0, v1}, Landroid/util/Log;->d(Ljava/lang/String;Ljava/lang/String;)I
+    return-void
+.end method
+"#;
+
+    #[test]
+    fn dex_source_reaches_sink_through_a_wide_argument_call() {
+        let dex_files = vec![dex_file(&[LEAK])];
+        let classes: Vec<ClassModel> = ClassModel::dex_classes(&dex_files).collect();
+        let report = analyze(&classes, TaintRules::builtin());
+        assert_eq!(report.analyzed_methods, 3, "skipped: {:?}", report.skipped_methods);
+        assert_eq!(report.findings.len(), 1);
+        let finding = &report.findings[0];
+        assert_eq!(finding.source_category, "device_id");
+        assert_eq!(finding.sink_category, "logging");
+        assert_eq!(finding.source.method_name, "leak");
+        assert_eq!(finding.sink.method_name, "send");
+        assert_eq!(finding.call_chain.len(), 1);
+        assert_eq!(finding.call_chain[0].method_name, "leak");
+    }
+}
@@ -0,0 +1,359 @@
+// Cross-reference database: who calls, reads or writes what
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::android::DexFile;
+use crate::hierarchy::normalize_class_name;
+use crate::java_analyzer::classpath::Classpath;
+use crate::model::ClassModel;
+use crate::program::{class_declaration, CodeReference, Program};
+use crate::project::{cached_index, ProjectClasses, ProjectData};
+
+/// How a location uses its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum XrefKind {
+    Call,
+    FieldRead,
+    FieldWrite,
+    Instantiate,
+    Cast,
+    InstanceOf,
+    ClassConstant,
+    StringConstant,
+    /// Array creation, `multianewarray` and other plain type references
+    TypeReference,
+}
+
+/// The instruction referencing a target.
+#[derive(Debug, Clone, Serialize)]
+pub struct XrefLocation {
+    pub class_name: String,
+    pub method_name: String,
+    pub method_descriptor: String,
+    pub offset: u32,
+    pub kind: XrefKind,
+}
+
+/// A field or method, owned by the class that declares it when that class is in the project.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct MemberRef {
+    pub owner: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+/// Index of every class, method, field and string reference of a project.
+#[derive(Debug, Clone, Default)]
+pub struct XrefIndex {
+    pub class_refs: HashMap<String, Vec<XrefLocation>>,
+    pub method_refs: HashMap<MemberRef, Vec<XrefLocation>>,
+    pub field_refs: HashMap<MemberRef, Vec<XrefLocation>>,
+    pub string_refs: HashMap<String, Vec<XrefLocation>>,
+}
+
+/// A usage together with the member it refers to, for results that span several members.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberUsage {
+    pub target: MemberRef,
+    pub location: XrefLocation,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StringUsage {
+    pub value: String,
+    pub location: XrefLocation,
+}
+
+impl XrefIndex {
+    pub fn from_classpath(classpath: &Classpath) -> Self {
+        let class_files = classpath.class_files();
+        XrefIndex::from_classes(class_files.iter().map(ClassModel::Jvm))
+    }
+
+    pub fn from_dex_files(dex_files: &[DexFile]) -> Self {
+        XrefIndex::from_classes(ClassModel::dex_classes(dex_files))
+    }
+
+    fn from_classes<'a>(classes: impl IntoIterator<Item = ClassModel<'a>>) -> Self {
+        let mut index = XrefIndex::default();
+        let mut declarations = Vec::new();
+        for class in classes {
+            declarations.extend(class_declaration(class));
+            index.add_class(class);
+        }
+        index.resolve_owners(&Program::new(declarations));
+        index
+    }
+
+    /// 逐个类读取项目并扫描，不保留类本身，只留下解析成员所需的声明
+    pub(crate) fn from_project(classes: &ProjectClasses) -> Self {
+        let mut index = XrefIndex::default();
+        let mut declarations = Vec::new();
+        classes.for_each_class(|class| {
+            declarations.extend(class_declaration(class));
+            index.add_class(class);
+        });
+        index.resolve_owners(&Program::new(declarations));
+        index
+    }
+
+    /// 扫描一个类中所有方法的指令
+    pub(crate) fn add_class(&mut self, class: ClassModel) {
+        let Some(class_name) = class.name() else {
+            return;
+        };
+
+        for method in class.methods() {
+            let method_name = method.name();
+            let method_descriptor = method.descriptor();
+            for instruction in method.instructions() {
+                let location = |kind| XrefLocation {
+                    class_name: class_name.clone(),
+                    method_name: method_name.to_string(),
+                    method_descriptor: method_descriptor.clone(),
+                    offset: instruction.offset,
+                    kind,
+                };
+                for reference in &instruction.references {
+                    match reference {
+                        CodeReference::Invoke { owner, name, descriptor, .. } => {
+                            self.method_refs.entry(member_ref(owner, name, descriptor)).or_default().push(location(XrefKind::Call));
+                        }
+                        CodeReference::Field { owner, name, descriptor, write } => {
+                            let kind = if *write { XrefKind::FieldWrite } else { XrefKind::FieldRead };
+                            self.field_refs.entry(member_ref(owner, name, descriptor)).or_default().push(location(kind));
+                        }
+                        CodeReference::Instantiate(name) => {
+                            self.class_refs.entry(name.clone()).or_default().push(location(XrefKind::Instantiate));
+                        }
+                        CodeReference::Type(name) => {
+                            // JVM 与 Dalvik 助记符
+                            let kind = match instruction.mnemonic {
+                                "checkcast" | "check-cast" => XrefKind::Cast,
+                                "instanceof" | "instance-of" => XrefKind::InstanceOf,
+                                "ldc" | "ldc_w" | "const-class" => XrefKind::ClassConstant,
+                                _ => XrefKind::TypeReference,
+                            };
+                            self.class_refs.entry(name.clone()).or_default().push(location(kind));
+                        }
+                        CodeReference::String(value) => {
+                            self.string_refs.entry(value.clone()).or_default().push(location(XrefKind::StringConstant));
+                        }
+                        CodeReference::MethodHandle { .. } => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// 把常量池中的所有者换成成员的声明类，通过子类访问的继承成员归到声明它的类；
+    /// 声明不在项目中的成员保持原样
+    fn resolve_owners(&mut self, program: &Program) {
+        let resolve = |refs: HashMap<MemberRef, Vec<XrefLocation>>, find: &dyn Fn(&MemberRef) -> Option<String>| {
+            let mut resolved: HashMap<MemberRef, Vec<XrefLocation>> = HashMap::new();
+            for (mut member, locations) in refs {
+                if let Some(owner) = find(&member) {
+                    member.owner = owner;
+                }
+                resolved.entry(member).or_default().extend(locations);
+            }
+            resolved
+        };
+        self.method_refs = resolve(std::mem::take(&mut self.method_refs), &|member| {
+            program.resolve_method(&member.owner, &member.name, &member.descriptor).map(|class| class.name.clone())
+        });
+        self.field_refs = resolve(std::mem::take(&mut self.field_refs), &|member| {
+            program.resolve_field(&member.owner, &member.name, &member.descriptor).map(|class| class.name.clone())
+        });
+    }
+
+    /// 类的所有使用位置，包括对其成员的访问
+    pub fn class_usages(&self, class_name: &str) -> Vec<XrefLocation> {
+        let class_name = normalize_class_name(class_name);
+        let mut usages: Vec<XrefLocation> = self.class_refs.get(&class_name).cloned().unwrap_or_default();
+        for (member, locations) in self.method_refs.iter().chain(self.field_refs.iter()) {
+            if member.owner == class_name {
+                usages.extend(locations.iter().cloned());
+            }
+        }
+        usages.sort_by(|a, b| location_key(a).cmp(&location_key(b)));
+        usages
+    }
+
+    /// 方法的调用位置，descriptor 为空时匹配所有重载
+    pub fn method_usages(&self, owner: &str, name: &str, descriptor: Option<&str>) -> Vec<MemberUsage> {
+        member_usages(&self.method_refs, owner, name, descriptor)
+    }
+
+    /// 字段的读写位置，descriptor 为空时只按名称匹配
+    pub fn field_usages(&self, owner: &str, name: &str, descriptor: Option<&str>) -> Vec<MemberUsage> {
+        member_usages(&self.field_refs, owner, name, descriptor)
+    }
+
+    /// 字符串常量的使用位置，exact 为 false 时按子串匹配
+    pub fn string_usages(&self, value: &str, exact: bool) -> Vec<StringUsage> {
+        let mut usages: Vec<StringUsage> = self
+            .string_refs
+            .iter()
+            .filter(|(s, _)| if exact { s.as_str() == value } else { s.contains(value) })
+            .flat_map(|(s, locations)| {
+                locations.iter().map(move |location| StringUsage {
+                    value: s.clone(),
+                    location: location.clone(),
+                })
+            })
+            .collect();
+        usages.sort_by(|a, b| location_key(&a.location).cmp(&location_key(&b.location)));
+        usages
+    }
+}
+
+fn member_ref(owner: &str, name: &str, descriptor: &str) -> MemberRef {
+    MemberRef {
+        owner: owner.to_string(),
+        name: name.to_string(),
+        descriptor: descriptor.to_string(),
+    }
+}
+
+fn member_usages(
+    refs: &HashMap<MemberRef, Vec<XrefLocation>>,
+    owner: &str,
+    name: &str,
+    descriptor: Option<&str>,
+) -> Vec<MemberUsage> {
+    let owner = normalize_class_name(owner);
+    let mut usages: Vec<MemberUsage> = refs
+        .iter()
+        .filter(|(member, _)| {
+            member.owner == owner
+                && member.name == name
+                && descriptor.is_none_or(|d| d.is_empty() || member.descriptor == d)
+        })
+        .flat_map(|(member, locations)| {
+            locations.iter().map(move |location| MemberUsage {
+                target: member.clone(),
+                location: location.clone(),
+            })
+        })
+        .collect();
+    usages.sort_by(|a, b| location_key(&a.location).cmp(&location_key(&b.location)));
+    usages
+}
+
+fn location_key(location: &XrefLocation) -> (&str, &str, u32) {
+    (&location.class_name, &location.method_name, location.offset)
+}
+
+/// 取出（必要时构建）项目的交叉引用索引后执行查询
+fn with_xrefs<T, F>(project_id: &str, f: F) -> Result<T, String>
+where
+    F: FnOnce(&XrefIndex) -> T,
+{
+    let xrefs = cached_index(
+        project_id,
+        |data| match data {
+            ProjectData::Java(java_data) => Some(&mut java_data.xrefs),
+            ProjectData::Android(android_data) => Some(&mut android_data.xrefs),
+            _ => None,
+        },
+        XrefIndex::from_project,
+    )?;
+    Ok(f(&xrefs))
+}
+
+#[tauri::command]
+pub fn xref_find_class_usages(project_id: String, class_name: String) -> Result<Vec<XrefLocation>, String> {
+    with_xrefs(&project_id, |xrefs| xrefs.class_usages(&class_name))
+}
+
+#[tauri::command]
+pub fn xref_find_method_usages(project_id: String, owner: String, name: String, descriptor: Option<String>) -> Result<Vec<MemberUsage>, String> {
+    with_xrefs(&project_id, |xrefs| xrefs.method_usages(&owner, &name, descriptor.as_deref()))
+}
+
+#[tauri::command]
+pub fn xref_find_field_usages(project_id: String, owner: String, name: String, descriptor: Option<String>) -> Result<Vec<MemberUsage>, String> {
+    with_xrefs(&project_id, |xrefs| xrefs.field_usages(&owner, &name, descriptor.as_deref()))
+}
+
+#[tauri::command]
+pub fn xref_find_string_usages(project_id: String, value: String, exact: bool) -> Result<Vec<StringUsage>, String> {
+    with_xrefs(&project_id, |xrefs| xrefs.string_usages(&value, exact))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::android_analyzer::test_support::dex_file;
+
+    const BASE: &str = r#"
+.class public Lapp/Base;
+.super Ljava/lang/Object;
+
+.field public count:I
+
+.method public run()V
+    .registers 1
+    return-void
+.end method
+"#;
+
+    const SUB: &str = r#"
+.class public Lapp/Sub;
+.super Lapp/Base;
+"#;
+
+    const USER: &str = r#"
+.class public Lapp/User;
+.super Ljava/lang/Object;
+
+.method public static direct(Lapp/Base;)V
+    .registers 2
+    invoke-virtual {p0}, Lapp/Base;->run()V
+    const/4 v0, 0x1
+    iput v0, p0, Lapp/Base;->count:I
+    return-void
+.end method
+
+.method public static inherited(Lapp/Sub;)I
+    .registers 2
+    invoke-virtual {p0}, Lapp/Sub;->run()V
+    invoke-virtual {p0}, Ljava/lang/Object;->hashCode()I
+    iget v0, p0, Lapp/Sub;->count:I
+    return v0
+.end method
+"#;
+
+    fn index() -> XrefIndex {
+        XrefIndex::from_dex_files(&[dex_file(&[BASE, SUB, USER])])
+    }
+
+    fn callers(usages: &[MemberUsage]) -> Vec<(&str, &str, XrefKind)> {
+        usages.iter().map(|usage| (usage.target.owner.as_str(), usage.location.method_name.as_str(), usage.location.kind)).collect()
+    }
+
+    #[test]
+    fn method_usages_include_calls_through_subclasses() {
+        let index = index();
+        assert_eq!(
+            callers(&index.method_usages("app.Base", "run", Some("()V"))),
+            vec![("app/Base", "direct", XrefKind::Call), ("app/Base", "inherited", XrefKind::Call)]
+        );
+        // 继承的方法归到声明类
+        assert!(index.method_usages("app/Sub", "run", None).is_empty());
+        // 声明不在项目中的方法保持常量池中的所有者
+        assert_eq!(callers(&index.method_usages("java/lang/Object", "hashCode", None)), vec![("java/lang/Object", "inherited", XrefKind::Call)]);
+    }
+
+    #[test]
+    fn field_usages_resolve_to_the_declaring_class() {
+        let index = index();
+        assert_eq!(
+            callers(&index.field_usages("app/Base", "count", None)),
+            vec![("app/Base", "direct", XrefKind::FieldWrite), ("app/Base", "inherited", XrefKind::FieldRead)]
+        );
+        assert!(index.field_usages("app/Sub", "count", Some("I")).is_empty());
+    }
+}